@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE "reconciliation_actions"
+(
+    id             SERIAL PRIMARY KEY       NOT NULL,
+    trader_pubkey  TEXT                     NOT NULL,
+    position_id    INTEGER REFERENCES positions (id),
+    inconsistency  TEXT                     NOT NULL,
+    action         TEXT                     NOT NULL,
+    successful     BOOLEAN                  NOT NULL,
+    error          TEXT,
+    created_at     timestamp WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE "liquidity_reservations"
+(
+    user_channel_id TEXT PRIMARY KEY         NOT NULL,
+    trader_pubkey   TEXT                     NOT NULL,
+    amount_sats     BIGINT                   NOT NULL,
+    created_at      timestamp WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at      timestamp WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE "counterparty_commitments"
+(
+    txid              TEXT PRIMARY KEY NOT NULL,
+    channel_keys_id   TEXT             NOT NULL,
+    commitment_number BIGINT           NOT NULL,
+    raw               TEXT             NOT NULL,
+    revoked           BOOLEAN          NOT NULL DEFAULT false
+);
+
+CREATE INDEX counterparty_commitments_channel_keys_id_commitment_number
+    ON counterparty_commitments (channel_keys_id, commitment_number);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE "reserved_utxos"
+(
+    outpoint        TEXT PRIMARY KEY NOT NULL,
+    user_channel_id TEXT             NOT NULL REFERENCES liquidity_reservations (user_channel_id) ON DELETE CASCADE,
+    amount_sats     BIGINT           NOT NULL
+);
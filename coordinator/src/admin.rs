@@ -1,6 +1,14 @@
 use crate::collaborative_revert;
 use crate::db;
+use crate::db::reconciliation_actions::ReconciliationAction;
+use crate::liquidity;
+use crate::liquidity::Consolidation;
+use crate::liquidity::LiquidityReport;
+use crate::node::reconciliation::Inconsistency;
+use crate::node::reconciliation::RepairAction;
+use crate::node::reconciliation::RepairOutcome;
 use crate::position::models::parse_channel_id;
+use crate::reserve::Reserve;
 use crate::routes::AppState;
 use crate::AppError;
 use anyhow::Context;
@@ -9,12 +17,26 @@ use axum::extract::Path;
 use axum::extract::Query;
 use axum::extract::State;
 use axum::Json;
+use bdk::FeeRate;
+use bdk::LocalUtxo;
 use bdk::TransactionDetails;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::psbt::PartiallySignedTransaction;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::Address;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
 use coordinator_commons::CollaborativeRevert;
 use dlc_manager::subchannel::SubChannel;
+use lightning::chain::chaininterface::ConfirmationTarget;
+use lightning::ln::PaymentHash;
 use lightning_invoice::Invoice;
+use ln_dlc_node::node::FeeBumpMethod;
 use ln_dlc_node::node::NodeInfo;
+use ln_dlc_node::node::PaymentAttempt;
+use ln_dlc_node::node::PaymentAttemptOutcome;
+use ln_dlc_node::UnconfirmedTransaction;
 use serde::de;
 use serde::Deserialize;
 use serde::Deserializer;
@@ -195,6 +217,506 @@ pub async fn list_on_chain_transactions(
     .map_err(|e| AppError::InternalServerError(format!("Failed to list transactions: {e:#}")))?
 }
 
+pub async fn list_unconfirmed_transactions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<UnconfirmedTransaction>>, AppError> {
+    spawn_blocking(move || {
+        let transactions = state.node.inner.unconfirmed_transactions().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to list transactions: {e:#}"))
+        })?;
+        Ok(Json(transactions))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to list transactions: {e:#}")))?
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BumpFee {
+    /// In sats/vbyte.
+    pub fee_rate: f32,
+    pub method: FeeBumpMethod,
+}
+
+#[instrument(skip_all, err(Debug))]
+pub async fn bump_fee(
+    State(state): State<Arc<AppState>>,
+    Path(txid): Path<String>,
+    Json(params): Json<BumpFee>,
+) -> Result<Json<Txid>, AppError> {
+    let txid = Txid::from_str(&txid)
+        .map_err(|e| AppError::BadRequest(format!("Invalid txid provided: {e:#}")))?;
+
+    spawn_blocking(move || {
+        let fee_rate = FeeRate::from_sat_per_vb(params.fee_rate);
+        let txid = state
+            .node
+            .inner
+            .bump_fee(&txid, fee_rate, params.method)
+            .map_err(|e| AppError::BadRequest(format!("Failed to bump fee: {e:#}")))?;
+        Ok(Json(txid))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to bump fee: {e:#}")))?
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fee {
+    HighPriority,
+    Normal,
+    Background,
+    /// In sats/vbyte.
+    FeeRate(f32),
+}
+
+impl From<Fee> for ln_dlc_node::Fee {
+    fn from(value: Fee) -> Self {
+        match value {
+            Fee::HighPriority => ln_dlc_node::Fee::Priority(ConfirmationTarget::HighPriority),
+            Fee::Normal => ln_dlc_node::Fee::Priority(ConfirmationTarget::Normal),
+            Fee::Background => ln_dlc_node::Fee::Priority(ConfirmationTarget::Background),
+            Fee::FeeRate(fee_rate) => ln_dlc_node::Fee::FeeRate(FeeRate::from_sat_per_vb(fee_rate)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SendOnChain {
+    pub address: Address,
+    /// The amount in sats, or `None` to send all available funds.
+    pub amount: Option<u64>,
+    pub fee: Fee,
+    /// If not empty, only these UTXOs are spent.
+    #[serde(default)]
+    pub utxos: Vec<OutPoint>,
+    /// Only create the transaction without broadcasting it.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The `raw_tx` of a previous dry run, to send exactly the transaction which was confirmed
+    /// instead of creating a new one.
+    pub raw_tx: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OnChainPayment {
+    pub txid: Txid,
+    pub amount: u64,
+    pub fee: u64,
+    /// In sats/vbyte.
+    pub fee_rate: f32,
+    pub raw_tx: String,
+    pub broadcast: bool,
+}
+
+pub async fn list_utxos(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<LocalUtxo>>, AppError> {
+    spawn_blocking(move || {
+        let utxos =
+            state.node.inner.wallet().list_utxos().map_err(|e| {
+                AppError::InternalServerError(format!("Failed to list UTXOs: {e:#}"))
+            })?;
+        Ok(Json(utxos))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to list UTXOs: {e:#}")))?
+}
+
+#[instrument(skip_all, err(Debug))]
+pub async fn send_on_chain(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<SendOnChain>,
+) -> Result<Json<OnChainPayment>, AppError> {
+    spawn_blocking(move || {
+        let wallet = state.node.inner.wallet();
+
+        let payment = match &params.raw_tx {
+            Some(raw_tx) => wallet.load_prepared_payment(raw_tx, &params.address, params.amount),
+            None => wallet.prepare_payment(
+                &params.address,
+                params.amount,
+                params.fee.into(),
+                &params.utxos,
+            ),
+        }
+        .map_err(|e| AppError::BadRequest(format!("Failed to create transaction: {e:#}")))?;
+
+        if !params.dry_run {
+            wallet
+                .broadcast_payment(&payment.transaction)
+                .map_err(|e| {
+                    AppError::InternalServerError(format!("Failed to send transaction: {e:#}"))
+                })?;
+        }
+
+        Ok(Json(OnChainPayment {
+            txid: payment.transaction.txid(),
+            amount: payment.amount,
+            fee: payment.fee,
+            fee_rate: payment.fee_rate(),
+            raw_tx: serialize_hex(&payment.transaction),
+            broadcast: !params.dry_run,
+        }))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to send on-chain: {e:#}")))?
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReserveInfo {
+    pub address: Address,
+    pub balance: bdk::Balance,
+    /// The spendable balance of the hot wallet, in sats.
+    pub hot_wallet_balance: u64,
+    pub min_liquidity_threshold_sats: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReserveTransfer {
+    /// The amount in sats. Defaults to whatever keeps the hot wallet at
+    /// `min_liquidity_threshold_sats`.
+    pub amount: Option<u64>,
+    pub fee: Fee,
+    /// Only create the transaction without broadcasting it.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopUpPsbt {
+    /// The unsigned PSBT, base64 encoded.
+    pub psbt: String,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignedPsbt {
+    /// Base64 encoded.
+    pub psbt: String,
+}
+
+fn reserve(state: &AppState) -> Result<Arc<Reserve>, AppError> {
+    state
+        .reserve
+        .clone()
+        .ok_or_else(|| AppError::BadRequest("No reserve configured".to_string()))
+}
+
+fn hot_wallet_balance(state: &AppState) -> Result<u64, AppError> {
+    let balance = state
+        .node
+        .inner
+        .get_on_chain_balance()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to get balance: {e:#}")))?;
+
+    Ok(balance.get_spendable())
+}
+
+pub async fn get_reserve(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ReserveInfo>, AppError> {
+    let min_liquidity_threshold_sats = state.settings.read().await.min_liquidity_threshold_sats;
+
+    spawn_blocking(move || {
+        let reserve = reserve(&state)?;
+        reserve
+            .sync()
+            .map_err(|e| AppError::InternalServerError(format!("{e:#}")))?;
+
+        let balance = reserve.balance().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to get reserve balance: {e:#}"))
+        })?;
+        let address = reserve.address().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to get reserve address: {e:#}"))
+        })?;
+
+        Ok(Json(ReserveInfo {
+            address,
+            balance,
+            hot_wallet_balance: hot_wallet_balance(&state)?,
+            min_liquidity_threshold_sats,
+        }))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to get reserve: {e:#}")))?
+}
+
+/// Sends the funds of the hot wallet above `min_liquidity_threshold_sats` to the reserve.
+///
+/// The liquidity reserved for pending JIT channels stays in the hot wallet as well.
+#[instrument(skip_all, err(Debug))]
+pub async fn sweep_to_reserve(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<ReserveTransfer>,
+) -> Result<Json<OnChainPayment>, AppError> {
+    let min_liquidity_threshold_sats = state.settings.read().await.min_liquidity_threshold_sats;
+
+    spawn_blocking(move || {
+        let address = reserve(&state)?.address().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to get reserve address: {e:#}"))
+        })?;
+
+        let keep_sats = keep_sats(&state, min_liquidity_threshold_sats)?;
+
+        let excess = hot_wallet_balance(&state)?.saturating_sub(keep_sats);
+        let amount = params.amount.unwrap_or(excess);
+
+        let wallet = state.node.inner.wallet();
+        let fee = params.fee.into();
+        let prepare = |amount| {
+            wallet
+                .prepare_payment(&address, Some(amount), fee, &[])
+                .map_err(|e| AppError::BadRequest(format!("Failed to create transaction: {e:#}")))
+        };
+
+        // The fee is paid from the hot wallet too, so it has to fit into the excess.
+        let mut payment = prepare(amount)?;
+        if amount + payment.fee > excess {
+            let amount = match params.amount {
+                None if excess > payment.fee => excess - payment.fee,
+                _ => {
+                    return Err(AppError::BadRequest(format!(
+                        "Sweeping {amount} sats would take the hot wallet below {keep_sats} sats"
+                    )))
+                }
+            };
+
+            payment = prepare(amount)?;
+        }
+
+        if !params.dry_run {
+            wallet
+                .broadcast_payment(&payment.transaction)
+                .map_err(|e| {
+                    AppError::InternalServerError(format!("Failed to send transaction: {e:#}"))
+                })?;
+        }
+
+        Ok(Json(OnChainPayment {
+            txid: payment.transaction.txid(),
+            amount: payment.amount,
+            fee: payment.fee,
+            fee_rate: payment.fee_rate(),
+            raw_tx: serialize_hex(&payment.transaction),
+            broadcast: !params.dry_run,
+        }))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to sweep to reserve: {e:#}")))?
+}
+
+/// The balance the hot wallet has to keep: the minimum liquidity plus the liquidity reserved for
+/// pending JIT channels.
+fn keep_sats(state: &AppState, min_liquidity_threshold_sats: u64) -> Result<u64, AppError> {
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to get connection: {e:#}")))?;
+    let reserved_sats = db::liquidity_reservations::get_reserved_sats(&mut conn).map_err(|e| {
+        AppError::InternalServerError(format!("Failed to get reserved liquidity: {e:#}"))
+    })?;
+
+    Ok(min_liquidity_threshold_sats + reserved_sats)
+}
+
+/// Builds an unsigned PSBT topping up the hot wallet from the reserve.
+///
+/// By default, the hot wallet is topped up to what [`sweep_to_reserve`] leaves in it.
+#[instrument(skip_all, err(Debug))]
+pub async fn top_up_from_reserve(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<ReserveTransfer>,
+) -> Result<Json<TopUpPsbt>, AppError> {
+    let min_liquidity_threshold_sats = state.settings.read().await.min_liquidity_threshold_sats;
+
+    spawn_blocking(move || {
+        let reserve = reserve(&state)?;
+
+        let keep_sats = keep_sats(&state, min_liquidity_threshold_sats)?;
+
+        let deficit = keep_sats.saturating_sub(hot_wallet_balance(&state)?);
+        let amount = params.amount.unwrap_or(deficit);
+        if amount == 0 {
+            return Err(AppError::BadRequest(format!(
+                "Hot wallet already holds at least {keep_sats} sats"
+            )));
+        }
+
+        reserve
+            .sync()
+            .map_err(|e| AppError::InternalServerError(format!("{e:#}")))?;
+
+        let address = state.node.inner.get_unused_address();
+        let fee_rate = state
+            .node
+            .inner
+            .wallet()
+            .resolve_fee_rate(params.fee.into());
+        let unsigned = reserve
+            .build_psbt(&address, amount, fee_rate)
+            .map_err(|e| AppError::BadRequest(format!("Failed to create PSBT: {e:#}")))?;
+
+        Ok(Json(TopUpPsbt {
+            psbt: unsigned.psbt.to_string(),
+            amount: unsigned.amount,
+            fee: unsigned.fee,
+        }))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to top up hot wallet: {e:#}")))?
+}
+
+/// Broadcasts a PSBT built by [`top_up_from_reserve`] once it has been signed.
+#[instrument(skip_all, err(Debug))]
+pub async fn broadcast_reserve_psbt(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<SignedPsbt>,
+) -> Result<Json<Txid>, AppError> {
+    let psbt = PartiallySignedTransaction::from_str(&params.psbt)
+        .map_err(|e| AppError::BadRequest(format!("Invalid PSBT provided: {e:#}")))?;
+
+    spawn_blocking(move || {
+        let txid = reserve(&state)?
+            .broadcast_psbt(psbt)
+            .map_err(|e| AppError::BadRequest(format!("Failed to broadcast PSBT: {e:#}")))?;
+
+        Ok(Json(txid))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to broadcast PSBT: {e:#}")))?
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Consolidate {
+    /// The fee to pay. If not set, UTXOs are only consolidated if fees are low.
+    pub fee: Option<Fee>,
+    /// Only create the transaction without broadcasting it.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[autometrics]
+pub async fn get_liquidity(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<LiquidityReport>, AppError> {
+    let settings = state.settings.read().await.clone();
+
+    spawn_blocking(move || {
+        let report = liquidity::report(
+            &state.node,
+            &settings.liquidity,
+            settings.min_liquidity_threshold_sats,
+        )
+        .map_err(|e| {
+            AppError::InternalServerError(format!("Failed to create liquidity report: {e:#}"))
+        })?;
+
+        Ok(Json(report))
+    })
+    .await
+    .map_err(|e| {
+        AppError::InternalServerError(format!("Failed to create liquidity report: {e:#}"))
+    })?
+}
+
+/// Spends the small UTXOs of the hot wallet back to itself.
+#[instrument(skip_all, err(Debug))]
+pub async fn consolidate_utxos(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<Consolidate>,
+) -> Result<Json<Option<Consolidation>>, AppError> {
+    let settings = state.settings.read().await.liquidity.clone();
+
+    spawn_blocking(move || {
+        let consolidation = liquidity::consolidate(
+            &state.node,
+            &settings,
+            params.fee.map(Fee::into),
+            params.dry_run,
+        )
+        .map_err(|e| AppError::BadRequest(format!("Failed to consolidate UTXOs: {e:#}")))?;
+
+        Ok(Json(consolidation))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to consolidate UTXOs: {e:#}")))?
+}
+
+#[derive(Serialize)]
+pub struct ReconciliationReportEntry {
+    pub inconsistency: Inconsistency,
+    /// The action that would be applied to repair the inconsistency. If `None`, the inconsistency
+    /// has to be resolved manually.
+    pub repair_action: Option<RepairAction>,
+}
+
+#[autometrics]
+pub async fn get_reconciliation_report(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ReconciliationReportEntry>>, AppError> {
+    spawn_blocking(move || {
+        let inconsistencies = state.node.find_inconsistencies().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to reconcile positions: {e:#}"))
+        })?;
+
+        let report = inconsistencies
+            .into_iter()
+            .map(|inconsistency| ReconciliationReportEntry {
+                repair_action: inconsistency.repair_action(),
+                inconsistency,
+            })
+            .collect();
+
+        Ok(Json(report))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to reconcile positions: {e:#}")))?
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RepairParams {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    trader: Option<PublicKey>,
+}
+
+#[instrument(skip_all, err(Debug))]
+#[autometrics]
+pub async fn repair_inconsistencies(
+    Query(params): Query<RepairParams>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<RepairOutcome>>, AppError> {
+    spawn_blocking(move || {
+        let outcomes = state
+            .node
+            .repair_inconsistencies(params.trader)
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Failed to repair inconsistencies: {e:#}"))
+            })?;
+
+        Ok(Json(outcomes))
+    })
+    .await
+    .map_err(|e| {
+        AppError::InternalServerError(format!("Failed to repair inconsistencies: {e:#}"))
+    })?
+}
+
+pub async fn list_reconciliation_actions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ReconciliationAction>>, AppError> {
+    let mut conn =
+        state.pool.clone().get().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to acquire db lock: {e:#}"))
+        })?;
+
+    let actions = db::reconciliation_actions::get_all(&mut conn).map_err(|e| {
+        AppError::InternalServerError(format!("Failed to load reconciliation actions: {e:#}"))
+    })?;
+
+    Ok(Json(actions))
+}
+
 pub async fn list_peers(State(state): State<Arc<AppState>>) -> Json<Vec<PublicKey>> {
     let peers = state.node.inner.list_peers();
     Json(peers)
@@ -284,6 +806,137 @@ pub async fn send_payment(
     Ok(())
 }
 
+#[cfg(feature = "bolt12")]
+#[derive(Debug, Deserialize)]
+pub struct CreateRefund {
+    pub amount_sats: u64,
+    pub description: String,
+    /// How long the trader has to claim the refund; it never expires if not set.
+    pub expiry_secs: Option<u64>,
+}
+
+/// Creates a BOLT12 refund, which a trader can claim to get paid `amount_sats`, e.g. to pay back a
+/// fee for a failed trade.
+#[cfg(feature = "bolt12")]
+pub async fn create_refund(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<CreateRefund>,
+) -> Result<Json<String>, AppError> {
+    let refund = state
+        .node
+        .inner
+        .create_refund(
+            params.amount_sats * 1000,
+            params.description,
+            None,
+            params.expiry_secs.map(std::time::Duration::from_secs),
+        )
+        .map_err(|e| AppError::InternalServerError(format!("Failed to create refund: {e:#}")))?;
+
+    Ok(Json(refund.to_string()))
+}
+
+#[derive(Serialize)]
+pub struct PaymentDiagnostics {
+    pub payment_hash: String,
+    pub status: Option<String>,
+    pub failure_reason: Option<String>,
+    pub attempts: Vec<PaymentAttemptDetails>,
+}
+
+#[derive(Serialize)]
+pub struct PaymentAttemptDetails {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub hops: Vec<PaymentHopDetails>,
+    pub amount_msat: u64,
+    pub fee_msat: u64,
+    pub probe: bool,
+    pub succeeded: bool,
+    /// Whether the failure ruled out any other path, e.g. because the recipient rejected it.
+    pub failed_permanently: bool,
+    pub failed_short_channel_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PaymentHopDetails {
+    pub node_id: PublicKey,
+    pub short_channel_id: u64,
+}
+
+impl From<PaymentAttempt> for PaymentAttemptDetails {
+    fn from(attempt: PaymentAttempt) -> Self {
+        let (succeeded, failed_permanently, failed_short_channel_id) = match attempt.outcome {
+            PaymentAttemptOutcome::Succeeded => (true, false, None),
+            PaymentAttemptOutcome::Failed {
+                permanently,
+                short_channel_id,
+            } => (false, permanently, short_channel_id),
+        };
+
+        Self {
+            timestamp: attempt.timestamp,
+            hops: attempt
+                .hops
+                .into_iter()
+                .map(|hop| PaymentHopDetails {
+                    node_id: hop.node_id,
+                    short_channel_id: hop.short_channel_id,
+                })
+                .collect(),
+            amount_msat: attempt.amount_msat,
+            fee_msat: attempt.fee_msat,
+            probe: attempt.probe,
+            succeeded,
+            failed_permanently,
+            failed_short_channel_id,
+        }
+    }
+}
+
+/// Shows the outcome of the payment with the given payment hash and the paths over which we tried
+/// to route it since the coordinator was started.
+pub async fn get_payment_attempts(
+    Path(payment_hash): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PaymentDiagnostics>, AppError> {
+    let hash = <[u8; 32]>::from_hex(&payment_hash)
+        .map_err(|e| AppError::BadRequest(format!("Invalid payment hash: {e:#}")))?;
+    let hash = PaymentHash(hash);
+
+    let mut conn = state.pool.get().map_err(|e| {
+        AppError::InternalServerError(format!("Failed to get database connection: {e:#}"))
+    })?;
+    let payment = db::payments::get(hash, &mut conn)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to load payment: {e:#}")))?;
+
+    let attempts = state.node.inner.payment_attempts(&hash);
+
+    if payment.is_none() && attempts.is_empty() {
+        return Err(AppError::BadRequest(format!(
+            "Unknown payment hash: {payment_hash}"
+        )));
+    }
+
+    let (status, failure_reason) = match payment {
+        Some((_, info)) => (
+            Some(info.status.to_string()),
+            info.failure_reason.map(|reason| reason.to_string()),
+        ),
+        None => (None, None),
+    };
+
+    Ok(Json(PaymentDiagnostics {
+        payment_hash,
+        status,
+        failure_reason,
+        attempts: attempts
+            .into_iter()
+            .map(PaymentAttemptDetails::from)
+            .collect(),
+    }))
+}
+
 #[instrument(skip_all, err(Debug))]
 pub async fn close_channel(
     Path(channel_id_string): Path<String>,
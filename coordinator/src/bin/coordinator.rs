@@ -1,6 +1,7 @@
 use anyhow::Context;
 use anyhow::Result;
 use coordinator::cli::Opts;
+use coordinator::liquidity;
 use coordinator::logger;
 use coordinator::message::spawn_delivering_messages_to_authenticated_users;
 use coordinator::message::NewUserMessage;
@@ -10,6 +11,7 @@ use coordinator::node;
 use coordinator::node::closed_positions;
 use coordinator::node::connection;
 use coordinator::node::expired_positions;
+use coordinator::node::match_timeout;
 use coordinator::node::rollover;
 use coordinator::node::storage::NodeStorage;
 use coordinator::node::unrealized_pnl;
@@ -17,17 +19,24 @@ use coordinator::node::Node;
 use coordinator::notifications::NotificationService;
 use coordinator::orderbook::async_match;
 use coordinator::orderbook::collaborative_revert;
+use coordinator::orderbook::depth;
+use coordinator::orderbook::depth::DepthFeed;
+use coordinator::orderbook::market_data;
 use coordinator::orderbook::trading;
+use coordinator::reserve::Reserve;
 use coordinator::routes::router;
 use coordinator::run_migration;
 use coordinator::scheduler::NotificationScheduler;
 use coordinator::settings::Settings;
+use coordinator::watchtower;
 use diesel::r2d2;
 use diesel::r2d2::ConnectionManager;
 use diesel::PgConnection;
 use lightning::util::events::Event;
 use ln_dlc_node::scorer;
 use ln_dlc_node::seed::Bip39Seed;
+use ln_dlc_node::watchtower::Admission;
+use ln_dlc_node::watchtower::Watchtower;
 use ln_dlc_node::CoordinatorEventHandler;
 use rand::thread_rng;
 use rand::RngCore;
@@ -48,6 +57,9 @@ const EXPIRED_POSITION_SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
 const CLOSED_POSITION_SYNC_INTERVAL: Duration = Duration::from_secs(30);
 const UNREALIZED_PNL_SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
 const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const MATCH_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const MARKET_STATS_BROADCAST_INTERVAL: Duration = Duration::from_secs(60);
+const LIQUIDITY_MANAGEMENT_INTERVAL: Duration = Duration::from_secs(10 * 60);
 
 const NODE_ALIAS: &str = "10101.finance";
 
@@ -103,6 +115,8 @@ async fn main() -> Result<()> {
 
     let (node_event_sender, mut node_event_receiver) = watch::channel::<Option<Event>>(None);
 
+    let chain_source = opts.chain_source();
+
     let node = Arc::new(ln_dlc_node::node::Node::new(
         ln_dlc_node::config::coordinator_config(),
         scorer::persistent_scorer,
@@ -113,7 +127,7 @@ async fn main() -> Result<()> {
         address,
         SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), address.port()),
         opts.p2p_announcement_addresses(),
-        opts.esplora.clone(),
+        chain_source.clone(),
         seed,
         ephemeral_randomness,
         settings.ln_dlc.clone(),
@@ -220,6 +234,8 @@ async fn main() -> Result<()> {
         auth_users_notifier.clone(),
         network,
     );
+    let depth_feed = Arc::new(DepthFeed::default());
+    let _handle = depth::monitor(pool.clone(), tx_price_feed.clone(), depth_feed.clone());
     let _handle = rollover::monitor(
         pool.clone(),
         tx_user_feed.clone(),
@@ -247,6 +263,45 @@ async fn main() -> Result<()> {
         }
     });
 
+    tokio::spawn({
+        let node = node.clone();
+        let notifier = auth_users_notifier.clone();
+        let tx_price_feed = tx_price_feed.clone();
+        async move {
+            loop {
+                tokio::time::sleep(MATCH_TIMEOUT_CHECK_INTERVAL).await;
+                if let Err(e) = match_timeout::fail_stuck_matches(
+                    node.clone(),
+                    notifier.clone(),
+                    tx_price_feed.clone(),
+                )
+                .await
+                {
+                    tracing::error!("Failed to fail stuck matches! Error: {e:#}");
+                }
+            }
+        }
+    });
+
+    tokio::spawn({
+        let pool = pool.clone();
+        let tx_price_feed = tx_price_feed.clone();
+        async move {
+            loop {
+                tokio::time::sleep(MARKET_STATS_BROADCAST_INTERVAL).await;
+                let result = pool
+                    .get()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|mut conn| {
+                        market_data::broadcast_market_stats(&mut conn, &tx_price_feed)
+                    });
+                if let Err(e) = result {
+                    tracing::error!("Failed to broadcast market stats! Error: {e:#}");
+                }
+            }
+        }
+    });
+
     tokio::spawn({
         let node = node.clone();
         async move {
@@ -259,11 +314,39 @@ async fn main() -> Result<()> {
         }
     });
 
+    tokio::spawn({
+        let node = node.clone();
+        async move {
+            loop {
+                tokio::time::sleep(LIQUIDITY_MANAGEMENT_INTERVAL).await;
+                if let Err(e) = liquidity::manage(node.clone()).await {
+                    tracing::error!("Failed to manage on-chain liquidity: {e:#}");
+                }
+            }
+        }
+    });
+
     tokio::spawn({
         let node = node.clone();
         connection::keep_public_channel_peers_connected(node.inner, CONNECTION_CHECK_INTERVAL)
     });
 
+    let reserve = match &opts.reserve_descriptor {
+        Some(descriptor) => {
+            let reserve = Reserve::new(
+                &data_dir,
+                network,
+                descriptor,
+                opts.reserve_change_descriptor.as_deref(),
+                &chain_source,
+            )
+            .context("Failed to open reserve")?;
+
+            Some(Arc::new(reserve))
+        }
+        None => None,
+    };
+
     let app = router(
         node.clone(),
         pool.clone(),
@@ -275,8 +358,32 @@ async fn main() -> Result<()> {
         tx_price_feed,
         tx_user_feed,
         auth_users_notifier.clone(),
+        depth_feed,
+        reserve,
     );
 
+    let app = if opts.watchtower {
+        // Only apps with a channel to the coordinator may hand their penalties to its watchtower.
+        let admission: Admission = Box::new({
+            let node = node.inner.clone();
+            move |node_id| {
+                node.channel_manager
+                    .list_channels()
+                    .iter()
+                    .any(|channel| channel.counterparty.node_id == *node_id)
+            }
+        });
+        let watchtower = Watchtower::new(&data_dir, opts.esplora.clone(), admission)
+            .context("Failed to open watchtower")?;
+        let watchtower = Arc::new(watchtower);
+
+        tokio::spawn(watchtower.clone().watch());
+
+        app.merge(watchtower::router(watchtower))
+    } else {
+        app
+    };
+
     let sender = notification_service.get_sender();
     let notification_scheduler =
         NotificationScheduler::new(sender, settings, network, node, auth_users_notifier);
@@ -0,0 +1,81 @@
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::secp256k1::PublicKey;
+use clap::Parser;
+use coordinator::cli::Network;
+use coordinator::logger;
+use coordinator::watchtower;
+use ln_dlc_node::watchtower::Watchtower;
+use std::collections::HashSet;
+use std::env::current_dir;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::metadata::LevelFilter;
+
+/// A watchtower broadcasting the penalty transactions handed to it by apps, for when the
+/// coordinator publishes a revoked transaction while the app is offline.
+#[derive(Parser)]
+struct Opts {
+    /// The IP address to listen on for the HTTP API.
+    #[clap(long, default_value = "0.0.0.0:8100")]
+    http_address: SocketAddr,
+
+    /// Where to permanently store data, defaults to the `data` directory in the current working
+    /// directory.
+    #[clap(long)]
+    data_dir: Option<PathBuf>,
+
+    #[clap(value_enum, default_value = "regtest")]
+    network: Network,
+
+    /// If enabled logs will be in json format
+    #[clap(short, long)]
+    json: bool,
+
+    /// The address to connect esplora API to.
+    #[clap(long, default_value = "http://localhost:3000")]
+    esplora: String,
+
+    /// The node IDs of the apps whose penalties the watchtower stores. Appointments of any other
+    /// node are refused.
+    #[clap(long = "allowed-node-id")]
+    allowed_node_ids: Vec<PublicKey>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Opts::parse();
+
+    logger::init_tracing(LevelFilter::DEBUG, opts.json, false)?;
+
+    let network = bitcoin::Network::from(opts.network);
+    let data_dir = match opts.data_dir {
+        None => current_dir()?.join("data"),
+        Some(path) => path,
+    }
+    .join(network.to_string());
+
+    let allowed_node_ids = opts.allowed_node_ids.into_iter().collect::<HashSet<_>>();
+    tracing::info!(
+        allowed_node_ids = allowed_node_ids.len(),
+        "Only accepting appointments of allowed nodes"
+    );
+
+    let watchtower = Watchtower::new(
+        &data_dir,
+        opts.esplora,
+        Box::new(move |node_id| allowed_node_ids.contains(node_id)),
+    )
+    .context("Failed to open watchtower")?;
+    let watchtower = Arc::new(watchtower);
+
+    tokio::spawn(watchtower.clone().watch());
+
+    tracing::debug!("listening on http://{}", opts.http_address);
+    axum::Server::bind(&opts.http_address)
+        .serve(watchtower::router(watchtower).into_make_service())
+        .await?;
+
+    Ok(())
+}
@@ -3,6 +3,7 @@ use bitcoin::XOnlyPublicKey;
 use clap::Parser;
 use lightning::ln::msgs::NetAddress;
 use ln_dlc_node::node::OracleInfo;
+use ln_dlc_node::ChainSource;
 use local_ip_address::local_ip;
 use std::env::current_dir;
 use std::net::IpAddr;
@@ -42,10 +43,44 @@ pub struct Opts {
     )]
     pub database: String,
 
-    /// The address to connect esplora API to
+    /// The address to connect esplora API to, used with `--chain-source esplora`.
     #[clap(long, default_value = "http://localhost:3000")]
     pub esplora: String,
 
+    /// Where to get the view of the blockchain from.
+    #[clap(long, value_enum, default_value = "esplora")]
+    pub chain_source: ChainSourceKind,
+
+    /// The bitcoind JSON-RPC endpoint, used with `--chain-source bitcoind`.
+    #[clap(long, default_value = "http://localhost:18443")]
+    pub bitcoind_rpc_url: String,
+
+    /// The bitcoind JSON-RPC user, required with `--chain-source bitcoind`.
+    #[clap(long, required_if_eq("chain_source", "bitcoind"))]
+    pub bitcoind_rpc_user: Option<String>,
+
+    /// The bitcoind JSON-RPC password, required with `--chain-source bitcoind`.
+    #[clap(long, required_if_eq("chain_source", "bitcoind"))]
+    pub bitcoind_rpc_password: Option<String>,
+
+    /// The Electrum server endpoint, used with `--chain-source electrum`.
+    #[clap(long, default_value = "tcp://localhost:50000")]
+    pub electrum: String,
+
+    /// The public descriptor of the watch-only wallet holding the coordinator's reserve. If not
+    /// specified, the coordinator has no reserve.
+    #[clap(long)]
+    pub reserve_descriptor: Option<String>,
+
+    /// The public change descriptor of the reserve.
+    #[clap(long, requires = "reserve_descriptor")]
+    pub reserve_change_descriptor: Option<String>,
+
+    /// If enabled, the coordinator also runs a watchtower for the channels of apps, which can be
+    /// reached on the HTTP API.
+    #[clap(long)]
+    pub watchtower: bool,
+
     /// If enabled, tokio runtime can be locally debugged with tokio_console
     #[clap(long)]
     pub tokio_console: bool,
@@ -71,6 +106,13 @@ pub struct Opts {
     oracle_pubkey: String,
 }
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ChainSourceKind {
+    Esplora,
+    Bitcoind,
+    Electrum,
+}
+
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
 pub enum Network {
     Regtest,
@@ -100,6 +142,28 @@ impl Opts {
         self.network.into()
     }
 
+    pub fn chain_source(&self) -> ChainSource {
+        match self.chain_source {
+            ChainSourceKind::Esplora => ChainSource::Esplora {
+                url: self.esplora.clone(),
+            },
+            ChainSourceKind::Bitcoind => ChainSource::Bitcoind {
+                url: self.bitcoind_rpc_url.clone(),
+                user: self
+                    .bitcoind_rpc_user
+                    .clone()
+                    .expect("bitcoind RPC user to be required"),
+                password: self
+                    .bitcoind_rpc_password
+                    .clone()
+                    .expect("bitcoind RPC password to be required"),
+            },
+            ChainSourceKind::Electrum => ChainSource::Electrum {
+                url: self.electrum.clone(),
+            },
+        }
+    }
+
     pub fn get_oracle_info(&self) -> OracleInfo {
         OracleInfo {
             endpoint: self.oracle_endpoint.clone(),
@@ -0,0 +1,73 @@
+use crate::schema::counterparty_commitments;
+use anyhow::Result;
+use bitcoin::consensus::deserialize;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use diesel::prelude::*;
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = counterparty_commitments)]
+struct NewCounterpartyCommitment {
+    txid: String,
+    channel_keys_id: String,
+    commitment_number: i64,
+    raw: String,
+}
+
+pub(crate) fn insert(
+    conn: &mut PgConnection,
+    channel_keys_id: [u8; 32],
+    commitment_number: u64,
+    transaction: &Transaction,
+) -> Result<()> {
+    diesel::insert_into(counterparty_commitments::table)
+        .values(NewCounterpartyCommitment {
+            txid: transaction.txid().to_string(),
+            channel_keys_id: channel_keys_id.to_hex(),
+            commitment_number: commitment_number as i64,
+            raw: serialize_hex(transaction),
+        })
+        // The same commitment transaction may be signed more than once, e.g. on reconnection.
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub(crate) fn revoke(
+    conn: &mut PgConnection,
+    channel_keys_id: [u8; 32],
+    commitment_number: u64,
+) -> Result<()> {
+    diesel::update(counterparty_commitments::table)
+        .filter(counterparty_commitments::channel_keys_id.eq(channel_keys_id.to_hex()))
+        .filter(counterparty_commitments::commitment_number.eq(commitment_number as i64))
+        .set(counterparty_commitments::revoked.eq(true))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub(crate) fn get_all_revoked(conn: &mut PgConnection) -> Result<Vec<Transaction>> {
+    let raw_txs: Vec<String> = counterparty_commitments::table
+        .filter(counterparty_commitments::revoked.eq(true))
+        .select(counterparty_commitments::raw)
+        .load(conn)?;
+
+    raw_txs
+        .iter()
+        .map(|raw| Ok(deserialize(&Vec::<u8>::from_hex(raw)?)?))
+        .collect()
+}
+
+pub(crate) fn delete(conn: &mut PgConnection, txid: &Txid) -> Result<()> {
+    diesel::delete(
+        counterparty_commitments::table.filter(counterparty_commitments::txid.eq(txid.to_string())),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
@@ -36,3 +36,14 @@ impl LiquidityRequestLog {
             .get_result(conn)
     }
 }
+
+/// Returns the liquidity requests made since `since`, oldest first.
+pub fn get_since(
+    conn: &mut PgConnection,
+    since: OffsetDateTime,
+) -> QueryResult<Vec<LiquidityRequestLog>> {
+    liquidity_request_logs::table
+        .filter(liquidity_request_logs::timestamp.ge(since))
+        .order_by(liquidity_request_logs::timestamp.asc())
+        .load(conn)
+}
@@ -0,0 +1,199 @@
+use crate::db::channels::ChannelState;
+use crate::schema::channels;
+use crate::schema::liquidity_reservations;
+use crate::schema::reserved_utxos;
+use anyhow::Result;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::OutPoint;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use ln_dlc_node::channel::UserChannelId;
+use serde::Serialize;
+use time::Duration;
+use time::OffsetDateTime;
+
+/// On-chain liquidity set aside for a JIT channel which has been promised to a trader, but not
+/// funded yet.
+#[derive(Insertable, Queryable, Serialize, Debug, Clone, PartialEq)]
+#[diesel(table_name = liquidity_reservations)]
+pub struct LiquidityReservation {
+    pub user_channel_id: String,
+    pub trader_pubkey: String,
+    pub amount_sats: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+/// A wallet output set aside for the JIT channel of a [`LiquidityReservation`], so that it is not
+/// spent by anything else, e.g. a consolidation, before the channel is funded.
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = reserved_utxos)]
+struct ReservedUtxo {
+    outpoint: String,
+    user_channel_id: String,
+    amount_sats: i64,
+}
+
+/// Arbitrary key of the advisory lock serialising the reservations.
+const RESERVATION_LOCK_KEY: i64 = 0x6c69_7175_6964_6974;
+
+/// Locks the reservations until the end of the current transaction, waiting for any other
+/// transaction holding the lock.
+///
+/// Checking whether there is enough liquidity left and reserving it has to happen under this lock,
+/// otherwise concurrent requests could reserve the same liquidity twice.
+pub fn lock(conn: &mut PgConnection) -> QueryResult<()> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+        .bind::<BigInt, _>(RESERVATION_LOCK_KEY)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Reserves `amount_sats`, funded by `utxos`, for the channel `user_channel_id` for the duration
+/// of `timeout`.
+///
+/// A trader holds at most one reservation, so reserving again, e.g. for a new onboarding attempt,
+/// replaces the previous reservation of the trader.
+pub fn insert(
+    conn: &mut PgConnection,
+    user_channel_id: UserChannelId,
+    trader: PublicKey,
+    amount_sats: u64,
+    utxos: &[(OutPoint, u64)],
+    timeout: Duration,
+) -> QueryResult<LiquidityReservation> {
+    let now = OffsetDateTime::now_utc();
+    let reservation = LiquidityReservation {
+        user_channel_id: user_channel_id.to_string(),
+        trader_pubkey: trader.to_string(),
+        amount_sats: amount_sats as i64,
+        created_at: now,
+        expires_at: now + timeout,
+    };
+
+    conn.transaction(|conn| {
+        diesel::delete(
+            liquidity_reservations::table
+                .filter(liquidity_reservations::trader_pubkey.eq(&reservation.trader_pubkey)),
+        )
+        .execute(conn)?;
+
+        let reservation = diesel::insert_into(liquidity_reservations::table)
+            .values(&reservation)
+            .on_conflict(liquidity_reservations::user_channel_id)
+            .do_update()
+            .set((
+                liquidity_reservations::trader_pubkey.eq(&reservation.trader_pubkey),
+                liquidity_reservations::amount_sats.eq(reservation.amount_sats),
+                liquidity_reservations::created_at.eq(reservation.created_at),
+                liquidity_reservations::expires_at.eq(reservation.expires_at),
+            ))
+            .get_result(conn)?;
+
+        // Updating the reservation of the channel does not cascade to its UTXOs.
+        diesel::delete(
+            reserved_utxos::table
+                .filter(reserved_utxos::user_channel_id.eq(&reservation.user_channel_id)),
+        )
+        .execute(conn)?;
+
+        let utxos = utxos
+            .iter()
+            .map(|(outpoint, amount_sats)| ReservedUtxo {
+                outpoint: outpoint.to_string(),
+                user_channel_id: reservation.user_channel_id.clone(),
+                amount_sats: *amount_sats as i64,
+            })
+            .collect::<Vec<_>>();
+        if !utxos.is_empty() {
+            diesel::insert_into(reserved_utxos::table)
+                .values(&utxos)
+                .execute(conn)?;
+        }
+
+        Ok(reservation)
+    })
+}
+
+pub fn get_all(conn: &mut PgConnection) -> QueryResult<Vec<LiquidityReservation>> {
+    liquidity_reservations::table
+        .order_by(liquidity_reservations::created_at.asc())
+        .load(conn)
+}
+
+/// The total amount currently reserved, in sats.
+pub fn get_reserved_sats(conn: &mut PgConnection) -> QueryResult<u64> {
+    let amounts: Vec<i64> = liquidity_reservations::table
+        .select(liquidity_reservations::amount_sats)
+        .load(conn)?;
+
+    Ok(amounts.into_iter().map(|amount| amount as u64).sum())
+}
+
+/// The total amount reserved for traders other than `trader`, in sats.
+///
+/// The reservation of `trader` is replaced by a new one, so it must not count against it.
+pub fn get_reserved_sats_for_others(
+    conn: &mut PgConnection,
+    trader: PublicKey,
+) -> QueryResult<u64> {
+    let amounts: Vec<i64> = liquidity_reservations::table
+        .filter(liquidity_reservations::trader_pubkey.ne(trader.to_string()))
+        .select(liquidity_reservations::amount_sats)
+        .load(conn)?;
+
+    Ok(amounts.into_iter().map(|amount| amount as u64).sum())
+}
+
+/// The outputs reserved for JIT channels, which must not be spent by anything else.
+pub fn get_reserved_outpoints(conn: &mut PgConnection) -> Result<Vec<OutPoint>> {
+    let outpoints: Vec<String> = reserved_utxos::table
+        .select(reserved_utxos::outpoint)
+        .load(conn)?;
+
+    outpoints
+        .iter()
+        .map(|outpoint| Ok(outpoint.parse()?))
+        .collect()
+}
+
+/// The outputs reserved for JIT channels of traders other than `trader`.
+///
+/// The reservation of `trader` is replaced by a new one, so its outputs can be reserved again.
+pub fn get_reserved_outpoints_for_others(
+    conn: &mut PgConnection,
+    trader: PublicKey,
+) -> Result<Vec<OutPoint>> {
+    let outpoints: Vec<String> = reserved_utxos::table
+        .inner_join(liquidity_reservations::table)
+        .filter(liquidity_reservations::trader_pubkey.ne(trader.to_string()))
+        .select(reserved_utxos::outpoint)
+        .load(conn)?;
+
+    outpoints
+        .iter()
+        .map(|outpoint| Ok(outpoint.parse()?))
+        .collect()
+}
+
+/// Deletes the reservations which have expired, or whose channel is not waiting for its funding
+/// transaction anymore.
+///
+/// Their UTXOs are released with them. Returns the number of released reservations.
+pub fn release_stale(conn: &mut PgConnection) -> QueryResult<usize> {
+    let funded = channels::table
+        .filter(channels::channel_state.ne(ChannelState::Announced))
+        .select(channels::user_channel_id);
+
+    diesel::delete(
+        liquidity_reservations::table.filter(
+            liquidity_reservations::expires_at
+                .lt(OffsetDateTime::now_utc())
+                .or(liquidity_reservations::user_channel_id.eq_any(funded)),
+        ),
+    )
+    .execute(conn)
+}
@@ -1,11 +1,14 @@
 pub mod channels;
 pub mod collaborative_reverts;
+pub mod counterparty_commitments;
 pub mod custom_types;
 pub mod liquidity;
 pub mod liquidity_options;
+pub mod liquidity_reservations;
 pub mod payments;
 pub mod positions;
 pub mod positions_helper;
+pub mod reconciliation_actions;
 pub mod routing_fees;
 pub mod spendable_outputs;
 pub mod trades;
@@ -13,6 +13,7 @@ use diesel::query_builder::QueryId;
 use diesel::AsExpression;
 use diesel::FromSqlRow;
 use std::any::TypeId;
+use std::str::FromStr;
 use time::OffsetDateTime;
 
 #[derive(Queryable, Debug, Clone)]
@@ -31,6 +32,7 @@ pub struct Payment {
     pub description: String,
     pub invoice: Option<String>,
     pub fee_msat: Option<i64>,
+    pub failure_reason: Option<String>,
 }
 
 pub fn get(
@@ -72,6 +74,27 @@ pub fn get_all(
     Ok(payments)
 }
 
+/// The payment hashes of our invoices with the given `description`.
+pub fn get_inbound_payment_hashes_by_description(
+    conn: &mut PgConnection,
+    description: &str,
+) -> Result<Vec<lightning::ln::PaymentHash>> {
+    let payment_hashes: Vec<String> = payments::table
+        .filter(payments::description.eq(description))
+        .filter(payments::flow.eq(PaymentFlow::Inbound))
+        .select(payments::payment_hash)
+        .load(conn)?;
+
+    payment_hashes
+        .iter()
+        .map(|payment_hash| {
+            Ok(lightning::ln::PaymentHash(<[u8; 32]>::from_hex(
+                payment_hash,
+            )?))
+        })
+        .collect()
+}
+
 impl From<(lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)> for NewPayment {
     fn from((payment_hash, info): (lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)) -> Self {
         Self {
@@ -84,6 +107,7 @@ impl From<(lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)> for NewPayment
             payment_timestamp: info.timestamp,
             description: info.description,
             invoice: info.invoice,
+            failure_reason: info.failure_reason.map(|reason| reason.to_string()),
         }
     }
 }
@@ -117,6 +141,11 @@ impl TryFrom<Payment> for (lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)
             ln_dlc_node::MillisatAmount::new(value.amount_msat.map(|amount| amount as u64));
         let fee_msat = ln_dlc_node::MillisatAmount::new(value.fee_msat.map(|amount| amount as u64));
 
+        let failure_reason = value
+            .failure_reason
+            .map(|reason| ln_dlc_node::PaymentFailureReason::from_str(&reason))
+            .transpose()?;
+
         Ok((
             payment_hash,
             ln_dlc_node::PaymentInfo {
@@ -129,6 +158,7 @@ impl TryFrom<Payment> for (lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)
                 timestamp: value.payment_timestamp,
                 description: value.description,
                 invoice: value.invoice,
+                failure_reason,
             },
         ))
     }
@@ -180,6 +210,8 @@ pub(crate) struct NewPayment {
     pub description: String,
     #[diesel(sql_type = Nullable<Text>)]
     pub invoice: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub failure_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
@@ -326,3 +358,21 @@ pub fn update(
 
     Ok(updated_at)
 }
+
+pub fn set_failure_reason(
+    payment_hash: lightning::ln::PaymentHash,
+    failure_reason: ln_dlc_node::PaymentFailureReason,
+    conn: &mut PgConnection,
+) -> Result<()> {
+    let affected_rows = diesel::update(payments::table)
+        .filter(payments::payment_hash.eq(payment_hash.0.to_hex()))
+        .set((
+            payments::failure_reason.eq(failure_reason.to_string()),
+            payments::updated_at.eq(OffsetDateTime::now_utc()),
+        ))
+        .execute(conn)?;
+
+    ensure!(affected_rows > 0, "Could not update payment failure reason");
+
+    Ok(())
+}
@@ -117,6 +117,41 @@ impl Position {
         Ok(positions)
     }
 
+    /// Returns all positions which are not closed yet, i.e. in state `Open`, `Closing` or
+    /// `Rollover`.
+    pub fn get_all_active_positions(
+        conn: &mut PgConnection,
+    ) -> QueryResult<Vec<crate::position::models::Position>> {
+        let positions = positions::table
+            .filter(positions::position_state.ne(PositionState::Closed))
+            .load::<Position>(conn)?;
+
+        let positions = positions
+            .into_iter()
+            .map(crate::position::models::Position::from)
+            .collect();
+
+        Ok(positions)
+    }
+
+    /// Like [`Position::get_all_active_positions`], but locks the returned positions until the end
+    /// of the current transaction.
+    pub fn lock_all_active_positions(
+        conn: &mut PgConnection,
+    ) -> QueryResult<Vec<crate::position::models::Position>> {
+        let positions = positions::table
+            .filter(positions::position_state.ne(PositionState::Closed))
+            .for_update()
+            .load::<Position>(conn)?;
+
+        let positions = positions
+            .into_iter()
+            .map(crate::position::models::Position::from)
+            .collect();
+
+        Ok(positions)
+    }
+
     /// sets the status of all open position to closing (note, we expect that number to be always
     /// exactly 1)
     pub fn set_open_position_to_closing(
@@ -198,6 +233,27 @@ impl Position {
         Ok(())
     }
 
+    /// Sets a `Closing` position back to `Open`, e.g. if the collaborative settlement of the DLC
+    /// never happened.
+    pub fn set_closing_position_to_open(conn: &mut PgConnection, id: i32) -> Result<()> {
+        let affected_rows = diesel::update(positions::table)
+            .filter(positions::id.eq(id))
+            .filter(positions::position_state.eq(PositionState::Closing))
+            .set((
+                positions::position_state.eq(PositionState::Open),
+                positions::closing_price.eq(None::<f32>),
+                positions::update_timestamp.eq(OffsetDateTime::now_utc()),
+            ))
+            .execute(conn)?;
+
+        ensure!(
+            affected_rows > 0,
+            "Could not set closing position {id} back to open"
+        );
+
+        Ok(())
+    }
+
     pub fn update_unrealized_pnl(conn: &mut PgConnection, id: i32, pnl: i64) -> Result<()> {
         let affected_rows = diesel::update(positions::table)
             .filter(positions::id.eq(id))
@@ -0,0 +1,70 @@
+use crate::schema::reconciliation_actions;
+use anyhow::ensure;
+use anyhow::Result;
+use bitcoin::secp256k1::PublicKey;
+use diesel::prelude::*;
+use diesel::Insertable;
+use diesel::PgConnection;
+use diesel::Queryable;
+use diesel::RunQueryDsl;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+/// An audit record of a repair action that has been applied by the reconciliation.
+#[derive(Queryable, Serialize, Debug, Clone, PartialEq)]
+#[diesel(table_name = reconciliation_actions)]
+pub struct ReconciliationAction {
+    pub id: i32,
+    pub trader_pubkey: String,
+    pub position_id: Option<i32>,
+    /// The JSON representation of the inconsistency that has been repaired.
+    pub inconsistency: String,
+    /// The JSON representation of the repair action that has been applied.
+    pub action: String,
+    pub successful: bool,
+    pub error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = reconciliation_actions)]
+struct NewReconciliationAction {
+    pub trader_pubkey: String,
+    pub position_id: Option<i32>,
+    pub inconsistency: String,
+    pub action: String,
+    pub successful: bool,
+    pub error: Option<String>,
+}
+
+pub fn insert(
+    conn: &mut PgConnection,
+    trader: PublicKey,
+    position_id: Option<i32>,
+    inconsistency: String,
+    action: String,
+    error: Option<String>,
+) -> Result<()> {
+    let affected_rows = diesel::insert_into(reconciliation_actions::table)
+        .values(NewReconciliationAction {
+            trader_pubkey: trader.to_string(),
+            position_id,
+            inconsistency,
+            action,
+            successful: error.is_none(),
+            error,
+        })
+        .execute(conn)?;
+
+    ensure!(affected_rows > 0, "Could not insert reconciliation action");
+
+    Ok(())
+}
+
+/// Returns all applied reconciliation actions, latest first.
+pub fn get_all(conn: &mut PgConnection) -> QueryResult<Vec<ReconciliationAction>> {
+    reconciliation_actions::table
+        .order_by(reconciliation_actions::created_at.desc())
+        .load(conn)
+}
@@ -7,6 +7,7 @@ use bitcoin::secp256k1::PublicKey;
 use diesel::prelude::*;
 use hex::FromHex;
 use lightning::ln::PaymentHash;
+use std::collections::HashSet;
 use std::str::FromStr;
 use time::OffsetDateTime;
 
@@ -66,6 +67,60 @@ pub fn is_payment_hash_registered_as_trade_fee(
     Ok(trade.is_some())
 }
 
+/// Returns the payment hashes of the order-matching fees of the trades with `trader` since
+/// `since`, newest first.
+pub fn get_fee_payment_hashes_since(
+    conn: &mut PgConnection,
+    trader: PublicKey,
+    since: OffsetDateTime,
+) -> Result<Vec<PaymentHash>> {
+    let trades = trades::table
+        .filter(trades::trader_pubkey.eq(trader.to_string()))
+        .filter(trades::timestamp.ge(since))
+        .order_by(trades::id.desc())
+        .load::<Trade>(conn)?;
+
+    Ok(trades
+        .into_iter()
+        .map(|trade| crate::trade::models::Trade::from(trade).fee_payment_hash)
+        .collect())
+}
+
+/// Returns the ids of the given positions for which at least one trade has been recorded.
+pub fn get_position_ids_with_trades(
+    conn: &mut PgConnection,
+    position_ids: Vec<i32>,
+) -> QueryResult<HashSet<i32>> {
+    let position_ids = trades::table
+        .filter(trades::position_id.eq_any(position_ids))
+        .select(trades::position_id)
+        .distinct()
+        .load::<i32>(conn)?;
+
+    Ok(position_ids.into_iter().collect())
+}
+
+/// Returns the trader and the timestamp of all trades since `since`.
+pub fn get_timestamps_since(
+    conn: &mut PgConnection,
+    since: OffsetDateTime,
+) -> QueryResult<Vec<(PublicKey, OffsetDateTime)>> {
+    let trades = trades::table
+        .filter(trades::timestamp.ge(since))
+        .select((trades::trader_pubkey, trades::timestamp))
+        .load::<(String, OffsetDateTime)>(conn)?;
+
+    Ok(trades
+        .into_iter()
+        .map(|(trader_pubkey, timestamp)| {
+            (
+                PublicKey::from_str(trader_pubkey.as_str()).expect("public key to decode"),
+                timestamp,
+            )
+        })
+        .collect())
+}
+
 impl From<crate::trade::models::NewTrade> for NewTrade {
     fn from(value: crate::trade::models::NewTrade) -> Self {
         NewTrade {
@@ -13,6 +13,7 @@ pub mod admin;
 pub mod cli;
 mod collaborative_revert;
 pub mod db;
+pub mod liquidity;
 pub mod logger;
 pub mod message;
 pub mod metrics;
@@ -20,12 +21,14 @@ pub mod node;
 pub mod notifications;
 pub mod orderbook;
 pub mod position;
+pub mod reserve;
 pub mod routes;
 pub mod routing_fee;
 pub mod scheduler;
 pub mod schema;
 pub mod settings;
 pub mod trade;
+pub mod watchtower;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
@@ -42,6 +45,9 @@ pub enum AppError {
     NoMatchFound(String),
     InvalidOrder(String),
     ServiceUnavailable(String),
+    Unauthorized(String),
+    Forbidden(String),
+    TooManyRequests(String),
 }
 
 impl IntoResponse for AppError {
@@ -52,6 +58,9 @@ impl IntoResponse for AppError {
             AppError::NoMatchFound(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
             AppError::InvalidOrder(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
         };
 
         let body = Json(json!({
@@ -62,11 +71,13 @@ impl IntoResponse for AppError {
     }
 }
 
-/// Check if the liquidity is sufficient to open a JIT channel from the coordinator
+/// Check if the liquidity is sufficient to open a JIT channel from the coordinator, given that
+/// `reserved_sats` are already reserved for other JIT channels.
 pub fn is_liquidity_sufficient(
     settings: &Settings,
     balance: bdk::Balance,
+    reserved_sats: u64,
     amount_sats: u64,
 ) -> bool {
-    balance.get_spendable() >= amount_sats + settings.min_liquidity_threshold_sats
+    balance.get_spendable() >= amount_sats + reserved_sats + settings.min_liquidity_threshold_sats
 }
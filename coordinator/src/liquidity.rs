@@ -0,0 +1,634 @@
+//! Management of the on-chain liquidity of the coordinator, which funds the JIT channels.
+//!
+//! Periodically, the coordinator
+//!
+//! - releases the liquidity and the UTXOs reserved for JIT channels which have been funded or
+//!   have not been funded in time;
+//! - reports how fragmented its UTXOs are and how long its liquidity will last, based on the
+//!   liquidity requests of the recent past;
+//! - logs an alert for anything which needs the attention of an operator;
+//! - consolidates small UTXOs, if fees are low.
+
+use crate::db;
+use crate::db::liquidity::LiquidityRequestLog;
+use crate::db::liquidity_reservations::LiquidityReservation;
+use crate::is_liquidity_sufficient;
+use crate::node::Node;
+use crate::settings::Settings;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::LocalUtxo;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use diesel::Connection;
+use lightning::chain::chaininterface::ConfirmationTarget;
+use ln_dlc_node::channel::UserChannelId;
+use ln_dlc_node::Fee;
+use serde::Deserialize;
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::task::spawn_blocking;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LiquiditySettings {
+    /// UTXOs worth less than this (in sats) are considered small and get consolidated.
+    pub small_utxo_threshold_sats: u64,
+    /// Consolidate automatically once the wallet holds at least this many small UTXOs.
+    pub consolidation_min_utxos: usize,
+    /// The maximum number of UTXOs spent by a single consolidation.
+    pub consolidation_max_inputs: usize,
+    /// Only consolidate automatically if the fee rate for background transactions is at most this,
+    /// in sats/vbyte.
+    pub consolidation_max_fee_rate: f32,
+    /// Consolidate small UTXOs without an operator asking for it. Disabled by default, as it
+    /// spends the wallet's funds on fees.
+    pub auto_consolidate: bool,
+    /// The time (in seconds) liquidity stays reserved for a JIT channel which has not been funded.
+    pub reservation_timeout_secs: u64,
+    /// The number of days of liquidity requests the forecast is based on.
+    pub forecast_window_days: u32,
+    /// Alert if the liquidity is forecast to last for fewer days than this.
+    pub min_runway_days: f32,
+}
+
+impl Default for LiquiditySettings {
+    fn default() -> Self {
+        Self {
+            small_utxo_threshold_sats: 100_000,
+            consolidation_min_utxos: 20,
+            consolidation_max_inputs: 100,
+            consolidation_max_fee_rate: 2.0,
+            auto_consolidate: false,
+            reservation_timeout_secs: 60 * 60,
+            forecast_window_days: 7,
+            min_runway_days: 3.0,
+        }
+    }
+}
+
+impl LiquiditySettings {
+    pub fn reservation_timeout(&self) -> time::Duration {
+        time::Duration::seconds(self.reservation_timeout_secs as i64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct UtxoStats {
+    pub count: usize,
+    pub total_sats: u64,
+    /// The UTXOs worth less than `small_utxo_threshold_sats`.
+    pub small_count: usize,
+    pub small_sats: u64,
+    pub largest_sats: u64,
+    /// The UTXOs already spent by a funding transaction, which has not been seen on-chain yet.
+    pub locked_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Forecast {
+    pub window_days: u32,
+    pub requests: usize,
+    /// The requests which were declined for lack of liquidity.
+    pub declined_requests: usize,
+    pub requested_sats: u64,
+    pub daily_demand_sats: u64,
+    /// The number of days the available liquidity lasts at the current demand. `None` if there is
+    /// no demand.
+    pub runway_days: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LiquidityAlert {
+    /// The spendable balance does not cover the reservations and the minimum liquidity.
+    BelowThreshold {
+        spendable_sats: u64,
+        required_sats: u64,
+    },
+    LowRunway {
+        runway_days: f32,
+    },
+    Fragmented {
+        small_utxos: usize,
+    },
+    DeclinedRequests {
+        count: usize,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct LiquidityReport {
+    pub spendable_sats: u64,
+    pub reserved_sats: u64,
+    /// What is left for new JIT channels, once the reservations and the minimum liquidity are
+    /// accounted for.
+    pub available_sats: u64,
+    pub min_liquidity_threshold_sats: u64,
+    /// The fee rate for background transactions, in sats/vbyte.
+    pub background_fee_rate: f32,
+    pub utxos: UtxoStats,
+    pub forecast: Forecast,
+    pub reservations: Vec<LiquidityReservation>,
+    pub alerts: Vec<LiquidityAlert>,
+}
+
+/// A transaction spending small UTXOs back to the wallet.
+#[derive(Debug, Serialize)]
+pub struct Consolidation {
+    pub txid: Txid,
+    pub inputs: usize,
+    pub amount: u64,
+    pub fee: u64,
+    /// In sats/vbyte.
+    pub fee_rate: f32,
+    pub broadcast: bool,
+}
+
+/// Releases stale reservations, logs alerts and consolidates small UTXOs if fees are low.
+pub async fn manage(node: Node) -> Result<()> {
+    let settings = node.settings().await;
+
+    spawn_blocking(move || {
+        let mut conn = node.pool.get()?;
+        let released = db::liquidity_reservations::release_stale(&mut conn)?;
+        if released > 0 {
+            tracing::debug!(released, "Released liquidity reservations");
+        }
+
+        let report = report(
+            &node,
+            &settings.liquidity,
+            settings.min_liquidity_threshold_sats,
+        )?;
+        for alert in report.alerts.iter() {
+            tracing::warn!(?alert, "On-chain liquidity needs attention");
+        }
+
+        if settings.liquidity.auto_consolidate {
+            consolidate(&node, &settings.liquidity, None, false)?;
+        }
+
+        Ok(())
+    })
+    .await
+    .expect("task to complete")
+}
+
+pub fn report(
+    node: &Node,
+    settings: &LiquiditySettings,
+    min_liquidity_threshold_sats: u64,
+) -> Result<LiquidityReport> {
+    let mut conn = node.pool.get()?;
+
+    let wallet = node.inner.wallet();
+    let spendable_sats = node.inner.get_on_chain_balance()?.get_spendable();
+    let utxos = utxo_stats(
+        &wallet.list_utxos()?,
+        &wallet.locked_outpoints(),
+        settings.small_utxo_threshold_sats,
+    );
+    let background_fee_rate = wallet
+        .get_fee_rate(ConfirmationTarget::Background)
+        .as_sat_per_vb();
+
+    let reservations = db::liquidity_reservations::get_all(&mut conn)?;
+    let reserved_sats = reservations
+        .iter()
+        .map(|reservation| reservation.amount_sats as u64)
+        .sum::<u64>();
+    let available_sats =
+        spendable_sats.saturating_sub(reserved_sats + min_liquidity_threshold_sats);
+
+    let since =
+        OffsetDateTime::now_utc() - time::Duration::days(settings.forecast_window_days as i64);
+    let requests = db::liquidity::get_since(&mut conn, since)?;
+    let forecast = forecast(&requests, settings.forecast_window_days, available_sats);
+
+    let alerts = alerts(
+        settings,
+        spendable_sats,
+        reserved_sats + min_liquidity_threshold_sats,
+        &utxos,
+        &forecast,
+    );
+
+    Ok(LiquidityReport {
+        spendable_sats,
+        reserved_sats,
+        available_sats,
+        min_liquidity_threshold_sats,
+        background_fee_rate,
+        utxos,
+        forecast,
+        reservations,
+        alerts,
+    })
+}
+
+/// Reserves `amount_sats` and enough UTXOs to cover them for the JIT channel `user_channel_id`
+/// of `trader`, if the liquidity not reserved for other traders suffices.
+///
+/// Returns `false` if the liquidity is not sufficient.
+pub fn reserve(
+    node: &Node,
+    settings: &Settings,
+    user_channel_id: UserChannelId,
+    trader: PublicKey,
+    amount_sats: u64,
+) -> Result<bool> {
+    let mut conn = node.pool.get()?;
+    let wallet = node.inner.wallet();
+
+    conn.transaction(|conn| {
+        db::liquidity_reservations::lock(conn)?;
+
+        let balance = node.inner.get_on_chain_balance()?;
+        let reserved_sats = db::liquidity_reservations::get_reserved_sats_for_others(conn, trader)?;
+        if !is_liquidity_sufficient(settings, balance, reserved_sats, amount_sats) {
+            return Ok(false);
+        }
+
+        let mut unavailable =
+            db::liquidity_reservations::get_reserved_outpoints_for_others(conn, trader)?;
+        unavailable.extend(wallet.locked_outpoints());
+
+        let utxos = match select_utxos_to_reserve(&wallet.list_utxos()?, &unavailable, amount_sats)
+        {
+            Some(utxos) => utxos,
+            None => {
+                tracing::warn!(
+                    %trader,
+                    amount_sats,
+                    "Not enough unreserved UTXOs for JIT channel"
+                );
+                return Ok(false);
+            }
+        };
+
+        db::liquidity_reservations::insert(
+            conn,
+            user_channel_id,
+            trader,
+            amount_sats,
+            &utxos,
+            settings.liquidity.reservation_timeout(),
+        )?;
+
+        Ok(true)
+    })
+}
+
+/// Spends small UTXOs back to the wallet.
+///
+/// UTXOs reserved for JIT channels are left alone.
+///
+/// Without a `fee`, this only happens if there are at least `consolidation_min_utxos` small UTXOs
+/// and the fee rate for background transactions does not exceed `consolidation_max_fee_rate`.
+/// Returns `None` if there is nothing to consolidate.
+pub fn consolidate(
+    node: &Node,
+    settings: &LiquiditySettings,
+    fee: Option<Fee>,
+    dry_run: bool,
+) -> Result<Option<Consolidation>> {
+    let wallet = node.inner.wallet();
+
+    let mut conn = node.pool.get()?;
+    let mut unavailable = db::liquidity_reservations::get_reserved_outpoints(&mut conn)?;
+    unavailable.extend(wallet.locked_outpoints());
+
+    let utxos = select_small_utxos(
+        &wallet.list_utxos()?,
+        &unavailable,
+        settings.small_utxo_threshold_sats,
+        settings.consolidation_max_inputs,
+    );
+
+    // Spending a single UTXO to ourselves would only cost fees.
+    if utxos.len() < 2 {
+        return Ok(None);
+    }
+
+    let fee_rate = match fee {
+        Some(fee) => wallet.resolve_fee_rate(fee),
+        None => {
+            if utxos.len() < settings.consolidation_min_utxos {
+                return Ok(None);
+            }
+
+            let fee_rate = wallet.get_fee_rate(ConfirmationTarget::Background);
+            if fee_rate.as_sat_per_vb() > settings.consolidation_max_fee_rate {
+                tracing::debug!(
+                    fee_rate = fee_rate.as_sat_per_vb(),
+                    max_fee_rate = settings.consolidation_max_fee_rate,
+                    "Not consolidating UTXOs while fees are high"
+                );
+                return Ok(None);
+            }
+
+            fee_rate
+        }
+    };
+
+    let address = node.inner.get_unused_address();
+    let payment = wallet
+        .prepare_payment(&address, None, Fee::FeeRate(fee_rate), &utxos)
+        .context("Failed to create consolidation transaction")?;
+
+    let txid = payment.transaction.txid();
+    if !dry_run {
+        wallet.broadcast_payment(&payment.transaction)?;
+
+        tracing::info!(
+            %txid,
+            inputs = utxos.len(),
+            amount = payment.amount,
+            fee = payment.fee,
+            "Consolidated UTXOs"
+        );
+    }
+
+    Ok(Some(Consolidation {
+        txid,
+        inputs: utxos.len(),
+        amount: payment.amount,
+        fee: payment.fee,
+        fee_rate: payment.fee_rate(),
+        broadcast: !dry_run,
+    }))
+}
+
+fn utxo_stats(
+    utxos: &[LocalUtxo],
+    locked: &[OutPoint],
+    small_utxo_threshold_sats: u64,
+) -> UtxoStats {
+    utxos.iter().fold(UtxoStats::default(), |mut stats, utxo| {
+        let value = utxo.txout.value;
+
+        stats.count += 1;
+        stats.total_sats += value;
+        stats.largest_sats = stats.largest_sats.max(value);
+
+        if value < small_utxo_threshold_sats {
+            stats.small_count += 1;
+            stats.small_sats += value;
+        }
+
+        if locked.contains(&utxo.outpoint) {
+            stats.locked_count += 1;
+        }
+
+        stats
+    })
+}
+
+/// The smallest UTXOs below `small_utxo_threshold_sats` which are not locked or reserved, at most
+/// `max_inputs` of them.
+fn select_small_utxos(
+    utxos: &[LocalUtxo],
+    unavailable: &[OutPoint],
+    small_utxo_threshold_sats: u64,
+    max_inputs: usize,
+) -> Vec<OutPoint> {
+    let mut small = utxos
+        .iter()
+        .filter(|utxo| utxo.txout.value < small_utxo_threshold_sats)
+        .filter(|utxo| !unavailable.contains(&utxo.outpoint))
+        .collect::<Vec<_>>();
+
+    small.sort_by_key(|utxo| utxo.txout.value);
+
+    small
+        .into_iter()
+        .take(max_inputs)
+        .map(|utxo| utxo.outpoint)
+        .collect()
+}
+
+/// The largest UTXOs which are not locked or reserved, until they add up to `amount_sats`.
+///
+/// Returns `None` if all of them together are not worth `amount_sats`.
+fn select_utxos_to_reserve(
+    utxos: &[LocalUtxo],
+    unavailable: &[OutPoint],
+    amount_sats: u64,
+) -> Option<Vec<(OutPoint, u64)>> {
+    let mut available = utxos
+        .iter()
+        .filter(|utxo| !unavailable.contains(&utxo.outpoint))
+        .collect::<Vec<_>>();
+
+    available.sort_by_key(|utxo| std::cmp::Reverse(utxo.txout.value));
+
+    let mut selected = Vec::new();
+    let mut selected_sats = 0;
+    for utxo in available {
+        if selected_sats >= amount_sats {
+            break;
+        }
+
+        selected.push((utxo.outpoint, utxo.txout.value));
+        selected_sats += utxo.txout.value;
+    }
+
+    (selected_sats >= amount_sats).then_some(selected)
+}
+
+fn forecast(requests: &[LiquidityRequestLog], window_days: u32, available_sats: u64) -> Forecast {
+    // Declined requests count towards the demand too, we would have served them if we could.
+    let requested_sats = requests
+        .iter()
+        .map(|request| request.requested_amount_sats as u64)
+        .sum::<u64>();
+    let declined_requests = requests
+        .iter()
+        .filter(|request| !request.successfully_requested)
+        .count();
+
+    let daily_demand_sats = requested_sats / window_days.max(1) as u64;
+    let runway_days =
+        (daily_demand_sats > 0).then(|| available_sats as f32 / daily_demand_sats as f32);
+
+    Forecast {
+        window_days,
+        requests: requests.len(),
+        declined_requests,
+        requested_sats,
+        daily_demand_sats,
+        runway_days,
+    }
+}
+
+fn alerts(
+    settings: &LiquiditySettings,
+    spendable_sats: u64,
+    required_sats: u64,
+    utxos: &UtxoStats,
+    forecast: &Forecast,
+) -> Vec<LiquidityAlert> {
+    let mut alerts = Vec::new();
+
+    if spendable_sats < required_sats {
+        alerts.push(LiquidityAlert::BelowThreshold {
+            spendable_sats,
+            required_sats,
+        });
+    }
+
+    if let Some(runway_days) = forecast.runway_days {
+        if runway_days < settings.min_runway_days {
+            alerts.push(LiquidityAlert::LowRunway { runway_days });
+        }
+    }
+
+    if utxos.small_count >= settings.consolidation_min_utxos {
+        alerts.push(LiquidityAlert::Fragmented {
+            small_utxos: utxos.small_count,
+        });
+    }
+
+    if forecast.declined_requests > 0 {
+        alerts.push(LiquidityAlert::DeclinedRequests {
+            count: forecast.declined_requests,
+        });
+    }
+
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::KeychainKind;
+    use bitcoin::hashes::Hash;
+    use bitcoin::TxOut;
+
+    #[test]
+    fn small_unlocked_utxos_are_consolidated_smallest_first() {
+        let utxos = vec![
+            utxo(0, 50_000),
+            utxo(1, 500_000),
+            utxo(2, 10_000),
+            utxo(3, 20_000),
+            utxo(4, 30_000),
+        ];
+        let locked = vec![utxos[3].outpoint];
+
+        let selected = select_small_utxos(&utxos, &locked, 100_000, 2);
+
+        assert_eq!(selected, vec![utxos[2].outpoint, utxos[4].outpoint]);
+
+        let stats = utxo_stats(&utxos, &locked, 100_000);
+        assert_eq!(
+            stats,
+            UtxoStats {
+                count: 5,
+                total_sats: 610_000,
+                small_count: 4,
+                small_sats: 110_000,
+                largest_sats: 500_000,
+                locked_count: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn largest_available_utxos_are_reserved() {
+        let utxos = vec![
+            utxo(0, 50_000),
+            utxo(1, 500_000),
+            utxo(2, 300_000),
+            utxo(3, 200_000),
+        ];
+        let unavailable = vec![utxos[1].outpoint];
+
+        let selected = select_utxos_to_reserve(&utxos, &unavailable, 400_000);
+
+        assert_eq!(
+            selected,
+            Some(vec![
+                (utxos[2].outpoint, 300_000),
+                (utxos[3].outpoint, 200_000)
+            ])
+        );
+        assert_eq!(select_utxos_to_reserve(&utxos, &unavailable, 600_000), None);
+    }
+
+    #[test]
+    fn forecast_counts_declined_requests_as_demand() {
+        let requests = vec![request(700_000, true), request(700_000, false)];
+
+        let forecast = forecast(&requests, 7, 1_000_000);
+
+        assert_eq!(forecast.daily_demand_sats, 200_000);
+        assert_eq!(forecast.runway_days, Some(5.0));
+        assert_eq!(forecast.declined_requests, 1);
+    }
+
+    #[test]
+    fn no_demand_means_no_runway_alert() {
+        let forecast = forecast(&[], 7, 0);
+        let settings = LiquiditySettings::default();
+
+        let alerts = alerts(&settings, 0, 0, &UtxoStats::default(), &forecast);
+
+        assert_eq!(forecast.runway_days, None);
+        assert!(alerts.is_empty());
+    }
+
+    #[test]
+    fn alerts_on_low_liquidity() {
+        let settings = LiquiditySettings::default();
+        let forecast = forecast(&[request(7_000_000, false)], 7, 1_000_000);
+        let utxos = UtxoStats {
+            small_count: settings.consolidation_min_utxos,
+            ..UtxoStats::default()
+        };
+
+        let alerts = alerts(&settings, 1_000_000, 2_000_000, &utxos, &forecast);
+
+        assert_eq!(
+            alerts,
+            vec![
+                LiquidityAlert::BelowThreshold {
+                    spendable_sats: 1_000_000,
+                    required_sats: 2_000_000,
+                },
+                LiquidityAlert::LowRunway { runway_days: 1.0 },
+                LiquidityAlert::Fragmented {
+                    small_utxos: settings.consolidation_min_utxos,
+                },
+                LiquidityAlert::DeclinedRequests { count: 1 },
+            ]
+        );
+    }
+
+    fn utxo(vout: u32, value: u64) -> LocalUtxo {
+        LocalUtxo {
+            outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            },
+            txout: TxOut {
+                value,
+                script_pubkey: Default::default(),
+            },
+            keychain: KeychainKind::External,
+            is_spent: false,
+        }
+    }
+
+    fn request(requested_amount_sats: i64, successfully_requested: bool) -> LiquidityRequestLog {
+        LiquidityRequestLog {
+            id: None,
+            trader_pk: String::new(),
+            timestamp: OffsetDateTime::now_utc(),
+            requested_amount_sats,
+            liquidity_option: 1,
+            successfully_requested,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::db;
+use crate::liquidity::LiquiditySettings;
 use crate::node::storage::NodeStorage;
 use crate::orderbook::db::matches;
 use crate::orderbook::db::orders;
@@ -35,10 +36,10 @@ use dlc_manager::ChannelId;
 use dlc_manager::ContractId;
 use dlc_messages::ChannelMessage;
 use dlc_messages::Message;
+use dlc_messages::SubChannelMessage;
 use lightning::ln::channelmanager::ChannelDetails;
 use lightning::ln::PaymentHash;
 use lightning::util::config::UserConfig;
-use lightning_invoice::Invoice;
 use ln_dlc_node::node;
 use ln_dlc_node::node::dlc_message_name;
 use ln_dlc_node::node::sub_channel_message_name;
@@ -62,7 +63,9 @@ pub mod channel_opening_fee;
 pub mod closed_positions;
 pub mod connection;
 pub mod expired_positions;
+pub mod match_timeout;
 pub mod order_matching_fee;
+pub mod reconciliation;
 pub mod rollover;
 pub mod routing_fees;
 pub mod storage;
@@ -83,6 +86,11 @@ pub struct NodeSettings {
     pub max_allowed_tx_fee_rate_when_opening_channel: Option<u32>,
     /// Defines the sats/vbyte to be used for all transactions within the sub-channel
     pub contract_tx_fee_rate: u64,
+    /// The time (in seconds) after which a matched but not executed market order is abandoned
+    pub match_execution_timeout_secs: u64,
+    /// Min balance to keep in on-chain wallet at all times
+    pub min_liquidity_threshold_sats: u64,
+    pub liquidity: LiquiditySettings,
 }
 
 impl NodeSettings {
@@ -126,6 +134,10 @@ impl Node {
         self.inner.wallet().update_settings(wallet_settings).await;
     }
 
+    pub async fn settings(&self) -> NodeSettings {
+        self.settings.read().await.clone()
+    }
+
     pub fn update_ldk_settings(&self, ldk_config: UserConfig) {
         self.inner.update_ldk_settings(ldk_config)
     }
@@ -147,12 +159,25 @@ impl Node {
         !usable_channels.is_empty()
     }
 
-    pub async fn trade(&self, trade_params: &TradeParams) -> Result<Invoice> {
+    /// Executes the trade described by `trade_params`, once the trader has paid the order-matching
+    /// fee invoice created via [`Node::fee_invoice_taker`].
+    ///
+    /// The payment of the fee is held while the DLC of the trade is being set up. It is settled
+    /// once the DLC has been signed and refunded if the trade fails.
+    pub async fn trade(&self, trade_params: &TradeParams) -> Result<()> {
         let mut connection = self.pool.get()?;
         let order_id = trade_params.filled_with.order_id;
         let trader_id = trade_params.pubkey;
-        match self.trade_internal(trade_params, &mut connection).await {
-            Ok(invoice) => {
+
+        // Prevents the match from being abandoned while we are executing it, see
+        // [`match_timeout::fail_stuck_matches`].
+        ensure!(
+            orders::try_lock_execution(&mut connection, order_id)?,
+            "Order {order_id} is already being executed"
+        );
+
+        let result = match self.trade_internal(trade_params, &mut connection).await {
+            Ok(()) => {
                 tracing::info!(
                     %trader_id,
                     %order_id,
@@ -164,8 +189,7 @@ impl Node {
                     order_id,
                     MatchState::Filled,
                     OrderState::Taken,
-                )?;
-                Ok(invoice)
+                )
             }
             Err(e) => {
                 tracing::error!(
@@ -178,20 +202,50 @@ impl Node {
                     order_id,
                     MatchState::Failed,
                     OrderState::Failed,
-                )?;
-
-                Err(e)
+                )
+                .and(Err(e))
             }
+        };
+
+        if let Err(e) = orders::unlock_execution(&mut connection, order_id) {
+            tracing::error!(%trader_id, %order_id, "Failed to unlock order execution. Error: {e:#}");
         }
+
+        result
     }
 
     async fn trade_internal(
         &self,
         trade_params: &TradeParams,
         connection: &mut PgConnection,
-    ) -> Result<Invoice> {
-        let (fee_payment_hash, invoice) = self.fee_invoice_taker(trade_params).await?;
+    ) -> Result<()> {
+        let fee_payment_hash = self.wait_for_fee_payment(trade_params).await?;
 
+        // The order-matching fee is only settled once the DLC has been signed, so we don't have
+        // to hold on to it if the trade can't even be started.
+        if let Err(e) = self
+            .execute_trade(trade_params, fee_payment_hash, connection)
+            .await
+        {
+            if let Err(e) = self.cancel_fee_invoice(fee_payment_hash) {
+                tracing::error!(
+                    trader_id = %trade_params.pubkey,
+                    "Failed to cancel order-matching fee invoice: {e:#}"
+                );
+            }
+
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn execute_trade(
+        &self,
+        trade_params: &TradeParams,
+        fee_payment_hash: PaymentHash,
+        connection: &mut PgConnection,
+    ) -> Result<()> {
         let order_id = trade_params.filled_with.order_id;
         let trader_id = trade_params.pubkey.to_string();
         let order = orders::get_with_id(connection, order_id)?.with_context(|| {
@@ -274,7 +328,7 @@ impl Node {
             }
         };
 
-        Ok(invoice)
+        Ok(())
     }
 
     #[autometrics]
@@ -555,17 +609,24 @@ impl Node {
                         dlc_message_name(&msg)
                     )
                 })?,
-            Message::SubChannel(msg) => self
-                .inner
-                .sub_channel_manager
-                .on_sub_channel_message(msg, &node_id)
-                .with_context(|| {
-                    format!(
-                        "Failed to handle {} message from {node_id}",
-                        sub_channel_message_name(msg)
-                    )
-                })?
-                .map(Message::SubChannel),
+            Message::SubChannel(msg) => {
+                let resp = self
+                    .inner
+                    .sub_channel_manager
+                    .on_sub_channel_message(msg, &node_id)
+                    .with_context(|| {
+                        format!(
+                            "Failed to handle {} message from {node_id}",
+                            sub_channel_message_name(msg)
+                        )
+                    });
+
+                if resp.is_err() {
+                    self.cancel_fee_of_failed_trade(node_id, sub_channel_id(msg));
+                }
+
+                resp?.map(Message::SubChannel)
+            }
         };
 
         // TODO(holzeis): It would be nice if dlc messages are also propagated via events, so the
@@ -575,6 +636,24 @@ impl Node {
             self.finalize_rollover(&r.channel_id)?;
         }
 
+        // Once the trader has finalized the DLC, the trade has been executed and we can claim the
+        // order-matching fee for it.
+        if let Message::SubChannel(
+            msg @ (SubChannelMessage::Finalize(_) | SubChannelMessage::CloseFinalize(_)),
+        ) = &msg
+        {
+            if let Err(e) = self.settle_fee_invoice(node_id, sub_channel_id(msg)) {
+                tracing::error!(
+                    trader_id = %node_id,
+                    "Failed to settle order-matching fee invoice: {e:#}"
+                );
+            }
+        }
+
+        if let Message::SubChannel(msg @ SubChannelMessage::Reject(_)) = &msg {
+            self.cancel_fee_of_failed_trade(node_id, sub_channel_id(msg));
+        }
+
         if let Some(msg) = resp {
             tracing::info!(
                 to = %node_id,
@@ -587,6 +666,33 @@ impl Node {
 
         Ok(())
     }
+
+    /// Refunds the order-matching fee of the trade being executed with `trader` over
+    /// `channel_id`, because its DLC protocol failed.
+    fn cancel_fee_of_failed_trade(&self, trader: PublicKey, channel_id: ChannelId) {
+        if let Err(e) = self.cancel_pending_fee_invoice(trader, channel_id) {
+            tracing::error!(
+                trader_id = %trader,
+                channel_id = %hex::encode(channel_id),
+                "Failed to cancel order-matching fee invoice of failed trade: {e:#}"
+            );
+        }
+    }
+}
+
+fn sub_channel_id(msg: &SubChannelMessage) -> ChannelId {
+    match msg {
+        SubChannelMessage::Offer(msg) => msg.channel_id,
+        SubChannelMessage::Accept(msg) => msg.channel_id,
+        SubChannelMessage::Confirm(msg) => msg.channel_id,
+        SubChannelMessage::Finalize(msg) => msg.channel_id,
+        SubChannelMessage::Revoke(msg) => msg.channel_id,
+        SubChannelMessage::CloseOffer(msg) => msg.channel_id,
+        SubChannelMessage::CloseAccept(msg) => msg.channel_id,
+        SubChannelMessage::CloseConfirm(msg) => msg.channel_id,
+        SubChannelMessage::CloseFinalize(msg) => msg.channel_id,
+        SubChannelMessage::Reject(msg) => msg.channel_id,
+    }
 }
 
 fn update_order_and_match(
@@ -601,7 +707,7 @@ fn update_order_and_match(
 
             orders::set_order_state(connection, order_id, order_state)?;
 
-            diesel::result::QueryResult::Ok(())
+            anyhow::Ok(())
         })
         .map_err(|e| anyhow!("Failed to update order and match. Error: {e:#}"))
 }
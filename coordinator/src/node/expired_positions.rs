@@ -94,6 +94,7 @@ pub async fn close(node: Node, trading_sender: mpsc::Sender<NewOrderMessage>) ->
             // close.
             expiry: OffsetDateTime::now_utc().add(EXPIRED_POSITION_TIMEOUT),
             stable: position.stable,
+            worst_price: None,
         };
 
         let (sender, mut receiver) = mpsc::channel::<Result<Order>>(1);
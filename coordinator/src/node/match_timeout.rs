@@ -0,0 +1,201 @@
+use crate::message::OrderbookMessage;
+use crate::node::Node;
+use crate::orderbook::db::matches;
+use crate::orderbook::db::orders;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::secp256k1::PublicKey;
+use diesel::Connection;
+use diesel::PgConnection;
+use orderbook_commons::Message;
+use orderbook_commons::Order;
+use orderbook_commons::OrderReason;
+use orderbook_commons::OrderState;
+use orderbook_commons::OrderType;
+use std::collections::HashMap;
+use time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// The outcome of abandoning the match of a market order.
+struct AbandonedMatch {
+    /// The traders which have to be notified about the abandoned match, together with the id of
+    /// their order.
+    traders: Vec<(PublicKey, Uuid)>,
+    /// The limit orders which have been released back into the orderbook.
+    released_orders: Vec<Order>,
+    /// The limit orders which have expired in the meantime and could not be released.
+    expired_orders: Vec<Uuid>,
+}
+
+/// Fails all market orders which have been matched but not executed within the configured match
+/// execution timeout, counted from the time the order has been matched.
+///
+/// The corresponding matches are failed and the liquidity of the matched limit orders is released
+/// back into the orderbook. Both sides of the match are notified with a
+/// [`Message::MatchAbandoned`].
+///
+/// Orders created because of an expired position are not considered, as those are executed
+/// asynchronously once the trader comes online and are handled in [`super::expired_positions`].
+/// Neither are orders which are currently being executed.
+pub async fn fail_stuck_matches(
+    node: Node,
+    notifier: mpsc::Sender<OrderbookMessage>,
+    tx_price_feed: broadcast::Sender<Message>,
+) -> Result<()> {
+    let timeout = Duration::seconds(node.settings.read().await.match_execution_timeout_secs as i64);
+    let mut conn = node.pool.get()?;
+
+    let now = OffsetDateTime::now_utc();
+    let matched_orders =
+        orders::get_all_orders(&mut conn, OrderType::Market, OrderState::Matched, false)?
+            .into_iter()
+            .filter(|order| order.order_reason == OrderReason::Manual)
+            .collect::<Vec<_>>();
+
+    for order in matched_orders {
+        let trader_id = order.trader_id;
+        let order_id = order.id;
+
+        let matched_at = match matched_at(&mut conn, &order) {
+            Ok(matched_at) => matched_at,
+            Err(e) => {
+                tracing::error!(%trader_id, %order_id, "Failed to load matches. Error: {e:#}");
+                continue;
+            }
+        };
+        if matched_at + timeout >= now {
+            continue;
+        }
+
+        tracing::warn!(
+            %trader_id,
+            %order_id,
+            ?timeout,
+            "Matched order has not been executed in time. Abandoning match"
+        );
+
+        let abandoned_match = match abandon_match(&mut conn, &order, now) {
+            Ok(Some(abandoned_match)) => abandoned_match,
+            Ok(None) => {
+                tracing::debug!(%trader_id, %order_id, "Order is being executed. Not abandoning match");
+                continue;
+            }
+            Err(e) => {
+                tracing::error!(%trader_id, %order_id, "Failed to abandon match. Error: {e:#}");
+                continue;
+            }
+        };
+
+        let price_feed_updates = abandoned_match
+            .released_orders
+            .into_iter()
+            .map(Message::Update)
+            .chain(
+                abandoned_match
+                    .expired_orders
+                    .into_iter()
+                    .map(Message::DeleteOrder),
+            );
+        for update in price_feed_updates {
+            if let Err(e) = tx_price_feed.send(update) {
+                tracing::warn!("Could not update price feed. Error: {e:#}");
+            }
+        }
+
+        let reason = format!("Trade has not been executed within {timeout}");
+        for (trader_id, order_id) in abandoned_match.traders {
+            let msg = OrderbookMessage::TraderMessage {
+                trader_id,
+                message: Message::MatchAbandoned {
+                    order_id,
+                    reason: reason.clone(),
+                },
+                notification: None,
+            };
+            if let Err(e) = notifier.send(msg).await {
+                tracing::warn!(%trader_id, %order_id, "Failed to notify trader about abandoned match. Error: {e:#}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the time at which the given order has been matched.
+fn matched_at(conn: &mut PgConnection, order: &Order) -> Result<OffsetDateTime> {
+    let matched_at = matches::get_matches_by_order_id(conn, order.id)?
+        .into_iter()
+        .map(|m| m.created_at)
+        .min()
+        // Only matched orders are considered, but we'd rather abandon an order without matches
+        // too late than never.
+        .unwrap_or(order.timestamp);
+
+    Ok(matched_at)
+}
+
+/// Fails the given market order and its pending matches and releases the matched limit orders.
+///
+/// Returns `None` if the order is being executed, or has been executed in the meantime.
+fn abandon_match(
+    conn: &mut PgConnection,
+    order: &Order,
+    now: OffsetDateTime,
+) -> Result<Option<AbandonedMatch>> {
+    conn.transaction(|conn| {
+        // The same lock is held by [`Node::trade`] while the order is executed.
+        if !orders::try_lock_execution_until_commit(conn, order.id)? {
+            return Ok(None);
+        }
+
+        let order = orders::get_with_id(conn, order.id)?
+            .with_context(|| format!("Could not find order {}", order.id))?;
+        if order.order_state != OrderState::Matched {
+            return Ok(None);
+        }
+
+        orders::set_order_state(conn, order.id, OrderState::Failed)?;
+
+        let failed_matches = matches::fail_pending_matches(conn, order.id)?;
+
+        // The matches of the makers reference their limit order as `order_id`.
+        let maker_orders = failed_matches
+            .into_iter()
+            .filter(|m| m.order_id != order.id)
+            .map(|m| (m.order_id, m.trader_id))
+            .collect::<HashMap<_, _>>();
+
+        let mut traders = vec![(order.trader_id, order.id)];
+        let mut released_orders = vec![];
+        let mut expired_orders = vec![];
+        for (maker_order_id, maker_id) in maker_orders {
+            traders.push((maker_id, maker_order_id));
+
+            let maker_order = orders::get_with_id(conn, maker_order_id)?
+                .with_context(|| format!("Could not find limit order {maker_order_id}"))?;
+
+            // The maker might have already taken the limit order in the meantime.
+            if maker_order.order_state != OrderState::Matched {
+                continue;
+            }
+
+            if maker_order.expiry > now {
+                let released_order =
+                    orders::set_order_state(conn, maker_order_id, OrderState::Open)?;
+                released_orders.push(released_order);
+            } else {
+                orders::set_order_state(conn, maker_order_id, OrderState::Failed)?;
+                expired_orders.push(maker_order_id);
+            }
+        }
+
+        Ok(Some(AbandonedMatch {
+            traders,
+            released_orders,
+            expired_orders,
+        }))
+    })
+}
@@ -1,25 +1,47 @@
 use crate::db;
 use crate::node::Node;
+use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
-use bitcoin::secp256k1::ThirtyTwoByteHash;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::PublicKey;
 use coordinator_commons::TradeParams;
+use dlc_manager::ChannelId;
 use lightning::ln::PaymentHash;
+use lightning::ln::PaymentPreimage;
 use lightning_invoice::Invoice;
+use ln_dlc_node::node::HoldInvoiceState;
 use ln_dlc_node::PaymentInfo;
 use orderbook_commons::order_matching_fee_taker;
 use orderbook_commons::FEE_INVOICE_DESCRIPTION_PREFIX_TAKER;
+use rand::Rng;
+use std::time::Duration;
+use time::OffsetDateTime;
 
 /// How long the fee invoice will last for.
-const INVOICE_EXPIRY: u32 = 3600;
+///
+/// The trader's payment is held for at most this long, so the DLC has to be signed within it.
+const INVOICE_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// How long we wait for the payment of the fee invoice before giving up on the trade.
+const FEE_PAYMENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often we check whether the payment of the fee invoice has arrived.
+const FEE_PAYMENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 impl Node {
+    /// Creates a hold invoice for the order-matching fee of the trade described by
+    /// `trade_params`.
+    ///
+    /// The trader has to pay it before requesting the trade, see [`Node::trade`]. The fee is only
+    /// settled once the DLC of the trade has been signed, see [`Node::settle_fee_invoice`].
     pub async fn fee_invoice_taker(
         &self,
         trade_params: &TradeParams,
     ) -> Result<(PaymentHash, Invoice)> {
-        let order_id = trade_params.filled_with.order_id;
-        let description = format!("{FEE_INVOICE_DESCRIPTION_PREFIX_TAKER}{order_id}");
+        let description = fee_invoice_description(trade_params);
 
         let fee = order_matching_fee_taker(
             trade_params.quantity,
@@ -27,12 +49,17 @@ impl Node {
         )
         .to_sat();
 
-        let invoice = self
-            .inner
-            .create_invoice(fee, description, INVOICE_EXPIRY)?;
+        let preimage = PaymentPreimage(rand::thread_rng().gen());
+        let fee_payment_hash = PaymentHash(sha256::Hash::hash(&preimage.0).into_inner());
 
-        let fee_payment_hash = PaymentHash((*invoice.payment_hash()).into_32());
-        let fee_payment_info = PaymentInfo::from(invoice.clone());
+        let invoice =
+            self.inner
+                .create_hold_invoice(fee, description, fee_payment_hash, INVOICE_EXPIRY)?;
+
+        let fee_payment_info = PaymentInfo {
+            preimage: Some(preimage),
+            ..PaymentInfo::from(invoice.clone())
+        };
         let mut conn = self.pool.get()?;
 
         db::payments::insert((fee_payment_hash, fee_payment_info), &mut conn)
@@ -40,4 +67,145 @@ impl Node {
 
         Ok((fee_payment_hash, invoice))
     }
+
+    /// Waits until the trader's payment of an order-matching fee invoice for the trade described by
+    /// `trade_params` is held, returning its payment hash.
+    ///
+    /// The payment has to be locked in before the DLC protocol starts, so that adding its HTLC
+    /// does not interfere with the channel updates of the DLC protocol. If it does not arrive in
+    /// time, the fee invoices of the trade are cancelled, so that a late payment is refunded.
+    pub async fn wait_for_fee_payment(&self, trade_params: &TradeParams) -> Result<PaymentHash> {
+        let description = fee_invoice_description(trade_params);
+
+        let fee_payment_hashes = {
+            let mut conn = self.pool.get()?;
+            db::payments::get_inbound_payment_hashes_by_description(&mut conn, &description)?
+        };
+        ensure!(
+            !fee_payment_hashes.is_empty(),
+            "No order-matching fee invoice for order {}",
+            trade_params.filled_with.order_id
+        );
+
+        let accepted = tokio::time::timeout(FEE_PAYMENT_TIMEOUT, async {
+            loop {
+                let accepted = fee_payment_hashes.iter().find(|fee_payment_hash| {
+                    matches!(
+                        self.inner.hold_invoice_state(fee_payment_hash),
+                        Some(HoldInvoiceState::Accepted { .. })
+                    )
+                });
+
+                match accepted {
+                    Some(fee_payment_hash) => return *fee_payment_hash,
+                    None => tokio::time::sleep(FEE_PAYMENT_POLL_INTERVAL).await,
+                }
+            }
+        })
+        .await;
+
+        match accepted {
+            Ok(fee_payment_hash) => Ok(fee_payment_hash),
+            Err(_) => {
+                for fee_payment_hash in fee_payment_hashes {
+                    if let Err(e) = self.cancel_fee_invoice(fee_payment_hash) {
+                        tracing::debug!(
+                            trader_id = %trade_params.pubkey,
+                            "Could not cancel unpaid order-matching fee invoice: {e:#}"
+                        );
+                    }
+                }
+
+                bail!(
+                    "Order-matching fee for order {} was not paid within {FEE_PAYMENT_TIMEOUT:?}",
+                    trade_params.filled_with.order_id
+                )
+            }
+        }
+    }
+
+    /// Settles the order-matching fee of the trade executed over `channel_id` with `trader`, once
+    /// its DLC has been signed.
+    pub fn settle_fee_invoice(&self, trader: PublicKey, channel_id: ChannelId) -> Result<()> {
+        let fee_payment_hash = match self.pending_fee_payment_hash(trader, channel_id)? {
+            Some(fee_payment_hash) => fee_payment_hash,
+            None => {
+                tracing::debug!(
+                    trader_id = %trader,
+                    channel_id = %hex::encode(channel_id),
+                    "No order-matching fee to settle"
+                );
+                return Ok(());
+            }
+        };
+
+        let mut conn = self.pool.get()?;
+        let (_, fee_payment_info) = db::payments::get(fee_payment_hash, &mut conn)?
+            .context("Could not find order-matching fee payment")?;
+        let preimage = fee_payment_info
+            .preimage
+            .context("Missing preimage of order-matching fee invoice")?;
+
+        self.inner.settle_hold_invoice(preimage)
+    }
+
+    /// Cancels the order-matching fee of the trade being executed over `channel_id` with `trader`,
+    /// e.g. because its DLC protocol failed.
+    pub fn cancel_pending_fee_invoice(
+        &self,
+        trader: PublicKey,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        match self.pending_fee_payment_hash(trader, channel_id)? {
+            Some(fee_payment_hash) => self.cancel_fee_invoice(fee_payment_hash),
+            None => Ok(()),
+        }
+    }
+
+    /// Cancels the order-matching fee invoice for `fee_payment_hash`, refunding the trader if they
+    /// already paid it.
+    pub fn cancel_fee_invoice(&self, fee_payment_hash: PaymentHash) -> Result<()> {
+        self.inner.cancel_hold_invoice(fee_payment_hash)
+    }
+
+    /// The payment hash of the order-matching fee of the trade being executed over `channel_id`
+    /// with `trader`.
+    ///
+    /// Only one DLC protocol can run on a channel at a time, so this is the only fee of the trader
+    /// which has been neither settled nor cancelled yet.
+    fn pending_fee_payment_hash(
+        &self,
+        trader: PublicKey,
+        channel_id: ChannelId,
+    ) -> Result<Option<PaymentHash>> {
+        ensure!(
+            self.inner
+                .channel_manager
+                .list_channels()
+                .iter()
+                .any(|channel| channel.channel_id == channel_id
+                    && channel.counterparty.node_id == trader),
+            "Channel {} is not a channel with trader {trader}",
+            hex::encode(channel_id)
+        );
+
+        let mut conn = self.pool.get()?;
+        let fee_payment_hashes = db::trades::get_fee_payment_hashes_since(
+            &mut conn,
+            trader,
+            OffsetDateTime::now_utc() - INVOICE_EXPIRY,
+        )?;
+
+        Ok(fee_payment_hashes.into_iter().find(|fee_payment_hash| {
+            matches!(
+                self.inner.hold_invoice_state(fee_payment_hash),
+                Some(HoldInvoiceState::Open | HoldInvoiceState::Accepted { .. })
+            )
+        }))
+    }
+}
+
+fn fee_invoice_description(trade_params: &TradeParams) -> String {
+    let order_id = trade_params.filled_with.order_id;
+    format!("{FEE_INVOICE_DESCRIPTION_PREFIX_TAKER}{order_id}")
 }
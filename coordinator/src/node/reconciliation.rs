@@ -0,0 +1,1023 @@
+//! Reconciliation of the positions in the coordinator database with the DLC channels in the
+//! dlc-manager store.
+//!
+//! The positions are a shadow representation of the DLCs. Whenever a protocol fails half-way
+//! through, the two can diverge. This module detects these inconsistencies and offers repair
+//! actions for the cases where the DLC channel tells us unambiguously what the position should
+//! look like. Every applied repair action is recorded in the `reconciliation_actions` table.
+//!
+//! The positions are also compared with the recorded trades and the filled matches of the
+//! orderbook. Inconsistencies between those can only be reported, as they can't be resolved
+//! without knowing which side is wrong.
+
+use crate::db;
+use crate::node::rollover::is_channel_in_intermediate_state;
+use crate::node::Node;
+use crate::orderbook;
+use crate::position::models::Position;
+use crate::position::models::PositionState;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::secp256k1::PublicKey;
+use diesel::Connection;
+use diesel::PgConnection;
+use dlc_manager::channel::Channel;
+use dlc_manager::subchannel::SubChannel;
+use dlc_manager::subchannel::SubChannelState;
+use dlc_manager::ChannelId;
+use dlc_manager::ContractId;
+use orderbook_commons::OrderState;
+use orderbook_commons::OrderType;
+use serde::Serialize;
+use serde::Serializer;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// The time after which we consider a `Closing` position to be stuck, if the DLC channel does not
+/// reflect the closing and the trader has no pending match.
+pub const STUCK_CLOSING_TIMEOUT: Duration = Duration::hours(1);
+
+/// The time after which we consider a position in `Rollover` to be stuck, if the DLC channel is
+/// not being renewed anymore.
+pub const STUCK_ROLLOVER_TIMEOUT: Duration = Duration::hours(1);
+
+/// How far back the filled matches are compared with the recorded trades.
+pub const TRADE_HISTORY_LOOKBACK: Duration = Duration::days(7);
+
+/// The state of a DLC channel, reduced to what is relevant for reconciling it with a position.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "state")]
+pub enum DlcChannelState {
+    /// The DLC channel is being set up.
+    Opening,
+    /// The DLC channel is signed.
+    Open {
+        #[serde(serialize_with = "optional_contract_id_as_hex")]
+        temporary_contract_id: Option<ContractId>,
+        /// Whether the DLC channel is in the middle of being renewed, i.e. rolled over.
+        renewing: bool,
+    },
+    /// The DLC channel is being closed.
+    Closing,
+    /// The DLC channel has been closed.
+    Closed,
+    /// The DLC channel could not be loaded from the dlc-manager store.
+    Unreadable,
+}
+
+impl DlcChannelState {
+    /// The priority of a DLC channel state when a trader has more than one sub-channel, e.g.
+    /// because an old LN channel has been closed. The higher the priority, the more relevant the
+    /// DLC channel is for the trader's current position.
+    fn priority(&self) -> u8 {
+        match self {
+            // We don't know what an unreadable DLC channel looks like, so we must not act on any
+            // other DLC channel of the trader either.
+            DlcChannelState::Unreadable => 4,
+            DlcChannelState::Open { .. } => 3,
+            DlcChannelState::Closing => 2,
+            DlcChannelState::Opening => 1,
+            DlcChannelState::Closed => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DlcChannelSnapshot {
+    pub trader: PublicKey,
+    pub sub_channel_id: ChannelId,
+    pub state: DlcChannelState,
+}
+
+/// A market order which has been filled by at least one match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilledOrder {
+    pub trader: PublicKey,
+    pub order_id: Uuid,
+    pub matched_at: OffsetDateTime,
+}
+
+/// The trades and filled orders the positions are compared with.
+#[derive(Debug, Clone, Default)]
+pub struct TradeHistory {
+    /// The ids of the active positions for which at least one trade has been recorded.
+    pub positions_with_trade: HashSet<i32>,
+    /// The market orders filled within the last [`TRADE_HISTORY_LOOKBACK`].
+    pub filled_orders: Vec<FilledOrder>,
+    /// The timestamps of the trades within the last [`TRADE_HISTORY_LOOKBACK`], by trader.
+    pub trades: HashMap<PublicKey, Vec<OffsetDateTime>>,
+}
+
+/// An inconsistency between a position in the database and the DLC channel of the trader.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum Inconsistency {
+    /// The position is `Open` or in `Rollover`, but the DLC channel backing it is closed or
+    /// missing.
+    OrphanPosition {
+        position_id: i32,
+        trader: PublicKey,
+        #[serde(serialize_with = "optional_contract_id_as_hex")]
+        position_contract_id: Option<ContractId>,
+        dlc_channel: Option<DlcChannelState>,
+    },
+    /// The DLC channel is open, but there is no position for the trader.
+    MissingPosition {
+        trader: PublicKey,
+        #[serde(serialize_with = "contract_id_as_hex")]
+        sub_channel_id: ChannelId,
+        dlc_channel: DlcChannelState,
+    },
+    /// The position has been `Closing` for longer than [`STUCK_CLOSING_TIMEOUT`], but the DLC
+    /// channel has either not been settled or it has been closed without the position being
+    /// updated.
+    StuckClosing {
+        position_id: i32,
+        trader: PublicKey,
+        #[serde(serialize_with = "optional_contract_id_as_hex")]
+        position_contract_id: Option<ContractId>,
+        #[serde(with = "time::serde::rfc3339")]
+        since: OffsetDateTime,
+        dlc_channel: Option<DlcChannelState>,
+    },
+    /// The position has been in `Rollover` for longer than [`STUCK_ROLLOVER_TIMEOUT`], but the
+    /// DLC channel is not being renewed anymore.
+    StuckRollover {
+        position_id: i32,
+        trader: PublicKey,
+        #[serde(with = "time::serde::rfc3339")]
+        since: OffsetDateTime,
+        dlc_channel: DlcChannelState,
+    },
+    /// The position references a different contract than the one of the open DLC channel.
+    ContractIdMismatch {
+        position_id: i32,
+        trader: PublicKey,
+        #[serde(serialize_with = "optional_contract_id_as_hex")]
+        position_contract_id: Option<ContractId>,
+        #[serde(serialize_with = "contract_id_as_hex")]
+        dlc_contract_id: ContractId,
+    },
+    /// The DLC channel could not be loaded, hence the position of the trader could not be
+    /// reconciled.
+    UnreadableDlcChannel {
+        trader: PublicKey,
+        #[serde(serialize_with = "contract_id_as_hex")]
+        sub_channel_id: ChannelId,
+        error: String,
+    },
+    /// The position is active, but no trade has been recorded for it.
+    PositionWithoutTrade { position_id: i32, trader: PublicKey },
+    /// The market order has been filled, but no trade has been recorded for it.
+    FilledOrderWithoutTrade {
+        trader: PublicKey,
+        order_id: Uuid,
+        #[serde(with = "time::serde::rfc3339")]
+        matched_at: OffsetDateTime,
+    },
+}
+
+/// A repair action that brings a position in line with its DLC channel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "action")]
+pub enum RepairAction {
+    /// Set the position to `Closed`, using the PnL of the closed contract if we can find it.
+    ClosePosition {
+        position_id: i32,
+        #[serde(serialize_with = "optional_contract_id_as_hex")]
+        temporary_contract_id: Option<ContractId>,
+    },
+    /// Set a `Closing` position back to `Open`, as the DLC channel has not been settled.
+    ReopenPosition { position_id: i32 },
+    /// Set a position in `Rollover` back to `Open`, using the contract of the DLC channel.
+    FinishRollover {
+        #[serde(serialize_with = "contract_id_as_hex")]
+        temporary_contract_id: ContractId,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairOutcome {
+    pub inconsistency: Inconsistency,
+    pub action: RepairAction,
+    pub error: Option<String>,
+}
+
+impl Inconsistency {
+    pub fn trader(&self) -> PublicKey {
+        match self {
+            Inconsistency::OrphanPosition { trader, .. }
+            | Inconsistency::MissingPosition { trader, .. }
+            | Inconsistency::StuckClosing { trader, .. }
+            | Inconsistency::StuckRollover { trader, .. }
+            | Inconsistency::ContractIdMismatch { trader, .. }
+            | Inconsistency::UnreadableDlcChannel { trader, .. }
+            | Inconsistency::PositionWithoutTrade { trader, .. }
+            | Inconsistency::FilledOrderWithoutTrade { trader, .. } => *trader,
+        }
+    }
+
+    pub fn position_id(&self) -> Option<i32> {
+        match self {
+            Inconsistency::OrphanPosition { position_id, .. }
+            | Inconsistency::StuckClosing { position_id, .. }
+            | Inconsistency::StuckRollover { position_id, .. }
+            | Inconsistency::ContractIdMismatch { position_id, .. }
+            | Inconsistency::PositionWithoutTrade { position_id, .. } => Some(*position_id),
+            Inconsistency::MissingPosition { .. }
+            | Inconsistency::UnreadableDlcChannel { .. }
+            | Inconsistency::FilledOrderWithoutTrade { .. } => None,
+        }
+    }
+
+    /// The repair action which is safe to apply for this inconsistency, if any.
+    ///
+    /// We only repair the database, never the DLC channel. Inconsistencies which would require
+    /// changes to the DLC channel (e.g. a missing position) have to be resolved manually. The
+    /// same applies to a contract id mismatch, as we can't tell whether the position belongs to
+    /// the contract of the DLC channel at all.
+    pub fn repair_action(&self) -> Option<RepairAction> {
+        match self {
+            Inconsistency::OrphanPosition {
+                position_id,
+                position_contract_id,
+                ..
+            } => Some(RepairAction::ClosePosition {
+                position_id: *position_id,
+                temporary_contract_id: *position_contract_id,
+            }),
+            Inconsistency::StuckClosing {
+                position_id,
+                position_contract_id,
+                dlc_channel,
+                ..
+            } => match dlc_channel {
+                Some(DlcChannelState::Open {
+                    renewing: false, ..
+                }) => Some(RepairAction::ReopenPosition {
+                    position_id: *position_id,
+                }),
+                None | Some(DlcChannelState::Closed) => Some(RepairAction::ClosePosition {
+                    position_id: *position_id,
+                    temporary_contract_id: *position_contract_id,
+                }),
+                _ => None,
+            },
+            Inconsistency::StuckRollover {
+                dlc_channel:
+                    DlcChannelState::Open {
+                        temporary_contract_id: Some(temporary_contract_id),
+                        renewing: false,
+                    },
+                ..
+            } => Some(RepairAction::FinishRollover {
+                temporary_contract_id: *temporary_contract_id,
+            }),
+            Inconsistency::StuckRollover { .. }
+            | Inconsistency::MissingPosition { .. }
+            | Inconsistency::ContractIdMismatch { .. }
+            | Inconsistency::UnreadableDlcChannel { .. }
+            | Inconsistency::PositionWithoutTrade { .. }
+            | Inconsistency::FilledOrderWithoutTrade { .. } => None,
+        }
+    }
+}
+
+impl Node {
+    /// Compares all active positions with the DLC channels, trades and filled orders and returns
+    /// every inconsistency found.
+    pub fn find_inconsistencies(&self) -> Result<Vec<Inconsistency>> {
+        let mut conn = self.pool.get()?;
+
+        let positions = db::positions::Position::get_all_active_positions(&mut conn)
+            .context("Failed to load active positions")?;
+
+        self.find_inconsistencies_for_positions(&mut conn, &positions)
+    }
+
+    /// Applies the repair actions for all repairable inconsistencies, optionally restricted to
+    /// a single trader.
+    ///
+    /// The active positions are locked while the inconsistencies are detected and repaired, so
+    /// that a concurrent trade or rollover can't change a position after we have decided how to
+    /// repair it. Every applied action is recorded, regardless of whether it succeeded or not.
+    pub fn repair_inconsistencies(&self, trader: Option<PublicKey>) -> Result<Vec<RepairOutcome>> {
+        let mut conn = self.pool.get()?;
+        conn.transaction(|conn| {
+            let positions = db::positions::Position::lock_all_active_positions(conn)
+                .context("Failed to lock active positions")?;
+
+            let inconsistencies = self.find_inconsistencies_for_positions(conn, &positions)?;
+
+            let mut outcomes = vec![];
+            for inconsistency in inconsistencies {
+                let trader_id = inconsistency.trader();
+                if trader.is_some() && trader != Some(trader_id) {
+                    continue;
+                }
+
+                let action = match inconsistency.repair_action() {
+                    Some(action) => action,
+                    None => {
+                        tracing::debug!(%trader_id, ?inconsistency, "No repair action available");
+                        continue;
+                    }
+                };
+
+                tracing::info!(%trader_id, ?inconsistency, ?action, "Repairing inconsistency");
+
+                // A failed repair action must not abort the whole transaction, hence we apply
+                // every action within its own savepoint.
+                let error = conn
+                    .transaction(|conn| self.apply_repair_action(conn, trader_id, action))
+                    .err()
+                    .map(|e| format!("{e:#}"));
+                if let Some(error) = &error {
+                    tracing::error!(%trader_id, ?inconsistency, ?action, "Failed to repair inconsistency: {error}");
+                }
+
+                db::reconciliation_actions::insert(
+                    conn,
+                    trader_id,
+                    inconsistency.position_id(),
+                    serde_json::to_string(&inconsistency)?,
+                    serde_json::to_string(&action)?,
+                    error.clone(),
+                )?;
+
+                outcomes.push(RepairOutcome {
+                    inconsistency,
+                    action,
+                    error,
+                });
+            }
+
+            Ok(outcomes)
+        })
+    }
+
+    fn find_inconsistencies_for_positions(
+        &self,
+        conn: &mut PgConnection,
+        positions: &[Position],
+    ) -> Result<Vec<Inconsistency>> {
+        let traders_with_pending_match = orderbook::db::orders::get_all_orders(
+            conn,
+            OrderType::Market,
+            OrderState::Matched,
+            false,
+        )
+        .context("Failed to load matched orders")?
+        .into_iter()
+        .map(|order| order.trader_id)
+        .collect::<HashSet<_>>();
+
+        let trade_history = self.trade_history(conn, positions)?;
+
+        // A DLC channel which can't be loaded must not prevent us from reconciling the others.
+        let mut unreadable_dlc_channels = vec![];
+        let dlc_channels = self
+            .inner
+            .list_dlc_channels()?
+            .into_iter()
+            .map(|sub_channel| {
+                let trader = sub_channel.counter_party;
+                let sub_channel_id = sub_channel.channel_id;
+                self.dlc_channel_snapshot(sub_channel)
+                    .unwrap_or_else(|e| {
+                        tracing::error!(%trader, sub_channel_id = %sub_channel_id.to_hex(), "Failed to load DLC channel: {e:#}");
+                        unreadable_dlc_channels.push(Inconsistency::UnreadableDlcChannel {
+                            trader,
+                            sub_channel_id,
+                            error: format!("{e:#}"),
+                        });
+
+                        DlcChannelSnapshot {
+                            trader,
+                            sub_channel_id,
+                            state: DlcChannelState::Unreadable,
+                        }
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut inconsistencies = find_inconsistencies(
+            positions,
+            &dlc_channels,
+            &trade_history,
+            &traders_with_pending_match,
+            OffsetDateTime::now_utc(),
+        );
+        inconsistencies.extend(unreadable_dlc_channels);
+
+        Ok(inconsistencies)
+    }
+
+    fn trade_history(
+        &self,
+        conn: &mut PgConnection,
+        positions: &[Position],
+    ) -> Result<TradeHistory> {
+        let since = OffsetDateTime::now_utc() - TRADE_HISTORY_LOOKBACK;
+
+        let positions_with_trade = db::trades::get_position_ids_with_trades(
+            conn,
+            positions.iter().map(|position| position.id).collect(),
+        )
+        .context("Failed to load trades of active positions")?;
+
+        // An order can be filled by more than one match.
+        let mut filled_orders: HashMap<Uuid, FilledOrder> = HashMap::new();
+        for m in orderbook::db::matches::get_filled_market_order_matches_since(conn, since)
+            .context("Failed to load filled matches")?
+        {
+            let filled_order = filled_orders.entry(m.order_id).or_insert(FilledOrder {
+                trader: m.trader_id,
+                order_id: m.order_id,
+                matched_at: m.created_at,
+            });
+            filled_order.matched_at = filled_order.matched_at.min(m.created_at);
+        }
+
+        let mut trades: HashMap<PublicKey, Vec<OffsetDateTime>> = HashMap::new();
+        for (trader, timestamp) in
+            db::trades::get_timestamps_since(conn, since).context("Failed to load trades")?
+        {
+            trades.entry(trader).or_default().push(timestamp);
+        }
+
+        Ok(TradeHistory {
+            positions_with_trade,
+            filled_orders: filled_orders.into_values().collect(),
+            trades,
+        })
+    }
+
+    fn apply_repair_action(
+        &self,
+        conn: &mut PgConnection,
+        trader: PublicKey,
+        action: RepairAction,
+    ) -> Result<()> {
+        match action {
+            RepairAction::ClosePosition {
+                position_id,
+                temporary_contract_id,
+            } => {
+                let closed_contract = match temporary_contract_id {
+                    Some(temporary_contract_id) => {
+                        self.inner.get_closed_contract(temporary_contract_id)?
+                    }
+                    None => None,
+                };
+
+                match closed_contract {
+                    Some(contract) => db::positions::Position::set_position_to_closed_with_pnl(
+                        conn,
+                        position_id,
+                        contract.pnl,
+                    ),
+                    None => db::positions::Position::set_position_to_closed(conn, position_id),
+                }
+            }
+            RepairAction::ReopenPosition { position_id } => {
+                db::positions::Position::set_closing_position_to_open(conn, position_id)
+            }
+            RepairAction::FinishRollover {
+                temporary_contract_id,
+            } => db::positions::Position::set_position_to_open(
+                conn,
+                trader.to_string(),
+                temporary_contract_id,
+            ),
+        }
+    }
+
+    fn dlc_channel_snapshot(&self, sub_channel: SubChannel) -> Result<DlcChannelSnapshot> {
+        let state = match sub_channel.state {
+            SubChannelState::Offered(_)
+            | SubChannelState::Accepted(_)
+            | SubChannelState::Confirmed(_)
+            | SubChannelState::Finalized(_) => DlcChannelState::Opening,
+            SubChannelState::Signed(_) => {
+                let dlc_channel_id = sub_channel
+                    .get_dlc_channel_id(0)
+                    .context("Signed sub-channel without DLC channel")?;
+
+                match self.inner.get_dlc_channel_by_id(&dlc_channel_id)? {
+                    Channel::Signed(signed_channel) => DlcChannelState::Open {
+                        temporary_contract_id: self
+                            .inner
+                            .get_contract_by_dlc_channel_id(&dlc_channel_id)
+                            .ok()
+                            .map(|contract| contract.get_temporary_id()),
+                        renewing: is_channel_in_intermediate_state(&signed_channel),
+                    },
+                    _ => DlcChannelState::Open {
+                        temporary_contract_id: None,
+                        renewing: false,
+                    },
+                }
+            }
+            SubChannelState::Closing(_)
+            | SubChannelState::CloseOffered(_)
+            | SubChannelState::CloseAccepted(_)
+            | SubChannelState::CloseConfirmed(_) => DlcChannelState::Closing,
+            SubChannelState::OffChainClosed
+            | SubChannelState::OnChainClosed
+            | SubChannelState::CounterOnChainClosed
+            | SubChannelState::ClosedPunished(_)
+            | SubChannelState::Rejected => DlcChannelState::Closed,
+        };
+
+        Ok(DlcChannelSnapshot {
+            trader: sub_channel.counter_party,
+            sub_channel_id: sub_channel.channel_id,
+            state,
+        })
+    }
+}
+
+/// Compares the active positions with the DLC channels and the trades of the traders.
+///
+/// Traders with a pending match are expected to execute a trade as soon as they come online,
+/// hence a `Closing` position of theirs is not considered stuck.
+pub fn find_inconsistencies(
+    positions: &[Position],
+    dlc_channels: &[DlcChannelSnapshot],
+    trade_history: &TradeHistory,
+    traders_with_pending_match: &HashSet<PublicKey>,
+    now: OffsetDateTime,
+) -> Vec<Inconsistency> {
+    let mut dlc_channel_by_trader: HashMap<PublicKey, &DlcChannelSnapshot> = HashMap::new();
+    for dlc_channel in dlc_channels {
+        let entry = dlc_channel_by_trader
+            .entry(dlc_channel.trader)
+            .or_insert(dlc_channel);
+        if dlc_channel.state.priority() > entry.state.priority() {
+            *entry = dlc_channel;
+        }
+    }
+
+    let mut inconsistencies = vec![];
+    for position in positions {
+        let trader = position.trader;
+        let dlc_channel = dlc_channel_by_trader
+            .get(&trader)
+            .map(|dlc_channel| dlc_channel.state);
+
+        match (&position.position_state, dlc_channel) {
+            (
+                PositionState::Open | PositionState::Rollover,
+                None | Some(DlcChannelState::Closed),
+            ) => inconsistencies.push(Inconsistency::OrphanPosition {
+                position_id: position.id,
+                trader,
+                position_contract_id: position.temporary_contract_id,
+                dlc_channel,
+            }),
+            (
+                PositionState::Open,
+                Some(DlcChannelState::Open {
+                    temporary_contract_id: Some(dlc_contract_id),
+                    renewing: false,
+                }),
+            ) if position.temporary_contract_id != Some(dlc_contract_id) => {
+                inconsistencies.push(Inconsistency::ContractIdMismatch {
+                    position_id: position.id,
+                    trader,
+                    position_contract_id: position.temporary_contract_id,
+                    dlc_contract_id,
+                })
+            }
+            (
+                PositionState::Closing { .. },
+                None | Some(DlcChannelState::Closed) | Some(DlcChannelState::Open { .. }),
+            ) if position.update_timestamp + STUCK_CLOSING_TIMEOUT < now
+                && !traders_with_pending_match.contains(&trader) =>
+            {
+                inconsistencies.push(Inconsistency::StuckClosing {
+                    position_id: position.id,
+                    trader,
+                    position_contract_id: position.temporary_contract_id,
+                    since: position.update_timestamp,
+                    dlc_channel,
+                })
+            }
+            (
+                PositionState::Rollover,
+                Some(
+                    dlc_channel @ DlcChannelState::Open {
+                        renewing: false, ..
+                    },
+                ),
+            ) if position.update_timestamp + STUCK_ROLLOVER_TIMEOUT < now => {
+                inconsistencies.push(Inconsistency::StuckRollover {
+                    position_id: position.id,
+                    trader,
+                    since: position.update_timestamp,
+                    dlc_channel,
+                })
+            }
+            _ => {}
+        }
+
+        if !trade_history.positions_with_trade.contains(&position.id) {
+            inconsistencies.push(Inconsistency::PositionWithoutTrade {
+                position_id: position.id,
+                trader,
+            });
+        }
+    }
+
+    let traders_with_position = positions
+        .iter()
+        .map(|position| position.trader)
+        .collect::<HashSet<_>>();
+
+    for dlc_channel in dlc_channel_by_trader.values() {
+        if matches!(dlc_channel.state, DlcChannelState::Open { .. })
+            && !traders_with_position.contains(&dlc_channel.trader)
+        {
+            inconsistencies.push(Inconsistency::MissingPosition {
+                trader: dlc_channel.trader,
+                sub_channel_id: dlc_channel.sub_channel_id,
+                dlc_channel: dlc_channel.state,
+            });
+        }
+    }
+
+    inconsistencies.extend(find_filled_orders_without_trade(trade_history));
+
+    inconsistencies
+}
+
+/// Pairs every filled order with the first unpaired trade of the trader recorded after the order
+/// has been matched, and returns an inconsistency for every filled order left without a trade.
+fn find_filled_orders_without_trade(trade_history: &TradeHistory) -> Vec<Inconsistency> {
+    let mut filled_orders = trade_history.filled_orders.clone();
+    filled_orders.sort_by_key(|order| order.matched_at);
+
+    let mut trades = trade_history.trades.clone();
+    for timestamps in trades.values_mut() {
+        timestamps.sort();
+    }
+
+    let mut inconsistencies = vec![];
+    for filled_order in filled_orders {
+        let timestamps = trades.entry(filled_order.trader).or_default();
+        match timestamps
+            .iter()
+            .position(|timestamp| *timestamp >= filled_order.matched_at)
+        {
+            Some(i) => {
+                timestamps.remove(i);
+            }
+            None => inconsistencies.push(Inconsistency::FilledOrderWithoutTrade {
+                trader: filled_order.trader,
+                order_id: filled_order.order_id,
+                matched_at: filled_order.matched_at,
+            }),
+        }
+    }
+
+    inconsistencies
+}
+
+fn contract_id_as_hex<S>(contract_id: &ContractId, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.serialize_str(&contract_id.to_hex())
+}
+
+fn optional_contract_id_as_hex<S>(contract_id: &Option<ContractId>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match contract_id {
+        Some(contract_id) => s.serialize_str(&contract_id.to_hex()),
+        None => s.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn open_position_without_dlc_channel_is_orphan() {
+        let position = Position::dummy();
+
+        let inconsistencies =
+            find_inconsistencies(&[position.clone()], &[], &traded(), &HashSet::new(), now());
+
+        assert_eq!(
+            inconsistencies,
+            vec![Inconsistency::OrphanPosition {
+                position_id: position.id,
+                trader: position.trader,
+                position_contract_id: None,
+                dlc_channel: None,
+            }]
+        );
+        assert_eq!(
+            inconsistencies[0].repair_action(),
+            Some(RepairAction::ClosePosition {
+                position_id: position.id,
+                temporary_contract_id: None
+            })
+        );
+    }
+
+    #[test]
+    fn open_dlc_channel_without_position_is_missing_position() {
+        let dlc_channel = dlc_channel(trader(), open([1; 32]));
+
+        let inconsistencies = find_inconsistencies(
+            &[],
+            &[dlc_channel.clone()],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert_eq!(
+            inconsistencies,
+            vec![Inconsistency::MissingPosition {
+                trader: trader(),
+                sub_channel_id: dlc_channel.sub_channel_id,
+                dlc_channel: dlc_channel.state,
+            }]
+        );
+        assert_eq!(inconsistencies[0].repair_action(), None);
+    }
+
+    #[test]
+    fn matching_position_and_dlc_channel_is_consistent() {
+        let position = Position {
+            temporary_contract_id: Some([1; 32]),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position],
+            &[dlc_channel(trader(), open([1; 32]))],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert!(inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn open_dlc_channel_takes_precedence_over_closed_one() {
+        let position = Position {
+            temporary_contract_id: Some([1; 32]),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position],
+            &[
+                dlc_channel(trader(), DlcChannelState::Closed),
+                dlc_channel(trader(), open([1; 32])),
+            ],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert!(inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn different_contract_id_is_mismatch() {
+        let position = Position {
+            temporary_contract_id: Some([1; 32]),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position.clone()],
+            &[dlc_channel(trader(), open([2; 32]))],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert_eq!(
+            inconsistencies,
+            vec![Inconsistency::ContractIdMismatch {
+                position_id: position.id,
+                trader: trader(),
+                position_contract_id: Some([1; 32]),
+                dlc_contract_id: [2; 32],
+            }]
+        );
+        assert_eq!(inconsistencies[0].repair_action(), None);
+    }
+
+    #[test]
+    fn unreadable_dlc_channel_does_not_orphan_position() {
+        let position = Position {
+            temporary_contract_id: Some([1; 32]),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position],
+            &[
+                dlc_channel(trader(), DlcChannelState::Closed),
+                dlc_channel(trader(), DlcChannelState::Unreadable),
+            ],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert!(inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn position_without_trade_is_reported() {
+        let position = Position {
+            temporary_contract_id: Some([1; 32]),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position.clone()],
+            &[dlc_channel(trader(), open([1; 32]))],
+            &TradeHistory::default(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert_eq!(
+            inconsistencies,
+            vec![Inconsistency::PositionWithoutTrade {
+                position_id: position.id,
+                trader: trader(),
+            }]
+        );
+        assert_eq!(inconsistencies[0].repair_action(), None);
+    }
+
+    #[test]
+    fn filled_order_without_later_trade_is_reported() {
+        let first_order = FilledOrder {
+            trader: trader(),
+            order_id: Uuid::from_u128(1),
+            matched_at: now() - Duration::hours(2),
+        };
+        let second_order = FilledOrder {
+            trader: trader(),
+            order_id: Uuid::from_u128(2),
+            matched_at: now() - Duration::hours(1),
+        };
+
+        // Only a single trade has been recorded, after both orders have been matched.
+        let trade_history = TradeHistory {
+            filled_orders: vec![second_order, first_order],
+            trades: HashMap::from([(trader(), vec![now() - Duration::minutes(30)])]),
+            ..TradeHistory::default()
+        };
+
+        let inconsistencies =
+            find_inconsistencies(&[], &[], &trade_history, &HashSet::new(), now());
+
+        assert_eq!(
+            inconsistencies,
+            vec![Inconsistency::FilledOrderWithoutTrade {
+                trader: trader(),
+                order_id: second_order.order_id,
+                matched_at: second_order.matched_at,
+            }]
+        );
+    }
+
+    #[test]
+    fn closing_position_with_open_dlc_channel_is_stuck_after_timeout() {
+        let position = Position {
+            position_state: PositionState::Closing {
+                closing_price: 30_000.0,
+            },
+            temporary_contract_id: Some([1; 32]),
+            update_timestamp: now() - STUCK_CLOSING_TIMEOUT - Duration::seconds(1),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position.clone()],
+            &[dlc_channel(trader(), open([1; 32]))],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+
+        assert_eq!(
+            inconsistencies[0].repair_action(),
+            Some(RepairAction::ReopenPosition {
+                position_id: position.id
+            })
+        );
+    }
+
+    #[test]
+    fn closing_position_with_pending_match_is_not_stuck() {
+        let position = Position {
+            position_state: PositionState::Closing {
+                closing_price: 30_000.0,
+            },
+            temporary_contract_id: Some([1; 32]),
+            update_timestamp: now() - STUCK_CLOSING_TIMEOUT - Duration::seconds(1),
+            ..Position::dummy()
+        };
+
+        let inconsistencies = find_inconsistencies(
+            &[position],
+            &[dlc_channel(trader(), open([1; 32]))],
+            &traded(),
+            &HashSet::from([trader()]),
+            now(),
+        );
+
+        assert!(inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn rollover_position_is_not_stuck_while_renewing() {
+        let position = Position {
+            position_state: PositionState::Rollover,
+            temporary_contract_id: Some([1; 32]),
+            update_timestamp: now() - STUCK_ROLLOVER_TIMEOUT - Duration::seconds(1),
+            ..Position::dummy()
+        };
+
+        let renewing = DlcChannelState::Open {
+            temporary_contract_id: Some([2; 32]),
+            renewing: true,
+        };
+        let inconsistencies = find_inconsistencies(
+            &[position.clone()],
+            &[dlc_channel(trader(), renewing)],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+        assert!(inconsistencies.is_empty());
+
+        let inconsistencies = find_inconsistencies(
+            &[position],
+            &[dlc_channel(trader(), open([2; 32]))],
+            &traded(),
+            &HashSet::new(),
+            now(),
+        );
+        assert_eq!(
+            inconsistencies[0].repair_action(),
+            Some(RepairAction::FinishRollover {
+                temporary_contract_id: [2; 32],
+            })
+        );
+    }
+
+    fn now() -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+
+    fn trader() -> PublicKey {
+        PublicKey::from_str("02bd998ebd176715fe92b7467cf6b1df8023950a4dd911db4c94dfc89cc9f5a655")
+            .unwrap()
+    }
+
+    /// A trade history in which the dummy position has been traded.
+    fn traded() -> TradeHistory {
+        TradeHistory {
+            positions_with_trade: HashSet::from([Position::dummy().id]),
+            ..TradeHistory::default()
+        }
+    }
+
+    fn open(temporary_contract_id: ContractId) -> DlcChannelState {
+        DlcChannelState::Open {
+            temporary_contract_id: Some(temporary_contract_id),
+            renewing: false,
+        }
+    }
+
+    fn dlc_channel(trader: PublicKey, state: DlcChannelState) -> DlcChannelSnapshot {
+        DlcChannelSnapshot {
+            trader,
+            sub_channel_id: [0; 32],
+            state,
+        }
+    }
+}
@@ -267,7 +267,7 @@ impl Node {
     }
 }
 
-fn is_channel_in_intermediate_state(signed_channel: &SignedChannel) -> bool {
+pub(crate) fn is_channel_in_intermediate_state(signed_channel: &SignedChannel) -> bool {
     use dlc_manager::channel::signed_channel::SignedChannelState;
     matches!(
         signed_channel.state,
@@ -3,6 +3,7 @@ use crate::db::payments;
 use anyhow::anyhow;
 use anyhow::Result;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::Txid;
 use diesel::r2d2::ConnectionManager;
 use diesel::r2d2::Pool;
 use diesel::PgConnection;
@@ -16,6 +17,7 @@ use ln_dlc_node::node;
 use ln_dlc_node::transaction::Transaction;
 use ln_dlc_node::HTLCStatus;
 use ln_dlc_node::MillisatAmount;
+use ln_dlc_node::PaymentFailureReason;
 use ln_dlc_node::PaymentFlow;
 use ln_dlc_node::PaymentInfo;
 use time::OffsetDateTime;
@@ -77,6 +79,7 @@ impl node::Storage for NodeStorage {
                             timestamp: OffsetDateTime::now_utc(),
                             description: "".to_string(),
                             invoice: None,
+                            failure_reason: None,
                         },
                     ),
                     &mut conn,
@@ -87,6 +90,15 @@ impl node::Storage for NodeStorage {
         Ok(())
     }
 
+    fn set_payment_failure_reason(
+        &self,
+        payment_hash: &PaymentHash,
+        failure_reason: PaymentFailureReason,
+    ) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        payments::set_failure_reason(*payment_hash, failure_reason, &mut conn)
+    }
+
     fn get_payment(
         &self,
         payment_hash: &PaymentHash,
@@ -184,4 +196,40 @@ impl node::Storage for NodeStorage {
             .collect::<Vec<_>>();
         Ok(transactions)
     }
+
+    // Counterparty commitments
+
+    fn insert_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+        transaction: bitcoin::Transaction,
+    ) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        db::counterparty_commitments::insert(
+            &mut conn,
+            channel_keys_id,
+            commitment_number,
+            &transaction,
+        )
+    }
+
+    fn revoke_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+    ) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        db::counterparty_commitments::revoke(&mut conn, channel_keys_id, commitment_number)
+    }
+
+    fn revoked_counterparty_commitments(&self) -> Result<Vec<bitcoin::Transaction>> {
+        let mut conn = self.pool.get()?;
+        db::counterparty_commitments::get_all_revoked(&mut conn)
+    }
+
+    fn delete_counterparty_commitment(&self, txid: &Txid) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        db::counterparty_commitments::delete(&mut conn, txid)
+    }
 }
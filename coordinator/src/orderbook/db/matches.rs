@@ -1,17 +1,25 @@
+use crate::db::positions::ContractSymbol;
+use crate::orderbook::db::custom_types::Direction;
 use crate::orderbook::db::custom_types::MatchState;
+use crate::orderbook::db::custom_types::OrderType;
+use crate::orderbook::state_machine;
 use crate::orderbook::trading::TraderMatchParams;
 use crate::schema::matches;
+use crate::schema::orders;
 use anyhow::ensure;
 use anyhow::Result;
 use bitcoin::secp256k1::PublicKey;
+use diesel::BoolExpressionMethods;
 use diesel::ExpressionMethods;
 use diesel::Insertable;
+use diesel::JoinOnDsl;
 use diesel::PgConnection;
 use diesel::QueryDsl;
 use diesel::QueryResult;
 use diesel::Queryable;
 use diesel::QueryableByName;
 use diesel::RunQueryDsl;
+use orderbook_commons::Trade;
 use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
@@ -46,6 +54,10 @@ pub fn insert(conn: &mut PgConnection, match_params: &TraderMatchParams) -> Resu
     Ok(())
 }
 
+/// Moves all matches of the given order to `match_state`.
+///
+/// Only matches which are allowed to transition to `match_state` are updated, i.e. matches which
+/// are already in a final state are left untouched.
 pub fn set_match_state(
     conn: &mut PgConnection,
     order_id: Uuid,
@@ -53,6 +65,7 @@ pub fn set_match_state(
 ) -> QueryResult<()> {
     diesel::update(matches::table)
         .filter(matches::order_id.eq(order_id))
+        .filter(matches::match_state.eq_any(predecessors(match_state)))
         .set(matches::match_state.eq(MatchState::from(match_state)))
         .execute(conn)?;
 
@@ -82,6 +95,7 @@ pub fn set_match_state_by_order_id(
 ) -> Result<()> {
     let affected_rows = diesel::update(matches::table)
         .filter(matches::order_id.eq(order_id))
+        .filter(matches::match_state.eq_any(predecessors(match_state)))
         .set(matches::match_state.eq(MatchState::from(match_state)))
         .execute(conn)?;
 
@@ -89,6 +103,131 @@ pub fn set_match_state_by_order_id(
     Ok(())
 }
 
+/// Fails all pending matches involving the given order, i.e. the matches of the order itself and
+/// the matches of the orders it has been matched with.
+///
+/// Returns the failed matches.
+pub fn fail_pending_matches(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+) -> QueryResult<Vec<orderbook_commons::Matches>> {
+    let matches: Vec<Matches> = diesel::update(matches::table)
+        .filter(
+            matches::order_id
+                .eq(order_id)
+                .or(matches::match_order_id.eq(order_id)),
+        )
+        .filter(matches::match_state.eq(MatchState::Pending))
+        .set(matches::match_state.eq(MatchState::Failed))
+        .get_results(conn)?;
+
+    Ok(matches
+        .into_iter()
+        .map(orderbook_commons::Matches::from)
+        .collect())
+}
+
+/// Returns at most `limit` filled matches of market orders created after `from` and until `to` as
+/// trades, latest first.
+///
+/// Only the matches of the market orders are considered, as the maker side of a match is not
+/// updated once the trade has been executed.
+pub fn get_trades(
+    conn: &mut PgConnection,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+    limit: i64,
+) -> QueryResult<Vec<Trade>> {
+    let trades = matches::table
+        .inner_join(orders::table.on(orders::trader_order_id.eq(matches::order_id)))
+        .filter(matches::match_state.eq(MatchState::Filled))
+        .filter(orders::order_type.eq(OrderType::Market))
+        .filter(matches::created_at.gt(from))
+        .filter(matches::created_at.le(to))
+        .order_by(matches::created_at.desc())
+        .limit(limit)
+        .select((
+            matches::id,
+            matches::execution_price,
+            matches::quantity,
+            matches::created_at,
+            orders::direction,
+            orders::contract_symbol,
+        ))
+        .load::<TradeRow>(conn)?;
+
+    Ok(trades.into_iter().map(Trade::from).collect())
+}
+
+/// Returns the filled matches of the given market order as trades.
+pub fn get_trades_by_order_id(conn: &mut PgConnection, order_id: Uuid) -> QueryResult<Vec<Trade>> {
+    let trades = matches::table
+        .inner_join(orders::table.on(orders::trader_order_id.eq(matches::order_id)))
+        .filter(matches::match_state.eq(MatchState::Filled))
+        .filter(orders::order_type.eq(OrderType::Market))
+        .filter(matches::order_id.eq(order_id))
+        .select((
+            matches::id,
+            matches::execution_price,
+            matches::quantity,
+            matches::created_at,
+            orders::direction,
+            orders::contract_symbol,
+        ))
+        .load::<TradeRow>(conn)?;
+
+    Ok(trades.into_iter().map(Trade::from).collect())
+}
+
+/// Returns the filled matches of all market orders created after `since`.
+pub fn get_filled_market_order_matches_since(
+    conn: &mut PgConnection,
+    since: OffsetDateTime,
+) -> QueryResult<Vec<orderbook_commons::Matches>> {
+    let matches = matches::table
+        .inner_join(orders::table.on(orders::trader_order_id.eq(matches::order_id)))
+        .filter(matches::match_state.eq(MatchState::Filled))
+        .filter(orders::order_type.eq(OrderType::Market))
+        .filter(matches::created_at.gt(since))
+        .select(matches::all_columns)
+        .load::<Matches>(conn)?;
+
+    Ok(matches
+        .into_iter()
+        .map(orderbook_commons::Matches::from)
+        .collect())
+}
+
+#[derive(Queryable)]
+struct TradeRow {
+    id: Uuid,
+    execution_price: f32,
+    quantity: f32,
+    created_at: OffsetDateTime,
+    direction: Direction,
+    contract_symbol: ContractSymbol,
+}
+
+impl From<TradeRow> for Trade {
+    fn from(value: TradeRow) -> Self {
+        Trade {
+            id: value.id,
+            contract_symbol: value.contract_symbol.into(),
+            price: Decimal::from_f32(value.execution_price).expect("to fit into decimal"),
+            quantity: Decimal::from_f32(value.quantity).expect("to fit into decimal"),
+            taker_direction: value.direction.into(),
+            timestamp: value.created_at,
+        }
+    }
+}
+
+fn predecessors(match_state: orderbook_commons::MatchState) -> Vec<MatchState> {
+    state_machine::match_predecessors(&match_state)
+        .into_iter()
+        .map(MatchState::from)
+        .collect()
+}
+
 impl Matches {
     pub fn new(match_params: &TraderMatchParams, match_state: MatchState) -> Vec<Matches> {
         let order_id = match_params.filled_with.order_id;
@@ -154,8 +293,8 @@ impl From<Matches> for orderbook_commons::Matches {
                 .expect("to be a valid public key"),
             execution_price: Decimal::from_f32(value.execution_price).expect("to fit into decimal"),
             quantity: Decimal::from_f32(value.quantity).expect("to fit into decimal"),
-            created_at: OffsetDateTime::now_utc(),
-            updated_at: OffsetDateTime::now_utc(),
+            created_at: value.created_at,
+            updated_at: value.updated_at,
         }
     }
 }
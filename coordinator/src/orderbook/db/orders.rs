@@ -4,11 +4,17 @@ use crate::orderbook::db::custom_types::MatchState;
 use crate::orderbook::db::custom_types::OrderReason;
 use crate::orderbook::db::custom_types::OrderState;
 use crate::orderbook::db::custom_types::OrderType;
+use crate::orderbook::state_machine;
 use crate::schema::matches;
 use crate::schema::orders;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
 use bitcoin::secp256k1::PublicKey;
 use diesel::prelude::*;
 use diesel::result::QueryResult;
+use diesel::sql_types::BigInt;
+use diesel::sql_types::Bool;
 use diesel::PgConnection;
 use orderbook_commons::NewOrder as OrderbookNewOrder;
 use orderbook_commons::Order as OrderbookOrder;
@@ -264,11 +270,7 @@ pub fn insert(
 }
 
 /// Returns the number of affected rows: 1.
-pub fn set_is_taken(
-    conn: &mut PgConnection,
-    id: Uuid,
-    is_taken: bool,
-) -> QueryResult<OrderbookOrder> {
+pub fn set_is_taken(conn: &mut PgConnection, id: Uuid, is_taken: bool) -> Result<OrderbookOrder> {
     if is_taken {
         set_order_state(conn, id, orderbook_commons::OrderState::Taken)
     } else {
@@ -276,20 +278,78 @@ pub fn set_is_taken(
     }
 }
 
-/// Returns the number of affected rows: 1.
+/// Moves the order to `order_state`, enforcing the transitions defined in
+/// [`state_machine::is_valid_order_transition`].
+///
+/// Setting an order to the state it is already in is a no-op.
 pub fn set_order_state(
     conn: &mut PgConnection,
     id: Uuid,
     order_state: orderbook_commons::OrderState,
-) -> QueryResult<OrderbookOrder> {
+) -> Result<OrderbookOrder> {
+    let order: Order = orders::table
+        .filter(orders::trader_order_id.eq(id))
+        .first(conn)
+        .with_context(|| format!("Could not find order {id}"))?;
+
+    let current_state = OrderBookOrderState::from(order.order_state);
+    if current_state == order_state {
+        return Ok(OrderbookOrder::from(order));
+    }
+
+    ensure!(
+        state_machine::is_valid_order_transition(&current_state, &order_state),
+        "Invalid order state transition from {current_state:?} to {order_state:?} for order {id}"
+    );
+
+    // Filtering on the current state ensures that we do not override a concurrent update.
     let order: Order = diesel::update(orders::table)
         .filter(orders::trader_order_id.eq(id))
+        .filter(orders::order_state.eq(order.order_state))
         .set((orders::order_state.eq(OrderState::from(order_state)),))
-        .get_result(conn)?;
+        .get_result(conn)
+        .with_context(|| format!("Order {id} has been updated concurrently"))?;
 
     Ok(OrderbookOrder::from(order))
 }
 
+/// Cancels the given limit order by setting it to [`OrderState::Failed`].
+///
+/// Only open limit orders can be cancelled, i.e. orders which are currently in execution are not
+/// affected.
+pub fn cancel_limit_order(
+    conn: &mut PgConnection,
+    id: Uuid,
+    trader_id: PublicKey,
+) -> Result<OrderbookOrder> {
+    let order: Order = diesel::update(orders::table)
+        .filter(orders::trader_order_id.eq(id))
+        .filter(orders::trader_id.eq(trader_id.to_string()))
+        .filter(orders::order_state.eq(OrderState::Open))
+        .filter(orders::order_type.eq(OrderType::Limit))
+        .set(orders::order_state.eq(OrderState::Failed))
+        .get_result(conn)
+        .optional()?
+        .with_context(|| format!("Order {id} is not an open limit order of trader {trader_id}"))?;
+
+    Ok(OrderbookOrder::from(order))
+}
+
+/// Cancels all open limit orders of `trader_id`, e.g. if the trader has disconnected.
+pub fn cancel_limit_orders_by_trader_id(
+    conn: &mut PgConnection,
+    trader_id: PublicKey,
+) -> QueryResult<Vec<OrderbookOrder>> {
+    let orders: Vec<Order> = diesel::update(orders::table)
+        .filter(orders::trader_id.eq(trader_id.to_string()))
+        .filter(orders::order_state.eq(OrderState::Open))
+        .filter(orders::order_type.eq(OrderType::Limit))
+        .set(orders::order_state.eq(OrderState::Failed))
+        .get_results(conn)?;
+
+    Ok(orders.into_iter().map(OrderbookOrder::from).collect())
+}
+
 pub fn set_expired_limit_orders_to_failed(
     conn: &mut PgConnection,
 ) -> QueryResult<Vec<OrderbookOrder>> {
@@ -336,7 +396,7 @@ pub fn get_by_trader_id_and_state(
 pub fn get_all_limit_order_filled_matches(
     conn: &mut PgConnection,
     trader_id: PublicKey,
-) -> QueryResult<Vec<(Uuid, Decimal)>> {
+) -> QueryResult<Vec<(Uuid, Decimal, Decimal)>> {
     let orders = orders::table
         // We use `matches::match_order_id` so that we can verify that the corresponding app trader
         // order is in `match_state` _`Filled`_. The maker's match remains in `Pending` (since the
@@ -357,22 +417,68 @@ pub fn get_all_limit_order_filled_matches(
             matches::order_id,
             matches::quantity,
             orders::direction,
+            matches::execution_price,
         ))
-        .load::<(Uuid, f32, Direction)>(conn)?;
+        .load::<(Uuid, f32, Direction, f32)>(conn)?;
 
     let filled_matches = orders
         .into_iter()
-        .map(|(order_id, quantity, direction_maker)| {
+        .map(|(order_id, quantity, direction_maker, execution_price)| {
             let quantity = Decimal::from_f32(quantity).expect("to fit into Decimal");
+            let execution_price = Decimal::from_f32(execution_price).expect("to fit into Decimal");
 
             let quantity = match direction_maker {
                 Direction::Long => quantity,
                 Direction::Short => -quantity,
             };
 
-            (order_id, quantity)
+            (order_id, quantity, execution_price)
         })
         .collect();
 
     Ok(filled_matches)
 }
+
+#[derive(QueryableByName)]
+struct AdvisoryLock {
+    #[diesel(sql_type = Bool)]
+    locked: bool,
+}
+
+/// Tries to lock the execution of the given order for the lifetime of the database session, i.e.
+/// until [`unlock_execution`] is called on the same connection.
+///
+/// Returns `false` if the execution of the order is already locked by another session.
+pub fn try_lock_execution(conn: &mut PgConnection, order_id: Uuid) -> QueryResult<bool> {
+    let lock = diesel::sql_query("SELECT pg_try_advisory_lock($1) AS locked")
+        .bind::<BigInt, _>(execution_lock_key(order_id))
+        .get_result::<AdvisoryLock>(conn)?;
+
+    Ok(lock.locked)
+}
+
+/// Releases the lock taken with [`try_lock_execution`].
+pub fn unlock_execution(conn: &mut PgConnection, order_id: Uuid) -> QueryResult<()> {
+    diesel::sql_query("SELECT pg_advisory_unlock($1) AS locked")
+        .bind::<BigInt, _>(execution_lock_key(order_id))
+        .get_result::<AdvisoryLock>(conn)?;
+
+    Ok(())
+}
+
+/// Like [`try_lock_execution`], but the lock is released at the end of the current transaction.
+pub fn try_lock_execution_until_commit(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+) -> QueryResult<bool> {
+    let lock = diesel::sql_query("SELECT pg_try_advisory_xact_lock($1) AS locked")
+        .bind::<BigInt, _>(execution_lock_key(order_id))
+        .get_result::<AdvisoryLock>(conn)?;
+
+    Ok(lock.locked)
+}
+
+fn execution_lock_key(order_id: Uuid) -> i64 {
+    let (key, _) = order_id.as_u64_pair();
+    key as i64
+}
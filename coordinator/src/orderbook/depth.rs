@@ -0,0 +1,108 @@
+use crate::orderbook::db::orders;
+use anyhow::Result;
+use diesel::r2d2::ConnectionManager;
+use diesel::r2d2::Pool;
+use diesel::PgConnection;
+use futures::future::RemoteHandle;
+use futures::FutureExt;
+use orderbook_commons::Depth;
+use orderbook_commons::DepthSnapshot;
+use orderbook_commons::DepthUpdate;
+use orderbook_commons::Message;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use trade::ContractSymbol;
+
+/// How often the aggregated orderbook is recomputed and changes are published.
+const DEPTH_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a full snapshot of the aggregated orderbook is published, allowing clients which
+/// missed an update to resynchronise.
+const DEPTH_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The latest aggregated orderbook together with its sequence number.
+///
+/// Every published [`DepthUpdate`] increments the sequence number by one.
+#[derive(Default)]
+pub struct DepthFeed {
+    inner: RwLock<(Depth, u64)>,
+}
+
+impl DepthFeed {
+    pub fn snapshot(&self) -> DepthSnapshot {
+        let (depth, sequence) = &*self.inner.read();
+        depth.to_snapshot(ContractSymbol::BtcUsd, *sequence)
+    }
+
+    /// Replaces the aggregated orderbook and publishes the changes, if any.
+    ///
+    /// The update is published while holding the lock so that a subscriber which reads the
+    /// snapshot after subscribing to the price feed does not miss any update.
+    fn update(&self, new_depth: Depth, tx_price_feed: &broadcast::Sender<Message>) {
+        let mut inner = self.inner.write();
+        let (depth, sequence) = &mut *inner;
+
+        let updates = depth.diff(&new_depth);
+        if updates.is_empty() {
+            return;
+        }
+
+        *depth = new_depth;
+        *sequence += 1;
+
+        let msg = Message::DepthUpdate(DepthUpdate {
+            contract_symbol: ContractSymbol::BtcUsd,
+            sequence: *sequence,
+            updates,
+        });
+        if let Err(e) = tx_price_feed.send(msg) {
+            tracing::trace!("Could not publish depth update. Error: {e:#}");
+        }
+    }
+}
+
+/// Periodically aggregates the open limit orders into price levels and publishes the changes as
+/// [`Message::DepthUpdate`]s and regular [`Message::DepthSnapshot`]s on the price feed.
+pub fn monitor(
+    pool: Pool<ConnectionManager<PgConnection>>,
+    tx_price_feed: broadcast::Sender<Message>,
+    depth_feed: Arc<DepthFeed>,
+) -> RemoteHandle<()> {
+    let (fut, remote_handle) = async move {
+        let mut update_interval = tokio::time::interval(DEPTH_UPDATE_INTERVAL);
+        let mut snapshot_interval = tokio::time::interval(DEPTH_SNAPSHOT_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = update_interval.tick() => {
+                    match load_depth(&pool) {
+                        Ok(depth) => depth_feed.update(depth, &tx_price_feed),
+                        Err(e) => tracing::error!("Failed to load orderbook depth. Error: {e:#}"),
+                    }
+                }
+                _ = snapshot_interval.tick() => {
+                    let snapshot = depth_feed.snapshot();
+                    if let Err(e) = tx_price_feed.send(Message::DepthSnapshot(snapshot)) {
+                        tracing::trace!("Could not publish depth snapshot. Error: {e:#}");
+                    }
+                }
+            }
+        }
+    }
+    .remote_handle();
+
+    tokio::spawn(fut);
+
+    remote_handle
+}
+
+fn load_depth(pool: &Pool<ConnectionManager<PgConnection>>) -> Result<Depth> {
+    let mut conn = pool.get()?;
+
+    // Expired and failed orders are not part of the orderbook.
+    let orders = orders::all(&mut conn, false, false)?;
+
+    Ok(Depth::from_orders(&orders, ContractSymbol::BtcUsd))
+}
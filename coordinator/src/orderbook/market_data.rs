@@ -0,0 +1,128 @@
+use crate::db;
+use crate::orderbook::db::matches;
+use anyhow::ensure;
+use anyhow::Result;
+use diesel::PgConnection;
+use orderbook_commons::Candle;
+use orderbook_commons::MarketStats;
+use orderbook_commons::Message;
+use orderbook_commons::Resolution;
+use orderbook_commons::Trade;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use trade::ContractSymbol;
+use uuid::Uuid;
+
+/// The maximum number of trades returned by the trades feed.
+pub const MAX_TRADES: i64 = 1000;
+
+/// The maximum number of candles returned for a single request.
+pub const MAX_CANDLES: i64 = 1000;
+
+/// The maximum number of trades aggregated into candles or market statistics.
+pub const MAX_AGGREGATED_TRADES: i64 = 100_000;
+
+/// Returns the latest trades, latest first.
+pub fn get_recent_trades(conn: &mut PgConnection, limit: i64) -> Result<Vec<Trade>> {
+    let trades = matches::get_trades(
+        conn,
+        OffsetDateTime::UNIX_EPOCH,
+        OffsetDateTime::now_utc(),
+        limit.min(MAX_TRADES),
+    )?;
+    Ok(trades)
+}
+
+/// Returns the OHLC candles of the given resolution between `from` and `to`, oldest first.
+pub fn get_candles(
+    conn: &mut PgConnection,
+    resolution: Resolution,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> Result<Vec<Candle>> {
+    ensure!(from < to, "Invalid time range from {from} to {to}");
+
+    let number_of_candles = (to - from) / resolution.duration();
+    ensure!(
+        number_of_candles <= MAX_CANDLES as f64,
+        "Requested {number_of_candles:.0} candles, but at most {MAX_CANDLES} are supported"
+    );
+
+    let trades = get_aggregated_trades(conn, from, to)?;
+
+    Ok(orderbook_commons::candles(&trades, resolution))
+}
+
+/// Returns the market statistics of the last 24 hours, including the current open interest.
+pub fn get_market_stats(conn: &mut PgConnection) -> Result<MarketStats> {
+    let now = OffsetDateTime::now_utc();
+    let trades = get_aggregated_trades(conn, now - Duration::days(1), now)?;
+    let open_interest = get_open_interest(conn)?;
+
+    Ok(orderbook_commons::market_stats(
+        ContractSymbol::BtcUsd,
+        &trades,
+        open_interest,
+        now,
+    ))
+}
+
+/// Returns all trades between `from` and `to`, as long as there are not more than
+/// [`MAX_AGGREGATED_TRADES`] of them.
+fn get_aggregated_trades(
+    conn: &mut PgConnection,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> Result<Vec<Trade>> {
+    // Loading one more trade than allowed tells us whether we would have missed any.
+    let trades = matches::get_trades(conn, from, to, MAX_AGGREGATED_TRADES + 1)?;
+    ensure!(
+        trades.len() as i64 <= MAX_AGGREGATED_TRADES,
+        "More than {MAX_AGGREGATED_TRADES} trades from {from} to {to}"
+    );
+
+    Ok(trades)
+}
+
+/// Returns the sum of the quantity of all positions which are not closed yet.
+pub fn get_open_interest(conn: &mut PgConnection) -> Result<Decimal> {
+    let positions = db::positions::Position::get_all_active_positions(conn)?;
+
+    let open_interest = positions
+        .iter()
+        .map(|position| Decimal::from_f32(position.quantity).expect("to fit into decimal"))
+        .sum();
+
+    Ok(open_interest)
+}
+
+/// Publishes the trades of the given market order on the price feed.
+pub fn broadcast_trades(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    tx_price_feed: &broadcast::Sender<Message>,
+) -> Result<()> {
+    for trade in matches::get_trades_by_order_id(conn, order_id)? {
+        if let Err(e) = tx_price_feed.send(Message::Trade(trade)) {
+            tracing::warn!(%order_id, "Could not publish trade. Error: {e:#}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Publishes the latest market statistics on the price feed.
+pub fn broadcast_market_stats(
+    conn: &mut PgConnection,
+    tx_price_feed: &broadcast::Sender<Message>,
+) -> Result<()> {
+    let stats = get_market_stats(conn)?;
+    if let Err(e) = tx_price_feed.send(Message::MarketStats(stats)) {
+        tracing::trace!("Could not publish market stats. Error: {e:#}");
+    }
+
+    Ok(())
+}
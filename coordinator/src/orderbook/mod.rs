@@ -1,7 +1,10 @@
 pub mod async_match;
 pub mod collaborative_revert;
 pub mod db;
+pub mod depth;
+pub mod market_data;
 pub mod routes;
+pub mod state_machine;
 pub mod trading;
 pub mod websocket;
 
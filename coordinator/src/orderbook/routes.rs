@@ -1,4 +1,5 @@
 use crate::orderbook;
+use crate::orderbook::market_data;
 use crate::orderbook::trading::NewOrderMessage;
 use crate::orderbook::trading::TradingError;
 use crate::orderbook::websocket::websocket_connection;
@@ -15,10 +16,16 @@ use axum::Json;
 use diesel::r2d2::ConnectionManager;
 use diesel::r2d2::PooledConnection;
 use diesel::PgConnection;
+use orderbook_commons::create_cancel_order_message;
+use orderbook_commons::Candle;
+use orderbook_commons::MarketStats;
 use orderbook_commons::Message;
 use orderbook_commons::NewOrder;
 use orderbook_commons::Order;
 use orderbook_commons::OrderReason;
+use orderbook_commons::Resolution;
+use orderbook_commons::Signature;
+use orderbook_commons::Trade;
 use serde::de;
 use serde::Deserialize;
 use serde::Deserializer;
@@ -26,11 +33,18 @@ use serde::Serialize;
 use std::fmt;
 use std::str::FromStr;
 use std::sync::Arc;
+use time::OffsetDateTime;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::mpsc;
 use tracing::instrument;
 use uuid::Uuid;
 
+/// The number of trades returned if no limit is given.
+const DEFAULT_NUMBER_OF_TRADES: i64 = 100;
+
+/// The number of candles returned if no start of the range is given.
+const DEFAULT_NUMBER_OF_CANDLES: i32 = 100;
+
 #[derive(Debug, Deserialize)]
 pub struct AllOrdersParams {
     #[serde(default, deserialize_with = "empty_string_as_none")]
@@ -114,6 +128,9 @@ pub async fn post_order(
     let order = result.map_err(|e| match e.downcast_ref() {
         Some(TradingError::InvalidOrder(reason)) => AppError::InvalidOrder(reason.to_string()),
         Some(TradingError::NoMatchFound(message)) => AppError::NoMatchFound(message.to_string()),
+        Some(TradingError::SlippageExceeded(message)) => {
+            AppError::NoMatchFound(message.to_string())
+        }
         _ => AppError::InternalServerError(format!("Failed to post order. Error: {e:#}")),
     })?;
 
@@ -150,9 +167,122 @@ pub async fn put_order(
     Ok(Json(order))
 }
 
+/// Cancels an open limit order.
+///
+/// The trader has to sign the [`create_cancel_order_message`] of the order to prove that they
+/// own it.
+pub async fn delete_order(
+    Path(order_id): Path<Uuid>,
+    State(state): State<Arc<AppState>>,
+    Json(signature): Json<Signature>,
+) -> Result<Json<Order>, AppError> {
+    let trader_id = signature.pubkey;
+    signature
+        .signature
+        .verify(&create_cancel_order_message(order_id), &trader_id)
+        .map_err(|e| AppError::Unauthorized(format!("Invalid signature: {e:#}")))?;
+
+    let mut conn = get_db_connection(&state)?;
+    let order = orderbook::db::orders::cancel_limit_order(&mut conn, order_id, trader_id)
+        .map_err(|e| AppError::BadRequest(format!("Failed to cancel order: {e:#}")))?;
+    let sender = state.tx_price_feed.clone();
+    update_pricefeed(Message::DeleteOrder(order.id), sender);
+
+    Ok(Json(order))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TradesParams {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    limit: Option<i64>,
+}
+
+pub async fn get_trades(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TradesParams>,
+) -> Result<Json<Vec<Trade>>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_NUMBER_OF_TRADES);
+    if limit < 0 {
+        return Err(AppError::BadRequest(format!(
+            "Invalid limit {limit}. The limit must not be negative"
+        )));
+    }
+
+    let mut conn = get_db_connection(&state)?;
+    let trades = market_data::get_recent_trades(&mut conn, limit)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to load trades: {e:#}")))?;
+
+    Ok(Json(trades))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CandlesParams {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    resolution: Option<Resolution>,
+    /// Unix timestamp (in seconds) of the start of the requested range.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    from: Option<i64>,
+    /// Unix timestamp (in seconds) of the end of the requested range. Defaults to now.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    to: Option<i64>,
+}
+
+pub async fn get_candles(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<CandlesParams>,
+) -> Result<Json<Vec<Candle>>, AppError> {
+    let resolution = params.resolution.unwrap_or(Resolution::OneHour);
+
+    let to = match params.to {
+        Some(to) => OffsetDateTime::from_unix_timestamp(to)
+            .map_err(|e| AppError::BadRequest(format!("Invalid end of range: {e:#}")))?,
+        None => OffsetDateTime::now_utc(),
+    };
+    let from = match params.from {
+        Some(from) => OffsetDateTime::from_unix_timestamp(from)
+            .map_err(|e| AppError::BadRequest(format!("Invalid start of range: {e:#}")))?,
+        None => to
+            .checked_sub(resolution.duration() * DEFAULT_NUMBER_OF_CANDLES)
+            .ok_or_else(|| AppError::BadRequest(format!("Invalid end of range {to}")))?,
+    };
+
+    if from >= to || (to - from) / resolution.duration() > market_data::MAX_CANDLES as f64 {
+        return Err(AppError::BadRequest(format!(
+            "Invalid range from {from} to {to} for resolution {resolution}. At most {} candles can be requested",
+            market_data::MAX_CANDLES
+        )));
+    }
+
+    let mut conn = get_db_connection(&state)?;
+    let candles = market_data::get_candles(&mut conn, resolution, from, to)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to load candles: {e:#}")))?;
+
+    Ok(Json(candles))
+}
+
+pub async fn get_market_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MarketStats>, AppError> {
+    let mut conn = get_db_connection(&state)?;
+    let stats = market_data::get_market_stats(&mut conn).map_err(|e| {
+        AppError::InternalServerError(format!("Failed to load market stats: {e:#}"))
+    })?;
+
+    Ok(Json(stats))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebsocketParams {
+    /// Opt in to the aggregated orderbook instead of the individual orders.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    depth: Option<bool>,
+}
+
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    Query(params): Query<WebsocketParams>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| websocket_connection(socket, state))
+    let depth = params.depth.unwrap_or_default();
+    ws.on_upgrade(move |socket| websocket_connection(socket, state, depth))
 }
@@ -0,0 +1,119 @@
+use orderbook_commons::MatchState;
+use orderbook_commons::OrderState;
+
+/// Returns true if an order is allowed to move from `from` to `to`.
+///
+/// Allowed transitions:
+/// - Open -> Matched: a market order or a limit order has been matched.
+/// - Open -> Taken: a limit order has been taken by the maker, or the maker could not be notified
+///   about a match.
+/// - Open -> Failed: no match has been found or the order expired.
+/// - Matched -> Taken: the trade has been executed.
+/// - Matched -> Failed: the trade execution failed or timed out.
+/// - Matched -> Open: the match of a limit order has been abandoned and its liquidity is released
+///   back into the orderbook.
+/// - Taken -> Open: the maker re-opened a limit order.
+///
+/// `Failed` is a final state.
+pub fn is_valid_order_transition(from: &OrderState, to: &OrderState) -> bool {
+    matches!(
+        (from, to),
+        (OrderState::Open, OrderState::Matched)
+            | (OrderState::Open, OrderState::Taken)
+            | (OrderState::Open, OrderState::Failed)
+            | (OrderState::Matched, OrderState::Taken)
+            | (OrderState::Matched, OrderState::Failed)
+            | (OrderState::Matched, OrderState::Open)
+            | (OrderState::Taken, OrderState::Open)
+    )
+}
+
+/// Returns true if a match is allowed to move from `from` to `to`.
+///
+/// A match is created in `Pending` and ends up either in `Filled` or `Failed`, both of which are
+/// final states.
+pub fn is_valid_match_transition(from: &MatchState, to: &MatchState) -> bool {
+    matches!(
+        (from, to),
+        (MatchState::Pending, MatchState::Filled) | (MatchState::Pending, MatchState::Failed)
+    )
+}
+
+/// Returns all order states from which an order is allowed to move to `to`.
+pub fn order_predecessors(to: &OrderState) -> Vec<OrderState> {
+    [
+        OrderState::Open,
+        OrderState::Matched,
+        OrderState::Taken,
+        OrderState::Failed,
+    ]
+    .into_iter()
+    .filter(|from| is_valid_order_transition(from, to))
+    .collect()
+}
+
+/// Returns all match states from which a match is allowed to move to `to`.
+pub fn match_predecessors(to: &MatchState) -> Vec<MatchState> {
+    [MatchState::Pending, MatchState::Filled, MatchState::Failed]
+        .into_iter()
+        .filter(|from| is_valid_match_transition(from, to))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_order_is_final() {
+        for to in [
+            OrderState::Open,
+            OrderState::Matched,
+            OrderState::Taken,
+            OrderState::Failed,
+        ] {
+            assert!(!is_valid_order_transition(&OrderState::Failed, &to));
+        }
+    }
+
+    #[test]
+    fn taken_order_can_only_be_reopened() {
+        assert!(is_valid_order_transition(
+            &OrderState::Taken,
+            &OrderState::Open
+        ));
+        assert!(!is_valid_order_transition(
+            &OrderState::Taken,
+            &OrderState::Matched
+        ));
+        assert!(!is_valid_order_transition(
+            &OrderState::Taken,
+            &OrderState::Failed
+        ));
+    }
+
+    #[test]
+    fn matched_order_can_be_released() {
+        assert_eq!(
+            order_predecessors(&OrderState::Open),
+            vec![OrderState::Matched, OrderState::Taken]
+        );
+        assert_eq!(
+            order_predecessors(&OrderState::Matched),
+            vec![OrderState::Open]
+        );
+    }
+
+    #[test]
+    fn only_pending_matches_can_be_updated() {
+        assert_eq!(
+            match_predecessors(&MatchState::Failed),
+            vec![MatchState::Pending]
+        );
+        assert_eq!(
+            match_predecessors(&MatchState::Filled),
+            vec![MatchState::Pending]
+        );
+        assert!(match_predecessors(&MatchState::Pending).is_empty());
+    }
+}
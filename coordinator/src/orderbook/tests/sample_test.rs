@@ -120,5 +120,6 @@ fn dummy_order(expiry: OffsetDateTime) -> NewOrder {
         contract_symbol: trade::ContractSymbol::BtcUsd,
         leverage: 1.0,
         stable: false,
+        worst_price: None,
     }
 }
@@ -48,6 +48,8 @@ pub enum TradingError {
     InvalidOrder(String),
     #[error("{0}")]
     NoMatchFound(String),
+    #[error("{0}")]
+    SlippageExceeded(String),
 }
 
 #[derive(Clone)]
@@ -183,6 +185,32 @@ async fn process_new_order(
             true,
         )?;
 
+        let opposite_direction_orders = match new_order.worst_price {
+            Some(worst_price) => {
+                let (acceptable_orders, rejected_orders): (Vec<_>, Vec<_>) =
+                    opposite_direction_orders.into_iter().partition(|o| {
+                        is_within_price_bound(order.direction, o.price, worst_price)
+                    });
+
+                if acceptable_orders.is_empty() && !rejected_orders.is_empty() {
+                    let best_price =
+                        sort_orders(rejected_orders, order.direction == Direction::Long)
+                            .first()
+                            .map(|o| o.price)
+                            .expect("to have at least one order");
+
+                    orders::set_order_state(conn, order.id, OrderState::Failed)?;
+                    bail!(TradingError::SlippageExceeded(format!(
+                        "Could not match order {} within worst acceptable price {worst_price}, best available price is {best_price}",
+                        order.id
+                    )));
+                }
+
+                acceptable_orders
+            }
+            None => opposite_direction_orders,
+        };
+
         let matched_orders = match match_order(&order, opposite_direction_orders, network) {
             Ok(Some(matched_orders)) => matched_orders,
             Ok(None) => {
@@ -373,6 +401,17 @@ fn match_order(
     }))
 }
 
+/// Returns true if a market order in the given direction may be filled at `price` without
+/// exceeding the `worst_price`.
+///
+/// A long order must not pay more and a short order must not receive less than the worst price.
+fn is_within_price_bound(direction: Direction, price: Decimal, worst_price: Decimal) -> bool {
+    match direction {
+        Direction::Long => price <= worst_price,
+        Direction::Short => price >= worst_price,
+    }
+}
+
 /// sorts the provided list of orders
 ///
 /// For matching market order and limit order we have to
@@ -400,6 +439,7 @@ fn sort_orders(mut orders: Vec<Order>, is_long: bool) -> Vec<Order> {
 
 #[cfg(test)]
 pub mod tests {
+    use crate::orderbook::trading::is_within_price_bound;
     use crate::orderbook::trading::match_order;
     use crate::orderbook::trading::sort_orders;
     use bitcoin::secp256k1::PublicKey;
@@ -715,4 +755,42 @@ pub mod tests {
 
         assert!(matched_orders.is_none());
     }
+
+    #[test]
+    fn long_order_only_accepts_prices_up_to_worst_price() {
+        assert!(is_within_price_bound(
+            Direction::Long,
+            dec!(29_900),
+            dec!(30_000)
+        ));
+        assert!(is_within_price_bound(
+            Direction::Long,
+            dec!(30_000),
+            dec!(30_000)
+        ));
+        assert!(!is_within_price_bound(
+            Direction::Long,
+            dec!(30_001),
+            dec!(30_000)
+        ));
+    }
+
+    #[test]
+    fn short_order_only_accepts_prices_down_to_worst_price() {
+        assert!(is_within_price_bound(
+            Direction::Short,
+            dec!(30_100),
+            dec!(30_000)
+        ));
+        assert!(is_within_price_bound(
+            Direction::Short,
+            dec!(30_000),
+            dec!(30_000)
+        ));
+        assert!(!is_within_price_bound(
+            Direction::Short,
+            dec!(29_999),
+            dec!(30_000)
+        ));
+    }
 }
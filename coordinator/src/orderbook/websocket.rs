@@ -1,25 +1,39 @@
 use crate::db::user;
 use crate::message::NewUserMessage;
-use crate::orderbook;
 use crate::orderbook::db::orders;
+use crate::orderbook::market_data;
 use crate::routes::AppState;
 use axum::extract::ws::Message as WebsocketMessage;
 use axum::extract::ws::WebSocket;
+use bitcoin::secp256k1::PublicKey;
 use futures::SinkExt;
 use futures::StreamExt;
 use orderbook_commons::create_sign_message;
 use orderbook_commons::Message;
 use orderbook_commons::OrderbookRequest;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::sync::Mutex;
 
 const WEBSOCKET_SEND_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// The number of authenticated websocket connections per trader.
+///
+/// A trader may reconnect before we notice that the old connection is gone, hence their limit
+/// orders are only cancelled once their last connection is closed.
+#[derive(Default)]
+pub struct AuthenticatedConnections(Mutex<HashMap<PublicKey, usize>>);
+
 // This function deals with a single websocket connection, i.e., a single
 // connected client / user, for which we will spawn two independent tasks (for
 // receiving / sending messages).
-pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>) {
+//
+// If `depth` is set, the client is kept up to date with the aggregated orderbook instead of the
+// individual orders.
+pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>, depth: bool) {
     // By splitting, we can send and receive at the same time.
     let (mut sender, mut receiver) = stream.split();
 
@@ -35,17 +49,36 @@ pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>) {
         }
     };
 
-    let orders = match orderbook::db::orders::all(&mut conn, false, false) {
-        Ok(orders) => orders,
-        Err(error) => {
-            tracing::error!("Could not load all orders from db {error:#}");
-            return;
+    if depth {
+        // Now send the aggregated orderbook to the new client. Depth updates with a lower
+        // sequence number received afterwards are ignored by the client.
+        let snapshot = state.depth_feed.snapshot();
+        if let Ok(msg) = serde_json::to_string(&Message::DepthSnapshot(snapshot)) {
+            let _ = sender.send(WebsocketMessage::Text(msg)).await;
         }
-    };
+    } else {
+        let orders = match orders::all(&mut conn, false, false) {
+            Ok(orders) => orders,
+            Err(error) => {
+                tracing::error!("Could not load all orders from db {error:#}");
+                return;
+            }
+        };
 
-    // Now send the "all orders" to the new client.
-    if let Ok(msg) = serde_json::to_string(&Message::AllOrders(orders)) {
-        let _ = sender.send(WebsocketMessage::Text(msg)).await;
+        // Now send the "all orders" to the new client.
+        if let Ok(msg) = serde_json::to_string(&Message::AllOrders(orders)) {
+            let _ = sender.send(WebsocketMessage::Text(msg)).await;
+        }
+    }
+
+    // Send the latest market stats so that the client does not have to wait for the next update.
+    match market_data::get_market_stats(&mut conn) {
+        Ok(stats) => {
+            if let Ok(msg) = serde_json::to_string(&Message::MarketStats(stats)) {
+                let _ = sender.send(WebsocketMessage::Text(msg)).await;
+            }
+        }
+        Err(e) => tracing::warn!("Could not load market stats from db {e:#}"),
     }
 
     let (local_sender, mut local_receiver) = mpsc::channel::<Message>(100);
@@ -77,6 +110,22 @@ pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>) {
         let local_sender = local_sender.clone();
         tokio::spawn(async move {
             while let Ok(st) = price_feed.recv().await {
+                // Clients subscribed to the aggregated orderbook do not get the individual orders.
+                // This way we do not leak the makers' identities. Clients which did not opt in
+                // keep getting the individual orders, as they do not know about depth messages.
+                let is_order_message = matches!(
+                    st,
+                    Message::AllOrders(_)
+                        | Message::NewOrder(_)
+                        | Message::DeleteOrder(_)
+                        | Message::Update(_)
+                );
+                let is_depth_message =
+                    matches!(st, Message::DepthSnapshot(_) | Message::DepthUpdate(_));
+                if (depth && is_order_message) || (!depth && is_depth_message) {
+                    continue;
+                }
+
                 if let Err(error) = local_sender.send(st).await {
                     tracing::error!("Could not send message {error:#}");
                     return;
@@ -85,8 +134,13 @@ pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>) {
         })
     };
 
+    // The trader authenticated on this connection, whose limit orders are cancelled once the
+    // connection is gone.
+    let (authenticated_tx, authenticated_rx) = watch::channel::<Option<PublicKey>>(None);
+
     // Spawn a task that takes messages from the websocket
     let local_sender = local_sender.clone();
+    let connection_state = state.clone();
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(WebsocketMessage::Text(text))) = receiver.next().await {
             match serde_json::from_str(text.as_str()) {
@@ -134,6 +188,17 @@ pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>) {
 
                     match signature.verify(&msg, &trader_id) {
                         Ok(_) => {
+                            let previous = authenticated_tx.send_replace(Some(trader_id));
+                            if previous != Some(trader_id) {
+                                if let Some(previous) = previous {
+                                    release_connection(&state, previous).await;
+                                }
+
+                                let mut connections =
+                                    state.authenticated_connections.0.lock().await;
+                                *connections.entry(trader_id).or_default() += 1;
+                            }
+
                             if let Err(e) = local_sender.send(Message::Authenticated).await {
                                 tracing::error!("Could not respond to user {e:#}");
                                 return;
@@ -190,4 +255,77 @@ pub async fn websocket_connection(stream: WebSocket, state: Arc<AppState>) {
             send_task.abort();
         },
     };
+
+    let trader_id = *authenticated_rx.borrow();
+    if let Some(trader_id) = trader_id {
+        release_connection(&connection_state, trader_id).await;
+    }
+}
+
+/// Releases an authenticated connection of the trader.
+///
+/// Dead man's switch: limit orders must not outlive the connections of the maker who posted
+/// them, as it cannot update them anymore and they could be filled at stale prices.
+async fn release_connection(state: &AppState, trader_id: PublicKey) {
+    let mut connections = state.authenticated_connections.0.lock().await;
+
+    let remaining = match connections.get_mut(&trader_id) {
+        Some(count) => {
+            *count = count.saturating_sub(1);
+            *count
+        }
+        None => 0,
+    };
+    if remaining > 0 {
+        tracing::debug!(
+            %trader_id,
+            remaining,
+            "Keeping limit orders of trader with remaining connections"
+        );
+        return;
+    }
+
+    connections.remove(&trader_id);
+
+    // We keep holding the lock while cancelling, so that the orders of a connection which is
+    // authenticating in the meantime are not cancelled.
+    cancel_limit_orders(state, trader_id);
+}
+
+fn cancel_limit_orders(state: &AppState, trader_id: PublicKey) {
+    let mut conn = match state.pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!(
+                %trader_id,
+                "Failed to get DB pool connection to cancel limit orders: {e:#}"
+            );
+            return;
+        }
+    };
+
+    let orders = match orders::cancel_limit_orders_by_trader_id(&mut conn, trader_id) {
+        Ok(orders) => orders,
+        Err(e) => {
+            tracing::error!(
+                %trader_id,
+                "Failed to cancel limit orders of disconnected trader: {e:#}"
+            );
+            return;
+        }
+    };
+
+    if !orders.is_empty() {
+        tracing::info!(
+            %trader_id,
+            orders = orders.len(),
+            "Cancelled limit orders of disconnected trader"
+        );
+    }
+
+    for order in orders {
+        if let Err(e) = state.tx_price_feed.send(Message::DeleteOrder(order.id)) {
+            tracing::error!("Could not update price feed: {e:#}");
+        }
+    }
 }
@@ -0,0 +1,311 @@
+//! The coordinator's reserve: on-chain funds kept in a watch-only descriptor wallet, whose keys
+//! never touch the coordinator.
+//!
+//! Funds move between the hot wallet of the node and the reserve in two ways:
+//!
+//! - A sweep sends everything the hot wallet holds above `min_liquidity_threshold_sats` to the
+//!   reserve. The hot wallet signs it right away.
+//! - A top-up is built as an unsigned PSBT spending from the reserve to the hot wallet. It is
+//!   exported for offline signing and imported again to be broadcast.
+
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::blockchain::AnyBlockchain;
+use bdk::blockchain::Blockchain;
+use bdk::database::BatchDatabase;
+use bdk::database::MemoryDatabase;
+use bdk::descriptor::IntoWalletDescriptor;
+use bdk::sled;
+use bdk::wallet::wallet_name_from_descriptor;
+use bdk::wallet::AddressIndex;
+use bdk::Balance;
+use bdk::FeeRate;
+use bdk::SignOptions;
+use bdk::SyncOptions;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::Address;
+use bitcoin::Network;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use ln_dlc_node::ChainSource;
+use parking_lot::Mutex;
+use std::path::Path;
+
+/// The number of consecutive unused addresses after which the reserve stops looking for funds.
+const RESERVE_STOP_GAP: usize = 20;
+
+const RESERVE_SYNC_CONCURRENCY: u8 = 4;
+
+pub struct Reserve {
+    wallet: Mutex<bdk::Wallet<sled::Tree>>,
+    blockchain: AnyBlockchain,
+}
+
+/// A PSBT spending from the reserve, waiting to be signed.
+#[derive(Debug)]
+pub struct UnsignedPsbt {
+    pub psbt: PartiallySignedTransaction,
+    /// The amount received by the recipient, in sats.
+    pub amount: u64,
+    /// In sats.
+    pub fee: u64,
+}
+
+impl Reserve {
+    /// Opens the reserve wallet for `descriptor` and `change_descriptor`, which must not contain
+    /// private keys.
+    pub fn new(
+        data_dir: &Path,
+        network: Network,
+        descriptor: &str,
+        change_descriptor: Option<&str>,
+        chain_source: &ChainSource,
+    ) -> Result<Self> {
+        let secp = Secp256k1::new();
+        for descriptor in std::iter::once(descriptor).chain(change_descriptor) {
+            let (_, keys) = descriptor
+                .into_wallet_descriptor(&secp, network)
+                .context("Invalid reserve descriptor")?;
+            ensure!(
+                keys.is_empty(),
+                "Reserve descriptor must not contain private keys"
+            );
+        }
+
+        let wallet_name =
+            wallet_name_from_descriptor(descriptor, change_descriptor, network, &secp)?;
+
+        let db = sled::open(data_dir.join("reserve"))?;
+        let db = db.open_tree(&wallet_name)?;
+        let wallet = bdk::Wallet::new(descriptor, change_descriptor, network, db)?;
+
+        let blockchain = chain_source.wallet_blockchain(
+            network,
+            wallet_name,
+            RESERVE_STOP_GAP,
+            RESERVE_SYNC_CONCURRENCY,
+        )?;
+
+        Ok(Self {
+            wallet: Mutex::new(wallet),
+            blockchain,
+        })
+    }
+
+    pub fn sync(&self) -> Result<()> {
+        self.wallet
+            .lock()
+            .sync(&self.blockchain, SyncOptions::default())
+            .context("Failed to sync reserve")
+    }
+
+    pub fn balance(&self) -> Result<Balance> {
+        Ok(self.wallet.lock().get_balance()?)
+    }
+
+    /// An address to sweep funds from the hot wallet to.
+    pub fn address(&self) -> Result<Address> {
+        Ok(self
+            .wallet
+            .lock()
+            .get_address(AddressIndex::LastUnused)?
+            .address)
+    }
+
+    /// Builds a PSBT paying `amount` sats from the reserve to `address`.
+    pub fn build_psbt(
+        &self,
+        address: &Address,
+        amount: u64,
+        fee_rate: FeeRate,
+    ) -> Result<UnsignedPsbt> {
+        build_psbt(&self.wallet.lock(), address, amount, fee_rate)
+    }
+
+    /// Finalizes the signed `psbt` and broadcasts the transaction.
+    pub fn broadcast_psbt(&self, psbt: PartiallySignedTransaction) -> Result<Txid> {
+        let tx = finalize_psbt(&self.wallet.lock(), psbt)?;
+        let txid = tx.txid();
+
+        self.blockchain
+            .broadcast(&tx)
+            .with_context(|| format!("Failed to broadcast transaction {txid}"))?;
+
+        tracing::info!(%txid, "Broadcast transaction from reserve");
+
+        Ok(txid)
+    }
+}
+
+fn build_psbt<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    address: &Address,
+    amount: u64,
+    fee_rate: FeeRate,
+) -> Result<UnsignedPsbt> {
+    let mut tx_builder = wallet.build_tx();
+    tx_builder
+        .add_recipient(address.script_pubkey(), amount)
+        .fee_rate(fee_rate)
+        .enable_rbf();
+
+    let (psbt, details) = tx_builder.finish()?;
+    let fee = details.fee.context("Unknown fee")?;
+
+    Ok(UnsignedPsbt { psbt, amount, fee })
+}
+
+fn finalize_psbt<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    mut psbt: PartiallySignedTransaction,
+) -> Result<Transaction> {
+    // Only broadcast what spends from the reserve, not whatever is handed to us.
+    let utxos = wallet.list_unspent()?;
+    for input in psbt.unsigned_tx.input.iter() {
+        if !utxos
+            .iter()
+            .any(|utxo| utxo.outpoint == input.previous_output)
+        {
+            bail!(
+                "Input {} is not an unspent output of the reserve",
+                input.previous_output
+            );
+        }
+    }
+
+    let finalized = wallet.finalize_psbt(&mut psbt, SignOptions::default())?;
+    ensure!(finalized, "PSBT is not fully signed");
+
+    Ok(psbt.extract_tx())
+}
+
+/// Signs reserve PSBTs with a private descriptor read from a file.
+///
+/// Only meant for tests and regtest, the keys of the reserve belong on an offline signer. The file
+/// holds the descriptor on the first line and, optionally, the change descriptor on the second.
+pub struct FileSigner {
+    wallet: bdk::Wallet<MemoryDatabase>,
+}
+
+impl FileSigner {
+    pub fn from_file(path: &Path, network: Network) -> Result<Self> {
+        let descriptors = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read descriptors from {}", path.display()))?;
+        let mut descriptors = descriptors.lines().filter(|line| !line.trim().is_empty());
+
+        let descriptor = descriptors.next().context("No descriptor")?;
+        let wallet = bdk::Wallet::new(
+            descriptor,
+            descriptors.next(),
+            network,
+            MemoryDatabase::default(),
+        )?;
+
+        Ok(Self { wallet })
+    }
+
+    /// Adds our signatures to `psbt`.
+    pub fn sign(&self, psbt: &mut PartiallySignedTransaction) -> Result<()> {
+        self.wallet.sign(
+            psbt,
+            SignOptions {
+                trust_witness_utxo: true,
+                try_finalize: false,
+                ..SignOptions::default()
+            },
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::populate_test_db;
+    use bdk::testutils;
+    use bitcoin::util::bip32::ExtendedPrivKey;
+    use bitcoin::util::bip32::ExtendedPubKey;
+    use rand::thread_rng;
+    use rand::RngCore;
+
+    #[test]
+    fn top_up_psbt_is_broadcast_only_once_signed() {
+        let (public, private) = descriptors();
+        let reserve = funded_wallet(&public, 100_000);
+
+        let signer_path = std::env::temp_dir().join(format!("reserve-{}", rand::random::<u64>()));
+        std::fs::write(&signer_path, private).unwrap();
+        let signer = FileSigner::from_file(&signer_path, Network::Regtest).unwrap();
+        std::fs::remove_file(&signer_path).unwrap();
+
+        let (hot_wallet, _) = descriptors();
+        let hot_wallet = bdk::Wallet::new(
+            &hot_wallet,
+            None,
+            Network::Regtest,
+            MemoryDatabase::default(),
+        )
+        .unwrap()
+        .get_address(AddressIndex::New)
+        .unwrap()
+        .address;
+        let unsigned =
+            build_psbt(&reserve, &hot_wallet, 50_000, FeeRate::from_sat_per_vb(2.0)).unwrap();
+
+        let mut psbt = unsigned.psbt;
+        assert!(finalize_psbt(&reserve, psbt.clone()).is_err());
+
+        signer.sign(&mut psbt).unwrap();
+        let tx = finalize_psbt(&reserve, psbt).unwrap();
+
+        assert!(tx
+            .output
+            .iter()
+            .any(|output| output.script_pubkey == hot_wallet.script_pubkey()
+                && output.value == 50_000));
+    }
+
+    #[test]
+    fn reserve_refuses_psbt_spending_foreign_outputs() {
+        let (public, _) = descriptors();
+        let reserve = funded_wallet(&public, 100_000);
+
+        let (other, _) = descriptors();
+        let other = funded_wallet(&other, 100_000);
+
+        let address = reserve.get_address(AddressIndex::New).unwrap().address;
+        let foreign = build_psbt(&other, &address, 50_000, FeeRate::from_sat_per_vb(2.0)).unwrap();
+
+        assert!(finalize_psbt(&reserve, foreign.psbt).is_err());
+    }
+
+    /// A public descriptor for the reserve and the matching private one for the signer.
+    fn descriptors() -> (String, String) {
+        let mut seed = [0u8; 32];
+        thread_rng().fill_bytes(&mut seed);
+
+        let xprv = ExtendedPrivKey::new_master(Network::Regtest, &seed).unwrap();
+        let xpub = ExtendedPubKey::from_priv(&Secp256k1::new(), &xprv);
+
+        (format!("wpkh({xpub}/0/*)"), format!("wpkh({xprv}/0/*)"))
+    }
+
+    fn funded_wallet(descriptor: &str, amount: u64) -> bdk::Wallet<MemoryDatabase> {
+        let descriptors = testutils!(@descriptors (descriptor));
+
+        let mut database = MemoryDatabase::default();
+        populate_test_db!(
+            &mut database,
+            testutils! {
+                @tx ( (@external descriptors, 0) => amount ) (@confirmations 1)
+            },
+            Some(100)
+        );
+
+        bdk::Wallet::new(descriptor, None, Network::Regtest, database).unwrap()
+    }
+}
@@ -1,30 +1,54 @@
+use crate::admin::broadcast_reserve_psbt;
+use crate::admin::bump_fee;
 use crate::admin::close_channel;
 use crate::admin::collaborative_revert;
 use crate::admin::connect_to_peer;
+use crate::admin::consolidate_utxos;
+#[cfg(feature = "bolt12")]
+use crate::admin::create_refund;
 use crate::admin::get_balance;
+use crate::admin::get_liquidity;
+use crate::admin::get_payment_attempts;
+use crate::admin::get_reconciliation_report;
+use crate::admin::get_reserve;
 use crate::admin::is_connected;
 use crate::admin::list_channels;
 use crate::admin::list_dlc_channels;
 use crate::admin::list_on_chain_transactions;
 use crate::admin::list_peers;
+use crate::admin::list_reconciliation_actions;
+use crate::admin::list_unconfirmed_transactions;
+use crate::admin::list_utxos;
 use crate::admin::open_channel;
+use crate::admin::repair_inconsistencies;
+use crate::admin::send_on_chain;
 use crate::admin::send_payment;
 use crate::admin::sign_message;
+use crate::admin::sweep_to_reserve;
+use crate::admin::top_up_from_reserve;
 use crate::collaborative_revert;
 use crate::db;
 use crate::db::liquidity::LiquidityRequestLog;
 use crate::db::user;
-use crate::is_liquidity_sufficient;
+use crate::liquidity;
 use crate::message::NewUserMessage;
 use crate::message::OrderbookMessage;
 use crate::node::Node;
+use crate::orderbook::depth::DepthFeed;
+use crate::orderbook::market_data;
+use crate::orderbook::routes::delete_order;
+use crate::orderbook::routes::get_candles;
+use crate::orderbook::routes::get_market_stats;
 use crate::orderbook::routes::get_order;
 use crate::orderbook::routes::get_orders;
+use crate::orderbook::routes::get_trades;
 use crate::orderbook::routes::post_order;
 use crate::orderbook::routes::put_order;
 use crate::orderbook::routes::websocket_handler;
 use crate::orderbook::trading::NewOrderMessage;
+use crate::orderbook::websocket::AuthenticatedConnections;
 use crate::position::models::parse_channel_id;
+use crate::reserve::Reserve;
 use crate::settings::Settings;
 use crate::AppError;
 use autometrics::autometrics;
@@ -85,6 +109,10 @@ pub struct AppState {
     pub announcement_addresses: Vec<NetAddress>,
     pub node_alias: String,
     pub auth_users_notifier: mpsc::Sender<OrderbookMessage>,
+    pub depth_feed: Arc<DepthFeed>,
+    /// The watch-only wallet holding the coordinator's reserve, if configured.
+    pub reserve: Option<Arc<Reserve>>,
+    pub authenticated_connections: AuthenticatedConnections,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -99,6 +127,8 @@ pub fn router(
     tx_price_feed: broadcast::Sender<Message>,
     tx_user_feed: broadcast::Sender<NewUserMessage>,
     auth_users_notifier: mpsc::Sender<OrderbookMessage>,
+    depth_feed: Arc<DepthFeed>,
+    reserve: Option<Arc<Reserve>>,
 ) -> Router {
     let app_state = Arc::new(AppState {
         node,
@@ -111,9 +141,12 @@ pub fn router(
         announcement_addresses,
         node_alias: node_alias.to_string(),
         auth_users_notifier,
+        depth_feed,
+        reserve,
+        authenticated_connections: AuthenticatedConnections::default(),
     });
 
-    Router::new()
+    let router = Router::new()
         .route("/", get(index))
         .route("/api/version", get(version))
         .route(
@@ -134,10 +167,14 @@ pub fn router(
         .route("/api/orderbook/orders", get(get_orders).post(post_order))
         .route(
             "/api/orderbook/orders/:order_id",
-            get(get_order).put(put_order),
+            get(get_order).put(put_order).delete(delete_order),
         )
         .route("/api/orderbook/websocket", get(websocket_handler))
+        .route("/api/market/trades", get(get_trades))
+        .route("/api/market/candles", get(get_candles))
+        .route("/api/market/stats", get(get_market_stats))
         .route("/api/trade", post(post_trade))
+        .route("/api/trade/fee_invoice", post(post_trade_fee_invoice))
         .route("/api/rollover/:dlc_channel_id", post(rollover))
         .route("/api/register", post(post_register))
         .route("/api/admin/balance", get(get_balance))
@@ -147,8 +184,25 @@ pub fn router(
         .route("/api/admin/channels/:channel_id", delete(close_channel))
         .route("/api/admin/peers", get(list_peers))
         .route("/api/admin/send_payment/:invoice", post(send_payment))
+        .route(
+            "/api/admin/payments/:payment_hash/attempts",
+            get(get_payment_attempts),
+        )
         .route("/api/admin/dlc_channels", get(list_dlc_channels))
         .route("/api/admin/transactions", get(list_on_chain_transactions))
+        .route(
+            "/api/admin/transactions/unconfirmed",
+            get(list_unconfirmed_transactions),
+        )
+        .route("/api/admin/transactions/:txid/bump", post(bump_fee))
+        .route("/api/admin/utxos", get(list_utxos))
+        .route("/api/admin/send_on_chain", post(send_on_chain))
+        .route("/api/admin/reserve", get(get_reserve))
+        .route("/api/admin/reserve/sweep", post(sweep_to_reserve))
+        .route("/api/admin/reserve/top_up", post(top_up_from_reserve))
+        .route("/api/admin/reserve/psbt", post(broadcast_reserve_psbt))
+        .route("/api/admin/liquidity", get(get_liquidity))
+        .route("/api/admin/liquidity/consolidate", post(consolidate_utxos))
         .route("/api/admin/sign/:msg", get(sign_message))
         .route("/api/admin/connect", post(connect_to_peer))
         .route("/api/admin/channels/revert", post(collaborative_revert))
@@ -162,13 +216,26 @@ pub fn router(
             get(get_settings).put(update_settings),
         )
         .route("/api/admin/sync", post(post_sync))
+        .route("/api/admin/reconciliation", get(get_reconciliation_report))
+        .route(
+            "/api/admin/reconciliation/repair",
+            post(repair_inconsistencies),
+        )
+        .route(
+            "/api/admin/reconciliation/actions",
+            get(list_reconciliation_actions),
+        )
         .route(
             "/api/admin/broadcast_announcement",
             post(post_broadcast_announcement),
         )
         .route("/metrics", get(get_metrics))
-        .route("/health", get(get_health))
-        .with_state(app_state)
+        .route("/health", get(get_health));
+
+    #[cfg(feature = "bolt12")]
+    let router = router.route("/api/admin/refunds", post(create_refund));
+
+    router.with_state(app_state)
 }
 
 #[derive(serde::Serialize)]
@@ -205,20 +272,24 @@ pub async fn prepare_onboarding_payment(
         ))
     })?;
 
+    let settings = app_state.settings.read().await.clone();
+
+    // Set the liquidity and the UTXOs covering it aside until the channel is funded, so that they
+    // are not promised to anyone else in the meantime. This replaces any earlier reservation of
+    // the trader, so repeated requests cannot tie up more than one channel's worth of liquidity.
+    let have_enough_liquidity = spawn_blocking({
+        let node = app_state.node.clone();
+        move || liquidity::reserve(&node, &settings, user_channel_id, target_node, amount_sats)
+    })
+    .await
+    .expect("task to complete")
+    .map_err(|e| AppError::InternalServerError(format!("Could not reserve liquidity: {e:#}")))?;
+
     let mut conn = app_state
         .pool
         .get()
         .map_err(|e| AppError::InternalServerError(format!("Could not get connection: {e:#}")))?;
 
-    let balance = app_state
-        .node
-        .inner
-        .get_on_chain_balance()
-        .map_err(|e| AppError::InternalServerError(format!("Could not get balance: {e:#}")))?;
-
-    let have_enough_liquidity =
-        is_liquidity_sufficient(&*app_state.settings.read().await, balance, amount_sats);
-
     LiquidityRequestLog::insert(
         &mut conn,
         target_node,
@@ -347,15 +418,44 @@ pub async fn get_open_channel_fee_invoice(
 // the library changes?
 #[instrument(skip_all, err(Debug))]
 #[autometrics]
-pub async fn post_trade(
+/// Creates the order-matching fee invoice the trader has to pay before requesting the trade.
+pub async fn post_trade_fee_invoice(
     State(state): State<Arc<AppState>>,
     trade_params: Json<TradeParams>,
 ) -> Result<String, AppError> {
-    let invoice = state.node.trade(&trade_params.0).await.map_err(|e| {
+    let (_, invoice) = state
+        .node
+        .fee_invoice_taker(&trade_params.0)
+        .await
+        .map_err(|e| {
+            AppError::InternalServerError(format!(
+                "Could not create order-matching fee invoice: {e:#}"
+            ))
+        })?;
+
+    Ok(invoice.to_string())
+}
+
+pub async fn post_trade(
+    State(state): State<Arc<AppState>>,
+    trade_params: Json<TradeParams>,
+) -> Result<(), AppError> {
+    state.node.trade(&trade_params.0).await.map_err(|e| {
         AppError::InternalServerError(format!("Could not handle trade request: {e:#}"))
     })?;
 
-    Ok(invoice.to_string())
+    let order_id = trade_params.filled_with.order_id;
+    match state.pool.get() {
+        Ok(mut conn) => {
+            if let Err(e) = market_data::broadcast_trades(&mut conn, order_id, &state.tx_price_feed)
+            {
+                tracing::warn!(%order_id, "Failed to publish executed trades. Error: {e:#}");
+            }
+        }
+        Err(e) => tracing::warn!(%order_id, "Failed to publish executed trades. Error: {e:#}"),
+    }
+
+    Ok(())
 }
 
 #[instrument(skip_all, err(Debug))]
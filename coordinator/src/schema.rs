@@ -74,6 +74,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    counterparty_commitments (txid) {
+        txid -> Text,
+        channel_keys_id -> Text,
+        commitment_number -> Int8,
+        raw -> Text,
+        revoked -> Bool,
+    }
+}
+
 diesel::table! {
     liquidity_options (id) {
         id -> Int4,
@@ -102,6 +112,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    liquidity_reservations (user_channel_id) {
+        user_channel_id -> Text,
+        trader_pubkey -> Text,
+        amount_sats -> Int8,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::MatchStateType;
@@ -165,6 +185,7 @@ diesel::table! {
         description -> Text,
         invoice -> Nullable<Text>,
         fee_msat -> Nullable<Int8>,
+        failure_reason -> Nullable<Text>,
     }
 }
 
@@ -198,6 +219,27 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    reconciliation_actions (id) {
+        id -> Int4,
+        trader_pubkey -> Text,
+        position_id -> Nullable<Int4>,
+        inconsistency -> Text,
+        action -> Text,
+        successful -> Bool,
+        error -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reserved_utxos (outpoint) {
+        outpoint -> Text,
+        user_channel_id -> Text,
+        amount_sats -> Int8,
+    }
+}
+
 diesel::table! {
     routing_fees (id) {
         id -> Int4,
@@ -244,6 +286,7 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         raw -> Text,
+        replaceable -> Bool,
     }
 }
 
@@ -260,17 +303,23 @@ diesel::table! {
 }
 
 diesel::joinable!(liquidity_request_logs -> liquidity_options (liquidity_option));
+diesel::joinable!(reconciliation_actions -> positions (position_id));
+diesel::joinable!(reserved_utxos -> liquidity_reservations (user_channel_id));
 diesel::joinable!(trades -> positions (position_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     channels,
     collaborative_reverts,
+    counterparty_commitments,
     liquidity_options,
     liquidity_request_logs,
+    liquidity_reservations,
     matches,
     orders,
     payments,
     positions,
+    reconciliation_actions,
+    reserved_utxos,
     routing_fees,
     spendable_outputs,
     trades,
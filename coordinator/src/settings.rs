@@ -1,4 +1,5 @@
 use crate::cli::Network;
+use crate::liquidity::LiquiditySettings;
 use crate::node::NodeSettings;
 use anyhow::Context;
 use anyhow::Result;
@@ -26,6 +27,9 @@ const ROLLOVER_CLOSE_SCHEDULE_REGTEST: &str = "0 5 22 * * *";
 /// Reminding to close an expired position every day at 12:00 UTC
 const CLOSE_EXPIRED_POSITION_SCHEDULE: &str = "0 0 12 * * *";
 
+/// The default time (in seconds) a trader has to execute a matched market order.
+const MATCH_EXECUTION_TIMEOUT_SECS: u64 = 300;
+
 /// Top-level settings.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Settings {
@@ -79,6 +83,15 @@ pub struct Settings {
 
     /// Min balance to keep in on-chain wallet at all times
     pub min_liquidity_threshold_sats: u64,
+
+    /// The time (in seconds) a trader has to execute a matched market order. Once elapsed, the
+    /// match is abandoned and the liquidity of the matched limit orders is released.
+    #[serde(default = "default_match_execution_timeout_secs")]
+    pub match_execution_timeout_secs: u64,
+
+    /// How the on-chain liquidity for JIT channels is managed.
+    #[serde(default)]
+    pub liquidity: LiquiditySettings,
 }
 
 impl Settings {
@@ -109,10 +122,16 @@ impl Settings {
             rollover_window_close_scheduler,
             close_expired_position_scheduler: CLOSE_EXPIRED_POSITION_SCHEDULE.to_string(),
             min_liquidity_threshold_sats: 10_000_000, // 0.1 BTC
+            match_execution_timeout_secs: MATCH_EXECUTION_TIMEOUT_SECS,
+            liquidity: LiquiditySettings::default(),
         }
     }
 }
 
+fn default_match_execution_timeout_secs() -> u64 {
+    MATCH_EXECUTION_TIMEOUT_SECS
+}
+
 async fn read_settings(data_dir: &Path) -> Result<Settings> {
     let settings_path = data_dir.join(SETTINGS_FILE_NAME);
     let data = fs::read_to_string(settings_path).await?;
@@ -157,6 +176,9 @@ impl Settings {
             max_allowed_tx_fee_rate_when_opening_channel: self
                 .max_allowed_tx_fee_rate_when_opening_channel,
             contract_tx_fee_rate: self.contract_tx_fee_rate,
+            match_execution_timeout_secs: self.match_execution_timeout_secs,
+            min_liquidity_threshold_sats: self.min_liquidity_threshold_sats,
+            liquidity: self.liquidity.clone(),
         }
     }
 
@@ -0,0 +1,42 @@
+use crate::AppError;
+use axum::extract::State;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use ln_dlc_node::watchtower::AddAppointmentError;
+use ln_dlc_node::watchtower::SignedAppointment;
+use ln_dlc_node::watchtower::Watchtower;
+use ln_dlc_node::watchtower::APPOINTMENTS_PATH;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::task::spawn_blocking;
+
+/// The HTTP API through which apps hand their penalty transactions to the watchtower.
+pub fn router(watchtower: Arc<Watchtower>) -> Router {
+    Router::new()
+        .route(APPOINTMENTS_PATH, post(post_appointment))
+        .with_state(watchtower)
+}
+
+pub async fn post_appointment(
+    State(watchtower): State<Arc<Watchtower>>,
+    Json(appointment): Json<SignedAppointment>,
+) -> Result<(), AppError> {
+    spawn_blocking(move || watchtower.add_appointment(appointment, OffsetDateTime::now_utc()))
+        .await
+        .expect("task to complete")
+        .map_err(|e| {
+            let msg = format!("Could not add appointment: {e:#}");
+            match e {
+                AddAppointmentError::Invalid(_) => AppError::BadRequest(msg),
+                AddAppointmentError::Unauthorized => AppError::Unauthorized(msg),
+                // The app cannot do anything about these by trying again.
+                AddAppointmentError::NotAdmitted(_) | AddAppointmentError::QuotaExceeded(_) => {
+                    AppError::Forbidden(msg)
+                }
+                AddAppointmentError::RateLimited => AppError::TooManyRequests(msg),
+                AddAppointmentError::Full => AppError::ServiceUnavailable(msg),
+                AddAppointmentError::Internal(_) => AppError::InternalServerError(msg),
+            }
+        })
+}
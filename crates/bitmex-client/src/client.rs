@@ -1,18 +1,38 @@
+use crate::models::CancelAllOrdersRequest;
+use crate::models::CancelOrderRequest;
 use crate::models::ContractSymbol;
+use crate::models::ExecInst;
+use crate::models::Execution;
+use crate::models::Funding;
+use crate::models::GetExecutionHistoryRequest;
+use crate::models::GetFundingRequest;
+use crate::models::GetInstrumentRequest;
+use crate::models::GetMarginRequest;
+use crate::models::GetOrdersRequest;
 use crate::models::GetPositionRequest;
+use crate::models::GetWalletRequest;
+use crate::models::Instrument;
+use crate::models::Margin;
 use crate::models::Network;
 use crate::models::OrdType;
 use crate::models::Order;
+use crate::models::PegPriceType;
 use crate::models::Position;
 use crate::models::PostOrderRequest;
+use crate::models::PutOrderRequest;
 use crate::models::Request;
 use crate::models::Side;
+use crate::models::TimeInForce;
+use crate::models::Wallet;
 use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
 use hex::encode as hexify;
 use reqwest;
+use reqwest::header::HeaderMap;
 use reqwest::Method;
 use reqwest::Response;
+use reqwest::StatusCode;
 use reqwest::Url;
 use ring::hmac;
 use serde::de::DeserializeOwned;
@@ -22,15 +42,67 @@ use serde_json::from_str;
 use serde_json::to_string as to_jstring;
 use serde_urlencoded::to_string as to_ustring;
 use std::ops::Add;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// The currency of all BitMEX wallets and margin accounts we use.
+const XBT: &str = "XBt";
+
+/// The maximum delay between two retries of a request.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
 
 #[derive(Clone)]
 pub struct Client {
     url: String,
     credentials: Option<Credentials>,
     client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+}
+
+/// The rate limit as reported by BitMEX with the latest response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of requests allowed per window.
+    pub limit: u32,
+    /// The number of requests left in the current window.
+    pub remaining: u32,
+    /// When `remaining` is reset to `limit`.
+    pub reset: OffsetDateTime,
+}
+
+/// How requests are retried if BitMEX is overloaded or we are rate limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of retries of a single request.
+    pub max_retries: u32,
+    /// How long to wait before the first retry. The delay doubles with every retry, up to 30
+    /// seconds, unless BitMEX tells us how long to wait.
+    pub initial_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// How long to wait before retrying a request which has already been retried `retries` times.
+    fn backoff(&self, retries: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2_u32.saturating_pow(retries))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+        }
+    }
 }
 
 impl Client {
@@ -39,6 +111,8 @@ impl Client {
             client: reqwest::Client::new(),
             url: network.to_url(),
             credentials: None,
+            retry_policy: RetryPolicy::default(),
+            rate_limit: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -49,10 +123,30 @@ impl Client {
         }
     }
 
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
+    /// Send requests to `url` instead of BitMEX, e.g. to a mock server.
+    pub fn with_url(self, url: impl ToString) -> Self {
+        Self {
+            url: url.to_string(),
+            ..self
+        }
+    }
+
     pub fn is_signed_in(&self) -> bool {
         self.credentials.is_some()
     }
 
+    /// The rate limit as reported with the latest response, if any.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().expect("lock not to be poisoned")
+    }
+
     pub async fn create_order(
         &self,
         symbol: ContractSymbol,
@@ -60,24 +154,226 @@ impl Client {
         side: Side,
         text: Option<String>,
     ) -> Result<Order> {
-        let order = self
-            .send_request(PostOrderRequest {
-                symbol,
-                side: Some(side),
-                order_qty: Some(quantity),
-                ord_type: Some(OrdType::Market),
-                text,
+        self.place_order(PostOrderRequest {
+            text,
+            ..PostOrderRequest::new(symbol, side, quantity, OrdType::Market)
+        })
+        .await
+    }
+
+    /// Create a limit order, which is `GoodTillCancel` unless specified otherwise.
+    pub async fn create_limit_order(
+        &self,
+        symbol: ContractSymbol,
+        quantity: i32,
+        side: Side,
+        price: f64,
+        time_in_force: Option<TimeInForce>,
+        text: Option<String>,
+    ) -> Result<Order> {
+        self.place_order(PostOrderRequest {
+            price: Some(price),
+            time_in_force,
+            text,
+            ..PostOrderRequest::new(symbol, side, quantity, OrdType::Limit)
+        })
+        .await
+    }
+
+    /// Create a limit order which is only added to the orderbook as a maker order.
+    ///
+    /// If the order would be filled immediately, BitMEX cancels it instead.
+    pub async fn create_post_only_order(
+        &self,
+        symbol: ContractSymbol,
+        quantity: i32,
+        side: Side,
+        price: f64,
+        text: Option<String>,
+    ) -> Result<Order> {
+        self.place_order(PostOrderRequest {
+            price: Some(price),
+            exec_inst: Some(ExecInst::ParticipateDoNotInitiate),
+            text,
+            ..PostOrderRequest::new(symbol, side, quantity, OrdType::Limit)
+        })
+        .await
+    }
+
+    /// Create a stop order which is triggered once the price reaches `stop_price`.
+    ///
+    /// Once triggered, the order is executed as a market order, or as a limit order at
+    /// `limit_price` if given.
+    pub async fn create_stop_order(
+        &self,
+        symbol: ContractSymbol,
+        quantity: i32,
+        side: Side,
+        stop_price: f64,
+        limit_price: Option<f64>,
+        text: Option<String>,
+    ) -> Result<Order> {
+        let ord_type = match limit_price {
+            Some(_) => OrdType::StopLimit,
+            None => OrdType::Stop,
+        };
+
+        self.place_order(PostOrderRequest {
+            price: limit_price,
+            stop_px: Some(stop_price),
+            text,
+            ..PostOrderRequest::new(symbol, side, quantity, ord_type)
+        })
+        .await
+    }
+
+    /// Create an order whose price follows the price given by `peg_price_type`, e.g. the best bid
+    /// or ask for `PrimaryPeg`, offset by `peg_offset`.
+    pub async fn create_pegged_order(
+        &self,
+        symbol: ContractSymbol,
+        quantity: i32,
+        side: Side,
+        peg_price_type: PegPriceType,
+        peg_offset: f64,
+        text: Option<String>,
+    ) -> Result<Order> {
+        self.place_order(PostOrderRequest {
+            peg_price_type: Some(peg_price_type),
+            peg_offset_value: Some(peg_offset),
+            text,
+            ..PostOrderRequest::new(symbol, side, quantity, OrdType::Pegged)
+        })
+        .await
+    }
+
+    /// Place an order with any combination of parameters supported by BitMEX.
+    pub async fn place_order(&self, order: PostOrderRequest) -> Result<Order> {
+        let order = self.send_request(order).await?;
+        Ok(order)
+    }
+
+    /// Amend the quantity or price of an open order.
+    pub async fn amend_order(&self, amendment: PutOrderRequest) -> Result<Order> {
+        let order = self.send_request(amendment).await?;
+        Ok(order)
+    }
+
+    /// Cancel an order, returning its final state.
+    ///
+    /// Cancelling an order which has already been filled is not an error; the filled order is
+    /// returned instead.
+    pub async fn cancel_order(&self, order_id: Uuid) -> Result<Order> {
+        let orders = self.send_request(CancelOrderRequest { order_id }).await?;
+        let order = orders
+            .into_iter()
+            .next()
+            .with_context(|| format!("No order returned when cancelling {order_id}"))?;
+        Ok(order)
+    }
+
+    /// Cancel all open orders, optionally only those for `symbol`, returning the cancelled orders.
+    pub async fn cancel_all_orders(&self, symbol: Option<ContractSymbol>) -> Result<Vec<Order>> {
+        let orders = self
+            .send_request(CancelAllOrdersRequest { symbol, text: None })
+            .await?;
+        Ok(orders)
+    }
+
+    /// Retrieve the current state of an order.
+    pub async fn get_order(&self, order_id: Uuid) -> Result<Order> {
+        let orders = self
+            .send_request(GetOrdersRequest {
+                filter: Some(format!(r#"{{"orderID":"{order_id}"}}"#)),
+                count: Some(1),
+                ..GetOrdersRequest::default()
             })
             .await?;
+        let order = orders
+            .into_iter()
+            .next()
+            .with_context(|| format!("Unknown order {order_id}"))?;
         Ok(order)
     }
 
+    /// Retrieve all open orders, optionally only those for `symbol`.
+    pub async fn open_orders(&self, symbol: Option<ContractSymbol>) -> Result<Vec<Order>> {
+        let orders = self
+            .send_request(GetOrdersRequest {
+                symbol,
+                filter: Some(r#"{"open":true}"#.to_string()),
+                ..GetOrdersRequest::default()
+            })
+            .await?;
+        Ok(orders)
+    }
+
+    /// Retrieve the executions which affected our balance, i.e. trades and funding payments.
+    pub async fn execution_history(
+        &self,
+        request: GetExecutionHistoryRequest,
+    ) -> Result<Vec<Execution>> {
+        let executions = self.send_request(request).await?;
+        Ok(executions)
+    }
+
+    /// Retrieve our XBT wallet.
+    pub async fn wallet(&self) -> Result<Wallet> {
+        let wallet = self
+            .send_request(GetWalletRequest {
+                currency: XBT.to_string(),
+            })
+            .await?;
+        Ok(wallet)
+    }
+
+    /// Retrieve the margin status of our XBT account.
+    pub async fn margin(&self) -> Result<Margin> {
+        let margin = self
+            .send_request(GetMarginRequest {
+                currency: XBT.to_string(),
+            })
+            .await?;
+        Ok(margin)
+    }
+
+    /// Retrieve the latest `count` funding rates of `symbol`, newest first.
+    pub async fn funding_history(
+        &self,
+        symbol: ContractSymbol,
+        count: Option<u32>,
+    ) -> Result<Vec<Funding>> {
+        let funding = self
+            .send_request(GetFundingRequest {
+                symbol,
+                count,
+                reverse: Some(true),
+            })
+            .await?;
+        Ok(funding)
+    }
+
+    /// Retrieve the metadata of `symbol`, e.g. its tick size and fees.
+    pub async fn instrument(&self, symbol: ContractSymbol) -> Result<Instrument> {
+        let instruments = self.send_request(GetInstrumentRequest { symbol }).await?;
+        let instrument = instruments
+            .into_iter()
+            .next()
+            .with_context(|| format!("Unknown instrument {symbol:?}"))?;
+        Ok(instrument)
+    }
+
     /// Retrieve the position information for all contract symbols.
     pub async fn positions(&self) -> Result<Vec<Position>> {
         let positions = self.send_request(GetPositionRequest).await?;
         Ok(positions)
     }
 
+    /// Send the request, retrying according to the [`RetryPolicy`] if BitMEX is overloaded or we
+    /// are rate limited.
+    ///
+    /// Requests which are not idempotent are not retried after connection errors, as they may
+    /// have been processed by BitMEX.
     async fn send_request<R>(&self, req: R) -> Result<R::Response>
     where
         R: Request,
@@ -95,6 +391,46 @@ impl Client {
             _ => "".to_string(),
         };
 
+        let mut retries = 0;
+        loop {
+            self.wait_for_rate_limit().await;
+
+            let result = self.request::<R>(&url, &body)?.send().await;
+
+            let backoff = self.retry_policy.backoff(retries);
+            let can_retry = retries < self.retry_policy.max_retries;
+
+            let resp = match result {
+                Ok(resp) => resp,
+                Err(_) if can_retry && R::METHOD == Method::GET => {
+                    retries += 1;
+                    tokio::time::sleep(backoff).await;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            self.update_rate_limit(resp.headers());
+
+            let status = resp.status();
+            if can_retry
+                && matches!(
+                    status,
+                    StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+                )
+            {
+                retries += 1;
+                tokio::time::sleep(retry_after(resp.headers()).unwrap_or(backoff)).await;
+                continue;
+            }
+
+            let response = self.handle_response(resp).await?;
+
+            return Ok(response);
+        }
+    }
+
+    fn request<R: Request>(&self, url: &Url, body: &str) -> Result<reqwest::RequestBuilder> {
         let mut builder = self.client.request(R::METHOD, url.clone());
 
         if R::SIGNED {
@@ -111,22 +447,39 @@ impl Client {
                 .expect("Time went backwards")
                 .add(Duration::from_secs(5))
                 .as_secs();
-            let (key, signature) = credentials.signature(R::METHOD, expires, &url, &body);
+            let (key, signature) = credentials.signature(R::METHOD, expires, url, body);
             builder = builder
                 .header("api-expires", expires)
                 .header("api-key", key)
                 .header("api-signature", signature)
         }
 
-        let resp = builder
+        Ok(builder
             .header("content-type", "application/json")
-            .body(body)
-            .send()
-            .await?;
+            .body(body.to_string()))
+    }
+
+    /// Waits until the rate limit is reset if there are no requests left in the current window.
+    async fn wait_for_rate_limit(&self) {
+        let rate_limit = self.rate_limit();
 
-        let response = self.handle_response(resp).await?;
+        if let Some(RateLimit {
+            remaining: 0,
+            reset,
+            ..
+        }) = rate_limit
+        {
+            // Fails if the reset is in the past, in which case we don't have to wait.
+            if let Ok(wait) = Duration::try_from(reset - OffsetDateTime::now_utc()) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
 
-        Ok(response)
+    fn update_rate_limit(&self, headers: &HeaderMap) {
+        if let Some(rate_limit) = rate_limit(headers) {
+            *self.rate_limit.lock().expect("lock not to be poisoned") = Some(rate_limit);
+        }
     }
 
     async fn handle_response<T: DeserializeOwned>(&self, resp: Response) -> Result<T> {
@@ -150,6 +503,23 @@ impl Client {
     }
 }
 
+/// The rate limit reported in the `x-ratelimit-*` headers of a response.
+fn rate_limit(headers: &HeaderMap) -> Option<RateLimit> {
+    let header = |name: &str| headers.get(name)?.to_str().ok()?.parse::<i64>().ok();
+
+    Some(RateLimit {
+        limit: header("x-ratelimit-limit")?.try_into().ok()?,
+        remaining: header("x-ratelimit-remaining")?.try_into().ok()?,
+        reset: OffsetDateTime::from_unix_timestamp(header("x-ratelimit-reset")?).ok()?,
+    })
+}
+
+/// How long BitMEX asks us to wait before sending the next request.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers.get("retry-after")?.to_str().ok()?.parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
 #[derive(Clone)]
 struct Credentials {
     api_key: String,
@@ -187,9 +557,25 @@ impl Credentials {
 #[cfg(test)]
 mod test {
     use super::Credentials;
+    use super::RetryPolicy;
+    use super::MAX_BACKOFF;
     use anyhow::Result;
     use reqwest::Method;
     use reqwest::Url;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_doubles_up_to_maximum() {
+        let retry_policy = RetryPolicy {
+            max_retries: u32::MAX,
+            initial_backoff: Duration::from_millis(500),
+        };
+
+        assert_eq!(retry_policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(retry_policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(retry_policy.backoff(10), MAX_BACKOFF);
+        assert_eq!(retry_policy.backoff(u32::MAX), MAX_BACKOFF);
+    }
 
     #[test]
     fn test_signature_get() -> Result<()> {
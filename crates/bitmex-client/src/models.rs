@@ -51,6 +51,12 @@ pub struct Order {
     pub ord_type: Option<OrdType>,
     #[serde(rename = "ordStatus")]
     pub ord_status: Option<OrderStatus>,
+    /// The number of contracts filled so far.
+    #[serde(rename = "cumQty")]
+    pub cum_qty: Option<i64>,
+    /// The average price of the filled contracts.
+    #[serde(rename = "avgPx")]
+    pub avg_px: Option<f64>,
     pub text: Option<String>,
     #[serde(rename = "transactTime", with = "time::serde::rfc3339::option")]
     pub transact_time: Option<OffsetDateTime>,
@@ -71,6 +77,9 @@ pub enum OrderStatus {
     Filled,
     Open,
     New,
+    PartiallyFilled,
+    Canceled,
+    Rejected,
     #[serde(other)]
     Unknown,
 }
@@ -109,6 +118,8 @@ pub enum OrdType {
     LimitIfTouched,
     MarketWithLeftOverAsLimit,
     Pegged,
+    #[serde(other)]
+    Unknown, // e.g. 'Funding' for funding executions
 }
 
 /// https://www.onixs.biz/fix-dictionary/5.0.SP2/tagNum_59.html
@@ -156,6 +167,7 @@ pub struct PostOrderRequest {
     /// Instrument symbol. e.g. 'XBTUSD'.
     pub symbol: ContractSymbol,
     /// Order side. Valid options: Buy, Sell. Defaults to 'Buy' unless `orderQty` is negative.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub side: Option<Side>,
     /// Order quantity in units of the instrument (i.e. contracts).
     #[serde(rename = "orderQty", skip_serializing_if = "Option::is_none")]
@@ -165,11 +177,57 @@ pub struct PostOrderRequest {
     /// specified. Defaults to 'StopLimit' when `price` and `stopPx` are specified.
     #[serde(rename = "ordType", skip_serializing_if = "Option::is_none")]
     pub ord_type: Option<OrdType>,
+    /// Optional limit price for 'Limit', 'StopLimit', and 'LimitIfTouched' orders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    /// Optional trigger price for 'Stop', 'StopLimit', 'MarketIfTouched', and 'LimitIfTouched'
+    /// orders.
+    #[serde(rename = "stopPx", skip_serializing_if = "Option::is_none")]
+    pub stop_px: Option<f64>,
+    /// Optional trailing offset from the current price for 'Stop', 'StopLimit',
+    /// 'MarketIfTouched', and 'LimitIfTouched' orders; use a negative offset for stop-sell orders
+    /// and buy-if-touched orders. Optional offset from the peg price for 'Pegged' orders.
+    #[serde(rename = "pegOffsetValue", skip_serializing_if = "Option::is_none")]
+    pub peg_offset_value: Option<f64>,
+    /// Optional peg price type. Valid options: MarketPeg, PrimaryPeg, TrailingStopPeg.
+    #[serde(rename = "pegPriceType", skip_serializing_if = "Option::is_none")]
+    pub peg_price_type: Option<PegPriceType>,
+    /// Time in force. Valid options: Day, GoodTillCancel, ImmediateOrCancel, FillOrKill. Defaults
+    /// to 'GoodTillCancel' for 'Limit', 'StopLimit', and 'LimitIfTouched' orders.
+    #[serde(rename = "timeInForce", skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<TimeInForce>,
+    /// Optional execution instructions. e.g. 'ParticipateDoNotInitiate' for post-only orders.
+    #[serde(rename = "execInst", skip_serializing_if = "Option::is_none")]
+    pub exec_inst: Option<ExecInst>,
+    /// Optional client order ID. This clOrdID will come back on the order and any related
+    /// executions.
+    #[serde(rename = "clOrdID", skip_serializing_if = "Option::is_none")]
+    pub cl_ord_id: Option<String>,
     /// Optional order annotation. e.g. 'Take profit'.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
 }
 
+impl PostOrderRequest {
+    /// A request for an order of the given type without any of the optional parameters.
+    pub fn new(symbol: ContractSymbol, side: Side, order_qty: i32, ord_type: OrdType) -> Self {
+        Self {
+            symbol,
+            side: Some(side),
+            order_qty: Some(order_qty),
+            ord_type: Some(ord_type),
+            price: None,
+            stop_px: None,
+            peg_offset_value: None,
+            peg_price_type: None,
+            time_in_force: None,
+            exec_inst: None,
+            cl_ord_id: None,
+            text: None,
+        }
+    }
+}
+
 impl Request for PostOrderRequest {
     const METHOD: Method = Method::POST;
     const SIGNED: bool = true;
@@ -178,6 +236,342 @@ impl Request for PostOrderRequest {
     type Response = Order;
 }
 
+/// Cancel an order.
+#[derive(Clone, Debug, Serialize)]
+pub struct CancelOrderRequest {
+    /// Order ID as assigned by BitMEX.
+    #[serde(rename = "orderID")]
+    pub order_id: Uuid,
+}
+
+impl Request for CancelOrderRequest {
+    const METHOD: Method = Method::DELETE;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/order";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Vec<Order>;
+}
+
+/// Amend the quantity or price of an open order.
+#[derive(Clone, Debug, Serialize)]
+pub struct PutOrderRequest {
+    /// Order ID as assigned by BitMEX.
+    #[serde(rename = "orderID")]
+    pub order_id: Uuid,
+    /// Optional order quantity in units of the instrument (i.e. contracts).
+    #[serde(rename = "orderQty", skip_serializing_if = "Option::is_none")]
+    pub order_qty: Option<i32>,
+    /// Optional leaves quantity in units of the instrument (i.e. contracts). Useful for amending
+    /// partially filled orders.
+    #[serde(rename = "leavesQty", skip_serializing_if = "Option::is_none")]
+    pub leaves_qty: Option<i32>,
+    /// Optional limit price for 'Limit', 'StopLimit', and 'LimitIfTouched' orders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    /// Optional trigger price for 'Stop', 'StopLimit', 'MarketIfTouched', and 'LimitIfTouched'
+    /// orders.
+    #[serde(rename = "stopPx", skip_serializing_if = "Option::is_none")]
+    pub stop_px: Option<f64>,
+    /// Optional trailing offset from the current price for 'Stop', 'StopLimit',
+    /// 'MarketIfTouched', and 'LimitIfTouched' orders. Optional offset from the peg price for
+    /// 'Pegged' orders.
+    #[serde(rename = "pegOffsetValue", skip_serializing_if = "Option::is_none")]
+    pub peg_offset_value: Option<f64>,
+    /// Optional amend annotation. e.g. 'Adjust skew'.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl PutOrderRequest {
+    /// A request which does not change anything about the order yet.
+    pub fn new(order_id: Uuid) -> Self {
+        Self {
+            order_id,
+            order_qty: None,
+            leaves_qty: None,
+            price: None,
+            stop_px: None,
+            peg_offset_value: None,
+            text: None,
+        }
+    }
+}
+
+impl Request for PutOrderRequest {
+    const METHOD: Method = Method::PUT;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/order";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Order;
+}
+
+/// Cancel all of your orders.
+#[derive(Clone, Debug, Serialize)]
+pub struct CancelAllOrdersRequest {
+    /// Optional symbol. If provided, only cancels orders for that symbol.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<ContractSymbol>,
+    /// Optional cancellation annotation. e.g. 'Spread Exceeded'.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl Request for CancelAllOrdersRequest {
+    const METHOD: Method = Method::DELETE;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/order/all";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Vec<Order>;
+}
+
+/// Get your orders.
+#[derive(Clone, Debug, Serialize, Default)]
+pub struct GetOrdersRequest {
+    /// Optional instrument symbol.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<ContractSymbol>,
+    /// Generic table filter as JSON, e.g. `{"open": true}` for open orders only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// Number of results to fetch. Must be a positive integer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    /// If true, will sort results newest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverse: Option<bool>,
+}
+
+impl Request for GetOrdersRequest {
+    const METHOD: Method = Method::GET;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/order";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Vec<Order>;
+}
+
+/// Get all balance-affecting executions, i.e. trades and funding payments.
+#[derive(Clone, Debug, Serialize, Default)]
+pub struct GetExecutionHistoryRequest {
+    /// Optional instrument symbol.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<ContractSymbol>,
+    /// Number of results to fetch. Must be a positive integer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    /// Starting point for results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<u32>,
+    /// If true, will sort results newest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverse: Option<bool>,
+    /// Starting date filter for results.
+    #[serde(
+        rename = "startTime",
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_time: Option<OffsetDateTime>,
+    /// Ending date filter for results.
+    #[serde(
+        rename = "endTime",
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end_time: Option<OffsetDateTime>,
+}
+
+impl Request for GetExecutionHistoryRequest {
+    const METHOD: Method = Method::GET;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/execution/tradeHistory";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Vec<Execution>;
+}
+
+/// A raw execution, e.g. a (partial) fill of an order or a funding payment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Execution {
+    #[serde(rename = "execID")]
+    pub exec_id: Uuid,
+    #[serde(rename = "orderID")]
+    pub order_id: Uuid,
+    #[serde(rename = "clOrdID")]
+    pub cl_ord_id: Option<String>,
+    pub account: Option<i64>,
+    pub symbol: Option<String>,
+    pub side: Option<Side>,
+    /// The number of contracts filled by this execution.
+    #[serde(rename = "lastQty")]
+    pub last_qty: Option<i64>,
+    /// The price of this execution.
+    #[serde(rename = "lastPx")]
+    pub last_px: Option<f64>,
+    #[serde(rename = "ordType")]
+    pub ord_type: Option<OrdType>,
+    #[serde(rename = "ordStatus")]
+    pub ord_status: Option<OrderStatus>,
+    #[serde(rename = "execType")]
+    pub exec_type: Option<ExecType>,
+    /// The fee rate, negative for rebates.
+    pub commission: Option<f64>,
+    /// The fee (or funding) paid in satoshis, negative if received.
+    #[serde(rename = "execComm")]
+    pub exec_comm: Option<i64>,
+    pub text: Option<String>,
+    #[serde(rename = "transactTime", with = "time::serde::rfc3339::option")]
+    pub transact_time: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+/// Get your current wallet information.
+#[derive(Clone, Debug, Serialize)]
+pub struct GetWalletRequest {
+    /// The currency of the wallet, e.g. 'XBt'.
+    pub currency: String,
+}
+
+impl Request for GetWalletRequest {
+    const METHOD: Method = Method::GET;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/user/wallet";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Wallet;
+}
+
+/// All amounts are denominated in the wallet's currency, i.e. satoshis for 'XBt'.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Wallet {
+    pub account: i64,
+    pub currency: String,
+    pub amount: Option<i64>,
+    pub deposited: Option<i64>,
+    pub withdrawn: Option<i64>,
+    #[serde(rename = "transferIn")]
+    pub transfer_in: Option<i64>,
+    #[serde(rename = "transferOut")]
+    pub transfer_out: Option<i64>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+/// Get your account's margin status.
+#[derive(Clone, Debug, Serialize)]
+pub struct GetMarginRequest {
+    /// The currency of the margin account, e.g. 'XBt'.
+    pub currency: String,
+}
+
+impl Request for GetMarginRequest {
+    const METHOD: Method = Method::GET;
+    const SIGNED: bool = true;
+    const ENDPOINT: &'static str = "/user/margin";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Margin;
+}
+
+/// All amounts are denominated in the account's currency, i.e. satoshis for 'XBt'.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Margin {
+    pub account: i64,
+    pub currency: String,
+    #[serde(rename = "walletBalance")]
+    pub wallet_balance: Option<i64>,
+    #[serde(rename = "marginBalance")]
+    pub margin_balance: Option<i64>,
+    #[serde(rename = "availableMargin")]
+    pub available_margin: Option<i64>,
+    #[serde(rename = "initMargin")]
+    pub init_margin: Option<i64>,
+    #[serde(rename = "maintMargin")]
+    pub maint_margin: Option<i64>,
+    #[serde(rename = "realisedPnl")]
+    pub realised_pnl: Option<i64>,
+    #[serde(rename = "unrealisedPnl")]
+    pub unrealised_pnl: Option<i64>,
+    #[serde(rename = "marginLeverage")]
+    pub margin_leverage: Option<f64>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+/// Get the funding history of an instrument.
+#[derive(Clone, Debug, Serialize)]
+pub struct GetFundingRequest {
+    pub symbol: ContractSymbol,
+    /// Number of results to fetch. Must be a positive integer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    /// If true, will sort results newest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverse: Option<bool>,
+}
+
+impl Request for GetFundingRequest {
+    const METHOD: Method = Method::GET;
+    const ENDPOINT: &'static str = "/funding";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Vec<Funding>;
+}
+
+/// A funding rate applied to all open positions of an instrument.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Funding {
+    pub symbol: ContractSymbol,
+    #[serde(rename = "fundingRate")]
+    pub funding_rate: f64,
+    #[serde(rename = "fundingRateDaily")]
+    pub funding_rate_daily: Option<f64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// Get the metadata of an instrument.
+#[derive(Clone, Debug, Serialize)]
+pub struct GetInstrumentRequest {
+    pub symbol: ContractSymbol,
+}
+
+impl Request for GetInstrumentRequest {
+    const METHOD: Method = Method::GET;
+    const ENDPOINT: &'static str = "/instrument";
+    const HAS_PAYLOAD: bool = true;
+    type Response = Vec<Instrument>;
+}
+
+/// Tradeable contract metadata.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Instrument {
+    pub symbol: ContractSymbol,
+    pub state: Option<String>,
+    #[serde(rename = "isInverse")]
+    pub is_inverse: Option<bool>,
+    #[serde(rename = "tickSize")]
+    pub tick_size: Option<f64>,
+    #[serde(rename = "lotSize")]
+    pub lot_size: Option<i64>,
+    #[serde(rename = "maxOrderQty")]
+    pub max_order_qty: Option<i64>,
+    #[serde(rename = "maxPrice")]
+    pub max_price: Option<f64>,
+    #[serde(rename = "makerFee")]
+    pub maker_fee: Option<f64>,
+    #[serde(rename = "takerFee")]
+    pub taker_fee: Option<f64>,
+    #[serde(rename = "fundingRate")]
+    pub funding_rate: Option<f64>,
+    #[serde(rename = "indicativeFundingRate")]
+    pub indicative_funding_rate: Option<f64>,
+    #[serde(rename = "fundingTimestamp", with = "time::serde::rfc3339::option")]
+    pub funding_timestamp: Option<OffsetDateTime>,
+    #[serde(rename = "markPrice")]
+    pub mark_price: Option<f64>,
+    #[serde(rename = "lastPrice")]
+    pub last_price: Option<f64>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Hash, Eq)]
 pub enum ContractSymbol {
     #[serde(rename = "XBTUSD")]
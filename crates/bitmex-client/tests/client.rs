@@ -0,0 +1,515 @@
+//! Tests of the BitMEX client against a mock server replaying responses recorded from the BitMEX
+//! testnet.
+
+use bitmex_client::client::Client;
+use bitmex_client::client::RetryPolicy;
+use bitmex_client::models::ContractSymbol;
+use bitmex_client::models::ExecType;
+use bitmex_client::models::GetExecutionHistoryRequest;
+use bitmex_client::models::OrdType;
+use bitmex_client::models::OrderStatus;
+use bitmex_client::models::PegPriceType;
+use bitmex_client::models::PutOrderRequest;
+use bitmex_client::models::Side;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn create_limit_order() {
+    let server = MockServer::start(vec![MockResponse::ok(include_str!(
+        "fixtures/order_limit.json"
+    ))])
+    .await;
+
+    let order = server
+        .client()
+        .create_limit_order(
+            ContractSymbol::XbtUsd,
+            100,
+            Side::Buy,
+            27000.5,
+            None,
+            Some("hedge".to_string()),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(order.ord_type, Some(OrdType::Limit));
+    assert_eq!(order.ord_status, Some(OrderStatus::New));
+    assert_eq!(order.price, Some(27000.5));
+
+    let request = server.request(0);
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.target, "/api/v1/order");
+    assert!(request.header("api-signature").is_some());
+    assert_eq!(
+        request.json(),
+        serde_json::json!({
+            "symbol": "XBTUSD",
+            "side": "Buy",
+            "orderQty": 100,
+            "ordType": "Limit",
+            "price": 27000.5,
+            "text": "hedge",
+        })
+    );
+}
+
+#[tokio::test]
+async fn create_stop_and_pegged_orders() {
+    let server = MockServer::start(vec![
+        MockResponse::ok(include_str!("fixtures/order_limit.json")),
+        MockResponse::ok(include_str!("fixtures/order_limit.json")),
+    ])
+    .await;
+    let client = server.client();
+
+    client
+        .create_stop_order(ContractSymbol::XbtUsd, 100, Side::Sell, 26000.0, None, None)
+        .await
+        .unwrap();
+    client
+        .create_pegged_order(
+            ContractSymbol::XbtUsd,
+            100,
+            Side::Buy,
+            PegPriceType::PrimaryPeg,
+            -0.5,
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.request(0).json(),
+        serde_json::json!({
+            "symbol": "XBTUSD",
+            "side": "Sell",
+            "orderQty": 100,
+            "ordType": "Stop",
+            "stopPx": 26000.0,
+        })
+    );
+    assert_eq!(
+        server.request(1).json(),
+        serde_json::json!({
+            "symbol": "XBTUSD",
+            "side": "Buy",
+            "orderQty": 100,
+            "ordType": "Pegged",
+            "pegOffsetValue": -0.5,
+            "pegPriceType": "PrimaryPeg",
+        })
+    );
+}
+
+#[tokio::test]
+async fn amend_order() {
+    let server = MockServer::start(vec![MockResponse::ok(include_str!(
+        "fixtures/order_amend.json"
+    ))])
+    .await;
+    let order_id = Uuid::parse_str("9f2a57a4-4e49-4c1c-9d3b-6f0c3f0b4a11").unwrap();
+
+    let order = server
+        .client()
+        .amend_order(PutOrderRequest {
+            order_qty: Some(200),
+            price: Some(27010.0),
+            ..PutOrderRequest::new(order_id)
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(order.order_qty, Some(200));
+    assert_eq!(order.price, Some(27010.0));
+
+    let request = server.request(0);
+    assert_eq!(request.method, "PUT");
+    assert_eq!(
+        request.json(),
+        serde_json::json!({
+            "orderID": order_id,
+            "orderQty": 200,
+            "price": 27010.0,
+        })
+    );
+}
+
+#[tokio::test]
+async fn cancel_all_orders() {
+    let server = MockServer::start(vec![MockResponse::ok(include_str!(
+        "fixtures/orders_cancel_all.json"
+    ))])
+    .await;
+
+    let orders = server
+        .client()
+        .cancel_all_orders(Some(ContractSymbol::XbtUsd))
+        .await
+        .unwrap();
+
+    assert_eq!(orders.len(), 2);
+    assert!(orders
+        .iter()
+        .all(|order| order.ord_status == Some(OrderStatus::Canceled)));
+    assert_eq!(orders[1].cum_qty, Some(100));
+
+    let request = server.request(0);
+    assert_eq!(request.method, "DELETE");
+    assert_eq!(request.target, "/api/v1/order/all?symbol=XBTUSD");
+}
+
+#[tokio::test]
+async fn open_orders() {
+    let server = MockServer::start(vec![MockResponse::ok(include_str!(
+        "fixtures/orders_open.json"
+    ))])
+    .await;
+
+    let orders = server.client().open_orders(None).await.unwrap();
+
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].ord_type, Some(OrdType::Stop));
+    assert_eq!(orders[1].ord_type, Some(OrdType::Pegged));
+
+    let request = server.request(0);
+    assert_eq!(request.method, "GET");
+    assert_eq!(
+        request.target,
+        "/api/v1/order?filter=%7B%22open%22%3Atrue%7D"
+    );
+}
+
+#[tokio::test]
+async fn execution_history_contains_trades_and_funding() {
+    let server = MockServer::start(vec![MockResponse::ok(include_str!(
+        "fixtures/executions.json"
+    ))])
+    .await;
+
+    let executions = server
+        .client()
+        .execution_history(GetExecutionHistoryRequest {
+            symbol: Some(ContractSymbol::XbtUsd),
+            count: Some(100),
+            ..GetExecutionHistoryRequest::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(executions.len(), 2);
+    assert_eq!(executions[0].exec_type, Some(ExecType::Trade));
+    assert_eq!(executions[0].last_qty, Some(100));
+    assert_eq!(executions[0].exec_comm, Some(74));
+    assert_eq!(executions[1].exec_type, Some(ExecType::Funding));
+    assert_eq!(executions[1].ord_type, Some(OrdType::Unknown));
+
+    assert_eq!(
+        server.request(0).target,
+        "/api/v1/execution/tradeHistory?symbol=XBTUSD&count=100"
+    );
+}
+
+#[tokio::test]
+async fn wallet_and_margin() {
+    let server = MockServer::start(vec![
+        MockResponse::ok(include_str!("fixtures/wallet.json")),
+        MockResponse::ok(include_str!("fixtures/margin.json")),
+    ])
+    .await;
+    let client = server.client();
+
+    let wallet = client.wallet().await.unwrap();
+    let margin = client.margin().await.unwrap();
+
+    assert_eq!(wallet.amount, Some(9876543));
+    assert_eq!(margin.available_margin, Some(9872529));
+    assert_eq!(margin.unrealised_pnl, Some(-2051));
+
+    assert_eq!(server.request(0).target, "/api/v1/user/wallet?currency=XBt");
+    assert_eq!(server.request(1).target, "/api/v1/user/margin?currency=XBt");
+}
+
+#[tokio::test]
+async fn funding_history_and_instrument_are_public() {
+    let server = MockServer::start(vec![
+        MockResponse::ok(include_str!("fixtures/funding.json")),
+        MockResponse::ok(include_str!("fixtures/instrument.json")),
+    ])
+    .await;
+    let client = Client::new(bitmex_client::models::Network::Testnet).with_url(server.url());
+
+    let funding = client
+        .funding_history(ContractSymbol::XbtUsd, Some(2))
+        .await
+        .unwrap();
+    let instrument = client.instrument(ContractSymbol::XbtUsd).await.unwrap();
+
+    assert_eq!(funding.len(), 2);
+    assert_eq!(funding[1].funding_rate, -0.000025);
+    assert_eq!(instrument.tick_size, Some(0.5));
+    assert_eq!(instrument.lot_size, Some(100));
+    assert_eq!(instrument.is_inverse, Some(true));
+
+    assert!(server.request(0).header("api-signature").is_none());
+    assert_eq!(
+        server.request(0).target,
+        "/api/v1/funding?symbol=XBTUSD&count=2&reverse=true"
+    );
+}
+
+#[tokio::test]
+async fn rate_limit_is_tracked() {
+    let server = MockServer::start(vec![MockResponse::ok(include_str!("fixtures/wallet.json"))
+        .with_header("x-ratelimit-limit", "120")
+        .with_header("x-ratelimit-remaining", "119")
+        .with_header("x-ratelimit-reset", "1697796000")])
+    .await;
+    let client = server.client();
+
+    assert_eq!(client.rate_limit(), None);
+
+    client.wallet().await.unwrap();
+
+    let rate_limit = client.rate_limit().unwrap();
+    assert_eq!(rate_limit.limit, 120);
+    assert_eq!(rate_limit.remaining, 119);
+    assert_eq!(rate_limit.reset.unix_timestamp(), 1697796000);
+}
+
+#[tokio::test]
+async fn retries_when_overloaded_or_rate_limited() {
+    let server = MockServer::start(vec![
+        MockResponse::new(503, include_str!("fixtures/error_overloaded.json")),
+        MockResponse::new(429, include_str!("fixtures/error_rate_limited.json"))
+            .with_header("retry-after", "0"),
+        MockResponse::ok(include_str!("fixtures/order_limit.json")),
+    ])
+    .await;
+
+    let order = server
+        .client()
+        .create_limit_order(ContractSymbol::XbtUsd, 100, Side::Buy, 27000.5, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(order.ord_status, Some(OrderStatus::New));
+    assert_eq!(server.requests().len(), 3);
+
+    // Every attempt is signed anew.
+    assert!(server
+        .requests()
+        .iter()
+        .all(|request| request.header("api-signature").is_some()));
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let server = MockServer::start(vec![
+        MockResponse::new(503, include_str!("fixtures/error_overloaded.json")),
+        MockResponse::new(503, include_str!("fixtures/error_overloaded.json")),
+    ])
+    .await;
+    let client = server.client().with_retry_policy(RetryPolicy {
+        max_retries: 1,
+        initial_backoff: Duration::from_millis(1),
+    });
+
+    let error = client.wallet().await.unwrap_err();
+
+    assert!(error.to_string().contains("overloaded"), "{error:#}");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn error_is_reported() {
+    let server = MockServer::start(vec![MockResponse::new(
+        400,
+        include_str!("fixtures/error_invalid_price.json"),
+    )])
+    .await;
+
+    let error = server
+        .client()
+        .create_limit_order(ContractSymbol::XbtUsd, 100, Side::Buy, 27000.3, None, None)
+        .await
+        .unwrap_err();
+
+    assert!(
+        error.to_string().contains("Invalid price tickSize"),
+        "{error:#}"
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+/// An HTTP server answering every request with the next of the given responses.
+struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+struct MockResponse {
+    status: u16,
+    headers: Vec<(&'static str, &'static str)>,
+    body: &'static str,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedRequest {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl MockServer {
+    async fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                for response in responses {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+
+                    let request = read_request(&mut stream).await;
+                    requests.lock().unwrap().push(request);
+
+                    stream.write_all(&response.to_bytes()).await.unwrap();
+                    stream.shutdown().await.unwrap();
+                }
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// A signed-in client which retries without delay.
+    fn client(&self) -> Client {
+        Client::new(bitmex_client::models::Network::Testnet)
+            .with_url(self.url())
+            .with_credentials(
+                "LAqUlngMIQkIUjXMUreyu3qn",
+                "chNOOS4KvNXR_Xq4k4c9qsfoKWvnDecLATCRlcBwyKDYnWgO",
+            )
+            .with_retry_policy(RetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::ZERO,
+            })
+    }
+
+    fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn request(&self, index: usize) -> RecordedRequest {
+        self.requests()[index].clone()
+    }
+}
+
+impl MockResponse {
+    fn new(status: u16, body: &'static str) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body,
+        }
+    }
+
+    fn ok(body: &'static str) -> Self {
+        Self::new(200, body)
+    }
+
+    fn with_header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {} Mock\r\n", self.status);
+        response.push_str("content-type: application/json\r\n");
+        response.push_str(&format!("content-length: {}\r\n", self.body.len()));
+        response.push_str("connection: close\r\n");
+        for (name, value) in &self.headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        response.push_str("\r\n");
+        response.push_str(self.body);
+
+        response.into_bytes()
+    }
+}
+
+impl RecordedRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> RecordedRequest {
+    let mut buffer = vec![];
+    let mut chunk = [0; 1024];
+
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await.unwrap();
+        assert!(n > 0, "Connection closed before request was complete");
+        buffer.extend_from_slice(&chunk[..n]);
+
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+
+    let head = String::from_utf8(buffer[..header_end].to_vec()).unwrap();
+    let mut lines = head.split("\r\n");
+
+    let mut request_line = lines.next().unwrap().split(' ');
+    let method = request_line.next().unwrap().to_string();
+    let target = request_line.next().unwrap().to_string();
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect::<Vec<_>>();
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .map(|(_, value)| value.parse::<usize>().unwrap())
+        .unwrap_or_default();
+
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        assert!(n > 0, "Connection closed before body was complete");
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+
+    let body = String::from_utf8(buffer[header_end..header_end + content_length].to_vec()).unwrap();
+
+    RecordedRequest {
+        method,
+        target,
+        headers,
+        body,
+    }
+}
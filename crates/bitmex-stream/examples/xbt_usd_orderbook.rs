@@ -0,0 +1,27 @@
+use anyhow::Result;
+use bitmex_stream::models::Table;
+use bitmex_stream::Network;
+use bitmex_stream::Subscription;
+use futures::StreamExt;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter("info,bitmex_stream=trace")
+        .init();
+
+    let (mut stream, health) = Subscription::new(
+        [
+            Table::OrderBookL2_25.topic("XBTUSD"),
+            Table::Trade.topic("XBTUSD"),
+        ],
+        Network::Testnet,
+    )
+    .start();
+
+    while let Some(update) = stream.next().await {
+        tracing::info!(health = ?*health.borrow(), ?update);
+    }
+
+    Ok(())
+}
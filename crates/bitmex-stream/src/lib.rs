@@ -17,15 +17,27 @@ use tokio_tungstenite::tungstenite;
 use tracing::Instrument;
 use url::Url;
 
+pub mod models;
+mod subscription;
+mod tables;
+
+pub use subscription::Backoff;
+pub use subscription::StreamHealth;
+pub use subscription::Subscription;
+pub use tables::Tables;
+
 /// Connects to the BitMex websocket API
 ///
 /// It subscribes to the specified topics (comma-separated) and yields all messages.
 /// If the topics need authentication please use `subscribe_with_credentials` instead.
+///
+/// The stream ends when the connection is lost. Use a [`Subscription`] for typed table updates
+/// and automatic resubscription.
 pub fn subscribe<const N: usize>(
     topics: [String; N],
     network: Network,
 ) -> impl Stream<Item = Result<String, Error>> + Unpin {
-    subscribe_impl(topics, network, None)
+    subscribe_impl(topics.to_vec(), network, None)
 }
 
 /// Connects to the BitMex websocket API with authentication
@@ -38,7 +50,7 @@ pub fn subscribe_with_credentials<const N: usize>(
     network: Network,
     credentials: Credentials,
 ) -> impl Stream<Item = Result<String, Error>> + Unpin {
-    subscribe_impl(topics, network, Some(credentials))
+    subscribe_impl(topics.to_vec(), network, Some(credentials))
 }
 
 /// Connects to the BitMex websocket API, subscribes to the specified topics (comma-separated) and
@@ -46,8 +58,8 @@ pub fn subscribe_with_credentials<const N: usize>(
 ///
 /// To keep the connection alive, a websocket `Ping` is sent every 5 seconds in case no other
 /// message was received in-between. This is according to BitMex's API documentation: https://www.bitmex.com/app/wsAPI#Heartbeats
-fn subscribe_impl<const N: usize>(
-    topics: [String; N],
+pub(crate) fn subscribe_impl(
+    topics: Vec<String>,
     network: Network,
     credentials: Option<Credentials>,
 ) -> impl Stream<Item = Result<String, Error>> + Unpin {
@@ -76,7 +88,7 @@ fn subscribe_impl<const N: usize>(
         }
         let _ = connection
                 .send(tungstenite::Message::try_from(Command::Subscribe(
-            topics.to_vec(),
+            topics,
         ))?)
         .await;
 
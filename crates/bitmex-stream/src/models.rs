@@ -0,0 +1,422 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+use std::fmt;
+use time::OffsetDateTime;
+
+/// The tables of BitMex's realtime API supported by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Table {
+    #[serde(rename = "quote")]
+    Quote,
+    #[serde(rename = "quoteBin1m")]
+    QuoteBin1m,
+    #[serde(rename = "trade")]
+    Trade,
+    #[serde(rename = "orderBookL2_25")]
+    OrderBookL2_25,
+    #[serde(rename = "execution")]
+    Execution,
+    #[serde(rename = "order")]
+    Order,
+    #[serde(rename = "position")]
+    Position,
+    #[serde(rename = "margin")]
+    Margin,
+}
+
+impl Table {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Table::Quote => "quote",
+            Table::QuoteBin1m => "quoteBin1m",
+            Table::Trade => "trade",
+            Table::OrderBookL2_25 => "orderBookL2_25",
+            Table::Execution => "execution",
+            Table::Order => "order",
+            Table::Position => "position",
+            Table::Margin => "margin",
+        }
+    }
+
+    /// The topic to subscribe to in order to receive this table for a single symbol, e.g.
+    /// `quote:XBTUSD`.
+    pub fn topic(&self, symbol: &str) -> String {
+        format!("{}:{symbol}", self.as_str())
+    }
+
+    /// The fields identifying a row of the table.
+    ///
+    /// Tables without keys are append-only: BitMex only ever inserts rows into them.
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            Table::Quote | Table::QuoteBin1m | Table::Trade | Table::Execution => &[],
+            Table::OrderBookL2_25 => &["symbol", "id", "side"],
+            Table::Order => &["orderID"],
+            Table::Position => &["account", "symbol"],
+            Table::Margin => &["account", "currency"],
+        }
+    }
+
+    /// Whether the table is maintained locally from partial, insert, update and delete messages.
+    pub fn is_keyed(&self) -> bool {
+        !self.keys().is_empty()
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    /// The full content of the table, sent after subscribing.
+    Partial,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            Action::Partial => "partial",
+            Action::Insert => "insert",
+            Action::Update => "update",
+            Action::Delete => "delete",
+        };
+
+        f.write_str(action)
+    }
+}
+
+/// A change of a table, with the affected rows.
+///
+/// Rows of updates to keyed tables contain all fields of the row after the update has been
+/// applied, and rows of deletes contain all fields of the row before it was deleted, even though
+/// BitMex only sends the keys and the changed fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableUpdate {
+    pub table: Table,
+    pub action: Action,
+    pub data: TableData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableData {
+    Quote(Vec<Quote>),
+    Trade(Vec<Trade>),
+    OrderBookL2(Vec<OrderBookL2>),
+    Execution(Vec<Execution>),
+    Order(Vec<Order>),
+    Position(Vec<Position>),
+    Margin(Vec<Margin>),
+}
+
+impl TableData {
+    pub(crate) fn from_rows(
+        table: Table,
+        rows: Vec<Map<String, Value>>,
+    ) -> serde_json::Result<Self> {
+        let rows = Value::Array(rows.into_iter().map(Value::Object).collect());
+
+        let data = match table {
+            Table::Quote | Table::QuoteBin1m => TableData::Quote(serde_json::from_value(rows)?),
+            Table::Trade => TableData::Trade(serde_json::from_value(rows)?),
+            Table::OrderBookL2_25 => TableData::OrderBookL2(serde_json::from_value(rows)?),
+            Table::Execution => TableData::Execution(serde_json::from_value(rows)?),
+            Table::Order => TableData::Order(serde_json::from_value(rows)?),
+            Table::Position => TableData::Position(serde_json::from_value(rows)?),
+            Table::Margin => TableData::Margin(serde_json::from_value(rows)?),
+        };
+
+        Ok(data)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            TableData::Quote(rows) => rows.len(),
+            TableData::Trade(rows) => rows.len(),
+            TableData::OrderBookL2(rows) => rows.len(),
+            TableData::Execution(rows) => rows.len(),
+            TableData::Order(rows) => rows.len(),
+            TableData::Position(rows) => rows.len(),
+            TableData::Margin(rows) => rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub symbol: String,
+    pub bid_size: Option<u64>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub bid_price: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub ask_price: Option<Decimal>,
+    pub ask_size: Option<u64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+    #[serde(rename = "trdMatchID")]
+    pub trd_match_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub size: u64,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
+    pub tick_direction: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// A price level of the orderbook.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderBookL2 {
+    pub symbol: String,
+    pub id: u64,
+    pub side: Side,
+    pub size: u64,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Execution {
+    #[serde(rename = "execID")]
+    pub exec_id: String,
+    #[serde(rename = "orderID")]
+    pub order_id: String,
+    #[serde(rename = "clOrdID")]
+    pub cl_ord_id: Option<String>,
+    pub symbol: String,
+    pub side: Option<Side>,
+    pub last_qty: Option<i64>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub last_px: Option<Decimal>,
+    pub exec_type: ExecType,
+    pub ord_status: Option<OrdStatus>,
+    /// The commission of the execution in satoshis. Negative for rebates.
+    pub exec_comm: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ExecType {
+    New,
+    Trade,
+    Funding,
+    Canceled,
+    Replaced,
+    Restated,
+    Settlement,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+    #[serde(rename = "orderID")]
+    pub order_id: String,
+    #[serde(rename = "clOrdID")]
+    pub cl_ord_id: Option<String>,
+    pub symbol: String,
+    pub side: Option<Side>,
+    pub order_qty: Option<i64>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub price: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub stop_px: Option<Decimal>,
+    pub ord_type: Option<String>,
+    pub ord_status: Option<OrdStatus>,
+    pub leaves_qty: Option<i64>,
+    pub cum_qty: Option<i64>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub avg_px: Option<Decimal>,
+    pub text: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum OrdStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    pub account: u64,
+    pub symbol: String,
+    pub currency: Option<String>,
+    /// The size of the position in contracts. Negative for short positions.
+    pub current_qty: Option<i64>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub avg_entry_price: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub mark_price: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub liquidation_price: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub leverage: Option<Decimal>,
+    /// In satoshis.
+    pub unrealised_pnl: Option<i64>,
+    /// In satoshis.
+    pub realised_pnl: Option<i64>,
+    pub is_open: Option<bool>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+/// The margin of the account in one currency. All amounts are in satoshis.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Margin {
+    pub account: u64,
+    pub currency: String,
+    pub amount: Option<i64>,
+    pub wallet_balance: Option<i64>,
+    pub margin_balance: Option<i64>,
+    pub available_margin: Option<i64>,
+    pub realised_pnl: Option<i64>,
+    pub unrealised_pnl: Option<i64>,
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub margin_leverage: Option<Decimal>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub timestamp: Option<OffsetDateTime>,
+}
+
+/// A message received from BitMex's realtime API.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Response {
+    Table(RawTableUpdate),
+    /// The response to a command, e.g. a subscription or authentication.
+    Ack {
+        success: bool,
+        subscribe: Option<String>,
+        error: Option<String>,
+    },
+    Error {
+        error: String,
+    },
+    Info {
+        info: String,
+    },
+}
+
+/// A table update as sent by BitMex, before its rows have been applied to the local table.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct RawTableUpdate {
+    pub table: Table,
+    pub action: Action,
+    pub data: Vec<Map<String, Value>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn can_deserialize_quote_update() {
+        let response = serde_json::from_str::<Response>(r#"{"table":"quoteBin1m","action":"insert","data":[{"timestamp":"2021-09-21T02:40:00.000Z","symbol":"XBTUSD","bidSize":50200,"bidPrice":42640.5,"askPrice":42641,"askSize":363600}]}"#).unwrap();
+
+        let update = match response {
+            Response::Table(update) => update,
+            other => panic!("Unexpected response {other:?}"),
+        };
+        assert_eq!(update.table, Table::QuoteBin1m);
+        assert_eq!(update.action, Action::Insert);
+
+        let data = TableData::from_rows(update.table, update.data).unwrap();
+        assert_eq!(
+            data,
+            TableData::Quote(vec![Quote {
+                symbol: "XBTUSD".to_string(),
+                bid_size: Some(50200),
+                bid_price: Some(dec!(42640.5)),
+                ask_price: Some(dec!(42641)),
+                ask_size: Some(363600),
+                timestamp: OffsetDateTime::from_unix_timestamp(1632192000).unwrap(),
+            }])
+        );
+    }
+
+    #[test]
+    fn can_deserialize_execution_update() {
+        let response = serde_json::from_str::<Response>(r#"{"table":"execution","action":"insert","data":[{"execID":"0193e879-cb6f-2891-d099-2c4eb40fee21","orderID":"00000000-0000-0000-0000-000000000000","symbol":"XBTUSD","side":"Sell","lastQty":100,"lastPx":27440.5,"execType":"Trade","ordStatus":"Filled","execComm":273,"timestamp":"2023-10-05T17:36:45.781Z"},{"execID":"0193e879-cb6f-2891-d099-2c4eb40fee22","orderID":"00000000-0000-0000-0000-000000000001","symbol":"XBTUSD","side":null,"lastQty":null,"lastPx":null,"execType":"Insurance","timestamp":"2023-10-05T17:36:45.781Z"}]}"#).unwrap();
+
+        let update = match response {
+            Response::Table(update) => update,
+            other => panic!("Unexpected response {other:?}"),
+        };
+
+        let executions = match TableData::from_rows(update.table, update.data).unwrap() {
+            TableData::Execution(executions) => executions,
+            other => panic!("Unexpected data {other:?}"),
+        };
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].side, Some(Side::Sell));
+        assert_eq!(executions[0].last_px, Some(dec!(27440.5)));
+        assert_eq!(executions[0].exec_type, ExecType::Trade);
+        assert_eq!(executions[0].ord_status, Some(OrdStatus::Filled));
+        assert_eq!(executions[1].exec_type, ExecType::Other);
+    }
+
+    #[test]
+    fn can_deserialize_acks_and_errors() {
+        let ack = serde_json::from_str::<Response>(r#"{"success":true,"subscribe":"quote:XBTUSD","request":{"op":"subscribe","args":["quote:XBTUSD"]}}"#).unwrap();
+        assert_eq!(
+            ack,
+            Response::Ack {
+                success: true,
+                subscribe: Some("quote:XBTUSD".to_string()),
+                error: None,
+            }
+        );
+
+        let error = serde_json::from_str::<Response>(
+            r#"{"status":401,"error":"Signature not valid.","meta":{},"request":{}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            error,
+            Response::Error {
+                error: "Signature not valid.".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::models::Response;
+use crate::models::TableUpdate;
+use crate::subscribe_impl;
+use crate::tables::Tables;
+use crate::Credentials;
+use crate::Network;
+use async_stream::stream;
+use futures::Stream;
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// The health of a [`Subscription`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamHealth {
+    /// Connecting to BitMex for the first time.
+    Connecting,
+    /// Connected and receiving messages.
+    Online,
+    /// The connection has been lost and will be re-established after a backoff.
+    Reconnecting { attempt: u32, error: String },
+}
+
+impl StreamHealth {
+    pub fn is_online(&self) -> bool {
+        matches!(self, StreamHealth::Online)
+    }
+}
+
+/// The delay between two attempts to reconnect, doubling with every failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before the `attempt`-th consecutive reconnect, starting at 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// A subscription to BitMex's realtime API yielding typed table updates.
+///
+/// Unlike [`crate::subscribe`], the stream never ends: whenever the connection is lost it
+/// reconnects and resubscribes to all topics, backing off exponentially. After a reconnect every
+/// keyed table starts over with a `partial`.
+pub struct Subscription {
+    topics: Vec<String>,
+    network: Network,
+    credentials: Option<Credentials>,
+    backoff: Backoff,
+}
+
+impl Subscription {
+    pub fn new(topics: impl IntoIterator<Item = String>, network: Network) -> Self {
+        Self {
+            topics: topics.into_iter().collect(),
+            network,
+            credentials: None,
+            backoff: Backoff::default(),
+        }
+    }
+
+    /// Authenticates the connection, needed for private topics such as `execution`, `order`,
+    /// `position` and `margin`.
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Starts the subscription, returning the table updates and a signal of the stream's health.
+    pub fn start(
+        self,
+    ) -> (
+        impl Stream<Item = TableUpdate> + Unpin,
+        watch::Receiver<StreamHealth>,
+    ) {
+        let (health_tx, health_rx) = watch::channel(StreamHealth::Connecting);
+
+        let stream = stream! {
+            let mut tables = Tables::default();
+            let mut attempt = 0;
+
+            loop {
+                let mut messages = subscribe_impl(
+                    self.topics.clone(),
+                    self.network,
+                    self.credentials.clone(),
+                );
+
+                let error = loop {
+                    let text = match messages.next().await {
+                        Some(Ok(text)) => text,
+                        Some(Err(e)) => break format!("{e:#}"),
+                        None => break "Stream ended".to_string(),
+                    };
+
+                    attempt = 0;
+                    health_tx.send_if_modified(|health| {
+                        let was_online = health.is_online();
+                        *health = StreamHealth::Online;
+                        !was_online
+                    });
+
+                    let update = match serde_json::from_str::<Response>(&text) {
+                        Ok(Response::Table(update)) => update,
+                        Ok(Response::Ack { success: false, error, .. }) => {
+                            tracing::error!(?error, "BitMex rejected command");
+                            continue;
+                        }
+                        Ok(Response::Ack { subscribe, .. }) => {
+                            tracing::debug!(?subscribe, "BitMex acknowledged command");
+                            continue;
+                        }
+                        Ok(Response::Error { error }) => {
+                            tracing::error!(%error, "Received error from BitMex");
+                            continue;
+                        }
+                        Ok(Response::Info { info }) => {
+                            tracing::debug!(%info, "Received info from BitMex");
+                            continue;
+                        }
+                        Err(_) => {
+                            tracing::debug!("Unexpected message: {text}");
+                            continue;
+                        }
+                    };
+
+                    match tables.apply(update) {
+                        Ok(Some(update)) => yield update,
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!("Failed to apply table update: {e:#}"),
+                    }
+                };
+
+                tables.clear();
+                attempt += 1;
+
+                let delay = self.backoff.delay(attempt);
+                tracing::warn!(
+                    %error,
+                    attempt,
+                    ?delay,
+                    "Lost connection to BitMex, resubscribing after backoff"
+                );
+                health_tx.send_replace(StreamHealth::Reconnecting { attempt, error });
+
+                tokio::time::sleep(delay).await;
+            }
+        };
+
+        (stream.boxed(), health_rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let backoff = Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(10),
+        };
+
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(2), Duration::from_secs(2));
+        assert_eq!(backoff.delay(4), Duration::from_secs(8));
+        assert_eq!(backoff.delay(5), Duration::from_secs(10));
+        assert_eq!(backoff.delay(100), Duration::from_secs(10));
+    }
+}
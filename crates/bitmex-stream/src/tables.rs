@@ -0,0 +1,262 @@
+use crate::models::Action;
+use crate::models::RawTableUpdate;
+use crate::models::Response;
+use crate::models::Table;
+use crate::models::TableData;
+use crate::models::TableUpdate;
+use anyhow::Context;
+use anyhow::Result;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::HashMap;
+
+type Row = Map<String, Value>;
+
+/// The local copy of the keyed tables of a subscription.
+///
+/// BitMex sends the full content of a table once after subscribing (`partial`) and only the
+/// changes afterwards. Updates only contain the keys and the changed fields of a row, hence the
+/// rows are kept here to hand out complete rows. Changes received before the partial of a table
+/// are ignored, as recommended by BitMex.
+#[derive(Debug, Default)]
+pub struct Tables {
+    tables: HashMap<Table, Vec<Row>>,
+}
+
+impl Tables {
+    /// Parses a message received from BitMex and applies it if it is a table update.
+    ///
+    /// Returns `None` for other messages, e.g. acknowledgements of subscriptions, and for table
+    /// updates which have not changed any row.
+    pub fn apply_message(&mut self, text: &str) -> Result<Option<TableUpdate>> {
+        match serde_json::from_str(text).context("Could not parse message")? {
+            Response::Table(update) => self.apply(update),
+            _ => Ok(None),
+        }
+    }
+
+    /// Applies a table update received from BitMex and returns the affected rows.
+    ///
+    /// Returns `None` if the update has not changed any row.
+    pub(crate) fn apply(&mut self, update: RawTableUpdate) -> Result<Option<TableUpdate>> {
+        let RawTableUpdate {
+            table,
+            action,
+            data,
+        } = update;
+
+        let keys = table.keys();
+
+        let rows = if !table.is_keyed() {
+            // Append-only tables are not kept, their rows are complete.
+            data
+        } else if action == Action::Partial {
+            self.tables.insert(table, data.clone());
+            data
+        } else {
+            let Some(rows) = self.tables.get_mut(&table) else {
+                tracing::trace!(%table, %action, "Ignoring table update before partial");
+                return Ok(None);
+            };
+
+            match action {
+                Action::Insert => {
+                    rows.extend(data.iter().cloned());
+                    data
+                }
+                Action::Update => data
+                    .into_iter()
+                    .filter_map(|changes| {
+                        let row = rows
+                            .iter_mut()
+                            .find(|row| has_same_keys(row, &changes, keys));
+
+                        let Some(row) = row else {
+                            tracing::debug!(%table, ?changes, "Ignoring update of unknown row");
+                            return None;
+                        };
+
+                        row.extend(changes);
+                        Some(row.clone())
+                    })
+                    .collect(),
+                Action::Delete => data
+                    .into_iter()
+                    .filter_map(|deleted| {
+                        let index = rows
+                            .iter()
+                            .position(|row| has_same_keys(row, &deleted, keys))?;
+
+                        Some(rows.remove(index))
+                    })
+                    .collect(),
+                Action::Partial => unreachable!("partial handled above"),
+            }
+        };
+
+        if rows.is_empty() && action != Action::Partial {
+            return Ok(None);
+        }
+
+        let data = TableData::from_rows(table, rows)
+            .with_context(|| format!("Could not deserialize {action} of table {table}"))?;
+
+        Ok(Some(TableUpdate {
+            table,
+            action,
+            data,
+        }))
+    }
+
+    /// The current content of a keyed table, or `None` if its partial has not been received.
+    pub fn snapshot(&self, table: Table) -> Result<Option<TableData>> {
+        let Some(rows) = self.tables.get(&table) else {
+            return Ok(None);
+        };
+
+        let data = TableData::from_rows(table, rows.clone())
+            .with_context(|| format!("Could not deserialize table {table}"))?;
+
+        Ok(Some(data))
+    }
+
+    /// Forgets all tables, e.g. after reconnecting, when BitMex sends every partial again.
+    pub fn clear(&mut self) {
+        self.tables.clear();
+    }
+}
+
+fn has_same_keys(row: &Row, other: &Row, keys: &[&str]) -> bool {
+    keys.iter().all(|key| row.get(*key) == other.get(*key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OrderBookL2;
+    use crate::models::Position;
+    use crate::models::Side;
+    use rust_decimal_macros::dec;
+
+    fn apply(tables: &mut Tables, message: &str) -> Option<TableUpdate> {
+        tables.apply_message(message).unwrap()
+    }
+
+    fn level(id: u64, side: Side, size: u64, price: &str) -> OrderBookL2 {
+        OrderBookL2 {
+            symbol: "XBTUSD".to_string(),
+            id,
+            side,
+            size,
+            price: price.parse().unwrap(),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn orderbook_is_maintained_locally() {
+        let mut tables = Tables::default();
+
+        // Changes before the partial are ignored.
+        assert_eq!(
+            apply(
+                &mut tables,
+                r#"{"table":"orderBookL2_25","action":"update","data":[{"symbol":"XBTUSD","id":1,"side":"Sell","size":10}]}"#,
+            ),
+            None
+        );
+
+        let partial = apply(
+            &mut tables,
+            r#"{"table":"orderBookL2_25","action":"partial","keys":["symbol","id","side"],"data":[{"symbol":"XBTUSD","id":1,"side":"Sell","size":100,"price":30001},{"symbol":"XBTUSD","id":2,"side":"Buy","size":200,"price":30000}]}"#,
+        )
+        .unwrap();
+        assert_eq!(partial.action, Action::Partial);
+
+        let update = apply(
+            &mut tables,
+            r#"{"table":"orderBookL2_25","action":"update","data":[{"symbol":"XBTUSD","id":1,"side":"Sell","size":50}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            update.data,
+            TableData::OrderBookL2(vec![level(1, Side::Sell, 50, "30001")])
+        );
+
+        apply(
+            &mut tables,
+            r#"{"table":"orderBookL2_25","action":"insert","data":[{"symbol":"XBTUSD","id":3,"side":"Buy","size":300,"price":29999.5}]}"#,
+        )
+        .unwrap();
+
+        let delete = apply(
+            &mut tables,
+            r#"{"table":"orderBookL2_25","action":"delete","data":[{"symbol":"XBTUSD","id":2,"side":"Buy"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            delete.data,
+            TableData::OrderBookL2(vec![level(2, Side::Buy, 200, "30000")])
+        );
+
+        assert_eq!(
+            tables.snapshot(Table::OrderBookL2_25).unwrap(),
+            Some(TableData::OrderBookL2(vec![
+                level(1, Side::Sell, 50, "30001"),
+                level(3, Side::Buy, 300, "29999.5"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn position_updates_are_merged_into_partial() {
+        let mut tables = Tables::default();
+
+        apply(
+            &mut tables,
+            r#"{"table":"position","action":"partial","data":[{"account":396867,"symbol":"XBTUSD","currency":"XBt","currentQty":0,"avgEntryPrice":null,"isOpen":false,"timestamp":"2023-10-05T17:00:00.000Z"}]}"#,
+        );
+
+        let update = apply(
+            &mut tables,
+            r#"{"table":"position","action":"update","data":[{"account":396867,"symbol":"XBTUSD","currentQty":100,"avgEntryPrice":27440.5,"isOpen":true,"timestamp":"2023-10-05T17:36:45.781Z"}]}"#,
+        )
+        .unwrap();
+
+        let positions = match update.data {
+            TableData::Position(positions) => positions,
+            other => panic!("Unexpected data {other:?}"),
+        };
+        assert_eq!(
+            positions,
+            vec![Position {
+                account: 396867,
+                symbol: "XBTUSD".to_string(),
+                currency: Some("XBt".to_string()),
+                current_qty: Some(100),
+                avg_entry_price: Some(dec!(27440.5)),
+                mark_price: None,
+                liquidation_price: None,
+                leverage: None,
+                unrealised_pnl: None,
+                realised_pnl: None,
+                is_open: Some(true),
+                timestamp: Some(time::macros::datetime!(2023-10-05 17:36:45.781 UTC),),
+            }]
+        );
+    }
+
+    #[test]
+    fn append_only_tables_do_not_need_a_partial() {
+        let mut tables = Tables::default();
+
+        let update = apply(
+            &mut tables,
+            r#"{"table":"trade","action":"insert","data":[{"timestamp":"2023-10-05T17:36:45.781Z","symbol":"XBTUSD","side":"Buy","size":100,"price":27440.5,"tickDirection":"PlusTick","trdMatchID":"c5b9ef4d-5bde-7a1a-3ab8-3d3ab9f5a5d5"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(update.data.len(), 1);
+        assert_eq!(tables.snapshot(Table::Trade).unwrap(), None);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::chain::confirm_sync::ConfirmationSource;
+use crate::chain::confirm_sync::ConfirmedTx;
+use crate::chain::OutputStatus;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoincore_rpc::jsonrpc;
+use bdk::bitcoincore_rpc::Client;
+use bdk::bitcoincore_rpc::RpcApi;
+use bitcoin::BlockHash;
+use bitcoin::BlockHeader;
+use bitcoin::OutPoint;
+use bitcoin::Script;
+use bitcoin::Txid;
+use lightning::chain::WatchedOutput;
+
+/// The error bitcoind returns for unknown transactions, `RPC_INVALID_ADDRESS_OR_KEY`.
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+pub(crate) fn estimate_fee(client: &Client, n_blocks: usize) -> Result<f32> {
+    let estimate = client.estimate_smart_fee(n_blocks as u16, None)?;
+    let Some(fee_rate) = estimate.fee_rate else {
+        bail!("No fee estimate for {n_blocks} blocks: {:?}", estimate.errors);
+    };
+
+    // bitcoind estimates in BTC/kvB.
+    Ok(fee_rate.to_sat() as f32 / 1000.0)
+}
+
+pub(crate) fn confirmation_height(client: &Client, txid: &Txid) -> Result<Option<u32>> {
+    let Some(block_hash) = tx_block_hash(client, txid)? else {
+        return Ok(None);
+    };
+
+    let block = client.get_block_header_info(&block_hash)?;
+    if block.confirmations < 1 {
+        return Ok(None);
+    }
+
+    Ok(Some(block.height as u32))
+}
+
+/// bitcoind does not index the transactions spending an output, so the spend is looked up in the
+/// blocks since the output was confirmed.
+pub(crate) fn output_status(client: &Client, outpoint: &OutPoint) -> Result<OutputStatus> {
+    if is_unspent(client, outpoint, true)? {
+        return Ok(OutputStatus::Unspent);
+    }
+
+    if is_unspent(client, outpoint, false)? {
+        return Ok(OutputStatus::SpentInMempool);
+    }
+
+    let confirmation_height =
+        find_confirmed_spend(client, outpoint, None, None)?.map(|spend| spend.block_height);
+
+    Ok(OutputStatus::Spent {
+        confirmation_height,
+    })
+}
+
+/// Whether `outpoint` is in the UTXO set, optionally taking the mempool into account.
+fn is_unspent(client: &Client, outpoint: &OutPoint, include_mempool: bool) -> Result<bool> {
+    let tx_out = client.get_tx_out(&outpoint.txid, outpoint.vout, Some(include_mempool))?;
+
+    Ok(tx_out.is_some())
+}
+
+/// Looks for the transaction spending `outpoint` in the blocks since `from_height`, or else since
+/// `from_block` or since the output was confirmed.
+fn find_confirmed_spend(
+    client: &Client,
+    outpoint: &OutPoint,
+    from_block: Option<BlockHash>,
+    from_height: Option<u32>,
+) -> Result<Option<ConfirmedTx>> {
+    let start = match from_height {
+        Some(from_height) => from_height as u64,
+        None => {
+            let from_block = match from_block {
+                Some(block_hash) => Some(block_hash),
+                None => tx_block_hash(client, &outpoint.txid)?,
+            };
+            let Some(from_block) = from_block else {
+                return Ok(None);
+            };
+
+            client.get_block_header_info(&from_block)?.height as u64
+        }
+    };
+    let tip = client.get_block_count()?;
+    for height in start..=tip {
+        let block_hash = client.get_block_hash(height)?;
+        let block = client.get_block(&block_hash)?;
+
+        let spend = block.txdata.iter().enumerate().find(|(_, tx)| {
+            tx.input
+                .iter()
+                .any(|input| input.previous_output == *outpoint)
+        });
+
+        if let Some((pos, tx)) = spend {
+            return Ok(Some(ConfirmedTx {
+                tx: tx.clone(),
+                block_header: block.header,
+                block_height: height as u32,
+                pos,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The hash of the block the transaction is in, if any.
+///
+/// Requires `txindex=1` for transactions which do not belong to the bitcoind wallet. Only an
+/// unknown transaction is reported as `None`, any other error is returned, as we must not treat
+/// a transaction as unconfirmed just because bitcoind could not be reached.
+fn tx_block_hash(client: &Client, txid: &Txid) -> Result<Option<BlockHash>> {
+    match client.get_raw_transaction_info(txid, None) {
+        Ok(tx) => Ok(tx.blockhash),
+        Err(bdk::bitcoincore_rpc::Error::JsonRpc(jsonrpc::Error::Rpc(e)))
+            if e.code == RPC_INVALID_ADDRESS_OR_KEY =>
+        {
+            tracing::trace!(%txid, "Unknown transaction: {}", e.message);
+            Ok(None)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to get transaction {txid}")),
+    }
+}
+
+fn confirmed_tx_in_block(
+    client: &Client,
+    txid: &Txid,
+    block_hash: &BlockHash,
+) -> Result<Option<ConfirmedTx>> {
+    let block = client.get_block_info(block_hash)?;
+    if block.confirmations < 1 {
+        return Ok(None);
+    }
+
+    let pos = block
+        .tx
+        .iter()
+        .position(|id| id == txid)
+        .with_context(|| format!("Transaction {txid} not in block {block_hash}"))?;
+
+    let tx = client.get_raw_transaction(txid, Some(block_hash))?;
+    let block_header = client.get_block_header(block_hash)?;
+
+    Ok(Some(ConfirmedTx {
+        tx,
+        block_header,
+        block_height: block.height as u32,
+        pos,
+    }))
+}
+
+impl ConfirmationSource for Client {
+    fn tip(&self) -> Result<(BlockHeader, u32)> {
+        let block_hash = self.get_best_block_hash()?;
+        let info = self.get_block_header_info(&block_hash)?;
+        let header = self.get_block_header(&block_hash)?;
+
+        Ok((header, info.height as u32))
+    }
+
+    fn get_confirmed_tx(&self, txid: &Txid, _: Option<&Script>) -> Result<Option<ConfirmedTx>> {
+        let Some(block_hash) = tx_block_hash(self, txid)? else {
+            return Ok(None);
+        };
+
+        confirmed_tx_in_block(self, txid, &block_hash)
+    }
+
+    fn get_confirmed_spend(
+        &self,
+        output: &WatchedOutput,
+        from_height: Option<u32>,
+    ) -> Result<Option<ConfirmedTx>> {
+        let outpoint = output.outpoint.into_bitcoin_outpoint();
+        if is_unspent(self, &outpoint, false)? {
+            return Ok(None);
+        }
+
+        find_confirmed_spend(self, &outpoint, output.block_hash, from_height)
+    }
+}
@@ -0,0 +1,465 @@
+use anyhow::Result;
+use bitcoin::BlockHash;
+use bitcoin::BlockHeader;
+use bitcoin::OutPoint;
+use bitcoin::Script;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use lightning::chain::channelmonitor::ANTI_REORG_DELAY;
+use lightning::chain::Confirm;
+use lightning::chain::Filter;
+use lightning::chain::WatchedOutput;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A transaction confirmed in the best chain.
+#[derive(Clone)]
+pub(crate) struct ConfirmedTx {
+    pub tx: Transaction,
+    pub block_header: BlockHeader,
+    pub block_height: u32,
+    /// The position of the transaction in the block.
+    pub pos: usize,
+}
+
+/// A backend which can tell [`ConfirmSync`] about the best chain.
+pub(crate) trait ConfirmationSource {
+    fn tip(&self) -> Result<(BlockHeader, u32)>;
+
+    /// The transaction `txid`, if it is confirmed in the best chain.
+    ///
+    /// `script_pubkey` is one of the scripts the transaction pays to, if known.
+    fn get_confirmed_tx(
+        &self,
+        txid: &Txid,
+        script_pubkey: Option<&Script>,
+    ) -> Result<Option<ConfirmedTx>>;
+
+    /// The transaction spending `output`, if it is confirmed in the best chain.
+    ///
+    /// The blocks below `from_height` have already been searched for the spend, if given.
+    fn get_confirmed_spend(
+        &self,
+        output: &WatchedOutput,
+        from_height: Option<u32>,
+    ) -> Result<Option<ConfirmedTx>>;
+}
+
+#[derive(Default)]
+struct SyncState {
+    watched_transactions: HashMap<Txid, Option<Script>>,
+    watched_outputs: HashMap<OutPoint, WatchedOutput>,
+    /// The height from which the blocks have to be searched for the spend of a watched output.
+    spend_search_heights: HashMap<OutPoint, u32>,
+    /// The watched transactions which have been reported as confirmed, and in which block.
+    reported: HashMap<Txid, BlockHash>,
+    last_tip: Option<BlockHash>,
+    /// Whether something was registered since the last sync.
+    pending_registrations: bool,
+}
+
+/// Syncs LDK through the [`Confirm`] interface against a [`ConfirmationSource`].
+///
+/// This is what [`lightning_transaction_sync::EsploraSyncClient`] does for Esplora, for backends
+/// which have no sync client of their own.
+///
+/// Transactions and outputs are watched until their confirmation is [`ANTI_REORG_DELAY`] blocks
+/// deep, so that they are confirmed again if they end up in a different block after a reorg.
+pub(crate) struct ConfirmSync<C> {
+    source: Arc<C>,
+    state: Mutex<SyncState>,
+}
+
+impl<C> ConfirmSync<C>
+where
+    C: ConfirmationSource,
+{
+    pub fn new(source: Arc<C>) -> Self {
+        Self {
+            source,
+            state: Mutex::new(SyncState::default()),
+        }
+    }
+
+    pub fn sync(&self, confirmables: Vec<&(dyn Confirm + Sync + Send)>) -> Result<()> {
+        let mut state = self.state.lock();
+
+        let (tip_header, tip_height) = self.source.tip()?;
+        let tip_hash = tip_header.block_hash();
+        if state.last_tip == Some(tip_hash) && !state.pending_registrations {
+            return Ok(());
+        }
+
+        // Transactions which were reorged out of the best chain have to be watched again.
+        for confirmable in confirmables.iter() {
+            for (txid, block_hash) in confirmable.get_relevant_txids() {
+                let confirmed = self.source.get_confirmed_tx(&txid, None)?;
+                let still_confirmed = match (confirmed, block_hash) {
+                    (Some(tx), Some(block_hash)) => tx.block_header.block_hash() == block_hash,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                };
+
+                if !still_confirmed {
+                    tracing::debug!(%txid, "Transaction no longer confirmed");
+                    confirmable.transaction_unconfirmed(&txid);
+                    state.watched_transactions.entry(txid).or_insert(None);
+                    state.reported.remove(&txid);
+                }
+            }
+        }
+
+        if state.last_tip != Some(tip_hash) {
+            for confirmable in confirmables.iter() {
+                confirmable.best_block_updated(&tip_header, tip_height);
+            }
+        }
+
+        let mut confirmed = Vec::new();
+        for (txid, script_pubkey) in state.watched_transactions.iter() {
+            if let Some(tx) = self.source.get_confirmed_tx(txid, script_pubkey.as_ref())? {
+                confirmed.push(tx);
+            }
+        }
+
+        // Blocks which may still be reorged out are searched again.
+        let next_spend_search_height = (tip_height + 1).saturating_sub(ANTI_REORG_DELAY);
+        let mut unspent = Vec::new();
+        for (outpoint, output) in state.watched_outputs.iter() {
+            let from_height = state.spend_search_heights.get(outpoint).copied();
+            match self.source.get_confirmed_spend(output, from_height)? {
+                Some(tx) => confirmed.push(tx),
+                None => unspent.push(*outpoint),
+            }
+        }
+        for outpoint in unspent {
+            state
+                .spend_search_heights
+                .insert(outpoint, next_spend_search_height);
+        }
+
+        // LDK expects transactions in the order in which they were confirmed.
+        confirmed.sort_unstable_by_key(|tx| (tx.block_height, tx.pos));
+        confirmed.dedup_by_key(|tx| tx.tx.txid());
+
+        for tx in confirmed.iter() {
+            let txid = tx.tx.txid();
+            let block_hash = tx.block_header.block_hash();
+            if state.reported.get(&txid) == Some(&block_hash) {
+                continue;
+            }
+
+            for confirmable in confirmables.iter() {
+                confirmable.transactions_confirmed(
+                    &tx.block_header,
+                    &[(tx.pos, &tx.tx)],
+                    tx.block_height,
+                );
+            }
+
+            state.reported.insert(txid, block_hash);
+        }
+
+        for tx in confirmed.iter() {
+            let confirmations = (tip_height + 1).saturating_sub(tx.block_height);
+            if confirmations < ANTI_REORG_DELAY {
+                continue;
+            }
+
+            let txid = tx.tx.txid();
+            state.watched_transactions.remove(&txid);
+            state.reported.remove(&txid);
+            for input in tx.tx.input.iter() {
+                state.watched_outputs.remove(&input.previous_output);
+                state.spend_search_heights.remove(&input.previous_output);
+            }
+        }
+
+        state.last_tip = Some(tip_hash);
+        state.pending_registrations = false;
+
+        Ok(())
+    }
+}
+
+impl<C> Filter for ConfirmSync<C> {
+    fn register_tx(&self, txid: &Txid, script_pubkey: &Script) {
+        let mut state = self.state.lock();
+        state
+            .watched_transactions
+            .insert(*txid, Some(script_pubkey.clone()));
+        state.pending_registrations = true;
+    }
+
+    fn register_output(&self, output: WatchedOutput) {
+        let mut state = self.state.lock();
+        state
+            .watched_outputs
+            .insert(output.outpoint.into_bitcoin_outpoint(), output);
+        state.pending_registrations = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::PackedLockTime;
+    use bitcoin::TxIn;
+    use bitcoin::TxMerkleNode;
+    use bitcoin::TxOut;
+    use lightning::chain::transaction::TransactionData;
+
+    #[test]
+    fn confirms_watched_transactions_in_chain_order() {
+        let chain = Arc::new(Chain::new(block_header(12), 12));
+        let sync = ConfirmSync::new(chain.clone());
+        let confirmable = Confirmable::default();
+
+        let funding_tx = transaction(OutPoint::null());
+        let funding_outpoint = OutPoint::new(funding_tx.txid(), 0);
+        let spend_tx = transaction(funding_outpoint);
+
+        // The spend is confirmed in an earlier block than the watched transaction.
+        chain.confirm(&funding_tx, 11, 1);
+        chain.confirm(&spend_tx, 10, 0);
+        chain.spend(funding_outpoint, &spend_tx, 10, 0);
+
+        sync.register_tx(&funding_tx.txid(), &Script::new());
+        sync.register_output(WatchedOutput {
+            block_hash: None,
+            outpoint: lightning::chain::transaction::OutPoint {
+                txid: funding_outpoint.txid,
+                index: funding_outpoint.vout as u16,
+            },
+            script_pubkey: Script::new(),
+        });
+
+        sync.sync(vec![&confirmable]).unwrap();
+
+        assert_eq!(
+            *confirmable.confirmed.lock(),
+            vec![(spend_tx.txid(), 10), (funding_tx.txid(), 11)]
+        );
+        assert_eq!(*confirmable.best_block.lock(), vec![12]);
+
+        // Confirmed transactions are not reported twice.
+        chain.set_tip(block_header(13), 13);
+        sync.sync(vec![&confirmable]).unwrap();
+
+        assert_eq!(confirmable.confirmed.lock().len(), 2);
+        assert_eq!(*confirmable.best_block.lock(), vec![12, 13]);
+    }
+
+    #[test]
+    fn transactions_are_watched_until_confirmation_is_final() {
+        let chain = Arc::new(Chain::new(block_header(11), 11));
+        let sync = ConfirmSync::new(chain.clone());
+        let confirmable = Confirmable::default();
+
+        let tx = transaction(OutPoint::null());
+        sync.register_tx(&tx.txid(), &Script::new());
+
+        chain.confirm(&tx, 11, 0);
+        sync.sync(vec![&confirmable]).unwrap();
+
+        // A reorg moves the transaction into the next block.
+        chain.confirm(&tx, 12, 0);
+        chain.set_tip(block_header(12), 12);
+        sync.sync(vec![&confirmable]).unwrap();
+
+        assert_eq!(
+            *confirmable.confirmed.lock(),
+            vec![(tx.txid(), 11), (tx.txid(), 12)]
+        );
+        assert!(sync
+            .state
+            .lock()
+            .watched_transactions
+            .contains_key(&tx.txid()));
+
+        chain.set_tip(block_header(17), 17);
+        sync.sync(vec![&confirmable]).unwrap();
+
+        assert_eq!(confirmable.confirmed.lock().len(), 2);
+        assert!(sync.state.lock().watched_transactions.is_empty());
+    }
+
+    #[test]
+    fn unconfirmed_transactions_are_watched_until_confirmed() {
+        let chain = Arc::new(Chain::new(block_header(10), 10));
+        let sync = ConfirmSync::new(chain.clone());
+        let confirmable = Confirmable::default();
+
+        let tx = transaction(OutPoint::null());
+        sync.register_tx(&tx.txid(), &Script::new());
+
+        sync.sync(vec![&confirmable]).unwrap();
+        assert!(confirmable.confirmed.lock().is_empty());
+
+        chain.confirm(&tx, 11, 1);
+        chain.set_tip(block_header(11), 11);
+
+        sync.sync(vec![&confirmable]).unwrap();
+        assert_eq!(*confirmable.confirmed.lock(), vec![(tx.txid(), 11)]);
+        assert_eq!(*confirmable.best_block.lock(), vec![10, 11]);
+    }
+
+    #[test]
+    fn reorged_transactions_are_unconfirmed_and_confirmed_again() {
+        let chain = Arc::new(Chain::new(block_header(11), 11));
+        let sync = ConfirmSync::new(chain.clone());
+        let confirmable = Confirmable::default();
+
+        let tx = transaction(OutPoint::null());
+
+        // LDK saw the transaction in a block which has been reorged out.
+        let stale_block = block_header(10);
+        confirmable
+            .relevant_txids
+            .lock()
+            .push((tx.txid(), Some(stale_block.block_hash())));
+        chain.confirm(&tx, 11, 0);
+
+        sync.sync(vec![&confirmable]).unwrap();
+
+        assert_eq!(*confirmable.unconfirmed.lock(), vec![tx.txid()]);
+        assert_eq!(*confirmable.confirmed.lock(), vec![(tx.txid(), 11)]);
+    }
+
+    #[test]
+    fn transactions_in_the_best_chain_are_not_unconfirmed() {
+        let chain = Arc::new(Chain::new(block_header(11), 11));
+        let sync = ConfirmSync::new(chain.clone());
+        let confirmable = Confirmable::default();
+
+        let tx = transaction(OutPoint::null());
+        chain.confirm(&tx, 11, 0);
+        confirmable
+            .relevant_txids
+            .lock()
+            .push((tx.txid(), Some(block_header(11).block_hash())));
+
+        sync.sync(vec![&confirmable]).unwrap();
+
+        assert!(confirmable.unconfirmed.lock().is_empty());
+        assert!(confirmable.confirmed.lock().is_empty());
+    }
+
+    struct Chain {
+        tip: Mutex<(BlockHeader, u32)>,
+        transactions: Mutex<HashMap<Txid, ConfirmedTx>>,
+        spends: Mutex<HashMap<OutPoint, ConfirmedTx>>,
+    }
+
+    impl Chain {
+        fn new(tip: BlockHeader, height: u32) -> Self {
+            Self {
+                tip: Mutex::new((tip, height)),
+                transactions: Mutex::default(),
+                spends: Mutex::default(),
+            }
+        }
+
+        fn set_tip(&self, tip: BlockHeader, height: u32) {
+            *self.tip.lock() = (tip, height);
+        }
+
+        fn confirm(&self, tx: &Transaction, height: u32, pos: usize) {
+            self.transactions
+                .lock()
+                .insert(tx.txid(), confirmed_tx(tx, height, pos));
+        }
+
+        fn spend(&self, outpoint: OutPoint, tx: &Transaction, height: u32, pos: usize) {
+            self.spends
+                .lock()
+                .insert(outpoint, confirmed_tx(tx, height, pos));
+        }
+    }
+
+    impl ConfirmationSource for Chain {
+        fn tip(&self) -> Result<(BlockHeader, u32)> {
+            Ok(*self.tip.lock())
+        }
+
+        fn get_confirmed_tx(&self, txid: &Txid, _: Option<&Script>) -> Result<Option<ConfirmedTx>> {
+            Ok(self.transactions.lock().get(txid).cloned())
+        }
+
+        fn get_confirmed_spend(
+            &self,
+            output: &WatchedOutput,
+            _: Option<u32>,
+        ) -> Result<Option<ConfirmedTx>> {
+            let outpoint = output.outpoint.into_bitcoin_outpoint();
+            Ok(self.spends.lock().get(&outpoint).cloned())
+        }
+    }
+
+    #[derive(Default)]
+    struct Confirmable {
+        relevant_txids: Mutex<Vec<(Txid, Option<BlockHash>)>>,
+        confirmed: Mutex<Vec<(Txid, u32)>>,
+        unconfirmed: Mutex<Vec<Txid>>,
+        best_block: Mutex<Vec<u32>>,
+    }
+
+    impl Confirm for Confirmable {
+        fn transactions_confirmed(&self, _: &BlockHeader, txdata: &TransactionData, height: u32) {
+            let mut confirmed = self.confirmed.lock();
+            for (_, tx) in txdata.iter() {
+                confirmed.push((tx.txid(), height));
+            }
+        }
+
+        fn transaction_unconfirmed(&self, txid: &Txid) {
+            self.unconfirmed.lock().push(*txid);
+        }
+
+        fn best_block_updated(&self, _: &BlockHeader, height: u32) {
+            self.best_block.lock().push(height);
+        }
+
+        fn get_relevant_txids(&self) -> Vec<(Txid, Option<BlockHash>)> {
+            self.relevant_txids.lock().clone()
+        }
+    }
+
+    fn confirmed_tx(tx: &Transaction, height: u32, pos: usize) -> ConfirmedTx {
+        ConfirmedTx {
+            tx: tx.clone(),
+            block_header: block_header(height),
+            block_height: height,
+            pos,
+        }
+    }
+
+    /// A distinct header per height, we only care about the block hashes.
+    fn block_header(height: u32) -> BlockHeader {
+        BlockHeader {
+            version: 1,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: height,
+            bits: 0,
+            nonce: 0,
+        }
+    }
+
+    fn transaction(previous_output: OutPoint) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime(rand::random()),
+            input: vec![TxIn {
+                previous_output,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: 100_000,
+                script_pubkey: Script::new(),
+            }],
+        }
+    }
+}
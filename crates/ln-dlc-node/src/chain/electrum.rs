@@ -0,0 +1,141 @@
+use crate::chain::confirm_sync::ConfirmationSource;
+use crate::chain::confirm_sync::ConfirmedTx;
+use crate::chain::OutputStatus;
+use anyhow::bail;
+use anyhow::Result;
+use bdk::electrum_client::Client;
+use bdk::electrum_client::ElectrumApi;
+use bitcoin::BlockHeader;
+use bitcoin::OutPoint;
+use bitcoin::Script;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use lightning::chain::WatchedOutput;
+
+pub(crate) fn estimate_fee(client: &Client, n_blocks: usize) -> Result<f32> {
+    let btc_per_kvbyte = client.estimate_fee(n_blocks)?;
+    if btc_per_kvbyte < 0.0 {
+        bail!("No fee estimate for {n_blocks} blocks");
+    }
+
+    Ok((btc_per_kvbyte * 100_000.0) as f32)
+}
+
+pub(crate) fn confirmation_height(client: &Client, txid: &Txid) -> Result<Option<u32>> {
+    let height = client
+        .get_confirmed_tx(txid, None)?
+        .map(|tx| tx.block_height);
+
+    Ok(height)
+}
+
+pub(crate) fn output_status(
+    client: &Client,
+    outpoint: &OutPoint,
+    script_pubkey: &Script,
+) -> Result<OutputStatus> {
+    let status = match find_spend(client, outpoint, script_pubkey)? {
+        Some((_, height)) if height > 0 => OutputStatus::Spent {
+            confirmation_height: Some(height as u32),
+        },
+        Some(_) => OutputStatus::SpentInMempool,
+        None => OutputStatus::Unspent,
+    };
+
+    Ok(status)
+}
+
+/// The transaction spending `outpoint` and its height, which is 0 or less if it is unconfirmed.
+fn find_spend(
+    client: &Client,
+    outpoint: &OutPoint,
+    script_pubkey: &Script,
+) -> Result<Option<(Transaction, i32)>> {
+    for entry in client.script_get_history(script_pubkey)? {
+        if entry.tx_hash == outpoint.txid {
+            continue;
+        }
+
+        let tx = client.transaction_get(&entry.tx_hash)?;
+        if tx
+            .input
+            .iter()
+            .any(|input| input.previous_output == *outpoint)
+        {
+            return Ok(Some((tx, entry.height)));
+        }
+    }
+
+    Ok(None)
+}
+
+fn confirmed_tx(client: &Client, tx: Transaction, height: i32) -> Result<Option<ConfirmedTx>> {
+    if height <= 0 {
+        return Ok(None);
+    }
+
+    let height = height as usize;
+    let merkle = client.transaction_get_merkle(&tx.txid(), height)?;
+    let block_header = client.block_header(height)?;
+
+    Ok(Some(ConfirmedTx {
+        tx,
+        block_header,
+        block_height: height as u32,
+        pos: merkle.pos,
+    }))
+}
+
+impl ConfirmationSource for Client {
+    fn tip(&self) -> Result<(BlockHeader, u32)> {
+        let tip = self.block_headers_subscribe()?;
+
+        Ok((tip.header, tip.height as u32))
+    }
+
+    fn get_confirmed_tx(
+        &self,
+        txid: &Txid,
+        script_pubkey: Option<&Script>,
+    ) -> Result<Option<ConfirmedTx>> {
+        let tx = match self.transaction_get(txid) {
+            Ok(tx) => tx,
+            Err(e) => {
+                tracing::trace!(%txid, "Could not get transaction: {e:#}");
+                return Ok(None);
+            }
+        };
+
+        // Electrum servers index transactions by script, so we need one to look up the height.
+        let script_pubkey = match script_pubkey {
+            Some(script_pubkey) => script_pubkey.clone(),
+            None => match tx.output.first() {
+                Some(output) => output.script_pubkey.clone(),
+                None => return Ok(None),
+            },
+        };
+
+        let entry = self
+            .script_get_history(&script_pubkey)?
+            .into_iter()
+            .find(|entry| entry.tx_hash == *txid);
+
+        match entry {
+            Some(entry) => confirmed_tx(self, tx, entry.height),
+            None => Ok(None),
+        }
+    }
+
+    // The history of the script tells us about the spend, no blocks have to be searched.
+    fn get_confirmed_spend(
+        &self,
+        output: &WatchedOutput,
+        _: Option<u32>,
+    ) -> Result<Option<ConfirmedTx>> {
+        let outpoint = output.outpoint.into_bitcoin_outpoint();
+        match find_spend(self, &outpoint, &output.script_pubkey)? {
+            Some((tx, height)) => confirmed_tx(self, tx, height),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! The blockchain backends of the node.
+//!
+//! The node can get its view of the blockchain from an Esplora server, a bitcoind node via JSON-RPC
+//! (with the `bitcoind` feature) or an Electrum server (with the `electrum` feature). Each backend
+//! is used for three things:
+//!
+//! - syncing the BDK on-chain wallet, via [`bdk::blockchain::AnyBlockchain`];
+//! - syncing LDK, via [`LightningSync`];
+//! - ad-hoc queries such as fee estimates and transaction confirmations, via [`ChainClient`].
+
+use crate::ln::TracingLogger;
+#[cfg(feature = "electrum")]
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "bitcoind")]
+use bdk::bitcoincore_rpc;
+#[cfg(feature = "bitcoind")]
+use bdk::bitcoincore_rpc::RpcApi;
+#[cfg(feature = "electrum")]
+use bdk::blockchain::electrum::ElectrumBlockchain;
+#[cfg(feature = "electrum")]
+use bdk::blockchain::electrum::ElectrumBlockchainConfig;
+#[cfg(feature = "bitcoind")]
+use bdk::blockchain::rpc::RpcBlockchain;
+#[cfg(feature = "bitcoind")]
+use bdk::blockchain::rpc::RpcConfig;
+use bdk::blockchain::AnyBlockchain;
+#[cfg(any(feature = "bitcoind", feature = "electrum"))]
+use bdk::blockchain::ConfigurableBlockchain;
+use bdk::blockchain::EsploraBlockchain;
+#[cfg(feature = "electrum")]
+use bdk::electrum_client;
+#[cfg(feature = "electrum")]
+use bdk::electrum_client::ElectrumApi;
+use bdk::FeeRate;
+use bitcoin::Block;
+use bitcoin::Network;
+use bitcoin::OutPoint;
+use bitcoin::Script;
+use bitcoin::Txid;
+use lightning::chain::Confirm;
+use lightning::chain::Filter;
+use lightning::chain::WatchedOutput;
+use lightning_transaction_sync::EsploraSyncClient;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(feature = "bitcoind")]
+mod bitcoind;
+#[cfg(any(feature = "bitcoind", feature = "electrum", test))]
+mod confirm_sync;
+#[cfg(feature = "electrum")]
+mod electrum;
+
+#[cfg(any(feature = "bitcoind", feature = "electrum"))]
+use confirm_sync::ConfirmSync;
+
+/// Where the node gets its view of the blockchain from.
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ChainSource {
+    Esplora {
+        url: String,
+    },
+    /// A bitcoind node with `txindex=1`, so that transactions not belonging to the wallet can be
+    /// looked up.
+    #[cfg(feature = "bitcoind")]
+    Bitcoind {
+        url: String,
+        user: String,
+        password: String,
+    },
+    #[cfg(feature = "electrum")]
+    Electrum {
+        url: String,
+    },
+}
+
+impl fmt::Debug for ChainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainSource::Esplora { url } => f.debug_struct("Esplora").field("url", url).finish(),
+            #[cfg(feature = "bitcoind")]
+            ChainSource::Bitcoind { url, user, .. } => f
+                .debug_struct("Bitcoind")
+                .field("url", url)
+                .field("user", user)
+                .finish_non_exhaustive(),
+            #[cfg(feature = "electrum")]
+            ChainSource::Electrum { url } => f.debug_struct("Electrum").field("url", url).finish(),
+        }
+    }
+}
+
+impl ChainSource {
+    /// A BDK blockchain to sync the on-chain wallet with.
+    ///
+    /// `wallet_name` is the name of the watch-only wallet created in bitcoind, it has to be unique
+    /// per wallet.
+    #[cfg_attr(not(feature = "bitcoind"), allow(unused_variables))]
+    pub fn wallet_blockchain(
+        &self,
+        network: Network,
+        wallet_name: String,
+        stop_gap: usize,
+        concurrency: u8,
+    ) -> Result<AnyBlockchain> {
+        let blockchain = match self {
+            ChainSource::Esplora { url } => {
+                let client = esplora_client::BlockingClient::from_agent(url.clone(), ureq::agent());
+
+                EsploraBlockchain::from_client(client, stop_gap)
+                    .with_concurrency(concurrency)
+                    .into()
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainSource::Bitcoind {
+                url,
+                user,
+                password,
+            } => RpcBlockchain::from_config(&RpcConfig {
+                url: url.clone(),
+                auth: bdk::blockchain::rpc::Auth::UserPass {
+                    username: user.clone(),
+                    password: password.clone(),
+                },
+                network,
+                wallet_name,
+                sync_params: None,
+            })?
+            .into(),
+            #[cfg(feature = "electrum")]
+            ChainSource::Electrum { url } => {
+                ElectrumBlockchain::from_config(&ElectrumBlockchainConfig {
+                    url: url.clone(),
+                    socks5: None,
+                    retry: 3,
+                    timeout: None,
+                    stop_gap,
+                    validate_domain: true,
+                })?
+                .into()
+            }
+        };
+
+        Ok(blockchain)
+    }
+}
+
+/// The status of a transaction output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutputStatus {
+    Unspent,
+    /// Spent by a transaction which is not confirmed yet.
+    SpentInMempool,
+    /// Spent by a transaction confirmed at `confirmation_height`, if the backend can tell.
+    Spent {
+        confirmation_height: Option<u32>,
+    },
+}
+
+/// A client for ad-hoc queries against the [`ChainSource`].
+pub(crate) enum ChainClient {
+    Esplora(esplora_client::BlockingClient),
+    #[cfg(feature = "bitcoind")]
+    Bitcoind(Arc<bitcoincore_rpc::Client>),
+    #[cfg(feature = "electrum")]
+    Electrum(Arc<electrum_client::Client>),
+}
+
+impl ChainClient {
+    pub fn new(source: &ChainSource) -> Result<Self> {
+        let client = match source {
+            ChainSource::Esplora { url } => ChainClient::Esplora(
+                esplora_client::BlockingClient::from_agent(url.clone(), ureq::agent()),
+            ),
+            #[cfg(feature = "bitcoind")]
+            ChainSource::Bitcoind {
+                url,
+                user,
+                password,
+            } => {
+                let auth = bitcoincore_rpc::Auth::UserPass(user.clone(), password.clone());
+                let client = bitcoincore_rpc::Client::new(url, auth)
+                    .with_context(|| format!("Could not connect to bitcoind at {url}"))?;
+                ChainClient::Bitcoind(Arc::new(client))
+            }
+            #[cfg(feature = "electrum")]
+            ChainSource::Electrum { url } => {
+                let client = electrum_client::Client::new(url)
+                    .with_context(|| format!("Could not connect to Electrum server at {url}"))?;
+                ChainClient::Electrum(Arc::new(client))
+            }
+        };
+
+        Ok(client)
+    }
+
+    /// The fee rate needed for a transaction to confirm within `n_blocks`.
+    pub fn estimate_fee(&self, n_blocks: usize) -> Result<FeeRate> {
+        let sats_per_vbyte = match self {
+            ChainClient::Esplora(client) => {
+                let estimates = client.get_fee_estimates()?;
+                esplora_client::convert_fee_rate(n_blocks, estimates)?
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => bitcoind::estimate_fee(client, n_blocks)?,
+            #[cfg(feature = "electrum")]
+            ChainClient::Electrum(client) => electrum::estimate_fee(client, n_blocks)?,
+        };
+
+        Ok(FeeRate::from_sat_per_vb(sats_per_vbyte))
+    }
+
+    /// The height of the block the transaction is confirmed in, or `None` if it is unconfirmed.
+    pub fn confirmation_height(&self, txid: &Txid) -> Result<Option<u32>> {
+        let height = match self {
+            ChainClient::Esplora(client) => client
+                .get_tx_status(txid)?
+                .and_then(|status| status.block_height),
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => bitcoind::confirmation_height(client, txid)?,
+            #[cfg(feature = "electrum")]
+            ChainClient::Electrum(client) => electrum::confirmation_height(client, txid)?,
+        };
+
+        Ok(height)
+    }
+
+    pub fn get_height(&self) -> Result<u32> {
+        let height = match self {
+            ChainClient::Esplora(client) => client.get_height()?,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_block_count()? as u32,
+            #[cfg(feature = "electrum")]
+            ChainClient::Electrum(client) => client.block_headers_subscribe()?.height as u32,
+        };
+
+        Ok(height)
+    }
+
+    pub fn get_block_at_height(&self, height: u32) -> Result<Block> {
+        match self {
+            ChainClient::Esplora(client) => {
+                let block_hash = client.get_block_hash(height)?;
+                client
+                    .get_block_by_hash(&block_hash)?
+                    .with_context(|| format!("Could not find block {block_hash}"))
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => {
+                let block_hash = client.get_block_hash(height as u64)?;
+                Ok(client.get_block(&block_hash)?)
+            }
+            #[cfg(feature = "electrum")]
+            ChainClient::Electrum(_) => bail!("Electrum servers do not serve full blocks"),
+        }
+    }
+
+    /// Whether `outpoint`, paying to `script_pubkey`, has been spent.
+    #[cfg_attr(not(feature = "electrum"), allow(unused_variables))]
+    pub fn get_output_status(
+        &self,
+        outpoint: &OutPoint,
+        script_pubkey: &Script,
+    ) -> Result<OutputStatus> {
+        match self {
+            ChainClient::Esplora(client) => {
+                let status = client.get_output_status(&outpoint.txid, outpoint.vout as u64)?;
+                let status = match status {
+                    Some(esplora_client::OutputStatus {
+                        spent: true,
+                        status: Some(status),
+                        ..
+                    }) if status.confirmed => OutputStatus::Spent {
+                        confirmation_height: status.block_height,
+                    },
+                    Some(esplora_client::OutputStatus { spent: true, .. }) => {
+                        OutputStatus::SpentInMempool
+                    }
+                    Some(_) | None => OutputStatus::Unspent,
+                };
+
+                Ok(status)
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => bitcoind::output_status(client, outpoint),
+            #[cfg(feature = "electrum")]
+            ChainClient::Electrum(client) => {
+                electrum::output_status(client, outpoint, script_pubkey)
+            }
+        }
+    }
+}
+
+/// Keeps LDK informed about the transactions it is interested in.
+pub(crate) enum LightningSync {
+    Esplora(EsploraSyncClient<Arc<TracingLogger>>),
+    #[cfg(feature = "bitcoind")]
+    Bitcoind(ConfirmSync<bitcoincore_rpc::Client>),
+    #[cfg(feature = "electrum")]
+    Electrum(ConfirmSync<electrum_client::Client>),
+}
+
+impl LightningSync {
+    pub fn new(source: &ChainSource, client: &ChainClient, logger: Arc<TracingLogger>) -> Self {
+        match client {
+            ChainClient::Esplora(_) => {
+                #[allow(irrefutable_let_patterns)]
+                let ChainSource::Esplora { url } = source else {
+                    unreachable!("Esplora client for non-Esplora chain source");
+                };
+                LightningSync::Esplora(EsploraSyncClient::new(url.clone(), logger))
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => {
+                LightningSync::Bitcoind(ConfirmSync::new(client.clone()))
+            }
+            #[cfg(feature = "electrum")]
+            ChainClient::Electrum(client) => {
+                LightningSync::Electrum(ConfirmSync::new(client.clone()))
+            }
+        }
+    }
+
+    pub fn sync(&self, confirmables: Vec<&(dyn Confirm + Sync + Send)>) -> Result<()> {
+        match self {
+            LightningSync::Esplora(client) => client.sync(confirmables)?,
+            #[cfg(feature = "bitcoind")]
+            LightningSync::Bitcoind(client) => client.sync(confirmables)?,
+            #[cfg(feature = "electrum")]
+            LightningSync::Electrum(client) => client.sync(confirmables)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Filter for LightningSync {
+    fn register_tx(&self, txid: &Txid, script_pubkey: &Script) {
+        match self {
+            LightningSync::Esplora(client) => client.register_tx(txid, script_pubkey),
+            #[cfg(feature = "bitcoind")]
+            LightningSync::Bitcoind(client) => client.register_tx(txid, script_pubkey),
+            #[cfg(feature = "electrum")]
+            LightningSync::Electrum(client) => client.register_tx(txid, script_pubkey),
+        }
+    }
+
+    fn register_output(&self, output: WatchedOutput) {
+        match self {
+            LightningSync::Esplora(client) => client.register_output(output),
+            #[cfg(feature = "bitcoind")]
+            LightningSync::Bitcoind(client) => client.register_output(output),
+            #[cfg(feature = "electrum")]
+            LightningSync::Electrum(client) => client.register_output(output),
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! We should reimplement some of these traits for production.
 
 use crate::ln_dlc_wallet::LnDlcWallet;
+use crate::node::Storage;
 use anyhow::anyhow;
 use anyhow::Result;
 use bitcoin::Script;
@@ -35,13 +36,20 @@ pub struct CustomSigner {
     in_memory_signer: Arc<Mutex<InMemorySigner>>,
     // TODO(tibo): this might not be safe.
     channel_public_keys: ChannelPublicKeys,
+    /// Remembers the counterparty commitment transactions we sign, so that the node can hand
+    /// their penalties to the watchtower once they are revoked.
+    storage: Arc<dyn Storage + Send + Sync + 'static>,
 }
 
 impl CustomSigner {
-    pub fn new(in_memory_signer: InMemorySigner) -> Self {
+    pub(crate) fn new(
+        in_memory_signer: InMemorySigner,
+        storage: Arc<dyn Storage + Send + Sync + 'static>,
+    ) -> Self {
         Self {
             channel_public_keys: in_memory_signer.pubkeys().clone(),
             in_memory_signer: Arc::new(Mutex::new(in_memory_signer)),
+            storage,
         }
     }
 
@@ -55,6 +63,7 @@ impl Clone for CustomSigner {
         Self {
             in_memory_signer: self.in_memory_signer.clone(),
             channel_public_keys: self.channel_public_keys.clone(),
+            storage: self.storage.clone(),
         }
     }
 }
@@ -72,16 +81,51 @@ impl EcdsaChannelSigner for CustomSigner {
         ),
         (),
     > {
-        self.in_memory_signer_lock().sign_counterparty_commitment(
-            commitment_tx,
-            preimages,
-            secp_ctx,
-        )
+        let (signatures, channel_keys_id) = {
+            let signer = self.in_memory_signer_lock();
+            let signatures =
+                signer.sign_counterparty_commitment(commitment_tx, preimages, secp_ctx)?;
+
+            (signatures, signer.channel_keys_id())
+        };
+
+        // We must not hand out a signature for a commitment transaction we could not penalise
+        // once it is revoked.
+        if let Err(e) = self.storage.insert_counterparty_commitment(
+            channel_keys_id,
+            commitment_tx.commitment_number(),
+            commitment_tx
+                .trust()
+                .built_transaction()
+                .transaction
+                .clone(),
+        ) {
+            tracing::error!("Failed to store counterparty commitment transaction: {e:#}");
+            return Err(());
+        }
+
+        Ok(signatures)
     }
 
     fn validate_counterparty_revocation(&self, idx: u64, secret: &SecretKey) -> Result<(), ()> {
-        self.in_memory_signer_lock()
-            .validate_counterparty_revocation(idx, secret)
+        let channel_keys_id = {
+            let signer = self.in_memory_signer_lock();
+            signer.validate_counterparty_revocation(idx, secret)?;
+
+            signer.channel_keys_id()
+        };
+
+        if let Err(e) = self
+            .storage
+            .revoke_counterparty_commitment(channel_keys_id, idx)
+        {
+            tracing::error!(
+                commitment_number = idx,
+                "Failed to mark counterparty commitment transaction as revoked: {e:#}"
+            );
+        }
+
+        Ok(())
     }
 
     fn sign_holder_commitment_and_htlcs(
@@ -247,13 +291,21 @@ impl Writeable for CustomSigner {
 pub struct CustomKeysManager {
     keys_manager: KeysManager,
     wallet: Arc<LnDlcWallet>,
+    /// Shared with every signer, so that the node can hand the penalties of revoked transactions
+    /// to the watchtower.
+    storage: Arc<dyn Storage + Send + Sync + 'static>,
 }
 
 impl CustomKeysManager {
-    pub fn new(keys_manager: KeysManager, wallet: Arc<LnDlcWallet>) -> Self {
+    pub fn new(
+        keys_manager: KeysManager,
+        wallet: Arc<LnDlcWallet>,
+        storage: Arc<dyn Storage + Send + Sync + 'static>,
+    ) -> Self {
         Self {
             keys_manager,
             wallet,
+            storage,
         }
     }
 
@@ -305,7 +357,7 @@ impl SignerProvider for CustomKeysManager {
 
     fn read_chan_signer(&self, reader: &[u8]) -> Result<Self::Signer, DecodeError> {
         let in_memory = self.keys_manager.read_chan_signer(reader)?;
-        Ok(CustomSigner::new(in_memory))
+        Ok(CustomSigner::new(in_memory, self.storage.clone()))
     }
 
     fn generate_channel_keys_id(
@@ -326,12 +378,8 @@ impl SignerProvider for CustomKeysManager {
         let inner = self
             .keys_manager
             .derive_channel_signer(channel_value_satoshis, channel_keys_id);
-        let pubkeys = inner.pubkeys();
 
-        CustomSigner {
-            channel_public_keys: pubkeys.clone(),
-            in_memory_signer: Arc::new(Mutex::new(inner)),
-        }
+        CustomSigner::new(inner, self.storage.clone())
     }
 }
 
@@ -1,3 +1,4 @@
+use crate::chain::ChainClient;
 use anyhow::Result;
 use bdk::FeeRate;
 use lightning::chain::chaininterface::ConfirmationTarget;
@@ -5,6 +6,7 @@ use lightning::chain::chaininterface::FeeEstimator;
 use lightning::chain::chaininterface::FEERATE_FLOOR_SATS_PER_KW;
 use parking_lot::RwLock;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 const CONFIRMATION_TARGETS: [(ConfirmationTarget, usize); 3] = [
     // We choose an extremely high background confirmation target to avoid force-closing channels
@@ -25,7 +27,7 @@ const FEE_RATE_DEFAULTS: [(ConfirmationTarget, u32); 3] = [
 ];
 
 pub struct FeeRateEstimator {
-    client: esplora_client::BlockingClient,
+    client: Arc<ChainClient>,
     fee_rate_cache: RwLock<HashMap<ConfirmationTarget, FeeRate>>,
 }
 
@@ -41,19 +43,14 @@ impl EstimateFeeRate for FeeRateEstimator {
 
 impl FeeRateEstimator {
     /// Constructor for the [`FeeRateEstimator`].
-    pub fn new(esplora_url: String) -> Self {
-        let client = esplora_client::BlockingClient::from_agent(esplora_url, ureq::agent());
-
-        let initial_fee_rates = match client.get_fee_estimates() {
-            Ok(estimates) => {
-                HashMap::from_iter(CONFIRMATION_TARGETS.into_iter().map(|(target, n_blocks)| {
-                    let fee_rate = esplora_client::convert_fee_rate(n_blocks, estimates.clone())
-                        .expect("fee rates for our confirmation targets");
-                    let fee_rate = FeeRate::from_sat_per_vb(fee_rate);
-
-                    (target, fee_rate)
-                }))
-            }
+    pub(crate) fn new(client: Arc<ChainClient>) -> Self {
+        let estimates = CONFIRMATION_TARGETS
+            .into_iter()
+            .map(|(target, n_blocks)| Ok((target, client.estimate_fee(n_blocks)?)))
+            .collect::<Result<HashMap<_, _>>>();
+
+        let initial_fee_rates = match estimates {
+            Ok(estimates) => estimates,
             Err(e) => {
                 tracing::warn!(defaults = ?FEE_RATE_DEFAULTS, "Initializing fee rate cache with default values: {e:#}");
 
@@ -82,14 +79,13 @@ impl FeeRateEstimator {
     }
 
     pub(crate) async fn update(&self) -> Result<()> {
-        let estimates = self.client.get_fee_estimates()?;
-
-        let mut locked_fee_rate_cache = self.fee_rate_cache.write();
+        let mut estimates = Vec::new();
         for (target, n_blocks) in CONFIRMATION_TARGETS {
-            let fee_rate = esplora_client::convert_fee_rate(n_blocks, estimates.clone())?;
-
-            let fee_rate = FeeRate::from_sat_per_vb(fee_rate);
+            estimates.push((target, n_blocks, self.client.estimate_fee(n_blocks)?));
+        }
 
+        let mut locked_fee_rate_cache = self.fee_rate_cache.write();
+        for (target, n_blocks, fee_rate) in estimates {
             locked_fee_rate_cache.insert(target, fee_rate);
             tracing::trace!(
                 n_blocks_confirmation = %n_blocks,
@@ -10,10 +10,13 @@ use bdk::blockchain::GetHeight;
 use bdk::database::BatchDatabase;
 use bdk::wallet::AddressIndex;
 use bdk::FeeRate;
+use bdk::LocalUtxo;
 use bdk::SignOptions;
 use bdk::SyncOptions;
 use bdk::TransactionDetails;
+use bitcoin::consensus::encode::deserialize;
 use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::hex::FromHex;
 use bitcoin::BlockHash;
 use bitcoin::OutPoint;
 use bitcoin::Script;
@@ -23,6 +26,7 @@ use lightning::chain::chaininterface::BroadcasterInterface;
 use lightning::chain::chaininterface::ConfirmationTarget;
 use parking_lot::Mutex;
 use parking_lot::MutexGuard;
+use serde::Serialize;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
@@ -43,6 +47,50 @@ where
     node_storage: Arc<dyn Storage + Send + Sync + 'static>,
 }
 
+/// A transaction of the wallet which has not been confirmed yet.
+#[derive(Clone, Debug, Serialize)]
+pub struct UnconfirmedTransaction {
+    pub txid: Txid,
+    /// `None` if not all inputs belong to the wallet, e.g. for a channel close initiated by the
+    /// counterparty.
+    pub fee: Option<u64>,
+    pub vsize: usize,
+    /// In sats/vbyte.
+    pub fee_rate: Option<f32>,
+    /// Whether the transaction is one of our payments and signals replaceability, i.e. it can be
+    /// bumped with RBF. Channel transactions are never replaced.
+    pub is_rbf: bool,
+    pub sent: u64,
+    pub received: u64,
+    /// The transactions whose outputs are spent by this transaction.
+    pub parents: Vec<Txid>,
+}
+
+/// How the fee of an on-chain payment is determined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fee {
+    /// The estimated fee rate for the confirmation target.
+    Priority(ConfirmationTarget),
+    FeeRate(FeeRate),
+}
+
+/// A signed on-chain payment which has not been broadcast yet.
+#[derive(Clone, Debug)]
+pub struct PreparedPayment {
+    pub transaction: Transaction,
+    /// The amount received by the recipient, in sats.
+    pub amount: u64,
+    /// In sats.
+    pub fee: u64,
+}
+
+impl PreparedPayment {
+    /// In sats/vbyte.
+    pub fn fee_rate(&self) -> f32 {
+        self.fee as f32 / self.transaction.vsize() as f32
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct WalletSettings {
     pub max_allowed_tx_fee_rate_when_opening_channel: Option<u32>,
@@ -110,6 +158,14 @@ where
         self.fee_rate_estimator.estimate(confirmation_target)
     }
 
+    /// The fee rate to pay for `fee`.
+    pub fn resolve_fee_rate(&self, fee: Fee) -> FeeRate {
+        match fee {
+            Fee::Priority(target) => self.get_fee_rate(target),
+            Fee::FeeRate(fee_rate) => fee_rate,
+        }
+    }
+
     pub(crate) async fn create_funding_transaction(
         &self,
         output_script: Script,
@@ -181,75 +237,347 @@ where
         Ok(self.bdk_lock().get_balance()?)
     }
 
+    /// Creates and signs a transaction paying to the given address, without broadcasting it.
+    ///
+    /// If `amount_sat_or_drain` is `None` all available funds will be spent, i.e. the wallet will
+    /// be drained, or only the given `utxos` if any. If `utxos` is not empty, only these UTXOs are
+    /// spent.
+    pub fn prepare_payment(
+        &self,
+        address: &bitcoin::Address,
+        amount_sat_or_drain: Option<u64>,
+        fee: Fee,
+        utxos: &[OutPoint],
+    ) -> Result<PreparedPayment> {
+        let fee_rate = self.resolve_fee_rate(fee);
+
+        let locked_wallet = self.bdk_lock();
+        let mut tx_builder = locked_wallet.build_tx();
+
+        if !utxos.is_empty() {
+            tx_builder.add_utxos(utxos)?.manually_selected_only();
+        }
+
+        match amount_sat_or_drain {
+            Some(amount_sats) => {
+                tx_builder.add_recipient(address.script_pubkey(), amount_sats);
+            }
+            None => {
+                if utxos.is_empty() {
+                    tx_builder.drain_wallet();
+                }
+                tx_builder.drain_to(address.script_pubkey());
+            }
+        }
+
+        tx_builder.fee_rate(fee_rate).enable_rbf();
+
+        let (mut psbt, details) = tx_builder.finish()?;
+        tracing::trace!("Created PSBT: {:?}", psbt);
+
+        if !locked_wallet.sign(&mut psbt, SignOptions::default())? {
+            bail!("On chain creation failed");
+        }
+
+        let transaction = psbt.extract_tx();
+        let script_pubkey = address.script_pubkey();
+        let amount = transaction
+            .output
+            .iter()
+            .filter(|output| output.script_pubkey == script_pubkey)
+            .map(|output| output.value)
+            .sum();
+
+        Ok(PreparedPayment {
+            transaction,
+            amount,
+            fee: details.fee.unwrap_or_default(),
+        })
+    }
+
+    /// Loads the payment of a signed transaction created by [`Wallet::prepare_payment`], e.g. to
+    /// broadcast the exact transaction the user has confirmed after a dry run.
+    ///
+    /// Fails if the transaction does not pay `amount_sat_or_drain` to `address` or if any of its
+    /// inputs is no longer an unspent output of the wallet.
+    pub fn load_prepared_payment(
+        &self,
+        raw_tx: &str,
+        address: &bitcoin::Address,
+        amount_sat_or_drain: Option<u64>,
+    ) -> Result<PreparedPayment> {
+        let transaction: Transaction = Vec::<u8>::from_hex(raw_tx)
+            .ok()
+            .and_then(|bytes| deserialize(&bytes).ok())
+            .context("Invalid raw transaction")?;
+        let txid = transaction.txid();
+
+        let utxos = self.list_utxos()?;
+        let locked_outpoints = self.locked_outpoints();
+        let mut input_value = 0;
+        for input in transaction.input.iter() {
+            let outpoint = input.previous_output;
+            let utxo = utxos
+                .iter()
+                .find(|utxo| utxo.outpoint == outpoint)
+                .filter(|_| !locked_outpoints.contains(&outpoint))
+                .with_context(|| {
+                    format!("Input {outpoint} of transaction {txid} is not spendable anymore")
+                })?;
+            input_value += utxo.txout.value;
+        }
+
+        let script_pubkey = address.script_pubkey();
+        let amount = transaction
+            .output
+            .iter()
+            .filter(|output| output.script_pubkey == script_pubkey)
+            .map(|output| output.value)
+            .sum();
+        match amount_sat_or_drain {
+            Some(amount_sats) if amount != amount_sats => {
+                bail!("Transaction {txid} pays {amount} instead of {amount_sats} sats to {address}")
+            }
+            _ if amount == 0 => bail!("Transaction {txid} does not pay to {address}"),
+            _ => {}
+        }
+
+        let output_value: u64 = transaction.output.iter().map(|output| output.value).sum();
+        let fee = input_value
+            .checked_sub(output_value)
+            .with_context(|| format!("Transaction {txid} spends more than its inputs"))?;
+
+        Ok(PreparedPayment {
+            transaction,
+            amount,
+            fee,
+        })
+    }
+
     /// Send funds to the given address.
     ///
-    /// If `amount_sat_or_drain` is `None` the wallet will be drained, i.e., all available funds
-    /// will be spent.
-    pub(crate) fn send_to_address(
+    /// See [`Wallet::prepare_payment`] for the meaning of the arguments.
+    pub fn send_to_address(
         &self,
         address: &bitcoin::Address,
         amount_sat_or_drain: Option<u64>,
+        fee: Fee,
+        utxos: &[OutPoint],
     ) -> Result<Txid> {
-        let fee_rate = self.fee_rate_estimator.estimate(ConfirmationTarget::Normal);
+        let payment = self.prepare_payment(address, amount_sat_or_drain, fee, utxos)?;
+
+        let txid = self.broadcast_payment(&payment.transaction)?;
+
+        tracing::info!(
+            %txid,
+            %address,
+            amount = payment.amount,
+            fee = payment.fee,
+            "Created new transaction sending funds on-chain"
+        );
+
+        Ok(txid)
+    }
+
+    /// The UTXOs of the wallet, e.g. to select which ones to spend.
+    pub fn list_utxos(&self) -> Result<Vec<LocalUtxo>> {
+        Ok(self.bdk_lock().list_unspent()?)
+    }
+
+    /// The UTXOs spent by funding transactions which have been created since the last sync.
+    ///
+    /// These must not be spent by anything else, even though the wallet still lists them as
+    /// unspent.
+    pub fn locked_outpoints(&self) -> Vec<OutPoint> {
+        self.locked_outpoints.lock().clone()
+    }
+
+    /// The transactions of the wallet which are still waiting for confirmation.
+    ///
+    /// A transaction is only reported as replaceable if it signals RBF and it is one of our
+    /// payments, i.e. it is not a channel transaction.
+    pub fn unconfirmed_transactions(&self) -> Result<Vec<UnconfirmedTransaction>> {
+        let transactions = self
+            .bdk_lock()
+            .list_transactions(true)
+            .context("Failed to list on chain transactions")?;
+
+        let unconfirmed = transactions
+            .into_iter()
+            .filter(|details| details.confirmation_time.is_none())
+            .filter_map(|details| {
+                let transaction = details.transaction?;
+                let vsize = transaction.vsize();
+                let mut parents = transaction
+                    .input
+                    .iter()
+                    .map(|input| input.previous_output.txid)
+                    .collect::<Vec<_>>();
+                parents.sort();
+                parents.dedup();
+                let is_replaceable = match self
+                    .node_storage
+                    .get_transaction(&details.txid.to_string())
+                {
+                    Ok(stored) => stored.map(|tx| tx.is_replaceable()).unwrap_or(false),
+                    Err(e) => {
+                        tracing::error!(txid = %details.txid, "Failed to load transaction: {e:#}");
+                        false
+                    }
+                };
+
+                Some(UnconfirmedTransaction {
+                    txid: details.txid,
+                    fee: details.fee,
+                    vsize,
+                    fee_rate: details.fee.map(|fee| fee as f32 / vsize as f32),
+                    is_rbf: is_replaceable
+                        && transaction
+                            .input
+                            .iter()
+                            .any(|input| input.sequence.is_rbf()),
+                    sent: details.sent,
+                    received: details.received,
+                    parents,
+                })
+            })
+            .collect();
+
+        Ok(unconfirmed)
+    }
+
+    /// Replaces the unconfirmed transaction `txid` with one paying `fee_rate` (RBF).
+    ///
+    /// The transaction must be one of our payments, signal replaceability and all its inputs must
+    /// belong to the wallet. Channel transactions, e.g. funding transactions, are never replaced
+    /// because that would change their txid; use [`Wallet::cpfp`] for them instead.
+    ///
+    /// The outputs of the replacement are reduced to pay for the additional fee only if the
+    /// transaction has no change output, e.g. if the wallet was drained.
+    pub fn bump_fee(&self, txid: &Txid, fee_rate: FeeRate) -> Result<Txid> {
+        let replaceable = self
+            .node_storage
+            .get_transaction(&txid.to_string())?
+            .map(|tx| tx.is_replaceable())
+            .unwrap_or(false);
+        if !replaceable {
+            bail!("Transaction {txid} must not be replaced, bump its fee with CPFP instead");
+        }
 
         let tx = {
             let locked_wallet = self.bdk_lock();
-            let mut tx_builder = locked_wallet.build_tx();
 
-            if let Some(amount_sats) = amount_sat_or_drain {
-                tx_builder
-                    .add_recipient(address.script_pubkey(), amount_sats)
-                    .fee_rate(fee_rate)
-                    .enable_rbf();
-            } else {
-                tx_builder
-                    .drain_wallet()
-                    .drain_to(address.script_pubkey())
-                    .fee_rate(fee_rate)
-                    .enable_rbf();
+            let mut tx_builder = locked_wallet
+                .build_fee_bump(*txid)
+                .with_context(|| format!("Cannot bump fee of transaction {txid}"))?;
+            tx_builder.fee_rate(fee_rate).enable_rbf();
+
+            let (mut psbt, _) = tx_builder
+                .finish()
+                .with_context(|| format!("Failed to create replacement for {txid}"))?;
+
+            if !locked_wallet.sign(&mut psbt, SignOptions::default())? {
+                bail!("Failed to sign replacement for {txid}");
             }
 
-            let mut psbt = match tx_builder.finish() {
-                Ok((psbt, _)) => {
-                    tracing::trace!("Created PSBT: {:?}", psbt);
-                    psbt
-                }
-                Err(err) => {
-                    bail!(err)
+            psbt.extract_tx()
+        };
+
+        let replacement = self.broadcast_payment(&tx)?;
+
+        tracing::info!(
+            %txid,
+            %replacement,
+            fee_rate = fee_rate.as_sat_per_vb(),
+            "Replaced transaction to bump its fee"
+        );
+
+        Ok(replacement)
+    }
+
+    /// Spends the outputs of the unconfirmed transaction `txid` which belong to the wallet back to
+    /// the wallet, so that both transactions together pay `fee_rate` (CPFP).
+    ///
+    /// Unlike [`Wallet::bump_fee`], this also works for transactions which do not signal
+    /// replaceability or have inputs of other parties, e.g. a channel close.
+    pub fn cpfp(&self, txid: &Txid, fee_rate: FeeRate) -> Result<Txid> {
+        let tx = {
+            let locked_wallet = self.bdk_lock();
+
+            let parent = locked_wallet
+                .get_tx(txid, true)?
+                .with_context(|| format!("Unknown transaction {txid}"))?;
+            if parent.confirmation_time.is_some() {
+                bail!("Transaction {txid} is already confirmed");
+            }
+            let parent_vsize = parent
+                .transaction
+                .as_ref()
+                .map(|transaction| transaction.vsize())
+                .with_context(|| format!("Missing raw transaction {txid}"))?;
+            // If we do not know the fee of the parent, the child pays for both transactions.
+            let parent_fee = parent.fee.unwrap_or_default();
+
+            let outpoints = locked_wallet
+                .list_unspent()?
+                .into_iter()
+                .filter(|utxo| utxo.outpoint.txid == *txid)
+                .map(|utxo| utxo.outpoint)
+                .collect::<Vec<_>>();
+            if outpoints.is_empty() {
+                bail!("Transaction {txid} has no unspent outputs of ours to bump it with CPFP");
+            }
+
+            let drain_to = locked_wallet
+                .get_address(AddressIndex::LastUnused)?
+                .script_pubkey();
+            let build_child = |fee: Option<u64>| -> Result<(Transaction, u64)> {
+                let mut tx_builder = locked_wallet.build_tx();
+                tx_builder
+                    .add_utxos(&outpoints)?
+                    .manually_selected_only()
+                    .drain_to(drain_to.clone())
+                    .enable_rbf();
+                match fee {
+                    Some(fee) => tx_builder.fee_absolute(fee),
+                    None => tx_builder.fee_rate(fee_rate),
+                };
+
+                let (mut psbt, details) = tx_builder.finish()?;
+                if !locked_wallet.sign(&mut psbt, SignOptions::default())? {
+                    bail!("Failed to sign child of {txid}");
                 }
+
+                Ok((psbt.extract_tx(), details.fee.unwrap_or_default()))
             };
 
-            match locked_wallet.sign(&mut psbt, SignOptions::default()) {
-                Ok(finalized) => {
-                    if !finalized {
-                        bail!("On chain creation failed");
-                    }
-                }
-                Err(err) => {
-                    bail!(err)
-                }
+            // The first draft only tells us the size of the child.
+            let (child, child_fee) =
+                build_child(None).with_context(|| format!("Failed to create child of {txid}"))?;
+
+            let package_fee =
+                (fee_rate.as_sat_per_vb() * (parent_vsize + child.vsize()) as f32).ceil() as u64;
+            let fee = package_fee.saturating_sub(parent_fee);
+
+            if fee <= child_fee {
+                child
+            } else {
+                build_child(Some(fee))
+                    .with_context(|| format!("Failed to create child of {txid}"))?
+                    .0
             }
-            psbt.extract_tx()
         };
 
-        let txid = self.broadcast_transaction(&tx)?;
+        let child = self.broadcast_payment(&tx)?;
 
-        if let Some(amount_sats) = amount_sat_or_drain {
-            tracing::info!(
-                "Created new transaction {} sending {}sats on-chain to address {}",
-                txid,
-                amount_sats,
-                address
-            );
-        } else {
-            tracing::info!(
-                "Created new transaction {} sending all available on-chain funds to address {}",
-                txid,
-                address
-            );
-        }
+        tracing::info!(
+            %txid,
+            %child,
+            fee_rate = fee_rate.as_sat_per_vb(),
+            "Created child transaction to bump fee"
+        );
 
-        Ok(txid)
+        Ok(child)
     }
 
     pub fn tip(&self) -> Result<(u32, BlockHash)> {
@@ -272,12 +600,27 @@ where
         Ok(transaction_details)
     }
 
+    /// Broadcasts one of our own payments, whose fee may later be bumped with RBF.
+    pub fn broadcast_payment(&self, tx: &Transaction) -> Result<Txid> {
+        let transaction: crate::transaction::Transaction = tx.into();
+        self.broadcast(tx, transaction.replaceable())
+    }
+
+    /// Broadcasts a transaction which must not be replaced, e.g. a channel transaction.
     pub fn broadcast_transaction(&self, tx: &Transaction) -> Result<Txid> {
+        self.broadcast(tx, tx.into())
+    }
+
+    fn broadcast(
+        &self,
+        tx: &Transaction,
+        transaction: crate::transaction::Transaction,
+    ) -> Result<Txid> {
         let txid = tx.txid();
 
         tracing::info!(%txid, raw_tx = %serialize_hex(&tx), "Broadcasting transaction");
 
-        if let Err(e) = self.node_storage.upsert_transaction(tx.into()) {
+        if let Err(e) = self.node_storage.upsert_transaction(transaction) {
             tracing::error!("Failed to store transaction {txid}. Error: {e:#}");
         }
 
@@ -311,6 +654,7 @@ pub mod tests {
     use crate::channel::Channel;
     use crate::fee_rate_estimator::EstimateFeeRate;
     use crate::ldk_node_wallet::Wallet;
+    use crate::node::InMemoryStore;
     use anyhow::Result;
     use bdk::blockchain::Blockchain;
     use bdk::blockchain::Capability;
@@ -378,6 +722,132 @@ pub mod tests {
             .is_err());
     }
 
+    #[test]
+    fn confirmed_transactions_cannot_be_bumped() {
+        let mut rng = thread_rng();
+        let test_wallet = new_test_wallet(&mut rng, Amount::from_btc(1.0).unwrap(), 1).unwrap();
+        let wallet = Wallet::new(
+            DummyEsplora,
+            test_wallet,
+            Arc::new(DummyFeeRateEstimator),
+            Arc::new(InMemoryStore::default()),
+        );
+
+        assert!(wallet.unconfirmed_transactions().unwrap().is_empty());
+
+        let txid = wallet.on_chain_transaction_list().unwrap()[0].txid;
+        let fee_rate = FeeRate::from_sat_per_vb(10.0);
+        assert!(wallet.bump_fee(&txid, fee_rate).is_err());
+        assert!(wallet.cpfp(&txid, fee_rate).is_err());
+    }
+
+    #[tokio::test]
+    async fn funding_transactions_cannot_be_replaced() {
+        let mut rng = thread_rng();
+        let test_wallet = new_test_wallet(&mut rng, Amount::from_btc(1.0).unwrap(), 1).unwrap();
+        let node_storage = Arc::new(InMemoryStore::default());
+        let wallet = Wallet::new(
+            DummyEsplora,
+            test_wallet,
+            Arc::new(DummyFeeRateEstimator),
+            node_storage.clone(),
+        );
+
+        let funding_tx = wallet
+            .create_funding_transaction(
+                Script::new(),
+                Amount::from_btc(0.5).unwrap().to_sat(),
+                ConfirmationTarget::Background,
+            )
+            .await
+            .unwrap();
+        // LDK hands the funding transaction to the broadcaster, which stores it.
+        node_storage
+            .upsert_transaction((&funding_tx).into())
+            .unwrap();
+
+        let txid = funding_tx.txid();
+        let replaceable = node_storage
+            .get_transaction(&txid.to_string())
+            .unwrap()
+            .unwrap()
+            .is_replaceable();
+        assert!(!replaceable);
+
+        let error = wallet
+            .bump_fee(&txid, FeeRate::from_sat_per_vb(10.0))
+            .unwrap_err();
+        assert!(error.to_string().contains("CPFP"));
+    }
+
+    #[test]
+    fn can_send_all_funds_of_selected_utxos() {
+        let mut rng = thread_rng();
+        let test_wallet = new_test_wallet(&mut rng, Amount::from_btc(1.0).unwrap(), 2).unwrap();
+        let wallet = Wallet::new(
+            DummyEsplora,
+            test_wallet,
+            Arc::new(DummyFeeRateEstimator),
+            Arc::new(DummyNodeStorage),
+        );
+        let address = wallet.get_last_unused_address().unwrap();
+        let fee = Fee::FeeRate(FeeRate::from_sat_per_vb(5.0));
+
+        let utxo = wallet.list_utxos().unwrap()[0].outpoint;
+        let payment = wallet
+            .prepare_payment(&address, None, fee, &[utxo])
+            .unwrap();
+        assert_eq!(payment.transaction.input.len(), 1);
+        assert_eq!(payment.transaction.output.len(), 1);
+        assert_eq!(
+            payment.amount + payment.fee,
+            Amount::from_btc(1.0).unwrap().to_sat()
+        );
+        assert!(payment.fee_rate() >= 5.0);
+
+        let payment = wallet.prepare_payment(&address, None, fee, &[]).unwrap();
+        assert_eq!(payment.transaction.input.len(), 2);
+        assert_eq!(
+            payment.amount + payment.fee,
+            Amount::from_btc(2.0).unwrap().to_sat()
+        );
+    }
+
+    #[test]
+    fn confirmed_payment_is_the_previewed_one() {
+        let mut rng = thread_rng();
+        let test_wallet = new_test_wallet(&mut rng, Amount::from_btc(1.0).unwrap(), 2).unwrap();
+        let wallet = Wallet::new(
+            DummyEsplora,
+            test_wallet,
+            Arc::new(DummyFeeRateEstimator),
+            Arc::new(InMemoryStore::default()),
+        );
+        let address = wallet.get_last_unused_address().unwrap();
+        let fee = Fee::FeeRate(FeeRate::from_sat_per_vb(5.0));
+
+        let preview = wallet
+            .prepare_payment(&address, Some(50_000), fee, &[])
+            .unwrap();
+        let raw_tx = serialize_hex(&preview.transaction);
+
+        let payment = wallet
+            .load_prepared_payment(&raw_tx, &address, Some(50_000))
+            .unwrap();
+        assert_eq!(payment.transaction, preview.transaction);
+        assert_eq!(payment.amount, preview.amount);
+        assert_eq!(payment.fee, preview.fee);
+
+        assert!(wallet
+            .load_prepared_payment(&raw_tx, &address, Some(60_000))
+            .is_err());
+
+        let other_address = bitcoin::Address::p2wsh(&Script::new(), Network::Regtest);
+        assert!(wallet
+            .load_prepared_payment(&raw_tx, &other_address, None)
+            .is_err());
+    }
+
     fn new_test_wallet(
         rng: &mut (impl RngCore + CryptoRng),
         utxo_amount: Amount,
@@ -494,6 +964,14 @@ pub mod tests {
             unimplemented!();
         }
 
+        fn set_payment_failure_reason(
+            &self,
+            _payment_hash: &lightning::ln::PaymentHash,
+            _failure_reason: crate::PaymentFailureReason,
+        ) -> Result<()> {
+            unimplemented!();
+        }
+
         fn get_payment(
             &self,
             _payment_hash: &lightning::ln::PaymentHash,
@@ -562,5 +1040,30 @@ pub mod tests {
         fn all_transactions_without_fees(&self) -> Result<Vec<crate::transaction::Transaction>> {
             unimplemented!();
         }
+
+        fn insert_counterparty_commitment(
+            &self,
+            _channel_keys_id: [u8; 32],
+            _commitment_number: u64,
+            _transaction: Transaction,
+        ) -> Result<()> {
+            unimplemented!();
+        }
+
+        fn revoke_counterparty_commitment(
+            &self,
+            _channel_keys_id: [u8; 32],
+            _commitment_number: u64,
+        ) -> Result<()> {
+            unimplemented!();
+        }
+
+        fn revoked_counterparty_commitments(&self) -> Result<Vec<Transaction>> {
+            unimplemented!();
+        }
+
+        fn delete_counterparty_commitment(&self, _txid: &Txid) -> Result<()> {
+            unimplemented!();
+        }
     }
 }
@@ -32,6 +32,7 @@ mod ln_dlc_wallet;
 mod on_chain_wallet;
 mod shadow;
 
+pub mod chain;
 pub mod channel;
 pub mod config;
 pub mod ln;
@@ -40,9 +41,14 @@ pub mod scorer;
 pub mod seed;
 pub mod transaction;
 pub mod util;
+pub mod watchtower;
 
+pub use chain::ChainSource;
 pub use config::CONFIRMATION_TARGET;
 pub use config::LIQUIDITY_MULTIPLIER;
+pub use ldk_node_wallet::Fee;
+pub use ldk_node_wallet::PreparedPayment;
+pub use ldk_node_wallet::UnconfirmedTransaction;
 pub use ldk_node_wallet::WalletSettings;
 pub use lightning;
 pub use lightning_invoice;
@@ -53,6 +59,7 @@ pub use ln::DlcChannelDetails;
 pub use ln::EventHandlerTrait;
 pub use ln::EventSender;
 pub use node::invoice::HTLCStatus;
+pub use node::invoice::PaymentFailureReason;
 
 #[cfg(test)]
 mod tests;
@@ -101,6 +108,8 @@ pub struct PaymentInfo {
     pub timestamp: OffsetDateTime,
     pub description: String,
     pub invoice: Option<String>,
+    /// Why the payment failed, if it did.
+    pub failure_reason: Option<PaymentFailureReason>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -146,6 +155,7 @@ impl From<Invoice> for PaymentInfo {
                 InvoiceDescription::Hash(hash) => hash.0.to_hex(),
             },
             invoice: Some(value.to_string()),
+            failure_reason: None,
         }
     }
 }
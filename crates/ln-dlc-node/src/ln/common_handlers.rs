@@ -1,9 +1,11 @@
 use super::event_handler::PendingInterceptedHtlcs;
 use crate::channel::Channel;
 use crate::config::CONFIRMATION_TARGET;
+use crate::node::hold_invoice::HeldPayment;
 use crate::node::invoice::HTLCStatus;
 use crate::node::ChannelManager;
 use crate::node::Node;
+use crate::node::PaymentAttemptOutcome;
 use crate::node::Storage;
 use crate::util;
 use crate::MillisatAmount;
@@ -22,6 +24,7 @@ use lightning::chain::keysinterface::SpendableOutputDescriptor;
 use lightning::ln::channelmanager::InterceptId;
 use lightning::ln::PaymentHash;
 use lightning::routing::gossip::NodeId;
+use lightning::routing::router::RouteHop;
 use lightning::util::events::PaymentPurpose;
 use rand::thread_rng;
 use rand::Rng;
@@ -32,12 +35,19 @@ use time::OffsetDateTime;
 use tokio::task::block_in_place;
 use uuid::Uuid;
 
-pub fn handle_payment_claimable(
-    channel_manager: &Arc<ChannelManager>,
+pub fn handle_payment_claimable<S>(
+    node: &Arc<Node<S>>,
     payment_hash: PaymentHash,
     purpose: PaymentPurpose,
     amount_msat: u64,
-) -> Result<()> {
+) -> Result<()>
+where
+    S: Storage,
+{
+    if node.hold_payment(payment_hash, amount_msat) != HeldPayment::NotHeld {
+        return Ok(());
+    }
+
     let payment_hash = util::hex_str(&payment_hash.0);
     let preimage = match purpose {
         PaymentPurpose::InvoicePayment {
@@ -51,7 +61,7 @@ pub fn handle_payment_claimable(
         }
     };
     tracing::info!(%payment_hash, %amount_msat, "Received payment");
-    channel_manager.claim_funds(preimage);
+    node.channel_manager.claim_funds(preimage);
     Ok(())
 }
 
@@ -190,6 +200,7 @@ where
                     timestamp: OffsetDateTime::now_utc(),
                     description: "".to_string(),
                     invoice: None,
+                    failure_reason: None,
                 },
             ) {
                 tracing::error!(
@@ -349,8 +360,11 @@ pub fn handle_payment_failed<S>(node: &Arc<Node<S>>, payment_hash: PaymentHash)
 where
     S: Storage,
 {
+    let failure_reason = node.payment_failure_reason(&payment_hash);
+
     tracing::warn!(
         payment_hash = %payment_hash.0.to_hex(),
+        %failure_reason,
         "Failed to send payment to payment hash: exhausted payment retry attempts",
     );
 
@@ -369,6 +383,88 @@ where
             "Failed to update failed payment: {e:#}"
         )
     }
+
+    if let Err(e) = node
+        .storage
+        .set_payment_failure_reason(&payment_hash, failure_reason)
+    {
+        tracing::error!(
+            payment_hash = %payment_hash.0.to_hex(),
+            "Failed to record failure reason of payment: {e:#}"
+        )
+    }
+}
+
+pub fn handle_payment_path_successful<S>(
+    node: &Arc<Node<S>>,
+    payment_hash: Option<PaymentHash>,
+    path: Vec<RouteHop>,
+) {
+    tracing::info!(?payment_hash, ?path, "Payment path successful");
+
+    if let Some(payment_hash) = payment_hash {
+        node.payment_attempts
+            .record(payment_hash, &path, false, PaymentAttemptOutcome::Succeeded);
+    }
+}
+
+pub fn handle_payment_path_failed<S>(
+    node: &Arc<Node<S>>,
+    payment_hash: PaymentHash,
+    payment_failed_permanently: bool,
+    short_channel_id: Option<u64>,
+    path: Vec<RouteHop>,
+) {
+    tracing::warn!(
+        payment_hash = %payment_hash.0.to_hex(),
+        %payment_failed_permanently,
+        ?short_channel_id,
+        "Payment path failed"
+    );
+
+    node.payment_attempts.record(
+        payment_hash,
+        &path,
+        false,
+        PaymentAttemptOutcome::Failed {
+            permanently: payment_failed_permanently,
+            short_channel_id,
+        },
+    );
+}
+
+pub fn handle_probe_successful<S>(
+    node: &Arc<Node<S>>,
+    payment_hash: PaymentHash,
+    path: Vec<RouteHop>,
+) {
+    tracing::debug!(payment_hash = %payment_hash.0.to_hex(), "Probe successful");
+
+    node.payment_attempts
+        .record(payment_hash, &path, true, PaymentAttemptOutcome::Succeeded);
+}
+
+pub fn handle_probe_failed<S>(
+    node: &Arc<Node<S>>,
+    payment_hash: PaymentHash,
+    short_channel_id: Option<u64>,
+    path: Vec<RouteHop>,
+) {
+    tracing::debug!(
+        payment_hash = %payment_hash.0.to_hex(),
+        ?short_channel_id,
+        "Probe failed"
+    );
+
+    node.payment_attempts.record(
+        payment_hash,
+        &path,
+        true,
+        PaymentAttemptOutcome::Failed {
+            permanently: false,
+            short_channel_id,
+        },
+    );
 }
 
 pub async fn handle_funding_generation_ready<S>(
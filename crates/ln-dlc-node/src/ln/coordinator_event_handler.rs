@@ -124,16 +124,24 @@ where
                 )?;
             }
             Event::PaymentPathSuccessful {
-                payment_id,
+                payment_hash, path, ..
+            } => {
+                common_handlers::handle_payment_path_successful(&self.node, payment_hash, path);
+            }
+            Event::PaymentPathFailed {
                 payment_hash,
+                payment_failed_permanently,
+                short_channel_id,
                 path,
+                ..
             } => {
-                tracing::info!(?payment_id, ?payment_hash, ?path, "Payment path successful");
-            }
-            Event::PaymentPathFailed { payment_hash, .. } => {
-                tracing::warn!(
-                    payment_hash = %payment_hash.0.to_hex(),
-                "Payment path failed");
+                common_handlers::handle_payment_path_failed(
+                    &self.node,
+                    payment_hash,
+                    payment_failed_permanently,
+                    short_channel_id,
+                    path,
+                );
             }
             Event::PaymentFailed { payment_hash, .. } => {
                 common_handlers::handle_payment_failed(&self.node, payment_hash);
@@ -180,8 +188,24 @@ where
             } => {
                 common_handlers::handle_discard_funding(transaction, channel_id);
             }
-            Event::ProbeSuccessful { .. } => {}
-            Event::ProbeFailed { .. } => {}
+            Event::ProbeSuccessful {
+                payment_hash, path, ..
+            } => {
+                common_handlers::handle_probe_successful(&self.node, payment_hash, path);
+            }
+            Event::ProbeFailed {
+                payment_hash,
+                short_channel_id,
+                path,
+                ..
+            } => {
+                common_handlers::handle_probe_failed(
+                    &self.node,
+                    payment_hash,
+                    short_channel_id,
+                    path,
+                );
+            }
             Event::ChannelReady {
                 channel_id,
                 counterparty_node_id,
@@ -233,7 +257,7 @@ where
                 via_user_channel_id: _,
             } => {
                 common_handlers::handle_payment_claimable(
-                    &self.node.channel_manager,
+                    &self.node,
                     payment_hash,
                     purpose,
                     amount_msat,
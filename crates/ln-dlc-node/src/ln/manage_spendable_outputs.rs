@@ -1,12 +1,11 @@
+use crate::chain::ChainClient;
+use crate::chain::OutputStatus;
 use crate::dlc_custom_signer::CustomKeysManager;
 use crate::fee_rate_estimator::FeeRateEstimator;
 use crate::ln_dlc_wallet::LnDlcWallet;
 use crate::node::Storage;
-use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
-use esplora_client::OutputStatus;
-use esplora_client::TxStatus;
 use lightning::chain::chaininterface::BroadcasterInterface;
 use lightning::chain::chaininterface::ConfirmationTarget;
 use lightning::chain::chaininterface::FeeEstimator;
@@ -26,7 +25,7 @@ const REQUIRED_CONFIRMATIONS: u32 = 6;
 /// Determine what to do with a [`SpendableOutputDescriptor`] and do it.
 pub fn manage_spendable_outputs(
     node_storage: Arc<impl Storage>,
-    esplora_client: impl Borrow<esplora_client::BlockingClient>,
+    chain_client: impl Borrow<ChainClient>,
     wallet: impl Borrow<LnDlcWallet>,
     fee_rate_estimator: impl Borrow<FeeRateEstimator>,
     keys_manager: impl Borrow<CustomKeysManager>,
@@ -35,7 +34,7 @@ pub fn manage_spendable_outputs(
 
     let spendable_outputs = &node_storage.all_spendable_outputs()?;
     for output in spendable_outputs.iter() {
-        let action = match choose_spendable_output_action(esplora_client.borrow(), output) {
+        let action = match choose_spendable_output_action(chain_client.borrow(), output) {
             Ok(action) => action,
             Err(e) => {
                 tracing::error!(
@@ -88,38 +87,38 @@ enum Action {
 /// Decide on which [`Action`] should be performed based on the characteristics and status of a
 /// [`SpendableOutputDescriptor`].
 fn choose_spendable_output_action(
-    esplora_client: &esplora_client::BlockingClient,
+    chain_client: &ChainClient,
     output: &SpendableOutputDescriptor,
 ) -> Result<Action> {
-    let esplora_client = esplora_client.borrow();
-
     use SpendableOutputDescriptor::*;
-    let outpoint = match output {
-        StaticPaymentOutput(StaticPaymentOutputDescriptor { outpoint, .. })
-        | DelayedPaymentOutput(DelayedPaymentOutputDescriptor { outpoint, .. }) => outpoint,
+    let (outpoint, txout) = match output {
+        StaticPaymentOutput(StaticPaymentOutputDescriptor {
+            outpoint, output, ..
+        })
+        | DelayedPaymentOutput(DelayedPaymentOutputDescriptor {
+            outpoint, output, ..
+        }) => (outpoint, output),
         // These are already owned by our on-chain wallet.
         StaticOutput { outpoint, .. } => return Ok(Action::Forget(*outpoint)),
     };
 
-    let output_status = esplora_client
-        .get_output_status(&outpoint.txid, outpoint.index.into())
+    let output_status = chain_client
+        .get_output_status(&outpoint.into_bitcoin_outpoint(), &txout.script_pubkey)
         .context("Could not get spendable output status")?;
 
     match output_status {
-        Some(OutputStatus { spent: false, .. }) | None => {
+        OutputStatus::Unspent => {
             tracing::debug!(?output, "Spendable output not yet spent");
             Ok(Action::Spend)
         }
-        Some(OutputStatus {
-            status:
-                Some(TxStatus {
-                    confirmed: true,
-                    block_height: Some(confirmation_height),
-                    ..
-                }),
-            ..
-        }) => {
-            let current_height = esplora_client.get_height()?;
+        OutputStatus::SpentInMempool => {
+            tracing::debug!(?output, "Spendable output spent by unconfirmed transaction");
+            Ok(Action::Monitor)
+        }
+        OutputStatus::Spent {
+            confirmation_height: Some(confirmation_height),
+        } => {
+            let current_height = chain_client.get_height()?;
 
             let confirmations = current_height
                 .checked_sub(confirmation_height)
@@ -151,8 +150,15 @@ fn choose_spendable_output_action(
                 Ok(Action::Monitor)
             }
         }
-        Some(_) => {
-            bail!("Spendable output in unexpected state: {output:?}");
+        // We cannot tell how deep the spend is buried yet, e.g. because the spending transaction
+        // has not been found in the blocks since the output was confirmed. Forgetting the output
+        // now could lose it on a re-org.
+        OutputStatus::Spent {
+            confirmation_height: None,
+        } => {
+            tracing::info!(?output, "Spendable output spent at unknown height");
+
+            Ok(Action::Monitor)
         }
     }
 }
@@ -17,6 +17,7 @@ pub use app_event_handler::AppEventHandler;
 pub use channel_details::ChannelDetails;
 pub use coordinator_event_handler::CoordinatorEventHandler;
 pub use dlc_channel_details::DlcChannelDetails;
+pub use dlc_channel_details::SubChannelState;
 pub use event_handler::EventHandlerTrait;
 pub use event_handler::EventSender;
 pub(crate) use logger::TracingLogger;
@@ -1,10 +1,10 @@
+use crate::chain::ChainClient;
 use crate::fee_rate_estimator::FeeRateEstimator;
 use crate::ldk_node_wallet;
 use crate::node::Storage;
-use crate::TracingLogger;
 use anyhow::Result;
-use bdk::blockchain::EsploraBlockchain;
-use bdk::esplora_client::TxStatus;
+use bdk::blockchain::AnyBlockchain;
+use bdk::blockchain::GetTx;
 use bdk::sled;
 use bdk::TransactionDetails;
 use bitcoin::secp256k1::All;
@@ -26,7 +26,6 @@ use dlc_manager::Signer;
 use dlc_manager::Utxo;
 use dlc_sled_storage_provider::SledStorageProvider;
 use lightning::chain::chaininterface::BroadcasterInterface;
-use lightning_transaction_sync::EsploraSyncClient;
 use parking_lot::RwLock;
 use rust_bitcoin_coin_selection::select_coins;
 use simple_wallet::WalletStorage;
@@ -35,7 +34,9 @@ use std::sync::Arc;
 /// This is a wrapper type introduced to be able to implement traits from `rust-dlc` on the
 /// `ldk_node::LightningWallet`.
 pub struct LnDlcWallet {
-    ln_wallet: Arc<ldk_node_wallet::Wallet<sled::Tree, EsploraBlockchain, FeeRateEstimator>>,
+    ln_wallet: Arc<ldk_node_wallet::Wallet<sled::Tree, AnyBlockchain, FeeRateEstimator>>,
+    /// Used for the queries of `rust-dlc` which the BDK blockchain cannot answer.
+    chain_client: Arc<ChainClient>,
     storage: Arc<SledStorageProvider>,
     secp: Secp256k1<All>,
     network: Network,
@@ -48,20 +49,14 @@ pub struct LnDlcWallet {
 }
 
 impl LnDlcWallet {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        esplora_client: Arc<EsploraSyncClient<Arc<TracingLogger>>>,
+    pub(crate) fn new(
+        blockchain: AnyBlockchain,
+        chain_client: Arc<ChainClient>,
         on_chain_wallet: bdk::Wallet<bdk::sled::Tree>,
         fee_rate_estimator: Arc<FeeRateEstimator>,
         storage: Arc<SledStorageProvider>,
-        bdk_client_stop_gap: usize,
-        bdk_client_concurrency: u8,
         node_storage: Arc<dyn Storage + Send + Sync + 'static>,
     ) -> Self {
-        let blockchain =
-            EsploraBlockchain::from_client(esplora_client.client().clone(), bdk_client_stop_gap)
-                .with_concurrency(bdk_client_concurrency);
-
         let network = on_chain_wallet.network();
 
         let wallet = Arc::new(ldk_node_wallet::Wallet::new(
@@ -77,6 +72,7 @@ impl LnDlcWallet {
 
         Self {
             ln_wallet: wallet,
+            chain_client,
             storage,
             secp: Secp256k1::new(),
             network,
@@ -86,7 +82,7 @@ impl LnDlcWallet {
 
     pub fn ldk_wallet(
         &self,
-    ) -> Arc<ldk_node_wallet::Wallet<sled::Tree, EsploraBlockchain, FeeRateEstimator>> {
+    ) -> Arc<ldk_node_wallet::Wallet<sled::Tree, AnyBlockchain, FeeRateEstimator>> {
         self.ln_wallet.clone()
     }
 
@@ -160,25 +156,11 @@ impl Blockchain for LnDlcWallet {
     }
 
     fn get_block_at_height(&self, height: u64) -> Result<Block, Error> {
-        let block_hash = self
-            .ln_wallet
-            .blockchain
-            .get_block_hash(height as u32)
-            .map_err(|e| {
-                Error::BlockchainError(format!("Could not find block at height {height}: {e:#}"))
-            })?;
-        let block = self
-            .ln_wallet
-            .blockchain
-            .get_block_by_hash(&block_hash)
+        self.chain_client
+            .get_block_at_height(height as u32)
             .map_err(|e| {
                 Error::BlockchainError(format!("Could not find block at height {height}: {e:#}"))
-            })?
-            .ok_or_else(|| {
-                Error::BlockchainError(format!("Could not find block at height {height}"))
-            })?;
-
-        Ok(block)
+            })
     }
 
     fn get_transaction(&self, txid: &Txid) -> Result<Transaction, Error> {
@@ -193,21 +175,16 @@ impl Blockchain for LnDlcWallet {
 
     fn get_transaction_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
         let confirmation_height = match self
-            .ln_wallet
-            .blockchain
-            .get_tx_status(txid)
+            .chain_client
+            .confirmation_height(txid)
             .map_err(|e| Error::BlockchainError(e.to_string()))?
         {
-            Some(TxStatus {
-                block_height: Some(height),
-                ..
-            }) => height,
-            _ => return Ok(0),
+            Some(height) => height,
+            None => return Ok(0),
         };
 
         let tip = self
-            .ln_wallet
-            .blockchain
+            .chain_client
             .get_height()
             .map_err(|e| Error::BlockchainError(e.to_string()))?;
         let confirmations = tip.checked_sub(confirmation_height).unwrap_or_default();
@@ -8,13 +8,13 @@ use anyhow::Result;
 use bitcoin::BlockHash;
 use lightning::chain::BestBlock;
 use lightning::chain::ChannelMonitorUpdateStatus;
+use lightning::chain::Filter;
 use lightning::chain::Watch;
 use lightning::ln::channelmanager::ChainParameters;
 use lightning::ln::channelmanager::ChannelManagerReadArgs;
 use lightning::util::config::UserConfig;
 use lightning::util::ser::ReadableArgs;
 use lightning_persister::FilesystemPersister;
-use lightning_transaction_sync::EsploraSyncClient;
 use std::sync::Arc;
 
 pub type ChannelManager = lightning::ln::channelmanager::ChannelManager<
@@ -34,7 +34,7 @@ pub(crate) fn build(
     keys_manager: Arc<CustomKeysManager>,
     ln_dlc_wallet: Arc<LnDlcWallet>,
     fee_rate_estimator: Arc<FeeRateEstimator>,
-    explora_client: Arc<EsploraSyncClient<Arc<TracingLogger>>>,
+    filter: Arc<dyn Filter + Send + Sync>,
     logger: Arc<TracingLogger>,
     chain_monitor: Arc<ChainMonitor>,
     ldk_config: UserConfig,
@@ -99,7 +99,7 @@ pub(crate) fn build(
     // Make sure our filter is initialized with all the txs and outputs
     // that we need to be watching based on our set of channel monitors
     for (_, monitor) in channelmonitors.iter() {
-        monitor.load_outputs_to_watch(&explora_client.clone());
+        monitor.load_outputs_to_watch(&filter);
     }
 
     for (_, monitor) in channelmonitors.drain(..) {
@@ -0,0 +1,273 @@
+use crate::ldk_node_wallet::UnconfirmedTransaction;
+use crate::ln_dlc_wallet::LnDlcWallet;
+use crate::node::LnDlcNodeSettings;
+use crate::node::Node;
+use crate::node::Storage;
+use anyhow::Result;
+use bdk::FeeRate;
+use bitcoin::Txid;
+use lightning::chain::chaininterface::ConfirmationTarget;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// The interval at which unconfirmed transactions are checked against the [`FeeBumpPolicy`].
+const FEE_BUMP_POLICY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeBumpMethod {
+    /// Replace the transaction with one paying a higher fee.
+    Rbf,
+    /// Spend an output of the transaction with a child paying for both.
+    Cpfp,
+}
+
+/// Bump the fee of our transactions automatically if they are not confirmed in time.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FeeBumpPolicy {
+    /// The number of blocks a transaction may stay unconfirmed before its fee is bumped to the
+    /// [`ConfirmationTarget::HighPriority`] fee rate.
+    pub max_pending_blocks: u32,
+    /// The highest fee rate we are willing to pay when bumping, in sats/vbyte.
+    pub max_fee_rate: f32,
+}
+
+impl<S> Node<S>
+where
+    S: Storage,
+{
+    pub fn unconfirmed_transactions(&self) -> Result<Vec<UnconfirmedTransaction>> {
+        self.wallet.ldk_wallet().unconfirmed_transactions()
+    }
+
+    /// Bumps the fee of the unconfirmed transaction `txid` to `fee_rate` and returns the ID of the
+    /// replacement or child transaction.
+    pub fn bump_fee(&self, txid: &Txid, fee_rate: FeeRate, method: FeeBumpMethod) -> Result<Txid> {
+        let wallet = self.wallet.ldk_wallet();
+        match method {
+            FeeBumpMethod::Rbf => wallet.bump_fee(txid, fee_rate),
+            FeeBumpMethod::Cpfp => wallet.cpfp(txid, fee_rate),
+        }
+    }
+}
+
+/// Applies the [`FeeBumpPolicy`] of the node settings, if any, at an interval.
+pub(crate) fn bump_fees_periodically(
+    settings: Arc<RwLock<LnDlcNodeSettings>>,
+    ln_dlc_wallet: Arc<LnDlcWallet>,
+) -> impl Fn() {
+    let handle = tokio::runtime::Handle::current();
+    move || {
+        // The height at which we first saw a transaction unconfirmed or last bumped its fee.
+        let mut pending_since = HashMap::new();
+
+        loop {
+            let policy = handle.block_on(async { settings.read().await.fee_bump_policy.clone() });
+
+            if let Some(policy) = policy {
+                if let Err(e) = apply_policy(&policy, &ln_dlc_wallet, &mut pending_since) {
+                    tracing::error!("Failed to apply fee bump policy: {e:#}");
+                }
+            }
+
+            std::thread::sleep(FEE_BUMP_POLICY_INTERVAL);
+        }
+    }
+}
+
+fn apply_policy(
+    policy: &FeeBumpPolicy,
+    ln_dlc_wallet: &LnDlcWallet,
+    pending_since: &mut HashMap<Txid, u32>,
+) -> Result<()> {
+    let wallet = ln_dlc_wallet.ldk_wallet();
+    let (height, _) = wallet.tip()?;
+
+    let unconfirmed = wallet.unconfirmed_transactions()?;
+    pending_since.retain(|txid, _| unconfirmed.iter().any(|tx| tx.txid == *txid));
+
+    let target = wallet.get_fee_rate(ConfirmationTarget::HighPriority);
+    let fee_rate = FeeRate::from_sat_per_vb(target.as_sat_per_vb().min(policy.max_fee_rate));
+
+    let unconfirmed_by_txid = unconfirmed
+        .iter()
+        .map(|tx| (tx.txid, tx))
+        .collect::<HashMap<_, _>>();
+    let with_unconfirmed_child = with_unconfirmed_child(&unconfirmed);
+
+    // Only transactions paid by us, incoming transactions are the sender's business.
+    for tx in unconfirmed.iter().filter(|tx| tx.sent > 0) {
+        // The fee of the whole package is bumped through the child, otherwise we would keep
+        // adding children to the same parent.
+        if with_unconfirmed_child.contains(&tx.txid) {
+            continue;
+        }
+
+        let since = *pending_since.entry(tx.txid).or_insert(height);
+        if height < since + policy.max_pending_blocks {
+            continue;
+        }
+
+        let current = ancestor_fee_rate(tx, &unconfirmed_by_txid);
+        if current >= fee_rate.as_sat_per_vb() {
+            tracing::debug!(
+                txid = %tx.txid,
+                current,
+                max_fee_rate = policy.max_fee_rate,
+                "Not bumping fee of unconfirmed transaction"
+            );
+            continue;
+        }
+
+        let method = bump_method(tx);
+
+        tracing::info!(
+            txid = %tx.txid,
+            pending_blocks = height - since,
+            current,
+            target = fee_rate.as_sat_per_vb(),
+            ?method,
+            "Bumping fee of unconfirmed transaction"
+        );
+
+        let bumped = match method {
+            FeeBumpMethod::Rbf => wallet.bump_fee(&tx.txid, fee_rate),
+            FeeBumpMethod::Cpfp => wallet.cpfp(&tx.txid, fee_rate),
+        };
+
+        match bumped {
+            Ok(txid) => {
+                // Give the bumped transaction as much time to confirm as the original one.
+                pending_since.insert(tx.txid, height);
+                pending_since.insert(txid, height);
+            }
+            Err(e) => {
+                tracing::error!(txid = %tx.txid, "Failed to bump fee: {e:#}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The transactions spent by another unconfirmed transaction of ours, e.g. by a CPFP child.
+fn with_unconfirmed_child(unconfirmed: &[UnconfirmedTransaction]) -> HashSet<Txid> {
+    let txids = unconfirmed.iter().map(|tx| tx.txid).collect::<HashSet<_>>();
+
+    unconfirmed
+        .iter()
+        .flat_map(|tx| tx.parents.iter())
+        .filter(|parent| txids.contains(parent))
+        .copied()
+        .collect()
+}
+
+/// The fee rate of the transaction together with all its unconfirmed ancestors, in sats/vbyte,
+/// i.e. the fee rate miners consider when including the transaction.
+///
+/// Ancestors with an unknown fee are counted as paying none.
+fn ancestor_fee_rate(
+    tx: &UnconfirmedTransaction,
+    unconfirmed_by_txid: &HashMap<Txid, &UnconfirmedTransaction>,
+) -> f32 {
+    let mut fee = 0;
+    let mut vsize = 0;
+
+    let mut visited = HashSet::new();
+    let mut package = vec![tx];
+    while let Some(tx) = package.pop() {
+        if !visited.insert(tx.txid) {
+            continue;
+        }
+
+        fee += tx.fee.unwrap_or_default();
+        vsize += tx.vsize;
+
+        package.extend(
+            tx.parents
+                .iter()
+                .filter_map(|parent| unconfirmed_by_txid.get(parent).copied()),
+        );
+    }
+
+    fee as f32 / vsize as f32
+}
+
+/// Replaces our own payments if possible, channel transactions are only ever bumped with CPFP.
+fn bump_method(tx: &UnconfirmedTransaction) -> FeeBumpMethod {
+    if tx.is_rbf && tx.fee.is_some() {
+        FeeBumpMethod::Rbf
+    } else {
+        FeeBumpMethod::Cpfp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn replaceable_payment_is_bumped_with_rbf() {
+        let tx = unconfirmed_transaction(true, Some(500));
+
+        assert_eq!(bump_method(&tx), FeeBumpMethod::Rbf);
+    }
+
+    #[test]
+    fn channel_transaction_is_bumped_with_cpfp() {
+        let tx = unconfirmed_transaction(false, Some(500));
+
+        assert_eq!(bump_method(&tx), FeeBumpMethod::Cpfp);
+    }
+
+    #[test]
+    fn transaction_with_unknown_fee_is_bumped_with_cpfp() {
+        let tx = unconfirmed_transaction(true, None);
+
+        assert_eq!(bump_method(&tx), FeeBumpMethod::Cpfp);
+    }
+
+    #[test]
+    fn child_pays_for_parent() {
+        let parent = unconfirmed_transaction(false, Some(200));
+        let child = UnconfirmedTransaction {
+            txid: Txid::from_str(
+                "1e4e5a2ac1b9a3a5a2e9fa1f6e5b94ecb1d0c36e2b2e4cc0b6f4dbbd4bc1e5d1",
+            )
+            .unwrap(),
+            parents: vec![parent.txid],
+            ..unconfirmed_transaction(true, Some(1_800))
+        };
+        let unconfirmed = vec![parent.clone(), child.clone()];
+        let unconfirmed_by_txid = unconfirmed.iter().map(|tx| (tx.txid, tx)).collect();
+
+        assert_eq!(
+            with_unconfirmed_child(&unconfirmed),
+            HashSet::from([parent.txid])
+        );
+        assert_eq!(ancestor_fee_rate(&parent, &unconfirmed_by_txid), 1.0);
+        assert_eq!(ancestor_fee_rate(&child, &unconfirmed_by_txid), 5.0);
+    }
+
+    fn unconfirmed_transaction(is_rbf: bool, fee: Option<u64>) -> UnconfirmedTransaction {
+        UnconfirmedTransaction {
+            txid: Txid::from_str(
+                "44fe3d70a3058eb1bef62e24379b4865ada8332f9ee30752cf606f37343461a0",
+            )
+            .unwrap(),
+            fee,
+            vsize: 200,
+            fee_rate: fee.map(|fee| fee as f32 / 200.0),
+            is_rbf,
+            sent: 100_000,
+            received: 0,
+            parents: vec![],
+        }
+    }
+}
@@ -0,0 +1,561 @@
+//! Hold invoices, i.e. invoices for an externally provided payment hash whose payments we only
+//! claim once we explicitly settle them.
+//!
+//! The state of hold invoices is kept in memory and rebuilt from our payments on startup, see
+//! [`HoldInvoices::load`]. Hold invoices which were settled before their payment arrived are
+//! treated as open again after a restart and have to be settled again.
+
+use crate::node::invoice::HTLCStatus;
+use crate::node::ChannelManager;
+use crate::node::Node;
+use crate::node::Storage;
+use crate::MillisatAmount;
+use crate::PaymentFlow;
+use crate::PaymentInfo;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::Secp256k1;
+use lightning::ln::PaymentHash;
+use lightning::ln::PaymentPreimage;
+use lightning_invoice::Invoice;
+use lightning_invoice::InvoiceBuilder;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use time::OffsetDateTime;
+
+/// The minimum number of blocks the payer has to leave us before the HTLCs of a hold invoice
+/// expire.
+///
+/// LDK fails back claimable HTLCs shortly before they expire, so this has to give us enough time
+/// to hold the payment until the invoice itself expires.
+const MIN_FINAL_CLTV_EXPIRY_DELTA: u16 = 144;
+
+/// For how long we remember settled or cancelled hold invoices after they expired.
+const RETENTION: time::Duration = time::Duration::days(1);
+
+/// How often we check for expired hold invoices.
+const CANCEL_EXPIRED_HOLD_INVOICES_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Default, Clone)]
+pub(crate) struct HoldInvoices(Arc<Mutex<HashMap<PaymentHash, HoldInvoice>>>);
+
+#[derive(Debug, Clone, Copy)]
+struct HoldInvoice {
+    expiry: OffsetDateTime,
+    state: HoldInvoiceState,
+    /// Set once the invoice is settled, which may happen before the payment arrives.
+    preimage: Option<PaymentPreimage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldInvoiceState {
+    /// Waiting for the payment.
+    Open,
+    /// The payment arrived and its HTLCs are held until the invoice is settled or cancelled.
+    Accepted {
+        amount_msat: u64,
+    },
+    Settled,
+    Cancelled,
+}
+
+/// What to do with the HTLCs of an incoming payment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeldPayment {
+    /// The payment is not for a hold invoice.
+    NotHeld,
+    Hold,
+    Claim(PaymentPreimage),
+    FailBack,
+}
+
+impl<P> Node<P>
+where
+    P: Storage,
+{
+    /// Creates an invoice for `payment_hash`, whose payment is held until the invoice is settled
+    /// with the corresponding preimage via [`Node::settle_hold_invoice`] or cancelled via
+    /// [`Node::cancel_hold_invoice`].
+    ///
+    /// If neither happens before the invoice expires, it is cancelled automatically.
+    pub fn create_hold_invoice(
+        &self,
+        amount_sats: u64,
+        description: String,
+        payment_hash: PaymentHash,
+        expiry: Duration,
+    ) -> Result<Invoice> {
+        let amount_msat = amount_sats * 1000;
+        let payment_secret = self
+            .channel_manager
+            .create_inbound_payment_for_hash(
+                payment_hash,
+                Some(amount_msat),
+                expiry.as_secs() as u32,
+                Some(MIN_FINAL_CLTV_EXPIRY_DELTA),
+            )
+            .map_err(|_| anyhow!("Failed to create inbound payment"))?;
+
+        let node_secret = self.keys_manager.get_node_secret_key();
+
+        let signed_invoice = InvoiceBuilder::new(self.get_currency())
+            .payee_pub_key(self.info.pubkey)
+            .description(description)
+            .amount_milli_satoshis(amount_msat)
+            .expiry_time(expiry)
+            .payment_hash(sha256::Hash::from_slice(&payment_hash.0)?)
+            .payment_secret(payment_secret)
+            .timestamp(SystemTime::now())
+            .min_final_cltv_expiry_delta(MIN_FINAL_CLTV_EXPIRY_DELTA.into())
+            .build_raw()?
+            .sign::<_, ()>(|hash| {
+                let secp_ctx = Secp256k1::new();
+                Ok(secp_ctx.sign_ecdsa_recoverable(hash, &node_secret))
+            })
+            .map_err(|_| anyhow!("Failed to sign invoice"))?;
+        let invoice = Invoice::from_signed(signed_invoice)?;
+
+        self.hold_invoices
+            .insert(payment_hash, OffsetDateTime::now_utc() + expiry);
+
+        tracing::info!(
+            payment_hash = %payment_hash.0.to_hex(),
+            %amount_sats,
+            ?expiry,
+            "Created hold invoice"
+        );
+
+        Ok(invoice)
+    }
+
+    /// Settles the hold invoice paying to the hash of `preimage`.
+    ///
+    /// If the payment has not arrived yet, it is claimed as soon as it does.
+    pub fn settle_hold_invoice(&self, preimage: PaymentPreimage) -> Result<()> {
+        let payment_hash = PaymentHash(sha256::Hash::hash(&preimage.0).into_inner());
+
+        if self.hold_invoices.settle(preimage)? {
+            self.channel_manager.claim_funds(preimage);
+
+            tracing::info!(payment_hash = %payment_hash.0.to_hex(), "Settled hold invoice");
+        } else {
+            // After a restart we don't know whether the payment has already arrived, in which case
+            // LDK still holds its HTLCs. Claiming them does nothing if they are not there (yet).
+            self.channel_manager.claim_funds(preimage);
+
+            tracing::info!(
+                payment_hash = %payment_hash.0.to_hex(),
+                "Hold invoice will be settled once the payment arrives"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Cancels the hold invoice for `payment_hash`, failing back its payment if it has already
+    /// arrived.
+    pub fn cancel_hold_invoice(&self, payment_hash: PaymentHash) -> Result<()> {
+        self.hold_invoices.cancel(payment_hash)?;
+
+        fail_back_hold_invoice(&self.channel_manager, self.storage.as_ref(), payment_hash);
+
+        Ok(())
+    }
+
+    /// The state of the hold invoice for `payment_hash`, if we know about it.
+    pub fn hold_invoice_state(&self, payment_hash: &PaymentHash) -> Option<HoldInvoiceState> {
+        self.hold_invoices.state(payment_hash)
+    }
+
+    /// Decides what to do with an incoming payment, in case it pays for a hold invoice.
+    pub(crate) fn hold_payment(&self, payment_hash: PaymentHash, amount_msat: u64) -> HeldPayment {
+        let held_payment =
+            self.hold_invoices
+                .accept(payment_hash, amount_msat, OffsetDateTime::now_utc());
+
+        let payment_hash_str = payment_hash.0.to_hex();
+        match held_payment {
+            HeldPayment::NotHeld => {}
+            HeldPayment::Hold => {
+                tracing::info!(
+                    payment_hash = %payment_hash_str,
+                    %amount_msat,
+                    "Holding payment until the hold invoice is settled or cancelled"
+                );
+            }
+            HeldPayment::Claim(preimage) => {
+                tracing::info!(
+                    payment_hash = %payment_hash_str,
+                    %amount_msat,
+                    "Claiming payment of settled hold invoice"
+                );
+                self.channel_manager.claim_funds(preimage);
+            }
+            HeldPayment::FailBack => {
+                tracing::info!(
+                    payment_hash = %payment_hash_str,
+                    %amount_msat,
+                    "Failing back payment of cancelled hold invoice"
+                );
+                fail_back_hold_invoice(&self.channel_manager, self.storage.as_ref(), payment_hash);
+            }
+        }
+
+        held_payment
+    }
+}
+
+impl HoldInvoices {
+    /// Rebuilds the hold invoices from our `payments`, so that they can still be settled or
+    /// cancelled after a restart.
+    ///
+    /// Hold invoices are the inbound payments for which LDK does not know the preimage, because
+    /// they were created for an external payment hash.
+    pub(crate) fn load(
+        payments: Vec<(PaymentHash, PaymentInfo)>,
+        channel_manager: &ChannelManager,
+        now: OffsetDateTime,
+    ) -> Self {
+        let hold_invoices = payments
+            .into_iter()
+            .filter_map(|(payment_hash, info)| {
+                let hold_invoice = HoldInvoice::from_payment(&info, now)?;
+
+                let secret = info.secret?;
+                channel_manager
+                    .get_payment_preimage(payment_hash, secret)
+                    .is_err()
+                    .then_some((payment_hash, hold_invoice))
+            })
+            .collect::<HashMap<_, _>>();
+
+        tracing::debug!(count = hold_invoices.len(), "Loaded hold invoices");
+
+        Self(Arc::new(Mutex::new(hold_invoices)))
+    }
+
+    fn insert(&self, payment_hash: PaymentHash, expiry: OffsetDateTime) {
+        self.0.lock().insert(
+            payment_hash,
+            HoldInvoice {
+                expiry,
+                state: HoldInvoiceState::Open,
+                preimage: None,
+            },
+        );
+    }
+
+    fn state(&self, payment_hash: &PaymentHash) -> Option<HoldInvoiceState> {
+        self.0
+            .lock()
+            .get(payment_hash)
+            .map(|hold_invoice| hold_invoice.state)
+    }
+
+    /// Marks the hold invoice for `payment_hash` as paid.
+    fn accept(
+        &self,
+        payment_hash: PaymentHash,
+        amount_msat: u64,
+        now: OffsetDateTime,
+    ) -> HeldPayment {
+        let mut hold_invoices = self.0.lock();
+        let hold_invoice = match hold_invoices.get_mut(&payment_hash) {
+            Some(hold_invoice) => hold_invoice,
+            None => return HeldPayment::NotHeld,
+        };
+
+        match (hold_invoice.state, hold_invoice.preimage) {
+            (HoldInvoiceState::Cancelled, _) => HeldPayment::FailBack,
+            (HoldInvoiceState::Open, _) if now >= hold_invoice.expiry => {
+                hold_invoice.state = HoldInvoiceState::Cancelled;
+                HeldPayment::FailBack
+            }
+            (HoldInvoiceState::Open | HoldInvoiceState::Settled, Some(preimage)) => {
+                hold_invoice.state = HoldInvoiceState::Settled;
+                HeldPayment::Claim(preimage)
+            }
+            (HoldInvoiceState::Open, None) => {
+                hold_invoice.state = HoldInvoiceState::Accepted { amount_msat };
+                HeldPayment::Hold
+            }
+            (HoldInvoiceState::Accepted { .. } | HoldInvoiceState::Settled, _) => HeldPayment::Hold,
+        }
+    }
+
+    /// Settles the hold invoice paying to the hash of `preimage`.
+    ///
+    /// Returns whether the payment has arrived and has to be claimed.
+    fn settle(&self, preimage: PaymentPreimage) -> Result<bool> {
+        let payment_hash = PaymentHash(sha256::Hash::hash(&preimage.0).into_inner());
+
+        let mut hold_invoices = self.0.lock();
+        let hold_invoice = hold_invoices
+            .get_mut(&payment_hash)
+            .with_context(|| format!("Unknown hold invoice {}", payment_hash.0.to_hex()))?;
+
+        let claim = match hold_invoice.state {
+            HoldInvoiceState::Open => false,
+            HoldInvoiceState::Accepted { .. } => {
+                hold_invoice.state = HoldInvoiceState::Settled;
+                true
+            }
+            HoldInvoiceState::Settled => false,
+            HoldInvoiceState::Cancelled => bail!("Hold invoice has already been cancelled"),
+        };
+        hold_invoice.preimage = Some(preimage);
+
+        Ok(claim)
+    }
+
+    fn cancel(&self, payment_hash: PaymentHash) -> Result<()> {
+        let mut hold_invoices = self.0.lock();
+        let hold_invoice = hold_invoices
+            .get_mut(&payment_hash)
+            .with_context(|| format!("Unknown hold invoice {}", payment_hash.0.to_hex()))?;
+
+        if hold_invoice.state == HoldInvoiceState::Settled {
+            bail!("Hold invoice has already been settled");
+        }
+        hold_invoice.state = HoldInvoiceState::Cancelled;
+
+        Ok(())
+    }
+
+    /// Cancels all hold invoices which expired before they were settled, returning their payment
+    /// hashes.
+    fn cancel_expired(&self, now: OffsetDateTime) -> Vec<PaymentHash> {
+        let mut hold_invoices = self.0.lock();
+        hold_invoices.retain(|_, hold_invoice| {
+            !matches!(
+                hold_invoice.state,
+                HoldInvoiceState::Settled | HoldInvoiceState::Cancelled
+            ) || now - hold_invoice.expiry < RETENTION
+        });
+
+        hold_invoices
+            .iter_mut()
+            .filter(|(_, hold_invoice)| {
+                matches!(
+                    hold_invoice.state,
+                    HoldInvoiceState::Open | HoldInvoiceState::Accepted { .. }
+                ) && now >= hold_invoice.expiry
+            })
+            .map(|(payment_hash, hold_invoice)| {
+                hold_invoice.state = HoldInvoiceState::Cancelled;
+                *payment_hash
+            })
+            .collect()
+    }
+}
+
+impl HoldInvoice {
+    /// The hold invoice an inbound payment might be paying, unless we don't have to remember it
+    /// anymore.
+    fn from_payment(info: &PaymentInfo, now: OffsetDateTime) -> Option<Self> {
+        if !matches!(info.flow, PaymentFlow::Inbound) {
+            return None;
+        }
+
+        let invoice = Invoice::from_str(info.invoice.as_ref()?).ok()?;
+        let expiry = OffsetDateTime::from(invoice.timestamp()) + invoice.expiry_time();
+
+        let (state, preimage) = match info.status {
+            HTLCStatus::Pending => (HoldInvoiceState::Open, None),
+            HTLCStatus::Succeeded => (HoldInvoiceState::Settled, info.preimage),
+            HTLCStatus::Failed => (HoldInvoiceState::Cancelled, None),
+        };
+
+        let forgotten = matches!(
+            state,
+            HoldInvoiceState::Settled | HoldInvoiceState::Cancelled
+        ) && now - expiry >= RETENTION;
+        if forgotten {
+            return None;
+        }
+
+        Some(Self {
+            expiry,
+            state,
+            preimage,
+        })
+    }
+}
+
+pub(crate) async fn cancel_expired_hold_invoices_periodically<
+    S: Storage + Send + Sync + 'static,
+>(
+    hold_invoices: HoldInvoices,
+    channel_manager: Arc<ChannelManager>,
+    node_storage: Arc<S>,
+) {
+    loop {
+        for payment_hash in hold_invoices.cancel_expired(OffsetDateTime::now_utc()) {
+            tracing::info!(payment_hash = %payment_hash.0.to_hex(), "Hold invoice expired");
+
+            fail_back_hold_invoice(&channel_manager, node_storage.as_ref(), payment_hash);
+        }
+
+        tokio::time::sleep(CANCEL_EXPIRED_HOLD_INVOICES_INTERVAL).await;
+    }
+}
+
+fn fail_back_hold_invoice<S: Storage>(
+    channel_manager: &ChannelManager,
+    node_storage: &S,
+    payment_hash: PaymentHash,
+) {
+    channel_manager.fail_htlc_backwards(&payment_hash);
+
+    if let Err(e) = node_storage.merge_payment(
+        &payment_hash,
+        PaymentFlow::Inbound,
+        MillisatAmount(None),
+        MillisatAmount(None),
+        HTLCStatus::Failed,
+        None,
+        None,
+    ) {
+        tracing::error!(
+            payment_hash = %payment_hash.0.to_hex(),
+            "Failed to update cancelled hold invoice payment: {e:#}"
+        );
+    }
+
+    tracing::info!(payment_hash = %payment_hash.0.to_hex(), "Cancelled hold invoice");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightning::ln::PaymentSecret;
+
+    fn hold_invoice(expiry: OffsetDateTime) -> (HoldInvoices, PaymentPreimage, PaymentHash) {
+        let preimage = PaymentPreimage([1; 32]);
+        let payment_hash = PaymentHash(sha256::Hash::hash(&preimage.0).into_inner());
+
+        let hold_invoices = HoldInvoices::default();
+        hold_invoices.insert(payment_hash, expiry);
+
+        (hold_invoices, preimage, payment_hash)
+    }
+
+    #[test]
+    fn payment_is_held_until_settled() {
+        let now = OffsetDateTime::now_utc();
+        let (hold_invoices, preimage, payment_hash) = hold_invoice(now + time::Duration::hours(1));
+
+        assert_eq!(
+            hold_invoices.accept(payment_hash, 1_000, now),
+            HeldPayment::Hold
+        );
+        assert_eq!(
+            hold_invoices.state(&payment_hash),
+            Some(HoldInvoiceState::Accepted { amount_msat: 1_000 })
+        );
+
+        assert!(hold_invoices.settle(preimage).unwrap());
+        assert_eq!(
+            hold_invoices.state(&payment_hash),
+            Some(HoldInvoiceState::Settled)
+        );
+        assert!(hold_invoices.cancel(payment_hash).is_err());
+    }
+
+    #[test]
+    fn payment_of_settled_invoice_is_claimed_on_arrival() {
+        let now = OffsetDateTime::now_utc();
+        let (hold_invoices, preimage, payment_hash) = hold_invoice(now + time::Duration::hours(1));
+
+        assert!(!hold_invoices.settle(preimage).unwrap());
+        assert_eq!(
+            hold_invoices.accept(payment_hash, 1_000, now),
+            HeldPayment::Claim(preimage)
+        );
+    }
+
+    #[test]
+    fn hold_invoice_is_rebuilt_from_payment() {
+        let now = OffsetDateTime::now_utc();
+        let invoice = test_invoice();
+        let payment = |status| PaymentInfo {
+            status,
+            ..PaymentInfo::from(invoice.clone())
+        };
+
+        let hold_invoice = HoldInvoice::from_payment(&payment(HTLCStatus::Pending), now).unwrap();
+        assert_eq!(hold_invoice.state, HoldInvoiceState::Open);
+        assert_eq!(
+            hold_invoice.expiry,
+            OffsetDateTime::from(invoice.timestamp()) + time::Duration::hours(1)
+        );
+
+        let hold_invoice = HoldInvoice::from_payment(&payment(HTLCStatus::Failed), now).unwrap();
+        assert_eq!(hold_invoice.state, HoldInvoiceState::Cancelled);
+
+        // Settled and cancelled hold invoices are forgotten a while after they expired.
+        assert!(HoldInvoice::from_payment(
+            &payment(HTLCStatus::Succeeded),
+            now + time::Duration::days(2)
+        )
+        .is_none());
+
+        let outbound = PaymentInfo {
+            flow: PaymentFlow::Outbound,
+            ..payment(HTLCStatus::Pending)
+        };
+        assert!(HoldInvoice::from_payment(&outbound, now).is_none());
+    }
+
+    fn test_invoice() -> Invoice {
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[42; 32]).unwrap();
+
+        InvoiceBuilder::new(lightning_invoice::Currency::Regtest)
+            .description("fee".to_string())
+            .amount_milli_satoshis(1_000)
+            .payment_hash(sha256::Hash::hash(&[1; 32]))
+            .payment_secret(PaymentSecret([2; 32]))
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(MIN_FINAL_CLTV_EXPIRY_DELTA.into())
+            .expiry_time(Duration::from_secs(3600))
+            .build_signed(|hash| Secp256k1::new().sign_ecdsa_recoverable(hash, &secret_key))
+            .unwrap()
+    }
+
+    #[test]
+    fn expired_and_cancelled_invoices_fail_back_payments() {
+        let now = OffsetDateTime::now_utc();
+        let (hold_invoices, preimage, payment_hash) = hold_invoice(now + time::Duration::hours(1));
+
+        assert_eq!(
+            hold_invoices.accept(payment_hash, 1_000, now),
+            HeldPayment::Hold
+        );
+        assert!(hold_invoices
+            .cancel_expired(now + time::Duration::minutes(30))
+            .is_empty());
+        assert_eq!(
+            hold_invoices.cancel_expired(now + time::Duration::hours(2)),
+            vec![payment_hash]
+        );
+
+        assert!(hold_invoices.settle(preimage).is_err());
+        assert_eq!(
+            hold_invoices.accept(payment_hash, 1_000, now),
+            HeldPayment::FailBack
+        );
+        assert_eq!(
+            hold_invoices.accept(PaymentHash([2; 32]), 1_000, now),
+            HeldPayment::NotHeld
+        );
+    }
+}
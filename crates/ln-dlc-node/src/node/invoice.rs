@@ -1,27 +1,52 @@
 use crate::channel::Channel;
+use crate::node::payment_attempts;
 use crate::node::LiquidityRequest;
 use crate::node::Node;
+use crate::node::PaymentAttempt;
+use crate::node::PaymentAttemptOutcome;
 use crate::node::Storage;
 use crate::MillisatAmount;
 use crate::PaymentFlow;
 use crate::PaymentInfo;
 use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
 use autometrics::autometrics;
+#[cfg(feature = "bolt12")]
+use bitcoin::blockdata::constants::ChainHash;
 use bitcoin::hashes::hex::ToHex;
 use bitcoin::hashes::sha256;
 use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::secp256k1::Secp256k1;
 use bitcoin::Network;
+use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::channelmanager::Retry;
 use lightning::ln::channelmanager::RetryableSendFailure;
 use lightning::ln::channelmanager::MIN_CLTV_EXPIRY_DELTA;
+use lightning::ln::channelmanager::MIN_FINAL_CLTV_EXPIRY_DELTA;
 use lightning::ln::PaymentHash;
+use lightning::ln::PaymentPreimage;
+#[cfg(feature = "bolt12")]
+use lightning::offers::offer::Amount;
+#[cfg(feature = "bolt12")]
+use lightning::offers::offer::Offer;
+#[cfg(feature = "bolt12")]
+use lightning::offers::offer::OfferBuilder;
+#[cfg(feature = "bolt12")]
+use lightning::offers::refund::Refund;
+#[cfg(feature = "bolt12")]
+use lightning::offers::refund::RefundBuilder;
+#[cfg(feature = "bolt12")]
+use lightning::onion_message::BlindedPath;
 use lightning::routing::gossip::RoutingFees;
+use lightning::routing::router::PaymentParameters;
 use lightning::routing::router::RouteHint;
 use lightning::routing::router::RouteHintHop;
+use lightning::routing::router::RouteParameters;
+use lightning::routing::router::Router as _;
 use lightning_invoice::payment::pay_invoice;
 use lightning_invoice::payment::pay_zero_value_invoice;
 use lightning_invoice::payment::PaymentError;
@@ -29,8 +54,10 @@ use lightning_invoice::Currency;
 use lightning_invoice::Invoice;
 use lightning_invoice::InvoiceBuilder;
 use lightning_invoice::InvoiceDescription;
+use rand::Rng;
 use std::fmt;
 use std::fmt::Formatter;
+use std::str::FromStr;
 use std::time::Duration;
 use std::time::SystemTime;
 use time::OffsetDateTime;
@@ -110,7 +137,7 @@ where
         Ok(invoice)
     }
 
-    fn get_currency(&self) -> Currency {
+    pub(crate) fn get_currency(&self) -> Currency {
         match self.network {
             Network::Bitcoin => Currency::Bitcoin,
             Network::Testnet => Currency::BitcoinTestnet,
@@ -225,25 +252,53 @@ where
     }
 
     pub fn pay_invoice(&self, invoice: &Invoice, amount: Option<u64>) -> Result<()> {
-        let (result, amt_msat) = match invoice.amount_milli_satoshis() {
-            Some(_) => {
-                let result = pay_invoice(invoice, Retry::Attempts(10), &self.channel_manager);
-                (result, invoice.amount_milli_satoshis().expect("to be set"))
-            }
-            None => {
-                let amount_msats =
-                    amount.context("Can't pay zero amount invoice without amount")? * 1000;
-                let result = pay_zero_value_invoice(
-                    invoice,
-                    amount_msats,
-                    Retry::Attempts(10),
-                    &self.channel_manager,
-                );
-                (result, amount_msats)
+        let description = match invoice.description() {
+            InvoiceDescription::Direct(des) => des.clone().into_inner(),
+            InvoiceDescription::Hash(lightning_invoice::Sha256(des)) => des.to_string(),
+        };
+
+        self.pay_invoice_with_description(invoice, amount, description)
+    }
+
+    /// Pays `invoice`, recording the payment with `description` instead of the description of the
+    /// invoice.
+    ///
+    /// Useful for invoices which only commit to a description hash, e.g. those of LNURL-pay.
+    pub fn pay_invoice_with_description(
+        &self,
+        invoice: &Invoice,
+        amount: Option<u64>,
+        description: String,
+    ) -> Result<()> {
+        let amt_msat = match invoice.amount_milli_satoshis() {
+            Some(amt_msat) => amt_msat,
+            None => amount.context("Can't pay zero amount invoice without amount")? * 1000,
+        };
+
+        let result = if self.has_outbound_capacity(amt_msat) {
+            let retry = *self.payment_retry.read();
+            let result = match invoice.amount_milli_satoshis() {
+                Some(_) => pay_invoice(invoice, retry, &self.channel_manager),
+                None => pay_zero_value_invoice(invoice, amt_msat, retry, &self.channel_manager),
+            };
+
+            match result {
+                Ok(payment_id) => Ok(payment_id),
+                Err(PaymentError::Invoice(err)) => {
+                    tracing::error!(%err, "Invalid invoice");
+                    anyhow::bail!(err);
+                }
+                Err(PaymentError::Sending(err)) => {
+                    tracing::error!(?err, "Failed to send payment");
+                    Err(PaymentFailureReason::from(err))
+                }
             }
+        } else {
+            tracing::error!(%amt_msat, "Insufficient outbound capacity to send payment");
+            Err(PaymentFailureReason::InsufficientBalance)
         };
 
-        let (status, err) = match result {
+        let (status, failure_reason) = match result {
             Ok(payment_id) => {
                 let payee_pubkey = match invoice.payee_pub_key() {
                     Some(pubkey) => *pubkey,
@@ -259,21 +314,7 @@ where
 
                 (HTLCStatus::Pending, None)
             }
-            Err(PaymentError::Invoice(err)) => {
-                tracing::error!(%err, "Invalid invoice");
-                anyhow::bail!(err);
-            }
-            Err(PaymentError::Sending(err)) => {
-                tracing::error!(?err, "Failed to send payment");
-                let failure_reason = retryable_send_failure_to_string(err);
-
-                (HTLCStatus::Failed, Some(failure_reason))
-            }
-        };
-
-        let description = match invoice.description() {
-            InvoiceDescription::Direct(des) => des.clone().into_inner(),
-            InvoiceDescription::Hash(lightning_invoice::Sha256(des)) => des.to_string(),
+            Err(reason) => (HTLCStatus::Failed, Some(reason)),
         };
 
         self.storage.insert_payment(
@@ -288,16 +329,234 @@ where
                 timestamp: OffsetDateTime::now_utc(),
                 description,
                 invoice: Some(format!("{invoice}")),
+                failure_reason,
             },
         )?;
 
-        if let Some(failure_reason) = err {
-            anyhow::bail!("Failed to send payment: {}, {}", failure_reason, invoice);
+        if let Some(failure_reason) = failure_reason {
+            anyhow::bail!(
+                "Failed to send payment: {}, {}",
+                failure_reason.description(),
+                invoice
+            );
         }
 
         Ok(())
     }
 
+    /// Sends a spontaneous payment of `amount_msat` to `payee`, which does not need an invoice.
+    ///
+    /// The payee has to accept keysend payments.
+    pub fn keysend(
+        &self,
+        payee: PublicKey,
+        amount_msat: u64,
+        description: String,
+    ) -> Result<PaymentHash> {
+        let preimage = PaymentPreimage(rand::thread_rng().gen());
+        let payment_hash = PaymentHash(sha256::Hash::hash(&preimage.0).into_inner());
+
+        let route_params = RouteParameters {
+            payment_params: PaymentParameters::for_keysend(
+                payee,
+                MIN_FINAL_CLTV_EXPIRY_DELTA as u32,
+            ),
+            final_value_msat: amount_msat,
+        };
+
+        let result = if self.has_outbound_capacity(amount_msat) {
+            self.channel_manager
+                .send_spontaneous_payment_with_retry(
+                    Some(preimage),
+                    PaymentId(payment_hash.0),
+                    route_params,
+                    *self.payment_retry.read(),
+                )
+                .map_err(|err| {
+                    tracing::error!(?err, "Failed to send keysend payment");
+                    PaymentFailureReason::from(err)
+                })
+        } else {
+            tracing::error!(%amount_msat, "Insufficient outbound capacity to send keysend payment");
+            Err(PaymentFailureReason::InsufficientBalance)
+        };
+
+        let (status, failure_reason) = match result {
+            Ok(_) => {
+                tracing::info!(
+                    peer_id = %payee,
+                    %amount_msat,
+                    payment_hash = %payment_hash.0.to_hex(),
+                    "Initiated keysend payment"
+                );
+
+                (HTLCStatus::Pending, None)
+            }
+            Err(reason) => (HTLCStatus::Failed, Some(reason)),
+        };
+
+        self.storage.insert_payment(
+            payment_hash,
+            PaymentInfo {
+                preimage: Some(preimage),
+                secret: None,
+                status,
+                amt_msat: MillisatAmount(Some(amount_msat)),
+                fee_msat: MillisatAmount(None),
+                flow: PaymentFlow::Outbound,
+                timestamp: OffsetDateTime::now_utc(),
+                description,
+                invoice: None,
+                failure_reason,
+            },
+        )?;
+
+        if let Some(failure_reason) = failure_reason {
+            anyhow::bail!(
+                "Failed to send keysend payment to {payee}: {}",
+                failure_reason.description()
+            );
+        }
+
+        Ok(payment_hash)
+    }
+
+    /// Probes the route to the recipient of `invoice`, without paying it.
+    ///
+    /// `amount_msat` is only used if the invoice does not specify an amount. We wait for at most
+    /// `timeout` for the probes to complete.
+    pub async fn probe_invoice(
+        &self,
+        invoice: &Invoice,
+        amount_msat: Option<u64>,
+        timeout: Duration,
+    ) -> Result<PaymentProbe> {
+        let amount_msat = invoice
+            .amount_milli_satoshis()
+            .or(amount_msat)
+            .context("Can't probe zero amount invoice without amount")?;
+
+        ensure!(
+            self.has_outbound_capacity(amount_msat),
+            "Insufficient outbound capacity to send {amount_msat} msats"
+        );
+
+        let payee = match invoice.payee_pub_key() {
+            Some(pubkey) => *pubkey,
+            None => invoice.recover_payee_pub_key(),
+        };
+
+        let mut payment_params =
+            PaymentParameters::from_node_id(payee, invoice.min_final_cltv_expiry_delta() as u32)
+                .with_route_hints(invoice.route_hints());
+        if let Some(features) = invoice.features() {
+            payment_params = payment_params.with_features(features.clone());
+        }
+
+        let route_params = RouteParameters {
+            payment_params,
+            final_value_msat: amount_msat,
+        };
+
+        let first_hops = self.channel_manager.list_usable_channels();
+        let first_hops = first_hops.iter().collect::<Vec<_>>();
+        let route = self
+            .router
+            .find_route(
+                &self.info.pubkey,
+                &route_params,
+                Some(&first_hops),
+                &self.channel_manager.compute_inflight_htlcs(),
+            )
+            .map_err(|e| anyhow!("No route found to {payee}: {}", e.err))?;
+
+        let mut probes = Vec::new();
+        for path in route.paths.iter() {
+            let (payment_hash, _) = self
+                .channel_manager
+                .send_probe(path.clone())
+                .map_err(|e| anyhow!("Failed to send probe: {e:?}"))?;
+            probes.push(payment_hash);
+        }
+
+        let succeeded = tokio::time::timeout(timeout, async {
+            loop {
+                let outcomes = probes
+                    .iter()
+                    .map(|payment_hash| {
+                        self.payment_attempts
+                            .get(payment_hash)
+                            .last()
+                            .map(|attempt| attempt.outcome)
+                    })
+                    .collect::<Option<Vec<_>>>();
+
+                if let Some(outcomes) = outcomes {
+                    return outcomes
+                        .iter()
+                        .all(|outcome| *outcome == PaymentAttemptOutcome::Succeeded);
+                }
+
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .ok();
+
+        let probe = PaymentProbe {
+            fee_msat: route.get_total_fees(),
+            paths: route.paths.len(),
+            succeeded,
+        };
+
+        tracing::info!(%payee, %amount_msat, ?probe, "Probed payment");
+
+        Ok(probe)
+    }
+
+    /// The paths over which we tried to route the payment with `payment_hash`.
+    pub fn payment_attempts(&self, payment_hash: &PaymentHash) -> Vec<PaymentAttempt> {
+        self.payment_attempts.get(payment_hash)
+    }
+
+    /// Why the outbound payment with `payment_hash` failed, once LDK has given up on it.
+    pub(crate) fn payment_failure_reason(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> PaymentFailureReason {
+        let attempts = self.payment_attempts.get(payment_hash);
+
+        let (started, invoice) = match self.storage.get_payment(payment_hash) {
+            Ok(Some((_, info))) => (
+                Some(info.timestamp),
+                info.invoice
+                    .and_then(|invoice| Invoice::from_str(&invoice).ok()),
+            ),
+            _ => (attempts.first().map(|attempt| attempt.timestamp), None),
+        };
+
+        let timed_out = retries_timed_out(
+            &self.payment_retry.read(),
+            started,
+            invoice.as_ref(),
+            OffsetDateTime::now_utc(),
+        );
+
+        payment_attempts::failure_reason(&attempts, timed_out)
+    }
+
+    /// Whether our usable channels can send `amount_msat` in total, ignoring routing fees.
+    fn has_outbound_capacity(&self, amount_msat: u64) -> bool {
+        let capacity_msat = self
+            .channel_manager
+            .list_usable_channels()
+            .iter()
+            .map(|channel| channel.next_outbound_htlc_limit_msat)
+            .sum::<u64>();
+
+        capacity_msat >= amount_msat
+    }
+
     #[cfg(test)]
     pub async fn wait_for_payment_claimed(
         &self,
@@ -357,6 +616,178 @@ where
     }
 }
 
+/// BOLT12 offers and refunds.
+///
+/// The LDK version we depend on can build and parse offers and refunds, but it can neither
+/// exchange `invoice_request` and `invoice` messages over onion messages nor pay to blinded
+/// payment paths. Until our LDK fork supports this, we can hand out and decode offers and refunds,
+/// but we can't act on them.
+#[cfg(feature = "bolt12")]
+impl<P> Node<P>
+where
+    P: Storage,
+{
+    /// Creates a reusable [`Offer`] to receive payments.
+    ///
+    /// If `introduction_node` is set, the offer only contains a blinded path starting at that node
+    /// (usually the coordinator) instead of our node ID, so that payers can reach us even though
+    /// we only have private channels.
+    pub fn create_offer(
+        &self,
+        amount_sats: Option<u64>,
+        description: String,
+        introduction_node: Option<PublicKey>,
+        expiry: Option<Duration>,
+    ) -> Result<Offer> {
+        let mut builder = OfferBuilder::new(description, self.info.pubkey).chain(self.network);
+
+        if let Some(amount_sats) = amount_sats {
+            builder = builder.amount_msats(amount_sats * 1000);
+        }
+
+        if let Some(introduction_node) = introduction_node {
+            builder = builder.path(self.blinded_path(introduction_node)?);
+        }
+
+        if let Some(expiry) = expiry {
+            builder = builder.absolute_expiry(absolute_expiry(expiry)?);
+        }
+
+        let offer = builder
+            .build()
+            .map_err(|e| anyhow!("Failed to build offer: {e:?}"))?;
+
+        tracing::info!(%offer, ?amount_sats, ?introduction_node, "Created offer");
+
+        Ok(offer)
+    }
+
+    /// Pays `offer`, with `amount_msat` if the offer does not specify an amount.
+    pub fn pay_offer(&self, offer: &Offer, amount_msat: Option<u64>) -> Result<()> {
+        let amount_msat = check_offer(offer, self.network, amount_msat)?;
+
+        tracing::warn!(
+            %offer,
+            %amount_msat,
+            "Cannot pay offer, requesting invoices for offers is not supported yet"
+        );
+
+        bail!("Paying BOLT12 offers is not supported yet")
+    }
+
+    /// Creates a [`Refund`] of `amount_msat`, which the recipient can claim by sending us an
+    /// invoice, e.g. for the coordinator to pay back a trader.
+    ///
+    /// As with [`Node::create_offer`], `introduction_node` hides our node ID behind a blinded
+    /// path.
+    pub fn create_refund(
+        &self,
+        amount_msat: u64,
+        description: String,
+        introduction_node: Option<PublicKey>,
+        expiry: Option<Duration>,
+    ) -> Result<Refund> {
+        let metadata = rand::thread_rng().gen::<[u8; 32]>().to_vec();
+
+        let mut builder = RefundBuilder::new(description, metadata, self.info.pubkey, amount_msat)
+            .map_err(|e| anyhow!("Failed to create refund: {e:?}"))?
+            .chain(self.network);
+
+        if let Some(introduction_node) = introduction_node {
+            builder = builder.path(self.blinded_path(introduction_node)?);
+        }
+
+        if let Some(expiry) = expiry {
+            builder = builder.absolute_expiry(absolute_expiry(expiry)?);
+        }
+
+        let refund = builder
+            .build()
+            .map_err(|e| anyhow!("Failed to build refund: {e:?}"))?;
+
+        tracing::info!(%refund, %amount_msat, "Created refund");
+
+        Ok(refund)
+    }
+
+    /// Claims `refund` by sending the refunding node an invoice for it.
+    pub fn claim_refund(&self, refund: &Refund) -> Result<()> {
+        ensure!(
+            refund.chain() == ChainHash::using_genesis_block(self.network),
+            "Refund is for a different network"
+        );
+        ensure!(!refund.is_expired(), "Refund has expired");
+
+        tracing::warn!(
+            %refund,
+            amount_msat = refund.amount_msats(),
+            "Cannot claim refund, sending invoices for refunds is not supported yet"
+        );
+
+        bail!("Claiming BOLT12 refunds is not supported yet")
+    }
+
+    /// A blinded path to our node, starting at `introduction_node`.
+    fn blinded_path(&self, introduction_node: PublicKey) -> Result<BlindedPath> {
+        BlindedPath::new(
+            &[introduction_node, self.info.pubkey],
+            &*self.keys_manager,
+            &Secp256k1::new(),
+        )
+        .map_err(|_| anyhow!("Failed to create blinded path via {introduction_node}"))
+    }
+}
+
+/// Checks that `offer` can be paid on `network` and returns the amount to pay.
+#[cfg(feature = "bolt12")]
+fn check_offer(offer: &Offer, network: Network, amount_msat: Option<u64>) -> Result<u64> {
+    ensure!(
+        offer.supports_chain(ChainHash::using_genesis_block(network)),
+        "Offer is for a different network"
+    );
+    ensure!(!offer.is_expired(), "Offer has expired");
+
+    match (offer.amount(), amount_msat) {
+        (Some(Amount::Bitcoin { amount_msats }), None) => Ok(*amount_msats),
+        (Some(Amount::Bitcoin { amount_msats }), Some(amount_msat)) => {
+            ensure!(
+                amount_msat >= *amount_msats,
+                "Amount is below the amount of the offer"
+            );
+            Ok(amount_msat)
+        }
+        (Some(Amount::Currency { .. }), _) => {
+            bail!("Offers denominated in a currency other than bitcoin are not supported")
+        }
+        (None, Some(amount_msat)) => Ok(amount_msat),
+        (None, None) => bail!("Can't pay offer without amount"),
+    }
+}
+
+/// A human-readable summary of `offer`, e.g. to show it before paying.
+#[cfg(feature = "bolt12")]
+pub fn offer_description(offer: &Offer) -> String {
+    match offer.issuer() {
+        Some(issuer) => format!("{} ({issuer})", offer.description()),
+        None => offer.description().to_string(),
+    }
+}
+
+/// The amount of `offer` in msats, if it is denominated in bitcoin.
+#[cfg(feature = "bolt12")]
+pub fn offer_amount_msat(offer: &Offer) -> Option<u64> {
+    match offer.amount()? {
+        Amount::Bitcoin { amount_msats } => Some(*amount_msats),
+        Amount::Currency { .. } => None,
+    }
+}
+
+/// The time since the UNIX epoch at which something valid for `expiry` from now expires.
+#[cfg(feature = "bolt12")]
+fn absolute_expiry(expiry: Duration) -> Result<Duration> {
+    Ok(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)? + expiry)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HTLCStatus {
     Pending,
@@ -374,10 +805,249 @@ impl fmt::Display for HTLCStatus {
     }
 }
 
-fn retryable_send_failure_to_string(failure: RetryableSendFailure) -> &'static str {
-    match failure {
-        RetryableSendFailure::DuplicatePayment => "Duplicate payment",
-        RetryableSendFailure::PaymentExpired => "Payment expired",
-        RetryableSendFailure::RouteNotFound => "Route not found",
+/// Why an outbound payment failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentFailureReason {
+    /// No route to the recipient could be found.
+    NoRoute,
+    /// Our channels can't send the amount of the payment.
+    InsufficientBalance,
+    /// The recipient rejected the payment, e.g. because the invoice is unknown or already paid.
+    RecipientRejected,
+    /// The payment did not succeed before the invoice or the retry timeout expired.
+    Timeout,
+    /// All retry attempts failed.
+    RetriesExhausted,
+    /// We already sent a payment with the same payment hash.
+    DuplicatePayment,
+}
+
+impl PaymentFailureReason {
+    /// A description of the reason, fit to show to users.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PaymentFailureReason::NoRoute => "Route not found",
+            PaymentFailureReason::InsufficientBalance => "Insufficient balance",
+            PaymentFailureReason::RecipientRejected => "Rejected by recipient",
+            PaymentFailureReason::Timeout => "Payment expired",
+            PaymentFailureReason::RetriesExhausted => "Retry attempts exhausted",
+            PaymentFailureReason::DuplicatePayment => "Duplicate payment",
+        }
+    }
+}
+
+impl fmt::Display for PaymentFailureReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentFailureReason::NoRoute => "NoRoute".fmt(f),
+            PaymentFailureReason::InsufficientBalance => "InsufficientBalance".fmt(f),
+            PaymentFailureReason::RecipientRejected => "RecipientRejected".fmt(f),
+            PaymentFailureReason::Timeout => "Timeout".fmt(f),
+            PaymentFailureReason::RetriesExhausted => "RetriesExhausted".fmt(f),
+            PaymentFailureReason::DuplicatePayment => "DuplicatePayment".fmt(f),
+        }
+    }
+}
+
+impl FromStr for PaymentFailureReason {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let reason = match s {
+            "NoRoute" => PaymentFailureReason::NoRoute,
+            "InsufficientBalance" => PaymentFailureReason::InsufficientBalance,
+            "RecipientRejected" => PaymentFailureReason::RecipientRejected,
+            "Timeout" => PaymentFailureReason::Timeout,
+            "RetriesExhausted" => PaymentFailureReason::RetriesExhausted,
+            "DuplicatePayment" => PaymentFailureReason::DuplicatePayment,
+            _ => bail!("Unknown payment failure reason: {s}"),
+        };
+
+        Ok(reason)
+    }
+}
+
+impl From<RetryableSendFailure> for PaymentFailureReason {
+    fn from(value: RetryableSendFailure) -> Self {
+        match value {
+            RetryableSendFailure::DuplicatePayment => PaymentFailureReason::DuplicatePayment,
+            RetryableSendFailure::PaymentExpired => PaymentFailureReason::Timeout,
+            RetryableSendFailure::RouteNotFound => PaymentFailureReason::NoRoute,
+        }
+    }
+}
+
+/// Whether LDK stopped retrying a payment `started` at that time because it ran out of time by
+/// `now`, either because the `retry` timeout elapsed or because the `invoice` expired.
+fn retries_timed_out(
+    retry: &Retry,
+    started: Option<OffsetDateTime>,
+    invoice: Option<&Invoice>,
+    now: OffsetDateTime,
+) -> bool {
+    let now_since_epoch = Duration::from_secs(now.unix_timestamp().max(0) as u64);
+
+    let invoice_expired = invoice
+        .map(|invoice| invoice.duration_since_epoch() + invoice.expiry_time() <= now_since_epoch)
+        .unwrap_or(false);
+
+    let retry_timed_out = match (retry, started) {
+        (Retry::Timeout(timeout), Some(started)) => {
+            (now - started).whole_milliseconds() >= timeout.as_millis() as i128
+        }
+        _ => false,
+    };
+
+    invoice_expired || retry_timed_out
+}
+
+/// The outcome of probing the route of a payment.
+#[derive(Debug, Clone, Copy)]
+pub struct PaymentProbe {
+    /// The routing fees of the payment, in msats.
+    pub fee_msat: u64,
+    /// Over how many paths the payment would be split.
+    pub paths: usize,
+    /// Whether the probes reached the recipient; `None` if they did not complete in time.
+    pub succeeded: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payment_failure_reason_roundtrips_through_string() {
+        for reason in [
+            PaymentFailureReason::NoRoute,
+            PaymentFailureReason::InsufficientBalance,
+            PaymentFailureReason::RecipientRejected,
+            PaymentFailureReason::Timeout,
+            PaymentFailureReason::RetriesExhausted,
+            PaymentFailureReason::DuplicatePayment,
+        ] {
+            assert_eq!(
+                PaymentFailureReason::from_str(&reason.to_string()).unwrap(),
+                reason
+            );
+        }
+    }
+
+    #[test]
+    fn payment_times_out_once_retry_timeout_elapsed() {
+        let started = OffsetDateTime::now_utc();
+        let retry = Retry::Timeout(Duration::from_secs(60));
+
+        assert!(!retries_timed_out(
+            &retry,
+            Some(started),
+            None,
+            started + time::Duration::seconds(59)
+        ));
+        assert!(retries_timed_out(
+            &retry,
+            Some(started),
+            None,
+            started + time::Duration::seconds(60)
+        ));
+    }
+
+    #[test]
+    fn payment_retried_by_attempts_does_not_time_out() {
+        let started = OffsetDateTime::now_utc();
+
+        assert!(!retries_timed_out(
+            &Retry::Attempts(10),
+            Some(started),
+            None,
+            started + time::Duration::days(1)
+        ));
+    }
+
+    #[test]
+    fn payment_times_out_once_invoice_expired() {
+        let invoice = test_invoice(60);
+        let created_at =
+            OffsetDateTime::from_unix_timestamp(invoice.duration_since_epoch().as_secs() as i64)
+                .unwrap();
+
+        assert!(!retries_timed_out(
+            &Retry::Attempts(10),
+            Some(created_at),
+            Some(&invoice),
+            created_at + time::Duration::seconds(30)
+        ));
+        assert!(retries_timed_out(
+            &Retry::Attempts(10),
+            Some(created_at),
+            Some(&invoice),
+            created_at + time::Duration::seconds(60)
+        ));
+    }
+
+    fn test_invoice(expiry_secs: u64) -> Invoice {
+        let secp = Secp256k1::new();
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[42; 32]).unwrap();
+
+        InvoiceBuilder::new(Currency::Regtest)
+            .description("test".to_string())
+            .payment_hash(sha256::Hash::hash(&[1; 32]))
+            .payment_secret(lightning::ln::PaymentSecret([2; 32]))
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(MIN_FINAL_CLTV_EXPIRY_DELTA.into())
+            .expiry_time(Duration::from_secs(expiry_secs))
+            .build_signed(|hash| secp.sign_ecdsa_recoverable(hash, &secret_key))
+            .unwrap()
+    }
+
+    #[cfg(feature = "bolt12")]
+    #[test]
+    fn offer_amount_is_at_least_the_amount_of_the_offer() {
+        let offer = test_offer(Network::Regtest, Some(5_000_000));
+
+        assert_eq!(
+            check_offer(&offer, Network::Regtest, None).unwrap(),
+            5_000_000
+        );
+        assert_eq!(
+            check_offer(&offer, Network::Regtest, Some(6_000_000)).unwrap(),
+            6_000_000
+        );
+        assert!(check_offer(&offer, Network::Regtest, Some(4_000_000)).is_err());
+    }
+
+    #[cfg(feature = "bolt12")]
+    #[test]
+    fn offer_without_amount_needs_an_amount() {
+        let offer = test_offer(Network::Regtest, None);
+
+        assert_eq!(
+            check_offer(&offer, Network::Regtest, Some(1_000)).unwrap(),
+            1_000
+        );
+        assert!(check_offer(&offer, Network::Regtest, None).is_err());
+    }
+
+    #[cfg(feature = "bolt12")]
+    #[test]
+    fn offer_for_other_network_is_rejected() {
+        let offer = test_offer(Network::Bitcoin, Some(5_000_000));
+
+        assert!(check_offer(&offer, Network::Regtest, None).is_err());
+    }
+
+    #[cfg(feature = "bolt12")]
+    fn test_offer(network: Network, amount_msat: Option<u64>) -> Offer {
+        let secp = Secp256k1::new();
+        let secret_key = bitcoin::secp256k1::SecretKey::new(&mut rand::thread_rng());
+        let node_id = PublicKey::from_secret_key(&secp, &secret_key);
+
+        let builder = OfferBuilder::new("test".to_string(), node_id).chain(network);
+        let builder = match amount_msat {
+            Some(amount_msat) => builder.amount_msats(amount_msat),
+            None => builder,
+        };
+
+        builder.build().unwrap()
     }
 }
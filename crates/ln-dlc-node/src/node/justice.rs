@@ -0,0 +1,282 @@
+//! Penalty transactions for revoked counterparty commitment transactions, handed to a watchtower
+//! so that we are protected while offline.
+//!
+//! We store every counterparty commitment transaction we sign (see
+//! [`crate::dlc_custom_signer::CustomSigner`]). Once the counterparty revokes one, we let a copy of
+//! the corresponding [`ChannelMonitor`] believe that it was published, which makes LDK build the
+//! penalty transaction for it without broadcasting anything.
+//!
+//! This covers commitment transactions spending the funding transaction as well as those spending
+//! the glue transaction of a DLC sub-channel. Revoked split transactions are not covered: their
+//! outputs can only be claimed with the counterparty's publish key, whose secret `rust-dlc`
+//! recovers from the counterparty's signature on the published split transaction. A penalty for
+//! them therefore cannot be signed in advance and handed to the watchtower. It is built by
+//! `rust-dlc` once the node sees the split transaction.
+
+use crate::dlc_custom_signer::CustomKeysManager;
+use crate::dlc_custom_signer::CustomSigner;
+use crate::fee_rate_estimator::FeeRateEstimator;
+use crate::ln::TracingLogger;
+use crate::ln_dlc_wallet::LnDlcWallet;
+use crate::node::LnDlcNodeSettings;
+use crate::node::Storage;
+use crate::watchtower::Appointment;
+use crate::watchtower::AppointmentRefused;
+use crate::watchtower::WatchtowerClient;
+use crate::ChainMonitor;
+use anyhow::anyhow;
+use anyhow::Result;
+use bitcoin::hashes::Hash;
+use bitcoin::BlockHash;
+use bitcoin::BlockHeader;
+use bitcoin::Transaction;
+use bitcoin::TxMerkleNode;
+use bitcoin::Txid;
+use lightning::chain::chaininterface::BroadcasterInterface;
+use lightning::chain::channelmonitor::ChannelMonitor;
+use lightning::util::ser::ReadableArgs;
+use lightning::util::ser::Writeable;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::sync::RwLock;
+use tokio::task::spawn_blocking;
+
+/// How often we hand the penalties of newly revoked transactions to the watchtower.
+const PUSH_JUSTICE_DATA_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often we look for a penalty for a revoked transaction before concluding that there is
+/// nothing to penalise, e.g. because all its outputs are ours.
+const MAX_PENALTY_ATTEMPTS: usize = 3;
+
+/// Whether the watchtower stores the penalties of our revoked transactions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WatchtowerStatus {
+    /// We have not handed any penalty to the watchtower yet.
+    #[default]
+    Unknown,
+    /// The watchtower accepted the last penalty we handed to it.
+    Accepted,
+    /// The watchtower refuses to store our penalties, so our funds are not safe while we are
+    /// offline.
+    Refused,
+}
+
+/// Builds the penalty transactions for revoked counterparty commitment transactions.
+#[derive(Clone)]
+pub(crate) struct Justice {
+    pub(crate) chain_monitor: Arc<ChainMonitor>,
+    pub(crate) keys_manager: Arc<CustomKeysManager>,
+    pub(crate) wallet: Arc<LnDlcWallet>,
+    pub(crate) fee_rate_estimator: Arc<FeeRateEstimator>,
+    pub(crate) logger: Arc<TracingLogger>,
+}
+
+/// Captures the transactions a [`ChannelMonitor`] would broadcast.
+#[derive(Default)]
+struct CapturingBroadcaster(Mutex<Vec<Transaction>>);
+
+impl BroadcasterInterface for CapturingBroadcaster {
+    fn broadcast_transaction(&self, tx: &Transaction) {
+        self.0.lock().push(tx.clone());
+    }
+}
+
+impl Justice {
+    /// The penalty transactions spending the outputs of `revoked_tx`.
+    pub(crate) fn penalty_transactions(
+        &self,
+        revoked_tx: &Transaction,
+    ) -> Result<Vec<Transaction>> {
+        let (height, tip) = self.wallet.tip()?;
+        let header = BlockHeader {
+            version: 2,
+            prev_blockhash: tip,
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: 0,
+            bits: 0,
+            nonce: 0,
+        };
+
+        let broadcaster = CapturingBroadcaster::default();
+        for funding_txo in self.chain_monitor.list_monitors() {
+            let monitor = self
+                .chain_monitor
+                .get_monitor(funding_txo)
+                .map_err(|_| anyhow!("Could not find monitor for {funding_txo:?}"))?
+                .encode();
+
+            // A copy of the monitor which we can safely make believe that `revoked_tx` was
+            // published.
+            let (_, monitor) = <(BlockHash, ChannelMonitor<CustomSigner>)>::read(
+                &mut Cursor::new(monitor),
+                (&*self.keys_manager, &*self.keys_manager),
+            )
+            .map_err(|e| anyhow!("Failed to copy monitor for {funding_txo:?}: {e:?}"))?;
+
+            monitor.transactions_confirmed(
+                &header,
+                &[(0, revoked_tx)],
+                height + 1,
+                &broadcaster,
+                &*self.fee_rate_estimator,
+                &*self.logger,
+            );
+        }
+
+        let revoked_txid = revoked_tx.txid();
+        let penalty_txs = broadcaster
+            .0
+            .into_inner()
+            .into_iter()
+            .filter(|tx| {
+                tx.input
+                    .iter()
+                    .any(|input| input.previous_output.txid == revoked_txid)
+            })
+            .collect();
+
+        Ok(penalty_txs)
+    }
+}
+
+/// Hands the penalties of revoked transactions to the watchtower configured in the node
+/// settings, if any, at an interval.
+///
+/// Revoked transactions are kept in the [`Storage`] until their penalties have been handed to the
+/// watchtower, so that none are lost if the node stops in between.
+///
+/// If the watchtower refuses our penalties, e.g. because we have exceeded our quota, we report it
+/// through `status` and stop handing it penalties until the node is restarted or another
+/// watchtower is configured.
+pub(crate) async fn push_justice_data_periodically<S: Storage>(
+    settings: Arc<RwLock<LnDlcNodeSettings>>,
+    storage: Arc<S>,
+    justice: Justice,
+    status: Arc<watch::Sender<WatchtowerStatus>>,
+) {
+    // How often we did not find a penalty for a revoked transaction.
+    let mut attempts = HashMap::<Txid, usize>::new();
+    // The watchtower which refused our penalties.
+    let mut refused_by: Option<String> = None;
+
+    loop {
+        let watchtower_url = settings.read().await.watchtower_url.clone();
+
+        let revoked_txs = match storage.revoked_counterparty_commitments() {
+            Ok(revoked_txs) => revoked_txs,
+            Err(e) => {
+                tracing::error!("Failed to load revoked transactions: {e:#}");
+                vec![]
+            }
+        };
+
+        match watchtower_url {
+            Some(url) if refused_by.as_ref() == Some(&url) => {}
+            Some(url) if !revoked_txs.is_empty() => {
+                let client =
+                    WatchtowerClient::new(url.clone(), justice.keys_manager.get_node_secret_key());
+
+                for revoked_tx in revoked_txs.iter() {
+                    let txid = revoked_tx.txid();
+                    match push_justice_data(&client, &justice, revoked_tx).await {
+                        Ok(0) => {
+                            // The monitor may just not know that the transaction is revoked yet.
+                            let attempts = attempts.entry(txid).or_default();
+                            *attempts += 1;
+                            if *attempts >= MAX_PENALTY_ATTEMPTS {
+                                tracing::debug!(
+                                    %txid,
+                                    "Nothing to penalise in revoked transaction"
+                                );
+                                delete_revoked_transaction(storage.as_ref(), &txid);
+                            }
+                        }
+                        Ok(_) => {
+                            delete_revoked_transaction(storage.as_ref(), &txid);
+                            set_status(&status, WatchtowerStatus::Accepted);
+                        }
+                        Err(e) if e.is::<AppointmentRefused>() => {
+                            tracing::error!(
+                                %txid,
+                                %url,
+                                "Watchtower refuses our penalties, our funds are not safe while \
+                                 we are offline: {e:#}"
+                            );
+                            set_status(&status, WatchtowerStatus::Refused);
+                            refused_by = Some(url);
+                            break;
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                %txid,
+                                "Failed to hand penalty to watchtower: {e:#}"
+                            );
+                        }
+                    }
+                }
+
+                attempts.retain(|txid, attempts| {
+                    *attempts < MAX_PENALTY_ATTEMPTS
+                        && revoked_txs.iter().any(|tx| tx.txid() == *txid)
+                });
+            }
+            Some(_) => {}
+            None => {
+                for revoked_tx in revoked_txs {
+                    let txid = revoked_tx.txid();
+                    tracing::debug!(
+                        %txid,
+                        "No watchtower configured, not handing penalty to watchtower"
+                    );
+                    delete_revoked_transaction(storage.as_ref(), &txid);
+                }
+            }
+        }
+
+        tokio::time::sleep(PUSH_JUSTICE_DATA_INTERVAL).await;
+    }
+}
+
+fn set_status(status: &watch::Sender<WatchtowerStatus>, new_status: WatchtowerStatus) {
+    status.send_if_modified(|status| std::mem::replace(status, new_status) != new_status);
+}
+
+fn delete_revoked_transaction(storage: &impl Storage, txid: &Txid) {
+    if let Err(e) = storage.delete_counterparty_commitment(txid) {
+        tracing::error!(%txid, "Failed to delete revoked transaction: {e:#}");
+    }
+}
+
+/// Hands the penalties of `revoked_tx` to the watchtower, returning how many there were.
+async fn push_justice_data(
+    client: &WatchtowerClient,
+    justice: &Justice,
+    revoked_tx: &Transaction,
+) -> Result<usize> {
+    let penalty_txs = spawn_blocking({
+        let justice = justice.clone();
+        let revoked_tx = revoked_tx.clone();
+        move || justice.penalty_transactions(&revoked_tx)
+    })
+    .await
+    .expect("task to complete")?;
+
+    let revoked_txid = revoked_tx.txid();
+    for penalty_tx in penalty_txs.iter() {
+        client
+            .add_appointment(Appointment::new(&revoked_txid, penalty_tx)?)
+            .await?;
+
+        tracing::info!(
+            %revoked_txid,
+            penalty_txid = %penalty_tx.txid(),
+            "Handed penalty to watchtower"
+        );
+    }
+
+    Ok(penalty_txs.len())
+}
@@ -1,9 +1,13 @@
 pub use self::dlc_manager::signed_channel_state_name;
 pub use self::dlc_manager::DlcManager;
+use crate::chain::ChainClient;
+use crate::chain::ChainSource;
+use crate::chain::LightningSync;
 use crate::channel::UserChannelId;
 use crate::disk;
 use crate::dlc_custom_signer::CustomKeysManager;
 use crate::fee_rate_estimator::FeeRateEstimator;
+use crate::ldk_node_wallet::Fee;
 use crate::ln::manage_spendable_outputs;
 use crate::ln::TracingLogger;
 use crate::ln_dlc_wallet::LnDlcWallet;
@@ -18,6 +22,7 @@ use crate::ChainMonitor;
 use crate::EventHandlerTrait;
 use crate::NetworkGraph;
 use crate::PeerManager;
+use crate::Router;
 pub use ::dlc_manager as rust_dlc_manager;
 use anyhow::Context;
 use anyhow::Result;
@@ -30,13 +35,27 @@ pub use dlc_channel::dlc_message_name;
 pub use dlc_channel::sub_channel_message_name;
 use dlc_messages::message_handler::MessageHandler as DlcMessageHandler;
 use dlc_sled_storage_provider::SledStorageProvider;
+pub use fee_bump::FeeBumpMethod;
+pub use fee_bump::FeeBumpPolicy;
 use futures::future::RemoteHandle;
 use futures::FutureExt;
+pub use hold_invoice::HoldInvoiceState;
+use hold_invoice::HoldInvoices;
+#[cfg(feature = "bolt12")]
+pub use invoice::offer_amount_msat;
+#[cfg(feature = "bolt12")]
+pub use invoice::offer_description;
 pub use invoice::HTLCStatus;
+pub use invoice::PaymentFailureReason;
+pub use invoice::PaymentProbe;
+pub use justice::WatchtowerStatus;
+use lightning::chain::chaininterface::ConfirmationTarget;
 use lightning::chain::chainmonitor;
 use lightning::chain::keysinterface::EntropySource;
 use lightning::chain::keysinterface::KeysManager;
 use lightning::chain::Confirm;
+use lightning::chain::Filter;
+use lightning::ln::channelmanager::Retry;
 use lightning::ln::msgs::NetAddress;
 use lightning::ln::peer_handler::MessageHandler;
 use lightning::routing::gossip::P2PGossipSync;
@@ -47,8 +66,11 @@ use lightning::util::config::UserConfig;
 use lightning_background_processor::process_events_async;
 use lightning_background_processor::GossipSync;
 use lightning_persister::FilesystemPersister;
-use lightning_transaction_sync::EsploraSyncClient;
 use p2pd_oracle_client::P2PDOracleClient;
+pub use payment_attempts::PaymentAttempt;
+pub use payment_attempts::PaymentAttemptOutcome;
+use payment_attempts::PaymentAttempts;
+pub use payment_attempts::PaymentHop;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_with::serde_as;
@@ -67,6 +89,8 @@ use std::time::SystemTime;
 pub use storage::InMemoryStore;
 pub use storage::Storage;
 pub use sub_channel_manager::SubChannelManager;
+use time::OffsetDateTime;
+use tokio::sync::watch;
 use tokio::sync::RwLock;
 use tokio::task::spawn_blocking;
 pub use wallet::PaymentDetails;
@@ -74,14 +98,18 @@ pub use wallet::PaymentDetails;
 mod channel_manager;
 mod connection;
 mod dlc_manager;
+mod fee_bump;
 mod ln_channel;
 mod oracle;
+mod payment_attempts;
 mod storage;
 mod sub_channel_manager;
 mod wallet;
 
 pub(crate) mod dlc_channel;
+pub(crate) mod hold_invoice;
 pub(crate) mod invoice;
+pub(crate) mod justice;
 
 pub mod peer_manager;
 
@@ -98,8 +126,6 @@ type Scorer = ProbabilisticScorer<Arc<NetworkGraph>, Arc<TracingLogger>>;
 type NodeGossipSync =
     P2PGossipSync<Arc<NetworkGraph>, Arc<dyn UtxoLookup + Send + Sync>, Arc<TracingLogger>>;
 
-type NodeEsploraClient = EsploraSyncClient<Arc<TracingLogger>>;
-
 type RequestedScid = u64;
 // TODO(holzeis): Move to coordinator
 type FakeChannelPaymentRequests = Arc<parking_lot::Mutex<HashMap<RequestedScid, LiquidityRequest>>>;
@@ -132,6 +158,12 @@ pub struct Node<S> {
 
     pub info: NodeInfo,
     pub(crate) fake_channel_payments: FakeChannelPaymentRequests,
+    pub(crate) payment_attempts: PaymentAttempts,
+    /// The retry strategy for outbound payments, kept in sync with [`LnDlcNodeSettings`].
+    pub(crate) payment_retry: Arc<parking_lot::RwLock<Retry>>,
+    pub(crate) hold_invoices: HoldInvoices,
+    /// Whether the watchtower stores the penalties of our revoked transactions.
+    watchtower_status: Arc<watch::Sender<WatchtowerStatus>>,
 
     pub dlc_manager: Arc<DlcManager>,
     pub sub_channel_manager: Arc<SubChannelManager>,
@@ -147,8 +179,9 @@ pub struct Node<S> {
     alias: String,
     announcement_addresses: Vec<NetAddress>,
     scorer: Arc<Mutex<Scorer>>,
-    esplora_server_url: String,
-    esplora_client: Arc<NodeEsploraClient>,
+    router: Arc<Router>,
+    chain_client: Arc<ChainClient>,
+    lightning_sync: Arc<LightningSync>,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
@@ -197,6 +230,25 @@ pub struct LnDlcNodeSettings {
     /// Note: This constant and value was copied from ldk_node
     /// XXX: Requires restart of the node to take effect
     pub bdk_client_concurrency: u8,
+
+    /// If set, the fees of our transactions are bumped automatically if they are not confirmed in
+    /// time.
+    #[serde(default)]
+    pub fee_bump_policy: Option<FeeBumpPolicy>,
+
+    /// How many times an outbound payment is retried over different paths before it fails.
+    #[serde(default = "default_payment_retry_attempts")]
+    pub payment_retry_attempts: usize,
+    /// If set, outbound payments are retried until this much time has passed since they were
+    /// sent, instead of for a fixed number of attempts.
+    #[serde_as(as = "Option<DurationSeconds>")]
+    #[serde(default)]
+    pub payment_retry_timeout: Option<Duration>,
+
+    /// If set, the penalties of revoked counterparty transactions are handed to the watchtower
+    /// at this URL, so that our funds are safe while we are offline.
+    #[serde(default)]
+    pub watchtower_url: Option<String>,
 }
 
 impl Default for LnDlcNodeSettings {
@@ -211,16 +263,35 @@ impl Default for LnDlcNodeSettings {
             shadow_sync_interval: Duration::from_secs(600),
             bdk_client_stop_gap: 20,
             bdk_client_concurrency: 4,
+            fee_bump_policy: None,
+            payment_retry_attempts: default_payment_retry_attempts(),
+            payment_retry_timeout: None,
+            watchtower_url: None,
         }
     }
 }
 
+impl LnDlcNodeSettings {
+    /// The strategy with which LDK retries outbound payments.
+    pub fn payment_retry(&self) -> Retry {
+        match self.payment_retry_timeout {
+            Some(timeout) => Retry::Timeout(timeout),
+            None => Retry::Attempts(self.payment_retry_attempts),
+        }
+    }
+}
+
+fn default_payment_retry_attempts() -> usize {
+    10
+}
+
 impl<S> Node<S>
 where
     S: Storage + Send + Sync + 'static,
 {
     pub async fn update_settings(&self, new_settings: LnDlcNodeSettings) {
         tracing::info!(?new_settings, "Updating LnDlcNode settings");
+        *self.payment_retry.write() = new_settings.payment_retry();
         *self.settings.write().await = new_settings;
     }
 
@@ -236,7 +307,7 @@ where
         announcement_address: SocketAddr,
         listen_address: SocketAddr,
         announcement_addresses: Vec<NetAddress>,
-        esplora_server_url: String,
+        chain_source: ChainSource,
         seed: Bip39Seed,
         ephemeral_randomness: [u8; 32],
         settings: LnDlcNodeSettings,
@@ -271,28 +342,39 @@ where
         let on_chain_wallet =
             OnChainWallet::new(on_chain_dir.as_path(), network, seed.wallet_seed())?;
 
-        let esplora_client = Arc::new(EsploraSyncClient::new(
-            esplora_server_url.clone(),
+        tracing::info!(?chain_source, "Using chain source");
+
+        let chain_client = Arc::new(ChainClient::new(&chain_source)?);
+        let lightning_sync = Arc::new(LightningSync::new(
+            &chain_source,
+            &chain_client,
             logger.clone(),
         ));
 
-        let fee_rate_estimator = Arc::new(FeeRateEstimator::new(esplora_server_url.clone()));
+        let fee_rate_estimator = Arc::new(FeeRateEstimator::new(chain_client.clone()));
         let ln_dlc_wallet = {
+            let blockchain = chain_source.wallet_blockchain(
+                network,
+                on_chain_wallet.name,
+                settings.bdk_client_stop_gap,
+                settings.bdk_client_concurrency,
+            )?;
+
             Arc::new(LnDlcWallet::new(
-                esplora_client.clone(),
+                blockchain,
+                chain_client.clone(),
                 on_chain_wallet.inner,
                 fee_rate_estimator.clone(),
                 dlc_storage.clone(),
-                settings.bdk_client_stop_gap,
-                settings.bdk_client_concurrency,
                 node_storage.clone(),
             ))
         };
 
+        let payment_retry = Arc::new(parking_lot::RwLock::new(settings.payment_retry()));
         let settings = Arc::new(RwLock::new(settings));
 
         let chain_monitor: Arc<ChainMonitor> = Arc::new(chainmonitor::ChainMonitor::new(
-            Some(esplora_client.clone()),
+            Some(lightning_sync.clone() as Arc<dyn Filter + Send + Sync>),
             ln_dlc_wallet.clone(),
             logger.clone(),
             fee_rate_estimator.clone(),
@@ -307,6 +389,7 @@ where
                     time_since_unix_epoch.subsec_nanos(),
                 ),
                 ln_dlc_wallet.clone(),
+                node_storage.clone(),
             ))
         };
 
@@ -336,13 +419,13 @@ where
             keys_manager.clone(),
             ln_dlc_wallet.clone(),
             fee_rate_estimator.clone(),
-            esplora_client.clone(),
+            lightning_sync.clone(),
             logger.clone(),
             chain_monitor.clone(),
             *ldk_config.read(),
             network,
             persister.clone(),
-            router,
+            router.clone(),
         )?;
 
         let channel_manager = Arc::new(channel_manager);
@@ -404,6 +487,14 @@ where
             channel_manager: channel_manager.clone(),
             info: node_info,
             fake_channel_payments,
+            payment_attempts: PaymentAttempts::default(),
+            payment_retry,
+            hold_invoices: HoldInvoices::load(
+                node_storage.all_payments()?,
+                &channel_manager,
+                OffsetDateTime::now_utc(),
+            ),
+            watchtower_status: Arc::new(watch::channel(WatchtowerStatus::default()).0),
             sub_channel_manager,
             oracle: oracle_client,
             dlc_message_handler,
@@ -419,8 +510,9 @@ where
             alias: alias.to_string(),
             announcement_addresses,
             scorer,
-            esplora_server_url,
-            esplora_client,
+            router,
+            chain_client,
+            lightning_sync,
         })
     }
 
@@ -440,11 +532,16 @@ where
             self.channel_manager.clone(),
         ));
 
+        std::thread::spawn(fee_bump::bump_fees_periodically(
+            self.settings.clone(),
+            self.wallet.clone(),
+        ));
+
         tokio::spawn(periodic_lightning_wallet_sync(
             self.channel_manager.clone(),
             self.chain_monitor.clone(),
             self.settings.clone(),
-            self.esplora_client.clone(),
+            self.lightning_sync.clone(),
         ));
 
         tokio::spawn(update_fee_rate_estimates(
@@ -477,18 +574,46 @@ where
         ));
 
         tokio::spawn(manage_spendable_outputs_task(
-            self.esplora_server_url.clone(),
+            self.chain_client.clone(),
             self.storage.clone(),
             self.wallet.clone(),
             self.fee_rate_estimator.clone(),
             self.keys_manager.clone(),
         ));
 
+        tokio::spawn(hold_invoice::cancel_expired_hold_invoices_periodically(
+            self.hold_invoices.clone(),
+            self.channel_manager.clone(),
+            self.storage.clone(),
+        ));
+
+        tokio::spawn(justice::push_justice_data_periodically(
+            self.settings.clone(),
+            self.storage.clone(),
+            self.justice(),
+            self.watchtower_status.clone(),
+        ));
+
         tracing::info!("Lightning node started with node ID {}", self.info);
 
         Ok(RunningNode { _handles: handles })
     }
 
+    pub(crate) fn justice(&self) -> justice::Justice {
+        justice::Justice {
+            chain_monitor: self.chain_monitor.clone(),
+            keys_manager: self.keys_manager.clone(),
+            wallet: self.wallet.clone(),
+            fee_rate_estimator: self.fee_rate_estimator.clone(),
+            logger: self.logger.clone(),
+        }
+    }
+
+    /// Subscribes to whether the watchtower stores the penalties of our revoked transactions.
+    pub fn watchtower_status(&self) -> watch::Receiver<WatchtowerStatus> {
+        self.watchtower_status.subscribe()
+    }
+
     pub fn update_ldk_settings(&self, ldk_config: UserConfig) {
         tracing::debug!("Updating LDK settings");
         *self.ldk_config.write() = ldk_config;
@@ -552,15 +677,18 @@ where
         lightning_wallet_sync(
             &self.channel_manager,
             &self.chain_monitor,
-            &self.esplora_client,
+            &self.lightning_sync,
         )
     }
 
     /// Send the given `amount_sats` sats to the given `address` on-chain.
     pub fn send_to_address(&self, address: &bitcoin::Address, amount_sats: u64) -> Result<Txid> {
-        self.wallet
-            .ldk_wallet()
-            .send_to_address(address, Some(amount_sats))
+        self.wallet.ldk_wallet().send_to_address(
+            address,
+            Some(amount_sats),
+            Fee::Priority(ConfirmationTarget::Normal),
+            &[],
+        )
     }
 }
 
@@ -624,10 +752,10 @@ async fn periodic_lightning_wallet_sync(
     channel_manager: Arc<ChannelManager>,
     chain_monitor: Arc<ChainMonitor>,
     settings: Arc<RwLock<LnDlcNodeSettings>>,
-    esplora_client: Arc<EsploraSyncClient<Arc<TracingLogger>>>,
+    lightning_sync: Arc<LightningSync>,
 ) {
     loop {
-        if let Err(e) = lightning_wallet_sync(&channel_manager, &chain_monitor, &esplora_client) {
+        if let Err(e) = lightning_wallet_sync(&channel_manager, &chain_monitor, &lightning_sync) {
             tracing::error!("Background sync of Lightning wallet failed: {e:#}")
         }
 
@@ -642,14 +770,14 @@ async fn periodic_lightning_wallet_sync(
 fn lightning_wallet_sync(
     channel_manager: &ChannelManager,
     chain_monitor: &ChainMonitor,
-    esplora_client: &EsploraSyncClient<Arc<TracingLogger>>,
+    lightning_sync: &LightningSync,
 ) -> Result<()> {
     let now = Instant::now();
     let confirmables = vec![
         channel_manager as &(dyn Confirm + Sync + Send),
         chain_monitor as &(dyn Confirm + Sync + Send),
     ];
-    esplora_client
+    lightning_sync
         .sync(confirmables)
         .context("Lightning wallet sync failed")?;
 
@@ -758,19 +886,15 @@ fn spawn_broadcast_node_annoucements(
 }
 
 async fn manage_spendable_outputs_task<S: Storage + Send + Sync + 'static>(
-    esplora_server_url: String,
+    chain_client: Arc<ChainClient>,
     node_storage: Arc<S>,
     ln_dlc_wallet: Arc<LnDlcWallet>,
     fee_rate_estimator: Arc<FeeRateEstimator>,
     keys_manager: Arc<CustomKeysManager>,
 ) {
-    let client = Arc::new(esplora_client::BlockingClient::from_agent(
-        esplora_server_url,
-        ureq::agent(),
-    ));
     loop {
         if let Err(e) = spawn_blocking({
-            let client = client.clone();
+            let chain_client = chain_client.clone();
             let node_storage = node_storage.clone();
             let ln_dlc_wallet = ln_dlc_wallet.clone();
             let fee_rate_estimator = fee_rate_estimator.clone();
@@ -778,7 +902,7 @@ async fn manage_spendable_outputs_task<S: Storage + Send + Sync + 'static>(
             move || {
                 manage_spendable_outputs(
                     node_storage,
-                    client,
+                    chain_client,
                     ln_dlc_wallet,
                     fee_rate_estimator,
                     keys_manager,
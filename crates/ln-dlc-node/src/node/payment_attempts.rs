@@ -0,0 +1,189 @@
+use crate::node::invoice::PaymentFailureReason;
+use bitcoin::secp256k1::PublicKey;
+use lightning::ln::PaymentHash;
+use lightning::routing::router::RouteHop;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// For how long we remember the attempts of a payment after its last attempt.
+const RETENTION: time::Duration = time::Duration::days(1);
+
+/// The paths over which we tried to route our outbound payments and probes, by payment hash.
+///
+/// Attempts are only kept in memory, for diagnosing recent payments.
+#[derive(Default, Clone)]
+pub struct PaymentAttempts(Arc<Mutex<HashMap<PaymentHash, Vec<PaymentAttempt>>>>);
+
+/// An attempt to route (a part of) a payment over a single path.
+#[derive(Debug, Clone)]
+pub struct PaymentAttempt {
+    pub timestamp: OffsetDateTime,
+    pub hops: Vec<PaymentHop>,
+    /// The amount delivered to the recipient over this path.
+    pub amount_msat: u64,
+    /// The fees paid to the forwarding nodes of this path.
+    pub fee_msat: u64,
+    /// Whether this was a probe, rather than an actual payment.
+    pub probe: bool,
+    pub outcome: PaymentAttemptOutcome,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaymentHop {
+    pub node_id: PublicKey,
+    pub short_channel_id: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentAttemptOutcome {
+    Succeeded,
+    Failed {
+        /// Whether the payment can't succeed over any path, e.g. because the recipient rejected
+        /// it.
+        permanently: bool,
+        /// The channel at which the payment failed, if known.
+        short_channel_id: Option<u64>,
+    },
+}
+
+impl PaymentAttempts {
+    pub(crate) fn record(
+        &self,
+        payment_hash: PaymentHash,
+        path: &[RouteHop],
+        probe: bool,
+        outcome: PaymentAttemptOutcome,
+    ) {
+        let now = OffsetDateTime::now_utc();
+        let attempt = PaymentAttempt::new(path, probe, outcome, now);
+
+        let mut attempts = self.0.lock();
+        attempts.retain(|_, attempts| {
+            attempts
+                .last()
+                .map(|attempt| now - attempt.timestamp < RETENTION)
+                .unwrap_or(false)
+        });
+        attempts.entry(payment_hash).or_default().push(attempt);
+    }
+
+    /// The attempts of the payment with `payment_hash`, oldest first.
+    pub fn get(&self, payment_hash: &PaymentHash) -> Vec<PaymentAttempt> {
+        self.0.lock().get(payment_hash).cloned().unwrap_or_default()
+    }
+}
+
+impl PaymentAttempt {
+    fn new(
+        path: &[RouteHop],
+        probe: bool,
+        outcome: PaymentAttemptOutcome,
+        timestamp: OffsetDateTime,
+    ) -> Self {
+        // The last hop carries the amount for the recipient, all other hops the fee of the
+        // respective forwarding node.
+        let (amount_msat, fee_msat) = match path.split_last() {
+            Some((last, forwarding)) => (
+                last.fee_msat,
+                forwarding.iter().map(|hop| hop.fee_msat).sum(),
+            ),
+            None => (0, 0),
+        };
+
+        Self {
+            timestamp,
+            hops: path
+                .iter()
+                .map(|hop| PaymentHop {
+                    node_id: hop.pubkey,
+                    short_channel_id: hop.short_channel_id,
+                })
+                .collect(),
+            amount_msat,
+            fee_msat,
+            probe,
+            outcome,
+        }
+    }
+}
+
+/// Why a payment failed after LDK gave up on it, judging by its `attempts`.
+///
+/// `timed_out` indicates whether LDK stopped retrying because the retry timeout or the invoice
+/// expired.
+pub(crate) fn failure_reason(attempts: &[PaymentAttempt], timed_out: bool) -> PaymentFailureReason {
+    let attempts = attempts
+        .iter()
+        .filter(|attempt| !attempt.probe)
+        .collect::<Vec<_>>();
+
+    let rejected = attempts.iter().any(|attempt| {
+        matches!(
+            attempt.outcome,
+            PaymentAttemptOutcome::Failed {
+                permanently: true,
+                ..
+            }
+        )
+    });
+
+    if rejected {
+        PaymentFailureReason::RecipientRejected
+    } else if timed_out {
+        PaymentFailureReason::Timeout
+    } else if attempts.is_empty() {
+        PaymentFailureReason::NoRoute
+    } else {
+        PaymentFailureReason::RetriesExhausted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(probe: bool, outcome: PaymentAttemptOutcome) -> PaymentAttempt {
+        PaymentAttempt::new(&[], probe, outcome, OffsetDateTime::now_utc())
+    }
+
+    fn failed(permanently: bool) -> PaymentAttemptOutcome {
+        PaymentAttemptOutcome::Failed {
+            permanently,
+            short_channel_id: None,
+        }
+    }
+
+    #[test]
+    fn payment_without_attempts_had_no_route() {
+        assert_eq!(
+            failure_reason(&[attempt(true, failed(false))], false),
+            PaymentFailureReason::NoRoute
+        );
+    }
+
+    #[test]
+    fn permanent_failure_is_rejection_by_recipient() {
+        let attempts = [attempt(false, failed(false)), attempt(false, failed(true))];
+
+        assert_eq!(
+            failure_reason(&attempts, true),
+            PaymentFailureReason::RecipientRejected
+        );
+    }
+
+    #[test]
+    fn temporary_failures_exhaust_retries_or_time_out() {
+        let attempts = [attempt(false, failed(false)), attempt(false, failed(false))];
+
+        assert_eq!(
+            failure_reason(&attempts, false),
+            PaymentFailureReason::RetriesExhausted
+        );
+        assert_eq!(
+            failure_reason(&attempts, true),
+            PaymentFailureReason::Timeout
+        );
+    }
+}
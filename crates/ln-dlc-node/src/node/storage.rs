@@ -3,10 +3,13 @@ use crate::channel::ChannelState;
 use crate::transaction::Transaction;
 use crate::HTLCStatus;
 use crate::MillisatAmount;
+use crate::PaymentFailureReason;
 use crate::PaymentFlow;
 use crate::PaymentInfo;
+use anyhow::Context;
 use anyhow::Result;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::Txid;
 use lightning::chain::keysinterface::DelayedPaymentOutputDescriptor;
 use lightning::chain::keysinterface::SpendableOutputDescriptor;
 use lightning::chain::keysinterface::StaticPaymentOutputDescriptor;
@@ -39,6 +42,12 @@ pub trait Storage {
         preimage: Option<PaymentPreimage>,
         secret: Option<PaymentSecret>,
     ) -> Result<()>;
+    /// Record why an outbound payment failed.
+    fn set_payment_failure_reason(
+        &self,
+        payment_hash: &PaymentHash,
+        failure_reason: PaymentFailureReason,
+    ) -> Result<()>;
     /// Get a payment based on its payment hash.
     ///
     /// # Returns
@@ -91,6 +100,34 @@ pub trait Storage {
     fn get_transaction(&self, txid: &str) -> Result<Option<Transaction>>;
     /// Get all transactions without fees
     fn all_transactions_without_fees(&self) -> Result<Vec<Transaction>>;
+
+    // Counterparty commitments
+
+    /// Add a counterparty commitment transaction we have signed.
+    fn insert_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+        transaction: bitcoin::Transaction,
+    ) -> Result<()>;
+    /// Mark a counterparty commitment transaction as revoked by the counterparty.
+    fn revoke_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+    ) -> Result<()>;
+    /// Get all counterparty commitment transactions which have been revoked.
+    fn revoked_counterparty_commitments(&self) -> Result<Vec<bitcoin::Transaction>>;
+    /// Delete a counterparty commitment transaction by its [`Txid`].
+    fn delete_counterparty_commitment(&self, txid: &Txid) -> Result<()>;
+}
+
+#[derive(Clone)]
+struct CounterpartyCommitment {
+    channel_keys_id: [u8; 32],
+    commitment_number: u64,
+    transaction: bitcoin::Transaction,
+    revoked: bool,
 }
 
 #[derive(Default, Clone)]
@@ -99,6 +136,7 @@ pub struct InMemoryStore {
     spendable_outputs: Arc<Mutex<HashMap<OutPoint, SpendableOutputDescriptor>>>,
     channels: Arc<Mutex<HashMap<String, Channel>>>,
     transactions: Arc<Mutex<HashMap<String, Transaction>>>,
+    counterparty_commitments: Arc<Mutex<HashMap<Txid, CounterpartyCommitment>>>,
 }
 
 impl Storage for InMemoryStore {
@@ -154,6 +192,7 @@ impl Storage for InMemoryStore {
                         timestamp: OffsetDateTime::now_utc(),
                         description: "".to_string(),
                         invoice: None,
+                        failure_reason: None,
                     },
                 );
             }
@@ -162,6 +201,20 @@ impl Storage for InMemoryStore {
         Ok(())
     }
 
+    fn set_payment_failure_reason(
+        &self,
+        payment_hash: &PaymentHash,
+        failure_reason: PaymentFailureReason,
+    ) -> Result<()> {
+        let mut payments = self.payments.lock();
+        let payment = payments
+            .get_mut(payment_hash)
+            .context("Payment not found")?;
+        payment.failure_reason = Some(failure_reason);
+
+        Ok(())
+    }
+
     fn get_payment(
         &self,
         payment_hash: &PaymentHash,
@@ -271,4 +324,57 @@ impl Storage for InMemoryStore {
             .cloned()
             .collect())
     }
+
+    // Counterparty commitments
+
+    fn insert_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+        transaction: bitcoin::Transaction,
+    ) -> Result<()> {
+        self.counterparty_commitments.lock().insert(
+            transaction.txid(),
+            CounterpartyCommitment {
+                channel_keys_id,
+                commitment_number,
+                transaction,
+                revoked: false,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn revoke_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+    ) -> Result<()> {
+        for commitment in self.counterparty_commitments.lock().values_mut() {
+            if commitment.channel_keys_id == channel_keys_id
+                && commitment.commitment_number == commitment_number
+            {
+                commitment.revoked = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn revoked_counterparty_commitments(&self) -> Result<Vec<bitcoin::Transaction>> {
+        Ok(self
+            .counterparty_commitments
+            .lock()
+            .values()
+            .filter(|c| c.revoked)
+            .map(|c| c.transaction.clone())
+            .collect())
+    }
+
+    fn delete_counterparty_commitment(&self, txid: &Txid) -> Result<()> {
+        self.counterparty_commitments.lock().remove(txid);
+
+        Ok(())
+    }
 }
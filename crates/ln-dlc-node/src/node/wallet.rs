@@ -2,12 +2,13 @@ use crate::fee_rate_estimator::FeeRateEstimator;
 use crate::ldk_node_wallet;
 use crate::node::HTLCStatus;
 use crate::node::Node;
+use crate::node::PaymentFailureReason;
 use crate::node::Storage;
 use crate::PaymentFlow;
 use crate::ToHex;
 use anyhow::Context;
 use anyhow::Result;
-use bdk::blockchain::EsploraBlockchain;
+use bdk::blockchain::AnyBlockchain;
 use bdk::sled;
 use bitcoin::secp256k1::SecretKey;
 use bitcoin::Address;
@@ -48,7 +49,7 @@ where
 {
     pub fn wallet(
         &self,
-    ) -> Arc<ldk_node_wallet::Wallet<sled::Tree, EsploraBlockchain, FeeRateEstimator>> {
+    ) -> Arc<ldk_node_wallet::Wallet<sled::Tree, AnyBlockchain, FeeRateEstimator>> {
         self.wallet.ldk_wallet()
     }
 
@@ -150,6 +151,7 @@ where
                 description: info.description.clone(),
                 preimage: info.preimage.map(|preimage| preimage.0.to_hex()),
                 invoice: info.invoice.clone(),
+                failure_reason: info.failure_reason,
             })
             .collect::<Vec<_>>();
 
@@ -170,6 +172,7 @@ pub struct PaymentDetails {
     pub description: String,
     pub preimage: Option<String>,
     pub invoice: Option<String>,
+    pub failure_reason: Option<PaymentFailureReason>,
 }
 
 impl fmt::Display for PaymentDetails {
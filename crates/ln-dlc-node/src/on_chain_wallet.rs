@@ -8,6 +8,8 @@ use std::path::Path;
 
 pub struct OnChainWallet {
     pub inner: bdk::Wallet<sled::Tree>,
+    /// A name derived from the wallet descriptors, unique per wallet.
+    pub name: String,
 }
 
 impl OnChainWallet {
@@ -36,7 +38,7 @@ impl OnChainWallet {
 
         // Create a database (using default sled type) to store wallet data
         let db = bdk::sled::open(data_dir.join("wallet"))?;
-        let db = db.open_tree(wallet_name)?;
+        let db = db.open_tree(&wallet_name)?;
 
         let bdk_wallet = bdk::Wallet::new(
             bdk::template::Bip84(ext_priv_key, KeychainKind::External),
@@ -45,6 +47,9 @@ impl OnChainWallet {
             db,
         )?;
 
-        Ok(OnChainWallet { inner: bdk_wallet })
+        Ok(OnChainWallet {
+            inner: bdk_wallet,
+            name: wallet_name,
+        })
     }
 }
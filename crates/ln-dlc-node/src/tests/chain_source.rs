@@ -0,0 +1,90 @@
+use crate::node::InMemoryStore;
+use crate::node::LnDlcNodeSettings;
+use crate::node::Node;
+use crate::tests::init_tracing;
+use crate::ChainSource;
+use bitcoin::Amount;
+use std::sync::Arc;
+
+#[cfg(feature = "bitcoind")]
+const BITCOIND_ORIGIN: &str = "http://localhost:18443";
+#[cfg(feature = "bitcoind")]
+const BITCOIND_RPC_USER: &str = "admin1";
+#[cfg(feature = "bitcoind")]
+const BITCOIND_RPC_PASSWORD: &str = "123";
+#[cfg(feature = "electrum")]
+const ELECTRUM_ORIGIN: &str = "tcp://localhost:50000";
+
+#[cfg(feature = "bitcoind")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn open_channel_and_pay_with_bitcoind_chain_source() {
+    init_tracing();
+
+    open_channel_and_pay(ChainSource::Bitcoind {
+        url: BITCOIND_ORIGIN.to_string(),
+        user: BITCOIND_RPC_USER.to_string(),
+        password: BITCOIND_RPC_PASSWORD.to_string(),
+    })
+    .await;
+}
+
+#[cfg(feature = "electrum")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn open_channel_and_pay_with_electrum_chain_source() {
+    init_tracing();
+
+    open_channel_and_pay(ChainSource::Electrum {
+        url: ELECTRUM_ORIGIN.to_string(),
+    })
+    .await;
+}
+
+/// Both nodes sync their wallets and LDK through `chain_source`. The coordinator does not accept
+/// 0-conf channels, so the channel only becomes usable once both nodes have seen the funding
+/// transaction confirm.
+async fn open_channel_and_pay(chain_source: ChainSource) {
+    // Arrange
+
+    let (app, _running_app) =
+        Node::start_test_app_with_chain_source("app", chain_source.clone()).unwrap();
+    let (coordinator, _running_coordinator) =
+        Node::start_test_coordinator_internal_with_chain_source(
+            "coordinator",
+            chain_source,
+            Arc::new(InMemoryStore::default()),
+            LnDlcNodeSettings::default(),
+            None,
+        )
+        .unwrap();
+
+    app.connect(coordinator.info).await.unwrap();
+
+    app.fund(Amount::from_btc(0.1).unwrap()).await.unwrap();
+
+    // Act
+
+    app.open_private_channel(&coordinator, 50_000, 0)
+        .await
+        .unwrap();
+
+    let invoice_amount = 3_000;
+    let invoice = coordinator
+        .create_invoice(invoice_amount, "".to_string(), 180)
+        .unwrap();
+
+    app.pay_invoice(&invoice, None).unwrap();
+
+    coordinator
+        .wait_for_payment_claimed(invoice.payment_hash())
+        .await
+        .unwrap();
+
+    // Assert
+
+    app.sync_on_chain().await.unwrap();
+    coordinator.sync_on_chain().await.unwrap();
+
+    assert_eq!(coordinator.get_ldk_balance().available(), invoice_amount);
+}
@@ -0,0 +1,173 @@
+use crate::node::HoldInvoiceState;
+use crate::node::InMemoryStore;
+use crate::node::Node;
+use crate::tests::dlc::create::create_dlc_channel;
+use crate::tests::dummy_contract_input;
+use crate::tests::init_tracing;
+use crate::tests::wait_for_n_usable_channels;
+use crate::tests::wait_until;
+use crate::tests::wait_until_dlc_channel_state;
+use crate::tests::SubChannelStateName;
+use crate::HTLCStatus;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::Amount;
+use lightning::ln::PaymentHash;
+use lightning::ln::PaymentPreimage;
+use lightning_invoice::Invoice;
+use std::time::Duration;
+
+const FEE_SATS: u64 = 1_000;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn fee_is_held_until_dlc_is_signed() {
+    init_tracing();
+
+    // Arrange
+
+    let (app, _running_app) = Node::start_test_app("app").unwrap();
+    let (coordinator, _running_coord) = Node::start_test_coordinator("coordinator").unwrap();
+    setup_channel(&app, &coordinator).await;
+
+    let (preimage, payment_hash, invoice) = fee_invoice(&coordinator);
+
+    // Act
+
+    app.pay_invoice(&invoice, None).unwrap();
+    wait_until_fee_is_held(&coordinator, payment_hash).await;
+
+    // The DLC is set up while the HTLC of the fee is pending in the channel.
+    create_dlc_channel(&app, &coordinator, 50_000, 25_000)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        coordinator.hold_invoice_state(&payment_hash),
+        Some(HoldInvoiceState::Accepted {
+            amount_msat: FEE_SATS * 1000
+        })
+    );
+
+    coordinator.settle_hold_invoice(preimage).unwrap();
+
+    // Assert
+
+    app.wait_for_payment(
+        HTLCStatus::Succeeded,
+        invoice.payment_hash(),
+        Some(Duration::from_secs(30)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        coordinator.hold_invoice_state(&payment_hash),
+        Some(HoldInvoiceState::Settled)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn fee_is_refunded_if_dlc_is_not_signed() {
+    init_tracing();
+
+    // Arrange
+
+    let (app, _running_app) = Node::start_test_app("app").unwrap();
+    let (coordinator, _running_coord) = Node::start_test_coordinator("coordinator").unwrap();
+    setup_channel(&app, &coordinator).await;
+
+    let app_balance_msat = channel_balance_msat(&app);
+
+    let (_, payment_hash, invoice) = fee_invoice(&coordinator);
+
+    // Act
+
+    app.pay_invoice(&invoice, None).unwrap();
+    wait_until_fee_is_held(&coordinator, payment_hash).await;
+
+    let channel_details = app.channel_manager.list_usable_channels()[0].clone();
+    let contract_input = dummy_contract_input(50_000, 25_000, app.oracle_pk());
+    app.propose_dlc_channel(channel_details, contract_input)
+        .await
+        .unwrap();
+    wait_until_dlc_channel_state(
+        Duration::from_secs(30),
+        &coordinator,
+        app.info.pubkey,
+        SubChannelStateName::Offered,
+    )
+    .await
+    .unwrap();
+
+    // The trade fails, so the DLC offer is never accepted.
+    coordinator.cancel_hold_invoice(payment_hash).unwrap();
+
+    // Assert
+
+    app.wait_for_payment(
+        HTLCStatus::Failed,
+        invoice.payment_hash(),
+        Some(Duration::from_secs(30)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        coordinator.hold_invoice_state(&payment_hash),
+        Some(HoldInvoiceState::Cancelled)
+    );
+
+    wait_until(Duration::from_secs(10), || async {
+        Ok((channel_balance_msat(&app) == app_balance_msat).then_some(()))
+    })
+    .await
+    .unwrap();
+}
+
+async fn setup_channel(app: &Node<InMemoryStore>, coordinator: &Node<InMemoryStore>) {
+    app.connect(coordinator.info).await.unwrap();
+
+    coordinator.fund(Amount::from_sat(400_000)).await.unwrap();
+    coordinator
+        .open_private_channel(app, 50_000, 100_000)
+        .await
+        .unwrap();
+
+    wait_for_n_usable_channels(1, app).await.unwrap();
+}
+
+fn fee_invoice(coordinator: &Node<InMemoryStore>) -> (PaymentPreimage, PaymentHash, Invoice) {
+    let preimage = PaymentPreimage(rand::random());
+    let payment_hash = PaymentHash(sha256::Hash::hash(&preimage.0).into_inner());
+
+    let invoice = coordinator
+        .create_hold_invoice(
+            FEE_SATS,
+            "fee".to_string(),
+            payment_hash,
+            Duration::from_secs(600),
+        )
+        .unwrap();
+
+    (preimage, payment_hash, invoice)
+}
+
+async fn wait_until_fee_is_held(coordinator: &Node<InMemoryStore>, payment_hash: PaymentHash) {
+    wait_until(Duration::from_secs(30), || async {
+        Ok(matches!(
+            coordinator.hold_invoice_state(&payment_hash),
+            Some(HoldInvoiceState::Accepted { .. })
+        )
+        .then_some(()))
+    })
+    .await
+    .unwrap();
+}
+
+fn channel_balance_msat(node: &Node<InMemoryStore>) -> u64 {
+    node.channel_manager
+        .list_channels()
+        .iter()
+        .map(|channel| channel.balance_msat)
+        .sum()
+}
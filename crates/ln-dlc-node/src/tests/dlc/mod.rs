@@ -1,4 +1,5 @@
 mod collaborative_settlement;
 mod create;
 mod dlc_setup_with_reconnects;
+mod fee_hold_invoice;
 mod non_collaborative_settlement;
@@ -0,0 +1,75 @@
+use crate::node::Node;
+use crate::node::Storage;
+use crate::tests::init_tracing;
+use crate::tests::wait_for_n_usable_channels;
+use crate::tests::wait_until;
+use crate::watchtower::Appointment;
+use bitcoin::Amount;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn penalty_transaction_for_revoked_commitment() {
+    init_tracing();
+
+    // Arrange
+
+    let (payer, _running_payer) = Node::start_test_app("payer").unwrap();
+    let (payee, _running_payee) = Node::start_test_app("payee").unwrap();
+
+    // An unreachable watchtower, so that the revoked transactions are kept in the storage.
+    {
+        let mut settings = payer.settings.read().await.clone();
+        settings.watchtower_url = Some("http://localhost:1".to_string());
+        payer.update_settings(settings).await;
+    }
+
+    payer.connect(payee.info).await.unwrap();
+
+    payer.fund(Amount::from_btc(0.1).unwrap()).await.unwrap();
+
+    payer.open_private_channel(&payee, 30_000, 0).await.unwrap();
+
+    wait_for_n_usable_channels(1, &payer).await.unwrap();
+
+    // Act
+
+    // Every payment makes the payee revoke commitment transactions the payer has signed. After
+    // the first one the payee has an output of its own, which the second one lets us penalise.
+    for _ in 0..2 {
+        let invoice = payee.create_invoice(3_000, "".to_string(), 180).unwrap();
+        payer.pay_invoice(&invoice, None).unwrap();
+        payee
+            .wait_for_payment_claimed(invoice.payment_hash())
+            .await
+            .unwrap();
+    }
+
+    let revoked_txs = wait_until(Duration::from_secs(10), || async {
+        let revoked_txs = payer.storage.revoked_counterparty_commitments()?;
+        Ok((revoked_txs.len() >= 2).then_some(revoked_txs))
+    })
+    .await
+    .unwrap();
+
+    // Assert
+
+    let justice = payer.justice();
+    let mut penalties = 0;
+    for revoked_tx in revoked_txs {
+        let revoked_txid = revoked_tx.txid();
+        for penalty_tx in justice.penalty_transactions(&revoked_tx).unwrap() {
+            assert!(penalty_tx
+                .input
+                .iter()
+                .any(|input| input.previous_output.txid == revoked_txid));
+
+            let appointment = Appointment::new(&revoked_txid, &penalty_tx).unwrap();
+            assert_eq!(appointment.decrypt(&revoked_txid).unwrap(), penalty_tx);
+
+            penalties += 1;
+        }
+    }
+
+    assert!(penalties > 0, "No penalty for any revoked commitment");
+}
@@ -8,6 +8,7 @@ use crate::tests::init_tracing;
 use crate::tests::wait_until_dlc_channel_state;
 use crate::tests::SubChannelStateName;
 use crate::AppEventHandler;
+use crate::ChainSource;
 use crate::EventHandlerTrait;
 use anyhow::Result;
 use bitcoin::XOnlyPublicKey;
@@ -39,7 +40,9 @@ async fn single_app_many_positions_load() {
         app_event_handler,
         "app",
         app_config(),
-        ESPLORA_ORIGIN_PUBLIC_REGTEST.to_string(),
+        ChainSource::Esplora {
+            url: ESPLORA_ORIGIN_PUBLIC_REGTEST.to_string(),
+        },
         OracleInfo {
             endpoint: ORACLE_ORIGIN_PUBLIC_REGTEST.to_string(),
             public_key: XOnlyPublicKey::from_str(ORACLE_PUBKEY_PUBLIC_REGTEST).unwrap(),
@@ -170,6 +170,16 @@ impl Coordinator {
             },
         };
 
+        // The fee has to be paid before the trade is executed, the coordinator holds it until
+        // the DLC has been signed.
+        let invoice = self
+            .post("api/trade/fee_invoice", &trade_params)
+            .await?
+            .text()
+            .await?
+            .parse()?;
+        app.pay_invoice(&invoice, None)?;
+
         self.post("api/trade", &trade_params).await?;
 
         tracing::info!("Sent trade request to coordinator successfully");
@@ -11,6 +11,7 @@ use crate::scorer;
 use crate::seed::Bip39Seed;
 use crate::util;
 use crate::AppEventHandler;
+use crate::ChainSource;
 use crate::CoordinatorEventHandler;
 use crate::EventHandlerTrait;
 use crate::EventSender;
@@ -55,8 +56,11 @@ use tokio::sync::watch;
 use tokio::task::block_in_place;
 
 mod bitcoind;
+#[cfg(any(feature = "bitcoind", feature = "electrum"))]
+mod chain_source;
 mod dlc;
 mod just_in_time_channel;
+mod justice;
 mod multi_hop_payment;
 mod single_hop_payment;
 
@@ -89,8 +93,21 @@ fn init_tracing() {
     })
 }
 
+fn esplora() -> ChainSource {
+    ChainSource::Esplora {
+        url: ESPLORA_ORIGIN.to_string(),
+    }
+}
+
 impl Node<InMemoryStore> {
     fn start_test_app(name: &str) -> Result<(Arc<Self>, RunningNode)> {
+        Self::start_test_app_with_chain_source(name, esplora())
+    }
+
+    fn start_test_app_with_chain_source(
+        name: &str,
+        chain_source: ChainSource,
+    ) -> Result<(Arc<Self>, RunningNode)> {
         let app_event_handler = |node, event_sender| {
             Arc::new(AppEventHandler::new(node, event_sender)) as Arc<dyn EventHandlerTrait>
         };
@@ -99,7 +116,7 @@ impl Node<InMemoryStore> {
             app_event_handler,
             name,
             app_config(),
-            ESPLORA_ORIGIN.to_string(),
+            chain_source,
             OracleInfo {
                 endpoint: ORACLE_ORIGIN.to_string(),
                 public_key: XOnlyPublicKey::from_str(ORACLE_PUBKEY)?,
@@ -124,6 +141,22 @@ impl Node<InMemoryStore> {
         storage: Arc<InMemoryStore>,
         settings: LnDlcNodeSettings,
         ldk_event_sender: Option<watch::Sender<Option<Event>>>,
+    ) -> Result<(Arc<Self>, RunningNode)> {
+        Self::start_test_coordinator_internal_with_chain_source(
+            name,
+            esplora(),
+            storage,
+            settings,
+            ldk_event_sender,
+        )
+    }
+
+    fn start_test_coordinator_internal_with_chain_source(
+        name: &str,
+        chain_source: ChainSource,
+        storage: Arc<InMemoryStore>,
+        settings: LnDlcNodeSettings,
+        ldk_event_sender: Option<watch::Sender<Option<Event>>>,
     ) -> Result<(Arc<Self>, RunningNode)> {
         let coordinator_event_handler = |node, event_sender| {
             Arc::new(CoordinatorEventHandler::new(node, event_sender)) as Arc<dyn EventHandlerTrait>
@@ -133,7 +166,7 @@ impl Node<InMemoryStore> {
             coordinator_event_handler,
             name,
             coordinator_config(),
-            ESPLORA_ORIGIN.to_string(),
+            chain_source,
             OracleInfo {
                 endpoint: ORACLE_ORIGIN.to_string(),
                 public_key: XOnlyPublicKey::from_str(ORACLE_PUBKEY)?,
@@ -149,7 +182,7 @@ impl Node<InMemoryStore> {
         event_handler_factory: EH,
         name: &str,
         ldk_config: UserConfig,
-        esplora_origin: String,
+        chain_source: ChainSource,
         oracle: OracleInfo,
         storage: Arc<InMemoryStore>,
         settings: LnDlcNodeSettings,
@@ -180,7 +213,7 @@ impl Node<InMemoryStore> {
             address,
             address,
             util::into_net_addresses(address),
-            esplora_origin,
+            chain_source,
             seed,
             ephemeral_randomness,
             settings,
@@ -2,6 +2,7 @@ use crate::node::Node;
 use crate::tests::init_tracing;
 use crate::tests::wait_for_n_usable_channels;
 use bitcoin::Amount;
+use std::time::Duration;
 
 #[tokio::test(flavor = "multi_thread")]
 #[ignore]
@@ -62,3 +63,54 @@ async fn single_hop_payment() {
         invoice_amount
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn probe_single_hop_payment() {
+    init_tracing();
+
+    // Arrange
+
+    let (payer, _running_payer) = Node::start_test_app("payer").unwrap();
+    let (payee, _running_payee) = Node::start_test_app("payee").unwrap();
+
+    payer.connect(payee.info).await.unwrap();
+
+    payer.fund(Amount::from_btc(0.1).unwrap()).await.unwrap();
+
+    payer.open_private_channel(&payee, 30_000, 0).await.unwrap();
+
+    wait_for_n_usable_channels(1, &payer).await.unwrap();
+
+    let payer_balance_before = payer.get_ldk_balance();
+
+    // Act
+
+    let invoice = payee.create_invoice(3_000, "".to_string(), 180).unwrap();
+
+    let probe = payer
+        .probe_invoice(&invoice, None, Duration::from_secs(30))
+        .await
+        .unwrap();
+
+    // Assert
+
+    // The payee is our direct peer, so the probe reaches them without any routing fees.
+    assert_eq!(probe.succeeded, Some(true));
+    assert_eq!(probe.fee_msat, 0);
+    assert_eq!(probe.paths, 1);
+
+    // Probing does not pay the invoice.
+    payer.sync_on_chain().await.unwrap();
+    assert_eq!(
+        payer.get_ldk_balance().available(),
+        payer_balance_before.available()
+    );
+
+    // We can't probe more than we can send.
+    let invoice = payee.create_invoice(50_000, "".to_string(), 180).unwrap();
+    assert!(payer
+        .probe_invoice(&invoice, None, Duration::from_secs(30))
+        .await
+        .is_err());
+}
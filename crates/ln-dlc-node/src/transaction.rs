@@ -13,6 +13,12 @@ pub struct Transaction {
     created_at: OffsetDateTime,
     updated_at: OffsetDateTime,
     raw: String,
+    /// Whether the fee of the transaction may be bumped by replacing it (RBF).
+    ///
+    /// Only our own payments may be replaced. Replacing a channel transaction, e.g. a funding
+    /// transaction, changes its txid and the channel could never confirm, hence their fee can
+    /// only be bumped with CPFP.
+    replaceable: bool,
 }
 
 impl Transaction {
@@ -22,6 +28,7 @@ impl Transaction {
         created_at: OffsetDateTime,
         updated_at: OffsetDateTime,
         raw: String,
+        replaceable: bool,
     ) -> Self {
         Self {
             txid,
@@ -29,6 +36,7 @@ impl Transaction {
             created_at,
             updated_at,
             raw,
+            replaceable,
         }
     }
 
@@ -59,13 +67,25 @@ impl Transaction {
     pub fn raw(&self) -> String {
         self.raw.clone()
     }
+
+    pub fn is_replaceable(&self) -> bool {
+        self.replaceable
+    }
+
+    /// Marks the transaction as one of our payments, whose fee may be bumped with RBF.
+    pub fn replaceable(self) -> Self {
+        Self {
+            replaceable: true,
+            ..self
+        }
+    }
 }
 
 impl From<&bitcoin::Transaction> for Transaction {
     fn from(value: &bitcoin::Transaction) -> Self {
         let now = OffsetDateTime::now_utc();
 
-        Self::new(value.txid(), 0, now, now, value.serialize().to_hex())
+        Self::new(value.txid(), 0, now, now, value.serialize().to_hex(), false)
     }
 }
 
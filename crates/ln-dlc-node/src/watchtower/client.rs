@@ -0,0 +1,65 @@
+use crate::watchtower::Appointment;
+use crate::watchtower::SignedAppointment;
+use crate::watchtower::APPOINTMENTS_PATH;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::secp256k1::SecretKey;
+use reqwest::StatusCode;
+
+/// Hands [`Appointment`]s to a watchtower over its HTTP API, signed with the node key.
+#[derive(Clone)]
+pub struct WatchtowerClient {
+    client: reqwest::Client,
+    url: String,
+    node_secret_key: SecretKey,
+}
+
+/// The watchtower refused to store an appointment for a reason which handing it over again does
+/// not fix, e.g. because we have exceeded our quota or are not admitted at all.
+#[derive(Debug, thiserror::Error)]
+#[error("Watchtower refused appointment with {status}: {reason}")]
+pub struct AppointmentRefused {
+    pub status: StatusCode,
+    pub reason: String,
+}
+
+impl WatchtowerClient {
+    pub fn new(url: String, node_secret_key: SecretKey) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            node_secret_key,
+        }
+    }
+
+    /// Hands an appointment to the watchtower.
+    ///
+    /// Fails with an [`AppointmentRefused`] error if retrying is pointless.
+    pub async fn add_appointment(&self, appointment: Appointment) -> Result<()> {
+        let response = self
+            .client
+            .post(format!("{}{APPOINTMENTS_PATH}", self.url))
+            .json(&SignedAppointment::new(appointment, &self.node_secret_key))
+            .send()
+            .await
+            .context("Failed to send appointment to watchtower")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+
+            if matches!(status, StatusCode::BAD_REQUEST | StatusCode::FORBIDDEN) {
+                return Err(AppointmentRefused {
+                    status,
+                    reason: text,
+                }
+                .into());
+            }
+
+            bail!("Watchtower rejected appointment with {status}: {text}");
+        }
+
+        Ok(())
+    }
+}
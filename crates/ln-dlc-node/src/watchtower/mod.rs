@@ -0,0 +1,265 @@
+//! A watchtower which broadcasts penalty transactions on behalf of nodes that are offline when
+//! their counterparty publishes a revoked transaction.
+//!
+//! Nodes hand the tower an [`Appointment`] for every revoked transaction. An appointment only
+//! contains the first half of the revoked transaction's ID as a [`Locator`] and the penalty
+//! transaction encrypted with the full ID. The tower thus learns nothing about a channel unless
+//! the revoked transaction is actually published, in which case it can decrypt and broadcast the
+//! penalty.
+//!
+//! Appointments are signed with the node key of the client handing them to the tower, which lets
+//! the tower only accept appointments from the nodes it serves and limit how many appointments
+//! every client may store.
+
+use anyhow::anyhow;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::consensus::deserialize;
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::Message;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use rand::Rng;
+use ring::aead::Aad;
+use ring::aead::LessSafeKey;
+use ring::aead::Nonce;
+use ring::aead::UnboundKey;
+use ring::aead::CHACHA20_POLY1305;
+use ring::aead::NONCE_LEN;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::fmt;
+use std::str::FromStr;
+
+mod client;
+mod server;
+
+pub use client::AppointmentRefused;
+pub use client::WatchtowerClient;
+pub use server::AddAppointmentError;
+pub use server::Admission;
+pub use server::Watchtower;
+
+/// The path of the watchtower's HTTP API to which appointments are posted.
+pub const APPOINTMENTS_PATH: &str = "/api/watchtower/appointments";
+
+/// Identifies the revoked transaction an [`Appointment`] is for, without revealing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Locator([u8; 16]);
+
+/// A penalty transaction to broadcast if a revoked transaction is published.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Appointment {
+    pub locator: Locator,
+    /// The penalty transaction, encrypted with the ID of the revoked transaction.
+    #[serde(with = "hex_blob")]
+    pub encrypted_blob: Vec<u8>,
+}
+
+/// An [`Appointment`] signed by the node handing it to the watchtower.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedAppointment {
+    pub appointment: Appointment,
+    pub node_id: PublicKey,
+    pub signature: Signature,
+}
+
+impl Locator {
+    pub fn new(revoked_txid: &Txid) -> Self {
+        let mut locator = [0; 16];
+        locator.copy_from_slice(&revoked_txid[..16]);
+
+        Self(locator)
+    }
+}
+
+impl Appointment {
+    /// Creates an appointment to broadcast `penalty_tx` once the transaction `revoked_txid` is
+    /// published.
+    pub fn new(revoked_txid: &Txid, penalty_tx: &Transaction) -> Result<Self> {
+        let nonce = rand::thread_rng().gen::<[u8; NONCE_LEN]>();
+
+        let mut ciphertext = serialize(penalty_tx);
+        key(revoked_txid)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut ciphertext,
+            )
+            .map_err(|_| anyhow!("Failed to encrypt penalty transaction"))?;
+
+        let mut encrypted_blob = nonce.to_vec();
+        encrypted_blob.extend(ciphertext);
+
+        Ok(Self {
+            locator: Locator::new(revoked_txid),
+            encrypted_blob,
+        })
+    }
+
+    /// Decrypts the penalty transaction with the ID of the revoked transaction it is for.
+    pub fn decrypt(&self, revoked_txid: &Txid) -> Result<Transaction> {
+        ensure!(
+            self.locator == Locator::new(revoked_txid),
+            "Appointment is not for transaction {revoked_txid}"
+        );
+        ensure!(
+            self.encrypted_blob.len() > NONCE_LEN,
+            "Encrypted blob is too short"
+        );
+
+        let (nonce, ciphertext) = self.encrypted_blob.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("Invalid nonce in encrypted blob"))?;
+
+        let mut ciphertext = ciphertext.to_vec();
+        let penalty_tx = key(revoked_txid)?
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt penalty transaction"))?;
+
+        deserialize(penalty_tx).context("Failed to deserialize penalty transaction")
+    }
+}
+
+impl SignedAppointment {
+    pub fn new(appointment: Appointment, node_secret_key: &SecretKey) -> Self {
+        let secp = Secp256k1::signing_only();
+        let signature = secp.sign_ecdsa(&appointment.message(), node_secret_key);
+
+        Self {
+            node_id: PublicKey::from_secret_key(&secp, node_secret_key),
+            appointment,
+            signature,
+        }
+    }
+
+    /// Whether the appointment was signed by the node `node_id`.
+    pub fn verify(&self) -> Result<()> {
+        Secp256k1::verification_only()
+            .verify_ecdsa(&self.appointment.message(), &self.signature, &self.node_id)
+            .context("Invalid appointment signature")
+    }
+}
+
+impl Appointment {
+    /// The message a node signs to hand the appointment to the watchtower.
+    fn message(&self) -> Message {
+        let mut data = self.locator.0.to_vec();
+        data.extend(&self.encrypted_blob);
+        let hash = sha256::Hash::hash(&data);
+
+        Message::from_slice(&hash[..]).expect("A SHA256 hash to always be a valid message")
+    }
+}
+
+/// The key to encrypt the penalty transaction for `revoked_txid` with.
+fn key(revoked_txid: &Txid) -> Result<LessSafeKey> {
+    let key = sha256::Hash::hash(&revoked_txid[..]);
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key[..])
+        .map_err(|_| anyhow!("Failed to create encryption key"))?;
+
+    Ok(LessSafeKey::new(key))
+}
+
+impl fmt::Display for Locator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.to_hex().fmt(f)
+    }
+}
+
+impl FromStr for Locator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let locator = <[u8; 16]>::from_hex(s).context("Invalid locator")?;
+        Ok(Self(locator))
+    }
+}
+
+impl Serialize for Locator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Locator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let locator = String::deserialize(deserializer)?;
+        locator.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+mod hex_blob {
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::hashes::hex::ToHex;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(blob: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&blob.to_hex())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let blob = String::deserialize(deserializer)?;
+        Vec::from_hex(&blob).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::PackedLockTime;
+    use bitcoin::TxIn;
+
+    fn transaction(lock_time: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime(lock_time),
+            input: vec![TxIn::default()],
+            output: vec![],
+        }
+    }
+
+    #[test]
+    fn penalty_transaction_can_only_be_decrypted_with_revoked_txid() {
+        let revoked_txid = transaction(1).txid();
+        let penalty_tx = transaction(2);
+
+        let appointment = Appointment::new(&revoked_txid, &penalty_tx).unwrap();
+
+        assert_eq!(appointment.locator, Locator::new(&revoked_txid));
+        assert_eq!(appointment.decrypt(&revoked_txid).unwrap(), penalty_tx);
+        assert!(appointment.decrypt(&transaction(3).txid()).is_err());
+    }
+
+    #[test]
+    fn signed_appointment_cannot_be_altered() {
+        let node_secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let appointment = Appointment::new(&transaction(1).txid(), &transaction(2)).unwrap();
+
+        let signed = SignedAppointment::new(appointment.clone(), &node_secret_key);
+        assert!(signed.verify().is_ok());
+
+        let mut altered = signed.clone();
+        altered.appointment.encrypted_blob.push(0);
+        assert!(altered.verify().is_err());
+
+        let other_secret_key = SecretKey::from_slice(&[2; 32]).unwrap();
+        let mut impersonated = signed;
+        impersonated.node_id =
+            PublicKey::from_secret_key(&Secp256k1::signing_only(), &other_secret_key);
+        assert!(impersonated.verify().is_err());
+    }
+}
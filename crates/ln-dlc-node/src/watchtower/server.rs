@@ -0,0 +1,1026 @@
+use crate::watchtower::Appointment;
+use crate::watchtower::Locator;
+use crate::watchtower::SignedAppointment;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::task::spawn_blocking;
+
+/// How often the watchtower checks for new blocks.
+const WATCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The largest encrypted blob we accept, which is enough for a penalty transaction claiming the
+/// outputs of a commitment transaction with a dozen HTLCs.
+const MAX_ENCRYPTED_BLOB_SIZE: usize = 4_096;
+
+/// How many of the most recent blocks we scan again on every pass, so that we notice revoked
+/// transactions which were only confirmed after a reorg.
+const REORG_DEPTH: u32 = 6;
+
+/// How many confirmations a penalty transaction needs before we forget its appointment.
+const PENALTY_CONFIRMATIONS: u32 = 6;
+
+/// How many confirmations a revoked transaction may have before we give up on its penalty
+/// transaction, e.g. because the outputs have been spent otherwise. This is the largest
+/// `to_self_delay` LDK accepts.
+const MAX_REVOKED_TRANSACTION_CONFIRMATIONS: u32 = 2016;
+
+/// Why the watchtower did not accept an appointment.
+#[derive(Debug, thiserror::Error)]
+pub enum AddAppointmentError {
+    #[error("Invalid appointment: {0:#}")]
+    Invalid(anyhow::Error),
+    #[error("Appointment is not signed by the node it claims to be from")]
+    Unauthorized,
+    #[error("Watchtower does not store appointments for node {0}")]
+    NotAdmitted(PublicKey),
+    #[error("Too many appointments in a short time, try again later")]
+    RateLimited,
+    #[error("Too many appointments stored for node {0}")]
+    QuotaExceeded(PublicKey),
+    #[error("Watchtower does not accept any more appointments")]
+    Full,
+    #[error("Failed to store appointment: {0:#}")]
+    Internal(anyhow::Error),
+}
+
+/// Limits protecting the watchtower from clients handing it too many appointments.
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    /// How many appointments a single client may store.
+    per_client: usize,
+    /// How many appointments a single client may hand us within `window`.
+    per_window: usize,
+    window: time::Duration,
+    /// How many appointments we store for all clients together. Only a small index of every
+    /// appointment is kept in memory, the encrypted blobs are kept on disk.
+    total: usize,
+    /// How long we keep an appointment whose revoked transaction is never published.
+    expiry: time::Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            per_client: 10_000,
+            per_window: 100,
+            window: time::Duration::minutes(1),
+            total: 1_000_000,
+            expiry: time::Duration::days(365),
+        }
+    }
+}
+
+/// Watches the chain for revoked transactions and broadcasts the penalty transactions of the
+/// corresponding [`Appointment`]s.
+///
+/// Only the appointments of nodes passing the [`Admission`] check are accepted, e.g. those of
+/// nodes with a channel to the coordinator, so that nobody can get around the per-client limits
+/// by handing us appointments under fresh node keys.
+///
+/// Every appointment is stored in a file of its own in the data directory, so that they survive
+/// restarts. Only an index of the appointments is kept in memory, the encrypted blobs are read
+/// from disk once their revoked transaction is published. The watchtower also remembers up to
+/// which block it has scanned the chain, so that it does not miss any revoked transaction
+/// published while it was down.
+///
+/// Once a revoked transaction is found, its penalty transactions are persisted as well and
+/// broadcast until they are buried deep enough that a reorg is unlikely to undo them. Only then
+/// is the appointment forgotten. If the revoked transaction disappears from the chain instead,
+/// the penalty transactions are dropped and the appointment is kept.
+///
+/// Methods touching the disk are blocking and must not be called on the async runtime directly.
+pub struct Watchtower {
+    appointments: Mutex<Appointments>,
+    /// When every client handed us appointments within the current rate limit window.
+    requests: Mutex<HashMap<PublicKey, VecDeque<OffsetDateTime>>>,
+    /// Penalty transactions of revoked transactions we have found, by penalty transaction ID.
+    penalties: Mutex<HashMap<Txid, Penalty>>,
+    limits: Limits,
+    admission: Admission,
+    appointments_dir: PathBuf,
+    penalties_file: PathBuf,
+    height_file: PathBuf,
+    esplora: Esplora,
+}
+
+/// Decides whether the watchtower accepts the appointments of a node.
+pub type Admission = Box<dyn Fn(&PublicKey) -> bool + Send + Sync>;
+
+/// An appointment as stored by the watchtower.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredAppointment {
+    #[serde(flatten)]
+    appointment: Appointment,
+    node_id: PublicKey,
+    /// When we accepted the appointment, as a UNIX timestamp.
+    created_at: i64,
+}
+
+/// What we keep in memory of a [`StoredAppointment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IndexedAppointment {
+    locator: Locator,
+    node_id: PublicKey,
+    /// Identifies the encrypted blob, which is only kept on disk.
+    blob_hash: sha256::Hash,
+    created_at: i64,
+}
+
+#[derive(Default)]
+struct Appointments {
+    by_locator: HashMap<Locator, Vec<IndexedAppointment>>,
+    /// How many appointments every client has stored.
+    per_client: HashMap<PublicKey, usize>,
+    total: usize,
+}
+
+/// A penalty transaction for a revoked transaction which has been published.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Penalty {
+    revoked_txid: Txid,
+    penalty_tx: Transaction,
+}
+
+struct Esplora {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+}
+
+impl Watchtower {
+    pub fn new(data_dir: &Path, esplora_url: String, admission: Admission) -> Result<Self> {
+        let data_dir = data_dir.join("watchtower");
+
+        let appointments_dir = data_dir.join("appointments");
+        fs::create_dir_all(&appointments_dir)
+            .context("Failed to create watchtower data directory")?;
+        let appointments = read_appointments(&appointments_dir)?;
+
+        let penalties_file = data_dir.join("penalties");
+        let penalties = read_penalties(&penalties_file)?;
+
+        tracing::info!(
+            appointments = appointments.total,
+            penalties = penalties.len(),
+            "Loaded watchtower appointments"
+        );
+
+        Ok(Self {
+            appointments: Mutex::new(appointments),
+            requests: Mutex::new(HashMap::new()),
+            penalties: Mutex::new(penalties),
+            limits: Limits::default(),
+            admission,
+            appointments_dir,
+            penalties_file,
+            height_file: data_dir.join("height"),
+            esplora: Esplora {
+                client: reqwest::Client::new(),
+                url: esplora_url,
+            },
+        })
+    }
+
+    pub fn add_appointment(
+        &self,
+        signed: SignedAppointment,
+        now: OffsetDateTime,
+    ) -> Result<(), AddAppointmentError> {
+        let SignedAppointment {
+            appointment,
+            node_id,
+            ..
+        } = &signed;
+
+        if appointment.encrypted_blob.len() > MAX_ENCRYPTED_BLOB_SIZE {
+            return Err(AddAppointmentError::Invalid(anyhow!(
+                "Encrypted blob is too large"
+            )));
+        }
+
+        signed
+            .verify()
+            .map_err(|_| AddAppointmentError::Unauthorized)?;
+
+        if !(self.admission)(node_id) {
+            return Err(AddAppointmentError::NotAdmitted(*node_id));
+        }
+
+        self.check_rate_limit(*node_id, now)?;
+
+        let appointment = StoredAppointment {
+            appointment: appointment.clone(),
+            node_id: *node_id,
+            created_at: now.unix_timestamp(),
+        };
+        let indexed = IndexedAppointment::from(&appointment);
+
+        let mut appointments = self.appointments.lock();
+        if appointments.contains(&indexed) {
+            return Ok(());
+        }
+
+        if appointments.of(node_id) >= self.limits.per_client {
+            return Err(AddAppointmentError::QuotaExceeded(*node_id));
+        }
+        if appointments.total >= self.limits.total {
+            return Err(AddAppointmentError::Full);
+        }
+
+        write_appointment(&indexed.path(&self.appointments_dir), &appointment)
+            .map_err(AddAppointmentError::Internal)?;
+
+        tracing::debug!(locator = %indexed.locator, %node_id, "Added appointment");
+
+        appointments.insert(indexed);
+
+        Ok(())
+    }
+
+    fn check_rate_limit(
+        &self,
+        node_id: PublicKey,
+        now: OffsetDateTime,
+    ) -> Result<(), AddAppointmentError> {
+        let mut requests = self.requests.lock();
+        let requests = requests.entry(node_id).or_default();
+
+        while requests
+            .front()
+            .map_or(false, |request| *request <= now - self.limits.window)
+        {
+            requests.pop_front();
+        }
+
+        if requests.len() >= self.limits.per_window {
+            return Err(AddAppointmentError::RateLimited);
+        }
+
+        requests.push_back(now);
+
+        Ok(())
+    }
+
+    /// Scans every new block for revoked transactions and broadcasts the corresponding penalty
+    /// transactions, forever.
+    pub async fn watch(self: Arc<Self>) {
+        loop {
+            if let Err(e) = self.scan_new_blocks().await {
+                tracing::error!("Failed to scan blocks for revoked transactions: {e:#}");
+            }
+
+            if let Err(e) = self.settle_penalties().await {
+                tracing::error!("Failed to settle penalty transactions: {e:#}");
+            }
+
+            let expired = spawn_blocking({
+                let watchtower = self.clone();
+                move || watchtower.expire_appointments(OffsetDateTime::now_utc())
+            })
+            .await
+            .expect("task to complete");
+            if let Err(e) = expired {
+                tracing::error!("Failed to expire appointments: {e:#}");
+            }
+
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+
+    async fn scan_new_blocks(self: &Arc<Self>) -> Result<()> {
+        let tip = self.esplora.tip_height().await?;
+
+        let mut height = match self.scanned_height()? {
+            Some(height) => (height + 1).saturating_sub(REORG_DEPTH),
+            // Appointments can only be for transactions which have not been published yet.
+            None => tip,
+        };
+
+        while height <= tip {
+            let txids = self.esplora.block_txids(height).await?;
+
+            let penalties = spawn_blocking({
+                let watchtower = self.clone();
+                move || watchtower.penalties_for(&txids)
+            })
+            .await
+            .expect("task to complete");
+            if !penalties.is_empty() {
+                tracing::warn!(
+                    %height,
+                    penalties = penalties.len(),
+                    "Found revoked transactions"
+                );
+            }
+            self.track_penalties(penalties)?;
+
+            fs::write(&self.height_file, height.to_string())
+                .context("Failed to persist scanned height")?;
+            height += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Broadcasts the penalty transactions we are tracking and forgets those which are either
+    /// buried deep enough or whose revoked transaction is no longer part of the chain.
+    async fn settle_penalties(self: &Arc<Self>) -> Result<()> {
+        let tip = self.esplora.tip_height().await?;
+        let penalties = self.penalties.lock().clone();
+
+        for (txid, penalty) in penalties {
+            let revoked_txid = penalty.revoked_txid;
+
+            match self.esplora.tx_status(&txid).await? {
+                Some(TxStatus {
+                    confirmed: true,
+                    block_height: Some(height),
+                }) if confirmations(tip, height) >= PENALTY_CONFIRMATIONS => {
+                    tracing::info!(%txid, %revoked_txid, "Penalty transaction confirmed");
+                    spawn_blocking({
+                        let watchtower = self.clone();
+                        move || watchtower.penalty_confirmed(&penalty)
+                    })
+                    .await
+                    .expect("task to complete")?;
+                    continue;
+                }
+                // Already in the mempool or not buried deep enough yet.
+                Some(_) => continue,
+                None => {}
+            }
+
+            match self.esplora.tx_status(&revoked_txid).await? {
+                None => {
+                    tracing::warn!(
+                        %txid,
+                        %revoked_txid,
+                        "Revoked transaction is no longer part of the chain, dropping penalty"
+                    );
+                    self.forget_penalty(&txid)?;
+                    continue;
+                }
+                Some(TxStatus {
+                    confirmed: true,
+                    block_height: Some(height),
+                }) if confirmations(tip, height) > MAX_REVOKED_TRANSACTION_CONFIRMATIONS => {
+                    tracing::warn!(
+                        %txid,
+                        %revoked_txid,
+                        "Giving up on penalty transaction for old revoked transaction"
+                    );
+                    self.forget_penalty(&txid)?;
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            match self.esplora.broadcast(&penalty.penalty_tx).await {
+                Ok(()) => tracing::info!(%txid, "Broadcast penalty transaction"),
+                Err(e) => {
+                    tracing::error!(%txid, "Failed to broadcast penalty transaction: {e:#}")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The penalty transactions for the revoked transactions among `txids`.
+    fn penalties_for(&self, txids: &[Txid]) -> Vec<Penalty> {
+        let candidates = {
+            let appointments = self.appointments.lock();
+            txids
+                .iter()
+                .flat_map(|txid| {
+                    appointments
+                        .for_locator(&Locator::new(txid))
+                        .iter()
+                        .map(move |indexed| (*txid, *indexed))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut penalties = vec![];
+        for (txid, indexed) in candidates {
+            match self.decrypt(&indexed, &txid) {
+                Ok(penalty_tx) => penalties.push(Penalty {
+                    revoked_txid: txid,
+                    penalty_tx,
+                }),
+                // The locator may match by chance, or the client sent a bogus appointment.
+                Err(e) => tracing::warn!(
+                    %txid,
+                    node_id = %indexed.node_id,
+                    "Failed to decrypt appointment: {e:#}"
+                ),
+            }
+        }
+
+        penalties
+    }
+
+    /// Reads the encrypted blob of an appointment from disk and decrypts its penalty transaction.
+    fn decrypt(&self, indexed: &IndexedAppointment, revoked_txid: &Txid) -> Result<Transaction> {
+        let stored = read_appointment(&indexed.path(&self.appointments_dir))?;
+        stored.appointment.decrypt(revoked_txid)
+    }
+
+    /// Remembers the penalty transactions we are not tracking yet.
+    fn track_penalties(&self, new_penalties: Vec<Penalty>) -> Result<()> {
+        let mut penalties = self.penalties.lock();
+
+        let mut changed = false;
+        for penalty in new_penalties {
+            let txid = penalty.penalty_tx.txid();
+            if !penalties.contains_key(&txid) {
+                penalties.insert(txid, penalty);
+                changed = true;
+            }
+        }
+
+        if changed {
+            write_penalties(&self.penalties_file, &penalties)
+                .context("Failed to persist penalty transactions")?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets a penalty transaction and the appointment it came from.
+    fn penalty_confirmed(&self, penalty: &Penalty) -> Result<()> {
+        let candidates = self
+            .appointments
+            .lock()
+            .for_locator(&Locator::new(&penalty.revoked_txid))
+            .to_vec();
+        let settled = candidates
+            .into_iter()
+            .filter(|indexed| {
+                self.decrypt(indexed, &penalty.revoked_txid).ok().as_ref()
+                    == Some(&penalty.penalty_tx)
+            })
+            .collect::<Vec<_>>();
+
+        let removed = self
+            .appointments
+            .lock()
+            .retain(|indexed| !settled.contains(indexed));
+        remove_appointments(&self.appointments_dir, &removed)
+            .context("Failed to remove settled appointment")?;
+
+        self.forget_penalty(&penalty.penalty_tx.txid())
+    }
+
+    fn forget_penalty(&self, txid: &Txid) -> Result<()> {
+        let mut penalties = self.penalties.lock();
+        if penalties.remove(txid).is_some() {
+            write_penalties(&self.penalties_file, &penalties)
+                .context("Failed to persist penalty transactions")?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets appointments which have expired, unless we are still broadcasting their penalty
+    /// transactions.
+    fn expire_appointments(&self, now: OffsetDateTime) -> Result<()> {
+        let revoked_txids = self
+            .penalties
+            .lock()
+            .values()
+            .map(|penalty| penalty.revoked_txid)
+            .collect::<Vec<_>>();
+
+        let expired_before = (now - self.limits.expiry).unix_timestamp();
+
+        let removed = self.appointments.lock().retain(|indexed| {
+            indexed.created_at > expired_before
+                || revoked_txids
+                    .iter()
+                    .any(|txid| indexed.locator == Locator::new(txid))
+        });
+        remove_appointments(&self.appointments_dir, &removed)
+            .context("Failed to remove expired appointments")?;
+
+        self.requests.lock().retain(|_, requests| {
+            requests
+                .back()
+                .map_or(false, |request| *request > now - self.limits.window)
+        });
+
+        Ok(())
+    }
+
+    fn scanned_height(&self) -> Result<Option<u32>> {
+        if !self.height_file.exists() {
+            return Ok(None);
+        }
+
+        let height = fs::read_to_string(&self.height_file)?;
+        let height = height.trim().parse().context("Invalid scanned height")?;
+
+        Ok(Some(height))
+    }
+}
+
+impl From<&StoredAppointment> for IndexedAppointment {
+    fn from(stored: &StoredAppointment) -> Self {
+        Self {
+            locator: stored.appointment.locator,
+            node_id: stored.node_id,
+            blob_hash: sha256::Hash::hash(&stored.appointment.encrypted_blob),
+            created_at: stored.created_at,
+        }
+    }
+}
+
+impl IndexedAppointment {
+    /// The file the appointment is stored in.
+    fn path(&self, appointments_dir: &Path) -> PathBuf {
+        appointments_dir.join(format!(
+            "{}-{}-{}",
+            self.locator, self.node_id, self.blob_hash
+        ))
+    }
+}
+
+impl Appointments {
+    fn insert(&mut self, appointment: IndexedAppointment) {
+        *self.per_client.entry(appointment.node_id).or_default() += 1;
+        self.total += 1;
+
+        self.by_locator
+            .entry(appointment.locator)
+            .or_default()
+            .push(appointment);
+    }
+
+    /// Whether the same appointment has already been stored by the same client, at any time.
+    fn contains(&self, appointment: &IndexedAppointment) -> bool {
+        self.for_locator(&appointment.locator)
+            .iter()
+            .any(|indexed| {
+                indexed.node_id == appointment.node_id && indexed.blob_hash == appointment.blob_hash
+            })
+    }
+
+    fn for_locator(&self, locator: &Locator) -> &[IndexedAppointment] {
+        self.by_locator
+            .get(locator)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// How many appointments the client `node_id` has stored.
+    fn of(&self, node_id: &PublicKey) -> usize {
+        self.per_client.get(node_id).copied().unwrap_or_default()
+    }
+
+    /// Keeps only the appointments for which `keep` returns true, returning those which were
+    /// removed.
+    fn retain(
+        &mut self,
+        mut keep: impl FnMut(&IndexedAppointment) -> bool,
+    ) -> Vec<IndexedAppointment> {
+        let mut removed = vec![];
+        for appointments in self.by_locator.values_mut() {
+            appointments.retain(|indexed| {
+                if keep(indexed) {
+                    return true;
+                }
+
+                if let Some(count) = self.per_client.get_mut(&indexed.node_id) {
+                    *count -= 1;
+                }
+                self.total -= 1;
+                removed.push(*indexed);
+
+                false
+            });
+        }
+
+        self.by_locator
+            .retain(|_, appointments| !appointments.is_empty());
+        self.per_client.retain(|_, count| *count > 0);
+
+        removed
+    }
+}
+
+/// How many confirmations a transaction confirmed at `height` has.
+fn confirmations(tip: u32, height: u32) -> u32 {
+    (tip + 1).saturating_sub(height)
+}
+
+impl Esplora {
+    async fn tip_height(&self) -> Result<u32> {
+        let height = self.get(&format!("{}/blocks/tip/height", self.url)).await?;
+        height.parse().context("Invalid tip height")
+    }
+
+    async fn block_txids(&self, height: u32) -> Result<Vec<Txid>> {
+        let hash = self
+            .get(&format!("{}/block-height/{height}", self.url))
+            .await?;
+
+        let txids = self
+            .client
+            .get(format!("{}/block/{hash}/txids", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<String>>()
+            .await?;
+
+        txids
+            .iter()
+            .map(|txid| txid.parse().context("Invalid txid"))
+            .collect()
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<()> {
+        let response = self
+            .client
+            .post(format!("{}/tx", self.url))
+            .body(serialize_hex(tx))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            bail!("Esplora rejected transaction: {text}");
+        }
+
+        Ok(())
+    }
+
+    /// The status of the transaction `txid`, or `None` if it is neither in the mempool nor in
+    /// the chain.
+    async fn tx_status(&self, txid: &Txid) -> Result<Option<TxStatus>> {
+        let response = self
+            .client
+            .get(format!("{}/tx/{txid}/status", self.url))
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let status = response.error_for_status()?.json().await?;
+
+        Ok(Some(status))
+    }
+
+    async fn get(&self, url: &str) -> Result<String> {
+        let text = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(text.trim().to_string())
+    }
+}
+
+/// Builds the index of the appointments stored in `dir`.
+fn read_appointments(dir: &Path) -> Result<Appointments> {
+    let mut appointments = Appointments::default();
+
+    for entry in fs::read_dir(dir).context("Failed to list appointments")? {
+        let path = entry?.path();
+
+        // Left behind if we stopped while writing an appointment.
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "tmp")
+        {
+            fs::remove_file(&path)?;
+            continue;
+        }
+
+        let appointment = read_appointment(&path)?;
+        appointments.insert(IndexedAppointment::from(&appointment));
+    }
+
+    Ok(appointments)
+}
+
+fn read_appointment(path: &Path) -> Result<StoredAppointment> {
+    let appointment =
+        fs::read(path).with_context(|| format!("Failed to read appointment {}", path.display()))?;
+    serde_json::from_slice(&appointment).context("Invalid appointment")
+}
+
+fn write_appointment(path: &Path, appointment: &StoredAppointment) -> Result<()> {
+    // Write to a temporary file first, so that we never end up with half an appointment.
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_vec(appointment)?).context("Failed to persist appointment")?;
+    fs::rename(tmp, path).context("Failed to persist appointment")?;
+
+    Ok(())
+}
+
+fn remove_appointments(dir: &Path, appointments: &[IndexedAppointment]) -> Result<()> {
+    for appointment in appointments {
+        fs::remove_file(appointment.path(dir))?;
+    }
+
+    Ok(())
+}
+
+fn read_penalties(path: &Path) -> Result<HashMap<Txid, Penalty>> {
+    let mut penalties = HashMap::new();
+    if !path.exists() {
+        return Ok(penalties);
+    }
+
+    for line in fs::read_to_string(path)?.lines() {
+        let penalty = serde_json::from_str::<Penalty>(line).context("Invalid penalty")?;
+        penalties.insert(penalty.penalty_tx.txid(), penalty);
+    }
+
+    Ok(penalties)
+}
+
+fn write_penalties(path: &Path, penalties: &HashMap<Txid, Penalty>) -> Result<()> {
+    write_lines(path, penalties.values())
+}
+
+fn write_lines<'a, T: Serialize + 'a>(
+    path: &Path,
+    items: impl Iterator<Item = &'a T>,
+) -> Result<()> {
+    let mut lines = String::new();
+    for item in items {
+        lines.push_str(&serde_json::to_string(item)?);
+        lines.push('\n');
+    }
+
+    // Write to a temporary file first, so that we never end up with half of the items.
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, lines)?;
+    fs::rename(tmp, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::PackedLockTime;
+    use bitcoin::TxIn;
+
+    fn transaction(lock_time: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime(lock_time),
+            input: vec![TxIn::default()],
+            output: vec![],
+        }
+    }
+
+    fn signed_appointment(revoked_tx: u32, penalty_tx: u32, node: u8) -> SignedAppointment {
+        let appointment =
+            Appointment::new(&transaction(revoked_tx).txid(), &transaction(penalty_tx)).unwrap();
+
+        SignedAppointment::new(appointment, &SecretKey::from_slice(&[node; 32]).unwrap())
+    }
+
+    fn temp_data_dir() -> PathBuf {
+        std::env::temp_dir().join(uuid::Uuid::new_v4().to_string())
+    }
+
+    fn open_watchtower(data_dir: &Path) -> Watchtower {
+        Watchtower::new(data_dir, String::new(), Box::new(|_| true)).unwrap()
+    }
+
+    #[test]
+    fn appointments_are_kept_until_penalty_confirms() {
+        let data_dir = temp_data_dir();
+        let revoked_txid = transaction(1).txid();
+        let penalty_tx = transaction(2);
+        let now = OffsetDateTime::now_utc();
+
+        let watchtower = open_watchtower(&data_dir);
+        watchtower
+            .add_appointment(signed_appointment(1, 2, 1), now)
+            .unwrap();
+
+        // Appointments survive a restart.
+        let watchtower = open_watchtower(&data_dir);
+
+        assert!(watchtower
+            .penalties_for(&[transaction(3).txid()])
+            .is_empty());
+
+        let penalties = watchtower.penalties_for(&[transaction(3).txid(), revoked_txid]);
+        let penalty = Penalty {
+            revoked_txid,
+            penalty_tx: penalty_tx.clone(),
+        };
+        assert_eq!(penalties, vec![penalty.clone()]);
+
+        // Scanning the block again, e.g. after a reorg, finds the same penalty.
+        watchtower.track_penalties(penalties).unwrap();
+        watchtower
+            .track_penalties(watchtower.penalties_for(&[revoked_txid]))
+            .unwrap();
+
+        // Found penalties survive a restart.
+        let watchtower = open_watchtower(&data_dir);
+        assert_eq!(
+            watchtower.penalties.lock().values().collect::<Vec<_>>(),
+            vec![&penalty]
+        );
+
+        // Until the penalty is confirmed, its appointment is not forgotten.
+        watchtower.forget_penalty(&penalty_tx.txid()).unwrap();
+        assert_eq!(
+            watchtower.penalties_for(&[revoked_txid]),
+            vec![penalty.clone()]
+        );
+
+        watchtower.track_penalties(vec![penalty.clone()]).unwrap();
+        watchtower.penalty_confirmed(&penalty).unwrap();
+
+        let watchtower = open_watchtower(&data_dir);
+        assert!(watchtower.penalties.lock().is_empty());
+        assert!(watchtower.penalties_for(&[revoked_txid]).is_empty());
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn appointments_must_be_signed_by_their_node() {
+        let data_dir = temp_data_dir();
+        let watchtower = open_watchtower(&data_dir);
+
+        let mut signed = signed_appointment(1, 2, 1);
+        signed.node_id = signed_appointment(1, 2, 2).node_id;
+
+        assert!(matches!(
+            watchtower.add_appointment(signed, OffsetDateTime::now_utc()),
+            Err(AddAppointmentError::Unauthorized)
+        ));
+        assert!(watchtower
+            .penalties_for(&[transaction(1).txid()])
+            .is_empty());
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn only_appointments_of_admitted_nodes_are_accepted() {
+        let data_dir = temp_data_dir();
+        let admitted = signed_appointment(1, 2, 1).node_id;
+        let watchtower = Watchtower::new(
+            &data_dir,
+            String::new(),
+            Box::new(move |node_id| *node_id == admitted),
+        )
+        .unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        watchtower
+            .add_appointment(signed_appointment(1, 2, 1), now)
+            .unwrap();
+        assert!(matches!(
+            watchtower.add_appointment(signed_appointment(3, 4, 2), now),
+            Err(AddAppointmentError::NotAdmitted(_))
+        ));
+        assert!(watchtower
+            .penalties_for(&[transaction(3).txid()])
+            .is_empty());
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn large_appointments_are_rejected() {
+        let data_dir = temp_data_dir();
+        let watchtower = open_watchtower(&data_dir);
+
+        let mut appointment = signed_appointment(1, 2, 1).appointment;
+        appointment.encrypted_blob = vec![0; MAX_ENCRYPTED_BLOB_SIZE + 1];
+        let signed = SignedAppointment::new(appointment, &SecretKey::from_slice(&[1; 32]).unwrap());
+
+        assert!(matches!(
+            watchtower.add_appointment(signed, OffsetDateTime::now_utc()),
+            Err(AddAppointmentError::Invalid(_))
+        ));
+        assert_eq!(watchtower.appointments.lock().total, 0);
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn clients_are_limited() {
+        let data_dir = temp_data_dir();
+        let mut watchtower = open_watchtower(&data_dir);
+        watchtower.limits = Limits {
+            per_client: 3,
+            per_window: 2,
+            window: time::Duration::minutes(1),
+            total: 4,
+            expiry: time::Duration::days(1),
+        };
+        let now = OffsetDateTime::now_utc();
+
+        watchtower
+            .add_appointment(signed_appointment(1, 2, 1), now)
+            .unwrap();
+        watchtower
+            .add_appointment(signed_appointment(3, 4, 1), now)
+            .unwrap();
+        assert!(matches!(
+            watchtower.add_appointment(signed_appointment(5, 6, 1), now),
+            Err(AddAppointmentError::RateLimited)
+        ));
+
+        // Other clients are not affected.
+        watchtower
+            .add_appointment(signed_appointment(5, 6, 2), now)
+            .unwrap();
+
+        let later = now + time::Duration::minutes(1);
+        watchtower
+            .add_appointment(signed_appointment(7, 8, 1), later)
+            .unwrap();
+        assert!(matches!(
+            watchtower.add_appointment(signed_appointment(9, 10, 1), later),
+            Err(AddAppointmentError::QuotaExceeded(_))
+        ));
+        assert!(matches!(
+            watchtower.add_appointment(signed_appointment(9, 10, 3), later),
+            Err(AddAppointmentError::Full)
+        ));
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn appointments_expire_unless_their_penalty_is_pending() {
+        let data_dir = temp_data_dir();
+        let watchtower = open_watchtower(&data_dir);
+        let now = OffsetDateTime::now_utc();
+
+        watchtower
+            .add_appointment(signed_appointment(1, 2, 1), now)
+            .unwrap();
+        watchtower
+            .add_appointment(signed_appointment(3, 4, 1), now)
+            .unwrap();
+        watchtower
+            .track_penalties(watchtower.penalties_for(&[transaction(3).txid()]))
+            .unwrap();
+
+        watchtower
+            .expire_appointments(now + Limits::default().expiry)
+            .unwrap();
+
+        let watchtower = open_watchtower(&data_dir);
+        assert!(watchtower
+            .penalties_for(&[transaction(1).txid()])
+            .is_empty());
+        assert_eq!(watchtower.penalties_for(&[transaction(3).txid()]).len(), 1);
+        assert_eq!(
+            watchtower
+                .appointments
+                .lock()
+                .of(&signed_appointment(1, 2, 1).node_id),
+            1
+        );
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+}
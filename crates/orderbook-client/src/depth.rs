@@ -0,0 +1,284 @@
+use orderbook_commons::Depth;
+use orderbook_commons::DepthSnapshot;
+use orderbook_commons::DepthUpdate;
+use orderbook_commons::Price;
+use orderbook_commons::PriceLevel;
+use rust_decimal::Decimal;
+use std::fmt;
+use trade::ContractSymbol;
+use trade::Direction;
+
+/// The error returned if a [`DepthUpdate`] can't be applied to the [`LocalOrderbook`].
+///
+/// The local orderbook is out of sync until the next [`DepthSnapshot`] has been applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfSync {
+    /// No snapshot has been applied yet.
+    MissingSnapshot,
+    /// At least one update has been missed.
+    Gap { expected: u64, received: u64 },
+}
+
+impl fmt::Display for OutOfSync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutOfSync::MissingSnapshot => write!(f, "No depth snapshot received yet"),
+            OutOfSync::Gap { expected, received } => write!(
+                f,
+                "Missed depth update: expected sequence {expected}, received {received}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OutOfSync {}
+
+/// A local copy of the aggregated orderbook, kept up to date with the depth feed of the
+/// orderbook.
+#[derive(Debug, Clone)]
+pub struct LocalOrderbook {
+    contract_symbol: ContractSymbol,
+    depth: Depth,
+    /// The sequence number of the last applied snapshot or update. `None` if the local orderbook
+    /// is out of sync.
+    sequence: Option<u64>,
+}
+
+impl LocalOrderbook {
+    pub fn new(contract_symbol: ContractSymbol) -> Self {
+        Self {
+            contract_symbol,
+            depth: Depth::default(),
+            sequence: None,
+        }
+    }
+
+    /// Replaces the local orderbook with the given snapshot.
+    ///
+    /// Snapshots which are older than the current state are ignored.
+    pub fn apply_snapshot(&mut self, snapshot: &DepthSnapshot) {
+        if snapshot.contract_symbol != self.contract_symbol {
+            return;
+        }
+
+        if matches!(self.sequence, Some(sequence) if snapshot.sequence < sequence) {
+            tracing::trace!(
+                sequence = snapshot.sequence,
+                "Ignoring outdated depth snapshot"
+            );
+            return;
+        }
+
+        self.depth = Depth::from(snapshot);
+        self.sequence = Some(snapshot.sequence);
+    }
+
+    /// Applies the given update to the local orderbook.
+    ///
+    /// Updates which are already contained in the local orderbook are ignored. If an update has
+    /// been missed the local orderbook is considered out of sync and further updates are rejected
+    /// until the next snapshot has been applied.
+    pub fn apply_update(&mut self, update: &DepthUpdate) -> Result<(), OutOfSync> {
+        if update.contract_symbol != self.contract_symbol {
+            return Ok(());
+        }
+
+        let sequence = self.sequence.ok_or(OutOfSync::MissingSnapshot)?;
+
+        if update.sequence <= sequence {
+            tracing::trace!(sequence = update.sequence, "Ignoring outdated depth update");
+            return Ok(());
+        }
+
+        if update.sequence != sequence + 1 {
+            self.sequence = None;
+            return Err(OutOfSync::Gap {
+                expected: sequence + 1,
+                received: update.sequence,
+            });
+        }
+
+        self.depth.apply(&update.updates);
+        self.sequence = Some(update.sequence);
+
+        Ok(())
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.sequence.is_some()
+    }
+
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    pub fn contract_symbol(&self) -> ContractSymbol {
+        self.contract_symbol
+    }
+
+    /// The best bid and ask of the local orderbook.
+    pub fn best_price(&self) -> Price {
+        self.depth.best_price()
+    }
+
+    /// The bid levels, highest price first.
+    pub fn bids(&self) -> Vec<PriceLevel> {
+        self.depth.bids()
+    }
+
+    /// The ask levels, lowest price first.
+    pub fn asks(&self) -> Vec<PriceLevel> {
+        self.depth.asks()
+    }
+
+    /// The volume weighted average price at which a market order of the given direction and
+    /// quantity would be filled.
+    ///
+    /// A long order is filled against the asks and a short order against the bids, best price
+    /// first. Returns `None` if the local orderbook does not hold enough liquidity to fill the
+    /// whole quantity.
+    pub fn expected_fill_price(&self, direction: Direction, quantity: Decimal) -> Option<Decimal> {
+        if quantity <= Decimal::ZERO {
+            return None;
+        }
+
+        let levels = match direction {
+            Direction::Long => self.asks(),
+            Direction::Short => self.bids(),
+        };
+
+        let mut remaining = quantity;
+        let mut cost = Decimal::ZERO;
+        for level in levels {
+            let filled = remaining.min(level.quantity);
+            cost += filled * level.price;
+            remaining -= filled;
+
+            if remaining.is_zero() {
+                return Some(cost / quantity);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orderbook_commons::LevelUpdate;
+
+    fn snapshot(sequence: u64) -> DepthSnapshot {
+        DepthSnapshot {
+            contract_symbol: ContractSymbol::BtcUsd,
+            sequence,
+            bids: vec![PriceLevel {
+                price: Decimal::from(29_900),
+                quantity: Decimal::from(100),
+            }],
+            asks: vec![PriceLevel {
+                price: Decimal::from(30_100),
+                quantity: Decimal::from(100),
+            }],
+        }
+    }
+
+    fn update(sequence: u64, price: i64, quantity: i64) -> DepthUpdate {
+        DepthUpdate {
+            contract_symbol: ContractSymbol::BtcUsd,
+            sequence,
+            updates: vec![LevelUpdate {
+                direction: Direction::Short,
+                price: Decimal::from(price),
+                quantity: Decimal::from(quantity),
+            }],
+        }
+    }
+
+    #[test]
+    fn rejects_update_without_snapshot() {
+        let mut book = LocalOrderbook::new(ContractSymbol::BtcUsd);
+
+        assert_eq!(
+            book.apply_update(&update(1, 30_000, 10)),
+            Err(OutOfSync::MissingSnapshot)
+        );
+    }
+
+    #[test]
+    fn applies_consecutive_updates() {
+        let mut book = LocalOrderbook::new(ContractSymbol::BtcUsd);
+        book.apply_snapshot(&snapshot(5));
+
+        book.apply_update(&update(6, 30_000, 10)).unwrap();
+        book.apply_update(&update(7, 30_100, 0)).unwrap();
+
+        assert_eq!(book.sequence(), Some(7));
+        assert_eq!(
+            book.asks(),
+            vec![PriceLevel {
+                price: Decimal::from(30_000),
+                quantity: Decimal::from(10),
+            }]
+        );
+        assert_eq!(book.best_price().ask, Some(Decimal::from(30_000)));
+    }
+
+    #[test]
+    fn ignores_outdated_updates() {
+        let mut book = LocalOrderbook::new(ContractSymbol::BtcUsd);
+        book.apply_snapshot(&snapshot(5));
+
+        book.apply_update(&update(5, 30_000, 10)).unwrap();
+
+        assert_eq!(book.sequence(), Some(5));
+        assert_eq!(book.best_price().ask, Some(Decimal::from(30_100)));
+    }
+
+    #[test]
+    fn detects_gap_and_resyncs_with_snapshot() {
+        let mut book = LocalOrderbook::new(ContractSymbol::BtcUsd);
+        book.apply_snapshot(&snapshot(5));
+
+        assert_eq!(
+            book.apply_update(&update(7, 30_000, 10)),
+            Err(OutOfSync::Gap {
+                expected: 6,
+                received: 7
+            })
+        );
+        assert!(!book.is_synced());
+        assert_eq!(
+            book.apply_update(&update(8, 30_000, 10)),
+            Err(OutOfSync::MissingSnapshot)
+        );
+
+        book.apply_snapshot(&snapshot(8));
+        book.apply_update(&update(9, 30_000, 10)).unwrap();
+        assert!(book.is_synced());
+    }
+
+    #[test]
+    fn expected_fill_price_walks_the_levels() {
+        let mut book = LocalOrderbook::new(ContractSymbol::BtcUsd);
+        book.apply_snapshot(&snapshot(1));
+        book.apply_update(&update(2, 30_200, 100)).unwrap();
+
+        assert_eq!(
+            book.expected_fill_price(Direction::Long, Decimal::from(50)),
+            Some(Decimal::from(30_100))
+        );
+        assert_eq!(
+            book.expected_fill_price(Direction::Long, Decimal::from(200)),
+            Some(Decimal::from(30_150))
+        );
+        assert_eq!(
+            book.expected_fill_price(Direction::Short, Decimal::from(100)),
+            Some(Decimal::from(29_900))
+        );
+        assert_eq!(
+            book.expected_fill_price(Direction::Short, Decimal::from(101)),
+            None
+        );
+    }
+}
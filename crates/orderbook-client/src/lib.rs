@@ -1,3 +1,8 @@
+mod depth;
+
+pub use crate::depth::LocalOrderbook;
+pub use crate::depth::OutOfSync;
+
 use anyhow::Context;
 use anyhow::Error;
 use anyhow::Result;
@@ -0,0 +1,350 @@
+use crate::Order;
+use crate::OrderState;
+use crate::OrderType;
+use crate::Price;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use trade::ContractSymbol;
+use trade::Direction;
+
+/// The aggregated quantity of all open orders at a given price.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PriceLevel {
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub quantity: Decimal,
+}
+
+/// A change of a single price level.
+///
+/// A quantity of zero indicates that the price level has been removed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LevelUpdate {
+    /// `Long` for bids and `Short` for asks.
+    pub direction: Direction,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub quantity: Decimal,
+}
+
+/// The full aggregated orderbook at the given sequence number.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DepthSnapshot {
+    pub contract_symbol: ContractSymbol,
+    pub sequence: u64,
+    /// Sorted by price, highest first.
+    pub bids: Vec<PriceLevel>,
+    /// Sorted by price, lowest first.
+    pub asks: Vec<PriceLevel>,
+}
+
+/// The changes to the aggregated orderbook since the previous sequence number.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DepthUpdate {
+    pub contract_symbol: ContractSymbol,
+    pub sequence: u64,
+    pub updates: Vec<LevelUpdate>,
+}
+
+/// The aggregated orderbook of a single contract symbol.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Depth {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl Depth {
+    /// Aggregates all open limit orders of the given contract symbol into price levels.
+    ///
+    /// Long orders make up the bids and short orders make up the asks. Market orders are not
+    /// resting in the book and are therefore ignored.
+    pub fn from_orders(orders: &[Order], contract_symbol: ContractSymbol) -> Self {
+        let mut depth = Depth::default();
+        for order in orders.iter().filter(|order| {
+            order.order_type == OrderType::Limit
+                && order.order_state == OrderState::Open
+                && order.contract_symbol == contract_symbol
+        }) {
+            *depth
+                .side_mut(order.direction)
+                .entry(order.price)
+                .or_insert(Decimal::ZERO) += order.quantity;
+        }
+
+        depth
+    }
+
+    /// Returns the updates needed to turn `self` into `other`.
+    pub fn diff(&self, other: &Depth) -> Vec<LevelUpdate> {
+        [Direction::Long, Direction::Short]
+            .into_iter()
+            .flat_map(|direction| {
+                let old = self.side(direction);
+                let new = other.side(direction);
+
+                let removed =
+                    old.keys()
+                        .filter(|price| !new.contains_key(*price))
+                        .map(move |price| LevelUpdate {
+                            direction,
+                            price: *price,
+                            quantity: Decimal::ZERO,
+                        });
+                let changed = new
+                    .iter()
+                    .filter(|(price, quantity)| old.get(*price) != Some(*quantity))
+                    .map(move |(price, quantity)| LevelUpdate {
+                        direction,
+                        price: *price,
+                        quantity: *quantity,
+                    });
+
+                removed.chain(changed).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn apply(&mut self, updates: &[LevelUpdate]) {
+        for update in updates {
+            let side = self.side_mut(update.direction);
+            if update.quantity.is_zero() {
+                side.remove(&update.price);
+            } else {
+                side.insert(update.price, update.quantity);
+            }
+        }
+    }
+
+    /// The bid levels, highest price first.
+    pub fn bids(&self) -> Vec<PriceLevel> {
+        self.bids
+            .iter()
+            .rev()
+            .map(|(price, quantity)| PriceLevel {
+                price: *price,
+                quantity: *quantity,
+            })
+            .collect()
+    }
+
+    /// The ask levels, lowest price first.
+    pub fn asks(&self) -> Vec<PriceLevel> {
+        self.asks
+            .iter()
+            .map(|(price, quantity)| PriceLevel {
+                price: *price,
+                quantity: *quantity,
+            })
+            .collect()
+    }
+
+    pub fn best_price(&self) -> Price {
+        Price {
+            bid: self.bids.keys().next_back().copied(),
+            ask: self.asks.keys().next().copied(),
+        }
+    }
+
+    pub fn to_snapshot(&self, contract_symbol: ContractSymbol, sequence: u64) -> DepthSnapshot {
+        DepthSnapshot {
+            contract_symbol,
+            sequence,
+            bids: self.bids(),
+            asks: self.asks(),
+        }
+    }
+
+    fn side(&self, direction: Direction) -> &BTreeMap<Decimal, Decimal> {
+        match direction {
+            Direction::Long => &self.bids,
+            Direction::Short => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, direction: Direction) -> &mut BTreeMap<Decimal, Decimal> {
+        match direction {
+            Direction::Long => &mut self.bids,
+            Direction::Short => &mut self.asks,
+        }
+    }
+}
+
+impl From<&DepthSnapshot> for Depth {
+    fn from(value: &DepthSnapshot) -> Self {
+        Depth {
+            bids: value
+                .bids
+                .iter()
+                .map(|level| (level.price, level.quantity))
+                .collect(),
+            asks: value
+                .asks
+                .iter()
+                .map(|level| (level.price, level.quantity))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderReason;
+    use rust_decimal_macros::dec;
+    use secp256k1::PublicKey;
+    use std::str::FromStr;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    fn order(direction: Direction, price: Decimal, quantity: Decimal, state: OrderState) -> Order {
+        Order {
+            id: Uuid::new_v4(),
+            price,
+            leverage: 1.0,
+            contract_symbol: ContractSymbol::BtcUsd,
+            trader_id: PublicKey::from_str(
+                "02bd998ebd176715fe92b7467cf6b1df8023950a4dd911db4c94dfc89cc9f5a655",
+            )
+            .unwrap(),
+            direction,
+            quantity,
+            order_type: OrderType::Limit,
+            timestamp: OffsetDateTime::now_utc(),
+            expiry: OffsetDateTime::now_utc(),
+            order_state: state,
+            order_reason: OrderReason::Manual,
+            stable: false,
+        }
+    }
+
+    #[test]
+    fn aggregates_open_orders_into_levels() {
+        let orders = vec![
+            order(Direction::Long, dec!(29_900), dec!(100), OrderState::Open),
+            order(Direction::Long, dec!(29_900), dec!(50), OrderState::Open),
+            order(Direction::Long, dec!(29_800), dec!(10), OrderState::Open),
+            order(Direction::Long, dec!(29_950), dec!(10), OrderState::Taken),
+            order(Direction::Short, dec!(30_100), dec!(20), OrderState::Open),
+            order(Direction::Short, dec!(30_000), dec!(30), OrderState::Open),
+        ];
+
+        let depth = Depth::from_orders(&orders, ContractSymbol::BtcUsd);
+
+        assert_eq!(
+            depth.bids(),
+            vec![
+                PriceLevel {
+                    price: dec!(29_900),
+                    quantity: dec!(150)
+                },
+                PriceLevel {
+                    price: dec!(29_800),
+                    quantity: dec!(10)
+                },
+            ]
+        );
+        assert_eq!(
+            depth.asks(),
+            vec![
+                PriceLevel {
+                    price: dec!(30_000),
+                    quantity: dec!(30)
+                },
+                PriceLevel {
+                    price: dec!(30_100),
+                    quantity: dec!(20)
+                },
+            ]
+        );
+        assert_eq!(
+            depth.best_price(),
+            Price {
+                bid: Some(dec!(29_900)),
+                ask: Some(dec!(30_000)),
+            }
+        );
+    }
+
+    #[test]
+    fn market_orders_are_not_part_of_depth() {
+        let market_order = Order {
+            order_type: OrderType::Market,
+            ..order(Direction::Long, dec!(30_500), dec!(100), OrderState::Open)
+        };
+        let orders = vec![
+            market_order,
+            order(Direction::Long, dec!(29_900), dec!(10), OrderState::Open),
+        ];
+
+        let depth = Depth::from_orders(&orders, ContractSymbol::BtcUsd);
+
+        assert_eq!(
+            depth.bids(),
+            vec![PriceLevel {
+                price: dec!(29_900),
+                quantity: dec!(10)
+            }]
+        );
+        assert!(depth.asks().is_empty());
+    }
+
+    #[test]
+    fn applying_diff_yields_new_depth() {
+        let old = Depth::from_orders(
+            &[
+                order(Direction::Long, dec!(29_900), dec!(100), OrderState::Open),
+                order(Direction::Long, dec!(29_800), dec!(10), OrderState::Open),
+                order(Direction::Short, dec!(30_000), dec!(30), OrderState::Open),
+            ],
+            ContractSymbol::BtcUsd,
+        );
+        let new = Depth::from_orders(
+            &[
+                order(Direction::Long, dec!(29_900), dec!(60), OrderState::Open),
+                order(Direction::Short, dec!(30_000), dec!(30), OrderState::Open),
+                order(Direction::Short, dec!(30_050), dec!(5), OrderState::Open),
+            ],
+            ContractSymbol::BtcUsd,
+        );
+
+        let updates = old.diff(&new);
+        assert_eq!(updates.len(), 3);
+
+        let mut depth = old.clone();
+        depth.apply(&updates);
+        assert_eq!(depth, new);
+    }
+
+    #[test]
+    fn no_diff_for_equal_depth() {
+        let orders = vec![order(
+            Direction::Long,
+            dec!(29_900),
+            dec!(100),
+            OrderState::Open,
+        )];
+        let depth = Depth::from_orders(&orders, ContractSymbol::BtcUsd);
+
+        assert!(depth.diff(&depth.clone()).is_empty());
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let depth = Depth::from_orders(
+            &[
+                order(Direction::Long, dec!(29_900), dec!(100), OrderState::Open),
+                order(Direction::Short, dec!(30_000), dec!(30), OrderState::Open),
+            ],
+            ContractSymbol::BtcUsd,
+        );
+
+        let snapshot = depth.to_snapshot(ContractSymbol::BtcUsd, 42);
+
+        assert_eq!(snapshot.sequence, 42);
+        assert_eq!(Depth::from(&snapshot), depth);
+    }
+}
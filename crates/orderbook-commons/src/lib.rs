@@ -18,9 +18,22 @@ use trade::ContractSymbol;
 use trade::Direction;
 use uuid::Uuid;
 
+mod depth;
+mod market_data;
 mod order_matching_fee;
 mod price;
 
+pub use crate::depth::Depth;
+pub use crate::depth::DepthSnapshot;
+pub use crate::depth::DepthUpdate;
+pub use crate::depth::LevelUpdate;
+pub use crate::depth::PriceLevel;
+pub use crate::market_data::candles;
+pub use crate::market_data::market_stats;
+pub use crate::market_data::Candle;
+pub use crate::market_data::MarketStats;
+pub use crate::market_data::Resolution;
+pub use crate::market_data::Trade;
 pub use crate::order_matching_fee::order_matching_fee_taker;
 pub use crate::price::best_current_price;
 pub use crate::price::Price;
@@ -79,6 +92,18 @@ pub fn create_sign_message() -> SecpMessage {
     msg
 }
 
+/// The message a trader has to sign to cancel the order with the given id.
+///
+/// As the message commits to the order id, the signature cannot be replayed to cancel other
+/// orders.
+pub fn create_cancel_order_message(order_id: Uuid) -> SecpMessage {
+    let sign_message = format!("Cancel order {order_id}");
+    let hashed_message = Sha256::new().chain_update(sign_message).finalize_fixed();
+
+    SecpMessage::from_slice(hashed_message.as_slice())
+        .expect("A SHA256 hash to always be a valid message")
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct NewOrder {
     pub id: Uuid,
@@ -93,6 +118,13 @@ pub struct NewOrder {
     pub order_type: OrderType,
     pub expiry: OffsetDateTime,
     pub stable: bool,
+    /// The worst price at which a market order may be filled.
+    ///
+    /// For a long order this is the highest and for a short order the lowest acceptable price.
+    /// If `None`, the market order is filled at the best available price regardless of the
+    /// slippage.
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub worst_price: Option<Decimal>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -143,7 +175,9 @@ pub enum Message {
     AllOrders(Vec<Order>),
     LimitOrderFilledMatches {
         trader_id: PublicKey,
-        matches: Vec<(Uuid, Decimal)>,
+        /// The filled matches as order ID, signed number of contracts (positive if the trader is
+        /// long) and execution price.
+        matches: Vec<(Uuid, Decimal, Decimal)>,
     },
     NewOrder(Order),
     DeleteOrder(Uuid),
@@ -164,6 +198,20 @@ pub enum Message {
         #[serde(with = "bitcoin::util::amount::serde::as_sat")]
         trader_amount: Amount,
     },
+    /// The match of the order with the given id has been abandoned, e.g. because the trade has
+    /// not been executed in time. The order will not be executed anymore.
+    MatchAbandoned {
+        order_id: Uuid,
+        reason: String,
+    },
+    /// A trade has been executed.
+    Trade(Trade),
+    /// The latest market statistics.
+    MarketStats(MarketStats),
+    /// The full aggregated orderbook. Sent when subscribing and periodically afterwards.
+    DepthSnapshot(DepthSnapshot),
+    /// The changes to the aggregated orderbook since the previous sequence number.
+    DepthUpdate(DepthUpdate),
 }
 
 impl Display for Message {
@@ -202,6 +250,21 @@ impl Display for Message {
             Message::CollaborativeRevert { .. } => {
                 write!(f, "CollaborativeRevert")
             }
+            Message::MatchAbandoned { .. } => {
+                write!(f, "MatchAbandoned")
+            }
+            Message::Trade(_) => {
+                write!(f, "Trade")
+            }
+            Message::MarketStats(_) => {
+                write!(f, "MarketStats")
+            }
+            Message::DepthSnapshot(_) => {
+                write!(f, "DepthSnapshot")
+            }
+            Message::DepthUpdate(_) => {
+                write!(f, "DepthUpdate")
+            }
         }
     }
 }
@@ -374,6 +437,7 @@ impl From<RoutingFees> for lightning::routing::gossip::RoutingFees {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MatchState {
     Pending,
     Filled,
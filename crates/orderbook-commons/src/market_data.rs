@@ -0,0 +1,334 @@
+use anyhow::bail;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use time::Duration;
+use time::OffsetDateTime;
+use trade::ContractSymbol;
+use trade::Direction;
+use uuid::Uuid;
+
+/// An executed trade, i.e. a filled match of a market order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trade {
+    /// The id of the match.
+    pub id: Uuid,
+    pub contract_symbol: ContractSymbol,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub quantity: Decimal,
+    /// The direction of the market order, i.e. the aggressor side of the trade.
+    pub taker_direction: Direction,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// The resolution of a [`Candle`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "4h")]
+    FourHours,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl Resolution {
+    pub fn duration(&self) -> Duration {
+        match self {
+            Resolution::OneMinute => Duration::minutes(1),
+            Resolution::FiveMinutes => Duration::minutes(5),
+            Resolution::FifteenMinutes => Duration::minutes(15),
+            Resolution::OneHour => Duration::hours(1),
+            Resolution::FourHours => Duration::hours(4),
+            Resolution::OneDay => Duration::days(1),
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let resolution = match s {
+            "1m" => Resolution::OneMinute,
+            "5m" => Resolution::FiveMinutes,
+            "15m" => Resolution::FifteenMinutes,
+            "1h" => Resolution::OneHour,
+            "4h" => Resolution::FourHours,
+            "1d" => Resolution::OneDay,
+            _ => bail!("Unknown resolution {s}"),
+        };
+
+        Ok(resolution)
+    }
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Resolution::OneMinute => "1m",
+            Resolution::FiveMinutes => "5m",
+            Resolution::FifteenMinutes => "15m",
+            Resolution::OneHour => "1h",
+            Resolution::FourHours => "4h",
+            Resolution::OneDay => "1d",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// An OHLC candle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Candle {
+    /// The start of the interval covered by the candle.
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub open: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub high: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub low: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub close: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub volume: Decimal,
+}
+
+/// Market statistics over the last 24 hours.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MarketStats {
+    pub contract_symbol: ContractSymbol,
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub last_price: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub high: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub low: Option<Decimal>,
+    /// The absolute price change compared to the first trade within the last 24 hours.
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub price_change: Option<Decimal>,
+    /// The price change in percent compared to the first trade within the last 24 hours.
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub price_change_percent: Option<Decimal>,
+    /// The traded quantity in contracts.
+    #[serde(with = "rust_decimal::serde::float")]
+    pub volume: Decimal,
+    /// The sum of the quantity of all open positions in contracts.
+    #[serde(with = "rust_decimal::serde::float")]
+    pub open_interest: Decimal,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// Aggregates the given trades into candles of the given resolution.
+///
+/// Candles are aligned to the unix epoch and sorted by time, oldest first. Intervals without any
+/// trades are omitted.
+pub fn candles(trades: &[Trade], resolution: Resolution) -> Vec<Candle> {
+    let interval = resolution.duration().whole_seconds();
+
+    let mut trades = trades.iter().collect::<Vec<_>>();
+    trades.sort_by_key(|trade| trade.timestamp);
+
+    let mut candles: BTreeMap<i64, Candle> = BTreeMap::new();
+    for trade in trades {
+        let start = trade.timestamp.unix_timestamp().div_euclid(interval) * interval;
+        candles
+            .entry(start)
+            .and_modify(|candle| {
+                candle.high = candle.high.max(trade.price);
+                candle.low = candle.low.min(trade.price);
+                candle.close = trade.price;
+                candle.volume += trade.quantity;
+            })
+            .or_insert_with(|| Candle {
+                timestamp: OffsetDateTime::from_unix_timestamp(start)
+                    .expect("to be a valid timestamp"),
+                open: trade.price,
+                high: trade.price,
+                low: trade.price,
+                close: trade.price,
+                volume: trade.quantity,
+            });
+    }
+
+    candles.into_values().collect()
+}
+
+/// Computes the market statistics of the 24 hours before `now` from the given trades.
+pub fn market_stats(
+    contract_symbol: ContractSymbol,
+    trades: &[Trade],
+    open_interest: Decimal,
+    now: OffsetDateTime,
+) -> MarketStats {
+    let since = now - Duration::days(1);
+
+    let mut trades = trades
+        .iter()
+        .filter(|trade| trade.contract_symbol == contract_symbol)
+        .filter(|trade| trade.timestamp > since && trade.timestamp <= now)
+        .collect::<Vec<_>>();
+    trades.sort_by_key(|trade| trade.timestamp);
+
+    let first_price = trades.first().map(|trade| trade.price);
+    let last_price = trades.last().map(|trade| trade.price);
+    let high = trades.iter().map(|trade| trade.price).max();
+    let low = trades.iter().map(|trade| trade.price).min();
+    let volume = trades.iter().map(|trade| trade.quantity).sum();
+
+    let (price_change, price_change_percent) = match (first_price, last_price) {
+        (Some(first), Some(last)) if !first.is_zero() => {
+            let change = last - first;
+            (Some(change), Some(change / first * Decimal::ONE_HUNDRED))
+        }
+        _ => (None, None),
+    };
+
+    MarketStats {
+        contract_symbol,
+        last_price,
+        high,
+        low,
+        price_change,
+        price_change_percent,
+        volume,
+        open_interest,
+        timestamp: now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    fn trade(price: Decimal, quantity: Decimal, timestamp: OffsetDateTime) -> Trade {
+        Trade {
+            id: Uuid::new_v4(),
+            contract_symbol: ContractSymbol::BtcUsd,
+            price,
+            quantity,
+            taker_direction: Direction::Long,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn aggregates_trades_into_candles() {
+        let trades = vec![
+            trade(dec!(30_100), dec!(10), datetime!(2023-10-20 10:01:30 UTC)),
+            trade(dec!(30_000), dec!(5), datetime!(2023-10-20 10:00:10 UTC)),
+            trade(dec!(30_200), dec!(20), datetime!(2023-10-20 10:00:50 UTC)),
+            trade(dec!(29_900), dec!(1), datetime!(2023-10-20 10:00:40 UTC)),
+        ];
+
+        let candles = candles(&trades, Resolution::OneMinute);
+
+        assert_eq!(
+            candles,
+            vec![
+                Candle {
+                    timestamp: datetime!(2023-10-20 10:00:00 UTC),
+                    open: dec!(30_000),
+                    high: dec!(30_200),
+                    low: dec!(29_900),
+                    close: dec!(30_200),
+                    volume: dec!(26),
+                },
+                Candle {
+                    timestamp: datetime!(2023-10-20 10:01:00 UTC),
+                    open: dec!(30_100),
+                    high: dec!(30_100),
+                    low: dec!(30_100),
+                    close: dec!(30_100),
+                    volume: dec!(10),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_intervals_without_trades() {
+        let trades = vec![
+            trade(dec!(30_000), dec!(5), datetime!(2023-10-20 10:00:00 UTC)),
+            trade(dec!(31_000), dec!(5), datetime!(2023-10-20 14:59:59 UTC)),
+        ];
+
+        let candles = candles(&trades, Resolution::OneHour);
+
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].timestamp, datetime!(2023-10-20 10:00:00 UTC));
+        assert_eq!(candles[1].timestamp, datetime!(2023-10-20 14:00:00 UTC));
+    }
+
+    #[test]
+    fn market_stats_only_consider_last_24_hours() {
+        let now = datetime!(2023-10-20 12:00:00 UTC);
+        let trades = vec![
+            trade(dec!(25_000), dec!(100), datetime!(2023-10-19 11:00:00 UTC)),
+            trade(dec!(30_000), dec!(10), datetime!(2023-10-19 13:00:00 UTC)),
+            trade(dec!(31_000), dec!(20), datetime!(2023-10-20 08:00:00 UTC)),
+            trade(dec!(30_600), dec!(5), datetime!(2023-10-20 11:00:00 UTC)),
+        ];
+
+        let stats = market_stats(ContractSymbol::BtcUsd, &trades, dec!(1_000), now);
+
+        assert_eq!(
+            stats,
+            MarketStats {
+                contract_symbol: ContractSymbol::BtcUsd,
+                last_price: Some(dec!(30_600)),
+                high: Some(dec!(31_000)),
+                low: Some(dec!(30_000)),
+                price_change: Some(dec!(600)),
+                price_change_percent: Some(dec!(2)),
+                volume: dec!(35),
+                open_interest: dec!(1_000),
+                timestamp: now,
+            }
+        );
+    }
+
+    #[test]
+    fn market_stats_without_trades() {
+        let now = datetime!(2023-10-20 12:00:00 UTC);
+
+        let stats = market_stats(ContractSymbol::BtcUsd, &[], dec!(0), now);
+
+        assert_eq!(stats.last_price, None);
+        assert_eq!(stats.price_change_percent, None);
+        assert_eq!(stats.volume, dec!(0));
+    }
+
+    #[test]
+    fn resolution_roundtrip() {
+        for resolution in [
+            Resolution::OneMinute,
+            Resolution::FiveMinutes,
+            Resolution::FifteenMinutes,
+            Resolution::OneHour,
+            Resolution::FourHours,
+            Resolution::OneDay,
+        ] {
+            assert_eq!(
+                Resolution::from_str(&resolution.to_string()).unwrap(),
+                resolution
+            );
+        }
+    }
+}
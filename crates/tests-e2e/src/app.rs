@@ -58,6 +58,7 @@ fn test_config() -> native::config::api::Config {
         coordinator_pubkey: "02dd6abec97f9a748bf76ad502b004ce05d1b2d1f43a9e76bd7d85e767ffb022c9"
             .to_string(),
         esplora_endpoint: "http://127.0.0.1:3000".to_string(),
+        electrum_endpoint: None,
         host: "127.0.0.1".to_string(),
         p2p_port: 9045,
         http_port: 8000,
@@ -66,5 +67,6 @@ fn test_config() -> native::config::api::Config {
         oracle_pubkey: "16f88cf7d21e6c0f46bcbc983a4e3b19726c6c98858cc31c83551a88fde171c0"
             .to_string(),
         health_check_interval_secs: 1, // We want to measure health more often in tests
+        watchtower_endpoint: None,
     }
 }
@@ -3,6 +3,7 @@ use anyhow::Result;
 use bitcoin::Address;
 use coordinator::admin::Balance;
 use coordinator::routes::InvoiceParams;
+use coordinator::settings::Settings;
 use coordinator_commons::CollaborativeRevert;
 use ln_dlc_node::lightning_invoice;
 use ln_dlc_node::node::NodeInfo;
@@ -161,6 +162,23 @@ impl Coordinator {
             .await
     }
 
+    pub async fn get_settings(&self) -> Result<Settings> {
+        Ok(self.get("/api/admin/settings").await?.json().await?)
+    }
+
+    pub async fn update_settings(&self, settings: &Settings) -> Result<()> {
+        self.client
+            .put(format!("{0}/api/admin/settings", self.host))
+            .json(settings)
+            .send()
+            .await
+            .context("Could not send PUT request to coordinator")?
+            .error_for_status()
+            .context("Coordinator did not return 200 OK")?;
+
+        Ok(())
+    }
+
     async fn get(&self, path: &str) -> Result<reqwest::Response> {
         self.client
             .get(format!("{0}{path}", self.host))
@@ -110,5 +110,6 @@ pub fn dummy_order() -> NewOrder {
         quantity: 1.0,
         order_type: Box::new(OrderType::Market),
         stable: false,
+        worst_price: None,
     }
 }
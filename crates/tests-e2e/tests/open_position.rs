@@ -18,6 +18,7 @@ fn dummy_order() -> NewOrder {
         quantity: 1.0,
         order_type: Box::new(OrderType::Market),
         stable: false,
+        worst_price: None,
     }
 }
 
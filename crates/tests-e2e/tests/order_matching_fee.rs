@@ -0,0 +1,102 @@
+use native::api;
+use native::api::ContractSymbol;
+use native::api::Status;
+use native::api::WalletHistoryItem;
+use native::api::WalletHistoryItemType;
+use native::trade::order::api::NewOrder;
+use native::trade::order::api::OrderType;
+use native::trade::order::OrderState;
+use native::trade::position::PositionState;
+use tests_e2e::app::AppHandle;
+use tests_e2e::setup::TestSetup;
+use tests_e2e::wait_until;
+use tokio::task::spawn_blocking;
+
+fn dummy_order() -> NewOrder {
+    NewOrder {
+        leverage: 2.0,
+        contract_symbol: ContractSymbol::BtcUsd,
+        direction: api::Direction::Long,
+        quantity: 1.0,
+        order_type: Box::new(OrderType::Market),
+        stable: false,
+        worst_price: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "need to be run with 'just e2e' command"]
+async fn order_matching_fee_is_settled_once_dlc_is_signed() {
+    let test = TestSetup::new_after_funding().await;
+    let app = &test.app;
+
+    spawn_blocking(|| api::submit_order(dummy_order()).unwrap())
+        .await
+        .unwrap();
+
+    wait_until!(app.rx.order().is_some());
+    let order_id = app.rx.order().unwrap().id.to_string();
+
+    // The fee is paid before the trade is executed and held while the DLC is being set up.
+    wait_until!(order_matching_fee(app, &order_id).is_some());
+
+    wait_until!(app
+        .rx
+        .position()
+        .map(|position| position.position_state == PositionState::Open)
+        .unwrap_or(false));
+
+    wait_until!(order_matching_fee(app, &order_id)
+        .map(|fee| matches!(fee.status, Status::Confirmed))
+        .unwrap_or(false));
+}
+
+#[tokio::test]
+#[ignore = "need to be run with 'just e2e' command"]
+async fn order_matching_fee_is_refunded_if_trade_fails() {
+    let test = TestSetup::new_after_funding().await;
+    let app = &test.app;
+
+    let ln_balance = app.rx.wallet_info().unwrap().balances.lightning;
+
+    // The coordinator refuses to open the position after the fee has been paid.
+    let settings = test.coordinator.get_settings().await.unwrap();
+    test.coordinator
+        .update_settings(&coordinator::settings::Settings {
+            new_positions_enabled: false,
+            ..settings.clone()
+        })
+        .await
+        .unwrap();
+
+    spawn_blocking(|| api::submit_order(dummy_order()).unwrap())
+        .await
+        .unwrap();
+
+    wait_until!(app.rx.order().is_some());
+    let order_id = app.rx.order().unwrap().id.to_string();
+
+    wait_until!(app
+        .rx
+        .order()
+        .map(|order| matches!(order.state, OrderState::Failed { .. }))
+        .unwrap_or(false));
+
+    wait_until!(order_matching_fee(app, &order_id)
+        .map(|fee| matches!(fee.status, Status::Failed))
+        .unwrap_or(false));
+
+    test.coordinator.update_settings(&settings).await.unwrap();
+
+    assert!(app.rx.position().is_none());
+    wait_until!(app.rx.wallet_info().unwrap().balances.lightning == ln_balance);
+}
+
+fn order_matching_fee(app: &AppHandle, order_id: &str) -> Option<WalletHistoryItem> {
+    app.rx.wallet_info()?.history.into_iter().find(|item| {
+        matches!(
+            item.wallet_type,
+            WalletHistoryItemType::OrderMatchingFee { order_id: ref id, .. } if id == order_id
+        )
+    })
+}
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop table if exists funding_payments;
+drop table if exists fills;
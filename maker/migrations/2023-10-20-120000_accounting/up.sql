@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TABLE "fills"
+(
+    id              SERIAL PRIMARY KEY       NOT NULL,
+    venue           TEXT                     NOT NULL,
+    execution_id    TEXT                     NOT NULL,
+    contract_symbol TEXT                     NOT NULL,
+    contracts       DOUBLE PRECISION         NOT NULL,
+    price           DOUBLE PRECISION         NOT NULL,
+    fee_btc         DOUBLE PRECISION         NOT NULL,
+    timestamp       timestamp WITH TIME ZONE NOT NULL,
+    UNIQUE (venue, execution_id)
+);
+
+CREATE TABLE "funding_payments"
+(
+    id              SERIAL PRIMARY KEY       NOT NULL,
+    execution_id    TEXT UNIQUE              NOT NULL,
+    contract_symbol TEXT                     NOT NULL,
+    amount_btc      DOUBLE PRECISION         NOT NULL,
+    timestamp       timestamp WITH TIME ZONE NOT NULL
+);
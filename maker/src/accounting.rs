@@ -0,0 +1,371 @@
+use crate::position::ContractSymbol;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use time::OffsetDateTime;
+
+/// Where a fill happened.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Venue {
+    /// The maker's orders on the 10101 orderbook.
+    TenTenOne,
+    /// The hedging venue, e.g. BitMEX.
+    Hedging,
+}
+
+impl fmt::Display for Venue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Venue::TenTenOne => "tentenone",
+            Venue::Hedging => "hedging",
+        };
+
+        f.write_str(s)
+    }
+}
+
+impl FromStr for Venue {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tentenone" => Ok(Venue::TenTenOne),
+            "hedging" => Ok(Venue::Hedging),
+            _ => anyhow::bail!("Unknown venue: {s}"),
+        }
+    }
+}
+
+/// A fill of one of the maker's orders, either on 10101 or on the hedging venue.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fill {
+    pub venue: Venue,
+    /// Uniquely identifies the fill on its venue.
+    pub execution_id: String,
+    pub contract_symbol: ContractSymbol,
+    /// The filled contracts, with the sign representing the direction: positive buy; negative
+    /// sell.
+    pub contracts: Decimal,
+    pub price: Decimal,
+    /// The fee paid in BTC. Negative if the maker received a rebate.
+    pub fee: Decimal,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// A funding payment for the maker's position on the hedging venue.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FundingPayment {
+    /// Uniquely identifies the funding payment on the hedging venue.
+    pub execution_id: String,
+    pub contract_symbol: ContractSymbol,
+    /// The amount paid in BTC. Negative if the maker received funding.
+    pub amount: Decimal,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+}
+
+/// The maker's inventory and PnL across all venues.
+#[derive(Debug, Default)]
+pub struct Accounting {
+    books: BTreeMap<(Venue, ContractSymbol), Book>,
+    /// The fills and funding payments which have been accounted for, so that replayed events are
+    /// ignored.
+    execution_ids: HashSet<(Venue, String)>,
+    /// The latest mid price of the hedging venue, used to value open positions.
+    mark_price: Option<Decimal>,
+}
+
+/// The position of the maker on one venue for one contract symbol.
+///
+/// All contracts are inverse contracts: a position of `q` contracts entered at price `p` is worth
+/// `q * (1/p - 1/m)` BTC at price `m`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Book {
+    /// Positive if the maker is long, negative if the maker is short.
+    contracts: Decimal,
+    /// The sum of `q/p` over the open contracts, i.e. their entry value in BTC.
+    entry_value: Decimal,
+    realized_pnl_btc: Decimal,
+    /// The realized PnL in USD, each closing fill converted at its own price.
+    realized_pnl_usd: Decimal,
+    fees_btc: Decimal,
+    funding_btc: Decimal,
+}
+
+impl Book {
+    fn apply_fill(&mut self, contracts: Decimal, price: Decimal, fee: Decimal) {
+        self.fees_btc += fee;
+
+        if price.is_zero() || contracts.is_zero() {
+            return;
+        }
+
+        let is_reducing = !self.contracts.is_zero()
+            && self.contracts.is_sign_positive() != contracts.is_sign_positive();
+
+        let opening = if is_reducing {
+            // The part of the open position which is closed by this fill.
+            let closed = if contracts.abs() <= self.contracts.abs() {
+                -contracts
+            } else {
+                self.contracts
+            };
+
+            let closed_entry_value = self.entry_value * closed / self.contracts;
+            let realized = closed_entry_value - closed / price;
+
+            self.realized_pnl_btc += realized;
+            self.realized_pnl_usd += realized * price;
+            self.contracts -= closed;
+            self.entry_value -= closed_entry_value;
+
+            // Whatever is left of the fill flips the position.
+            contracts + closed
+        } else {
+            contracts
+        };
+
+        self.contracts += opening;
+        self.entry_value += opening / price;
+    }
+
+    /// The price at which the open contracts have been entered on average.
+    fn average_entry_price(&self) -> Option<Decimal> {
+        (!self.entry_value.is_zero()).then(|| self.contracts / self.entry_value)
+    }
+
+    fn unrealized_pnl_btc(&self, mark_price: Decimal) -> Decimal {
+        self.entry_value - self.contracts / mark_price
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PnlReport {
+    /// The price the open positions are valued at, if known.
+    pub mark_price: Option<Decimal>,
+    pub books: Vec<BookReport>,
+    /// The PnL across all venues in BTC, after fees and funding.
+    pub net_pnl_btc: Option<Decimal>,
+    /// The PnL across all venues in USD at the mark price, after fees and funding.
+    pub net_pnl_usd: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BookReport {
+    pub venue: Venue,
+    pub contract_symbol: ContractSymbol,
+    pub contracts: Decimal,
+    pub average_entry_price: Option<Decimal>,
+    pub realized_pnl_btc: Decimal,
+    pub realized_pnl_usd: Decimal,
+    pub unrealized_pnl_btc: Option<Decimal>,
+    pub unrealized_pnl_usd: Option<Decimal>,
+    pub fees_btc: Decimal,
+    pub funding_btc: Decimal,
+}
+
+impl Accounting {
+    /// Rebuilds the accounting from all persisted fills and funding payments.
+    pub fn from_history(fills: Vec<Fill>, funding_payments: Vec<FundingPayment>) -> Self {
+        let mut accounting = Self::default();
+
+        for fill in fills {
+            accounting.apply_fill(&fill);
+        }
+
+        for funding_payment in funding_payments {
+            accounting.apply_funding(&funding_payment);
+        }
+
+        accounting
+    }
+
+    /// Accounts for the fill, returning whether it is new.
+    pub fn apply_fill(&mut self, fill: &Fill) -> bool {
+        if !self
+            .execution_ids
+            .insert((fill.venue, fill.execution_id.clone()))
+        {
+            return false;
+        }
+
+        self.books
+            .entry((fill.venue, fill.contract_symbol))
+            .or_default()
+            .apply_fill(fill.contracts, fill.price, fill.fee);
+
+        true
+    }
+
+    /// Accounts for the funding payment, returning whether it is new.
+    pub fn apply_funding(&mut self, funding_payment: &FundingPayment) -> bool {
+        if !self
+            .execution_ids
+            .insert((Venue::Hedging, funding_payment.execution_id.clone()))
+        {
+            return false;
+        }
+
+        self.books
+            .entry((Venue::Hedging, funding_payment.contract_symbol))
+            .or_default()
+            .funding_btc += funding_payment.amount;
+
+        true
+    }
+
+    pub fn update_mark_price(&mut self, mark_price: Decimal) {
+        self.mark_price = Some(mark_price);
+    }
+
+    pub fn report(&self) -> PnlReport {
+        let mark_price = self.mark_price.filter(|price| !price.is_zero());
+
+        let books = self
+            .books
+            .iter()
+            .map(|((venue, contract_symbol), book)| {
+                let unrealized_pnl_btc = mark_price.map(|price| book.unrealized_pnl_btc(price));
+
+                BookReport {
+                    venue: *venue,
+                    contract_symbol: *contract_symbol,
+                    contracts: book.contracts,
+                    average_entry_price: book.average_entry_price(),
+                    realized_pnl_btc: book.realized_pnl_btc,
+                    realized_pnl_usd: book.realized_pnl_usd,
+                    unrealized_pnl_btc,
+                    unrealized_pnl_usd: unrealized_pnl_btc.zip(mark_price).map(|(pnl, p)| pnl * p),
+                    fees_btc: book.fees_btc,
+                    funding_btc: book.funding_btc,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let net_pnl_btc = books
+            .iter()
+            .map(|book| {
+                book.unrealized_pnl_btc.map(|unrealized| {
+                    book.realized_pnl_btc + unrealized - book.fees_btc - book.funding_btc
+                })
+            })
+            .sum::<Option<Decimal>>();
+
+        PnlReport {
+            mark_price,
+            books,
+            net_pnl_btc,
+            net_pnl_usd: net_pnl_btc.zip(mark_price).map(|(pnl, price)| pnl * price),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn fill(venue: Venue, execution_id: &str, contracts: Decimal, price: Decimal) -> Fill {
+        Fill {
+            venue,
+            execution_id: execution_id.to_string(),
+            contract_symbol: ContractSymbol::BtcUsd,
+            contracts,
+            price,
+            fee: Decimal::ZERO,
+            timestamp: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[test]
+    fn average_entry_price_is_harmonic_mean() {
+        let mut book = Book::default();
+
+        book.apply_fill(dec!(1_000), dec!(20_000), Decimal::ZERO);
+        book.apply_fill(dec!(3_000), dec!(60_000), Decimal::ZERO);
+
+        assert_eq!(book.average_entry_price(), Some(dec!(40_000)));
+    }
+
+    #[test]
+    fn closing_long_realizes_pnl() {
+        let mut book = Book::default();
+
+        book.apply_fill(dec!(3_000), dec!(20_000), Decimal::ZERO);
+        book.apply_fill(dec!(-1_500), dec!(30_000), Decimal::ZERO);
+
+        // 1_500 * (1/20_000 - 1/30_000) = 0.025 BTC
+        assert_eq!(book.realized_pnl_btc, dec!(0.025));
+        assert_eq!(book.realized_pnl_usd, dec!(750));
+        assert_eq!(book.contracts, dec!(1_500));
+        assert_eq!(book.average_entry_price(), Some(dec!(20_000)));
+    }
+
+    #[test]
+    fn flipping_short_opens_position_at_fill_price() {
+        let mut book = Book::default();
+
+        book.apply_fill(dec!(-1_000), dec!(25_000), Decimal::ZERO);
+        book.apply_fill(dec!(3_000), dec!(20_000), Decimal::ZERO);
+
+        // 1_000 short contracts closed with a profit of 1_000 * (1/20_000 - 1/25_000) = 0.01 BTC
+        assert_eq!(book.realized_pnl_btc, dec!(0.01));
+        assert_eq!(book.contracts, dec!(2_000));
+        assert_eq!(book.average_entry_price(), Some(dec!(20_000)));
+        assert_eq!(book.unrealized_pnl_btc(dec!(25_000)), dec!(0.02));
+    }
+
+    #[test]
+    fn hedged_position_has_no_unrealized_pnl() {
+        let mut accounting = Accounting::default();
+
+        accounting.apply_fill(&fill(Venue::TenTenOne, "a", dec!(-2_000), dec!(20_000)));
+        accounting.apply_fill(&fill(Venue::Hedging, "b", dec!(2_000), dec!(20_000)));
+        accounting.update_mark_price(dec!(25_000));
+
+        let report = accounting.report();
+
+        assert_eq!(report.books.len(), 2);
+        assert_eq!(report.net_pnl_btc, Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn fees_and_funding_reduce_net_pnl() {
+        let mut accounting = Accounting::default();
+
+        accounting.apply_fill(&Fill {
+            fee: dec!(0.0001),
+            ..fill(Venue::Hedging, "a", dec!(1_000), dec!(20_000))
+        });
+        accounting.apply_funding(&FundingPayment {
+            execution_id: "b".to_string(),
+            contract_symbol: ContractSymbol::BtcUsd,
+            amount: dec!(0.0002),
+            timestamp: OffsetDateTime::now_utc(),
+        });
+        accounting.update_mark_price(dec!(20_000));
+
+        let report = accounting.report();
+
+        assert_eq!(report.books[0].fees_btc, dec!(0.0001));
+        assert_eq!(report.books[0].funding_btc, dec!(0.0002));
+        assert_eq!(report.net_pnl_btc, Some(dec!(-0.0003)));
+        assert_eq!(report.net_pnl_usd, Some(dec!(-6)));
+    }
+
+    #[test]
+    fn replayed_fills_are_ignored() {
+        let mut accounting = Accounting::default();
+        let fill = fill(Venue::TenTenOne, "a", dec!(1_000), dec!(20_000));
+
+        assert!(accounting.apply_fill(&fill));
+        assert!(!accounting.apply_fill(&fill));
+
+        assert_eq!(accounting.report().books[0].contracts, dec!(1_000));
+    }
+}
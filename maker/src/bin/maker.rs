@@ -1,13 +1,18 @@
 use anyhow::Context;
 use anyhow::Result;
-use bitcoin::Network;
 use diesel::r2d2;
 use diesel::r2d2::ConnectionManager;
 use diesel::PgConnection;
+use ln_dlc_node::ln::SubChannelState;
 use ln_dlc_node::node::InMemoryStore;
 use ln_dlc_node::node::LnDlcNodeSettings;
+use ln_dlc_node::node::Node;
 use ln_dlc_node::seed::Bip39Seed;
+use ln_dlc_node::DlcChannelDetails;
+use maker::accounting::Accounting;
+use maker::cli::HedgingVenue;
 use maker::cli::Opts;
+use maker::db;
 use maker::health;
 use maker::ln::ldk_config;
 use maker::ln::EventHandler;
@@ -16,9 +21,16 @@ use maker::metrics;
 use maker::metrics::init_meter;
 use maker::orderbook_ws;
 use maker::position;
+use maker::position::GetPnl;
+use maker::position::PauseQuoting;
+use maker::price_feed;
+use maker::risk::RiskMonitor;
 use maker::routes::router;
 use maker::run_migration;
 use maker::trading;
+use maker::trading::quoting::LadderStrategy;
+use maker::venue::bitmex::BitmexVenue;
+use maker::venue::simulated::SimulatedExchange;
 use rand::thread_rng;
 use rand::RngCore;
 use std::backtrace::Backtrace;
@@ -27,6 +39,8 @@ use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::signal;
 use tokio::task::spawn_blocking;
 use tracing::metadata::LevelFilter;
 
@@ -35,6 +49,9 @@ const PROCESS_PROMETHEUS_METRICS: Duration = Duration::from_secs(10);
 /// Interval after which we'll try to reconnect to the pricefeed again
 const PRICEFEED_RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
 
+/// How long to wait for DLC protocols in progress to finish when shutting down.
+const DLC_PROTOCOLS_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     std::panic::set_hook(
@@ -88,7 +105,7 @@ async fn main() -> Result<()> {
         address,
         SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), address.port()),
         announcement_addresses.clone(),
-        opts.esplora.clone(),
+        opts.chain_source(),
         seed,
         ephemeral_randomness,
         LnDlcNodeSettings::default(),
@@ -102,43 +119,89 @@ async fn main() -> Result<()> {
 
     let (health, health_tx) = health::Health::new();
 
-    let bitmex_http_client = bitmex_client::client::Client::new(match network {
-        Network::Bitcoin => bitmex_client::models::Network::Mainnet,
-        _ => bitmex_client::models::Network::Testnet,
-    });
+    let price_sources = opts
+        .price_sources()?
+        .into_iter()
+        .map(|config| config.into_source())
+        .collect();
 
-    let bitmex_http_client = match (bitmex_api_key.clone(), bitmex_api_secret.clone()) {
-        (Some(bitmex_api_key), Some(bitmex_secret)) => {
-            tracing::info!("BitMEX credentials provided");
-            bitmex_http_client.with_credentials(bitmex_api_key, bitmex_secret)
+    let venue: Arc<dyn maker::venue::HedgingVenue> = match opts.hedging_venue {
+        HedgingVenue::Bitmex => {
+            Arc::new(BitmexVenue::new(network, bitmex_api_key, bitmex_api_secret))
         }
-        _ => {
-            tracing::info!("BitMEX credentials not provided");
-            bitmex_http_client
+        HedgingVenue::Simulated => {
+            tracing::info!("Hedging on simulated exchange");
+            Arc::new(SimulatedExchange::new(opts.simulated_params()))
         }
     };
 
+    let manager = ConnectionManager::<PgConnection>::new(opts.database.clone());
+    let pool = r2d2::Pool::builder()
+        .build(manager)
+        .expect("Failed to create pool.");
+
+    let mut conn = pool.get().expect("to get connection from pool");
+    run_migration(&mut conn);
+
+    let fills = db::fills::get_all(&mut conn).context("Failed to load fills")?;
+    let funding_payments =
+        db::funding_payments::get_all(&mut conn).context("Failed to load funding payments")?;
+    let risk = RiskMonitor::from_history(
+        opts.risk_limits(),
+        &fills,
+        &funding_payments,
+        OffsetDateTime::now_utc(),
+    );
+    let accounting = Accounting::from_history(fills, funding_payments);
+
+    let (reference_price_tx, reference_price) = tokio::sync::watch::channel(None);
+    let price_feed = price_feed::spawn(
+        opts.aggregation_params(),
+        price_sources,
+        match opts.hedging_venue {
+            HedgingVenue::Bitmex => "bitmex",
+            HedgingVenue::Simulated => "simulated",
+        },
+        reference_price_tx,
+        health_tx.price_sources,
+        PRICEFEED_RECONNECT_INTERVAL,
+    );
+
+    let quoting_status = health_tx.quoting.subscribe();
+
     let (position_manager, mailbox) = xtra::Mailbox::unbounded();
     tokio::spawn(xtra::run(
         mailbox,
-        position::Manager::new(bitmex_http_client),
+        position::Manager::new(
+            venue.clone(),
+            opts.execution_params(),
+            risk,
+            health_tx.quoting,
+            accounting,
+            pool.clone(),
+        ),
     ));
 
     let node_pubkey = node.info.pubkey;
-    tokio::spawn({
+    let node_key = node.node_key();
+    let trading = tokio::spawn({
         let orderbook_url = opts.orderbook.clone();
         let position_manager = position_manager.clone();
+        let quoting_strategy = LadderStrategy::new(opts.ladder_params());
+        let order_expiry_after = time::Duration::seconds(opts.order_expiry_after_seconds as i64);
         async move {
             trading::run(
                 &orderbook_url,
                 node_pubkey,
-                network,
-                opts.concurrent_orders,
-                time::Duration::seconds(opts.order_expiry_after_seconds as i64),
+                node_key,
+                venue,
+                quoting_strategy,
+                order_expiry_after,
                 health_tx.bitmex_pricefeed,
                 position_manager,
-                bitmex_api_key,
-                bitmex_api_secret,
+                price_feed,
+                reference_price,
+                quoting_status,
                 PRICEFEED_RECONNECT_INTERVAL,
             )
             .await;
@@ -157,11 +220,13 @@ async fn main() -> Result<()> {
     let _collect_prometheus_metrics = tokio::spawn({
         let node = node.clone();
         let health = health.clone();
+        let position_manager = position_manager.clone();
         async move {
             loop {
                 let node = node.clone();
                 let health = health.clone();
-                spawn_blocking(move || metrics::collect(node, health))
+                let pnl = position_manager.send(GetPnl).await.ok();
+                spawn_blocking(move || metrics::collect(node, health, pnl))
                     .await
                     .expect("To spawn blocking thread");
                 tokio::time::sleep(PROCESS_PROMETHEUS_METRICS).await;
@@ -169,30 +234,23 @@ async fn main() -> Result<()> {
         }
     });
 
-    let manager = ConnectionManager::<PgConnection>::new(opts.database);
-    let pool = r2d2::Pool::builder()
-        .build(manager)
-        .expect("Failed to create pool.");
-
-    let mut conn = pool.get().expect("to get connection from pool");
-    run_migration(&mut conn);
-
     orderbook_ws::Client::new(
-        opts.orderbook,
+        opts.orderbook.clone(),
         node_pubkey,
-        node.node_key(),
+        node_key,
         position_manager.clone(),
         health_tx.orderbook,
     )
     .spawn_supervised_connection();
 
     let app = router(
-        node,
+        node.clone(),
         exporter,
-        position_manager,
+        position_manager.clone(),
         health,
         announcement_addresses.clone(),
         node_alias,
+        pool,
     );
 
     // Start the metrics exporter
@@ -203,6 +261,7 @@ async fn main() -> Result<()> {
 
     match axum::Server::bind(&addr)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
         .await
     {
         Ok(_) => {
@@ -213,8 +272,90 @@ async fn main() -> Result<()> {
         }
     }
 
+    tracing::info!("Shutting down");
+
+    // Stop quoting before cancelling our orders so that no new orders are posted in the meantime.
+    if let Err(e) = position_manager.send(PauseQuoting).await {
+        tracing::warn!("Failed to pause quoting: {e:#}");
+    }
+    trading.abort();
+    let _ = trading.await;
+
+    if let Err(e) = trading::cancel_all_orders(&opts.orderbook, node_key).await {
+        tracing::error!("Failed to cancel all orders: {e:#}");
+    }
+
+    wait_for_dlc_protocols(&node, DLC_PROTOCOLS_SHUTDOWN_TIMEOUT).await;
+
+    tracing::info!("Shutdown complete");
+
     Ok(())
 }
+
+/// Resolves once the process receives SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install SIGINT handler");
+    };
+
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
+
+/// Waits until no DLC protocol is in progress, e.g. opening a position for an order which has
+/// just been matched, so that we do not leave a counterparty with a half-finished protocol.
+async fn wait_for_dlc_protocols(node: &Node<InMemoryStore>, timeout: Duration) {
+    let wait = async {
+        loop {
+            let in_progress = match node.list_dlc_channels() {
+                Ok(dlc_channels) => dlc_channels
+                    .into_iter()
+                    .map(DlcChannelDetails::from)
+                    .filter(|dlc_channel| is_protocol_in_progress(&dlc_channel.state))
+                    .count(),
+                Err(e) => {
+                    tracing::error!("Failed to list DLC channels: {e:#}");
+                    return;
+                }
+            };
+
+            if in_progress == 0 {
+                return;
+            }
+
+            tracing::info!(in_progress, "Waiting for DLC protocols to finish");
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    };
+
+    if tokio::time::timeout(timeout, wait).await.is_err() {
+        tracing::warn!(?timeout, "DLC protocols still in progress after timeout");
+    }
+}
+
+fn is_protocol_in_progress(state: &SubChannelState) -> bool {
+    matches!(
+        state,
+        SubChannelState::Offered
+            | SubChannelState::Accepted
+            | SubChannelState::Confirmed
+            | SubChannelState::Finalized
+            | SubChannelState::CloseOffered
+            | SubChannelState::CloseAccepted
+            | SubChannelState::CloseConfirmed
+    )
+}
 fn reqwest_client() -> reqwest::Client {
     reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(30))
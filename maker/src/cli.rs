@@ -1,10 +1,21 @@
+use crate::position::execution::ExecutionParams;
+use crate::position::execution::ExecutionPolicy;
+use crate::price_feed::AggregationParams;
+use crate::price_feed::PriceSourceConfig;
+use crate::risk::RiskLimits;
+use crate::trading::quoting::LadderParams;
+use crate::venue::simulated::SimulatedParams;
+use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
 use ln_dlc_node::node::OracleInfo;
+use ln_dlc_node::ChainSource;
 use reqwest::Url;
+use rust_decimal::Decimal;
 use std::env::current_dir;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 pub struct Opts {
@@ -34,17 +45,69 @@ pub struct Opts {
     )]
     pub database: String,
 
-    /// The Esplora server endpoint.
+    /// The Esplora server endpoint, used with `--chain-source esplora`.
     #[clap(long, default_value = "http://localhost:3000")]
     pub esplora: String,
 
+    /// Where to get the view of the blockchain from.
+    #[clap(long, value_enum, default_value = "esplora")]
+    pub chain_source: ChainSourceKind,
+
+    /// The bitcoind JSON-RPC endpoint, used with `--chain-source bitcoind`.
+    #[clap(long, default_value = "http://localhost:18443")]
+    pub bitcoind_rpc_url: String,
+
+    /// The bitcoind JSON-RPC user, required with `--chain-source bitcoind`.
+    #[clap(long, required_if_eq("chain_source", "bitcoind"))]
+    pub bitcoind_rpc_user: Option<String>,
+
+    /// The bitcoind JSON-RPC password, required with `--chain-source bitcoind`.
+    #[clap(long, required_if_eq("chain_source", "bitcoind"))]
+    pub bitcoind_rpc_password: Option<String>,
+
+    /// The Electrum server endpoint, used with `--chain-source electrum`.
+    #[clap(long, default_value = "tcp://localhost:50000")]
+    pub electrum: String,
+
     /// If enabled logs will be in JSON format.
     #[clap(short, long)]
     pub json: bool,
 
-    /// Amount of concurrent orders (buy,sell) that the maker will create at a time.
+    /// Number of orders the maker quotes on each side of the orderbook.
     #[clap(long, default_value = "5")]
-    pub concurrent_orders: usize,
+    pub quote_levels: usize,
+
+    /// Distance between the best bid and the best ask quote in basis points.
+    #[clap(long, default_value = "10")]
+    pub spread_bps: Decimal,
+
+    /// Distance between two consecutive quotes on the same side in basis points.
+    #[clap(long, default_value = "5")]
+    pub level_spacing_bps: Decimal,
+
+    /// Quantity of the quotes closest to the mid price.
+    #[clap(long, default_value = "1000")]
+    pub base_quantity: Decimal,
+
+    /// Quantity added to the quotes of every subsequent level.
+    #[clap(long, default_value = "500")]
+    pub quantity_increment: Decimal,
+
+    /// Maximum shift of the quotes in basis points due to the maker's 10101 position.
+    #[clap(long, default_value = "10")]
+    pub max_skew_bps: Decimal,
+
+    /// 10101 position in contracts at which the maximum skew is applied.
+    #[clap(long, default_value = "10000")]
+    pub max_inventory: Decimal,
+
+    /// Minimum change of the mid price or the skew in basis points before the maker requotes.
+    #[clap(long, default_value = "2")]
+    pub min_requote_bps: Decimal,
+
+    /// Leverage of the orders created by the maker.
+    #[clap(long, default_value = "1.0")]
+    pub leverage: f32,
 
     /// Orders created by maker will be valid for this number of seconds.
     #[clap(long, default_value = "60")]
@@ -61,6 +124,96 @@ pub struct Opts {
     )]
     oracle_pubkey: String,
 
+    /// The venue used for hedging and as the source of reference prices.
+    #[clap(long, value_enum, default_value = "bitmex")]
+    pub hedging_venue: HedgingVenue,
+
+    /// How hedging orders are executed.
+    #[clap(long, value_enum, default_value = "post-only")]
+    pub hedge_execution: HedgeExecution,
+
+    /// Seconds after which unfilled post-only hedging orders are replaced by market orders.
+    #[clap(long, default_value = "30")]
+    pub hedge_fallback_after_seconds: u64,
+
+    /// Maximum number of contracts per hedging order. Larger hedges are split into slices.
+    #[clap(long, default_value = "1000")]
+    pub hedge_max_order_contracts: i32,
+
+    /// Maximum number of contracts traded on the hedging venue within any minute.
+    #[clap(long, default_value = "5000")]
+    pub hedge_max_contracts_per_minute: i32,
+
+    /// Seconds between two slices of the same hedge.
+    #[clap(long, default_value = "10")]
+    pub hedge_slice_interval_seconds: u64,
+
+    /// Maximum absolute 10101 position in contracts before quoting is halted.
+    #[clap(long, default_value = "100000")]
+    pub max_net_exposure: Decimal,
+
+    /// Maximum number of seconds the 10101 position may stay unhedged before quoting is halted.
+    #[clap(long, default_value = "300")]
+    pub max_unhedged_seconds: u64,
+
+    /// Maximum loss in BTC within a UTC day before quoting is halted.
+    #[clap(long, default_value = "0.1")]
+    pub max_daily_loss_btc: Decimal,
+
+    /// Maximum age in seconds of the latest price from the hedging venue before quoting is halted.
+    #[clap(long, default_value = "180")]
+    pub max_price_age_seconds: u64,
+
+    /// JSON file configuring additional sources of the reference price besides the hedging venue.
+    /// See `price_sources.example.json`.
+    #[clap(long)]
+    pub price_sources: Option<PathBuf>,
+
+    /// Maximum age in seconds of the latest quote of a price source for it to be considered.
+    #[clap(long, default_value = "90")]
+    pub price_source_max_age_seconds: u64,
+
+    /// Maximum distance in basis points of a price source from the median of all sources before
+    /// it is ignored as an outlier.
+    #[clap(long, default_value = "50")]
+    pub price_source_max_deviation_bps: Decimal,
+
+    /// Minimum number of price sources agreeing on the price for the maker to quote.
+    #[clap(long, default_value = "1")]
+    pub min_price_sources: usize,
+
+    /// Initial mid price of the simulated hedging venue.
+    #[clap(long, default_value = "30000")]
+    pub simulated_price: Decimal,
+
+    /// Distance between bid and ask on the simulated hedging venue.
+    #[clap(long, default_value = "1")]
+    pub simulated_spread: Decimal,
+
+    /// Maximum change of the mid price per quote in basis points on the simulated hedging venue.
+    #[clap(long, default_value = "5")]
+    pub simulated_volatility_bps: Decimal,
+
+    /// Interval in milliseconds between quotes of the simulated hedging venue.
+    #[clap(long, default_value = "1000")]
+    pub simulated_quote_interval_ms: u64,
+
+    /// Time in milliseconds the simulated hedging venue takes to process an order.
+    #[clap(long, default_value = "100")]
+    pub simulated_latency_ms: u64,
+
+    /// Share of every order filled by the simulated hedging venue, between 0 and 1.
+    #[clap(long, default_value = "1.0")]
+    pub simulated_fill_ratio: Decimal,
+
+    /// Probability of the simulated hedging venue rejecting an order, between 0 and 1.
+    #[clap(long, default_value = "0.0")]
+    pub simulated_reject_rate: f64,
+
+    /// Seed of the simulated hedging venue, making its price movements reproducible.
+    #[clap(long, default_value = "0")]
+    pub simulated_seed: u64,
+
     /// BitMEX API key.
     #[clap(long)]
     pub bitmex_api_key: Option<String>,
@@ -78,6 +231,25 @@ pub enum Network {
     Mainnet,
 }
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ChainSourceKind {
+    Esplora,
+    Bitcoind,
+    Electrum,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum HedgingVenue {
+    Bitmex,
+    Simulated,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum HedgeExecution {
+    Market,
+    PostOnly,
+}
+
 impl From<Network> for bitcoin::Network {
     fn from(network: Network) -> Self {
         match network {
@@ -99,6 +271,28 @@ impl Opts {
         self.network.into()
     }
 
+    pub fn chain_source(&self) -> ChainSource {
+        match self.chain_source {
+            ChainSourceKind::Esplora => ChainSource::Esplora {
+                url: self.esplora.clone(),
+            },
+            ChainSourceKind::Bitcoind => ChainSource::Bitcoind {
+                url: self.bitcoind_rpc_url.clone(),
+                user: self
+                    .bitcoind_rpc_user
+                    .clone()
+                    .expect("bitcoind RPC user to be required"),
+                password: self
+                    .bitcoind_rpc_password
+                    .clone()
+                    .expect("bitcoind RPC password to be required"),
+            },
+            ChainSourceKind::Electrum => ChainSource::Electrum {
+                url: self.electrum.clone(),
+            },
+        }
+    }
+
     pub fn data_dir(&self) -> Result<PathBuf> {
         let data_dir = match self.data_dir.clone() {
             None => current_dir()?.join("data"),
@@ -109,6 +303,78 @@ impl Opts {
         Ok(data_dir)
     }
 
+    pub fn ladder_params(&self) -> LadderParams {
+        LadderParams {
+            spread_bps: self.spread_bps,
+            levels: self.quote_levels,
+            level_spacing_bps: self.level_spacing_bps,
+            base_quantity: self.base_quantity,
+            quantity_increment: self.quantity_increment,
+            max_skew_bps: self.max_skew_bps,
+            max_inventory: self.max_inventory,
+            min_requote_bps: self.min_requote_bps,
+            leverage: self.leverage,
+        }
+    }
+
+    pub fn execution_params(&self) -> ExecutionParams {
+        let policy = match self.hedge_execution {
+            HedgeExecution::Market => ExecutionPolicy::Market,
+            HedgeExecution::PostOnly => ExecutionPolicy::PostOnly {
+                fallback_after: Duration::from_secs(self.hedge_fallback_after_seconds),
+            },
+        };
+
+        ExecutionParams {
+            policy,
+            max_order_contracts: self.hedge_max_order_contracts,
+            max_contracts_per_minute: self.hedge_max_contracts_per_minute,
+            slice_interval: Duration::from_secs(self.hedge_slice_interval_seconds),
+        }
+    }
+
+    pub fn risk_limits(&self) -> RiskLimits {
+        RiskLimits {
+            max_net_exposure: self.max_net_exposure,
+            max_unhedged_duration: time::Duration::seconds(self.max_unhedged_seconds as i64),
+            max_daily_loss: self.max_daily_loss_btc,
+            max_price_age: time::Duration::seconds(self.max_price_age_seconds as i64),
+        }
+    }
+
+    pub fn aggregation_params(&self) -> AggregationParams {
+        AggregationParams {
+            max_age: time::Duration::seconds(self.price_source_max_age_seconds as i64),
+            max_deviation_bps: self.price_source_max_deviation_bps,
+            min_sources: self.min_price_sources,
+        }
+    }
+
+    pub fn price_sources(&self) -> Result<Vec<PriceSourceConfig>> {
+        let Some(path) = &self.price_sources else {
+            return Ok(vec![]);
+        };
+
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read price sources from {}", path.display()))?;
+        let sources = serde_json::from_str(&config).context("Invalid price sources")?;
+
+        Ok(sources)
+    }
+
+    pub fn simulated_params(&self) -> SimulatedParams {
+        SimulatedParams {
+            initial_price: self.simulated_price,
+            spread: self.simulated_spread,
+            volatility_bps: self.simulated_volatility_bps,
+            quote_interval: Duration::from_millis(self.simulated_quote_interval_ms),
+            latency: Duration::from_millis(self.simulated_latency_ms),
+            fill_ratio: self.simulated_fill_ratio,
+            reject_rate: self.simulated_reject_rate,
+            seed: self.simulated_seed,
+        }
+    }
+
     pub fn get_oracle_info(&self) -> OracleInfo {
         OracleInfo {
             endpoint: self.oracle_endpoint.clone(),
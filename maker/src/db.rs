@@ -0,0 +1,19 @@
+use crate::position::ContractSymbol;
+use anyhow::bail;
+use anyhow::Result;
+
+pub mod fills;
+pub mod funding_payments;
+
+fn contract_symbol_to_sql(contract_symbol: ContractSymbol) -> String {
+    match contract_symbol {
+        ContractSymbol::BtcUsd => "BtcUsd".to_string(),
+    }
+}
+
+fn contract_symbol_from_sql(contract_symbol: &str) -> Result<ContractSymbol> {
+    match contract_symbol {
+        "BtcUsd" => Ok(ContractSymbol::BtcUsd),
+        _ => bail!("Unknown contract symbol: {contract_symbol}"),
+    }
+}
@@ -0,0 +1,91 @@
+use crate::accounting;
+use crate::db::contract_symbol_from_sql;
+use crate::db::contract_symbol_to_sql;
+use crate::schema::fills;
+use anyhow::Context;
+use anyhow::Result;
+use diesel::prelude::*;
+use diesel::Insertable;
+use diesel::PgConnection;
+use diesel::Queryable;
+use diesel::RunQueryDsl;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+#[derive(Queryable, Debug, Clone, PartialEq)]
+#[diesel(table_name = fills)]
+struct Fill {
+    #[allow(dead_code)]
+    id: i32,
+    venue: String,
+    execution_id: String,
+    contract_symbol: String,
+    contracts: f64,
+    price: f64,
+    fee_btc: f64,
+    timestamp: OffsetDateTime,
+}
+
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = fills)]
+struct NewFill {
+    venue: String,
+    execution_id: String,
+    contract_symbol: String,
+    contracts: f64,
+    price: f64,
+    fee_btc: f64,
+    timestamp: OffsetDateTime,
+}
+
+/// Inserts the fill, unless it has already been recorded.
+pub fn insert(conn: &mut PgConnection, fill: &accounting::Fill) -> Result<()> {
+    diesel::insert_into(fills::table)
+        .values(NewFill::from(fill))
+        .on_conflict((fills::venue, fills::execution_id))
+        .do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Returns all fills in the order they happened.
+pub fn get_all(conn: &mut PgConnection) -> Result<Vec<accounting::Fill>> {
+    let fills = fills::table
+        .order_by((fills::timestamp.asc(), fills::id.asc()))
+        .load::<Fill>(conn)?;
+
+    fills.into_iter().map(accounting::Fill::try_from).collect()
+}
+
+impl From<&accounting::Fill> for NewFill {
+    fn from(value: &accounting::Fill) -> Self {
+        Self {
+            venue: value.venue.to_string(),
+            execution_id: value.execution_id.clone(),
+            contract_symbol: contract_symbol_to_sql(value.contract_symbol),
+            contracts: value.contracts.to_f64().expect("contracts to fit into f64"),
+            price: value.price.to_f64().expect("price to fit into f64"),
+            fee_btc: value.fee.to_f64().expect("fee to fit into f64"),
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl TryFrom<Fill> for accounting::Fill {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Fill) -> Result<Self> {
+        Ok(Self {
+            venue: value.venue.parse()?,
+            execution_id: value.execution_id,
+            contract_symbol: contract_symbol_from_sql(&value.contract_symbol)?,
+            contracts: Decimal::from_f64(value.contracts).context("Invalid contracts")?,
+            price: Decimal::from_f64(value.price).context("Invalid price")?,
+            fee: Decimal::from_f64(value.fee_btc).context("Invalid fee")?,
+            timestamp: value.timestamp,
+        })
+    }
+}
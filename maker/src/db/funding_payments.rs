@@ -0,0 +1,85 @@
+use crate::accounting;
+use crate::db::contract_symbol_from_sql;
+use crate::db::contract_symbol_to_sql;
+use crate::schema::funding_payments;
+use anyhow::Context;
+use anyhow::Result;
+use diesel::prelude::*;
+use diesel::Insertable;
+use diesel::PgConnection;
+use diesel::Queryable;
+use diesel::RunQueryDsl;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+#[derive(Queryable, Debug, Clone, PartialEq)]
+#[diesel(table_name = funding_payments)]
+struct FundingPayment {
+    #[allow(dead_code)]
+    id: i32,
+    execution_id: String,
+    contract_symbol: String,
+    amount_btc: f64,
+    timestamp: OffsetDateTime,
+}
+
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = funding_payments)]
+struct NewFundingPayment {
+    execution_id: String,
+    contract_symbol: String,
+    amount_btc: f64,
+    timestamp: OffsetDateTime,
+}
+
+/// Inserts the funding payment, unless it has already been recorded.
+pub fn insert(conn: &mut PgConnection, funding_payment: &accounting::FundingPayment) -> Result<()> {
+    diesel::insert_into(funding_payments::table)
+        .values(NewFundingPayment::from(funding_payment))
+        .on_conflict(funding_payments::execution_id)
+        .do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Returns all funding payments in the order they happened.
+pub fn get_all(conn: &mut PgConnection) -> Result<Vec<accounting::FundingPayment>> {
+    let funding_payments = funding_payments::table
+        .order_by((
+            funding_payments::timestamp.asc(),
+            funding_payments::id.asc(),
+        ))
+        .load::<FundingPayment>(conn)?;
+
+    funding_payments
+        .into_iter()
+        .map(accounting::FundingPayment::try_from)
+        .collect()
+}
+
+impl From<&accounting::FundingPayment> for NewFundingPayment {
+    fn from(value: &accounting::FundingPayment) -> Self {
+        Self {
+            execution_id: value.execution_id.clone(),
+            contract_symbol: contract_symbol_to_sql(value.contract_symbol),
+            amount_btc: value.amount.to_f64().expect("amount to fit into f64"),
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl TryFrom<FundingPayment> for accounting::FundingPayment {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FundingPayment) -> Result<Self> {
+        Ok(Self {
+            execution_id: value.execution_id,
+            contract_symbol: contract_symbol_from_sql(&value.contract_symbol)?,
+            amount: Decimal::from_f64(value.amount_btc).context("Invalid amount")?,
+            timestamp: value.timestamp,
+        })
+    }
+}
@@ -1,3 +1,5 @@
+use crate::price_feed::SourceStatus;
+use crate::risk::QuotingStatus;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
@@ -5,6 +7,7 @@ use reqwest::Client;
 use reqwest::StatusCode;
 use reqwest::Url;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::time::Duration;
 use tokio::sync::watch;
 
@@ -31,6 +34,10 @@ pub struct Health {
     orderbook_rx: watch::Receiver<ServiceStatus>,
     /// Bitmex pricefeed stream status
     bitmex_pricefeed_rx: watch::Receiver<ServiceStatus>,
+    /// Status of every source of the reference price
+    price_sources_rx: watch::Receiver<BTreeMap<String, SourceStatus>>,
+    /// Whether the maker is quoting or has been paused or halted
+    quoting_rx: watch::Receiver<QuotingStatus>,
 }
 
 /// Transmitters that need to be plugged in the services that need to publish their health status.
@@ -38,6 +45,8 @@ pub struct Tx {
     pub orderbook: watch::Sender<ServiceStatus>,
     pub coordinator: watch::Sender<ServiceStatus>,
     pub bitmex_pricefeed: watch::Sender<ServiceStatus>,
+    pub price_sources: watch::Sender<BTreeMap<String, SourceStatus>>,
+    pub quoting: watch::Sender<QuotingStatus>,
 }
 
 /// Struct returned by maker's health endpoint.
@@ -46,6 +55,8 @@ pub struct OverallMakerHealth {
     coordinator: ServiceStatus,
     orderbook: ServiceStatus,
     bitmex_pricefeed: ServiceStatus,
+    price_sources: BTreeMap<String, SourceStatus>,
+    quoting: QuotingStatus,
 }
 
 impl OverallMakerHealth {
@@ -53,6 +64,7 @@ impl OverallMakerHealth {
         self.coordinator == ServiceStatus::Online
             && self.bitmex_pricefeed == ServiceStatus::Online
             && self.orderbook == ServiceStatus::Online
+            && !matches!(self.quoting, QuotingStatus::Halted { .. })
     }
 }
 
@@ -61,17 +73,23 @@ impl Health {
         let (orderbook_tx, orderbook_rx) = watch::channel(ServiceStatus::Unknown);
         let (coordinator_tx, coordinator_rx) = watch::channel(ServiceStatus::Unknown);
         let (bitmex_pricefeed_tx, bitmex_pricefeed_rx) = watch::channel(ServiceStatus::Unknown);
+        let (price_sources_tx, price_sources_rx) = watch::channel(BTreeMap::new());
+        let (quoting_tx, quoting_rx) = watch::channel(QuotingStatus::Active);
 
         (
             Self {
                 coordinator_rx,
                 orderbook_rx,
                 bitmex_pricefeed_rx,
+                price_sources_rx,
+                quoting_rx,
             },
             Tx {
                 orderbook: orderbook_tx,
                 coordinator: coordinator_tx,
                 bitmex_pricefeed: bitmex_pricefeed_tx,
+                price_sources: price_sources_tx,
+                quoting: quoting_tx,
             },
         )
     }
@@ -81,6 +99,8 @@ impl Health {
             coordinator: self.get_coordinator_status(),
             orderbook: self.get_orderbook_status(),
             bitmex_pricefeed: self.get_bitmex_pricefeed_status(),
+            price_sources: self.get_price_sources_status(),
+            quoting: self.get_quoting_status(),
         };
 
         match health_info.is_healthy() {
@@ -102,6 +122,14 @@ impl Health {
     pub fn get_bitmex_pricefeed_status(&self) -> ServiceStatus {
         *self.bitmex_pricefeed_rx.borrow()
     }
+
+    pub fn get_price_sources_status(&self) -> BTreeMap<String, SourceStatus> {
+        self.price_sources_rx.borrow().clone()
+    }
+
+    pub fn get_quoting_status(&self) -> QuotingStatus {
+        self.quoting_rx.borrow().clone()
+    }
 }
 
 /// Simple way of checking if a service is online or offline
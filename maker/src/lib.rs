@@ -6,16 +6,21 @@ use diesel_migrations::MigrationHarness;
 #[cfg(test)]
 mod tests;
 
+pub mod accounting;
 pub mod cli;
+pub mod db;
 pub mod health;
 pub mod ln;
 pub mod logger;
 pub mod metrics;
 pub mod orderbook_ws;
 pub mod position;
+pub mod price_feed;
+pub mod risk;
 pub mod routes;
 pub mod schema;
 pub mod trading;
+pub mod venue;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
@@ -1,10 +1,15 @@
+use crate::accounting::PnlReport;
 use crate::health::Health;
 use crate::health::ServiceStatus;
+use crate::price_feed::SourceStatus;
+use crate::risk::QuotingStatus;
 use lazy_static::lazy_static;
 use lightning::ln::channelmanager::ChannelDetails;
 use ln_dlc_node::node::InMemoryStore;
 use ln_dlc_node::node::Node;
 use opentelemetry::global;
+use opentelemetry::metrics::Counter;
+use opentelemetry::metrics::Histogram;
 use opentelemetry::metrics::Meter;
 use opentelemetry::metrics::ObservableGauge;
 use opentelemetry::sdk::export::metrics::aggregation;
@@ -14,6 +19,8 @@ use opentelemetry::sdk::metrics::selectors;
 use opentelemetry::Context;
 use opentelemetry::KeyValue;
 use opentelemetry_prometheus::PrometheusExporter;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -33,6 +40,18 @@ lazy_static! {
         .with_description("Bitmex pricefeed status")
         .init();
 
+    pub static ref PRICE_SOURCE_STATUS: ObservableGauge<u64> = METER.u64_observable_gauge("price_source_status")
+        .with_description("Status of a source of the reference price")
+        .init();
+
+    pub static ref QUOTING_STATUS: ObservableGauge<u64> = METER.u64_observable_gauge("quoting_status")
+        .with_description("Quoting status")
+        .init();
+
+    pub static ref RISK_LIMIT_BREACHES: ObservableGauge<u64> = METER.u64_observable_gauge("risk_limit_breaches")
+        .with_description("Number of breached risk limits")
+        .init();
+
     // channel details metrics
     pub static ref CHANNEL_BALANCE_SATOSHI: ObservableGauge<u64> = METER
         .u64_observable_gauge("channel_balance_satoshi")
@@ -60,6 +79,46 @@ lazy_static! {
         .u64_observable_gauge("node_balance_satoshi")
         .with_description("Node balance in satoshi")
         .init();
+
+    // hedging metrics
+    pub static ref HEDGE_CONTRACTS: Counter<u64> = METER
+        .u64_counter("hedge_contracts_total")
+        .with_description("Total number of contracts traded on the hedging venue")
+        .init();
+    pub static ref HEDGE_SLIPPAGE_BPS: Histogram<f64> = METER
+        .f64_histogram("hedge_slippage_bps")
+        .with_description("Slippage of hedging orders against the 10101 fill price in basis points")
+        .init();
+
+    // accounting metrics
+    pub static ref POSITION_CONTRACTS: ObservableGauge<f64> = METER
+        .f64_observable_gauge("position_contracts")
+        .with_description("Position in contracts, negative if short")
+        .init();
+    pub static ref REALIZED_PNL_BTC: ObservableGauge<f64> = METER
+        .f64_observable_gauge("realized_pnl_btc")
+        .with_description("Realized PnL in BTC")
+        .init();
+    pub static ref UNREALIZED_PNL_BTC: ObservableGauge<f64> = METER
+        .f64_observable_gauge("unrealized_pnl_btc")
+        .with_description("Unrealized PnL in BTC at the mark price")
+        .init();
+    pub static ref FEES_BTC: ObservableGauge<f64> = METER
+        .f64_observable_gauge("fees_btc")
+        .with_description("Total fees paid in BTC")
+        .init();
+    pub static ref FUNDING_BTC: ObservableGauge<f64> = METER
+        .f64_observable_gauge("funding_btc")
+        .with_description("Total funding paid in BTC")
+        .init();
+    pub static ref NET_PNL_BTC: ObservableGauge<f64> = METER
+        .f64_observable_gauge("net_pnl_btc")
+        .with_description("PnL across all venues in BTC after fees and funding")
+        .init();
+    pub static ref NET_PNL_USD: ObservableGauge<f64> = METER
+        .f64_observable_gauge("net_pnl_usd")
+        .with_description("PnL across all venues in USD after fees and funding")
+        .init();
 }
 
 pub fn init_meter() -> PrometheusExporter {
@@ -73,13 +132,16 @@ pub fn init_meter() -> PrometheusExporter {
     opentelemetry_prometheus::exporter(controller).init()
 }
 
-pub fn collect(node: Arc<Node<InMemoryStore>>, health: Health) {
+pub fn collect(node: Arc<Node<InMemoryStore>>, health: Health, pnl: Option<PnlReport>) {
     let cx = opentelemetry::Context::current();
 
     let channels = node.channel_manager.list_channels();
     channel_metrics(&cx, channels);
     node_metrics(&cx, node);
     health_metrics(&cx, &health);
+    if let Some(pnl) = pnl {
+        pnl_metrics(&cx, pnl);
+    }
 }
 
 fn health_metrics(cx: &Context, health: &Health) {
@@ -90,6 +152,27 @@ fn health_metrics(cx: &Context, health: &Health) {
         health.get_bitmex_pricefeed_status(),
         &BITMEX_PRICEFEED_STATUS,
     );
+    for (source, status) in health.get_price_sources_status() {
+        let value = match status {
+            SourceStatus::Offline => 0,
+            SourceStatus::Online => 1,
+            SourceStatus::Unknown => 2,
+            SourceStatus::Stale => 3,
+            SourceStatus::Outlier => 4,
+        };
+        PRICE_SOURCE_STATUS.observe(cx, value, &[KeyValue::new("source", source)]);
+    }
+    quoting_metrics(cx, health.get_quoting_status());
+}
+
+fn quoting_metrics(cx: &Context, quoting_status: QuotingStatus) {
+    let (value, breaches) = match quoting_status {
+        QuotingStatus::Halted { breaches } => (0, breaches.len()),
+        QuotingStatus::Active => (1, 0),
+        QuotingStatus::Paused => (2, 0),
+    };
+    QUOTING_STATUS.observe(cx, value, &[]);
+    RISK_LIMIT_BREACHES.observe(cx, breaches as u64, &[]);
 }
 
 /// Updates the health metric given a service status
@@ -102,6 +185,33 @@ fn update_health_metric(cx: &Context, service_status: ServiceStatus, gauge: &Obs
     gauge.observe(cx, value, &[]);
 }
 
+fn pnl_metrics(cx: &Context, pnl: PnlReport) {
+    for book in pnl.books {
+        let key_values = [
+            KeyValue::new("venue", book.venue.to_string()),
+            KeyValue::new("contract_symbol", format!("{:?}", book.contract_symbol)),
+        ];
+        POSITION_CONTRACTS.observe(cx, to_f64(book.contracts), &key_values);
+        REALIZED_PNL_BTC.observe(cx, to_f64(book.realized_pnl_btc), &key_values);
+        if let Some(unrealized_pnl_btc) = book.unrealized_pnl_btc {
+            UNREALIZED_PNL_BTC.observe(cx, to_f64(unrealized_pnl_btc), &key_values);
+        }
+        FEES_BTC.observe(cx, to_f64(book.fees_btc), &key_values);
+        FUNDING_BTC.observe(cx, to_f64(book.funding_btc), &key_values);
+    }
+
+    if let Some(net_pnl_btc) = pnl.net_pnl_btc {
+        NET_PNL_BTC.observe(cx, to_f64(net_pnl_btc), &[]);
+    }
+    if let Some(net_pnl_usd) = pnl.net_pnl_usd {
+        NET_PNL_USD.observe(cx, to_f64(net_pnl_usd), &[]);
+    }
+}
+
+fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().expect("decimal to fit into f64")
+}
+
 fn channel_metrics(cx: &Context, channels: Vec<ChannelDetails>) {
     for channel_detail in channels {
         let key_values = [
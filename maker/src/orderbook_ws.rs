@@ -162,12 +162,13 @@ async fn process_message(
 
             let orders = matches
                 .into_iter()
-                .map(|(order_id, contracts)| {
+                .map(|(order_id, contracts, price)| {
                     OrderTenTenOne::new(
                         order_id,
                         // TODO: Get `ContractSymbol` from the orderbook.
                         position::ContractSymbol::BtcUsd,
                         contracts,
+                        price,
                     )
                 })
                 .collect::<Vec<_>>();
@@ -190,13 +191,20 @@ async fn process_message(
         Message::InvalidAuthentication(e) => {
             tracing::error!("Orderbook authentication failed: {e}");
         }
+        Message::MatchAbandoned { order_id, reason } => {
+            tracing::warn!(%order_id, %reason, "Match abandoned");
+        }
         Message::AllOrders(_)
         | Message::NewOrder(_)
         | Message::DeleteOrder(_)
         | Message::Update(_)
         | Message::AsyncMatch { .. }
         | Message::Rollover { .. }
-        | Message::CollaborativeRevert { .. } => {
+        | Message::CollaborativeRevert { .. }
+        | Message::Trade(_)
+        | Message::MarketStats(_)
+        | Message::DepthSnapshot(_)
+        | Message::DepthUpdate(_) => {
             // Nothing to do.
         }
     }
@@ -1,5 +1,24 @@
+use crate::accounting;
+use crate::accounting::Accounting;
+use crate::accounting::PnlReport;
+use crate::db;
+use crate::position::execution::ExecutionParams;
+use crate::position::execution::Executor;
+use crate::position::execution::OrderNotCancelled;
+use crate::risk::Breach;
+use crate::risk::Exposure;
+use crate::risk::QuotingStatus;
+use crate::risk::RiskMonitor;
+use crate::venue::Fill;
+use crate::venue::Funding;
+use crate::venue::HedgingVenue;
+use crate::venue::Quote;
+use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use diesel::r2d2::ConnectionManager;
+use diesel::r2d2::Pool;
+use diesel::PgConnection;
 use hedging::derive_hedging_action;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
@@ -10,17 +29,36 @@ use std::collections::HashSet;
 use std::fmt::Display;
 use std::hash::Hash;
 use std::ops::Add;
+use std::sync::Arc;
 use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use tokio::task::spawn_blocking;
 use uuid::Uuid;
 use xtra::Mailbox;
 
 mod bitmex;
+pub mod execution;
 mod hedging;
 mod tentenone;
 
 pub struct Manager {
     position: Position,
-    bitmex_http_client: bitmex_client::client::Client,
+    executor: Arc<Executor>,
+    /// Whether a hedge is currently being executed. Only one hedge is executed at a time, so that
+    /// the next one can be derived from the outcome of the previous one.
+    is_hedging: bool,
+    /// The orders on the hedging venue which could not be cancelled and may still be filled. No
+    /// hedge is executed and quoting is halted until they have been cancelled.
+    unconfirmed_orders: HashSet<String>,
+    /// The 10101 fills which have not been hedged yet, as sums of contracts and notional value.
+    unhedged_fills: HashMap<ContractSymbol, (Decimal, Decimal)>,
+    risk: RiskMonitor,
+    /// Whether quoting has been paused manually.
+    is_paused: bool,
+    quoting_status: watch::Sender<QuotingStatus>,
+    accounting: Accounting,
+    pool: Pool<ConnectionManager<PgConnection>>,
 }
 
 #[async_trait]
@@ -41,6 +79,17 @@ impl xtra::Actor for Manager {
             }
         });
 
+        tokio::spawn({
+            let mailbox = mailbox.clone();
+            async move {
+                loop {
+                    let _ = mailbox.address().send(CheckRisk).await;
+
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        });
+
         Ok(())
     }
 
@@ -48,16 +97,85 @@ impl xtra::Actor for Manager {
 }
 
 impl Manager {
-    pub fn new(bitmex_http_client: bitmex_client::client::Client) -> Self {
+    pub fn new(
+        venue: Arc<dyn HedgingVenue>,
+        execution_params: ExecutionParams,
+        risk: RiskMonitor,
+        quoting_status: watch::Sender<QuotingStatus>,
+        accounting: Accounting,
+        pool: Pool<ConnectionManager<PgConnection>>,
+    ) -> Self {
         Self {
             position: Position::new(),
-            bitmex_http_client,
+            executor: Arc::new(Executor::new(venue, execution_params)),
+            is_hedging: false,
+            unconfirmed_orders: HashSet::new(),
+            unhedged_fills: HashMap::new(),
+            risk,
+            is_paused: false,
+            quoting_status,
+            accounting,
+            pool,
         }
     }
 
-    /// Adjust hedging on _BitMEX_ based on the balance between the [`bitmex::Position`] and the
-    /// [`tentenone::Position`].
-    async fn hedge(&self, contract_symbol: &ContractSymbol) {
+    /// Accounts for the fill and persists it, unless it has been recorded before.
+    fn record_fill(&mut self, fill: accounting::Fill) {
+        if !self.accounting.apply_fill(&fill) {
+            return;
+        }
+
+        self.risk.on_fill(fill.contracts, fill.price, fill.fee);
+
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let result = pool
+                .get()
+                .context("Failed to get connection")
+                .and_then(|mut conn| db::fills::insert(&mut conn, &fill));
+
+            if let Err(e) = result {
+                tracing::error!(?fill, "Failed to persist fill: {e:#}");
+            }
+        });
+    }
+
+    /// Accounts for the funding payment and persists it, unless it has been recorded before.
+    fn record_funding(&mut self, funding_payment: accounting::FundingPayment) {
+        if !self.accounting.apply_funding(&funding_payment) {
+            return;
+        }
+
+        self.risk.on_funding(funding_payment.amount);
+
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let result = pool
+                .get()
+                .context("Failed to get connection")
+                .and_then(|mut conn| db::funding_payments::insert(&mut conn, &funding_payment));
+
+            if let Err(e) = result {
+                tracing::error!(?funding_payment, "Failed to persist funding payment: {e:#}");
+            }
+        });
+    }
+
+    /// Adjust hedging on the [`HedgingVenue`] based on the balance between the
+    /// [`bitmex::Position`] and the [`tentenone::Position`].
+    ///
+    /// The hedge is executed in the background, reporting back with [`HedgeExecuted`].
+    fn hedge(&mut self, contract_symbol: &ContractSymbol, address: xtra::Address<Self>) {
+        if self.is_hedging {
+            tracing::debug!("Skipping hedging while previous hedge is being executed");
+            return;
+        }
+
+        if !self.unconfirmed_orders.is_empty() {
+            self.cancel_unconfirmed_orders(address);
+            return;
+        }
+
         let tentenone = self.position.get_tentenone(contract_symbol);
 
         // For the purposes of hedging we have to round to the number of 10101 contracts to the
@@ -71,39 +189,133 @@ impl Manager {
 
         let action = derive_hedging_action(tentenone, bitmex);
 
-        if let Err(e) = self.create_bitmex_order(&action, contract_symbol).await {
-            tracing::error!(
-                ?action,
-                "Failed to create order on BitMEX based on required hedging action: {e:#}"
-            )
+        let contracts = action.contracts();
+        if contracts == 0 {
+            return;
         }
-    }
 
-    async fn create_bitmex_order(
-        &self,
-        action: &hedging::Action,
-        contract_symbol: &ContractSymbol,
-    ) -> Result<()> {
-        let (contracts, side) = match action.contracts() {
-            0 => return Ok(()),
-            n @ 1..=i32::MAX => (n.abs(), bitmex_client::models::Side::Buy),
-            n @ i32::MIN..=-1 => (n.abs(), bitmex_client::models::Side::Sell),
-        };
+        let reference_price = self
+            .unhedged_fills
+            .remove(contract_symbol)
+            .and_then(|(contracts, notional)| (!contracts.is_zero()).then(|| notional / contracts));
 
         tracing::info!(
             ?action,
-            "Creating BitMEX order based on required hedging action"
+            ?reference_price,
+            "Executing hedge based on required hedging action"
         );
 
-        let contract_symbol = match contract_symbol {
-            ContractSymbol::BtcUsd => bitmex_client::models::ContractSymbol::XbtUsd,
+        self.is_hedging = true;
+
+        let executor = self.executor.clone();
+        let contract_symbol = *contract_symbol;
+        tokio::spawn(async move {
+            let mut unconfirmed_orders = vec![];
+            match executor
+                .execute(contract_symbol.into(), contracts, reference_price)
+                .await
+            {
+                Ok(executed) => {
+                    tracing::info!(?action, executed, "Executed hedge");
+                }
+                Err(e) => {
+                    tracing::error!(
+                        ?action,
+                        "Failed to execute hedge based on required hedging action: {e:#}"
+                    );
+
+                    if let Some(OrderNotCancelled { order_id }) =
+                        e.downcast_ref::<OrderNotCancelled>()
+                    {
+                        unconfirmed_orders.push(order_id.clone());
+                    }
+                }
+            }
+
+            let _ = address.send(HedgeExecuted { unconfirmed_orders }).await;
+        });
+    }
+
+    /// Tries once more to cancel the orders which could not be cancelled while hedging.
+    ///
+    /// The outcome is reported back with [`HedgeExecuted`], like a hedge.
+    fn cancel_unconfirmed_orders(&mut self, address: xtra::Address<Self>) {
+        tracing::info!(orders = ?self.unconfirmed_orders, "Cancelling unconfirmed hedging orders");
+
+        self.is_hedging = true;
+
+        let executor = self.executor.clone();
+        let orders = self.unconfirmed_orders.clone();
+        tokio::spawn(async move {
+            let mut unconfirmed_orders = vec![];
+            for order_id in orders {
+                match executor.cancel_order(&order_id).await {
+                    Ok(ack) => tracing::info!(?ack, "Cancelled unconfirmed hedging order"),
+                    Err(e) => {
+                        tracing::error!(%order_id, "Failed to cancel unconfirmed hedging order: {e:#}");
+                        unconfirmed_orders.push(order_id);
+                    }
+                }
+            }
+
+            let _ = address.send(HedgeExecuted { unconfirmed_orders }).await;
+        });
+    }
+
+    /// Checks the [`RiskLimits`](crate::risk::RiskLimits) and publishes the resulting [`QuotingStatus`].
+    ///
+    /// Quoting is halted for as long as any of the limits is breached.
+    fn check_risk(&mut self) -> QuotingStatus {
+        // TODO: Check the exposure for all `ContractSymbol` enum variants.
+        let contract_symbol = ContractSymbol::BtcUsd;
+
+        let contracts = self.position.get_tentenone(&contract_symbol);
+        let tentenone = contracts
+            .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+            .to_i32()
+            .expect("10101 position to fit in i32");
+        let bitmex = self.position.get_bitmex(&contract_symbol);
+
+        let exposure = Exposure {
+            contract_symbol,
+            contracts,
+            is_unhedged: derive_hedging_action(tentenone, bitmex).contracts() != 0,
         };
 
-        self.bitmex_http_client
-            .create_order(contract_symbol, contracts, side, None)
-            .await?;
+        let mut breaches = self.risk.check(&[exposure], OffsetDateTime::now_utc());
+        breaches.extend(self.unconfirmed_orders.iter().map(|order_id| {
+            Breach::UnconfirmedHedgingOrder {
+                order_id: order_id.clone(),
+            }
+        }));
 
-        Ok(())
+        let status = if !breaches.is_empty() {
+            QuotingStatus::Halted { breaches }
+        } else if self.is_paused {
+            QuotingStatus::Paused
+        } else {
+            QuotingStatus::Active
+        };
+
+        self.quoting_status.send_if_modified(|current| {
+            if *current == status {
+                return false;
+            }
+
+            match &status {
+                QuotingStatus::Halted { breaches } => {
+                    let breaches = breaches.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+                    tracing::warn!(?breaches, "Risk limits breached, halting quoting");
+                }
+                QuotingStatus::Paused => tracing::info!("Quoting paused"),
+                QuotingStatus::Active => tracing::info!("Quoting active"),
+            }
+
+            *current = status.clone();
+            true
+        });
+
+        status
     }
 }
 
@@ -117,14 +329,22 @@ pub struct OrderTenTenOne {
     ///
     /// The sign determines the direction: positive is long; negative is short.
     contracts: Decimal,
+    /// The price at which the order was filled.
+    price: Decimal,
 }
 
 impl OrderTenTenOne {
-    pub fn new(order_id: Uuid, contract_symbol: ContractSymbol, contracts: Decimal) -> Self {
+    pub fn new(
+        order_id: Uuid,
+        contract_symbol: ContractSymbol,
+        contracts: Decimal,
+        price: Decimal,
+    ) -> Self {
         Self {
             order_id,
             contract_symbol,
             contracts,
+            price,
         }
     }
 }
@@ -140,8 +360,32 @@ pub struct GetPositionResponse {
     pub tentenone: HashMap<ContractSymbol, Decimal>,
 }
 
+/// The best bid and ask on the [`HedgingVenue`].
+pub struct VenueQuote(pub Quote);
+
+/// A fill of one of our orders on the [`HedgingVenue`].
+pub struct VenueFill(pub Fill);
+
+/// A funding payment for our position on the [`HedgingVenue`].
+pub struct VenueFunding(pub Funding);
+
+pub struct GetPnl;
+
+/// Stop quoting until [`ResumeQuoting`] is received.
+pub struct PauseQuoting;
+
+/// Resume quoting after [`PauseQuoting`]. Quoting stays halted while any risk limit is breached.
+pub struct ResumeQuoting;
+
 struct Hedge;
 
+struct CheckRisk;
+
+struct HedgeExecuted {
+    /// The orders which have been left open on the hedging venue.
+    unconfirmed_orders: Vec<String>,
+}
+
 #[async_trait]
 impl xtra::Handler<PositionUpdateTenTenOne> for Manager {
     type Return = ();
@@ -155,10 +399,29 @@ impl xtra::Handler<PositionUpdateTenTenOne> for Manager {
             order_id,
             contract_symbol,
             contracts,
+            price,
         } in update.0
         {
-            self.position
+            let is_new = self
+                .position
                 .update_tentenone(contract_symbol, order_id, contracts);
+
+            self.record_fill(accounting::Fill {
+                venue: accounting::Venue::TenTenOne,
+                execution_id: order_id.to_string(),
+                contract_symbol,
+                contracts,
+                price,
+                fee: Decimal::ZERO,
+                timestamp: OffsetDateTime::now_utc(),
+            });
+
+            if is_new {
+                let (unhedged_contracts, unhedged_notional) =
+                    self.unhedged_fills.entry(contract_symbol).or_default();
+                *unhedged_contracts += contracts.abs();
+                *unhedged_notional += contracts.abs() * price;
+            }
         }
     }
 }
@@ -198,9 +461,109 @@ impl xtra::Handler<GetPosition> for Manager {
 impl xtra::Handler<Hedge> for Manager {
     type Return = ();
 
-    async fn handle(&mut self, _: Hedge, _: &mut xtra::Context<Self>) -> Self::Return {
+    async fn handle(&mut self, _: Hedge, ctx: &mut xtra::Context<Self>) -> Self::Return {
         // TODO(lucas): Hedge for all `ContractSymbol` enum variants.
-        self.hedge(&ContractSymbol::BtcUsd).await;
+        self.hedge(&ContractSymbol::BtcUsd, ctx.mailbox().address());
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<HedgeExecuted> for Manager {
+    type Return = ();
+
+    async fn handle(&mut self, msg: HedgeExecuted, _: &mut xtra::Context<Self>) -> Self::Return {
+        self.is_hedging = false;
+        self.unconfirmed_orders = HashSet::from_iter(msg.unconfirmed_orders);
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<VenueQuote> for Manager {
+    type Return = ();
+
+    async fn handle(&mut self, quote: VenueQuote, _: &mut xtra::Context<Self>) -> Self::Return {
+        let quote = quote.0;
+        let mid = (quote.bid() + quote.ask()) / Decimal::TWO;
+
+        self.risk.on_price(mid, quote.timestamp);
+        self.accounting.update_mark_price(mid);
+        self.executor.update_quote(quote);
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<VenueFill> for Manager {
+    type Return = ();
+
+    async fn handle(&mut self, fill: VenueFill, _: &mut xtra::Context<Self>) -> Self::Return {
+        let fill = fill.0;
+
+        self.record_fill(accounting::Fill {
+            venue: accounting::Venue::Hedging,
+            execution_id: fill.execution_id.clone(),
+            contract_symbol: fill.contract_symbol.into(),
+            contracts: Decimal::from(fill.contracts),
+            price: fill.price,
+            fee: fill.fee,
+            timestamp: fill.timestamp,
+        });
+        self.executor.notify_fill(fill);
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<VenueFunding> for Manager {
+    type Return = ();
+
+    async fn handle(&mut self, funding: VenueFunding, _: &mut xtra::Context<Self>) -> Self::Return {
+        let funding = funding.0;
+
+        tracing::info!(?funding, "Funding paid on hedging venue");
+
+        self.record_funding(accounting::FundingPayment {
+            execution_id: funding.execution_id,
+            contract_symbol: funding.contract_symbol.into(),
+            amount: funding.amount,
+            timestamp: funding.timestamp,
+        });
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<GetPnl> for Manager {
+    type Return = PnlReport;
+
+    async fn handle(&mut self, _: GetPnl, _: &mut xtra::Context<Self>) -> Self::Return {
+        self.accounting.report()
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<CheckRisk> for Manager {
+    type Return = ();
+
+    async fn handle(&mut self, _: CheckRisk, _: &mut xtra::Context<Self>) -> Self::Return {
+        self.check_risk();
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<PauseQuoting> for Manager {
+    type Return = QuotingStatus;
+
+    async fn handle(&mut self, _: PauseQuoting, _: &mut xtra::Context<Self>) -> Self::Return {
+        self.is_paused = true;
+        self.check_risk()
+    }
+}
+
+#[async_trait]
+impl xtra::Handler<ResumeQuoting> for Manager {
+    type Return = QuotingStatus;
+
+    async fn handle(&mut self, _: ResumeQuoting, _: &mut xtra::Context<Self>) -> Self::Return {
+        self.is_paused = false;
+        self.check_risk()
     }
 }
 
@@ -219,21 +582,24 @@ impl Position {
         }
     }
 
+    /// Returns whether the order is new.
     fn update_tentenone(
         &mut self,
         contract_symbol: ContractSymbol,
         order_id: Uuid,
         contracts: Decimal,
-    ) {
+    ) -> bool {
         let mut position = self
             .tentenone
             .get(&contract_symbol)
             .cloned()
             .unwrap_or(tentenone::Position::new(ContractSymbol::BtcUsd));
 
-        position.update(order_id, contracts);
+        let is_new = position.update(order_id, contracts);
 
         self.tentenone.replace(position);
+
+        is_new
     }
 
     fn update_bitmex(&mut self, contract_symbol: ContractSymbol, contracts: i32) {
@@ -267,7 +633,7 @@ impl Position {
     }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize)]
 pub enum ContractSymbol {
     BtcUsd,
 }
@@ -280,6 +646,14 @@ impl From<trade::ContractSymbol> for ContractSymbol {
     }
 }
 
+impl From<ContractSymbol> for trade::ContractSymbol {
+    fn from(value: ContractSymbol) -> Self {
+        match value {
+            ContractSymbol::BtcUsd => Self::BtcUsd,
+        }
+    }
+}
+
 /// The number of contracts in the position, including their direction.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Contracts {
@@ -0,0 +1,722 @@
+use crate::metrics::HEDGE_CONTRACTS;
+use crate::metrics::HEDGE_SLIPPAGE_BPS;
+use crate::venue::Fill;
+use crate::venue::HedgeOrder;
+use crate::venue::HedgeOrderType;
+use crate::venue::HedgingVenue;
+use crate::venue::OrderAck;
+use crate::venue::Quote;
+use anyhow::Result;
+use opentelemetry::KeyValue;
+use parking_lot::Mutex;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use trade::ContractSymbol;
+
+/// The smallest number of contracts which can be traded on the hedging venue.
+pub const LOT_SIZE: i32 = 100;
+
+/// The window of the per-minute size cap.
+const BUDGET_WINDOW: Duration = Duration::from_secs(60);
+
+/// The number of fills buffered while waiting for a limit order to be filled.
+const FILL_BUFFER_SIZE: usize = 100;
+
+/// How long a market order may take to be filled before the remainder is cancelled.
+const MARKET_ORDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the state of an order is loaded while waiting for it to be closed.
+const ORDER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The maximum time between two attempts to cancel an order.
+const MAX_CANCEL_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long we try to cancel an order before giving up on it.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionParams {
+    pub policy: ExecutionPolicy,
+    /// The maximum number of contracts per order. Larger hedges are split into slices.
+    pub max_order_contracts: i32,
+    /// The maximum number of contracts traded within any minute.
+    pub max_contracts_per_minute: i32,
+    /// The time between two slices of the same hedge.
+    pub slice_interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecutionPolicy {
+    /// Every slice is sent as a market order.
+    Market,
+    /// Every slice is sent as a post-only limit order, pegged to the best bid when buying and to
+    /// the best ask when selling. Whatever has not been filled after `fallback_after` is sent as
+    /// a market order.
+    PostOnly { fallback_after: Duration },
+}
+
+/// Executes hedges on the [`HedgingVenue`] according to the [`ExecutionParams`].
+pub struct Executor {
+    venue: Arc<dyn HedgingVenue>,
+    params: ExecutionParams,
+    budget: Mutex<MinuteBudget>,
+    quotes: watch::Sender<Option<Quote>>,
+    fills: broadcast::Sender<Fill>,
+}
+
+/// An order on the hedging venue which could not be cancelled within [`CANCEL_TIMEOUT`].
+///
+/// The order may still be filled, hence no further hedge must be executed until its state has
+/// been reconciled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderNotCancelled {
+    pub order_id: String,
+}
+
+impl fmt::Display for OrderNotCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hedging order {} could not be cancelled", self.order_id)
+    }
+}
+
+impl std::error::Error for OrderNotCancelled {}
+
+/// An order on the hedging venue which has been (partially) filled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ExecutedOrder {
+    order_type: &'static str,
+    /// The filled contracts, with the sign representing the direction: positive buy; negative
+    /// sell.
+    contracts: i32,
+    average_price: Option<Decimal>,
+}
+
+impl Executor {
+    pub fn new(venue: Arc<dyn HedgingVenue>, params: ExecutionParams) -> Self {
+        let (quotes, _) = watch::channel(None);
+        let (fills, _) = broadcast::channel(FILL_BUFFER_SIZE);
+
+        Self {
+            venue,
+            params,
+            budget: Mutex::new(MinuteBudget::new(params.max_contracts_per_minute)),
+            quotes,
+            fills,
+        }
+    }
+
+    /// Updates the best bid and ask limit orders are pegged to.
+    pub fn update_quote(&self, quote: Quote) {
+        self.quotes.send_replace(Some(quote));
+    }
+
+    /// Notifies pending limit orders about fills on the venue.
+    pub fn notify_fill(&self, fill: Fill) {
+        // Sending only fails if no limit order is waiting for fills.
+        let _ = self.fills.send(fill);
+    }
+
+    /// Trades `contracts` on the venue, in slices respecting the size caps.
+    ///
+    /// The slippage of every order is measured against `reference_price`, the average price of
+    /// the 10101 fills which are being hedged.
+    ///
+    /// Returns the number of contracts traded, which may be less than `contracts` if orders could
+    /// not be filled. Fails with [`OrderNotCancelled`] if an order has been left open on the venue.
+    pub async fn execute(
+        &self,
+        contract_symbol: ContractSymbol,
+        contracts: i32,
+        reference_price: Option<Decimal>,
+    ) -> Result<i32> {
+        let mut remaining = contracts;
+
+        loop {
+            let available = self.wait_for_budget().await;
+            let slice = next_slice(remaining, self.params.max_order_contracts, available);
+            if slice == 0 {
+                break;
+            }
+
+            tracing::info!(slice, remaining, "Executing hedge slice");
+
+            let orders = match self.params.policy {
+                ExecutionPolicy::Market => self.market(contract_symbol, slice).await?,
+                ExecutionPolicy::PostOnly { fallback_after } => {
+                    self.post_only(contract_symbol, slice, fallback_after)
+                        .await?
+                }
+            };
+
+            let filled = orders.iter().map(|order| order.contracts).sum::<i32>();
+            self.budget.lock().record(Instant::now(), filled);
+
+            for order in orders {
+                record_metrics(order, reference_price);
+            }
+
+            // If nothing was filled, retrying immediately is not going to help.
+            if filled == 0 {
+                break;
+            }
+
+            remaining -= filled;
+            if remaining == 0 {
+                break;
+            }
+
+            tokio::time::sleep(self.params.slice_interval).await;
+        }
+
+        Ok(contracts - remaining)
+    }
+
+    /// Waits until at least one lot may be traded without exceeding the per-minute cap, returning
+    /// the number of contracts which may be traded.
+    async fn wait_for_budget(&self) -> i32 {
+        loop {
+            let (available, next_release) = {
+                let mut budget = self.budget.lock();
+                let available = budget.available(Instant::now());
+                (available, budget.next_release())
+            };
+
+            match next_release {
+                Some(next_release) if available < LOT_SIZE => {
+                    tracing::debug!("Waiting for per-minute hedging cap");
+                    tokio::time::sleep_until(next_release).await;
+                }
+                _ => return available,
+            }
+        }
+    }
+
+    async fn market(
+        &self,
+        contract_symbol: ContractSymbol,
+        contracts: i32,
+    ) -> Result<Vec<ExecutedOrder>> {
+        let ack = self
+            .venue
+            .place_order(HedgeOrder {
+                contract_symbol,
+                contracts,
+                order_type: HedgeOrderType::Market,
+            })
+            .await?;
+
+        let ack = self.close_order(ack, MARKET_ORDER_TIMEOUT).await?;
+
+        Ok(executed_order("market", ack).into_iter().collect())
+    }
+
+    /// Waits for the order to be closed by the venue, cancelling it after `timeout`.
+    ///
+    /// The venue may acknowledge an order before it has been filled. Returning early would let
+    /// the order be filled after the hedge has been reported as executed, hedging twice.
+    async fn close_order(&self, mut ack: OrderAck, timeout: Duration) -> Result<OrderAck> {
+        let deadline = Instant::now() + timeout;
+
+        while ack.is_open && Instant::now() < deadline {
+            tokio::time::sleep(ORDER_POLL_INTERVAL).await;
+
+            match self.venue.get_order(&ack.order_id).await {
+                Ok(order) => ack = order,
+                Err(e) => {
+                    tracing::warn!(order_id = %ack.order_id, "Failed to load hedging order: {e:#}")
+                }
+            }
+        }
+
+        if !ack.is_open {
+            return Ok(ack);
+        }
+
+        self.cancel_order(&ack.order_id).await
+    }
+
+    /// Cancels the order, returning its final state.
+    ///
+    /// Retries until the venue confirms that the order is closed, so that we never leave a live
+    /// order behind which could be filled without us knowing. Gives up after [`CANCEL_TIMEOUT`]
+    /// with [`OrderNotCancelled`], leaving it to the caller to reconcile the order.
+    pub async fn cancel_order(&self, order_id: &str) -> Result<OrderAck> {
+        let deadline = Instant::now() + CANCEL_TIMEOUT;
+        let mut backoff = ORDER_POLL_INTERVAL;
+
+        loop {
+            match self.venue.cancel_order(order_id).await {
+                Ok(ack) if !ack.is_open => return Ok(ack),
+                Ok(_) => tracing::warn!(%order_id, "Hedging order still open after cancelling"),
+                Err(e) => tracing::warn!(%order_id, "Failed to cancel hedging order: {e:#}"),
+            }
+
+            if Instant::now() + backoff > deadline {
+                tracing::error!(%order_id, timeout = ?CANCEL_TIMEOUT, "Giving up on cancelling hedging order");
+
+                return Err(OrderNotCancelled {
+                    order_id: order_id.to_string(),
+                }
+                .into());
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_CANCEL_BACKOFF);
+        }
+    }
+
+    /// Pegs a post-only limit order to the book until it is filled or `fallback_after` has passed.
+    /// The order is replaced whenever the best price moves away from it. The remainder is sent as
+    /// a market order.
+    async fn post_only(
+        &self,
+        contract_symbol: ContractSymbol,
+        contracts: i32,
+        fallback_after: Duration,
+    ) -> Result<Vec<ExecutedOrder>> {
+        let deadline = Instant::now() + fallback_after;
+        let mut quotes = self.quotes.subscribe();
+
+        let mut orders = vec![];
+        let mut remaining = contracts;
+
+        while remaining != 0 && Instant::now() < deadline {
+            let price = match peg_price(*quotes.borrow_and_update(), remaining) {
+                Some(price) => price,
+                None => {
+                    tracing::warn!("No quote to peg hedging order to");
+                    break;
+                }
+            };
+
+            // We subscribe before placing the order so that we do not miss any fills.
+            let mut fills = self.fills.subscribe();
+
+            let ack = match self
+                .venue
+                .place_order(HedgeOrder {
+                    contract_symbol,
+                    contracts: remaining,
+                    order_type: HedgeOrderType::PostOnlyLimit { price },
+                })
+                .await
+            {
+                Ok(ack) => ack,
+                Err(e) => {
+                    tracing::warn!("Failed to place post-only hedging order: {e:#}");
+                    break;
+                }
+            };
+
+            let mut filled = ack.filled_contracts;
+            while filled != remaining {
+                tokio::select! {
+                    fill = fills.recv() => match fill {
+                        Ok(fill) if fill.order_id == ack.order_id => filled += fill.contracts,
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    changed = quotes.changed() => {
+                        let new_price = peg_price(*quotes.borrow(), remaining);
+                        if changed.is_err() || new_price != Some(price) {
+                            tracing::debug!(%price, "Best price moved away from hedging order");
+                            break;
+                        }
+                    }
+                    _ = tokio::time::sleep_until(deadline) => break,
+                }
+            }
+
+            // The final state of the order is authoritative, as it may have been filled while we
+            // were cancelling it.
+            let ack = self.cancel_order(&ack.order_id).await?;
+
+            remaining -= ack.filled_contracts;
+            orders.extend(executed_order("limit", ack));
+        }
+
+        if remaining != 0 {
+            tracing::info!(remaining, "Falling back to market order for hedge");
+
+            orders.extend(self.market(contract_symbol, remaining).await?);
+        }
+
+        Ok(orders)
+    }
+}
+
+/// Keeps track of the contracts traded within the last minute.
+#[derive(Debug)]
+struct MinuteBudget {
+    max_contracts: i32,
+    traded: VecDeque<(Instant, i32)>,
+}
+
+impl MinuteBudget {
+    fn new(max_contracts: i32) -> Self {
+        Self {
+            max_contracts,
+            traded: VecDeque::new(),
+        }
+    }
+
+    /// The number of contracts which may be traded at `now`.
+    fn available(&mut self, now: Instant) -> i32 {
+        while let Some((traded_at, _)) = self.traded.front() {
+            if now.duration_since(*traded_at) < BUDGET_WINDOW {
+                break;
+            }
+
+            self.traded.pop_front();
+        }
+
+        let traded = self
+            .traded
+            .iter()
+            .map(|(_, contracts)| contracts)
+            .sum::<i32>();
+        (self.max_contracts - traded).max(0)
+    }
+
+    /// When the oldest trade within the window stops counting towards the cap.
+    fn next_release(&self) -> Option<Instant> {
+        self.traded
+            .front()
+            .map(|(traded_at, _)| *traded_at + BUDGET_WINDOW)
+    }
+
+    fn record(&mut self, now: Instant, contracts: i32) {
+        if contracts != 0 {
+            self.traded.push_back((now, contracts.abs()));
+        }
+    }
+}
+
+/// The signed number of contracts of the next slice, given the `remaining` contracts to trade,
+/// the per-order cap and the `available` per-minute budget.
+fn next_slice(remaining: i32, max_order_contracts: i32, available: i32) -> i32 {
+    let contracts = remaining.abs().min(max_order_contracts).min(available);
+    let contracts = contracts - contracts % LOT_SIZE;
+
+    contracts * remaining.signum()
+}
+
+/// The price a post-only order is pegged to: the best bid when buying, the best ask when selling.
+fn peg_price(quote: Option<Quote>, contracts: i32) -> Option<Decimal> {
+    let quote = quote?;
+
+    Some(if contracts.is_positive() {
+        quote.bid()
+    } else {
+        quote.ask()
+    })
+}
+
+fn executed_order(order_type: &'static str, ack: OrderAck) -> Option<ExecutedOrder> {
+    (ack.filled_contracts != 0).then_some(ExecutedOrder {
+        order_type,
+        contracts: ack.filled_contracts,
+        average_price: ack.average_price,
+    })
+}
+
+/// The slippage of a hedge in basis points of the `reference_price`. Positive if the hedge was
+/// executed at a worse price than the reference, i.e. buying higher or selling lower.
+fn slippage_bps(contracts: i32, price: Decimal, reference_price: Decimal) -> Decimal {
+    let slippage = (price - reference_price) / reference_price * Decimal::from(10_000);
+
+    if contracts.is_positive() {
+        slippage
+    } else {
+        -slippage
+    }
+}
+
+fn record_metrics(order: ExecutedOrder, reference_price: Option<Decimal>) {
+    let cx = opentelemetry::Context::current();
+    let attributes = [KeyValue::new("order_type", order.order_type)];
+
+    HEDGE_CONTRACTS.add(&cx, order.contracts.unsigned_abs() as u64, &attributes);
+
+    match (order.average_price, reference_price) {
+        (Some(price), Some(reference_price)) if !reference_price.is_zero() => {
+            let slippage = slippage_bps(order.contracts, price, reference_price);
+
+            tracing::info!(?order, %reference_price, %slippage, "Executed hedging order");
+
+            HEDGE_SLIPPAGE_BPS.record(
+                &cx,
+                slippage.to_f64().expect("slippage to fit into f64"),
+                &attributes,
+            );
+        }
+        _ => tracing::info!(?order, "Executed hedging order"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::venue::simulated::SimulatedExchange;
+    use crate::venue::simulated::SimulatedParams;
+    use crate::venue::Position;
+    use crate::venue::VenueEvent;
+    use anyhow::bail;
+    use async_trait::async_trait;
+    use futures::stream::BoxStream;
+    use rust_decimal_macros::dec;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn slices_respect_caps_and_lot_size() {
+        assert_eq!(next_slice(1_000, 300, 5_000), 300);
+        assert_eq!(next_slice(-1_000, 300, 5_000), -300);
+        assert_eq!(next_slice(-250, 1_000, 5_000), -200);
+        assert_eq!(next_slice(1_000, 1_000, 450), 400);
+        assert_eq!(next_slice(1_000, 1_000, 50), 0);
+    }
+
+    #[test]
+    fn budget_is_released_after_a_minute() {
+        let start = Instant::now();
+        let mut budget = MinuteBudget::new(1_000);
+
+        budget.record(start, -600);
+        budget.record(start + Duration::from_secs(30), 300);
+
+        assert_eq!(budget.available(start + Duration::from_secs(59)), 100);
+        assert_eq!(budget.available(start + Duration::from_secs(60)), 700);
+        assert_eq!(budget.next_release(), Some(start + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn slippage_is_positive_when_hedge_is_worse() {
+        assert_eq!(slippage_bps(100, dec!(30_030), dec!(30_000)), dec!(10));
+        assert_eq!(slippage_bps(-100, dec!(30_030), dec!(30_000)), dec!(-10));
+        assert_eq!(slippage_bps(-100, dec!(29_970), dec!(30_000)), dec!(10));
+    }
+
+    fn simulated_exchange() -> Arc<SimulatedExchange> {
+        Arc::new(SimulatedExchange::new(simulated_params()))
+    }
+
+    fn simulated_params() -> SimulatedParams {
+        SimulatedParams {
+            initial_price: dec!(30_000),
+            spread: dec!(1),
+            volatility_bps: Decimal::ZERO,
+            quote_interval: Duration::from_millis(10),
+            latency: Duration::ZERO,
+            fill_ratio: Decimal::ONE,
+            reject_rate: 0.0,
+            seed: 0,
+        }
+    }
+
+    fn market_params() -> ExecutionParams {
+        ExecutionParams {
+            policy: ExecutionPolicy::Market,
+            max_order_contracts: 1_000,
+            max_contracts_per_minute: 1_000,
+            slice_interval: Duration::ZERO,
+        }
+    }
+
+    /// A [`SimulatedExchange`] which acknowledges market orders before reporting them as filled
+    /// and fails to cancel orders a number of times.
+    struct FlakyVenue {
+        exchange: SimulatedExchange,
+        ack_market_orders_as_open: bool,
+        failing_cancels: AtomicUsize,
+        placed_orders: AtomicUsize,
+    }
+
+    impl FlakyVenue {
+        fn new(ack_market_orders_as_open: bool, failing_cancels: usize) -> Arc<Self> {
+            Arc::new(Self {
+                exchange: SimulatedExchange::new(simulated_params()),
+                ack_market_orders_as_open,
+                failing_cancels: AtomicUsize::new(failing_cancels),
+                placed_orders: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl HedgingVenue for FlakyVenue {
+        async fn place_order(&self, order: HedgeOrder) -> Result<OrderAck> {
+            self.placed_orders.fetch_add(1, Ordering::SeqCst);
+
+            let ack = self.exchange.place_order(order).await?;
+            match order.order_type {
+                HedgeOrderType::Market if self.ack_market_orders_as_open => Ok(OrderAck {
+                    filled_contracts: 0,
+                    average_price: None,
+                    is_open: true,
+                    ..ack
+                }),
+                _ => Ok(ack),
+            }
+        }
+
+        async fn cancel_order(&self, order_id: &str) -> Result<OrderAck> {
+            let failing = self
+                .failing_cancels
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                bail!("Simulated cancel failure");
+            }
+
+            self.exchange.cancel_order(order_id).await
+        }
+
+        async fn get_order(&self, order_id: &str) -> Result<OrderAck> {
+            self.exchange.get_order(order_id).await
+        }
+
+        async fn get_positions(&self) -> Result<Vec<Position>> {
+            self.exchange.get_positions().await
+        }
+
+        async fn subscribe(&self) -> BoxStream<'static, Result<VenueEvent>> {
+            self.exchange.subscribe().await
+        }
+    }
+
+    #[tokio::test]
+    async fn splits_hedge_into_slices() {
+        let venue = simulated_exchange();
+        let executor = Executor::new(
+            venue.clone(),
+            ExecutionParams {
+                policy: ExecutionPolicy::Market,
+                max_order_contracts: 200,
+                max_contracts_per_minute: 1_000,
+                slice_interval: Duration::ZERO,
+            },
+        );
+
+        let executed = executor
+            .execute(ContractSymbol::BtcUsd, -500, None)
+            .await
+            .unwrap();
+
+        assert_eq!(executed, -500);
+        assert_eq!(venue.get_positions().await.unwrap()[0].contracts, -500);
+    }
+
+    #[tokio::test]
+    async fn unfilled_post_only_order_falls_back_to_market() {
+        let venue = simulated_exchange();
+        let executor = Executor::new(
+            venue.clone(),
+            ExecutionParams {
+                policy: ExecutionPolicy::PostOnly {
+                    fallback_after: Duration::from_millis(50),
+                },
+                max_order_contracts: 1_000,
+                max_contracts_per_minute: 1_000,
+                slice_interval: Duration::ZERO,
+            },
+        );
+        executor.update_quote(Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid: dec!(29_999.5),
+            ask: dec!(30_000.5),
+            timestamp: time::OffsetDateTime::now_utc(),
+        });
+
+        let executed = executor
+            .execute(ContractSymbol::BtcUsd, 300, None)
+            .await
+            .unwrap();
+
+        assert_eq!(executed, 300);
+        assert_eq!(venue.get_positions().await.unwrap()[0].contracts, 300);
+    }
+
+    #[tokio::test]
+    async fn waits_for_market_order_to_be_filled() {
+        let venue = FlakyVenue::new(true, 0);
+        let executor = Executor::new(venue.clone(), market_params());
+
+        let executed = executor
+            .execute(ContractSymbol::BtcUsd, 300, None)
+            .await
+            .unwrap();
+
+        assert_eq!(executed, 300);
+        assert_eq!(venue.placed_orders.load(Ordering::SeqCst), 1);
+        assert_eq!(venue.get_positions().await.unwrap()[0].contracts, 300);
+    }
+
+    #[tokio::test]
+    async fn retries_cancelling_post_only_order() {
+        let venue = FlakyVenue::new(false, 2);
+        let executor = Executor::new(
+            venue.clone(),
+            ExecutionParams {
+                policy: ExecutionPolicy::PostOnly {
+                    fallback_after: Duration::from_millis(50),
+                },
+                ..market_params()
+            },
+        );
+        executor.update_quote(Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid: dec!(29_999.5),
+            ask: dec!(30_000.5),
+            timestamp: time::OffsetDateTime::now_utc(),
+        });
+
+        let executed = executor
+            .execute(ContractSymbol::BtcUsd, 300, None)
+            .await
+            .unwrap();
+
+        // The limit order is only given up on once it has been cancelled, falling back to a
+        // single market order.
+        assert_eq!(executed, 300);
+        assert_eq!(venue.failing_cancels.load(Ordering::SeqCst), 0);
+        assert_eq!(venue.placed_orders.load(Ordering::SeqCst), 2);
+        assert_eq!(venue.get_positions().await.unwrap()[0].contracts, 300);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_cancelling_order_after_timeout() {
+        let venue = FlakyVenue::new(false, usize::MAX);
+        let executor = Executor::new(
+            venue.clone(),
+            ExecutionParams {
+                policy: ExecutionPolicy::PostOnly {
+                    fallback_after: Duration::from_millis(50),
+                },
+                ..market_params()
+            },
+        );
+        executor.update_quote(Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid: dec!(29_999.5),
+            ask: dec!(30_000.5),
+            timestamp: time::OffsetDateTime::now_utc(),
+        });
+
+        let error = executor
+            .execute(ContractSymbol::BtcUsd, 300, None)
+            .await
+            .unwrap_err();
+
+        // No market order is sent while the limit order may still be filled.
+        assert!(error.downcast_ref::<OrderNotCancelled>().is_some());
+        assert_eq!(venue.placed_orders.load(Ordering::SeqCst), 1);
+    }
+}
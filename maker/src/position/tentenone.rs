@@ -26,10 +26,11 @@ impl Position {
         }
     }
 
-    pub fn update(&mut self, order_id: Uuid, contracts: Decimal) {
+    /// Returns whether the order is new.
+    pub fn update(&mut self, order_id: Uuid, contracts: Decimal) -> bool {
         let before = self.contracts();
 
-        match self.orders.insert(order_id, contracts) {
+        let is_new = match self.orders.insert(order_id, contracts) {
             Some(old_contracts) if old_contracts != contracts => {
                 tracing::warn!(
                     %order_id,
@@ -37,15 +38,18 @@ impl Position {
                     new_contracts = %contracts,
                     "Updated 10101 contracts for existing order"
                 );
+
+                false
             }
             Some(_) => {
                 // Inconsequential update.
-                return;
+                return false;
             }
             None => {
                 // New order.
+                true
             }
-        }
+        };
 
         let after = self.contracts();
 
@@ -56,6 +60,8 @@ impl Position {
             %order_id,
             "Updated 10101 position"
         );
+
+        is_new
     }
 
     pub(super) fn contracts(&self) -> Contracts {
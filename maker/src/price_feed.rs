@@ -0,0 +1,464 @@
+use crate::trading::quoting::ReferencePrice;
+use crate::venue::Quote;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+
+pub mod rest;
+pub mod websocket;
+
+/// How often the price feed checks whether its sources have become stale.
+const STALENESS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A source of reference prices other than the hedging venue, e.g. another exchange.
+#[async_trait]
+pub trait PriceSource: Send + Sync + 'static {
+    /// The name of the source, unique among all sources.
+    fn name(&self) -> &str;
+
+    /// Subscribes to the best bid and ask of the source.
+    ///
+    /// The stream yields an error if the connection to the source is lost. It is up to the caller
+    /// to resubscribe.
+    async fn subscribe(&self) -> BoxStream<'static, Result<Quote>>;
+}
+
+/// The configuration of a [`PriceSource`], as read from the file passed with `--price-sources`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceSourceConfig {
+    Rest(rest::RestSource),
+    Websocket(websocket::WebsocketSource),
+}
+
+impl PriceSourceConfig {
+    pub fn into_source(self) -> Arc<dyn PriceSource> {
+        match self {
+            PriceSourceConfig::Rest(source) => Arc::new(source),
+            PriceSourceConfig::Websocket(source) => Arc::new(source),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AggregationParams {
+    /// How old the latest quote of a source may be for the source to be considered.
+    pub max_age: time::Duration,
+    /// The maximum distance of the mid price of a source from the median mid price of all
+    /// sources in basis points. Sources further away are ignored as outliers.
+    pub max_deviation_bps: Decimal,
+    /// The minimum number of sources needed to compute a reference price.
+    pub min_sources: usize,
+}
+
+/// The status of a price source, as exposed by the maker's health endpoint.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SourceStatus {
+    /// No quote has been received yet.
+    #[default]
+    Unknown,
+    /// The source is part of the reference price.
+    Online,
+    /// The latest quote of the source is too old.
+    Stale,
+    /// The mid price of the source is too far from the other sources.
+    Outlier,
+    /// The connection to the source has been lost.
+    Offline,
+}
+
+/// Aggregates the quotes of several sources into a single [`ReferencePrice`].
+///
+/// The reference bid and ask are the medians of the bids and asks of all sources which are
+/// neither stale nor outliers. With only two sources which disagree by more than twice the
+/// maximum deviation both are outliers, as it is impossible to tell which one is right.
+#[derive(Debug)]
+pub struct Aggregator {
+    params: AggregationParams,
+    sources: BTreeMap<String, SourceState>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SourceState {
+    /// The latest quote of the source and when it was received.
+    latest: Option<(Quote, OffsetDateTime)>,
+    is_connected: bool,
+}
+
+impl Aggregator {
+    pub fn new<'a>(params: AggregationParams, sources: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            params,
+            sources: sources
+                .into_iter()
+                .map(|source| (source.to_string(), SourceState::default()))
+                .collect(),
+        }
+    }
+
+    pub fn on_quote(&mut self, source: &str, quote: Quote, now: OffsetDateTime) {
+        let state = self.sources.entry(source.to_string()).or_default();
+        state.latest = Some((quote, now));
+        state.is_connected = true;
+    }
+
+    pub fn on_disconnected(&mut self, source: &str) {
+        if let Some(state) = self.sources.get_mut(source) {
+            state.is_connected = false;
+        }
+    }
+
+    /// Computes the reference price and the status of every source.
+    pub fn aggregate(
+        &self,
+        now: OffsetDateTime,
+    ) -> (Option<ReferencePrice>, BTreeMap<String, SourceStatus>) {
+        let mut statuses = BTreeMap::new();
+        let mut fresh = vec![];
+
+        for (source, state) in &self.sources {
+            let status = match state.latest {
+                _ if !state.is_connected && state.latest.is_some() => SourceStatus::Offline,
+                None => SourceStatus::Unknown,
+                Some((_, received_at)) if now - received_at > self.params.max_age => {
+                    SourceStatus::Stale
+                }
+                Some((quote, _)) if quote.bid <= Decimal::ZERO || quote.ask < quote.bid => {
+                    tracing::debug!(%source, ?quote, "Ignoring crossed or empty quote");
+                    SourceStatus::Outlier
+                }
+                Some((quote, _)) => {
+                    fresh.push((source, quote));
+                    SourceStatus::Online
+                }
+            };
+
+            statuses.insert(source.clone(), status);
+        }
+
+        let Some(median_mid) = median(fresh.iter().map(|(_, quote)| mid(quote)).collect()) else {
+            return (None, statuses);
+        };
+
+        let accepted = fresh
+            .into_iter()
+            .filter(|(source, quote)| {
+                let deviation_bps = (mid(quote) - median_mid).abs() / median_mid * dec!(10_000);
+                if deviation_bps > self.params.max_deviation_bps {
+                    tracing::debug!(%source, %deviation_bps, "Ignoring outlier");
+                    statuses.insert(source.to_string(), SourceStatus::Outlier);
+                    return false;
+                }
+
+                true
+            })
+            .map(|(_, quote)| quote)
+            .collect::<Vec<_>>();
+
+        if accepted.len() < self.params.min_sources.max(1) {
+            return (None, statuses);
+        }
+
+        let bid = median(accepted.iter().map(|quote| quote.bid).collect());
+        let ask = median(accepted.iter().map(|quote| quote.ask).collect());
+
+        let reference = bid.zip(ask).map(|(bid, ask)| ReferencePrice { bid, ask });
+
+        (reference, statuses)
+    }
+}
+
+fn mid(quote: &Quote) -> Decimal {
+    (quote.bid + quote.ask) / Decimal::TWO
+}
+
+fn median(mut values: Vec<Decimal>) -> Option<Decimal> {
+    values.sort();
+
+    let middle = values.len() / 2;
+    match values.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(values[middle]),
+        _ => Some((values[middle - 1] + values[middle]) / Decimal::TWO),
+    }
+}
+
+enum SourceEvent {
+    Quote { source: String, quote: Quote },
+    Disconnected { source: String },
+}
+
+/// Feeds the quotes of the hedging venue into the price feed, which are not subscribed to by the
+/// price feed itself but forwarded by [`crate::trading::run`].
+#[derive(Clone)]
+pub struct PriceFeed {
+    hedging_venue: String,
+    tx: mpsc::UnboundedSender<SourceEvent>,
+}
+
+impl PriceFeed {
+    pub fn on_venue_quote(&self, quote: Quote) {
+        let _ = self.tx.send(SourceEvent::Quote {
+            source: self.hedging_venue.clone(),
+            quote,
+        });
+    }
+
+    pub fn on_venue_disconnected(&self) {
+        let _ = self.tx.send(SourceEvent::Disconnected {
+            source: self.hedging_venue.clone(),
+        });
+    }
+}
+
+/// Spawns the price feed, subscribing to all `sources`.
+///
+/// Besides the `sources`, the price feed expects the quotes of the `hedging_venue` through the
+/// returned [`PriceFeed`]. The reference price is published on `reference_tx` whenever a quote is
+/// received, and the status of all sources on `status_tx` whenever it changes.
+pub fn spawn(
+    params: AggregationParams,
+    sources: Vec<Arc<dyn PriceSource>>,
+    hedging_venue: &str,
+    reference_tx: watch::Sender<Option<ReferencePrice>>,
+    status_tx: watch::Sender<BTreeMap<String, SourceStatus>>,
+    reconnect_after: Duration,
+) -> PriceFeed {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let names = sources
+        .iter()
+        .map(|source| source.name())
+        .chain([hedging_venue]);
+    let mut aggregator = Aggregator::new(params, names);
+
+    for source in sources {
+        tokio::spawn(forward_quotes(source, tx.clone(), reconnect_after));
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(STALENESS_CHECK_INTERVAL);
+
+        loop {
+            let is_quote = tokio::select! {
+                event = rx.recv() => match event {
+                    Some(SourceEvent::Quote { source, quote }) => {
+                        tracing::trace!(%source, ?quote, "Received quote");
+                        aggregator.on_quote(&source, quote, OffsetDateTime::now_utc());
+                        true
+                    }
+                    Some(SourceEvent::Disconnected { source }) => {
+                        aggregator.on_disconnected(&source);
+                        false
+                    }
+                    None => return,
+                },
+                _ = interval.tick() => false,
+            };
+
+            let (reference, statuses) = aggregator.aggregate(OffsetDateTime::now_utc());
+
+            // Every quote is published, so that the quotes are refreshed even if the market has
+            // not moved.
+            if is_quote {
+                reference_tx.send_replace(reference);
+            } else {
+                reference_tx.send_if_modified(|current| {
+                    let is_modified = *current != reference;
+                    *current = reference;
+                    is_modified
+                });
+            }
+
+            status_tx.send_if_modified(|current| {
+                let is_modified = *current != statuses;
+                *current = statuses;
+                is_modified
+            });
+        }
+    });
+
+    PriceFeed {
+        hedging_venue: hedging_venue.to_string(),
+        tx,
+    }
+}
+
+async fn forward_quotes(
+    source: Arc<dyn PriceSource>,
+    tx: mpsc::UnboundedSender<SourceEvent>,
+    reconnect_after: Duration,
+) {
+    let name = source.name().to_string();
+
+    loop {
+        let mut stream = source.subscribe().await;
+
+        loop {
+            match stream.next().await {
+                Some(Ok(quote)) => {
+                    let event = SourceEvent::Quote {
+                        source: name.clone(),
+                        quote,
+                    };
+                    if tx.send(event).is_err() {
+                        return;
+                    }
+                }
+                Some(Err(e)) => {
+                    tracing::warn!(source = %name, "Price source failed: {e:#}");
+                    break;
+                }
+                None => {
+                    tracing::warn!(source = %name, "Price source closed");
+                    break;
+                }
+            }
+        }
+
+        let _ = tx.send(SourceEvent::Disconnected {
+            source: name.clone(),
+        });
+
+        tokio::time::sleep(reconnect_after).await;
+    }
+}
+
+/// Reads a price from the JSON `value` at the JSON `pointer`, e.g. `/result/XXBTZUSD/b/0`.
+///
+/// Exchanges represent prices as numbers or as strings, both are supported.
+fn price_at(value: &Value, pointer: &str) -> Option<Decimal> {
+    match value.pointer(pointer)? {
+        Value::Number(number) => Decimal::from_str(&number.to_string())
+            .or_else(|_| Decimal::from_scientific(&number.to_string()))
+            .ok(),
+        Value::String(string) => Decimal::from_str(string).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+    use trade::ContractSymbol;
+
+    fn params() -> AggregationParams {
+        AggregationParams {
+            max_age: time::Duration::seconds(90),
+            max_deviation_bps: dec!(50),
+            min_sources: 2,
+        }
+    }
+
+    fn quote(bid: Decimal, ask: Decimal) -> Quote {
+        Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid,
+            ask,
+            timestamp: datetime!(2023-10-01 12:00 UTC),
+        }
+    }
+
+    #[test]
+    fn reference_price_is_median_of_sources() {
+        let now = datetime!(2023-10-01 12:00 UTC);
+        let mut aggregator = Aggregator::new(params(), ["bitmex", "kraken", "bitstamp"]);
+
+        aggregator.on_quote("bitmex", quote(dec!(30_000), dec!(30_002)), now);
+        aggregator.on_quote("kraken", quote(dec!(30_010), dec!(30_011)), now);
+        aggregator.on_quote("bitstamp", quote(dec!(30_004), dec!(30_008)), now);
+
+        let (reference, statuses) = aggregator.aggregate(now);
+
+        assert_eq!(
+            reference,
+            Some(ReferencePrice {
+                bid: dec!(30_004),
+                ask: dec!(30_008)
+            })
+        );
+        assert!(statuses
+            .values()
+            .all(|status| *status == SourceStatus::Online));
+    }
+
+    #[test]
+    fn outliers_and_stale_sources_are_ignored() {
+        let now = datetime!(2023-10-01 12:00 UTC);
+        let mut aggregator = Aggregator::new(params(), ["bitmex", "kraken", "bitstamp", "other"]);
+
+        aggregator.on_quote("bitmex", quote(dec!(30_000), dec!(30_002)), now);
+        aggregator.on_quote("kraken", quote(dec!(30_010), dec!(30_012)), now);
+        // 1% away from the others.
+        aggregator.on_quote("bitstamp", quote(dec!(30_300), dec!(30_302)), now);
+        aggregator.on_quote(
+            "other",
+            quote(dec!(29_000), dec!(29_002)),
+            now - time::Duration::minutes(5),
+        );
+
+        let (reference, statuses) = aggregator.aggregate(now);
+
+        assert_eq!(
+            reference,
+            Some(ReferencePrice {
+                bid: dec!(30_005),
+                ask: dec!(30_007)
+            })
+        );
+        assert_eq!(statuses["bitmex"], SourceStatus::Online);
+        assert_eq!(statuses["kraken"], SourceStatus::Online);
+        assert_eq!(statuses["bitstamp"], SourceStatus::Outlier);
+        assert_eq!(statuses["other"], SourceStatus::Stale);
+    }
+
+    #[test]
+    fn no_reference_price_without_enough_sources() {
+        let now = datetime!(2023-10-01 12:00 UTC);
+        let mut aggregator = Aggregator::new(params(), ["bitmex", "kraken"]);
+
+        aggregator.on_quote("bitmex", quote(dec!(30_000), dec!(30_002)), now);
+        aggregator.on_quote("kraken", quote(dec!(30_010), dec!(30_012)), now);
+        aggregator.on_disconnected("kraken");
+
+        let (reference, statuses) = aggregator.aggregate(now);
+
+        assert_eq!(reference, None);
+        assert_eq!(statuses["kraken"], SourceStatus::Offline);
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let sources: Vec<PriceSourceConfig> =
+            serde_json::from_str(include_str!("../price_sources.example.json")).unwrap();
+
+        let names = sources
+            .into_iter()
+            .map(|config| config.into_source().name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["kraken", "bitstamp"]);
+    }
+
+    #[test]
+    fn prices_can_be_numbers_or_strings() {
+        let value = serde_json::json!({"result": {"b": ["30000.5", 1], "a": [30001.5, 2]}});
+
+        assert_eq!(price_at(&value, "/result/b/0"), Some(dec!(30_000.5)));
+        assert_eq!(price_at(&value, "/result/a/0"), Some(dec!(30_001.5)));
+        assert_eq!(price_at(&value, "/result/c/0"), None);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::price_feed::price_at;
+use crate::price_feed::PriceSource;
+use crate::venue::Quote;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use time::OffsetDateTime;
+use trade::ContractSymbol;
+
+/// A price source polling a REST API, e.g. `https://api.kraken.com/0/public/Ticker?pair=XBTUSD`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RestSource {
+    pub name: String,
+    pub url: String,
+    /// The JSON pointer to the best bid in the response, e.g. `/result/XXBTZUSD/b/0`.
+    pub bid: String,
+    /// The JSON pointer to the best ask in the response, e.g. `/result/XXBTZUSD/a/0`.
+    pub ask: String,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64,
+}
+
+fn default_interval_seconds() -> u64 {
+    5
+}
+
+#[async_trait]
+impl PriceSource for RestSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn subscribe(&self) -> BoxStream<'static, Result<Quote>> {
+        let source = self.clone();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build reqwest client");
+
+        let stream = stream! {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(source.interval_seconds.max(1)));
+
+            loop {
+                interval.tick().await;
+
+                match source.poll(&client).await {
+                    Ok(quote) => yield Ok(quote),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+        };
+
+        stream.boxed()
+    }
+}
+
+impl RestSource {
+    async fn poll(&self, client: &reqwest::Client) -> Result<Quote> {
+        let response = client
+            .get(&self.url)
+            .send()
+            .await
+            .context("Failed to request price")?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let response: Value = serde_json::from_str(&response).context("Invalid JSON response")?;
+
+        let bid = price_at(&response, &self.bid).ok_or_else(|| anyhow!("No bid in response"))?;
+        let ask = price_at(&response, &self.ask).ok_or_else(|| anyhow!("No ask in response"))?;
+
+        Ok(Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid,
+            ask,
+            timestamp: OffsetDateTime::now_utc(),
+        })
+    }
+}
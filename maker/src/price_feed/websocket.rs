@@ -0,0 +1,133 @@
+use crate::price_feed::price_at;
+use crate::price_feed::PriceSource;
+use crate::venue::Quote;
+use anyhow::anyhow;
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio_tungstenite::tungstenite;
+use trade::ContractSymbol;
+
+/// The connection is considered lost if no message has been received for this long.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A price source streaming over a websocket, e.g. `wss://ws.bitstamp.net`.
+///
+/// Messages without a bid or ask, e.g. heartbeats, are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WebsocketSource {
+    pub name: String,
+    pub url: String,
+    /// The message sent after connecting, e.g. to subscribe to a channel.
+    pub subscribe: Option<Value>,
+    /// The JSON pointer to the best bid in a message, e.g. `/data/bids/0/0`.
+    pub bid: String,
+    /// The JSON pointer to the best ask in a message, e.g. `/data/asks/0/0`.
+    pub ask: String,
+}
+
+#[async_trait]
+impl PriceSource for WebsocketSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn subscribe(&self) -> BoxStream<'static, Result<Quote>> {
+        let source = self.clone();
+
+        let stream = stream! {
+            let connection = tokio_tungstenite::connect_async(source.url.as_str()).await;
+            let (mut connection, _) = match connection {
+                Ok(connection) => connection,
+                Err(e) => {
+                    yield Err(anyhow!("Could not connect to {}: {e}", source.url));
+                    return;
+                }
+            };
+
+            tracing::info!(source = %source.name, "Connected to price source");
+
+            if let Some(subscribe) = &source.subscribe {
+                let message = tungstenite::Message::Text(subscribe.to_string());
+                if let Err(e) = connection.send(message).await {
+                    yield Err(anyhow!("Could not subscribe: {e}"));
+                    return;
+                }
+            }
+
+            loop {
+                let message = match tokio::time::timeout(TIMEOUT, connection.next()).await {
+                    Ok(Some(Ok(message))) => message,
+                    Ok(Some(Err(e))) => {
+                        yield Err(anyhow!(e));
+                        return;
+                    }
+                    Ok(None) => {
+                        yield Err(anyhow!("Connection closed"));
+                        return;
+                    }
+                    Err(_) => {
+                        yield Err(anyhow!("No message received within {TIMEOUT:?}"));
+                        return;
+                    }
+                };
+
+                let tungstenite::Message::Text(text) = message else {
+                    continue;
+                };
+
+                if let Some(quote) = source.parse(&text) {
+                    yield Ok(quote);
+                }
+            }
+        };
+
+        stream.boxed()
+    }
+}
+
+impl WebsocketSource {
+    fn parse(&self, text: &str) -> Option<Quote> {
+        let message: Value = serde_json::from_str(text).ok()?;
+
+        Some(Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid: price_at(&message, &self.bid)?,
+            ask: price_at(&message, &self.ask)?,
+            timestamp: OffsetDateTime::now_utc(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn can_parse_order_book_message() {
+        let source: WebsocketSource = serde_json::from_str(
+            r#"{"name":"bitstamp","url":"wss://ws.bitstamp.net","subscribe":{"event":"bts:subscribe","data":{"channel":"order_book_btcusd"}},"bid":"/data/bids/0/0","ask":"/data/asks/0/0"}"#,
+        )
+        .unwrap();
+
+        let quote = source
+            .parse(r#"{"data":{"timestamp":"1696527405","bids":[["27440","0.5"]],"asks":[["27441.5","0.1"]]},"channel":"order_book_btcusd","event":"data"}"#)
+            .unwrap();
+        assert_eq!(quote.bid, dec!(27440));
+        assert_eq!(quote.ask, dec!(27441.5));
+
+        assert!(source
+            .parse(
+                r#"{"event":"bts:subscription_succeeded","channel":"order_book_btcusd","data":{}}"#
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,421 @@
+use crate::accounting::Fill;
+use crate::accounting::FundingPayment;
+use crate::position::ContractSymbol;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use time::Date;
+use time::Duration;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskLimits {
+    /// The maximum absolute 10101 position in contracts per contract symbol.
+    pub max_net_exposure: Decimal,
+    /// How long the 10101 position may differ from the position on the hedging venue.
+    pub max_unhedged_duration: Duration,
+    /// The maximum loss in BTC since the start of the current UTC day.
+    pub max_daily_loss: Decimal,
+    /// How old the latest price from the hedging venue may be.
+    pub max_price_age: Duration,
+}
+
+/// Whether the maker is quoting on the 10101 orderbook.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum QuotingStatus {
+    #[default]
+    Active,
+    /// Quoting has been paused manually.
+    Paused,
+    /// Quoting has been stopped because at least one risk limit is breached.
+    Halted { breaches: Vec<Breach> },
+}
+
+impl QuotingStatus {
+    pub fn is_active(&self) -> bool {
+        matches!(self, QuotingStatus::Active)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "limit", rename_all = "snake_case")]
+pub enum Breach {
+    NetExposure {
+        contract_symbol: ContractSymbol,
+        contracts: Decimal,
+    },
+    UnhedgedExposure {
+        contract_symbol: ContractSymbol,
+        unhedged_seconds: i64,
+    },
+    DailyLoss {
+        loss_btc: Decimal,
+    },
+    StalePrice {
+        age_seconds: i64,
+    },
+    /// An order on the hedging venue could not be cancelled and may still be filled.
+    UnconfirmedHedgingOrder {
+        order_id: String,
+    },
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breach::NetExposure {
+                contract_symbol,
+                contracts,
+            } => write!(
+                f,
+                "Net exposure of {contracts} {contract_symbol:?} contracts"
+            ),
+            Breach::UnhedgedExposure {
+                contract_symbol,
+                unhedged_seconds,
+            } => write!(f, "{contract_symbol:?} unhedged for {unhedged_seconds}s"),
+            Breach::DailyLoss { loss_btc } => write!(f, "Daily loss of {loss_btc} BTC"),
+            Breach::StalePrice { age_seconds } => write!(f, "Latest price is {age_seconds}s old"),
+            Breach::UnconfirmedHedgingOrder { order_id } => {
+                write!(f, "Hedging order {order_id} could not be cancelled")
+            }
+        }
+    }
+}
+
+/// The exposure of the maker for one contract symbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    pub contract_symbol: ContractSymbol,
+    /// The 10101 position in contracts. Positive if the maker is long, negative if the maker is
+    /// short.
+    pub contracts: Decimal,
+    /// Whether the position on the hedging venue does not match the 10101 position.
+    pub is_unhedged: bool,
+}
+
+/// Checks the [`RiskLimits`] against the state of the maker.
+#[derive(Debug)]
+pub struct RiskMonitor {
+    limits: RiskLimits,
+    /// The latest mid price of the hedging venue and when it was received.
+    last_price: (Option<Decimal>, OffsetDateTime),
+    unhedged_since: HashMap<ContractSymbol, OffsetDateTime>,
+    pnl: Pnl,
+    /// The current UTC day and the PnL at its start.
+    day: Option<(Date, Decimal)>,
+    /// The day of startup and the PnL of the fills and funding payments recorded before it.
+    history: Option<(Date, Pnl)>,
+}
+
+/// The PnL of all fills of inverse contracts, on 10101 and on the hedging venue, net of fees and
+/// funding.
+///
+/// A position of `q` contracts entered at price `p` is worth `q * (1/p - 1/m)` BTC at price `m`.
+/// Summed over all fills, the PnL is `cash - contracts / m - costs`, with `cash` being the sum of
+/// `q/p` and `costs` the sum of all fees and funding payments.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Pnl {
+    contracts: Decimal,
+    cash: Decimal,
+    costs: Decimal,
+}
+
+impl Pnl {
+    fn add_fill(&mut self, contracts: Decimal, price: Decimal, fee: Decimal) {
+        self.costs += fee;
+
+        if price.is_zero() {
+            return;
+        }
+
+        self.contracts += contracts;
+        self.cash += contracts / price;
+    }
+
+    fn add_funding(&mut self, amount: Decimal) {
+        self.costs += amount;
+    }
+
+    fn at(&self, price: Decimal) -> Decimal {
+        self.cash - self.contracts / price - self.costs
+    }
+}
+
+impl RiskMonitor {
+    pub fn new(limits: RiskLimits, now: OffsetDateTime) -> Self {
+        Self {
+            limits,
+            // The price is only considered stale after `max_price_age` has passed since startup.
+            last_price: (None, now),
+            unhedged_since: HashMap::new(),
+            pnl: Pnl::default(),
+            day: None,
+            history: None,
+        }
+    }
+
+    /// Creates a monitor which accounts for the fills and funding payments recorded before
+    /// startup, so that a loss made earlier on the current day counts towards its limit.
+    ///
+    /// The position carried into the current day is valued at the first price received after
+    /// startup, as we do not know the price at the start of the day.
+    pub fn from_history(
+        limits: RiskLimits,
+        fills: &[Fill],
+        funding_payments: &[FundingPayment],
+        now: OffsetDateTime,
+    ) -> Self {
+        let mut monitor = Self::new(limits, now);
+
+        let today = now.date();
+        let mut before_today = Pnl::default();
+        for fill in fills {
+            monitor.on_fill(fill.contracts, fill.price, fill.fee);
+            if fill.timestamp.date() < today {
+                before_today.add_fill(fill.contracts, fill.price, fill.fee);
+            }
+        }
+
+        for funding_payment in funding_payments {
+            monitor.on_funding(funding_payment.amount);
+            if funding_payment.timestamp.date() < today {
+                before_today.add_funding(funding_payment.amount);
+            }
+        }
+
+        monitor.history = Some((today, before_today));
+        monitor
+    }
+
+    pub fn on_price(&mut self, mid: Decimal, now: OffsetDateTime) {
+        self.last_price = (Some(mid), now);
+    }
+
+    /// Records a fill on 10101 or on the hedging venue. Positive `contracts` for buys, negative
+    /// for sells.
+    pub fn on_fill(&mut self, contracts: Decimal, price: Decimal, fee: Decimal) {
+        self.pnl.add_fill(contracts, price, fee);
+    }
+
+    /// Records a funding payment on the hedging venue. Negative if the maker received funding.
+    pub fn on_funding(&mut self, amount: Decimal) {
+        self.pnl.add_funding(amount);
+    }
+
+    /// Returns all risk limits which are currently breached.
+    pub fn check(&mut self, exposures: &[Exposure], now: OffsetDateTime) -> Vec<Breach> {
+        let mut breaches = vec![];
+
+        for exposure in exposures {
+            if exposure.contracts.abs() > self.limits.max_net_exposure {
+                breaches.push(Breach::NetExposure {
+                    contract_symbol: exposure.contract_symbol,
+                    contracts: exposure.contracts,
+                });
+            }
+
+            if !exposure.is_unhedged {
+                self.unhedged_since.remove(&exposure.contract_symbol);
+                continue;
+            }
+
+            let unhedged_since = *self
+                .unhedged_since
+                .entry(exposure.contract_symbol)
+                .or_insert(now);
+
+            let unhedged_for = now - unhedged_since;
+            if unhedged_for > self.limits.max_unhedged_duration {
+                breaches.push(Breach::UnhedgedExposure {
+                    contract_symbol: exposure.contract_symbol,
+                    unhedged_seconds: unhedged_for.whole_seconds(),
+                });
+            }
+        }
+
+        let (price, received_at) = self.last_price;
+
+        let price_age = now - received_at;
+        if price_age > self.limits.max_price_age {
+            breaches.push(Breach::StalePrice {
+                age_seconds: price_age.whole_seconds(),
+            });
+        }
+
+        if let Some(price) = price {
+            let loss = -self.daily_pnl(price, now);
+            if loss > self.limits.max_daily_loss {
+                breaches.push(Breach::DailyLoss { loss_btc: loss });
+            }
+        }
+
+        breaches
+    }
+
+    /// The PnL since the start of the current UTC day.
+    fn daily_pnl(&mut self, price: Decimal, now: OffsetDateTime) -> Decimal {
+        let pnl = self.pnl.at(price);
+        let today = now.date();
+
+        let start_of_day_pnl = match self.day {
+            Some((day, start_of_day_pnl)) if day == today => start_of_day_pnl,
+            _ => {
+                let start_of_day_pnl = match self.history.take() {
+                    Some((day, before_today)) if day == today => before_today.at(price),
+                    _ => pnl,
+                };
+
+                self.day = Some((today, start_of_day_pnl));
+                start_of_day_pnl
+            }
+        };
+
+        pnl - start_of_day_pnl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    fn limits() -> RiskLimits {
+        RiskLimits {
+            max_net_exposure: dec!(10_000),
+            max_unhedged_duration: Duration::minutes(5),
+            max_daily_loss: dec!(0.01),
+            max_price_age: Duration::minutes(3),
+        }
+    }
+
+    fn exposure(contracts: Decimal, is_unhedged: bool) -> Exposure {
+        Exposure {
+            contract_symbol: ContractSymbol::BtcUsd,
+            contracts,
+            is_unhedged,
+        }
+    }
+
+    #[test]
+    fn net_exposure_is_limited() {
+        let now = datetime!(2023-10-01 12:00 UTC);
+        let mut monitor = RiskMonitor::new(limits(), now);
+
+        assert!(monitor
+            .check(&[exposure(dec!(-10_000), false)], now)
+            .is_empty());
+        assert_eq!(
+            monitor.check(&[exposure(dec!(-10_001), false)], now),
+            vec![Breach::NetExposure {
+                contract_symbol: ContractSymbol::BtcUsd,
+                contracts: dec!(-10_001),
+            }]
+        );
+    }
+
+    #[test]
+    fn unhedged_exposure_is_limited_in_time() {
+        let start = datetime!(2023-10-01 12:00 UTC);
+        let mut monitor = RiskMonitor::new(limits(), start);
+        monitor.on_price(dec!(30_000), start + Duration::minutes(6));
+
+        assert!(monitor
+            .check(&[exposure(dec!(500), true)], start)
+            .is_empty());
+        assert_eq!(
+            monitor.check(&[exposure(dec!(500), true)], start + Duration::minutes(6)),
+            vec![Breach::UnhedgedExposure {
+                contract_symbol: ContractSymbol::BtcUsd,
+                unhedged_seconds: 360,
+            }]
+        );
+
+        // Once hedged, the clock starts over.
+        monitor.check(&[exposure(dec!(500), false)], start + Duration::minutes(6));
+        assert!(monitor
+            .check(&[exposure(dec!(500), true)], start + Duration::minutes(7))
+            .is_empty());
+    }
+
+    #[test]
+    fn stale_price_is_detected() {
+        let start = datetime!(2023-10-01 12:00 UTC);
+        let mut monitor = RiskMonitor::new(limits(), start);
+
+        assert!(monitor.check(&[], start + Duration::minutes(3)).is_empty());
+        assert_eq!(
+            monitor.check(&[], start + Duration::minutes(4)),
+            vec![Breach::StalePrice { age_seconds: 240 }]
+        );
+
+        monitor.on_price(dec!(30_000), start + Duration::minutes(4));
+        assert!(monitor.check(&[], start + Duration::minutes(5)).is_empty());
+    }
+
+    #[test]
+    fn daily_loss_is_limited_and_reset_every_day() {
+        let start = datetime!(2023-10-01 12:00 UTC);
+        let mut monitor = RiskMonitor::new(limits(), start);
+
+        // Long 3_000 contracts at 30_000.
+        monitor.on_fill(dec!(3_000), dec!(30_000), Decimal::ZERO);
+        monitor.on_price(dec!(30_000), start);
+        assert!(monitor.check(&[], start).is_empty());
+
+        // At 20_000 the position has lost 3_000 * (1/20_000 - 1/30_000) = 0.05 BTC.
+        monitor.on_price(dec!(20_000), start + Duration::minutes(1));
+        assert_eq!(
+            monitor.check(&[], start + Duration::minutes(1)),
+            vec![Breach::DailyLoss {
+                loss_btc: dec!(0.05)
+            }]
+        );
+
+        // The loss is carried over into the next day, but does not count towards its limit.
+        let next_day = datetime!(2023-10-02 00:01 UTC);
+        monitor.on_price(dec!(20_000), next_day);
+        assert!(monitor.check(&[], next_day).is_empty());
+    }
+
+    #[test]
+    fn daily_loss_before_startup_is_counted() {
+        let now = datetime!(2023-10-02 12:00 UTC);
+        let fill = |contracts: Decimal, price: Decimal, timestamp: OffsetDateTime| Fill {
+            venue: crate::accounting::Venue::TenTenOne,
+            execution_id: format!("{timestamp}"),
+            contract_symbol: ContractSymbol::BtcUsd,
+            contracts,
+            price,
+            fee: Decimal::ZERO,
+            timestamp,
+        };
+
+        // Yesterday's gain of 0.05 BTC does not offset today's loss of 0.05 BTC.
+        let fills = [
+            fill(dec!(3_000), dec!(20_000), datetime!(2023-10-01 08:00 UTC)),
+            fill(dec!(-3_000), dec!(30_000), datetime!(2023-10-01 12:00 UTC)),
+            fill(dec!(3_000), dec!(30_000), datetime!(2023-10-02 08:00 UTC)),
+            fill(dec!(-3_000), dec!(20_000), datetime!(2023-10-02 10:00 UTC)),
+        ];
+        let funding_payments = [FundingPayment {
+            execution_id: "funding".to_string(),
+            contract_symbol: ContractSymbol::BtcUsd,
+            amount: dec!(0.001),
+            timestamp: datetime!(2023-10-02 04:00 UTC),
+        }];
+
+        let mut monitor = RiskMonitor::from_history(limits(), &fills, &funding_payments, now);
+        monitor.on_price(dec!(20_000), now);
+
+        assert_eq!(
+            monitor.check(&[], now),
+            vec![Breach::DailyLoss {
+                loss_btc: dec!(0.051)
+            }]
+        );
+    }
+}
@@ -1,8 +1,15 @@
+use crate::accounting::Fill;
+use crate::accounting::PnlReport;
+use crate::db;
 use crate::health::Health;
 use crate::health::OverallMakerHealth;
 use crate::position;
 use crate::position::ContractSymbol;
+use crate::position::GetPnl;
 use crate::position::GetPosition;
+use crate::position::PauseQuoting;
+use crate::position::ResumeQuoting;
+use crate::risk::QuotingStatus;
 use axum::extract::Path;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -12,14 +19,21 @@ use axum::routing::get;
 use axum::routing::post;
 use axum::Json;
 use axum::Router;
+use bdk::FeeRate;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::Txid;
+use diesel::r2d2::ConnectionManager;
+use diesel::r2d2::Pool;
+use diesel::PgConnection;
 use lightning::ln::msgs::NetAddress;
 use ln_dlc_node::node::peer_manager::alias_as_bytes;
 use ln_dlc_node::node::peer_manager::broadcast_node_announcement;
+use ln_dlc_node::node::FeeBumpMethod;
 use ln_dlc_node::node::InMemoryStore;
 use ln_dlc_node::node::Node;
 use ln_dlc_node::node::NodeInfo;
 use ln_dlc_node::ChannelDetails;
+use ln_dlc_node::UnconfirmedTransaction;
 use opentelemetry_prometheus::PrometheusExporter;
 use prometheus::Encoder;
 use prometheus::TextEncoder;
@@ -41,6 +55,7 @@ pub struct AppState {
     announcement_addresses: Vec<NetAddress>,
     node_alias: String,
     health: Health,
+    pool: Pool<ConnectionManager<PgConnection>>,
 }
 
 pub fn router(
@@ -50,12 +65,14 @@ pub fn router(
     health: Health,
     announcement_addresses: Vec<NetAddress>,
     node_alias: &str,
+    pool: Pool<ConnectionManager<PgConnection>>,
 ) -> Router {
     let app_state = Arc::new(AppState {
         node,
         exporter,
         position_manager,
         health,
+        pool,
         announcement_addresses,
         node_alias: node_alias.to_string(),
     });
@@ -69,7 +86,17 @@ pub fn router(
         .route("/api/connect", post(connect_to_peer))
         .route("/api/pay-invoice/:invoice", post(pay_invoice))
         .route("/api/sync-on-chain", post(sync_on_chain))
+        .route(
+            "/api/transactions/unconfirmed",
+            get(list_unconfirmed_transactions),
+        )
+        .route("/api/transactions/:txid/bump", post(bump_fee))
         .route("/api/position", get(get_position))
+        .route("/api/pnl", get(get_pnl))
+        .route("/api/fills", get(get_fills))
+        .route("/api/quoting", get(get_quoting_status))
+        .route("/api/quoting/pause", post(pause_quoting))
+        .route("/api/quoting/resume", post(resume_quoting))
         .route("/api/node", get(get_node_info))
         .route("/metrics", get(get_metrics))
         .route("/health", get(get_health))
@@ -268,6 +295,43 @@ pub async fn sync_on_chain(State(state): State<Arc<AppState>>) -> Result<(), App
     Ok(())
 }
 
+pub async fn list_unconfirmed_transactions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<UnconfirmedTransaction>>, AppError> {
+    let transactions = spawn_blocking(move || state.node.unconfirmed_transactions())
+        .await
+        .expect("task to complete")
+        .map_err(|e| {
+            AppError::InternalServerError(format!("Could not list transactions: {e:#}"))
+        })?;
+
+    Ok(Json(transactions))
+}
+
+#[derive(Deserialize)]
+pub struct BumpFee {
+    /// In sats/vbyte.
+    pub fee_rate: f32,
+    pub method: FeeBumpMethod,
+}
+
+pub async fn bump_fee(
+    State(state): State<Arc<AppState>>,
+    Path(txid): Path<String>,
+    Json(params): Json<BumpFee>,
+) -> Result<Json<Txid>, AppError> {
+    let txid = Txid::from_str(&txid)
+        .map_err(|e| AppError::BadRequest(format!("Invalid txid provided: {e:#}")))?;
+
+    let fee_rate = FeeRate::from_sat_per_vb(params.fee_rate);
+    let txid = spawn_blocking(move || state.node.bump_fee(&txid, fee_rate, params.method))
+        .await
+        .expect("task to complete")
+        .map_err(|e| AppError::BadRequest(format!("Could not bump fee: {e:#}")))?;
+
+    Ok(Json(txid))
+}
+
 #[derive(Serialize)]
 pub struct Position {
     tentenone: HashSet<PositionForContractSymbol>,
@@ -310,6 +374,60 @@ pub async fn get_position(State(state): State<Arc<AppState>>) -> Result<Json<Pos
     }))
 }
 
+pub async fn get_pnl(State(state): State<Arc<AppState>>) -> Result<Json<PnlReport>, AppError> {
+    let pnl = state
+        .position_manager
+        .send(GetPnl)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Failed to get PnL: {e:#}")))?;
+
+    Ok(Json(pnl))
+}
+
+pub async fn get_fills(State(state): State<Arc<AppState>>) -> Result<Json<Vec<Fill>>, AppError> {
+    spawn_blocking(move || {
+        let mut conn = state.pool.get().map_err(|e| {
+            AppError::InternalServerError(format!("Failed to get connection: {e:#}"))
+        })?;
+
+        let fills = db::fills::get_all(&mut conn)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to load fills: {e:#}")))?;
+
+        Ok(Json(fills))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to load fills: {e:#}")))?
+}
+
+pub async fn get_quoting_status(State(state): State<Arc<AppState>>) -> Json<QuotingStatus> {
+    Json(state.health.get_quoting_status())
+}
+
+pub async fn pause_quoting(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<QuotingStatus>, AppError> {
+    let status = state
+        .position_manager
+        .send(PauseQuoting)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Failed to pause quoting: {e:#}")))?;
+
+    Ok(Json(status))
+}
+
+/// Resumes quoting, unless it is halted because of breached risk limits.
+pub async fn resume_quoting(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<QuotingStatus>, AppError> {
+    let status = state
+        .position_manager
+        .send(ResumeQuoting)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Failed to resume quoting: {e:#}")))?;
+
+    Ok(Json(status))
+}
+
 pub async fn get_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let autometrics = match autometrics::prometheus_exporter::encode_to_string() {
         Ok(metrics) => metrics,
@@ -1 +1,26 @@
 // @generated automatically by Diesel CLI.
+
+diesel::table! {
+    fills (id) {
+        id -> Int4,
+        venue -> Text,
+        execution_id -> Text,
+        contract_symbol -> Text,
+        contracts -> Float8,
+        price -> Float8,
+        fee_btc -> Float8,
+        timestamp -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    funding_payments (id) {
+        id -> Int4,
+        execution_id -> Text,
+        contract_symbol -> Text,
+        amount_btc -> Float8,
+        timestamp -> Timestamptz,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(fills, funding_payments,);
@@ -0,0 +1,281 @@
+use crate::trading::quoting::QuoteOrder;
+use bitcoin::secp256k1::PublicKey;
+use orderbook_commons::Order;
+use orderbook_commons::OrderState;
+use orderbook_commons::OrderType;
+use std::collections::HashSet;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// An order of the maker which is currently on the orderbook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiveOrder {
+    pub id: Uuid,
+    pub quote: QuoteOrder,
+    pub expiry: OffsetDateTime,
+}
+
+/// The changes needed to turn the live orders into the desired quotes.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    pub cancel: Vec<Uuid>,
+    pub post: Vec<QuoteOrder>,
+}
+
+/// Keeps track of the orders the maker has on the orderbook.
+#[derive(Debug, Default)]
+pub struct LiveOrders {
+    orders: Vec<LiveOrder>,
+}
+
+impl LiveOrders {
+    pub fn insert(&mut self, order: LiveOrder) {
+        self.orders.push(order);
+    }
+
+    pub fn remove(&mut self, id: Uuid) {
+        self.orders.retain(|order| order.id != id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Aligns the tracked orders with the orderbook.
+    ///
+    /// Tracked orders which are not open on the orderbook anymore, e.g. because they have been
+    /// matched, are forgotten. Returns the ids of the open limit orders of the maker which are not
+    /// tracked, e.g. because they have been created before a restart. These orders are stale and
+    /// should be cancelled.
+    pub fn sync(&mut self, orderbook_orders: &[Order], maker_id: PublicKey) -> Vec<Uuid> {
+        let open_orders = orderbook_orders
+            .iter()
+            .filter(|order| {
+                order.trader_id == maker_id
+                    && order.order_type == OrderType::Limit
+                    && order.order_state == OrderState::Open
+            })
+            .map(|order| order.id)
+            .collect::<HashSet<_>>();
+
+        self.orders.retain(|order| open_orders.contains(&order.id));
+
+        open_orders
+            .into_iter()
+            .filter(|id| !self.orders.iter().any(|order| order.id == *id))
+            .collect()
+    }
+
+    /// Determines which live orders have to be cancelled and which quotes have to be posted so
+    /// that exactly the `desired` quotes are live.
+    ///
+    /// Live orders which expire before `refresh_before` are replaced, even if they match a
+    /// desired quote.
+    pub fn reconcile(
+        &self,
+        desired: &[QuoteOrder],
+        refresh_before: OffsetDateTime,
+    ) -> Reconciliation {
+        let mut kept = HashSet::new();
+        let mut post = vec![];
+
+        for quote in desired {
+            let live_order = self.orders.iter().find(|order| {
+                order.quote == *quote && order.expiry >= refresh_before && !kept.contains(&order.id)
+            });
+
+            match live_order {
+                Some(order) => {
+                    kept.insert(order.id);
+                }
+                None => post.push(*quote),
+            }
+        }
+
+        let cancel = self
+            .orders
+            .iter()
+            .filter(|order| !kept.contains(&order.id))
+            .map(|order| order.id)
+            .collect();
+
+        Reconciliation { cancel, post }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orderbook_commons::OrderReason;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::str::FromStr;
+    use time::Duration;
+    use trade::ContractSymbol;
+    use trade::Direction;
+
+    fn maker_id() -> PublicKey {
+        PublicKey::from_str("02bd998ebd176715fe92b7467cf6b1df8023950a4dd911db4c94dfc89cc9f5a655")
+            .unwrap()
+    }
+
+    fn quote(direction: Direction, price: Decimal) -> QuoteOrder {
+        QuoteOrder {
+            direction,
+            price,
+            quantity: dec!(1000),
+            leverage: 1.0,
+        }
+    }
+
+    fn live_order(quote: QuoteOrder, expiry: OffsetDateTime) -> LiveOrder {
+        LiveOrder {
+            id: Uuid::new_v4(),
+            quote,
+            expiry,
+        }
+    }
+
+    fn orderbook_order(id: Uuid, trader_id: PublicKey, order_state: OrderState) -> Order {
+        Order {
+            id,
+            price: dec!(30_000),
+            leverage: 1.0,
+            contract_symbol: ContractSymbol::BtcUsd,
+            trader_id,
+            direction: Direction::Long,
+            quantity: dec!(1000),
+            order_type: OrderType::Limit,
+            timestamp: OffsetDateTime::now_utc(),
+            expiry: OffsetDateTime::now_utc() + Duration::minutes(1),
+            order_state,
+            order_reason: OrderReason::Manual,
+            stable: false,
+        }
+    }
+
+    #[test]
+    fn keeps_matching_orders_and_replaces_the_rest() {
+        let now = OffsetDateTime::now_utc();
+        let bid = live_order(
+            quote(Direction::Long, dec!(29_970)),
+            now + Duration::minutes(1),
+        );
+        let ask = live_order(
+            quote(Direction::Short, dec!(30_030)),
+            now + Duration::minutes(1),
+        );
+
+        let mut live_orders = LiveOrders::default();
+        live_orders.insert(bid);
+        live_orders.insert(ask);
+
+        let reconciliation = live_orders.reconcile(
+            &[
+                quote(Direction::Long, dec!(29_970)),
+                quote(Direction::Short, dec!(30_040)),
+            ],
+            now,
+        );
+
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                cancel: vec![ask.id],
+                post: vec![quote(Direction::Short, dec!(30_040))],
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_quotes_need_separate_orders() {
+        let now = OffsetDateTime::now_utc();
+        let bid = live_order(
+            quote(Direction::Long, dec!(29_970)),
+            now + Duration::minutes(1),
+        );
+
+        let mut live_orders = LiveOrders::default();
+        live_orders.insert(bid);
+
+        let reconciliation = live_orders.reconcile(
+            &[
+                quote(Direction::Long, dec!(29_970)),
+                quote(Direction::Long, dec!(29_970)),
+            ],
+            now,
+        );
+
+        assert_eq!(reconciliation.cancel, vec![]);
+        assert_eq!(
+            reconciliation.post,
+            vec![quote(Direction::Long, dec!(29_970))]
+        );
+    }
+
+    #[test]
+    fn replaces_orders_which_are_about_to_expire() {
+        let now = OffsetDateTime::now_utc();
+        let bid = live_order(
+            quote(Direction::Long, dec!(29_970)),
+            now + Duration::seconds(10),
+        );
+
+        let mut live_orders = LiveOrders::default();
+        live_orders.insert(bid);
+
+        let reconciliation = live_orders.reconcile(
+            &[quote(Direction::Long, dec!(29_970))],
+            now + Duration::seconds(30),
+        );
+
+        assert_eq!(reconciliation.cancel, vec![bid.id]);
+        assert_eq!(
+            reconciliation.post,
+            vec![quote(Direction::Long, dec!(29_970))]
+        );
+    }
+
+    #[test]
+    fn sync_forgets_filled_orders_and_reports_stale_ones() {
+        let now = OffsetDateTime::now_utc();
+        let open = live_order(
+            quote(Direction::Long, dec!(29_970)),
+            now + Duration::minutes(1),
+        );
+        let matched = live_order(
+            quote(Direction::Short, dec!(30_030)),
+            now + Duration::minutes(1),
+        );
+        let stale_id = Uuid::new_v4();
+        let other_trader = PublicKey::from_str(
+            "027f31ebc5462c1fdce1b737ecff52d37d75dea43ce11c74d25aa297165faa2007",
+        )
+        .unwrap();
+
+        let mut live_orders = LiveOrders::default();
+        live_orders.insert(open);
+        live_orders.insert(matched);
+
+        let stale = live_orders.sync(
+            &[
+                orderbook_order(open.id, maker_id(), OrderState::Open),
+                orderbook_order(matched.id, maker_id(), OrderState::Matched),
+                orderbook_order(stale_id, maker_id(), OrderState::Open),
+                orderbook_order(Uuid::new_v4(), other_trader, OrderState::Open),
+            ],
+            maker_id(),
+        );
+
+        assert_eq!(stale, vec![stale_id]);
+        assert_eq!(live_orders.len(), 1);
+        assert_eq!(
+            live_orders.reconcile(&[open.quote], now).cancel,
+            Vec::<Uuid>::new()
+        );
+    }
+}
@@ -1,10 +1,26 @@
 use crate::health::ServiceStatus;
 use crate::position;
+use crate::position::GetPosition;
 use crate::position::PositionUpdateBitmex;
-use crate::trading::bitmex_ws_client::Event;
+use crate::position::VenueFill;
+use crate::position::VenueFunding;
+use crate::position::VenueQuote;
+use crate::price_feed::PriceFeed;
+use crate::risk::QuotingStatus;
+use crate::trading::live_orders::LiveOrder;
+use crate::trading::live_orders::LiveOrders;
+use crate::trading::live_orders::Reconciliation;
+use crate::trading::quoting::QuoteContext;
+use crate::trading::quoting::QuoteOrder;
+use crate::trading::quoting::QuotingStrategy;
+use crate::trading::quoting::ReferencePrice;
+use crate::venue::HedgingVenue;
+use crate::venue::VenueEvent;
+use anyhow::Context;
+use anyhow::Result;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::Network;
-use bitmex_stream::Credentials;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::secp256k1::SECP256K1;
 use futures::TryStreamExt;
 use orderbook_commons::NewOrder;
 use orderbook_commons::OrderResponse;
@@ -12,21 +28,27 @@ use orderbook_commons::OrderType;
 use orderbook_http_client::OrderbookClient;
 use reqwest::Url;
 use rust_decimal::Decimal;
-use rust_decimal_macros::dec;
+use std::sync::Arc;
 use std::time::Duration;
 use time::OffsetDateTime;
 use tokio::sync::watch;
 use trade::ContractSymbol;
-use trade::Direction;
 use uuid::Uuid;
 
-mod bitmex_ws_client;
+mod live_orders;
 mod orderbook_http_client;
+pub mod quoting;
 
-/// Perform trading related actions based on a subscription to BitMEX's WebSocket API. Specifically:
+/// Perform trading related actions based on a subscription to the [`HedgingVenue`] and the
+/// reference price of the [`price_feed`](crate::price_feed). Specifically:
 ///
-/// - Create orders based on relevant price updates from BitMEX.
-/// - Forward updates about all BitMEX positions.
+/// - Maintain orders based on changes of the reference price, as defined by the
+///   [`QuotingStrategy`], cancelling and replacing them as the quotes change.
+/// - Forward quotes of the venue to the [`PriceFeed`] and the [`position::Manager`].
+/// - Forward fills, funding payments and updates about all positions on the venue to the
+///   [`position::Manager`].
+/// - Cancel all orders and stop quoting while the [`QuotingStatus`] is not active or no reference
+///   price is available.
 ///
 /// In the unlikely event that the stream is closed, the function will continue to try to reconnect
 /// after the [`Duration`] specified by `reconnect_after`.
@@ -34,99 +56,281 @@ mod orderbook_http_client;
 pub async fn run(
     orderbook_url: &Url,
     maker_id: PublicKey,
-    network: Network,
-    concurrent_orders: usize,
+    auth_sk: SecretKey,
+    venue: Arc<dyn HedgingVenue>,
+    quoting_strategy: impl QuotingStrategy,
     order_expiry_after: time::Duration,
     bitmex_pricefeed_tx: watch::Sender<ServiceStatus>,
     position_manager: xtra::Address<position::Manager>,
-    bitmex_api_key: Option<String>,
-    bitmex_api_secret: Option<String>,
+    price_feed: PriceFeed,
+    mut reference_price: watch::Receiver<Option<ReferencePrice>>,
+    mut quoting_status: watch::Receiver<QuotingStatus>,
     reconnect_after: Duration,
 ) {
-    let network = match network {
-        Network::Bitcoin => bitmex_stream::Network::Mainnet,
-        _ => bitmex_stream::Network::Testnet,
-    };
-
-    let orderbook_client = OrderbookClient::new();
+    let orderbook_client = OrderbookClient::new(auth_sk);
 
-    let mut orders: Vec<OrderResponse> = Vec::new();
+    let mut live_orders = LiveOrders::default();
 
-    // Closure to avoid repeating the same code
-    let add_new_10101_order = |price, direction| {
-        add_10101_order(
-            &orderbook_client,
-            orderbook_url,
-            price,
-            direction,
-            maker_id,
-            dec!(1000),
-            OffsetDateTime::now_utc() + order_expiry_after,
-        )
-    };
+    // The market state the live quotes have been derived from and when they have been created.
+    let mut last_quoted: Option<(QuoteContext, OffsetDateTime)> = None;
 
-    let credentials = match (bitmex_api_key, bitmex_api_secret) {
-        (Some(api_key), Some(secret)) => Some(Credentials { api_key, secret }),
-        _ => None,
-    };
+    // Quotes are refreshed before they expire, even if the market has not moved.
+    let max_quote_age = order_expiry_after / 2;
 
     loop {
-        let mut stream = bitmex_ws_client::stream(network, credentials.clone()).await;
+        let mut stream = venue.subscribe().await;
         loop {
-            match stream.try_next().await {
-                Ok(Some(Event::Quote(quote))) => {
-                    let _ = bitmex_pricefeed_tx.send(ServiceStatus::Online);
-                    tracing::debug!("Received new quote {quote:?}");
-
-                    orders.clear();
-
-                    for _i in 0..concurrent_orders {
-                        if let Some(order) = add_new_10101_order(quote.bid(), Direction::Long).await
-                        {
-                            orders.push(order)
-                        };
-
-                        if let Some(order) =
-                            add_new_10101_order(quote.ask(), Direction::Short).await
-                        {
-                            orders.push(order)
-                        };
+            tokio::select! {
+                event = stream.try_next() => match event {
+                    Ok(Some(VenueEvent::Quote(quote))) => {
+                        let _ = bitmex_pricefeed_tx.send(ServiceStatus::Online);
+                        tracing::debug!("Received new quote {quote:?}");
+
+                        price_feed.on_venue_quote(quote);
+                        let _ = position_manager.send(VenueQuote(quote)).await;
+                    }
+                    Ok(Some(VenueEvent::Position(position))) => {
+                        let _ = position_manager
+                            .send(PositionUpdateBitmex {
+                                contract_symbol: position.contract_symbol.into(),
+                                contracts: position.contracts,
+                            })
+                            .await;
                     }
+                    Ok(Some(VenueEvent::Fill(fill))) => {
+                        tracing::info!(?fill, "Hedging order filled on venue");
+
+                        let _ = position_manager.send(VenueFill(fill)).await;
+                    }
+                    Ok(Some(VenueEvent::Funding(funding))) => {
+                        let _ = position_manager.send(VenueFunding(funding)).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("Closing venue stream after encountering error: {e:#}");
+                        break;
+                    }
+                    Ok(None) => {
+                        tracing::error!("Venue stream closed");
+                        break;
+                    }
+                },
+                Ok(()) = reference_price.changed() => {
+                    let reference = *reference_price.borrow();
+
+                    let is_active = quoting_status.borrow().is_active();
+                    if !is_active {
+                        continue;
+                    }
+
+                    let now = OffsetDateTime::now_utc();
+
+                    let Some(reference) = reference else {
+                        // We check the live orders rather than `last_quoted`, as the latter is
+                        // reset on reconnects while orders may still be live.
+                        if !live_orders.is_empty() {
+                            tracing::warn!("Cancelling all orders as there is no reference price");
+
+                            update_quotes(
+                                &orderbook_client,
+                                orderbook_url,
+                                maker_id,
+                                &mut live_orders,
+                                &[],
+                                now,
+                                now,
+                            )
+                            .await;
+
+                            last_quoted = None;
+                        }
+                        continue;
+                    };
+
+                    let context = QuoteContext {
+                        reference,
+                        inventory: get_inventory(&position_manager).await,
+                    };
+
+                    let requote = match last_quoted {
+                        Some((last_context, quoted_at)) => {
+                            now - quoted_at >= max_quote_age
+                                || quoting_strategy.should_requote(&last_context, &context)
+                        }
+                        None => true,
+                    };
+
+                    if !requote {
+                        continue;
+                    }
+
+                    update_quotes(
+                        &orderbook_client,
+                        orderbook_url,
+                        maker_id,
+                        &mut live_orders,
+                        &quoting_strategy.quotes(&context),
+                        now + order_expiry_after,
+                        now + max_quote_age,
+                    )
+                    .await;
+
+                    last_quoted = Some((context, now));
                 }
-                Ok(Some(Event::Position(position))) => {
-                    let _ = position_manager
-                        .send(PositionUpdateBitmex {
-                            contract_symbol: position.contract_symbol.into(),
-                            contracts: position.contracts,
-                        })
+                Ok(()) = quoting_status.changed() => {
+                    let status = quoting_status.borrow().clone();
+                    if !status.is_active() {
+                        tracing::warn!(?status, "Cancelling all orders as quoting is stopped");
+
+                        let now = OffsetDateTime::now_utc();
+                        update_quotes(
+                            &orderbook_client,
+                            orderbook_url,
+                            maker_id,
+                            &mut live_orders,
+                            &[],
+                            now,
+                            now,
+                        )
                         .await;
-                }
-                Err(e) => {
-                    tracing::error!("Closing BitMEX WS after encountering error: {e:#}");
-                    break;
-                }
-                Ok(None) => {
-                    tracing::error!("BitMEX WS closed");
-                    break;
+                    }
+
+                    // Requote with the next reference price once quoting is active again.
+                    last_quoted = None;
                 }
             }
         }
 
         let _ = bitmex_pricefeed_tx.send(ServiceStatus::Offline);
+        price_feed.on_venue_disconnected();
+
+        // Without the venue we can neither hedge nor trust our quotes, hence they are pulled until
+        // we are reconnected.
+        tracing::warn!("Cancelling all orders as the venue stream is offline");
+
+        let now = OffsetDateTime::now_utc();
+        update_quotes(
+            &orderbook_client,
+            orderbook_url,
+            maker_id,
+            &mut live_orders,
+            &[],
+            now,
+            now,
+        )
+        .await;
+
+        // Requote as soon as we are reconnected.
+        last_quoted = None;
 
-        tracing::error!(timeout = ?reconnect_after, "Reconnecting to BitMEX WS after timeout");
+        tracing::error!(timeout = ?reconnect_after, "Reconnecting to venue stream after timeout");
 
         tokio::time::sleep(reconnect_after).await;
     }
 }
 
+/// Cancels all open orders of the maker on the orderbook, including orders which are not tracked
+/// by [`run`], e.g. left over from a previous run.
+pub async fn cancel_all_orders(orderbook_url: &Url, auth_sk: SecretKey) -> Result<()> {
+    let maker_id = auth_sk.public_key(SECP256K1);
+    let orderbook_client = OrderbookClient::new(auth_sk);
+
+    let orderbook_orders = orderbook_client.get_orders(orderbook_url).await?;
+    let open_orders = LiveOrders::default().sync(&orderbook_orders, maker_id);
+
+    tracing::info!(orders = open_orders.len(), "Cancelling all orders");
+
+    for order_id in open_orders {
+        orderbook_client
+            .delete_order(orderbook_url, order_id)
+            .await
+            .with_context(|| format!("Failed to cancel order {order_id}"))?;
+    }
+
+    Ok(())
+}
+
+/// Cancels and replaces the maker's orders on the orderbook so that exactly the `desired` quotes
+/// are live.
+///
+/// Open orders of the maker which are not tracked, e.g. left over from before a reconnect or
+/// restart, are cancelled. Live orders expiring before `refresh_before` are replaced.
+async fn update_quotes(
+    orderbook_client: &OrderbookClient,
+    orderbook_url: &Url,
+    maker_id: PublicKey,
+    live_orders: &mut LiveOrders,
+    desired: &[QuoteOrder],
+    expiry: OffsetDateTime,
+    refresh_before: OffsetDateTime,
+) {
+    let stale_orders = match orderbook_client.get_orders(orderbook_url).await {
+        Ok(orderbook_orders) => live_orders.sync(&orderbook_orders, maker_id),
+        Err(e) => {
+            tracing::warn!("Failed to load orders from orderbook, using local view: {e:#}");
+            vec![]
+        }
+    };
+
+    let Reconciliation { cancel, post } = live_orders.reconcile(desired, refresh_before);
+
+    tracing::debug!(
+        stale = stale_orders.len(),
+        cancel = cancel.len(),
+        post = post.len(),
+        "Updating quotes"
+    );
+
+    // We post the new quotes before cancelling the old ones so that the orderbook is never empty.
+    for quote_order in post {
+        if let Some(order) = add_10101_order(
+            orderbook_client,
+            orderbook_url,
+            quote_order,
+            maker_id,
+            expiry,
+        )
+        .await
+        {
+            live_orders.insert(LiveOrder {
+                id: order.id,
+                quote: quote_order,
+                expiry,
+            });
+        }
+    }
+
+    for order_id in cancel.into_iter().chain(stale_orders) {
+        match orderbook_client.delete_order(orderbook_url, order_id).await {
+            Ok(()) => live_orders.remove(order_id),
+            // The order is kept until the next sync with the orderbook tells us it is gone, e.g.
+            // because it has been matched in the meantime.
+            Err(e) => tracing::warn!(%order_id, "Failed to cancel order: {e:#}"),
+        }
+    }
+
+    tracing::debug!(live_orders = live_orders.len(), "Updated quotes");
+}
+
+/// The maker's 10101 position in contracts, defaulting to zero if it is unknown.
+async fn get_inventory(position_manager: &xtra::Address<position::Manager>) -> Decimal {
+    match position_manager.send(GetPosition).await {
+        Ok(position) => position
+            .tentenone
+            .get(&position::ContractSymbol::BtcUsd)
+            .copied()
+            .unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("Failed to get 10101 position, assuming no inventory: {e:#}");
+            Decimal::ZERO
+        }
+    }
+}
+
 async fn add_10101_order(
     orderbook_client: &OrderbookClient,
     orderbook_url: &Url,
-    price: Decimal,
-    direction: Direction,
+    quote_order: QuoteOrder,
     maker_id: PublicKey,
-    quantity: Decimal,
     expiry: OffsetDateTime,
 ) -> Option<OrderResponse> {
     orderbook_client
@@ -135,14 +339,15 @@ async fn add_10101_order(
             NewOrder {
                 id: Uuid::new_v4(),
                 contract_symbol: ContractSymbol::BtcUsd,
-                price,
-                quantity,
+                price: quote_order.price,
+                quantity: quote_order.quantity,
                 trader_id: maker_id,
-                direction,
-                leverage: 1.0,
+                direction: quote_order.direction,
+                leverage: quote_order.leverage,
                 order_type: OrderType::Limit,
                 expiry,
                 stable: false,
+                worst_price: None,
             },
         )
         .await
@@ -1,20 +1,29 @@
 use anyhow::bail;
 use anyhow::Result;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::secp256k1::SECP256K1;
+use orderbook_commons::create_cancel_order_message;
 use orderbook_commons::NewOrder;
+use orderbook_commons::Order;
 use orderbook_commons::OrderResponse;
+use orderbook_commons::Signature;
 use reqwest::Url;
+use uuid::Uuid;
 
 pub struct OrderbookClient {
     client: reqwest::Client,
+    /// Secret key used to prove ownership of the maker's orders.
+    auth_sk: SecretKey,
 }
 
 impl OrderbookClient {
-    pub fn new() -> Self {
+    pub fn new(auth_sk: SecretKey) -> Self {
         Self {
             client: reqwest::Client::builder()
                 .timeout(std::time::Duration::from_secs(30))
                 .build()
                 .expect("to build client from static config"),
+            auth_sk,
         }
     }
 
@@ -31,4 +40,40 @@ impl OrderbookClient {
             bail!("Could not create new order ")
         }
     }
+
+    /// Loads all orders of the orderbook which are neither expired nor failed.
+    pub async fn get_orders(&self, url: &Url) -> Result<Vec<Order>> {
+        let url = url.join("/api/orderbook/orders")?;
+
+        let response = self.client.get(url).send().await?;
+
+        if response.status().as_u16() == 200 {
+            let response = response.json().await?;
+            Ok(response)
+        } else {
+            bail!("Could not load orders: {}", response.status())
+        }
+    }
+
+    /// Cancels the open limit order with the given id.
+    pub async fn delete_order(&self, url: &Url, order_id: Uuid) -> Result<()> {
+        let url = url.join(&format!("/api/orderbook/orders/{order_id}"))?;
+
+        let signature = Signature {
+            pubkey: self.auth_sk.public_key(SECP256K1),
+            signature: self
+                .auth_sk
+                .sign_ecdsa(create_cancel_order_message(order_id)),
+        };
+
+        let response = self.client.delete(url).json(&signature).send().await?;
+
+        if response.status().as_u16() == 200 {
+            Ok(())
+        } else {
+            let status = response.status();
+            let text = response.text().await?;
+            bail!("Could not delete order {order_id}: {status} {text}")
+        }
+    }
 }
@@ -0,0 +1,257 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use trade::Direction;
+
+/// The smallest price increment of the quotes.
+const PRICE_TICK: Decimal = dec!(0.5);
+
+const BPS: Decimal = dec!(10_000);
+
+/// The reference price the maker quotes around, e.g. the best bid and ask on BitMEX.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferencePrice {
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+impl ReferencePrice {
+    pub fn mid(&self) -> Decimal {
+        (self.bid + self.ask) / dec!(2)
+    }
+}
+
+/// The market state a set of quotes is derived from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuoteContext {
+    pub reference: ReferencePrice,
+    /// The maker's 10101 position in contracts. Positive if the maker is long, negative if the
+    /// maker is short.
+    pub inventory: Decimal,
+}
+
+/// A limit order the maker wants to have on the 10101 orderbook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuoteOrder {
+    pub direction: Direction,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub leverage: f32,
+}
+
+pub trait QuotingStrategy {
+    /// The limit orders to quote on the 10101 orderbook, given the current market state.
+    fn quotes(&self, context: &QuoteContext) -> Vec<QuoteOrder>;
+
+    /// Whether the quotes derived from `last` need to be replaced, given the market state has
+    /// changed to `new`.
+    fn should_requote(&self, last: &QuoteContext, new: &QuoteContext) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LadderParams {
+    /// The distance between the best bid and the best ask quote in basis points of the mid price.
+    pub spread_bps: Decimal,
+    /// The number of quotes on each side of the orderbook.
+    pub levels: usize,
+    /// The distance between two consecutive quotes on the same side in basis points of the mid
+    /// price.
+    pub level_spacing_bps: Decimal,
+    /// The quantity of the quotes closest to the mid price.
+    pub base_quantity: Decimal,
+    /// The quantity added to every subsequent level.
+    pub quantity_increment: Decimal,
+    /// The maximum shift of all quotes in basis points of the mid price, applied when the
+    /// inventory reaches `max_inventory`.
+    pub max_skew_bps: Decimal,
+    /// The inventory in contracts at which the maximum skew is applied.
+    pub max_inventory: Decimal,
+    /// The minimum change of the mid price or the skew in basis points before the quotes are
+    /// replaced.
+    pub min_requote_bps: Decimal,
+    pub leverage: f32,
+}
+
+/// Quotes a ladder of limit orders on both sides of the reference mid price.
+///
+/// The quotes are shifted away from the side the maker is already exposed to, i.e. if the maker
+/// is long all quotes are lowered to attract buyers and deter sellers, and vice versa.
+#[derive(Debug, Clone, Copy)]
+pub struct LadderStrategy {
+    params: LadderParams,
+}
+
+impl LadderStrategy {
+    pub fn new(params: LadderParams) -> Self {
+        Self { params }
+    }
+
+    /// The shift of all quotes in basis points. Positive values lower the quotes.
+    fn skew_bps(&self, inventory: Decimal) -> Decimal {
+        if self.params.max_inventory <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let ratio = (inventory / self.params.max_inventory).clamp(-Decimal::ONE, Decimal::ONE);
+        ratio * self.params.max_skew_bps
+    }
+}
+
+impl QuotingStrategy for LadderStrategy {
+    fn quotes(&self, context: &QuoteContext) -> Vec<QuoteOrder> {
+        let mid = context.reference.mid();
+        let half_spread_bps = self.params.spread_bps / dec!(2);
+        let skew_bps = self.skew_bps(context.inventory);
+
+        (0..self.params.levels)
+            .flat_map(|level| {
+                let offset_bps =
+                    half_spread_bps + Decimal::from(level) * self.params.level_spacing_bps;
+                let quantity = self.params.base_quantity
+                    + Decimal::from(level) * self.params.quantity_increment;
+
+                let bid = mid * (Decimal::ONE - (offset_bps + skew_bps) / BPS);
+                let ask = mid * (Decimal::ONE + (offset_bps - skew_bps) / BPS);
+
+                [
+                    QuoteOrder {
+                        direction: Direction::Long,
+                        price: (bid / PRICE_TICK).floor() * PRICE_TICK,
+                        quantity,
+                        leverage: self.params.leverage,
+                    },
+                    QuoteOrder {
+                        direction: Direction::Short,
+                        price: (ask / PRICE_TICK).ceil() * PRICE_TICK,
+                        quantity,
+                        leverage: self.params.leverage,
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    fn should_requote(&self, last: &QuoteContext, new: &QuoteContext) -> bool {
+        let last_mid = last.reference.mid();
+        if last_mid.is_zero() {
+            return true;
+        }
+
+        let mid_change_bps = (new.reference.mid() - last_mid).abs() / last_mid * BPS;
+        let skew_change_bps = (self.skew_bps(new.inventory) - self.skew_bps(last.inventory)).abs();
+
+        mid_change_bps >= self.params.min_requote_bps
+            || skew_change_bps >= self.params.min_requote_bps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> LadderParams {
+        LadderParams {
+            spread_bps: dec!(20),
+            levels: 2,
+            level_spacing_bps: dec!(10),
+            base_quantity: dec!(1000),
+            quantity_increment: dec!(500),
+            max_skew_bps: dec!(10),
+            max_inventory: dec!(10_000),
+            min_requote_bps: dec!(2),
+            leverage: 2.0,
+        }
+    }
+
+    fn context(bid: Decimal, ask: Decimal, inventory: Decimal) -> QuoteContext {
+        QuoteContext {
+            reference: ReferencePrice { bid, ask },
+            inventory,
+        }
+    }
+
+    fn prices(quotes: &[QuoteOrder], direction: Direction) -> Vec<Decimal> {
+        quotes
+            .iter()
+            .filter(|quote| quote.direction == direction)
+            .map(|quote| quote.price)
+            .collect()
+    }
+
+    #[test]
+    fn quotes_ladder_around_mid_price() {
+        let strategy = LadderStrategy::new(params());
+
+        let quotes = strategy.quotes(&context(dec!(29_995), dec!(30_005), Decimal::ZERO));
+
+        assert_eq!(
+            prices(&quotes, Direction::Long),
+            vec![dec!(29_970), dec!(29_940)]
+        );
+        assert_eq!(
+            prices(&quotes, Direction::Short),
+            vec![dec!(30_030), dec!(30_060)]
+        );
+        assert_eq!(
+            quotes
+                .iter()
+                .map(|quote| quote.quantity)
+                .collect::<Vec<_>>(),
+            vec![dec!(1000), dec!(1000), dec!(1500), dec!(1500)]
+        );
+        assert!(quotes.iter().all(|quote| quote.leverage == 2.0));
+    }
+
+    #[test]
+    fn long_inventory_lowers_quotes() {
+        let strategy = LadderStrategy::new(params());
+
+        let quotes = strategy.quotes(&context(dec!(29_995), dec!(30_005), dec!(5_000)));
+
+        assert_eq!(
+            prices(&quotes, Direction::Long),
+            vec![dec!(29_955), dec!(29_925)]
+        );
+        assert_eq!(
+            prices(&quotes, Direction::Short),
+            vec![dec!(30_015), dec!(30_045)]
+        );
+    }
+
+    #[test]
+    fn skew_is_capped_at_max_inventory() {
+        let strategy = LadderStrategy::new(params());
+
+        let capped = strategy.quotes(&context(dec!(29_995), dec!(30_005), dec!(-10_000)));
+        let beyond = strategy.quotes(&context(dec!(29_995), dec!(30_005), dec!(-50_000)));
+
+        assert_eq!(capped, beyond);
+        assert_eq!(prices(&capped, Direction::Long)[0], dec!(30_000));
+    }
+
+    #[test]
+    fn prices_are_rounded_away_from_mid() {
+        let strategy = LadderStrategy::new(LadderParams {
+            spread_bps: dec!(1),
+            levels: 1,
+            ..params()
+        });
+
+        let quotes = strategy.quotes(&context(dec!(30_000), dec!(30_000), Decimal::ZERO));
+
+        assert_eq!(prices(&quotes, Direction::Long), vec![dec!(29_998.5)]);
+        assert_eq!(prices(&quotes, Direction::Short), vec![dec!(30_001.5)]);
+    }
+
+    #[test]
+    fn only_requotes_on_significant_changes() {
+        let strategy = LadderStrategy::new(params());
+        let last = context(dec!(29_995), dec!(30_005), Decimal::ZERO);
+
+        assert!(
+            !strategy.should_requote(&last, &context(dec!(29_997), dec!(30_007), Decimal::ZERO))
+        );
+        assert!(strategy.should_requote(&last, &context(dec!(30_001), dec!(30_011), Decimal::ZERO)));
+        assert!(!strategy.should_requote(&last, &context(dec!(29_995), dec!(30_005), dec!(1_000))));
+        assert!(strategy.should_requote(&last, &context(dec!(29_995), dec!(30_005), dec!(2_000))));
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use rust_decimal::Decimal;
+use std::fmt;
+use time::OffsetDateTime;
+use trade::ContractSymbol;
+
+pub mod bitmex;
+mod bitmex_ws_client;
+pub mod simulated;
+
+/// A venue on which the maker hedges its 10101 position, e.g. BitMEX.
+///
+/// The venue also provides the reference prices the maker quotes around.
+#[async_trait]
+pub trait HedgingVenue: Send + Sync + 'static {
+    /// Places an order on the venue.
+    ///
+    /// The order may be filled partially or not at all; fills are reported as
+    /// [`VenueEvent::Fill`]s.
+    async fn place_order(&self, order: HedgeOrder) -> Result<OrderAck>;
+
+    /// Cancels an order on the venue, returning its final state.
+    ///
+    /// Cancelling an order which has already been filled is not an error.
+    async fn cancel_order(&self, order_id: &str) -> Result<OrderAck>;
+
+    /// Loads the current state of an order on the venue.
+    async fn get_order(&self, order_id: &str) -> Result<OrderAck>;
+
+    /// Loads our current positions on the venue.
+    async fn get_positions(&self) -> Result<Vec<Position>>;
+
+    /// Subscribes to quotes, fills and position updates.
+    ///
+    /// The stream yields an error if the connection to the venue is lost. It is up to the caller
+    /// to resubscribe.
+    async fn subscribe(&self) -> BoxStream<'static, Result<VenueEvent>>;
+}
+
+#[derive(Debug, Clone)]
+pub enum VenueEvent {
+    Quote(Quote),
+    Position(Position),
+    Fill(Fill),
+    Funding(Funding),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HedgeOrder {
+    pub contract_symbol: ContractSymbol,
+    /// The number of contracts, with the sign representing the direction: positive buy; negative
+    /// sell.
+    pub contracts: i32,
+    pub order_type: HedgeOrderType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeOrderType {
+    /// Taking liquidity at the best available price.
+    Market,
+    /// Providing liquidity at `price`. The order is cancelled by the venue instead of being
+    /// filled immediately.
+    PostOnlyLimit { price: Decimal },
+}
+
+/// The state of an order on the venue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderAck {
+    pub order_id: String,
+    /// The number of contracts filled so far, with the sign representing the direction: positive
+    /// buy; negative sell.
+    pub filled_contracts: i32,
+    /// The average price of the filled contracts, if any.
+    pub average_price: Option<Decimal>,
+    /// Whether the order may still be filled, i.e. it has neither been filled completely nor
+    /// cancelled.
+    pub is_open: bool,
+}
+
+/// The best bid and ask on the hedging venue.
+#[derive(Clone, Copy)]
+pub struct Quote {
+    pub contract_symbol: ContractSymbol,
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub timestamp: OffsetDateTime,
+}
+
+impl fmt::Debug for Quote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rfc3339_timestamp = self
+            .timestamp
+            .format(&time::format_description::well_known::Rfc3339)
+            .expect("Timestamp to be formatted");
+
+        f.debug_struct("Quote")
+            .field("timestamp", &rfc3339_timestamp)
+            .field("bid", &self.bid)
+            .field("ask", &self.ask)
+            .field("contract_symbol", &self.contract_symbol)
+            .finish()
+    }
+}
+
+impl Quote {
+    pub fn bid(&self) -> Decimal {
+        self.bid
+    }
+
+    pub fn ask(&self) -> Decimal {
+        self.ask
+    }
+
+    #[allow(dead_code)]
+    pub fn is_older_than(&self, duration: time::Duration) -> bool {
+        let required_quote_timestamp = (OffsetDateTime::now_utc() - duration).unix_timestamp();
+
+        self.timestamp.unix_timestamp() < required_quote_timestamp
+    }
+}
+
+/// Our position on the hedging venue.
+#[derive(Clone, Copy)]
+pub struct Position {
+    pub contract_symbol: ContractSymbol,
+    pub contracts: i32,
+    pub timestamp: OffsetDateTime,
+}
+
+impl fmt::Debug for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rfc3339_timestamp = self
+            .timestamp
+            .format(&time::format_description::well_known::Rfc3339)
+            .expect("Timestamp to be formatted");
+
+        f.debug_struct("Position")
+            .field("contract_symbol", &self.contract_symbol)
+            .field("contracts", &self.contracts)
+            .field("timestamp", &rfc3339_timestamp)
+            .finish()
+    }
+}
+
+/// A (partial) fill of one of our orders on the hedging venue.
+#[derive(Clone, Debug)]
+pub struct Fill {
+    /// Uniquely identifies the fill on the venue.
+    pub execution_id: String,
+    pub order_id: String,
+    pub contract_symbol: ContractSymbol,
+    /// The filled contracts, with the sign representing the direction: positive buy; negative
+    /// sell.
+    pub contracts: i32,
+    pub price: Decimal,
+    /// The fee paid for the fill in BTC. Negative if we received a rebate.
+    pub fee: Decimal,
+    pub timestamp: OffsetDateTime,
+}
+
+/// A funding payment for our position on the hedging venue.
+#[derive(Clone, Debug)]
+pub struct Funding {
+    /// Uniquely identifies the funding payment on the venue.
+    pub execution_id: String,
+    pub contract_symbol: ContractSymbol,
+    /// The amount paid in BTC. Negative if we received funding.
+    pub amount: Decimal,
+    pub timestamp: OffsetDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use time::ext::NumericalDuration;
+
+    #[test]
+    fn quote_from_now_is_not_old() {
+        let quote = dummy_quote_at(OffsetDateTime::now_utc());
+
+        let is_older = quote.is_older_than(1.minutes());
+
+        assert!(!is_older)
+    }
+
+    #[test]
+    fn quote_from_one_hour_ago_is_old() {
+        let quote = dummy_quote_at(OffsetDateTime::now_utc() - 1.hours());
+
+        let is_older = quote.is_older_than(1.minutes());
+
+        assert!(is_older)
+    }
+
+    fn dummy_quote_at(timestamp: OffsetDateTime) -> Quote {
+        Quote {
+            timestamp,
+            bid: dec!(10),
+            ask: dec!(10),
+            contract_symbol: trade::ContractSymbol::BtcUsd,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::venue::bitmex_ws_client;
+use crate::venue::HedgeOrder;
+use crate::venue::HedgeOrderType;
+use crate::venue::HedgingVenue;
+use crate::venue::OrderAck;
+use crate::venue::Position;
+use crate::venue::VenueEvent;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bitmex_client::models::OrderStatus;
+use bitmex_client::models::Side;
+use bitmex_stream::Credentials;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+use trade::ContractSymbol;
+
+/// Hedging on BitMEX, using its REST API to place orders and its WebSocket API for quotes, fills
+/// and position updates.
+pub struct BitmexVenue {
+    client: bitmex_client::client::Client,
+    network: bitmex_stream::Network,
+    credentials: Option<Credentials>,
+}
+
+impl BitmexVenue {
+    pub fn new(network: bitcoin::Network, api_key: Option<String>, secret: Option<String>) -> Self {
+        let client = bitmex_client::client::Client::new(match network {
+            bitcoin::Network::Bitcoin => bitmex_client::models::Network::Mainnet,
+            _ => bitmex_client::models::Network::Testnet,
+        });
+
+        let (client, credentials) = match (api_key, secret) {
+            (Some(api_key), Some(secret)) => {
+                tracing::info!("BitMEX credentials provided");
+                (
+                    client.with_credentials(&api_key, &secret),
+                    Some(Credentials { api_key, secret }),
+                )
+            }
+            _ => {
+                tracing::info!("BitMEX credentials not provided");
+                (client, None)
+            }
+        };
+
+        let network = match network {
+            bitcoin::Network::Bitcoin => bitmex_stream::Network::Mainnet,
+            _ => bitmex_stream::Network::Testnet,
+        };
+
+        Self {
+            client,
+            network,
+            credentials,
+        }
+    }
+}
+
+#[async_trait]
+impl HedgingVenue for BitmexVenue {
+    async fn place_order(&self, order: HedgeOrder) -> Result<OrderAck> {
+        let (contracts, side) = match order.contracts {
+            n @ 0..=i32::MAX => (n, Side::Buy),
+            n @ i32::MIN..=-1 => (n.abs(), Side::Sell),
+        };
+
+        let contract_symbol = match order.contract_symbol {
+            ContractSymbol::BtcUsd => bitmex_client::models::ContractSymbol::XbtUsd,
+        };
+
+        let order = match order.order_type {
+            HedgeOrderType::Market => {
+                self.client
+                    .create_order(contract_symbol, contracts, side, None)
+                    .await?
+            }
+            HedgeOrderType::PostOnlyLimit { price } => {
+                self.client
+                    .create_post_only_order(
+                        contract_symbol,
+                        contracts,
+                        side,
+                        price
+                            .to_f64()
+                            .with_context(|| format!("Invalid limit price {price}"))?,
+                        None,
+                    )
+                    .await?
+            }
+        };
+
+        order_ack(order)
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<OrderAck> {
+        let order_id = order_id.parse().context("Invalid BitMEX order ID")?;
+        let order = self.client.cancel_order(order_id).await?;
+
+        order_ack(order)
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderAck> {
+        let order_id = order_id.parse().context("Invalid BitMEX order ID")?;
+        let order = self.client.get_order(order_id).await?;
+
+        order_ack(order)
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>> {
+        let positions = self
+            .client
+            .positions()
+            .await?
+            .into_iter()
+            .map(|position| {
+                let current_qty = position.current_qty.unwrap_or_default();
+                let contracts = i32::try_from(current_qty)
+                    .with_context(|| format!("Position of {current_qty} contracts too large"))?;
+
+                Ok(Position {
+                    contract_symbol: match position.symbol {
+                        bitmex_client::models::ContractSymbol::XbtUsd => ContractSymbol::BtcUsd,
+                    },
+                    contracts,
+                    timestamp: position.timestamp.unwrap_or_else(OffsetDateTime::now_utc),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(positions)
+    }
+
+    async fn subscribe(&self) -> BoxStream<'static, Result<VenueEvent>> {
+        bitmex_ws_client::stream(self.network, self.credentials.clone())
+            .await
+            .boxed()
+    }
+}
+
+fn order_ack(order: bitmex_client::models::Order) -> Result<OrderAck> {
+    let cum_qty = order.cum_qty.unwrap_or_default();
+    let filled_contracts = i32::try_from(cum_qty)
+        .with_context(|| format!("Filled quantity {cum_qty} of order too large"))?;
+    let filled_contracts = match order.side {
+        Some(Side::Sell) => -filled_contracts,
+        _ => filled_contracts,
+    };
+
+    // If the status is unknown, we have to assume that the order may still be filled.
+    let is_open = !matches!(
+        order.ord_status,
+        Some(OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected)
+    );
+
+    Ok(OrderAck {
+        order_id: order.order_id.to_string(),
+        filled_contracts,
+        average_price: order.avg_px.and_then(Decimal::from_f64),
+        is_open,
+    })
+}
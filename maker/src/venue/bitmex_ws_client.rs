@@ -0,0 +1,270 @@
+use crate::venue::Fill;
+use crate::venue::Funding;
+use crate::venue::Position;
+use crate::venue::Quote;
+use crate::venue::VenueEvent;
+use anyhow::anyhow;
+use anyhow::Error;
+use anyhow::Result;
+use async_stream::stream;
+use bitmex_stream::models::Action;
+use bitmex_stream::models::ExecType;
+use bitmex_stream::models::Side;
+use bitmex_stream::models::Table;
+use bitmex_stream::models::TableData;
+use bitmex_stream::models::TableUpdate;
+use bitmex_stream::Credentials;
+use bitmex_stream::Network;
+use bitmex_stream::StreamHealth;
+use bitmex_stream::Subscription;
+use futures::Stream;
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+use trade::ContractSymbol;
+
+const XBT_USD: &str = "XBTUSD";
+
+/// Streams quotes and, if `credentials` are provided, position updates, fills and funding
+/// payments of the BitMEX account.
+///
+/// The stream yields an error and ends as soon as the connection to BitMEX is lost, so that the
+/// caller learns about the outage, e.g. to pull its quotes, before resubscribing.
+pub async fn stream(
+    network: Network,
+    credentials: Option<Credentials>,
+) -> impl Stream<Item = Result<VenueEvent, Error>> + Unpin {
+    let subscription = match credentials {
+        Some(credentials) => Subscription::new(
+            [
+                Table::QuoteBin1m.topic(XBT_USD),
+                Table::Position.topic(XBT_USD),
+                Table::Execution.topic(XBT_USD),
+            ],
+            network,
+        )
+        .with_credentials(credentials),
+        None => Subscription::new([Table::QuoteBin1m.topic(XBT_USD)], network),
+    };
+
+    let (mut updates, mut health) = subscription.start();
+
+    let stream = stream! {
+        loop {
+            tokio::select! {
+                Some(update) = updates.next() => {
+                    for event in events(update) {
+                        tracing::debug!(?event, "Received new event");
+
+                        yield Ok(event);
+                    }
+                }
+                Ok(()) = health.changed() => {
+                    let health = health.borrow().clone();
+                    match health {
+                        StreamHealth::Online => tracing::info!("BitMEX stream online"),
+                        StreamHealth::Connecting => {}
+                        StreamHealth::Reconnecting { error, .. } => {
+                            yield Err(anyhow!("Lost connection to BitMEX: {error}"));
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    stream.boxed()
+}
+
+fn contract_symbol(symbol: &str) -> Option<ContractSymbol> {
+    match symbol {
+        XBT_USD => Some(ContractSymbol::BtcUsd),
+        _ => None,
+    }
+}
+
+fn events(update: TableUpdate) -> Vec<VenueEvent> {
+    match update.data {
+        TableData::Quote(quotes) => quotes
+            .into_iter()
+            .filter_map(|quote| {
+                Some(VenueEvent::Quote(Quote {
+                    contract_symbol: contract_symbol(&quote.symbol)?,
+                    bid: quote.bid_price?,
+                    ask: quote.ask_price?,
+                    timestamp: quote.timestamp,
+                }))
+            })
+            .collect(),
+        // A deleted position is closed, which BitMEX also reports as an update to zero contracts.
+        TableData::Position(_) if update.action == Action::Delete => vec![],
+        TableData::Position(positions) => positions
+            .into_iter()
+            .filter_map(|position| {
+                let current_qty = position.current_qty.unwrap_or_default();
+                let contracts = match i32::try_from(current_qty) {
+                    Ok(contracts) => contracts,
+                    Err(_) => {
+                        tracing::warn!(current_qty, "Skipping position update with invalid size");
+                        return None;
+                    }
+                };
+
+                Some(VenueEvent::Position(Position {
+                    contract_symbol: contract_symbol(&position.symbol)?,
+                    contracts,
+                    timestamp: position.timestamp.unwrap_or_else(OffsetDateTime::now_utc),
+                }))
+            })
+            .collect(),
+        TableData::Execution(executions) => executions
+            .into_iter()
+            .filter_map(|execution| {
+                // Commissions are denominated in satoshis.
+                let fee = Decimal::new(execution.exec_comm.unwrap_or_default(), 8);
+
+                match execution.exec_type {
+                    ExecType::Trade => {
+                        let last_qty = execution.last_qty?;
+                        let last_qty = match i32::try_from(last_qty) {
+                            Ok(last_qty) => last_qty,
+                            Err(_) => {
+                                tracing::warn!(
+                                    exec_id = %execution.exec_id,
+                                    last_qty,
+                                    "Skipping fill with invalid size"
+                                );
+                                return None;
+                            }
+                        };
+                        let contracts = match execution.side? {
+                            Side::Buy => last_qty,
+                            Side::Sell => -last_qty,
+                        };
+
+                        Some(VenueEvent::Fill(Fill {
+                            execution_id: execution.exec_id,
+                            order_id: execution.order_id,
+                            contract_symbol: contract_symbol(&execution.symbol)?,
+                            contracts,
+                            price: execution.last_px?,
+                            fee,
+                            timestamp: execution.timestamp,
+                        }))
+                    }
+                    ExecType::Funding => Some(VenueEvent::Funding(Funding {
+                        execution_id: execution.exec_id,
+                        contract_symbol: contract_symbol(&execution.symbol)?,
+                        amount: fee,
+                        timestamp: execution.timestamp,
+                    })),
+                    // Other executions, e.g. order placements or cancellations, do not change our
+                    // position.
+                    _ => None,
+                }
+            })
+            .collect(),
+        // Not subscribed to.
+        TableData::Trade(_)
+        | TableData::OrderBookL2(_)
+        | TableData::Order(_)
+        | TableData::Margin(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitmex_stream::Tables;
+    use rust_decimal_macros::dec;
+
+    fn events_from(messages: &[&str]) -> Vec<VenueEvent> {
+        let mut tables = Tables::default();
+
+        messages
+            .iter()
+            .filter_map(|message| tables.apply_message(message).unwrap())
+            .flat_map(events)
+            .collect()
+    }
+
+    #[test]
+    fn quote_update_is_mapped_to_quote() {
+        let events = events_from(&[
+            r#"{"table":"quoteBin1m","action":"insert","data":[{"timestamp":"2021-09-21T02:40:00.000Z","symbol":"XBTUSD","bidSize":50200,"bidPrice":42640.5,"askPrice":42641,"askSize":363600}]}"#,
+        ]);
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            VenueEvent::Quote(quote) => {
+                assert_eq!(quote.contract_symbol, ContractSymbol::BtcUsd);
+                assert_eq!(quote.bid, dec!(42640.5));
+                assert_eq!(quote.ask, dec!(42641));
+                assert_eq!(quote.timestamp.unix_timestamp(), 1632192000);
+            }
+            _ => panic!("Unexpected event"),
+        }
+    }
+
+    #[test]
+    fn position_update_is_mapped_to_position() {
+        let events = events_from(&[
+            r#"{"table":"position","action":"partial","data":[{"account":396867,"symbol":"XBTUSD","currency":"XBt","currentQty":0,"timestamp":"2023-10-05T17:00:00.000Z"}]}"#,
+            r#"{"table":"position","action":"update","data":[{"account":396867,"symbol":"XBTUSD","currency":"XBt","currentQty":100,"markPrice":27452.26,"markValue":-364269,"riskValue":364269,"homeNotional":0.00364269,"maintMargin":65585,"unrealisedPnl":-6327,"unrealisedPnlPcnt":-0.0177,"unrealisedRoePcnt":-0.0884,"liquidationPrice":23349.5,"timestamp":"2023-10-05T17:36:45.781Z"}]}"#,
+        ]);
+
+        assert_eq!(events.len(), 2);
+        match &events[1] {
+            VenueEvent::Position(position) => {
+                assert_eq!(position.contract_symbol, ContractSymbol::BtcUsd);
+                assert_eq!(position.contracts, 100);
+                assert_eq!(position.timestamp.unix_timestamp(), 1696527405)
+            }
+            _ => panic!("Unexpected event"),
+        }
+    }
+
+    #[test]
+    fn trade_execution_is_mapped_to_fill() {
+        let events = events_from(&[
+            r#"{"table":"execution","action":"insert","data":[{"execID":"0193e879-cb6f-2891-d099-2c4eb40fee21","orderID":"00000000-0000-0000-0000-000000000000","symbol":"XBTUSD","side":"Sell","lastQty":100,"lastPx":27440.5,"execType":"Trade","execComm":273,"timestamp":"2023-10-05T17:36:45.781Z"},{"execID":"0193e879-cb6f-2891-d099-2c4eb40fee22","orderID":"00000000-0000-0000-0000-000000000001","symbol":"XBTUSD","side":"Buy","lastQty":null,"lastPx":null,"execType":"New","timestamp":"2023-10-05T17:36:45.781Z"}]}"#,
+        ]);
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            VenueEvent::Fill(fill) => {
+                assert_eq!(fill.order_id, "00000000-0000-0000-0000-000000000000");
+                assert_eq!(fill.contracts, -100);
+                assert_eq!(fill.price, dec!(27440.5));
+                assert_eq!(fill.fee, dec!(0.00000273));
+            }
+            _ => panic!("Unexpected event"),
+        }
+    }
+
+    #[test]
+    fn funding_execution_is_mapped_to_funding() {
+        let events = events_from(&[
+            r#"{"table":"execution","action":"insert","data":[{"execID":"0193e879-cb6f-2891-d099-2c4eb40fee23","orderID":"00000000-0000-0000-0000-000000000000","symbol":"XBTUSD","side":null,"lastQty":null,"lastPx":null,"execType":"Funding","execComm":-1520,"timestamp":"2023-10-05T20:00:00.000Z"}]}"#,
+        ]);
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            VenueEvent::Funding(funding) => {
+                assert_eq!(funding.execution_id, "0193e879-cb6f-2891-d099-2c4eb40fee23");
+                assert_eq!(funding.amount, dec!(-0.0000152));
+            }
+            _ => panic!("Unexpected event"),
+        }
+    }
+
+    #[test]
+    fn fill_with_invalid_size_is_skipped() {
+        let events = events_from(&[
+            r#"{"table":"execution","action":"insert","data":[{"execID":"0193e879-cb6f-2891-d099-2c4eb40fee21","orderID":"00000000-0000-0000-0000-000000000000","symbol":"XBTUSD","side":"Sell","lastQty":10000000000,"lastPx":27440.5,"execType":"Trade","execComm":273,"timestamp":"2023-10-05T17:36:45.781Z"}]}"#,
+        ]);
+
+        assert!(events.is_empty());
+    }
+}
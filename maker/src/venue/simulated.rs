@@ -0,0 +1,520 @@
+use crate::venue::Fill;
+use crate::venue::HedgeOrder;
+use crate::venue::HedgeOrderType;
+use crate::venue::HedgingVenue;
+use crate::venue::OrderAck;
+use crate::venue::Position;
+use crate::venue::Quote;
+use crate::venue::VenueEvent;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_decimal::RoundingStrategy;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use trade::ContractSymbol;
+use uuid::Uuid;
+
+/// The number of fills and position updates buffered for slow subscribers.
+const EVENT_BUFFER_SIZE: usize = 100;
+
+/// The fee for taking liquidity as a share of the notional value.
+const TAKER_FEE_RATE: Decimal = dec!(0.00075);
+
+/// The fee for providing liquidity as a share of the notional value. Negative, as makers receive
+/// a rebate.
+const MAKER_FEE_RATE: Decimal = dec!(-0.0001);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatedParams {
+    /// The mid price the simulation starts with.
+    pub initial_price: Decimal,
+    /// The distance between bid and ask.
+    pub spread: Decimal,
+    /// The maximum change of the mid price per quote in basis points.
+    pub volatility_bps: Decimal,
+    /// How often a new quote is published.
+    pub quote_interval: Duration,
+    /// How long it takes until an order is processed.
+    pub latency: Duration,
+    /// The share of every market order which is filled, between 0 and 1. The rest of the order
+    /// is cancelled. Resting limit orders are filled by the same share every time the price
+    /// crosses them.
+    pub fill_ratio: Decimal,
+    /// The probability of an order being rejected, between 0 and 1.
+    pub reject_rate: f64,
+    /// The seed of the random number generator, making the simulation reproducible.
+    pub seed: u64,
+}
+
+/// A local exchange which fills market orders at the current simulated bid or ask, and limit
+/// orders once the simulated price crosses them.
+///
+/// Allows running the maker without network access, e.g. in CI or on regtest.
+pub struct SimulatedExchange {
+    params: SimulatedParams,
+    state: Arc<Mutex<State>>,
+    events: broadcast::Sender<VenueEvent>,
+}
+
+struct State {
+    mid: Decimal,
+    positions: HashMap<ContractSymbol, i32>,
+    orders: HashMap<String, SimulatedOrder>,
+    rng: StdRng,
+}
+
+struct SimulatedOrder {
+    contract_symbol: ContractSymbol,
+    /// The number of contracts, with the sign representing the direction: positive buy; negative
+    /// sell.
+    contracts: i32,
+    filled_contracts: i32,
+    /// The sum of the price of all filled contracts.
+    filled_notional: Decimal,
+    /// The limit price of a resting order. `None` for market orders.
+    limit_price: Option<Decimal>,
+    is_open: bool,
+}
+
+impl SimulatedOrder {
+    fn ack(&self, order_id: &str) -> OrderAck {
+        let average_price = match self.filled_contracts {
+            0 => None,
+            n => Some(self.filled_notional / Decimal::from(n.abs())),
+        };
+
+        OrderAck {
+            order_id: order_id.to_string(),
+            filled_contracts: self.filled_contracts,
+            average_price,
+            is_open: self.is_open,
+        }
+    }
+}
+
+impl State {
+    fn quote(&self, spread: Decimal) -> Quote {
+        let half_spread = spread / Decimal::TWO;
+
+        Quote {
+            contract_symbol: ContractSymbol::BtcUsd,
+            bid: self.mid - half_spread,
+            ask: self.mid + half_spread,
+            timestamp: OffsetDateTime::now_utc(),
+        }
+    }
+
+    /// Moves the mid price randomly by at most `volatility_bps`.
+    fn move_price(&mut self, volatility_bps: Decimal) {
+        let change = Decimal::try_from(self.rng.gen_range(-1.0..=1.0))
+            .expect("to fit into decimal")
+            * volatility_bps
+            / Decimal::from(10_000);
+
+        self.mid = (self.mid * (Decimal::ONE + change)).round_dp(1);
+    }
+
+    /// Fills `contracts` of the order with the given ID at `price`.
+    fn fill(&mut self, order_id: &str, contracts: i32, price: Decimal) -> [VenueEvent; 2] {
+        let order = self.orders.get_mut(order_id).expect("order to exist");
+        order.filled_contracts += contracts;
+        order.filled_notional += Decimal::from(contracts.abs()) * price;
+        order.is_open &= order.filled_contracts != order.contracts;
+
+        let contract_symbol = order.contract_symbol;
+        let fee_rate = match order.limit_price {
+            Some(_) => MAKER_FEE_RATE,
+            None => TAKER_FEE_RATE,
+        };
+
+        let position = self.positions.entry(contract_symbol).or_insert(0);
+        *position += contracts;
+
+        let now = OffsetDateTime::now_utc();
+        [
+            VenueEvent::Fill(Fill {
+                execution_id: Uuid::new_v4().to_string(),
+                order_id: order_id.to_string(),
+                contract_symbol,
+                contracts,
+                price,
+                fee: Decimal::from(contracts.abs()) / price * fee_rate,
+                timestamp: now,
+            }),
+            VenueEvent::Position(Position {
+                contract_symbol,
+                contracts: *position,
+                timestamp: now,
+            }),
+        ]
+    }
+
+    /// Fills the resting limit orders which are crossed by the current quote.
+    fn match_resting_orders(&mut self, spread: Decimal, fill_ratio: Decimal) -> Vec<VenueEvent> {
+        let quote = self.quote(spread);
+
+        let fills = self
+            .orders
+            .iter()
+            .filter(|(_, order)| order.is_open)
+            .filter_map(|(order_id, order)| {
+                let price = order.limit_price?;
+                let is_crossed = if order.contracts.is_positive() {
+                    quote.ask <= price
+                } else {
+                    quote.bid >= price
+                };
+
+                if !is_crossed {
+                    return None;
+                }
+
+                let contracts = partial_fill(order.contracts - order.filled_contracts, fill_ratio);
+                (contracts != 0).then(|| (order_id.clone(), contracts, price))
+            })
+            .collect::<Vec<_>>();
+
+        fills
+            .into_iter()
+            .flat_map(|(order_id, contracts, price)| self.fill(&order_id, contracts, price))
+            .collect()
+    }
+}
+
+impl SimulatedExchange {
+    pub fn new(params: SimulatedParams) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+
+        Self {
+            params,
+            state: Arc::new(Mutex::new(State {
+                mid: params.initial_price,
+                positions: HashMap::new(),
+                orders: HashMap::new(),
+                rng: StdRng::seed_from_u64(params.seed),
+            })),
+            events,
+        }
+    }
+}
+
+#[async_trait]
+impl HedgingVenue for SimulatedExchange {
+    async fn place_order(&self, order: HedgeOrder) -> Result<OrderAck> {
+        tokio::time::sleep(self.params.latency).await;
+
+        let order_id = Uuid::new_v4().to_string();
+
+        let (ack, events) = {
+            let mut state = self.state.lock();
+
+            if state.rng.gen_bool(self.params.reject_rate) {
+                bail!("Order rejected by simulated exchange");
+            }
+
+            let quote = state.quote(self.params.spread);
+            let is_buy = order.contracts.is_positive();
+
+            let limit_price = match order.order_type {
+                HedgeOrderType::Market => None,
+                HedgeOrderType::PostOnlyLimit { price } => Some(price),
+            };
+
+            // A post-only order which would be filled immediately is cancelled instead.
+            let would_take = match limit_price {
+                Some(price) if is_buy => price >= quote.ask,
+                Some(price) => price <= quote.bid,
+                None => false,
+            };
+
+            state.orders.insert(
+                order_id.clone(),
+                SimulatedOrder {
+                    contract_symbol: order.contract_symbol,
+                    contracts: order.contracts,
+                    filled_contracts: 0,
+                    filled_notional: Decimal::ZERO,
+                    limit_price,
+                    is_open: limit_price.is_some() && !would_take,
+                },
+            );
+
+            let events = match order.order_type {
+                HedgeOrderType::Market => {
+                    let contracts = partial_fill(order.contracts, self.params.fill_ratio);
+                    let price = if is_buy { quote.ask } else { quote.bid };
+
+                    match contracts {
+                        0 => vec![],
+                        contracts => state.fill(&order_id, contracts, price).to_vec(),
+                    }
+                }
+                HedgeOrderType::PostOnlyLimit { .. } => vec![],
+            };
+
+            (state.orders[&order_id].ack(&order_id), events)
+        };
+
+        publish(&self.events, events);
+
+        Ok(ack)
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<OrderAck> {
+        tokio::time::sleep(self.params.latency).await;
+
+        let mut state = self.state.lock();
+        let order = state
+            .orders
+            .get_mut(order_id)
+            .with_context(|| format!("Unknown order {order_id}"))?;
+
+        order.is_open = false;
+
+        Ok(order.ack(order_id))
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderAck> {
+        let state = self.state.lock();
+        let order = state
+            .orders
+            .get(order_id)
+            .with_context(|| format!("Unknown order {order_id}"))?;
+
+        Ok(order.ack(order_id))
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>> {
+        let now = OffsetDateTime::now_utc();
+        let positions = self
+            .state
+            .lock()
+            .positions
+            .iter()
+            .map(|(contract_symbol, contracts)| Position {
+                contract_symbol: *contract_symbol,
+                contracts: *contracts,
+                timestamp: now,
+            })
+            .collect();
+
+        Ok(positions)
+    }
+
+    async fn subscribe(&self) -> BoxStream<'static, Result<VenueEvent>> {
+        let params = self.params;
+        let state = self.state.clone();
+        let sender = self.events.clone();
+        let mut events = self.events.subscribe();
+
+        let stream = stream! {
+            let mut interval = tokio::time::interval(params.quote_interval);
+
+            loop {
+                let event = tokio::select! {
+                    _ = interval.tick() => {
+                        let (quote, fills) = {
+                            let mut state = state.lock();
+                            state.move_price(params.volatility_bps);
+                            let fills =
+                                state.match_resting_orders(params.spread, params.fill_ratio);
+
+                            (state.quote(params.spread), fills)
+                        };
+
+                        // Fills of resting orders reach all subscribers, like those of market
+                        // orders.
+                        publish(&sender, fills);
+
+                        VenueEvent::Quote(quote)
+                    }
+                    event = events.recv() => match event {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, "Simulated exchange subscriber lagged behind");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+
+                yield Ok(event);
+            }
+        };
+
+        stream.boxed()
+    }
+}
+
+fn publish(sender: &broadcast::Sender<VenueEvent>, events: Vec<VenueEvent>) {
+    for event in events {
+        // Sending only fails if there are no subscribers, in which case nobody is interested in
+        // the event.
+        let _ = sender.send(event);
+    }
+}
+
+/// The share of `contracts` filled given the `fill_ratio`, rounded towards zero.
+fn partial_fill(contracts: i32, fill_ratio: Decimal) -> i32 {
+    (Decimal::from(contracts) * fill_ratio)
+        .round_dp_with_strategy(0, RoundingStrategy::ToZero)
+        .to_i32()
+        .expect("to fit into i32")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn params() -> SimulatedParams {
+        SimulatedParams {
+            initial_price: dec!(30_000),
+            spread: dec!(1),
+            volatility_bps: Decimal::ZERO,
+            quote_interval: Duration::from_millis(10),
+            latency: Duration::ZERO,
+            fill_ratio: Decimal::ONE,
+            reject_rate: 0.0,
+            seed: 0,
+        }
+    }
+
+    fn market_order(contracts: i32) -> HedgeOrder {
+        HedgeOrder {
+            contract_symbol: ContractSymbol::BtcUsd,
+            contracts,
+            order_type: HedgeOrderType::Market,
+        }
+    }
+
+    fn post_only_order(contracts: i32, price: Decimal) -> HedgeOrder {
+        HedgeOrder {
+            contract_symbol: ContractSymbol::BtcUsd,
+            contracts,
+            order_type: HedgeOrderType::PostOnlyLimit { price },
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_market_orders_at_bid_and_ask() {
+        let exchange = SimulatedExchange::new(params());
+        let mut events = exchange.subscribe().await;
+
+        let buy = exchange.place_order(market_order(200)).await.unwrap();
+        let sell = exchange.place_order(market_order(-100)).await.unwrap();
+
+        assert_eq!(buy.filled_contracts, 200);
+        assert_eq!(buy.average_price, Some(dec!(30_000.5)));
+        assert_eq!(sell.filled_contracts, -100);
+        assert_eq!(sell.average_price, Some(dec!(29_999.5)));
+
+        let mut fills = vec![];
+        while fills.len() < 2 {
+            if let VenueEvent::Fill(fill) = events.next().await.unwrap().unwrap() {
+                fills.push((fill.contracts, fill.price));
+            }
+        }
+
+        assert_eq!(fills, vec![(200, dec!(30_000.5)), (-100, dec!(29_999.5))]);
+
+        let positions = exchange.get_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].contracts, 100);
+    }
+
+    #[tokio::test]
+    async fn fills_orders_partially() {
+        let exchange = SimulatedExchange::new(SimulatedParams {
+            fill_ratio: dec!(0.5),
+            ..params()
+        });
+
+        exchange.place_order(market_order(-301)).await.unwrap();
+
+        let positions = exchange.get_positions().await.unwrap();
+        assert_eq!(positions[0].contracts, -150);
+    }
+
+    #[tokio::test]
+    async fn rejects_orders() {
+        let exchange = SimulatedExchange::new(SimulatedParams {
+            reject_rate: 1.0,
+            ..params()
+        });
+
+        let result = exchange.place_order(market_order(100)).await;
+
+        assert!(result.is_err());
+        assert!(exchange.get_positions().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn post_only_order_is_cancelled_instead_of_taking() {
+        let exchange = SimulatedExchange::new(params());
+
+        let ack = exchange
+            .place_order(post_only_order(100, dec!(30_000.5)))
+            .await
+            .unwrap();
+
+        assert_eq!(ack.filled_contracts, 0);
+        assert!(!exchange.state.lock().orders[&ack.order_id].is_open);
+    }
+
+    #[tokio::test]
+    async fn fills_resting_orders_once_price_crosses() {
+        let exchange = SimulatedExchange::new(params());
+
+        let ack = exchange
+            .place_order(post_only_order(-100, dec!(30_000)))
+            .await
+            .unwrap();
+        assert_eq!(ack.filled_contracts, 0);
+
+        exchange.state.lock().mid = dec!(30_001);
+        let mut events = exchange.subscribe().await;
+
+        let fill = loop {
+            if let VenueEvent::Fill(fill) = events.next().await.unwrap().unwrap() {
+                break fill;
+            }
+        };
+
+        assert_eq!(fill.contracts, -100);
+        assert_eq!(fill.price, dec!(30_000));
+
+        let ack = exchange.cancel_order(&ack.order_id).await.unwrap();
+        assert_eq!(ack.filled_contracts, -100);
+        assert_eq!(ack.average_price, Some(dec!(30_000)));
+    }
+
+    #[tokio::test]
+    async fn publishes_quotes() {
+        let exchange = SimulatedExchange::new(params());
+        let mut events = exchange.subscribe().await;
+
+        match events.next().await.unwrap().unwrap() {
+            VenueEvent::Quote(quote) => {
+                assert_eq!(quote.bid, dec!(29_999.5));
+                assert_eq!(quote.ask, dec!(30_000.5));
+            }
+            event => panic!("Unexpected event {event:?}"),
+        }
+    }
+}
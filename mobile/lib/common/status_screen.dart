@@ -22,6 +22,8 @@ class _StatusScreenState extends State<StatusScreen> {
         serviceStatusToString(serviceStatusNotifier.getServiceStatus(Service.Orderbook));
     final coordinatorStatus =
         serviceStatusToString(serviceStatusNotifier.getServiceStatus(Service.Coordinator));
+    final watchtowerStatus =
+        serviceStatusToString(serviceStatusNotifier.getServiceStatus(Service.Watchtower));
     final overallStatus = serviceStatusToString(serviceStatusNotifier.overall());
 
     ChannelStatusNotifier channelStatusNotifier = context.watch<ChannelStatusNotifier>();
@@ -54,6 +56,13 @@ class _StatusScreenState extends State<StatusScreen> {
                 label: "LSP",
                 valueTextStyle: const TextStyle(fontWeight: FontWeight.bold),
               ),
+              const SizedBox(height: 10),
+              ValueDataRow(
+                type: ValueType.text,
+                value: watchtowerStatus,
+                label: "Watchtower",
+                valueTextStyle: const TextStyle(fontWeight: FontWeight.bold),
+              ),
             ],
           )),
       Padding(
@@ -56,6 +56,10 @@ class WalletService {
         return OnChainAddress.fromApi(result);
       } else if (result is rust.Destination_OnChainAddress) {
         return OnChainAddress.fromAddress(result);
+      } else if (result is rust.Destination_Node) {
+        return NodeDestination.fromApi(result, destination);
+      } else if (result is rust.Destination_LnUrlPay) {
+        return LnUrlPayDestination.fromApi(result);
       } else {
         return null;
       }
@@ -65,9 +69,22 @@ class WalletService {
     }
   }
 
-  Future<void> sendPayment(Destination destination, Amount? amount) async {
+  Future<void> sendPayment(Destination destination, Amount? amount, {String? comment}) async {
     logger.i("Sending payment of $amount");
 
+    rust.SendPayment payment;
+    if (destination is NodeDestination) {
+      payment = rust.SendPayment_Keysend(pubkey: destination.payee, amount: amount!.sats);
+    } else if (destination is LnUrlPayDestination) {
+      payment =
+          rust.SendPayment_LnUrlPay(lnurl: destination.raw, amount: amount!.sats, comment: comment);
+    } else {
+      payment = _sendPayment(destination, amount);
+    }
+    await rust.api.sendPayment(payment: payment);
+  }
+
+  rust.SendPayment _sendPayment(Destination destination, Amount? amount) {
     rust.SendPayment payment;
     switch (destination.getWalletType()) {
       case WalletType.lightning:
@@ -77,7 +94,12 @@ class WalletService {
       default:
         throw Exception("unsupported wallet type: ${destination.getWalletType().name}");
     }
-    await rust.api.sendPayment(payment: payment);
+    return payment;
+  }
+
+  /// Receives [amount] from an LNURL-withdraw link.
+  Future<void> withdrawLnUrl(String lnurl, Amount amount) async {
+    await rust.api.withdrawLnurl(lnurl: lnurl, amountSats: amount.sats);
   }
 
   String getUnusedAddress() {
@@ -71,3 +71,50 @@ class LightningInvoice extends Destination {
     return WalletType.lightning;
   }
 }
+
+/// A node which is paid via keysend, i.e. without an invoice.
+class NodeDestination extends Destination {
+  NodeDestination({required super.payee, required super.raw}) : super(amount: Amount.zero());
+
+  static fromApi(rust.Destination_Node node, String raw) {
+    return NodeDestination(payee: node.pubkey, raw: raw);
+  }
+
+  @override
+  WalletType getWalletType() {
+    return WalletType.lightning;
+  }
+}
+
+/// An LNURL-pay link or a Lightning Address.
+class LnUrlPayDestination extends Destination {
+  final Amount minSendable;
+  final Amount maxSendable;
+
+  /// The maximum length of a comment, 0 if no comment is accepted.
+  final int commentAllowed;
+
+  LnUrlPayDestination(
+      {required super.description,
+      required super.payee,
+      required super.raw,
+      required this.minSendable,
+      required this.maxSendable,
+      required this.commentAllowed})
+      : super(amount: Amount.zero());
+
+  static fromApi(rust.Destination_LnUrlPay request) {
+    return LnUrlPayDestination(
+        description: request.description,
+        payee: request.lightningAddress ?? request.lnurl,
+        raw: request.lnurl,
+        minSendable: Amount(request.minSendableSats),
+        maxSendable: Amount(request.maxSendableSats),
+        commentAllowed: request.commentAllowed);
+  }
+
+  @override
+  WalletType getWalletType() {
+    return WalletType.lightning;
+  }
+}
@@ -107,7 +107,8 @@ abstract class WalletHistoryItemData {
         paymentHash: type.paymentHash,
         feeMsats: type.feeMsat,
         expiry: expiry,
-        invoice: type.invoice);
+        invoice: type.invoice,
+        failureReason: type.failureReason);
   }
 }
 
@@ -118,6 +119,7 @@ class LightningPaymentData extends WalletHistoryItemData {
   final String? invoice;
   final DateTime? expiry;
   final int? feeMsats;
+  final String? failureReason;
 
   LightningPaymentData(
       {required super.flow,
@@ -129,7 +131,8 @@ class LightningPaymentData extends WalletHistoryItemData {
       required this.invoice,
       required this.expiry,
       required this.feeMsats,
-      required this.paymentHash});
+      required this.paymentHash,
+      this.failureReason});
 
   @override
   WalletHistoryItem toWidget() {
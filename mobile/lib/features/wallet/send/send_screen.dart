@@ -204,6 +204,15 @@ class _SendScreenState extends State<SendScreen> {
                     return "Missing destination";
                   }
 
+                  final destination = _destination;
+                  if (destination is LnUrlPayDestination &&
+                      (amount.sats < destination.minSendable.sats ||
+                          amount.sats > destination.maxSendable.sats)) {
+                    final min = formatSats(destination.minSendable);
+                    final max = formatSats(destination.maxSendable);
+                    return "Amount must be between $min and $max.";
+                  }
+
                   final bal = balance[_destination!.getWalletType()]!.$1;
                   if (amount.sats > bal.sats) {
                     return "Not enough funds.";
@@ -281,6 +281,10 @@ class LightningPaymentHistoryItem extends WalletHistoryItem {
   @override
   List<Widget> getDetails() {
     return [
+      Visibility(
+        visible: data.failureReason != null,
+        child: HistoryDetail(label: "Failure reason", value: data.failureReason ?? ''),
+      ),
       Visibility(
         visible: data.feeMsats != null && data.flow == PaymentFlow.outbound,
         child: HistoryDetail(label: "Fee", value: "${(data.feeMsats ?? 0) / 1000} sats"),
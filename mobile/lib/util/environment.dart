@@ -10,6 +10,10 @@ class Environment {
     int httpPort = const int.fromEnvironment("COORDINATOR_PORT_HTTP", defaultValue: 8000);
     String esploraEndpoint =
         const String.fromEnvironment("ESPLORA_ENDPOINT", defaultValue: "http://127.0.0.1:3000");
+    // If set, the app uses the Electrum server instead of Esplora.
+    String electrumEndpoint = const String.fromEnvironment("ELECTRUM_ENDPOINT");
+    // If set, the app hands the penalties of revoked channel transactions to this watchtower.
+    String watchtowerEndpoint = const String.fromEnvironment("WATCHTOWER_ENDPOINT");
     String network = const String.fromEnvironment('NETWORK', defaultValue: "regtest");
     String oracleEndpoint =
         const String.fromEnvironment("ORACLE_ENDPOINT", defaultValue: "http://127.0.0.1:8081");
@@ -32,6 +36,7 @@ class Environment {
     return Config(
       host: host,
       esploraEndpoint: esploraEndpoint,
+      electrumEndpoint: electrumEndpoint.isEmpty ? null : electrumEndpoint,
       coordinatorPubkey: coordinatorPublicKey,
       p2PPort: lightningPort,
       httpPort: httpPort,
@@ -39,6 +44,7 @@ class Environment {
       oracleEndpoint: oracleEndpoint,
       oraclePubkey: oraclePubkey,
       healthCheckIntervalSecs: healthCheckIntervalSeconds,
+      watchtowerEndpoint: watchtowerEndpoint.isEmpty ? null : watchtowerEndpoint,
     );
   }
 }
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE
+    payments
+    ADD
+        COLUMN "failure_reason" TEXT;
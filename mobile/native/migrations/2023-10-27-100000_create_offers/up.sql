@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE "offers" (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    -- NULL if the offer does not specify an amount
+    amount_sats BIGINT,
+    offer TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
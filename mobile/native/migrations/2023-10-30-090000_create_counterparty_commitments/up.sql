@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE "counterparty_commitments" (
+    txid TEXT PRIMARY KEY NOT NULL,
+    channel_keys_id TEXT NOT NULL,
+    commitment_number BIGINT NOT NULL,
+    raw TEXT NOT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT false
+);
@@ -16,21 +16,27 @@ use crate::orderbook;
 use crate::trade::order;
 use crate::trade::order::api::NewOrder;
 use crate::trade::order::api::Order;
+use crate::trade::order::api::OrderType;
 use crate::trade::position;
 use crate::trade::position::api::Position;
 use crate::trade::users;
 use anyhow::Context;
 use anyhow::Result;
+use bdk::FeeRate;
+use bitcoin::consensus::encode::serialize_hex;
 use bitcoin::Amount;
+use bitcoin::OutPoint;
 use flutter_rust_bridge::frb;
 use flutter_rust_bridge::StreamSink;
 use flutter_rust_bridge::SyncReturn;
 use ln_dlc_node::channel::UserChannelId;
+use ln_dlc_node::lightning::chain::chaininterface::ConfirmationTarget;
 use orderbook_commons::order_matching_fee_taker;
 use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::Decimal;
 use state::Storage;
 use std::backtrace::Backtrace;
+use std::str::FromStr;
 use time::OffsetDateTime;
 pub use trade::ContractSymbol;
 pub use trade::Direction;
@@ -86,6 +92,8 @@ pub enum WalletHistoryItemType {
         invoice: Option<String>,
         fee_msat: Option<u64>,
         expiry_timestamp: Option<u64>,
+        /// Why the payment failed, if it did.
+        failure_reason: Option<String>,
     },
     Trade {
         order_id: String,
@@ -170,6 +178,23 @@ pub fn calculate_pnl(
     )
 }
 
+/// Calculate the expected average execution price of a market order based on the current state
+/// of the orderbook. Returns `None` if there is not enough liquidity to fill the order.
+pub fn calculate_expected_fill_price(
+    quantity: f32,
+    direction: Direction,
+) -> Result<SyncReturn<Option<f32>>> {
+    let fill_price = calculations::calculate_expected_fill_price(quantity, direction)?;
+    Ok(SyncReturn(fill_price))
+}
+
+/// Calculate the expected slippage of a market order in percent, relative to the best price in
+/// the orderbook. Returns `None` if there is not enough liquidity to fill the order.
+pub fn calculate_slippage(quantity: f32, direction: Direction) -> Result<SyncReturn<Option<f32>>> {
+    let slippage = calculations::calculate_slippage(quantity, direction)?;
+    Ok(SyncReturn(slippage))
+}
+
 /// Calculate the order matching fee that the app user will have to pay for if the corresponding
 /// trade gets executed.
 ///
@@ -185,7 +210,15 @@ pub fn order_matching_fee(quantity: f32, price: f32) -> SyncReturn<u64> {
 
 #[tokio::main(flavor = "current_thread")]
 pub async fn submit_order(order: NewOrder) -> Result<String> {
-    order::handler::submit_order(order.into())
+    let worst_price = match (*order.order_type, order.max_slippage) {
+        (OrderType::Market, Some(max_slippage)) => Some(
+            calculations::calculate_worst_price(order.direction, max_slippage)?
+                .context("Cannot limit slippage without a price in the orderbook")?,
+        ),
+        _ => None,
+    };
+
+    order::handler::submit_order(order.into(), worst_price)
         .await
         .map(|id| id.to_string())
 }
@@ -278,7 +311,7 @@ pub fn run(
     let runtime = ln_dlc::get_or_create_tokio_runtime()?;
     ln_dlc::run(app_dir, seed_dir, runtime)?;
 
-    let (_health, tx) = health::Health::new(config, runtime);
+    let (_health, tx) = health::Health::new(config, runtime, ln_dlc::get_watchtower_status());
 
     orderbook::subscribe(ln_dlc::get_node_key(), runtime, tx.orderbook, fcm_token)
 }
@@ -375,6 +408,8 @@ pub fn create_onboarding_invoice(amount_sats: u64, liquidity_option_id: i32) ->
 pub struct PaymentRequest {
     pub bip21: String,
     pub lightning: String,
+    /// A reusable BOLT12 offer, if BOLT12 is enabled.
+    pub offer: Option<String>,
 }
 
 pub fn create_payment_request(amount_sats: Option<u64>) -> Result<PaymentRequest> {
@@ -383,9 +418,17 @@ pub fn create_payment_request(amount_sats: Option<u64>) -> Result<PaymentRequest
         .unwrap_or_default();
     let addr = ln_dlc::get_unused_address();
 
+    #[cfg(feature = "bolt12")]
+    let offer = ln_dlc::get_or_create_offer(amount_sats)
+        .map_err(|e| tracing::warn!("Failed to get BOLT12 offer: {e:#}"))
+        .ok();
+    #[cfg(not(feature = "bolt12"))]
+    let offer = None;
+
     Ok(PaymentRequest {
         bip21: format!("bitcoin:{addr}{amount_query}"),
         lightning: ln_dlc::create_invoice(amount_sats)?.to_string(),
+        offer,
     })
 }
 
@@ -398,12 +441,221 @@ pub enum SendPayment {
         address: String,
         amount: u64,
     },
+    /// A spontaneous payment to a node, which does not need an invoice.
+    Keysend {
+        pubkey: String,
+        amount: u64,
+    },
+    /// A payment to an LNURL-pay link or a Lightning Address.
+    LnUrlPay {
+        lnurl: String,
+        amount: u64,
+        comment: Option<String>,
+    },
+    /// A payment to a BOLT12 offer, with `amount` if the offer does not specify one.
+    ///
+    /// Fails if the app has been built without BOLT12 support.
+    Bolt12Offer {
+        offer: String,
+        amount: Option<u64>,
+    },
 }
 
 pub fn send_payment(payment: SendPayment) -> Result<()> {
     ln_dlc::send_payment(payment)
 }
 
+pub struct PaymentProbe {
+    /// The estimated routing fee.
+    pub fee_sats: u64,
+    /// Whether the payment is likely to succeed; `None` if the probe did not complete in time.
+    pub succeeded: Option<bool>,
+}
+
+/// Probes the route to the recipient of `invoice` without paying it, to estimate the routing fee
+/// and whether the payment will succeed.
+pub fn probe_payment(invoice: String, amount: Option<u64>) -> Result<PaymentProbe> {
+    let probe = ln_dlc::probe_payment(&invoice, amount)?;
+
+    Ok(PaymentProbe {
+        fee_sats: (probe.fee_msat + 999) / 1000,
+        succeeded: probe.succeeded,
+    })
+}
+
+/// Receives `amount_sats` from an LNURL-withdraw link.
+///
+/// The payment arrives like any other payment to one of our invoices.
+pub fn withdraw_lnurl(lnurl: String, amount_sats: u64) -> Result<()> {
+    ln_dlc::withdraw_lnurl(&lnurl, amount_sats)
+}
+
+/// Claims a BOLT12 refund.
+///
+/// Fails if the app has been built without BOLT12 support.
+pub fn claim_refund(refund: String) -> Result<()> {
+    ln_dlc::claim_refund(&refund)
+}
+
+pub enum Fee {
+    /// The estimated fee rate for a confirmation within about 3 blocks.
+    HighPriority,
+    /// The estimated fee rate for a confirmation within about 6 blocks.
+    Normal,
+    /// The estimated fee rate for a confirmation at some point.
+    Background,
+    /// An explicit fee rate, in sats/vbyte.
+    FeeRate { sats_per_vbyte: f32 },
+}
+
+impl From<Fee> for ln_dlc_node::Fee {
+    fn from(value: Fee) -> Self {
+        match value {
+            Fee::HighPriority => ln_dlc_node::Fee::Priority(ConfirmationTarget::HighPriority),
+            Fee::Normal => ln_dlc_node::Fee::Priority(ConfirmationTarget::Normal),
+            Fee::Background => ln_dlc_node::Fee::Priority(ConfirmationTarget::Background),
+            Fee::FeeRate { sats_per_vbyte } => {
+                ln_dlc_node::Fee::FeeRate(FeeRate::from_sat_per_vb(sats_per_vbyte))
+            }
+        }
+    }
+}
+
+pub struct OnChainPayment {
+    pub address: String,
+    /// The amount to send in sats, or `None` to send all available funds.
+    pub amount: Option<u64>,
+    pub fee: Fee,
+    /// The outpoints (`txid:vout`) of the UTXOs to spend. If empty, the UTXOs are selected
+    /// automatically.
+    pub utxos: Vec<String>,
+    /// The `raw_tx` of the dry run confirmed by the user. If set, exactly this transaction is
+    /// sent instead of creating a new one.
+    pub raw_tx: Option<String>,
+}
+
+pub struct PreparedOnChainPayment {
+    pub txid: String,
+    /// The amount received by the recipient, in sats.
+    pub amount: u64,
+    pub fee: u64,
+    /// In sats/vbyte.
+    pub fee_rate: f32,
+    pub raw_tx: String,
+}
+
+impl From<ln_dlc_node::PreparedPayment> for PreparedOnChainPayment {
+    fn from(value: ln_dlc_node::PreparedPayment) -> Self {
+        Self {
+            txid: value.transaction.txid().to_string(),
+            amount: value.amount,
+            fee: value.fee,
+            fee_rate: value.fee_rate(),
+            raw_tx: serialize_hex(&value.transaction),
+        }
+    }
+}
+
+pub struct Utxo {
+    pub outpoint: String,
+    pub amount: u64,
+}
+
+/// Returns the UTXOs of the on-chain wallet, e.g. to select the ones to spend with
+/// [`send_on_chain_payment`].
+pub fn list_utxos() -> Result<Vec<Utxo>> {
+    let utxos = ln_dlc::list_utxos()?
+        .into_iter()
+        .map(|utxo| Utxo {
+            outpoint: utxo.outpoint.to_string(),
+            amount: utxo.txout.value,
+        })
+        .collect();
+
+    Ok(utxos)
+}
+
+/// Sends an on-chain payment.
+///
+/// With `dry_run` the transaction is only created, so that its fee can be confirmed by the user
+/// before sending the payment for real by passing back its `raw_tx`.
+pub fn send_on_chain_payment(
+    payment: OnChainPayment,
+    dry_run: bool,
+) -> Result<PreparedOnChainPayment> {
+    let utxos = payment
+        .utxos
+        .iter()
+        .map(|outpoint| OutPoint::from_str(outpoint))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Invalid outpoint")?;
+
+    let payment = ln_dlc::send_on_chain_payment(
+        &payment.address,
+        payment.amount,
+        payment.fee.into(),
+        &utxos,
+        payment.raw_tx.as_deref(),
+        dry_run,
+    )?;
+
+    Ok(payment.into())
+}
+
+pub struct UnconfirmedTransaction {
+    pub txid: String,
+    pub fee: Option<u64>,
+    /// In sats/vbyte.
+    pub fee_rate: Option<f32>,
+    /// Whether the fee can be bumped by replacing the transaction.
+    pub is_rbf: bool,
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl From<ln_dlc_node::UnconfirmedTransaction> for UnconfirmedTransaction {
+    fn from(value: ln_dlc_node::UnconfirmedTransaction) -> Self {
+        Self {
+            txid: value.txid.to_string(),
+            fee: value.fee,
+            fee_rate: value.fee_rate,
+            is_rbf: value.is_rbf,
+            sent: value.sent,
+            received: value.received,
+        }
+    }
+}
+
+pub enum FeeBumpMethod {
+    Rbf,
+    Cpfp,
+}
+
+impl From<FeeBumpMethod> for ln_dlc_node::node::FeeBumpMethod {
+    fn from(value: FeeBumpMethod) -> Self {
+        match value {
+            FeeBumpMethod::Rbf => ln_dlc_node::node::FeeBumpMethod::Rbf,
+            FeeBumpMethod::Cpfp => ln_dlc_node::node::FeeBumpMethod::Cpfp,
+        }
+    }
+}
+
+/// Returns the on-chain transactions of the wallet which are waiting for confirmation.
+pub fn unconfirmed_transactions() -> Result<Vec<UnconfirmedTransaction>> {
+    let transactions = ln_dlc::unconfirmed_transactions()?;
+    Ok(transactions
+        .into_iter()
+        .map(UnconfirmedTransaction::from)
+        .collect())
+}
+
+/// Bumps the fee of an unconfirmed transaction to `fee_rate` sats/vbyte and returns the ID of the
+/// replacement or child transaction.
+pub fn bump_fee(txid: String, fee_rate: f32, method: FeeBumpMethod) -> Result<String> {
+    let txid = ln_dlc::bump_fee(&txid, fee_rate, method.into())?;
+    Ok(txid.to_string())
+}
+
 pub struct LastLogin {
     pub id: i32,
     pub date: String,
@@ -439,6 +691,39 @@ pub enum Destination {
         message: String,
         amount_sats: Option<u64>,
     },
+    /// A node ID, to be paid via keysend.
+    Node {
+        pubkey: String,
+    },
+    /// An LNURL-pay link or a Lightning Address.
+    LnUrlPay {
+        lnurl: String,
+        description: String,
+        lightning_address: Option<String>,
+        min_sendable_sats: u64,
+        max_sendable_sats: u64,
+        /// The maximum length of a comment, 0 if no comment is accepted.
+        comment_allowed: u16,
+    },
+    /// An LNURL-withdraw link, to receive a payment with [`withdraw_lnurl`].
+    LnUrlWithdraw {
+        lnurl: String,
+        description: String,
+        min_withdrawable_sats: u64,
+        max_withdrawable_sats: u64,
+    },
+    /// A BOLT12 offer.
+    Bolt12Offer {
+        offer: String,
+        description: String,
+        amount_sats: Option<u64>,
+    },
+    /// A BOLT12 refund, to receive a payment with [`claim_refund`].
+    Bolt12Refund {
+        refund: String,
+        description: String,
+        amount_sats: u64,
+    },
 }
 
 pub fn decode_destination(destination: String) -> Result<Destination> {
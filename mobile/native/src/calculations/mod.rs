@@ -1,4 +1,8 @@
+use crate::orderbook;
+use anyhow::ensure;
+use anyhow::Context;
 use anyhow::Result;
+use orderbook_client::LocalOrderbook;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use trade::cfd;
@@ -59,3 +63,90 @@ pub fn calculate_liquidation_price(price: f32, leverage: f32, direction: Directi
 
     liquidation_price
 }
+
+/// Calculate the expected average execution price of a market order from the latest known state
+/// of the orderbook.
+///
+/// Returns `None` if the orderbook does not hold enough liquidity to fill the given quantity.
+pub fn calculate_expected_fill_price(quantity: f32, direction: Direction) -> Result<Option<f32>> {
+    let quantity =
+        Decimal::try_from(quantity).with_context(|| format!("Invalid quantity {quantity}"))?;
+
+    let fill_price = orderbook::get_local_orderbook().expected_fill_price(direction, quantity);
+
+    Ok(fill_price.and_then(|price| price.to_f32()))
+}
+
+/// Calculate the expected slippage of a market order in percent, i.e. by how much the expected
+/// execution price is worse than the best price in the orderbook.
+///
+/// Returns `None` if the orderbook does not hold enough liquidity to fill the given quantity.
+pub fn calculate_slippage(quantity: f32, direction: Direction) -> Result<Option<f32>> {
+    let quantity =
+        Decimal::try_from(quantity).with_context(|| format!("Invalid quantity {quantity}"))?;
+
+    let orderbook = orderbook::get_local_orderbook();
+    let best_price = match best_price_for_direction(&orderbook, direction) {
+        Some(best_price) => best_price,
+        None => return Ok(None),
+    };
+    let fill_price = match orderbook.expected_fill_price(direction, quantity) {
+        Some(fill_price) => fill_price,
+        None => return Ok(None),
+    };
+
+    let slippage = match direction {
+        Direction::Long => fill_price - best_price,
+        Direction::Short => best_price - fill_price,
+    };
+
+    Ok((slippage / best_price * Decimal::ONE_HUNDRED).to_f32())
+}
+
+/// Calculate the worst acceptable execution price of a market order which may deviate at most
+/// `max_slippage` percent from the best price in the orderbook.
+///
+/// Returns `None` if there is no price in the orderbook for the given direction. Fails if
+/// `max_slippage` is not between 0 and 100 percent.
+pub fn calculate_worst_price(direction: Direction, max_slippage: f32) -> Result<Option<Decimal>> {
+    let max_slippage = Decimal::try_from(max_slippage)
+        .with_context(|| format!("Invalid max slippage {max_slippage}"))?;
+    ensure!(
+        (Decimal::ZERO..=Decimal::ONE_HUNDRED).contains(&max_slippage),
+        "Max slippage must be between 0 and 100 percent, got {max_slippage}"
+    );
+
+    let best_price = match best_price_for_direction(&orderbook::get_local_orderbook(), direction) {
+        Some(best_price) => best_price,
+        None => return Ok(None),
+    };
+
+    let deviation = best_price * max_slippage / Decimal::ONE_HUNDRED;
+    let worst_price = match direction {
+        Direction::Long => best_price + deviation,
+        Direction::Short => best_price - deviation,
+    };
+
+    Ok(Some(worst_price))
+}
+
+/// The best price a market order of the given direction can be filled at, i.e. the best ask for
+/// going long and the best bid for going short.
+fn best_price_for_direction(orderbook: &LocalOrderbook, direction: Direction) -> Option<Decimal> {
+    let best_price = orderbook.best_price();
+    match direction {
+        Direction::Long => best_price.ask,
+        Direction::Short => best_price.bid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_slippage_out_of_range_is_rejected() {
+        assert!(calculate_worst_price(Direction::Long, -0.1).is_err());
+        assert!(calculate_worst_price(Direction::Short, 100.1).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use crate::config::ConfigInternal;
 use bdk::bitcoin::Network;
 use bdk::bitcoin::XOnlyPublicKey;
 use flutter_rust_bridge::frb;
+use ln_dlc_node::ChainSource;
 use std::str::FromStr;
 
 #[frb]
@@ -9,6 +10,8 @@ use std::str::FromStr;
 pub struct Config {
     pub coordinator_pubkey: String,
     pub esplora_endpoint: String,
+    /// If set, the Electrum server is used as the chain source instead of Esplora.
+    pub electrum_endpoint: Option<String>,
     pub host: String,
     pub p2p_port: u16,
     pub http_port: u16,
@@ -16,6 +19,8 @@ pub struct Config {
     pub oracle_endpoint: String,
     pub oracle_pubkey: String,
     pub health_check_interval_secs: u64,
+    /// If set, the penalties of revoked channel transactions are handed to this watchtower.
+    pub watchtower_endpoint: Option<String>,
 }
 
 impl From<Config> for ConfigInternal {
@@ -23,7 +28,15 @@ impl From<Config> for ConfigInternal {
         tracing::debug!(?config, "Parsing config from flutter");
         Self {
             coordinator_pubkey: config.coordinator_pubkey.parse().expect("PK to be valid"),
-            esplora_endpoint: config.esplora_endpoint,
+            chain_source: match config.electrum_endpoint {
+                #[cfg(feature = "electrum")]
+                Some(url) => ChainSource::Electrum { url },
+                #[cfg(not(feature = "electrum"))]
+                Some(_) => panic!("Electrum endpoint requires the `electrum` feature"),
+                None => ChainSource::Esplora {
+                    url: config.esplora_endpoint,
+                },
+            },
             http_endpoint: format!("{}:{}", config.host, config.http_port)
                 .parse()
                 .expect("host and http_port to be valid"),
@@ -37,6 +50,7 @@ impl From<Config> for ConfigInternal {
             health_check_interval: std::time::Duration::from_secs(
                 config.health_check_interval_secs,
             ),
+            watchtower_endpoint: config.watchtower_endpoint,
         }
     }
 }
@@ -6,6 +6,7 @@ use bdk::bitcoin::secp256k1::PublicKey;
 use bdk::bitcoin::XOnlyPublicKey;
 use ln_dlc_node::node::NodeInfo;
 use ln_dlc_node::node::OracleInfo;
+use ln_dlc_node::ChainSource;
 use state::Storage;
 use std::net::SocketAddr;
 use std::time::Duration;
@@ -15,13 +16,14 @@ static CONFIG: Storage<ConfigInternal> = Storage::new();
 #[derive(Clone)]
 pub struct ConfigInternal {
     coordinator_pubkey: PublicKey,
-    esplora_endpoint: String,
+    chain_source: ChainSource,
     http_endpoint: SocketAddr,
     p2p_endpoint: SocketAddr,
     network: bitcoin::Network,
     oracle_endpoint: String,
     oracle_pubkey: XOnlyPublicKey,
     health_check_interval: Duration,
+    watchtower_endpoint: Option<String>,
 }
 
 impl ConfigInternal {
@@ -46,8 +48,8 @@ pub fn get_coordinator_info() -> NodeInfo {
     }
 }
 
-pub fn get_esplora_endpoint() -> String {
-    CONFIG.get().esplora_endpoint.clone()
+pub fn get_chain_source() -> ChainSource {
+    CONFIG.get().chain_source.clone()
 }
 
 pub fn get_oracle_info() -> OracleInfo {
@@ -65,3 +67,7 @@ pub fn get_http_endpoint() -> SocketAddr {
 pub fn get_network() -> bitcoin::Network {
     CONFIG.get().network
 }
+
+pub fn get_watchtower_endpoint() -> Option<String> {
+    CONFIG.get().watchtower_endpoint.clone()
+}
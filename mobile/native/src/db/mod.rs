@@ -1,6 +1,9 @@
 use crate::api;
 use crate::db::models::base64_engine;
 use crate::db::models::Channel;
+use crate::db::models::CounterpartyCommitment;
+use crate::db::models::Offer;
+use crate::db::models::OfferInsertable;
 use crate::db::models::Order;
 use crate::db::models::OrderState;
 use crate::db::models::PaymentInsertable;
@@ -16,6 +19,9 @@ use anyhow::Context;
 use anyhow::Result;
 use base64::Engine;
 use bdk::bitcoin;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::hex::ToHex;
 use bitcoin::secp256k1::PublicKey;
 use diesel::connection::SimpleConnection;
 use diesel::r2d2;
@@ -313,6 +319,21 @@ pub fn update_payment(
     Ok(())
 }
 
+pub fn set_payment_failure_reason(
+    payment_hash: lightning::ln::PaymentHash,
+    failure_reason: ln_dlc_node::PaymentFailureReason,
+) -> Result<()> {
+    tracing::info!(?payment_hash, %failure_reason, "Recording payment failure reason");
+
+    let mut db = connection()?;
+
+    PaymentInsertable::set_failure_reason(
+        base64_engine().encode(payment_hash.0),
+        failure_reason.to_string(),
+        &mut db,
+    )
+}
+
 pub fn get_payment(
     payment_hash: lightning::ln::PaymentHash,
 ) -> Result<Option<(lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)>> {
@@ -444,6 +465,87 @@ pub fn get_announced_channel(
     Ok(channel)
 }
 
+// Offer
+
+/// The BOLT12 offer we handed out for `amount_sats`, if any.
+pub fn get_offer(amount_sats: Option<u64>) -> Result<Option<String>> {
+    let mut db = connection()?;
+    let offer = Offer::get(amount_sats.map(|amount| amount as i64), &mut db)?;
+
+    Ok(offer.map(|offer| offer.offer))
+}
+
+pub fn insert_offer(amount_sats: Option<u64>, offer: String) -> Result<()> {
+    tracing::debug!(?amount_sats, %offer, "Inserting offer");
+
+    let mut db = connection()?;
+    Offer::insert(
+        OfferInsertable {
+            amount_sats: amount_sats.map(|amount| amount as i64),
+            offer,
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+        },
+        &mut db,
+    )
+}
+
+// Counterparty commitments
+
+pub fn insert_counterparty_commitment(
+    channel_keys_id: [u8; 32],
+    commitment_number: u64,
+    transaction: bitcoin::Transaction,
+) -> Result<()> {
+    let txid = transaction.txid();
+    tracing::debug!(%txid, %commitment_number, "Inserting counterparty commitment");
+
+    let mut db = connection()?;
+    CounterpartyCommitment::insert(
+        CounterpartyCommitment {
+            txid: txid.to_string(),
+            channel_keys_id: channel_keys_id.to_hex(),
+            commitment_number: commitment_number as i64,
+            raw: serialize_hex(&transaction),
+            revoked: false,
+        },
+        &mut db,
+    )
+}
+
+pub fn revoke_counterparty_commitment(
+    channel_keys_id: [u8; 32],
+    commitment_number: u64,
+) -> Result<()> {
+    tracing::debug!(%commitment_number, "Revoking counterparty commitment");
+
+    let mut db = connection()?;
+    CounterpartyCommitment::revoke(&channel_keys_id.to_hex(), commitment_number as i64, &mut db)?;
+
+    Ok(())
+}
+
+pub fn get_revoked_counterparty_commitments() -> Result<Vec<bitcoin::Transaction>> {
+    let mut db = connection()?;
+    let commitments = CounterpartyCommitment::get_all_revoked(&mut db)?;
+
+    commitments
+        .iter()
+        .map(|commitment| {
+            let raw = Vec::<u8>::from_hex(&commitment.raw)?;
+            Ok(bitcoin::consensus::deserialize(&raw)?)
+        })
+        .collect()
+}
+
+pub fn delete_counterparty_commitment(txid: &bitcoin::Txid) -> Result<()> {
+    tracing::debug!(%txid, "Deleting counterparty commitment");
+
+    let mut db = connection()?;
+    CounterpartyCommitment::delete(&txid.to_string(), &mut db)?;
+
+    Ok(())
+}
+
 // Transaction
 
 pub fn upsert_transaction(transaction: ln_dlc_node::transaction::Transaction) -> Result<()> {
@@ -1,7 +1,9 @@
 use crate::api;
 use crate::schema;
 use crate::schema::channels;
+use crate::schema::counterparty_commitments;
 use crate::schema::last_login;
+use crate::schema::offers;
 use crate::schema::orders;
 use crate::schema::payments;
 use crate::schema::positions;
@@ -28,6 +30,7 @@ use lightning::util::ser::Readable;
 use lightning::util::ser::Writeable;
 use ln_dlc_node::channel::UserChannelId;
 use ln_dlc_node::node::rust_dlc_manager::ChannelId;
+use ln_dlc_node::PaymentFailureReason;
 use std::str::FromStr;
 use time::format_description;
 use time::OffsetDateTime;
@@ -723,6 +726,8 @@ pub(crate) struct PaymentInsertable {
     pub description: String,
     #[diesel(sql_type = Nullable<Text>)]
     pub invoice: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub failure_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
@@ -830,6 +835,24 @@ impl PaymentInsertable {
 
         Ok(updated_at)
     }
+
+    pub fn set_failure_reason(
+        payment_hash: String,
+        failure_reason: String,
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        let affected_rows = diesel::update(payments::table)
+            .filter(schema::payments::payment_hash.eq(&payment_hash))
+            .set((
+                schema::payments::failure_reason.eq(failure_reason),
+                schema::payments::updated_at.eq(OffsetDateTime::now_utc().unix_timestamp()),
+            ))
+            .execute(conn)?;
+
+        ensure!(affected_rows > 0, "Could not update payment failure reason");
+
+        Ok(())
+    }
 }
 
 #[derive(Queryable, Debug, Clone, PartialEq)]
@@ -847,6 +870,7 @@ pub(crate) struct PaymentQueryable {
     pub description: String,
     pub invoice: Option<String>,
     pub fee_msat: Option<i64>,
+    pub failure_reason: Option<String>,
 }
 
 impl PaymentQueryable {
@@ -879,6 +903,7 @@ impl From<(lightning::ln::PaymentHash, ln_dlc_node::PaymentInfo)> for PaymentIns
             updated_at: timestamp,
             description: info.description,
             invoice: info.invoice,
+            failure_reason: info.failure_reason.map(|reason| reason.to_string()),
         }
     }
 }
@@ -932,6 +957,11 @@ impl TryFrom<PaymentQueryable> for (lightning::ln::PaymentHash, ln_dlc_node::Pay
         let description = value.description;
         let invoice = value.invoice;
 
+        let failure_reason = value
+            .failure_reason
+            .map(|reason| PaymentFailureReason::from_str(&reason))
+            .transpose()?;
+
         Ok((
             payment_hash,
             ln_dlc_node::PaymentInfo {
@@ -944,6 +974,7 @@ impl TryFrom<PaymentQueryable> for (lightning::ln::PaymentHash, ln_dlc_node::Pay
                 timestamp,
                 description,
                 invoice,
+                failure_reason,
             },
         ))
     }
@@ -1056,6 +1087,97 @@ impl SpendableOutputQueryable {
     }
 }
 
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = offers)]
+pub(crate) struct OfferInsertable {
+    pub amount_sats: Option<i64>,
+    pub offer: String,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Debug, Clone, PartialEq)]
+#[diesel(table_name = offers)]
+pub(crate) struct Offer {
+    pub id: i32,
+    pub amount_sats: Option<i64>,
+    pub offer: String,
+    pub created_at: i64,
+}
+
+impl Offer {
+    pub fn insert(offer: OfferInsertable, conn: &mut SqliteConnection) -> Result<()> {
+        let affected_rows = diesel::insert_into(offers::table)
+            .values(&offer)
+            .execute(conn)?;
+
+        ensure!(affected_rows > 0, "Could not insert offer");
+
+        Ok(())
+    }
+
+    /// The most recent offer for `amount_sats`, or for any amount if `amount_sats` is `None`.
+    pub fn get(amount_sats: Option<i64>, conn: &mut SqliteConnection) -> QueryResult<Option<Self>> {
+        let query = offers::table.order_by(offers::id.desc()).into_boxed();
+
+        let query = match amount_sats {
+            Some(amount_sats) => query.filter(offers::amount_sats.eq(amount_sats)),
+            None => query.filter(offers::amount_sats.is_null()),
+        };
+
+        query.first(conn).optional()
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, PartialEq)]
+#[diesel(table_name = counterparty_commitments)]
+pub(crate) struct CounterpartyCommitment {
+    pub txid: String,
+    pub channel_keys_id: String,
+    pub commitment_number: i64,
+    pub raw: String,
+    pub revoked: bool,
+}
+
+impl CounterpartyCommitment {
+    pub fn insert(commitment: CounterpartyCommitment, conn: &mut SqliteConnection) -> Result<()> {
+        // The same commitment transaction may be signed more than once, e.g. on reconnection.
+        diesel::insert_or_ignore_into(counterparty_commitments::table)
+            .values(&commitment)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn revoke(
+        channel_keys_id: &str,
+        commitment_number: i64,
+        conn: &mut SqliteConnection,
+    ) -> QueryResult<()> {
+        diesel::update(counterparty_commitments::table)
+            .filter(counterparty_commitments::channel_keys_id.eq(channel_keys_id))
+            .filter(counterparty_commitments::commitment_number.eq(commitment_number))
+            .set(counterparty_commitments::revoked.eq(true))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_all_revoked(conn: &mut SqliteConnection) -> QueryResult<Vec<Self>> {
+        counterparty_commitments::table
+            .filter(counterparty_commitments::revoked.eq(true))
+            .load(conn)
+    }
+
+    pub fn delete(txid: &str, conn: &mut SqliteConnection) -> QueryResult<()> {
+        diesel::delete(
+            counterparty_commitments::table.filter(counterparty_commitments::txid.eq(txid)),
+        )
+        .execute(conn)?;
+
+        Ok(())
+    }
+}
+
 fn outpoint_to_string(outpoint: lightning::chain::transaction::OutPoint) -> String {
     format!("{}:{}", outpoint.txid, outpoint.index)
 }
@@ -1178,6 +1300,7 @@ pub(crate) struct Transaction {
     pub created_at: i64,
     pub updated_at: i64,
     pub raw: String,
+    pub replaceable: bool,
 }
 
 impl Transaction {
@@ -1216,6 +1339,7 @@ impl From<ln_dlc_node::transaction::Transaction> for Transaction {
             created_at: value.created_at().unix_timestamp(),
             updated_at: value.updated_at().unix_timestamp(),
             raw: value.raw(),
+            replaceable: value.is_replaceable(),
         }
     }
 }
@@ -1228,6 +1352,7 @@ impl From<Transaction> for ln_dlc_node::transaction::Transaction {
             OffsetDateTime::from_unix_timestamp(value.created_at).expect("valid timestamp"),
             OffsetDateTime::from_unix_timestamp(value.updated_at).expect("valid timestamp"),
             value.raw,
+            value.replaceable,
         )
     }
 }
@@ -1583,6 +1708,7 @@ pub mod test {
             updated_at,
             description: description.clone(),
             invoice: invoice.clone(),
+            failure_reason: None,
         };
 
         PaymentInsertable::insert(payment, &mut connection).unwrap();
@@ -1601,6 +1727,7 @@ pub mod test {
                 updated_at: 200,
                 description: "payment2".to_string(),
                 invoice: Some("invoice2".to_string()),
+                failure_reason: None,
             },
             &mut connection,
         )
@@ -1623,6 +1750,7 @@ pub mod test {
             updated_at,
             description,
             invoice,
+            failure_reason: None,
         };
 
         assert_eq!(expected_payment, loaded_payment);
@@ -1660,6 +1788,20 @@ pub mod test {
         };
 
         assert_eq!(expected_payment, loaded_payment);
+
+        // Verify that we can record why the payment failed
+
+        PaymentInsertable::set_failure_reason(
+            payment_hash.to_string(),
+            "NoRoute".to_string(),
+            &mut connection,
+        )
+        .unwrap();
+
+        let loaded_payment =
+            PaymentQueryable::get(payment_hash.to_string(), &mut connection).unwrap();
+
+        assert_eq!(loaded_payment.failure_reason, Some("NoRoute".to_string()));
     }
 
     #[test]
@@ -1735,6 +1877,75 @@ pub mod test {
         };
     }
 
+    #[test]
+    pub fn offer_round_trip() {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        connection.run_pending_migrations(MIGRATIONS).unwrap();
+
+        assert_eq!(Offer::get(None, &mut connection).unwrap(), None);
+
+        for (amount_sats, offer) in [(None, "lno1any"), (Some(1_000), "lno1amount")] {
+            Offer::insert(
+                OfferInsertable {
+                    amount_sats,
+                    offer: offer.to_string(),
+                    created_at: 0,
+                },
+                &mut connection,
+            )
+            .unwrap();
+        }
+
+        // Each amount gets its own offer
+        let offer = Offer::get(None, &mut connection).unwrap().unwrap();
+        assert_eq!(offer.offer, "lno1any");
+
+        let offer = Offer::get(Some(1_000), &mut connection).unwrap().unwrap();
+        assert_eq!(offer.offer, "lno1amount");
+
+        assert_eq!(Offer::get(Some(2_000), &mut connection).unwrap(), None);
+    }
+
+    #[test]
+    pub fn counterparty_commitment_round_trip() {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        connection.run_pending_migrations(MIGRATIONS).unwrap();
+
+        let commitment = |txid: &str, commitment_number| CounterpartyCommitment {
+            txid: txid.to_string(),
+            channel_keys_id: "channel".to_string(),
+            commitment_number,
+            raw: "raw".to_string(),
+            revoked: false,
+        };
+
+        CounterpartyCommitment::insert(commitment("first", 2), &mut connection).unwrap();
+        CounterpartyCommitment::insert(commitment("second", 1), &mut connection).unwrap();
+        // Signing the same commitment transaction again is fine
+        CounterpartyCommitment::insert(commitment("first", 2), &mut connection).unwrap();
+
+        assert!(CounterpartyCommitment::get_all_revoked(&mut connection)
+            .unwrap()
+            .is_empty());
+
+        CounterpartyCommitment::revoke("channel", 2, &mut connection).unwrap();
+        CounterpartyCommitment::revoke("other channel", 1, &mut connection).unwrap();
+
+        assert_eq!(
+            CounterpartyCommitment::get_all_revoked(&mut connection).unwrap(),
+            vec![CounterpartyCommitment {
+                revoked: true,
+                ..commitment("first", 2)
+            }]
+        );
+
+        CounterpartyCommitment::delete("first", &mut connection).unwrap();
+
+        assert!(CounterpartyCommitment::get_all_revoked(&mut connection)
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     pub fn channel_round_trip() {
         let mut connection = SqliteConnection::establish(":memory:").unwrap();
@@ -1808,6 +2019,7 @@ pub mod test {
             OffsetDateTime::now_utc().replace_time(Time::from_hms(0, 0, 0).unwrap()),
             OffsetDateTime::now_utc().replace_time(Time::from_hms(0, 0, 0).unwrap()),
             "0200...doesntmattermuch".to_string(),
+            true,
         );
 
         Transaction::upsert(transaction.clone().into(), &mut connection).unwrap();
@@ -1830,6 +2042,7 @@ pub mod test {
             OffsetDateTime::now_utc(),
             OffsetDateTime::now_utc(),
             "0200...doesntmattermuch".to_string(),
+            false,
         );
         Transaction::upsert(second_tx.into(), &mut connection).unwrap();
         // Verify that we can load all transactions without fees
@@ -1,21 +1,105 @@
 use crate::api::Destination;
+use crate::ln_dlc;
+use crate::lnurl;
+use crate::lnurl::LnUrlRequest;
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
+use bitcoin::secp256k1::PublicKey;
 use bitcoin::Address;
 use bitcoin::Amount;
+#[cfg(feature = "bolt12")]
+use lightning::offers::offer::Offer;
+#[cfg(feature = "bolt12")]
+use lightning::offers::refund::Refund;
 use lightning_invoice::Invoice;
 use lightning_invoice::InvoiceDescription;
+#[cfg(feature = "bolt12")]
+use ln_dlc_node::node::offer_amount_msat;
+#[cfg(feature = "bolt12")]
+use ln_dlc_node::node::offer_description;
 use std::ops::Add;
 use std::str::FromStr;
 use std::time::Duration;
 use std::time::SystemTime;
 
 pub fn decode_destination(destination: String) -> Result<Destination> {
-    decode_bip21(&destination)
-        .or(decode_invoice(&destination))
+    if let Some(url) = lnurl::parse(&destination) {
+        return decode_lnurl(destination, &url);
+    }
+
+    let decoded = decode_bip21(&destination).or(decode_invoice(&destination));
+    #[cfg(feature = "bolt12")]
+    let decoded = decoded.or(decode_bolt12(&destination));
+
+    decoded
+        .or(decode_node(&destination))
         .or(decode_address(destination))
-        .context("Failed to parse destination as Bolt11 invoice, Bip21 URI, or on chain address")
+        .context(
+            "Failed to parse destination as Bolt11 invoice, Bolt12 offer or refund, Bip21 URI, \
+             LNURL, Lightning Address, node ID or on chain address",
+        )
+}
+
+fn decode_lnurl(lnurl: String, url: &str) -> Result<Destination> {
+    let request = ln_dlc::resolve_lnurl(url)?;
+
+    let destination = match request {
+        LnUrlRequest::Pay(request) => Destination::LnUrlPay {
+            description: request.description(),
+            lightning_address: request.lightning_address(),
+            min_sendable_sats: msats_to_sats_ceil(request.min_sendable),
+            max_sendable_sats: request.max_sendable / 1000,
+            comment_allowed: request.comment_allowed,
+            lnurl,
+        },
+        LnUrlRequest::Withdraw(request) => Destination::LnUrlWithdraw {
+            description: request.default_description,
+            min_withdrawable_sats: msats_to_sats_ceil(request.min_withdrawable),
+            max_withdrawable_sats: request.max_withdrawable / 1000,
+            lnurl,
+        },
+    };
+
+    Ok(destination)
+}
+
+/// A node ID, to be paid via keysend.
+fn decode_node(request: &str) -> Result<Destination> {
+    let pubkey = PublicKey::from_str(request).context("request is not a valid node ID")?;
+    Ok(Destination::Node {
+        pubkey: pubkey.to_string(),
+    })
+}
+
+/// A BOLT12 offer (`lno…`) or refund (`lnr…`).
+#[cfg(feature = "bolt12")]
+fn decode_bolt12(request: &str) -> Result<Destination> {
+    let request = request.trim();
+    let request = request
+        .strip_prefix("lightning:")
+        .or(request.strip_prefix("LIGHTNING:"))
+        .unwrap_or(request);
+
+    if let Ok(offer) = Offer::from_str(request) {
+        return Ok(Destination::Bolt12Offer {
+            description: offer_description(&offer),
+            amount_sats: offer_amount_msat(&offer).map(msats_to_sats_ceil),
+            offer: request.to_string(),
+        });
+    }
+
+    let refund = Refund::from_str(request)
+        .map_err(|e| anyhow!("request is not valid BOLT12 offer or refund: {e:?}"))?;
+    Ok(Destination::Bolt12Refund {
+        description: refund.description().to_string(),
+        amount_sats: refund.amount_msats() / 1000,
+        refund: request.to_string(),
+    })
+}
+
+fn msats_to_sats_ceil(msats: u64) -> u64 {
+    (msats + 999) / 1000
 }
 
 fn decode_bip21(request: &str) -> Result<Destination> {
@@ -75,3 +159,70 @@ fn decode_invoice(request: &str) -> Result<Destination> {
         payee,
     })
 }
+
+#[cfg(all(test, feature = "bolt12"))]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::secp256k1::SecretKey;
+    use lightning::offers::offer::OfferBuilder;
+    use lightning::offers::refund::RefundBuilder;
+
+    #[test]
+    fn decode_bolt12_offer() {
+        let offer = OfferBuilder::new("coffee".to_string(), node_id())
+            .amount_msats(1_500)
+            .build()
+            .unwrap()
+            .to_string();
+
+        let destination = decode_bolt12(&format!("lightning:{offer}")).unwrap();
+
+        match destination {
+            Destination::Bolt12Offer {
+                offer: decoded,
+                description,
+                amount_sats,
+            } => {
+                assert_eq!(decoded, offer);
+                assert_eq!(description, "coffee");
+                assert_eq!(amount_sats, Some(2));
+            }
+            _ => panic!("Expected an offer"),
+        }
+    }
+
+    #[test]
+    fn decode_bolt12_refund() {
+        let refund = RefundBuilder::new("refund".to_string(), vec![1; 32], node_id(), 10_000_000)
+            .unwrap()
+            .build()
+            .unwrap()
+            .to_string();
+
+        let destination = decode_bolt12(&refund).unwrap();
+
+        match destination {
+            Destination::Bolt12Refund {
+                refund: decoded,
+                description,
+                amount_sats,
+            } => {
+                assert_eq!(decoded, refund);
+                assert_eq!(description, "refund");
+                assert_eq!(amount_sats, 10_000);
+            }
+            _ => panic!("Expected a refund"),
+        }
+    }
+
+    #[test]
+    fn decode_bolt12_rejects_invoice() {
+        assert!(decode_bolt12("lnbcrt1u1pjsq8rq").is_err());
+    }
+
+    fn node_id() -> PublicKey {
+        let secret_key = SecretKey::from_slice(&[42; 32]).unwrap();
+        PublicKey::from_secret_key(&Secp256k1::new(), &secret_key)
+    }
+}
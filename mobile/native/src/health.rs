@@ -6,6 +6,7 @@ use anyhow::Context;
 use anyhow::Result;
 use futures::future::RemoteHandle;
 use futures::FutureExt;
+use ln_dlc_node::node::WatchtowerStatus;
 use reqwest::StatusCode;
 use std::time::Duration;
 use tokio::runtime::Runtime;
@@ -16,6 +17,8 @@ use tokio::sync::watch;
 pub enum Service {
     Orderbook,
     Coordinator,
+    /// Offline if the watchtower refuses to store the penalties of our revoked transactions.
+    Watchtower,
 }
 
 /// Health status of the node
@@ -33,6 +36,16 @@ pub struct ServiceUpdate {
     pub status: ServiceStatus,
 }
 
+impl From<WatchtowerStatus> for ServiceStatus {
+    fn from(status: WatchtowerStatus) -> Self {
+        match status {
+            WatchtowerStatus::Unknown => ServiceStatus::Unknown,
+            WatchtowerStatus::Accepted => ServiceStatus::Online,
+            WatchtowerStatus::Refused => ServiceStatus::Offline,
+        }
+    }
+}
+
 impl From<(Service, ServiceStatus)> for ServiceUpdate {
     fn from(tuple: (Service, ServiceStatus)) -> Self {
         let (service, status) = tuple;
@@ -53,7 +66,11 @@ pub struct Health {
 }
 
 impl Health {
-    pub fn new(config: Config, runtime: &Runtime) -> (Self, Tx) {
+    pub fn new(
+        config: Config,
+        runtime: &Runtime,
+        watchtower_rx: watch::Receiver<WatchtowerStatus>,
+    ) -> (Self, Tx) {
         let (orderbook_tx, orderbook_rx) = watch::channel(ServiceStatus::Unknown);
 
         let config: ConfigInternal = config.into();
@@ -83,6 +100,12 @@ impl Health {
             .1;
         tasks.push(coordinator_monitoring);
 
+        let watchtower_monitoring = runtime
+            .spawn(publish_status_updates(Service::Watchtower, watchtower_rx))
+            .remote_handle()
+            .1;
+        tasks.push(watchtower_monitoring);
+
         (
             Self { _tasks: tasks },
             Tx {
@@ -93,14 +116,17 @@ impl Health {
 }
 
 /// Publishes the health status updates for a given service to the event hub
-async fn publish_status_updates(service: Service, mut rx: watch::Receiver<ServiceStatus>) {
+async fn publish_status_updates<T: Into<ServiceStatus> + Copy>(
+    service: Service,
+    mut rx: watch::Receiver<T>,
+) {
     loop {
         match rx.changed().await {
             Ok(()) => {
-                let status = rx.borrow();
+                let status: ServiceStatus = (*rx.borrow()).into();
 
                 event::publish(&EventInternal::ServiceHealthUpdate(
-                    (service, *status).into(),
+                    (service, status).into(),
                 ));
             }
             Err(_) => {
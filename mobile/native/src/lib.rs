@@ -23,3 +23,4 @@ mod orderbook;
 )]
 mod bridge_generated;
 mod destination;
+mod lnurl;
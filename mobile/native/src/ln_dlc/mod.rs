@@ -15,6 +15,8 @@ use crate::event::EventInternal;
 use crate::ln_dlc::channel_status::track_channel_status;
 use crate::ln_dlc::node::Node;
 use crate::ln_dlc::node::NodeStorage;
+use crate::lnurl;
+use crate::lnurl::LnUrlRequest;
 use crate::trade::order;
 use crate::trade::order::FailureReason;
 use crate::trade::order::Order;
@@ -31,7 +33,9 @@ use bdk::bitcoin::Txid;
 use bdk::bitcoin::XOnlyPublicKey;
 use bdk::BlockTime;
 use bdk::FeeRate;
+use bdk::LocalUtxo;
 use bitcoin::hashes::hex::ToHex;
+use bitcoin::secp256k1::PublicKey;
 use bitcoin::secp256k1::Secp256k1;
 use bitcoin::Address;
 use bitcoin::Amount;
@@ -49,6 +53,10 @@ use coordinator_commons::TradeParams;
 use itertools::chain;
 use itertools::Itertools;
 use lightning::ln::channelmanager::ChannelDetails;
+#[cfg(feature = "bolt12")]
+use lightning::offers::offer::Offer;
+#[cfg(feature = "bolt12")]
+use lightning::offers::refund::Refund;
 use lightning::util::events::Event;
 use lightning_invoice::Invoice;
 use ln_dlc_node::channel::Channel;
@@ -60,14 +68,20 @@ use ln_dlc_node::node::rust_dlc_manager::subchannel::LNChannelManager;
 use ln_dlc_node::node::rust_dlc_manager::subchannel::SubChannelState;
 use ln_dlc_node::node::rust_dlc_manager::ChannelId;
 use ln_dlc_node::node::rust_dlc_manager::Storage as DlcStorage;
+use ln_dlc_node::node::FeeBumpMethod;
 use ln_dlc_node::node::LnDlcNodeSettings;
 use ln_dlc_node::node::NodeInfo;
+use ln_dlc_node::node::PaymentProbe;
 use ln_dlc_node::node::Storage as LnDlcNodeStorage;
+use ln_dlc_node::node::WatchtowerStatus;
 use ln_dlc_node::scorer;
 use ln_dlc_node::seed::Bip39Seed;
 use ln_dlc_node::util;
 use ln_dlc_node::AppEventHandler;
+use ln_dlc_node::Fee;
 use ln_dlc_node::HTLCStatus;
+use ln_dlc_node::PreparedPayment;
+use ln_dlc_node::UnconfirmedTransaction;
 use ln_dlc_node::CONFIRMATION_TARGET;
 use orderbook_commons::RouteHintHop;
 use orderbook_commons::FEE_INVOICE_DESCRIPTION_PREFIX_TAKER;
@@ -154,6 +168,10 @@ pub fn get_node_key() -> SecretKey {
     NODE.get().inner.node_key()
 }
 
+pub fn get_watchtower_status() -> watch::Receiver<WatchtowerStatus> {
+    NODE.get().inner.watchtower_status()
+}
+
 pub fn get_node_info() -> Result<NodeInfo> {
     Ok(NODE
         .try_get()
@@ -257,10 +275,13 @@ pub fn run(data_dir: String, seed_dir: String, runtime: &Runtime) -> Result<()>
             address,
             SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), address.port()),
             util::into_net_addresses(address),
-            config::get_esplora_endpoint(),
+            config::get_chain_source(),
             seed,
             ephemeral_randomness,
-            LnDlcNodeSettings::default(),
+            LnDlcNodeSettings {
+                watchtower_url: config::get_watchtower_endpoint(),
+                ..LnDlcNodeSettings::default()
+            },
             config::get_oracle_info().into(),
         )?;
         let node = Arc::new(node);
@@ -502,6 +523,9 @@ fn keep_wallet_balance_and_history_up_to_date(node: &Node) -> Result<()> {
                 invoice: details.invoice.clone(),
                 fee_msat: details.fee_msat,
                 expiry_timestamp,
+                failure_reason: details
+                    .failure_reason
+                    .map(|reason| reason.description().to_string()),
             }
         };
 
@@ -953,6 +977,13 @@ pub fn create_onboarding_invoice(amount_sats: u64, liquidity_option_id: i32) ->
 }
 
 pub fn create_invoice(amount_sats: Option<u64>) -> Result<Invoice> {
+    create_invoice_with_description(amount_sats, "".to_string())
+}
+
+fn create_invoice_with_description(
+    amount_sats: Option<u64>,
+    description: String,
+) -> Result<Invoice> {
     let runtime = get_or_create_tokio_runtime()?;
 
     runtime.block_on(async {
@@ -979,7 +1010,7 @@ pub fn create_invoice(amount_sats: Option<u64>) -> Result<Invoice> {
         node.inner.create_invoice_with_route_hint(
             amount_sats,
             None,
-            "".to_string(),
+            description,
             final_route_hint_hop,
         )
     })
@@ -995,18 +1026,189 @@ pub fn send_payment(payment: SendPayment) -> Result<()> {
             let address = Address::from_str(&address)?;
             NODE.get().inner.send_to_address(&address, amount)?;
         }
+        SendPayment::Keysend { pubkey, amount } => {
+            let pubkey = PublicKey::from_str(&pubkey).context("Invalid node ID")?;
+            NODE.get()
+                .inner
+                .keysend(pubkey, amount * 1000, format!("Keysend to {pubkey}"))?;
+        }
+        SendPayment::LnUrlPay {
+            lnurl,
+            amount,
+            comment,
+        } => {
+            let LnUrlRequest::Pay(request) = resolve_lnurl_destination(&lnurl)? else {
+                bail!("Not an LNURL-pay link: {lnurl}");
+            };
+
+            let comment = comment.filter(|comment| !comment.is_empty());
+            let runtime = get_or_create_tokio_runtime()?;
+            let invoice = runtime.block_on(lnurl::fetch_invoice(
+                &reqwest_client(),
+                &request,
+                amount * 1000,
+                comment.as_deref(),
+            ))?;
+
+            NODE.get().inner.pay_invoice_with_description(
+                &invoice,
+                None,
+                request.payment_description(comment.as_deref()),
+            )?;
+        }
+        SendPayment::Bolt12Offer { offer, amount } => pay_offer(&offer, amount)?,
     }
     Ok(())
 }
 
+/// How long we wait for the probes of a payment to complete.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn probe_payment(invoice: &str, amount_sats: Option<u64>) -> Result<PaymentProbe> {
+    let invoice = Invoice::from_str(invoice)?;
+
+    let runtime = get_or_create_tokio_runtime()?;
+    runtime.block_on(NODE.get().inner.probe_invoice(
+        &invoice,
+        amount_sats.map(|amount| amount * 1000),
+        PROBE_TIMEOUT,
+    ))
+}
+
+/// The reusable BOLT12 offer for `amount_sats`.
+///
+/// We hand out the same offer every time, so that it keeps working for anyone we gave it to. Only
+/// if we have none for `amount_sats` yet, we create one, with the coordinator as the introduction
+/// node of its blinded path.
+#[cfg(feature = "bolt12")]
+pub fn get_or_create_offer(amount_sats: Option<u64>) -> Result<String> {
+    if let Some(offer) = db::get_offer(amount_sats)? {
+        return Ok(offer);
+    }
+
+    let offer = NODE
+        .get()
+        .inner
+        .create_offer(
+            amount_sats,
+            "".to_string(),
+            Some(config::get_coordinator_info().pubkey),
+            None,
+        )?
+        .to_string();
+
+    db::insert_offer(amount_sats, offer.clone())?;
+
+    Ok(offer)
+}
+
+fn pay_offer(offer: &str, amount_sats: Option<u64>) -> Result<()> {
+    #[cfg(not(feature = "bolt12"))]
+    bail!("BOLT12 is not enabled, cannot pay offer {offer} with {amount_sats:?} sats");
+
+    #[cfg(feature = "bolt12")]
+    {
+        let offer = Offer::from_str(offer).map_err(|e| anyhow!("Invalid offer: {e:?}"))?;
+        NODE.get()
+            .inner
+            .pay_offer(&offer, amount_sats.map(|amount| amount * 1000))
+    }
+}
+
+/// Claims the BOLT12 `refund`, e.g. one handed out by the coordinator.
+pub fn claim_refund(refund: &str) -> Result<()> {
+    #[cfg(not(feature = "bolt12"))]
+    bail!("BOLT12 is not enabled, cannot claim refund {refund}");
+
+    #[cfg(feature = "bolt12")]
+    {
+        let refund = Refund::from_str(refund).map_err(|e| anyhow!("Invalid refund: {e:?}"))?;
+        NODE.get().inner.claim_refund(&refund)
+    }
+}
+
+/// Fetches what the LNURL at `url` is asking for.
+pub fn resolve_lnurl(url: &str) -> Result<LnUrlRequest> {
+    let runtime = get_or_create_tokio_runtime()?;
+    runtime.block_on(lnurl::resolve(&reqwest_client(), url))
+}
+
+fn resolve_lnurl_destination(lnurl: &str) -> Result<LnUrlRequest> {
+    let url = lnurl::parse(lnurl).context("Invalid LNURL or Lightning Address")?;
+    resolve_lnurl(&url)
+}
+
+pub fn withdraw_lnurl(lnurl: &str, amount_sats: u64) -> Result<()> {
+    let LnUrlRequest::Withdraw(request) = resolve_lnurl_destination(lnurl)? else {
+        bail!("Not an LNURL-withdraw link: {lnurl}");
+    };
+
+    let invoice =
+        create_invoice_with_description(Some(amount_sats), request.default_description.clone())?;
+
+    let runtime = get_or_create_tokio_runtime()?;
+    runtime.block_on(lnurl::withdraw(&reqwest_client(), &request, &invoice))?;
+
+    tracing::info!(
+        payment_hash = %invoice.payment_hash(),
+        amount_sats,
+        "Requested LNURL withdrawal"
+    );
+
+    Ok(())
+}
+
+pub fn list_utxos() -> Result<Vec<LocalUtxo>> {
+    NODE.get().inner.wallet().list_utxos()
+}
+
+pub fn send_on_chain_payment(
+    address: &str,
+    amount_sat_or_drain: Option<u64>,
+    fee: Fee,
+    utxos: &[OutPoint],
+    raw_tx: Option<&str>,
+    dry_run: bool,
+) -> Result<PreparedPayment> {
+    let address = Address::from_str(address)?;
+    let wallet = NODE.get().inner.wallet();
+
+    let payment = match raw_tx {
+        Some(raw_tx) => wallet.load_prepared_payment(raw_tx, &address, amount_sat_or_drain)?,
+        None => wallet.prepare_payment(&address, amount_sat_or_drain, fee, utxos)?,
+    };
+    if !dry_run {
+        wallet.broadcast_payment(&payment.transaction)?;
+    }
+
+    Ok(payment)
+}
+
+pub fn unconfirmed_transactions() -> Result<Vec<UnconfirmedTransaction>> {
+    NODE.get().inner.unconfirmed_transactions()
+}
+
+pub fn bump_fee(txid: &str, fee_rate: f32, method: FeeBumpMethod) -> Result<Txid> {
+    let txid = Txid::from_str(txid).context("Invalid txid")?;
+    let fee_rate = FeeRate::from_sat_per_vb(fee_rate);
+
+    NODE.get().inner.bump_fee(&txid, fee_rate, method)
+}
+
 pub async fn trade(trade_params: TradeParams) -> Result<(), (FailureReason, Error)> {
     let client = reqwest_client();
+
+    // The coordinator only executes the trade once we have paid the order-matching fee. It holds
+    // the payment until the DLC has been signed and refunds it if the trade fails.
     let response = client
-        .post(format!("http://{}/api/trade", config::get_http_endpoint()))
+        .post(format!(
+            "http://{}/api/trade/fee_invoice",
+            config::get_http_endpoint()
+        ))
         .json(&trade_params)
         .send()
         .await
-        .context("Failed to register with coordinator")
+        .context("Failed to request order-matching fee invoice from coordinator")
         .map_err(|e| (FailureReason::TradeRequest, e))?;
 
     if !response.status().is_success() {
@@ -1018,12 +1220,10 @@ pub async fn trade(trade_params: TradeParams) -> Result<(), (FailureReason, Erro
         };
         return Err((
             FailureReason::TradeResponse,
-            anyhow!("Could not post trade to coordinator: {response_text}"),
+            anyhow!("Could not get order-matching fee invoice from coordinator: {response_text}"),
         ));
     }
 
-    tracing::info!("Sent trade request to coordinator successfully");
-
     let order_matching_fee_invoice = response.text().await.map_err(|e| {
         (
             FailureReason::TradeResponse,
@@ -1039,11 +1239,40 @@ pub async fn trade(trade_params: TradeParams) -> Result<(), (FailureReason, Erro
 
     let payment_hash = *order_matching_fee_invoice.payment_hash();
 
-    spawn_blocking(|| {
-        *NODE.get().order_matching_fee_invoice.write() = Some(order_matching_fee_invoice);
-    });
+    spawn_blocking(move || {
+        NODE.get()
+            .inner
+            .pay_invoice(&order_matching_fee_invoice, None)
+    })
+    .await
+    .expect("task to complete")
+    .context("Failed to pay order-matching fee")
+    .map_err(|e| (FailureReason::TradeRequest, e))?;
 
-    tracing::info!(%payment_hash, "Registered order-matching fee invoice to be paid later");
+    tracing::info!(%payment_hash, "Triggered payment of order-matching fee");
+
+    let response = client
+        .post(format!("http://{}/api/trade", config::get_http_endpoint()))
+        .json(&trade_params)
+        .send()
+        .await
+        .context("Failed to register with coordinator")
+        .map_err(|e| (FailureReason::TradeRequest, e))?;
+
+    if !response.status().is_success() {
+        let response_text = match response.text().await {
+            Ok(text) => text,
+            Err(err) => {
+                format!("could not decode response {err:#}")
+            }
+        };
+        return Err((
+            FailureReason::TradeResponse,
+            anyhow!("Could not post trade to coordinator: {response_text}"),
+        ));
+    }
+
+    tracing::info!("Sent trade request to coordinator successfully");
 
     Ok(())
 }
@@ -9,8 +9,8 @@ use crate::trade::position::PositionState;
 use anyhow::Context;
 use anyhow::Result;
 use bdk::bitcoin::secp256k1::PublicKey;
+use bdk::bitcoin::Txid;
 use bdk::TransactionDetails;
-use dlc_messages::sub_channel::SubChannelCloseFinalize;
 use dlc_messages::sub_channel::SubChannelRevoke;
 use dlc_messages::ChannelMessage;
 use dlc_messages::Message;
@@ -22,7 +22,6 @@ use lightning::chain::transaction::OutPoint;
 use lightning::ln::PaymentHash;
 use lightning::ln::PaymentPreimage;
 use lightning::ln::PaymentSecret;
-use lightning_invoice::Invoice;
 use ln_dlc_node::channel::Channel;
 use ln_dlc_node::node;
 use ln_dlc_node::node::dlc_message_name;
@@ -33,9 +32,9 @@ use ln_dlc_node::node::RunningNode;
 use ln_dlc_node::transaction::Transaction;
 use ln_dlc_node::HTLCStatus;
 use ln_dlc_node::MillisatAmount;
+use ln_dlc_node::PaymentFailureReason;
 use ln_dlc_node::PaymentFlow;
 use ln_dlc_node::PaymentInfo;
-use parking_lot::RwLock;
 use std::sync::Arc;
 use std::time::Duration;
 use time::OffsetDateTime;
@@ -44,10 +43,6 @@ use time::OffsetDateTime;
 pub struct Node {
     pub inner: Arc<ln_dlc_node::node::Node<NodeStorage>>,
     _running: Arc<RunningNode>,
-    /// The order-matching fee invoice to be paid once the current trade is executed.
-    ///
-    /// We assume that only one trade can be executed at a time.
-    pub order_matching_fee_invoice: Arc<RwLock<Option<Invoice>>>,
 }
 
 impl Node {
@@ -55,7 +50,6 @@ impl Node {
         Self {
             inner: node,
             _running: Arc::new(running),
-            order_matching_fee_invoice: Arc::new(RwLock::new(None)),
         }
     }
 }
@@ -266,10 +260,6 @@ impl Node {
             event::publish(&EventInternal::BackgroundNotification(
                 BackgroundTask::RecoverDlc(TaskStatus::Success),
             ));
-
-            if let Err(e) = self.pay_order_matching_fee(&channel_id) {
-                tracing::error!("{e:#}");
-            }
         }
 
         if let Some(msg) = resp {
@@ -292,11 +282,7 @@ impl Node {
 
         // After sending the `CloseFinalize` message, we need to do some post-processing based on
         // the fact that the DLC channel has been closed
-        if let Message::SubChannel(SubChannelMessage::CloseFinalize(SubChannelCloseFinalize {
-            channel_id,
-            ..
-        })) = msg
-        {
+        if let Message::SubChannel(SubChannelMessage::CloseFinalize(_)) = msg {
             let filled_order = order::handler::order_filled()?;
             position::handler::update_position_after_dlc_closure(Some(filled_order))
                 .context("Failed to update position after DLC closure")?;
@@ -313,45 +299,11 @@ impl Node {
             event::publish(&EventInternal::BackgroundNotification(
                 BackgroundTask::RecoverDlc(TaskStatus::Success),
             ));
-
-            if let Err(e) = self.pay_order_matching_fee(&channel_id) {
-                tracing::error!("{e:#}");
-            }
         };
 
         Ok(())
     }
 
-    fn pay_order_matching_fee(&self, channel_id: &[u8; 32]) -> Result<()> {
-        let channel_id_str = hex::encode(channel_id);
-
-        tracing::info!(channel_id = %channel_id_str, "Paying order-matching fee");
-
-        let invoice = self
-            .order_matching_fee_invoice
-            .write()
-            .take()
-            .context("Expected to have to pay an order-matching fee invoice")?;
-
-        // Without this `sleep` the coordinator can end up not generating the corresponding
-        // `PaymentClaimable` event, leaving the payment in a pending state seemingly forever
-        std::thread::sleep(Duration::from_secs(5));
-
-        self.inner
-            .pay_invoice(&invoice, None)
-            .context("Failed order-matching fee invoice payment")?;
-
-        tracing::info!(
-            channel_id = %channel_id_str,
-            payment_hash = %invoice.payment_hash(),
-            description = ?invoice.description(),
-            amount_msat = ?invoice.amount_milli_satoshis(),
-            "Triggered payment of order-matching fee"
-        );
-
-        Ok(())
-    }
-
     pub async fn keep_connected(&self, peer: NodeInfo) {
         let reconnect_interval = Duration::from_secs(1);
         loop {
@@ -424,6 +376,7 @@ impl node::Storage for NodeStorage {
                         timestamp: OffsetDateTime::now_utc(),
                         description: "".to_string(),
                         invoice: None,
+                        failure_reason: None,
                     },
                 )?;
             }
@@ -431,6 +384,13 @@ impl node::Storage for NodeStorage {
 
         Ok(())
     }
+    fn set_payment_failure_reason(
+        &self,
+        payment_hash: &PaymentHash,
+        failure_reason: PaymentFailureReason,
+    ) -> Result<()> {
+        db::set_payment_failure_reason(*payment_hash, failure_reason)
+    }
     fn get_payment(
         &self,
         payment_hash: &PaymentHash,
@@ -502,4 +462,31 @@ impl node::Storage for NodeStorage {
     fn all_transactions_without_fees(&self) -> Result<Vec<Transaction>> {
         db::get_all_transactions_without_fees()
     }
+
+    // Counterparty commitments
+
+    fn insert_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+        transaction: bitcoin::Transaction,
+    ) -> Result<()> {
+        db::insert_counterparty_commitment(channel_keys_id, commitment_number, transaction)
+    }
+
+    fn revoke_counterparty_commitment(
+        &self,
+        channel_keys_id: [u8; 32],
+        commitment_number: u64,
+    ) -> Result<()> {
+        db::revoke_counterparty_commitment(channel_keys_id, commitment_number)
+    }
+
+    fn revoked_counterparty_commitments(&self) -> Result<Vec<bitcoin::Transaction>> {
+        db::get_revoked_counterparty_commitments()
+    }
+
+    fn delete_counterparty_commitment(&self, txid: &Txid) -> Result<()> {
+        db::delete_counterparty_commitment(txid)
+    }
 }
@@ -0,0 +1,416 @@
+//! Paying to LNURL-pay links and Lightning Addresses, and receiving through LNURL-withdraw links.
+//!
+//! See <https://github.com/lnurl/luds> for the specification.
+
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use bitcoin::bech32;
+use bitcoin::bech32::FromBase32;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use lightning_invoice::Invoice;
+use lightning_invoice::InvoiceDescription;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A resolved LNURL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LnUrlRequest {
+    Pay(PayRequest),
+    Withdraw(WithdrawRequest),
+}
+
+/// Tells us how to request an invoice from the recipient of a payment (LUD-06).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayRequest {
+    pub callback: String,
+    pub min_sendable: u64,
+    pub max_sendable: u64,
+    /// A JSON array of `[mime type, content]` pairs describing the payment.
+    pub metadata: String,
+    /// The maximum length of a comment, where 0 means no comment is accepted (LUD-12).
+    #[serde(default)]
+    pub comment_allowed: u16,
+}
+
+/// Tells us how to have the sender pay our invoice (LUD-03).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawRequest {
+    pub callback: String,
+    pub k1: String,
+    #[serde(default)]
+    pub default_description: String,
+    pub min_withdrawable: u64,
+    pub max_withdrawable: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "tag")]
+enum LnUrlResponse {
+    #[serde(rename = "payRequest")]
+    Pay(PayRequest),
+    #[serde(rename = "withdrawRequest")]
+    Withdraw(WithdrawRequest),
+}
+
+#[derive(Deserialize)]
+struct InvoiceResponse {
+    pr: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+enum Status {
+    Ok,
+    Error,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    status: Status,
+    reason: Option<String>,
+}
+
+impl PayRequest {
+    /// The description of the payment, from the `text/plain` entry of the metadata.
+    pub fn description(&self) -> String {
+        let entries: Vec<(String, serde_json::Value)> =
+            serde_json::from_str(&self.metadata).unwrap_or_default();
+
+        entries
+            .into_iter()
+            .find(|(mime_type, _)| mime_type == "text/plain")
+            .and_then(|(_, content)| content.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    /// The Lightning Address the payment goes to, if it has one (LUD-16).
+    pub fn lightning_address(&self) -> Option<String> {
+        let entries: Vec<(String, serde_json::Value)> =
+            serde_json::from_str(&self.metadata).unwrap_or_default();
+
+        entries
+            .into_iter()
+            .find(|(mime_type, _)| mime_type == "text/identifier" || mime_type == "text/email")
+            .and_then(|(_, content)| content.as_str().map(str::to_string))
+    }
+
+    /// What the payment is recorded with: the description and, if known, the Lightning Address
+    /// of the recipient and our comment.
+    pub fn payment_description(&self, comment: Option<&str>) -> String {
+        let mut description = match self.lightning_address() {
+            Some(address) => format!("{address}: {}", self.description()),
+            None => self.description(),
+        };
+
+        if let Some(comment) = comment {
+            description = format!("{description} ({comment})");
+        }
+
+        description
+    }
+
+    fn check(&self, amount_msat: u64, comment: Option<&str>) -> Result<()> {
+        ensure!(
+            (self.min_sendable..=self.max_sendable).contains(&amount_msat),
+            "Amount must be between {} and {} sats",
+            self.min_sendable / 1000,
+            self.max_sendable / 1000
+        );
+
+        if let Some(comment) = comment {
+            ensure!(
+                comment.chars().count() <= self.comment_allowed as usize,
+                "Comment must not be longer than {} characters",
+                self.comment_allowed
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl WithdrawRequest {
+    fn check(&self, amount_msat: u64) -> Result<()> {
+        ensure!(
+            (self.min_withdrawable..=self.max_withdrawable).contains(&amount_msat),
+            "Amount must be between {} and {} sats",
+            self.min_withdrawable / 1000,
+            self.max_withdrawable / 1000
+        );
+
+        Ok(())
+    }
+}
+
+/// The URL to query for `input`, if it is an LNURL or a Lightning Address.
+///
+/// Accepts bech32 encoded LNURLs, LUD-17 URLs (`lnurlp://`, `lnurlw://`) and Lightning Addresses,
+/// optionally prefixed with `lightning:`.
+pub fn parse(input: &str) -> Option<String> {
+    let input = input.trim();
+    let input = strip_prefix_ignore_case(input, "lightning:").unwrap_or(input);
+
+    if input.to_lowercase().starts_with("lnurl1") {
+        let (hrp, data, _) = bech32::decode(input).ok()?;
+        if hrp != "lnurl" {
+            return None;
+        }
+
+        let url = Vec::<u8>::from_base32(&data).ok()?;
+        return String::from_utf8(url).ok();
+    }
+
+    for scheme in ["lnurlp://", "lnurlw://"] {
+        if let Some(rest) = strip_prefix_ignore_case(input, scheme) {
+            return Some(format!("{}://{rest}", http_scheme(rest)));
+        }
+    }
+
+    let (user, domain) = input.split_once('@')?;
+    let is_valid_user = !user.is_empty()
+        && user
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-_.+".contains(c));
+    if !is_valid_user || !domain.contains('.') || domain.contains('/') {
+        return None;
+    }
+
+    Some(format!(
+        "{}://{domain}/.well-known/lnurlp/{user}",
+        http_scheme(domain)
+    ))
+}
+
+/// Fetches what the LNURL at `url` is asking for.
+pub async fn resolve(client: &reqwest::Client, url: &str) -> Result<LnUrlRequest> {
+    let response: serde_json::Value = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to query {url}"))?
+        .json()
+        .await?;
+
+    check_status(&response)?;
+
+    let request = match serde_json::from_value(response).context("Unsupported LNURL")? {
+        LnUrlResponse::Pay(request) => LnUrlRequest::Pay(request),
+        LnUrlResponse::Withdraw(request) => LnUrlRequest::Withdraw(request),
+    };
+
+    Ok(request)
+}
+
+/// Requests an invoice for paying `amount_msat` with an optional `comment`.
+pub async fn fetch_invoice(
+    client: &reqwest::Client,
+    request: &PayRequest,
+    amount_msat: u64,
+    comment: Option<&str>,
+) -> Result<Invoice> {
+    request.check(amount_msat, comment)?;
+
+    let mut query = vec![("amount", amount_msat.to_string())];
+    if let Some(comment) = comment {
+        query.push(("comment", comment.to_string()));
+    }
+
+    let response: serde_json::Value = client
+        .get(&request.callback)
+        .query(&query)
+        .send()
+        .await
+        .context("Failed to request invoice")?
+        .json()
+        .await?;
+
+    check_status(&response)?;
+
+    let InvoiceResponse { pr } =
+        serde_json::from_value(response).context("Invalid invoice response")?;
+    let invoice = Invoice::from_str(&pr).context("Invalid invoice")?;
+
+    check_invoice(request, &invoice, amount_msat)?;
+
+    Ok(invoice)
+}
+
+/// Asks the service behind `request` to pay `invoice`.
+///
+/// The payment arrives asynchronously, like any other payment to one of our invoices.
+pub async fn withdraw(
+    client: &reqwest::Client,
+    request: &WithdrawRequest,
+    invoice: &Invoice,
+) -> Result<()> {
+    let amount_msat = invoice
+        .amount_milli_satoshis()
+        .context("Invoice without amount")?;
+    request.check(amount_msat)?;
+
+    let response: serde_json::Value = client
+        .get(&request.callback)
+        .query(&[("k1", request.k1.clone()), ("pr", invoice.to_string())])
+        .send()
+        .await
+        .context("Failed to request withdrawal")?
+        .json()
+        .await?;
+
+    check_status(&response)
+}
+
+/// The invoice has to be for the requested amount and commit to the metadata we have been shown.
+fn check_invoice(request: &PayRequest, invoice: &Invoice, amount_msat: u64) -> Result<()> {
+    ensure!(
+        invoice.amount_milli_satoshis() == Some(amount_msat),
+        "Invoice amount does not match the requested amount of {amount_msat} msats"
+    );
+
+    match invoice.description() {
+        InvoiceDescription::Hash(hash) => ensure!(
+            hash.0 == sha256::Hash::hash(request.metadata.as_bytes()),
+            "Invoice description hash does not match the metadata"
+        ),
+        InvoiceDescription::Direct(_) => bail!("Invoice does not commit to the metadata"),
+    }
+
+    Ok(())
+}
+
+fn check_status(response: &serde_json::Value) -> Result<()> {
+    let Ok(StatusResponse { status, reason }) =
+        serde_json::from_value::<StatusResponse>(response.clone())
+    else {
+        // Only some responses carry a status.
+        return Ok(());
+    };
+
+    match status {
+        Status::Ok => Ok(()),
+        Status::Error => bail!(
+            "LNURL service returned an error: {}",
+            reason.unwrap_or_default()
+        ),
+    }
+}
+
+/// Onion services are reached over plain HTTP (LUD-17).
+fn http_scheme(host: &str) -> &'static str {
+    let host = host.split(['/', ':']).next().unwrap_or_default();
+    if host.ends_with(".onion") {
+        "http"
+    } else {
+        "https"
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = input.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &input[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::bech32::ToBase32;
+    use bitcoin::bech32::Variant;
+
+    #[test]
+    fn parses_lightning_address() {
+        assert_eq!(
+            parse("satoshi@example.com"),
+            Some("https://example.com/.well-known/lnurlp/satoshi".to_string())
+        );
+        assert_eq!(
+            parse("satoshi@example.onion"),
+            Some("http://example.onion/.well-known/lnurlp/satoshi".to_string())
+        );
+        assert_eq!(parse("Satoshi@example.com"), None);
+        assert_eq!(parse("satoshi@localhost"), None);
+    }
+
+    #[test]
+    fn parses_bech32_lnurl() {
+        let url = "https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd34";
+        let lnurl = bech32::encode("lnurl", url.as_bytes().to_base32(), Variant::Bech32).unwrap();
+
+        assert_eq!(parse(&lnurl), Some(url.to_string()));
+        assert_eq!(
+            parse(&format!("lightning:{}", lnurl.to_uppercase())),
+            Some(url.to_string())
+        );
+    }
+
+    #[test]
+    fn parses_lud17_url() {
+        assert_eq!(
+            parse("lnurlw://service.com/withdraw?k1=abc"),
+            Some("https://service.com/withdraw?k1=abc".to_string())
+        );
+    }
+
+    #[test]
+    fn does_not_parse_other_destinations() {
+        assert_eq!(parse("bcrt1qh6rjlzr3p8dmkvdtejyvcw3kqzak7p3zp4yfmf"), None);
+        assert_eq!(
+            parse("02dd6abec97f9a748bf76ad502b004ce05d1b2d1f43a9e76bd7d85e767ffb022c9"),
+            None
+        );
+    }
+
+    #[test]
+    fn pay_request_checks_amount_and_comment() {
+        let request = PayRequest {
+            callback: "https://example.com/callback".to_string(),
+            min_sendable: 1_000,
+            max_sendable: 10_000,
+            metadata: r#"[["text/plain","Coffee"],["text/identifier","satoshi@example.com"]]"#
+                .to_string(),
+            comment_allowed: 5,
+        };
+
+        assert_eq!(request.description(), "Coffee");
+        assert_eq!(
+            request.lightning_address(),
+            Some("satoshi@example.com".to_string())
+        );
+
+        assert_eq!(
+            request.payment_description(Some("Thanks")),
+            "satoshi@example.com: Coffee (Thanks)"
+        );
+
+        assert!(request.check(5_000, Some("Hello")).is_ok());
+        assert!(request.check(500, None).is_err());
+        assert!(request.check(20_000, None).is_err());
+        assert!(request.check(5_000, Some("Hello!")).is_err());
+    }
+
+    #[test]
+    fn parses_lnurl_responses() {
+        let response: LnUrlResponse = serde_json::from_str(
+            r#"{
+                "tag": "withdrawRequest",
+                "callback": "https://example.com/withdraw",
+                "k1": "abc",
+                "defaultDescription": "Prize",
+                "minWithdrawable": 1000,
+                "maxWithdrawable": 100000
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(response, LnUrlResponse::Withdraw(_)));
+
+        let error = serde_json::json!({ "status": "ERROR", "reason": "Expired" });
+        assert!(check_status(&error).is_err());
+        assert!(check_status(&serde_json::json!({ "status": "OK" })).is_ok());
+        assert!(check_status(&serde_json::json!({ "pr": "lnbc1" })).is_ok());
+    }
+}
@@ -5,30 +5,39 @@ use crate::event::EventInternal;
 use crate::event::TaskStatus;
 use crate::health::ServiceStatus;
 use crate::ln_dlc;
+use crate::trade::order;
 use crate::trade::position;
 use anyhow::Result;
 use bdk::bitcoin::secp256k1::SecretKey;
 use bdk::bitcoin::secp256k1::SECP256K1;
 use futures::TryStreamExt;
-use orderbook_commons::best_current_price;
+use orderbook_client::LocalOrderbook;
 use orderbook_commons::Message;
-use orderbook_commons::Order;
 use orderbook_commons::Prices;
 use orderbook_commons::Signature;
-use parking_lot::Mutex;
+use parking_lot::RwLock;
+use parking_lot::RwLockReadGuard;
+use state::Storage;
 use std::collections::HashMap;
-use std::sync::Arc;
 use std::time::Duration;
-use time::OffsetDateTime;
 use tokio::runtime::Runtime;
 use tokio::sync::watch;
-use uuid::Uuid;
+use trade::ContractSymbol;
 
 /// The reconnect timeout should be high enough for the coordinator to get ready after a restart. If
 /// we reconnect too early we may not be ready process messages which require DLC actions.
 const WS_RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
-const EXPIRED_ORDER_PRUNING_INTERVAL: Duration = Duration::from_secs(30);
+/// The latest known state of the aggregated orderbook.
+static LOCAL_ORDERBOOK: Storage<RwLock<LocalOrderbook>> = Storage::new();
+
+pub(crate) fn get_local_orderbook() -> RwLockReadGuard<'static, LocalOrderbook> {
+    local_orderbook().read()
+}
+
+fn local_orderbook() -> &'static RwLock<LocalOrderbook> {
+    LOCAL_ORDERBOOK.get_or_set(|| RwLock::new(LocalOrderbook::new(ContractSymbol::BtcUsd)))
+}
 
 pub fn subscribe(
     secret_key: SecretKey,
@@ -38,7 +47,7 @@ pub fn subscribe(
 ) -> Result<()> {
     runtime.spawn(async move {
         let url = format!(
-            "ws://{}/api/orderbook/websocket",
+            "ws://{}/api/orderbook/websocket?depth=true",
             config::get_http_endpoint()
         );
 
@@ -48,39 +57,6 @@ pub fn subscribe(
             Signature { pubkey, signature }
         };
 
-        // Need a Mutex as it's being accessed from websocket stream and pruning task
-        let orders = Arc::new(Mutex::new(Vec::<Order>::new()));
-
-        let _prune_expired_orders_task = {
-            let orders = orders.clone();
-            tokio::spawn(async move {
-                loop {
-                    {
-                        tracing::debug!("Pruning expired orders");
-                        let mut orders = orders.lock();
-                        let orders_before_pruning = orders.len();
-                        *orders = orders.iter().filter(|order| order.expiry >= OffsetDateTime::now_utc()).cloned().collect::<Vec<_>>();
-                        let orders_after_pruning = orders.len();
-
-                        if orders_after_pruning < orders_before_pruning {
-                            let amount_pruned = orders_before_pruning - orders_after_pruning;
-                            tracing::debug!(
-                                orders_before_pruning,
-                                orders_after_pruning,
-                                "Pruned {amount_pruned} expired orders"
-                            );
-
-                            // Current best price might have changed
-                            if let Err(e) = position::handler::price_update(best_current_price(&orders)) {
-                                tracing::error!("Price update from the orderbook failed. Error: {e:#}");
-                            }
-                        }
-                    }
-                    tokio::time::sleep(EXPIRED_ORDER_PRUNING_INTERVAL).await;
-                }
-            })
-        };
-
         let fcm_token = if fcm_token.is_empty() {
             None
         } else {
@@ -99,6 +75,7 @@ pub fn subscribe(
                         };
 
                     let mut cached_best_price : Prices = HashMap::new();
+                    *local_orderbook().write() = LocalOrderbook::new(ContractSymbol::BtcUsd);
                     loop {
                         match stream.try_next().await {
                             Ok(Some(msg)) => {
@@ -141,44 +118,37 @@ pub fn subscribe(
                                             tracing::error!(order_id = %filled.order_id, "Trade request sent to coordinator failed. Error: {e:#}");
                                         }
                                     },
-                                    Message::AllOrders(initial_orders) => {
-                                        let mut orders = orders.lock();
-                                        if !orders.is_empty() {
-                                            tracing::debug!("Received new set of initial orders from orderbook, replacing the previously stored orders");
-                                        }
-                                        else {
-                                            tracing::debug!(?orders, "Received all orders from orderbook");
-                                        }
-                                        *orders = initial_orders;
-                                        update_prices_if_needed(&mut cached_best_price, &orders);
-                                    },
-                                    Message::NewOrder(order) => {
-                                        let mut orders = orders.lock();
-                                        orders.push(order);
-                                        update_prices_if_needed(&mut cached_best_price, &orders);
+                                    Message::DepthSnapshot(snapshot) => {
+                                        tracing::debug!(sequence = snapshot.sequence, "Received depth snapshot from orderbook");
+                                        local_orderbook().write().apply_snapshot(&snapshot);
+                                        update_prices_if_needed(&mut cached_best_price);
                                     }
-                                    Message::DeleteOrder(order_id) => {
-                                        let mut orders = orders.lock();
-                                        let found = remove_order(&mut orders, order_id);
-                                        if !found {
-                                            tracing::warn!(%order_id, "Could not remove non-existing order");
+                                    Message::DepthUpdate(update) => {
+                                        let result = local_orderbook().write().apply_update(&update);
+                                        if let Err(e) = result {
+                                            tracing::warn!("Local orderbook out of sync, waiting for next snapshot: {e}");
+                                            continue;
                                         }
-                                        update_prices_if_needed(&mut cached_best_price, &orders);
-                                    },
-                                    Message::Update(updated_order) => {
-                                        let mut orders = orders.lock();
-                                        let found = remove_order(&mut orders, updated_order.id);
-                                        if !found {
-                                            tracing::warn!(?updated_order, "Update without prior knowledge of order");
-                                        }
-                                        orders.push(updated_order);
-                                        update_prices_if_needed(&mut cached_best_price, &orders);
-                                    },
+                                        update_prices_if_needed(&mut cached_best_price);
+                                    }
                                     msg @ Message::LimitOrderFilledMatches { .. } |
                                     msg @ Message::InvalidAuthentication(_) |
-                                    msg @ Message::Authenticated => {
+                                    msg @ Message::Authenticated |
+                                    msg @ Message::Trade(_) |
+                                    msg @ Message::MarketStats(_) |
+                                    msg @ Message::AllOrders(_) |
+                                    msg @ Message::NewOrder(_) |
+                                    msg @ Message::DeleteOrder(_) |
+                                    msg @ Message::Update(_) => {
                                         tracing::debug!(?msg, "Skipping message from orderbook");
                                     }
+                                    Message::MatchAbandoned { order_id, reason } => {
+                                        tracing::warn!(%order_id, %reason, "Match has been abandoned by the orderbook");
+
+                                        if let Err(e) = order::handler::order_abandoned(order_id, reason) {
+                                            tracing::error!(%order_id, "Failed to process abandoned match. Error: {e:#}");
+                                        }
+                                    }
                                     Message::CollaborativeRevert { channel_id, coordinator_address, coordinator_amount, trader_amount } => {
                                         tracing::debug!("Received request to revert channel");
                                         if let Err(err) = ln_dlc::collaborative_revert_channel(channel_id, coordinator_address, coordinator_amount, trader_amount) {
@@ -216,8 +186,14 @@ pub fn subscribe(
     Ok(())
 }
 
-fn update_prices_if_needed(cached_best_price: &mut Prices, orders: &[Order]) {
-    let best_price = best_current_price(orders);
+fn update_prices_if_needed(cached_best_price: &mut Prices) {
+    let best_price = {
+        let local_orderbook = get_local_orderbook();
+        HashMap::from([(
+            local_orderbook.contract_symbol(),
+            local_orderbook.best_price(),
+        )])
+    };
     if *cached_best_price != best_price {
         if let Err(e) = position::handler::price_update(best_price.clone()) {
             tracing::error!("Price update from the orderbook failed. Error: {e:#}");
@@ -225,16 +201,3 @@ fn update_prices_if_needed(cached_best_price: &mut Prices, orders: &[Order]) {
         *cached_best_price = best_price;
     }
 }
-
-// Returns true if the order was found and removed
-fn remove_order(orders: &mut Vec<Order>, order_id: Uuid) -> bool {
-    let mut found = false;
-    for (index, element) in orders.iter().enumerate() {
-        if element.id == order_id {
-            found = true;
-            orders.remove(index);
-            break;
-        }
-    }
-    found
-}
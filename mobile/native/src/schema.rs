@@ -15,6 +15,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    counterparty_commitments (txid) {
+        txid -> Text,
+        channel_keys_id -> Text,
+        commitment_number -> BigInt,
+        raw -> Text,
+        revoked -> Bool,
+    }
+}
+
 diesel::table! {
     last_login (id) {
         id -> Nullable<Integer>,
@@ -22,6 +32,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    offers (id) {
+        id -> Integer,
+        amount_sats -> Nullable<BigInt>,
+        offer -> Text,
+        created_at -> BigInt,
+    }
+}
+
 diesel::table! {
     orders (id) {
         id -> Text,
@@ -55,6 +74,7 @@ diesel::table! {
         description -> Text,
         invoice -> Nullable<Text>,
         fee_msat -> Nullable<BigInt>,
+        failure_reason -> Nullable<Text>,
     }
 }
 
@@ -90,12 +110,15 @@ diesel::table! {
         created_at -> BigInt,
         updated_at -> BigInt,
         raw -> Text,
+        replaceable -> Bool,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
     channels,
+    counterparty_commitments,
     last_login,
+    offers,
     orders,
     payments,
     positions,
@@ -47,6 +47,10 @@ pub struct NewOrder {
     pub order_type: Box<OrderType>,
     #[frb(non_final)]
     pub stable: bool,
+    /// The maximum slippage in percent a market order may be filled at, relative to the best
+    /// price in the orderbook. If `None`, the order is filled at any price.
+    #[frb(non_final)]
+    pub max_slippage: Option<f32>,
 }
 
 #[frb]
@@ -15,13 +15,17 @@ use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use reqwest::Url;
+use rust_decimal::Decimal;
 use time::Duration;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
 const ORDER_OUTDATED_AFTER: Duration = Duration::minutes(5);
 
-pub async fn submit_order(order: Order) -> Result<Uuid> {
+/// Submits the order to the orderbook.
+///
+/// If a `worst_price` is given, the orderbook will not fill the market order at a worse price.
+pub async fn submit_order(order: Order, worst_price: Option<Decimal>) -> Result<Uuid> {
     let url = format!("http://{}", config::get_http_endpoint());
     let orderbook_client = OrderbookClient::new(Url::parse(&url)?);
 
@@ -32,7 +36,12 @@ pub async fn submit_order(order: Order) -> Result<Uuid> {
 
     db::insert_order(order)?;
 
-    if let Err(err) = orderbook_client.post_new_order(order.into()).await {
+    let new_order = orderbook_commons::NewOrder {
+        worst_price,
+        ..order.into()
+    };
+
+    if let Err(err) = orderbook_client.post_new_order(new_order).await {
         let order_id = order.id.to_string();
         tracing::error!(order_id, "Failed to post new order. Error: {err:#}");
         update_order_state_in_db_and_ui(order.id, OrderState::Rejected)?;
@@ -108,6 +117,25 @@ pub(crate) fn order_failed(
     Ok(())
 }
 
+/// Update order state to failed after the orderbook abandoned the match of the order.
+///
+/// Orders which have already been filled or failed are left untouched.
+pub(crate) fn order_abandoned(order_id: Uuid, reason: String) -> Result<()> {
+    let order = db::get_order(order_id)?;
+
+    match order.state {
+        OrderState::Open | OrderState::Filling { .. } => order_failed(
+            Some(order_id),
+            FailureReason::TimedOut,
+            anyhow!("Match has been abandoned: {reason}"),
+        ),
+        state => {
+            tracing::debug!(%order_id, ?state, "Ignoring abandoned match for order");
+            Ok(())
+        }
+    }
+}
+
 pub async fn get_orders_for_ui() -> Result<Vec<Order>> {
     db::get_orders_for_ui()
 }
@@ -182,6 +182,7 @@ impl From<Order> for orderbook_commons::NewOrder {
             order_type: order.order_type.into(),
             expiry: order.order_expiry_timestamp,
             stable: order.stable,
+            worst_price: None,
         }
     }
 }